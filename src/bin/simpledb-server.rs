@@ -0,0 +1,43 @@
+use std::{env, sync::Arc, thread};
+
+use simpledb::{
+    network::{PgServer, Server},
+    SimpleDB,
+};
+
+// Start a SimpleDB server.
+// Usage: simpledb-server [dbdir] [address] [--pg [pgaddress]]
+// With --pg, the database is also served over the
+// PostgreSQL protocol, so that psql can connect.
+fn main() -> std::io::Result<()> {
+    let mut positional = Vec::new();
+    let mut pg_addr = None;
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        if arg == "--pg" {
+            let addr = args.next_if(|a| !a.starts_with("--"));
+            pg_addr = Some(addr.unwrap_or_else(|| PgServer::DEFAULT_ADDR.to_string()));
+        } else {
+            positional.push(arg);
+        }
+    }
+    let mut positional = positional.into_iter();
+    let dirname = positional.next().unwrap_or_else(|| "studentdb".to_string());
+    let addr = positional
+        .next()
+        .unwrap_or_else(|| Server::DEFAULT_ADDR.to_string());
+
+    let db = Arc::new(SimpleDB::new(
+        &dirname,
+        SimpleDB::BLOCK_SIZE,
+        SimpleDB::BUFFER_SIZE,
+    )?);
+    if let Some(pg_addr) = pg_addr {
+        let pg_server = PgServer::bind(Arc::clone(&db), &pg_addr)?;
+        println!("postgres server ready on {}", pg_server.local_addr()?);
+        thread::spawn(move || pg_server.serve());
+    }
+    let server = Server::bind(db, &addr)?;
+    println!("database server ready on {}", server.local_addr()?);
+    server.serve()
+}
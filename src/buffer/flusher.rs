@@ -0,0 +1,109 @@
+use super::BufferManager;
+use crate::file::BlockStore;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+// A background writer for a buffer manager.
+// Its thread wakes up periodically and writes the
+// dirty buffers that nobody has pinned, so that
+// commits and replacements find fewer buffers to
+// write, and recovery has less of the log to redo.
+// The thread stops when the flusher is dropped.
+pub struct BufferFlusher {
+    stopped: Arc<(Mutex<bool>, Condvar)>,
+    flushed: Arc<AtomicU64>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BufferFlusher {
+    // Start a thread that flushes the buffers of the
+    // specified manager at the specified interval.
+    pub fn start<S: BlockStore + 'static>(bm: Arc<BufferManager<S>>, interval: Duration) -> Self {
+        let stopped = Arc::new((Mutex::new(false), Condvar::new()));
+        let flushed = Arc::new(AtomicU64::new(0));
+        let handle = {
+            let stopped = Arc::clone(&stopped);
+            let flushed = Arc::clone(&flushed);
+            thread::spawn(move || {
+                let (lock, cvar) = &*stopped;
+                let mut stop = lock.lock().unwrap();
+                while !*stop {
+                    stop = cvar.wait_timeout(stop, interval).unwrap().0;
+                    if *stop {
+                        break;
+                    }
+                    // a failed write is retried on the next round
+                    if let Ok(n) = bm.flush_unpinned() {
+                        flushed.fetch_add(n as u64, Ordering::Relaxed);
+                    }
+                }
+            })
+        };
+        BufferFlusher {
+            stopped,
+            flushed,
+            handle: Some(handle),
+        }
+    }
+
+    // The number of buffers written so far.
+    pub fn flushed(&self) -> u64 {
+        self.flushed.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for BufferFlusher {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.stopped;
+        *lock.lock().unwrap() = true;
+        cvar.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, error::DbResult, file::Page};
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_background_flush() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let blk = tx.append("testfile")?;
+        let pinned = tx.append("testfile")?;
+        tx.pin(&blk)?;
+        tx.pin(&pinned)?;
+        tx.set_int(&blk, 0, 42, true)?;
+        tx.set_int(&pinned, 0, 43, true)?;
+        tx.unpin(&blk);
+
+        // Only the unpinned buffer is written
+        let flusher =
+            BufferFlusher::start(Arc::clone(db.buffer_manager()), Duration::from_millis(10));
+        let start = Instant::now();
+        while flusher.flushed() == 0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+        drop(flusher);
+        let mut page = Page::new(db.file_manager().block_size());
+        db.file_manager().read(&blk, &mut page)?;
+        assert_eq!(page.get_int(0), 42);
+        db.file_manager().read(&pinned, &mut page)?;
+        assert_eq!(page.get_int(0), 0);
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -1,62 +1,420 @@
+use super::{NaivePolicy, ReplacementPolicy};
 use crate::{
     buffer::BufferPage,
-    file::{BlockId, FileManager},
-    log::LogManager,
+    file::{BlockId, BlockStore, FileManager},
+    log::{LogManager, Lsn},
 };
 use std::{
-    sync::{Arc, Mutex},
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{Hash, Hasher},
+    sync::{Arc, Condvar, Mutex, MutexGuard, TryLockError},
     time::{Duration, Instant},
 };
 
 #[derive(Debug)]
-pub struct BufferError(pub String);
+pub enum BufferError {
+    // No buffer could be had, for the given reason.
+    Abort(String),
+    // A block could not be read or written.
+    Io(std::io::Error),
+}
+
+// A snapshot of the activity of the buffer pool
+// since it was created.
+// A low hit ratio or a long total wait time are
+// signs that the pool is too small for its workload.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BufferStats {
+    // The number of buffers in the pool.
+    pub capacity: usize,
+    // The number of unpinned buffers.
+    pub available: usize,
+    // The number of buffers holding modifications
+    // that have not been written to disk.
+    pub dirty: usize,
+    // Pins of blocks that were already in a buffer.
+    pub hits: u64,
+    // Pins that had to read their block from disk.
+    pub misses: u64,
+    // Misses that replaced the block of another buffer.
+    pub evictions: u64,
+    // Pins that had to wait for a buffer to be unpinned.
+    pub waits: u64,
+    // Pins that gave up waiting.
+    pub timeouts: u64,
+    // The total time spent waiting by those pins.
+    pub wait_time: Duration,
+}
+
+impl BufferStats {
+    // The fraction of pins that found their block
+    // already in the pool.
+    pub fn hit_ratio(&self) -> f64 {
+        let pins = self.hits + self.misses;
+        if pins == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / pins as f64
+    }
+}
 
-pub struct BufferManager {
-    buffer_pool: Vec<Arc<Mutex<BufferPage>>>,
-    num_available: Mutex<usize>,
+// The buffer pool is partitioned into shards.
+// Each block belongs to the shard picked by its hash,
+// and is only ever assigned to a buffer of that shard,
+// so threads pinning blocks of different shards do
+// not contend for the same latch.
+pub struct BufferManager<S: BlockStore = FileManager> {
+    fm: Arc<S>,
+    lm: Arc<Mutex<LogManager<S>>>,
+    shards: Vec<Arc<Shard<S>>>,
     max_time: u64,
+    // The most buffers that the operators of a query, such as
+    // a sort or a hash join, may claim for their work; None
+    // if they may claim every unpinned buffer.
+    work_limit: Option<usize>,
+}
+
+struct Shard<S: BlockStore> {
+    // Held while pinning and unpinning,
+    // so that a block is never assigned to two buffers.
+    state: Mutex<PoolState<S>>,
+    // Signaled by unpin when a buffer becomes available.
+    freed: Condvar,
+}
+
+// A buffer pinned to a block, as returned by pin.
+// The buffer stays pinned until the guard is dropped,
+// so a pinned buffer can never be leaked or
+// unpinned twice.
+pub struct PinnedBuffer<S: BlockStore = FileManager> {
+    shard: Arc<Shard<S>>,
+    // The position of the buffer in its shard.
+    index: usize,
+    buffer: Arc<Mutex<BufferPage<S>>>,
+}
+
+impl<S: BlockStore> PinnedBuffer<S> {
+    // Locks the page of the buffer.
+    pub fn page(&self) -> MutexGuard<'_, BufferPage<S>> {
+        self.buffer.lock().unwrap()
+    }
+
+    // The pinned buffer itself, which can be
+    // shared with code that locks it as needed.
+    pub fn buffer(&self) -> &Arc<Mutex<BufferPage<S>>> {
+        &self.buffer
+    }
+}
+
+impl<S: BlockStore> Drop for PinnedBuffer<S> {
+    // Unpins the buffer.
+    fn drop(&mut self) {
+        let mut state = self.shard.state.lock().unwrap();
+        self.shard.unpin(&mut state, self.index);
+    }
+}
+
+// The bookkeeping of a shard, kept in step with its
+// buffers so that neither pinning nor unpinning needs
+// to look at every buffer.
+struct PoolState<S: BlockStore> {
+    // The buffers of the shard, by position.
+    // The position of a buffer removed by resize is
+    // empty until a new buffer takes its place.
+    buffers: Vec<Option<Arc<Mutex<BufferPage<S>>>>>,
+    // The number of buffers the shard should have.
+    // It has more while the buffers to be removed are pinned.
+    target: usize,
+    // The position in the shard of the buffer assigned to each block.
+    block_map: HashMap<BlockId, usize>,
+    // The positions of the unpinned buffers.
+    unpinned: BTreeSet<usize>,
+    policy: Box<dyn ReplacementPolicy>,
+    // The tickets of the threads waiting to pin a buffer,
+    // in the order they started to wait.
+    waiting: VecDeque<u64>,
+    next_ticket: u64,
+    // The counters of the shard; the other fields of the
+    // returned statistics are computed by stats().
+    stats: BufferStats,
+}
+
+impl<S: BlockStore> PoolState<S> {
+    fn buffer(&self, i: usize) -> &Arc<Mutex<BufferPage<S>>> {
+        self.buffers[i]
+            .as_ref()
+            .expect("no buffer at this position")
+    }
+
+    // The number of buffers in the shard.
+    fn size(&self) -> usize {
+        self.buffers.iter().flatten().count()
+    }
+
+    // Returns true if the block is in a pinned buffer,
+    // which a pin can share without taking a buffer
+    // that a waiting thread needs.
+    fn is_pinned(&self, block: &BlockId) -> bool {
+        self.block_map
+            .get(block)
+            .is_some_and(|i| !self.unpinned.contains(i))
+    }
+
+    // Adds a waiting thread to the end of the queue,
+    // and returns its ticket.
+    fn enqueue(&mut self) -> u64 {
+        self.next_ticket += 1;
+        self.waiting.push_back(self.next_ticket);
+        self.next_ticket
+    }
+
+    // Reads the blocks not in the shard into unpinned
+    // buffers, and returns their number.
+    fn prefetch(&mut self, blocks: &[&BlockId]) -> std::io::Result<usize> {
+        let mut free: Vec<usize> = self.unpinned.iter().copied().collect();
+        let mut victims: Vec<(BlockId, usize)> = Vec::new();
+        for &block in blocks {
+            if self.block_map.contains_key(block) || victims.iter().any(|(b, _)| b == block) {
+                continue;
+            }
+            let Some(i) = self.policy.choose_victim(&free) else {
+                break;
+            };
+            free.retain(|&j| j != i);
+            victims.push((block.clone(), i));
+        }
+
+        let buffs: Vec<_> = victims
+            .iter()
+            .map(|&(_, i)| Arc::clone(self.buffer(i)))
+            .collect();
+        let mut guards: Vec<_> = buffs.iter().map(|buff| buff.lock().unwrap()).collect();
+        for page in &guards {
+            if let Some(old) = page.block() {
+                self.block_map.remove(old);
+                self.stats.evictions += 1;
+            }
+        }
+        let mut pages: Vec<&mut BufferPage<S>> =
+            guards.iter_mut().map(|page| &mut **page).collect();
+        let blocks = victims.iter().map(|(block, _)| block.clone()).collect();
+        BufferPage::assign_batch(&mut pages, blocks)?;
+        for (block, i) in victims.iter() {
+            self.block_map.insert(block.clone(), *i);
+            self.policy.on_assign(*i);
+        }
+        Ok(victims.len())
+    }
+
+    // Removes the unpinned buffer at the specified
+    // position, writing it first if it is dirty.
+    fn remove(&mut self, i: usize) -> std::io::Result<()> {
+        let buff = Arc::clone(self.buffer(i));
+        let mut page = buff.lock().unwrap();
+        page.flush()?;
+        if let Some(block) = page.block() {
+            self.block_map.remove(block);
+        }
+        self.unpinned.remove(&i);
+        self.buffers[i] = None;
+        Ok(())
+    }
 }
 
 // Manages the pinning and unpinning of buffers to blocks.
-impl BufferManager {
-    const DEFAULT_MAX_TIME: u64 = 10_000;
+impl<S: BlockStore> BufferManager<S> {
+    // How long a pin waits for a buffer by default, in milliseconds.
+    pub const DEFAULT_MAX_TIME: u64 = 10_000;
 
     // Creates a buffer manager having the specified number
     // of buffer slots.
-    pub fn new(fm: Arc<FileManager>, lm: Arc<Mutex<LogManager>>, num_buffs: usize) -> Self {
+    pub fn new(fm: Arc<S>, lm: Arc<Mutex<LogManager<S>>>, num_buffs: usize) -> Self {
         Self::new_with_timeout(fm, lm, num_buffs, Self::DEFAULT_MAX_TIME)
     }
 
     pub fn new_with_timeout(
-        fm: Arc<FileManager>,
-        lm: Arc<Mutex<LogManager>>,
+        fm: Arc<S>,
+        lm: Arc<Mutex<LogManager<S>>>,
         num_buffs: usize,
         max_time: u64,
     ) -> Self {
-        let buffer_pool = (0..num_buffs)
-            .map(|_| {
-                Arc::new(Mutex::new(BufferPage::new(
-                    Arc::clone(&fm),
-                    Arc::clone(&lm),
-                )))
+        Self::with_policy(fm, lm, num_buffs, max_time, Box::new(NaivePolicy::new()))
+    }
+
+    // Creates a buffer manager that uses the specified
+    // policy to choose the buffers to replace.
+    pub fn with_policy(
+        fm: Arc<S>,
+        lm: Arc<Mutex<LogManager<S>>>,
+        num_buffs: usize,
+        max_time: u64,
+        policy: Box<dyn ReplacementPolicy>,
+    ) -> Self {
+        Self::build(fm, lm, num_buffs, max_time, vec![policy])
+    }
+
+    // Creates a buffer manager whose buffers are split
+    // evenly among the specified number of shards.
+    // Each shard gets its own replacement policy.
+    pub fn with_shards(
+        fm: Arc<S>,
+        lm: Arc<Mutex<LogManager<S>>>,
+        num_buffs: usize,
+        max_time: u64,
+        num_shards: usize,
+        policy: impl Fn() -> Box<dyn ReplacementPolicy>,
+    ) -> Self {
+        assert!(
+            (1..=num_buffs).contains(&num_shards),
+            "every shard needs a buffer"
+        );
+        let policies = (0..num_shards).map(|_| policy()).collect();
+        Self::build(fm, lm, num_buffs, max_time, policies)
+    }
+
+    fn build(
+        fm: Arc<S>,
+        lm: Arc<Mutex<LogManager<S>>>,
+        num_buffs: usize,
+        max_time: u64,
+        policies: Vec<Box<dyn ReplacementPolicy>>,
+    ) -> Self {
+        let num_shards = policies.len();
+        let shards = policies
+            .into_iter()
+            .enumerate()
+            .map(|(s, policy)| {
+                let size = Self::share(num_buffs, num_shards, s);
+                let buffers = (0..size)
+                    .map(|_| {
+                        Some(Arc::new(Mutex::new(BufferPage::new(
+                            Arc::clone(&fm),
+                            Arc::clone(&lm),
+                        ))))
+                    })
+                    .collect();
+                Arc::new(Shard {
+                    state: Mutex::new(PoolState {
+                        buffers,
+                        target: size,
+                        block_map: HashMap::new(),
+                        unpinned: (0..size).collect(),
+                        policy,
+                        waiting: VecDeque::new(),
+                        next_ticket: 0,
+                        stats: BufferStats::default(),
+                    }),
+                    freed: Condvar::new(),
+                })
             })
             .collect();
 
         BufferManager {
-            buffer_pool,
-            num_available: Mutex::new(num_buffs),
+            fm,
+            lm,
+            shards,
             max_time,
+            work_limit: None,
+        }
+    }
+
+    // Limits the buffers that the operators of a query may claim,
+    // so that a big query leaves the rest of the pool to others.
+    pub fn with_work_limit(mut self, limit: Option<usize>) -> Self {
+        self.work_limit = limit;
+        self
+    }
+
+    // The number of buffers of shard s,
+    // the first shards taking the remainder.
+    fn share(num_buffs: usize, num_shards: usize, s: usize) -> usize {
+        num_buffs / num_shards + usize::from(s < num_buffs % num_shards)
+    }
+
+    // Changes the number of buffers in the pool,
+    // split among the shards as when it was created.
+    // A growing shard gets new buffers right away.
+    // A shrinking shard removes the unpinned buffers its
+    // policy chooses, writing them first if they are dirty;
+    // if it has too few unpinned buffers, the others are
+    // removed as they get unpinned.
+    pub fn resize(&self, num_buffs: usize) -> Result<(), BufferError> {
+        if num_buffs < self.shards.len() {
+            return Err(BufferError::Abort(
+                "Could not resize pool: every shard needs a buffer".into(),
+            ));
+        }
+        for (s, shard) in self.shards.iter().enumerate() {
+            let mut state = shard.state.lock().unwrap();
+            state.target = Self::share(num_buffs, self.shards.len(), s);
+            while state.size() < state.target {
+                let buff = BufferPage::new(Arc::clone(&self.fm), Arc::clone(&self.lm));
+                let buff = Some(Arc::new(Mutex::new(buff)));
+                let i = match state.buffers.iter().position(Option::is_none) {
+                    Some(i) => i,
+                    None => {
+                        state.buffers.push(None);
+                        state.buffers.len() - 1
+                    }
+                };
+                state.buffers[i] = buff;
+                state.unpinned.insert(i);
+                shard.freed.notify_all();
+            }
+            while state.size() > state.target {
+                let unpinned: Vec<usize> = state.unpinned.iter().copied().collect();
+                let Some(i) = state.policy.choose_victim(&unpinned) else {
+                    break;
+                };
+                state.remove(i).map_err(BufferError::Io)?;
+            }
         }
+        Ok(())
     }
 
     // Returns the number of available (i.e. unpinned) buffers.
     pub fn available(&self) -> usize {
-        *self.num_available.lock().unwrap()
+        self.shards
+            .iter()
+            .map(|shard| shard.state.lock().unwrap().unpinned.len())
+            .sum()
+    }
+
+    // Returns the number of available buffers that the
+    // operators of a query may claim, which is at most
+    // the work limit.
+    pub fn work_buffers(&self) -> usize {
+        let available = self.available();
+        self.work_limit
+            .map_or(available, |limit| available.min(limit))
+    }
+
+    // Returns the statistics of the pool, summed over its shards.
+    pub fn stats(&self) -> BufferStats {
+        let mut total = BufferStats::default();
+        for shard in &self.shards {
+            let state = shard.state.lock().unwrap();
+            total.capacity += state.size();
+            total.available += state.unpinned.len();
+            total.dirty += state
+                .buffers
+                .iter()
+                .flatten()
+                .filter(|buff| buff.lock().unwrap().modifying_txn() >= 0)
+                .count();
+            total.hits += state.stats.hits;
+            total.misses += state.stats.misses;
+            total.evictions += state.stats.evictions;
+            total.waits += state.stats.waits;
+            total.timeouts += state.stats.timeouts;
+            total.wait_time += state.stats.wait_time;
+        }
+        total
     }
 
     // Flushes the dirty buffers modified by the specified transaction.
     pub fn flush_all(&self, txnum: i32) -> std::io::Result<()> {
-        for buff in &self.buffer_pool {
+        for buff in self.buffers() {
             let mut buff = buff.lock().unwrap();
             if buff.modifying_txn() == txnum {
                 buff.flush()?;
@@ -65,89 +423,401 @@ impl BufferManager {
         Ok(())
     }
 
-    // Unpins the specified data buffer
-    pub fn unpin(&mut self, buffer: Arc<Mutex<BufferPage>>) {
-        let mut buffer = buffer.lock().unwrap();
-        buffer.unpin();
+    // Forgets the buffers of the blocks of the file from the
+    // specified block number on, which are about to be cut off
+    // it, without writing them; otherwise a later write would
+    // extend the file again. A pinned buffer keeps its block,
+    // but loses its modifications.
+    pub fn discard(&self, filename: &str, from: u64) {
+        for shard in &self.shards {
+            let mut state = shard.state.lock().unwrap();
+            let doomed: Vec<(BlockId, usize)> = state
+                .block_map
+                .iter()
+                .filter(|(block, _)| block.filename() == filename && block.number() >= from)
+                .map(|(block, &i)| (block.clone(), i))
+                .collect();
+            for (block, i) in doomed {
+                let mut page = state.buffer(i).lock().unwrap();
+                page.discard();
+                if page.block().is_none() {
+                    drop(page);
+                    state.block_map.remove(&block);
+                }
+            }
+        }
+    }
 
-        if !buffer.is_pinned() {
-            let mut num_available = self.num_available.lock().unwrap();
-            *num_available += 1;
+    // Returns the dirty page table: the recovery lsn of
+    // each block whose buffer has logged modifications
+    // that are not yet on disk.
+    // The smallest of them bounds the log that must be
+    // redone after a crash.
+    pub fn dirty_page_table(&self) -> HashMap<BlockId, Lsn> {
+        let mut table = HashMap::new();
+        for buff in self.buffers() {
+            let buff = buff.lock().unwrap();
+            if let (Some(block), Some(lsn)) = (buff.block(), buff.recovery_lsn()) {
+                if buff.modifying_txn() >= 0 {
+                    table.insert(block.clone(), lsn);
+                }
+            }
         }
+        table
+    }
+
+    // Writes every dirty buffer, pinned or not,
+    // whatever transaction modified it.
+    // Returns the number of buffers written.
+    pub fn flush_all_dirty(&self) -> std::io::Result<usize> {
+        self.flush_where(|_| true)
+    }
+
+    // Writes the dirty buffers that are not pinned.
+    // Returns the number of buffers written.
+    pub fn flush_unpinned(&self) -> std::io::Result<usize> {
+        self.flush_where(|buff| !buff.is_pinned())
+    }
+
+    // Writes the dirty buffers that satisfy the predicate.
+    // A buffer's flush writes its log records first,
+    // so the log always reaches disk before the data.
+    // The buffers that can be locked right away are written
+    // as one batch; the others are written one at a time
+    // afterwards, so that no lock is waited for while
+    // the batch is held.
+    fn flush_where(&self, pred: impl Fn(&BufferPage<S>) -> bool) -> std::io::Result<usize> {
+        let buffers = self.buffers();
+        let mut batch = Vec::new();
+        let mut busy = Vec::new();
+        for buff in &buffers {
+            match buff.try_lock() {
+                Ok(page) => {
+                    if page.modifying_txn() >= 0 && pred(&page) {
+                        batch.push(page);
+                    }
+                }
+                Err(TryLockError::WouldBlock) => busy.push(buff),
+                Err(TryLockError::Poisoned(e)) => panic!("{}", e),
+            }
+        }
+        let mut flushed = batch.len();
+        let mut pages: Vec<&mut BufferPage<S>> = batch.iter_mut().map(|page| &mut **page).collect();
+        BufferPage::flush_batch(&mut pages)?;
+        drop(batch);
+
+        for buff in busy {
+            let mut buff = buff.lock().unwrap();
+            if buff.modifying_txn() >= 0 && pred(&buff) {
+                buff.flush()?;
+                flushed += 1;
+            }
+        }
+        Ok(flushed)
+    }
+
+    // The buffers of every shard.
+    // The shards are not latched while the buffers are used.
+    fn buffers(&self) -> Vec<Arc<Mutex<BufferPage<S>>>> {
+        self.shards
+            .iter()
+            .flat_map(|shard| {
+                let state = shard.state.lock().unwrap();
+                state.buffers.iter().flatten().cloned().collect::<Vec<_>>()
+            })
+            .collect()
     }
 
     // Pins a buffer to the specified block, potentially
     // waiting until a buffer becomes available.
+    // The buffer is unpinned when the returned guard is dropped.
+    // Waiting threads queue up, and get the buffers
+    // unpinned in their shard in the order they arrived;
+    // a thread arriving while others wait queues behind
+    // them, unless its block is already pinned.
     // If no buffer becomes available within a fixed
     // time period, then a BufferError is thrown.
-    pub fn pin(&self, block: BlockId) -> Result<Arc<Mutex<BufferPage>>, BufferError> {
-        let deadline = Instant::now() + Duration::from_millis(self.max_time);
+    pub fn pin(&self, block: BlockId) -> Result<PinnedBuffer<S>, BufferError> {
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(self.max_time);
+        let shard = self.shard(&block);
+        let mut state = shard.state.lock().unwrap();
+        let mut ticket = None;
 
-        while Instant::now() < deadline {
-            if let Ok(Some(buffer)) = self.try_to_pin(block.clone()) {
-                return Ok(buffer);
+        let result = loop {
+            let first = match ticket {
+                None => state.waiting.is_empty(),
+                Some(ticket) => state.waiting.front() == Some(&ticket),
+            };
+            if first || state.is_pinned(&block) {
+                match shard.try_to_pin(&block, &mut state) {
+                    Ok(None) => {}
+                    Ok(Some(index)) => break Ok(index),
+                    Err(e) => break Err(BufferError::Io(e)),
+                }
+            }
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                state.stats.timeouts += 1;
+                break Err(BufferError::Abort("Could not pin buffer: timeout".into()));
             }
-            std::thread::sleep(Duration::from_millis(10));
+            if ticket.is_none() {
+                ticket = Some(state.enqueue());
+            }
+            state = shard.freed.wait_timeout(state, timeout).unwrap().0;
+        };
+        if let Some(ticket) = ticket {
+            state.waiting.retain(|&t| t != ticket);
+            // the next waiter may now be first in line
+            shard.freed.notify_all();
+            state.stats.waits += 1;
+            state.stats.wait_time += start.elapsed();
+        }
+        result.map(|index| PinnedBuffer {
+            shard: Arc::clone(shard),
+            index,
+            buffer: Arc::clone(state.buffer(index)),
+        })
+    }
+
+    // Pins buffers to all of the specified blocks, or to none of them.
+    // The shards of the blocks are latched in order of position,
+    // and nothing is pinned until each of them has room for
+    // its blocks; a caller waiting for room holds no buffers,
+    // so callers can never deadlock on each other's partial pins.
+    // The returned guards are in the order of the blocks.
+    pub fn pin_all(&self, blocks: &[BlockId]) -> Result<Vec<PinnedBuffer<S>>, BufferError> {
+        let start = Instant::now();
+        let deadline = start + Duration::from_millis(self.max_time);
+        // the positions of the blocks of each shard
+        let mut wanted: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (pos, block) in blocks.iter().enumerate() {
+            wanted.entry(self.shard_index(block)).or_default().push(pos);
         }
 
-        Err(BufferError("Could not pin buffer: timeout".into()))
+        let mut waited = false;
+        loop {
+            let mut states: Vec<(usize, MutexGuard<'_, PoolState<S>>)> = wanted
+                .keys()
+                .map(|&s| (s, self.shards[s].state.lock().unwrap()))
+                .collect();
+            for (s, state) in &states {
+                let distinct: HashSet<&BlockId> =
+                    wanted[s].iter().map(|&pos| &blocks[pos]).collect();
+                if distinct.len() > state.size() {
+                    return Err(BufferError::Abort(
+                        "Could not pin buffers: more blocks than buffers".into(),
+                    ));
+                }
+            }
+            let full = states
+                .iter()
+                .position(|(s, state)| !Shard::has_room(blocks, &wanted[s], state));
+            let Some(full) = full else {
+                if waited {
+                    let stats = &mut states[0].1.stats;
+                    stats.waits += 1;
+                    stats.wait_time += start.elapsed();
+                }
+                return self.pin_latched(blocks, &wanted, &mut states);
+            };
+
+            // wait for room in the full shard, holding no other latch
+            let (s, mut state) = states.swap_remove(full);
+            drop(states);
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                state.stats.timeouts += 1;
+                return Err(BufferError::Abort("Could not pin buffers: timeout".into()));
+            }
+            waited = true;
+            drop(self.shards[s].freed.wait_timeout(state, timeout).unwrap());
+        }
     }
 
-    // Tries to pin a buffer to the specified block.
-    // If there is already a buffer assigned to that block
-    // then that buffer is used;
-    // otherwise, an unpinned buffer from the pool is chosen.
-    // Returns a null value if there are no available buffers.
-    fn try_to_pin(&self, block: BlockId) -> Result<Option<Arc<Mutex<BufferPage>>>, std::io::Error> {
-        if let Some(buff) = self.find_existing_buffer(&block) {
-            let mut buffer = buff.lock().unwrap();
-            if !buffer.is_pinned() {
-                let mut num_available = self.num_available.lock().unwrap();
-                *num_available -= 1;
-            }
-            buffer.pin();
-            return Ok(Some(buff.clone()));
-        }
-
-        if let Some(buff) = self.choose_unpinned_buffer() {
-            let mut buffer = buff.lock().unwrap();
-            buffer.assign_to_block(block)?;
-            let mut num_available = self.num_available.lock().unwrap();
-            *num_available -= 1;
-            buffer.pin();
-            Ok(Some(buff.clone()))
-        } else {
-            Ok(None)
-        }
-    }
-
-    fn find_existing_buffer(&self, block: &BlockId) -> Option<Arc<Mutex<BufferPage>>> {
-        self.buffer_pool.iter().find_map(|buff| {
-            let buffer = buff.lock().unwrap();
-            if buffer.block().map_or(false, |b| b == block) {
-                Some(Arc::clone(buff))
-            } else {
-                None
+    // Pins the blocks of pin_all, whose shards
+    // are latched and have room for them.
+    // If a block cannot be read, the buffers pinned
+    // so far are unpinned again.
+    fn pin_latched(
+        &self,
+        blocks: &[BlockId],
+        wanted: &BTreeMap<usize, Vec<usize>>,
+        states: &mut [(usize, MutexGuard<'_, PoolState<S>>)],
+    ) -> Result<Vec<PinnedBuffer<S>>, BufferError> {
+        let mut pinned: Vec<Option<(usize, usize)>> = vec![None; blocks.len()];
+        let mut buffers: Vec<Option<Arc<Mutex<BufferPage<S>>>>> = vec![None; blocks.len()];
+        let mut error = None;
+        'shards: for (s, state) in states.iter_mut() {
+            let shard = &self.shards[*s];
+            // pin the blocks already in the shard first,
+            // so that none of them is chosen as a victim
+            let (hits, misses): (Vec<usize>, Vec<usize>) = wanted[s]
+                .iter()
+                .partition(|&&pos| state.block_map.contains_key(&blocks[pos]));
+            for pos in hits.into_iter().chain(misses) {
+                match shard.try_to_pin(&blocks[pos], state) {
+                    Ok(Some(index)) => {
+                        pinned[pos] = Some((*s, index));
+                        buffers[pos] = Some(Arc::clone(state.buffer(index)));
+                    }
+                    Ok(None) => unreachable!("the shard has room for the blocks"),
+                    Err(e) => {
+                        error = Some(BufferError::Io(e));
+                        break 'shards;
+                    }
+                }
             }
-        })
+        }
+
+        if let Some(error) = error {
+            for (s, state) in states.iter_mut() {
+                for &(_, index) in pinned.iter().flatten().filter(|(ps, _)| ps == s) {
+                    self.shards[*s].unpin(state, index);
+                }
+            }
+            return Err(error);
+        }
+        Ok(pinned
+            .into_iter()
+            .zip(buffers)
+            .map(|(pin, buffer)| {
+                let (s, index) = pin.expect("every block is pinned");
+                PinnedBuffer {
+                    shard: Arc::clone(&self.shards[s]),
+                    index,
+                    buffer: buffer.expect("every block is pinned"),
+                }
+            })
+            .collect())
+    }
+
+    // Reads the specified blocks into unpinned buffers, so
+    // that pinning them later finds them in the pool.
+    // The blocks of each shard that are not yet in it are
+    // read with a single request to the store, into buffers
+    // chosen by the shard's replacement policy; the blocks
+    // left over when a shard runs out of unpinned buffers
+    // are skipped.
+    // Returns the number of blocks read.
+    pub fn prefetch(&self, blocks: &[BlockId]) -> Result<usize, BufferError> {
+        let mut wanted: BTreeMap<usize, Vec<&BlockId>> = BTreeMap::new();
+        for block in blocks {
+            wanted
+                .entry(self.shard_index(block))
+                .or_default()
+                .push(block);
+        }
+        let mut read = 0;
+        for (s, blocks) in wanted {
+            let mut state = self.shards[s].state.lock().unwrap();
+            read += state.prefetch(&blocks).map_err(BufferError::Io)?;
+        }
+        Ok(read)
     }
 
-    // Naive implementation
-    fn choose_unpinned_buffer(&self) -> Option<Arc<Mutex<BufferPage>>> {
-        self.buffer_pool.iter().find_map(|buff| {
-            let buffer = buff.lock().unwrap();
-            if !buffer.is_pinned() {
-                Some(Arc::clone(buff))
-            } else {
-                None
+    // The shard that the block belongs to.
+    fn shard(&self, block: &BlockId) -> &Arc<Shard<S>> {
+        &self.shards[self.shard_index(block)]
+    }
+
+    fn shard_index(&self, block: &BlockId) -> usize {
+        let mut hasher = DefaultHasher::new();
+        block.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+}
+
+impl<S: BlockStore> Shard<S> {
+    // Returns true if the shard can pin all of the blocks
+    // at the specified positions without waiting.
+    // Each block not in the shard needs an unpinned buffer
+    // that does not hold one of the other blocks.
+    fn has_room(blocks: &[BlockId], positions: &[usize], state: &PoolState<S>) -> bool {
+        let distinct: HashSet<&BlockId> = positions.iter().map(|&pos| &blocks[pos]).collect();
+        let mut misses = 0;
+        let mut free = state.unpinned.len();
+        for block in distinct {
+            match state.block_map.get(block) {
+                Some(i) if state.unpinned.contains(i) => free -= 1,
+                Some(_) => {}
+                None => misses += 1,
             }
-        })
+        }
+        misses <= free
+    }
+
+    // Unpins the buffer at the specified position.
+    // If its pin count drops to zero, the buffer is removed
+    // if the shard is to shrink, and otherwise the threads
+    // waiting for a buffer of the shard are woken up.
+    fn unpin(&self, state: &mut PoolState<S>, index: usize) {
+        let pinned = {
+            let mut page = state.buffer(index).lock().unwrap();
+            page.unpin();
+            page.is_pinned()
+        };
+        if pinned {
+            return;
+        }
+        // a buffer that cannot be written is kept
+        if state.size() > state.target && state.remove(index).is_ok() {
+            return;
+        }
+        state.unpinned.insert(index);
+        state.policy.on_unpin(index);
+        self.freed.notify_all();
+    }
+
+    // Tries to pin a buffer to the specified block.
+    // If there is already a buffer assigned to that block
+    // then that buffer is used;
+    // otherwise, the replacement policy chooses
+    // an unpinned buffer from the shard.
+    // Returns the position of the pinned buffer,
+    // or a null value if there are no available buffers.
+    fn try_to_pin(
+        &self,
+        block: &BlockId,
+        state: &mut PoolState<S>,
+    ) -> Result<Option<usize>, std::io::Error> {
+        if let Some(&i) = state.block_map.get(block) {
+            let buff = state.buffer(i);
+            buff.lock().unwrap().pin();
+            state.unpinned.remove(&i);
+            state.policy.on_pin(i);
+            state.stats.hits += 1;
+            return Ok(Some(i));
+        }
+
+        let unpinned: Vec<usize> = state.unpinned.iter().copied().collect();
+        let Some(i) = state.policy.choose_victim(&unpinned) else {
+            return Ok(None);
+        };
+        let buff = Arc::clone(state.buffer(i));
+        let mut buffer = buff.lock().unwrap();
+        if let Some(old) = buffer.block() {
+            state.block_map.remove(old);
+            state.stats.evictions += 1;
+        }
+        state.stats.misses += 1;
+        buffer.assign_to_block(block.clone())?;
+        state.block_map.insert(block.clone(), i);
+        state.policy.on_assign(i);
+        buffer.pin();
+        state.unpinned.remove(&i);
+        state.policy.on_pin(i);
+        Ok(Some(i))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        buffer::{ClockPolicy, FifoPolicy, LruKPolicy, LruPolicy},
+        file::Page,
+    };
     use tempfile::TempDir;
 
     fn setup() -> (TempDir, Arc<FileManager>, Arc<Mutex<LogManager>>) {
@@ -156,13 +826,17 @@ mod tests {
         let lm = Arc::new(Mutex::new(
             LogManager::new(Arc::clone(&fm), "test.log".to_string()).unwrap(),
         ));
+        // the blocks pinned by the tests
+        for _ in 0..5 {
+            fm.append("test_file1").unwrap();
+        }
         (temp_dir, fm, lm)
     }
 
     #[test]
     fn test_buffer_pinning() {
         let (_temp_dir, fm, lm) = setup();
-        let mut bm = BufferManager::new_with_timeout(Arc::clone(&fm), Arc::clone(&lm), 3, 100);
+        let bm = BufferManager::new_with_timeout(Arc::clone(&fm), Arc::clone(&lm), 3, 100);
 
         assert_eq!(bm.available(), 3, "All buffers should be available");
 
@@ -181,7 +855,7 @@ mod tests {
         assert_eq!(bm.available(), 0);
 
         // Unpin one buffer
-        bm.unpin(buff1);
+        drop(buff1);
         assert_eq!(bm.available(), 1);
 
         // Should be able to pin a new block
@@ -212,10 +886,408 @@ mod tests {
 
         // Try to pin when n buffers available
         match bm.pin(block4.clone()) {
-            Err(BufferError(msg)) => {
+            Err(BufferError::Abort(msg)) => {
                 assert!(msg.contains("timeout"));
             }
-            Ok(_) => panic!("Expected buffer pin to fail with timeout"),
+            _ => panic!("Expected buffer pin to fail with timeout"),
+        }
+    }
+
+    #[test]
+    fn test_pin_wakes_on_unpin() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = Arc::new(BufferManager::new_with_timeout(fm, lm, 2, 10_000));
+        let buff1 = bm.pin(BlockId::new("test_file1".to_string(), 1)).unwrap();
+        let _buff2 = bm.pin(BlockId::new("test_file1".to_string(), 2)).unwrap();
+
+        // A second thread waits for a buffer
+        let waiter = {
+            let bm = Arc::clone(&bm);
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                let buff = bm.pin(BlockId::new("test_file1".to_string(), 3));
+                (buff.ok(), start.elapsed())
+            })
+        };
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(bm.available(), 0);
+        drop(buff1);
+
+        // It gets the buffer right away, long before its timeout
+        let (pinned, waited) = waiter.join().unwrap();
+        assert!(pinned.is_some());
+        assert!(waited < Duration::from_secs(5), "waited {:?}", waited);
+        assert_eq!(bm.available(), 0);
+    }
+
+    #[test]
+    fn test_replacement_policy() {
+        let (_temp_dir, fm, lm) = setup();
+        let blocks: Vec<BlockId> = (0..4)
+            .map(|i| BlockId::new("test_file1".to_string(), i))
+            .collect();
+
+        // Pin blocks 0-2, and unpin them in the order 1, 0, 2;
+        // then block 3 replaces the buffer the policy picks
+        let victim = |policy: Box<dyn ReplacementPolicy>| {
+            let bm = BufferManager::with_policy(Arc::clone(&fm), Arc::clone(&lm), 3, 100, policy);
+            let mut pinned: Vec<_> = blocks[..3]
+                .iter()
+                .map(|blk| Some(bm.pin(blk.clone()).unwrap()))
+                .collect();
+            let buffs: Vec<_> = pinned
+                .iter()
+                .map(|b| Arc::clone(b.as_ref().unwrap().buffer()))
+                .collect();
+            for i in [1, 0, 2] {
+                pinned[i] = None;
+            }
+            let buff = bm.pin(blocks[3].clone()).unwrap();
+            buffs.iter().position(|b| Arc::ptr_eq(b, buff.buffer()))
+        };
+        assert_eq!(victim(Box::new(NaivePolicy::new())), Some(0));
+        assert_eq!(victim(Box::new(LruPolicy::new())), Some(1));
+        assert_eq!(victim(Box::new(FifoPolicy::new())), Some(0));
+        assert_eq!(victim(Box::new(ClockPolicy::new())), Some(0));
+    }
+
+    #[test]
+    fn test_scan_resistance() {
+        let (_temp_dir, fm, lm) = setup();
+        let block = |i| BlockId::new("test_file1".to_string(), i);
+
+        // Block 0 is used twice, then blocks 1-4 are
+        // read once each, as by a table scan.
+        // Return whether block 0 is still in the pool.
+        let survives = |policy: Box<dyn ReplacementPolicy>| {
+            let bm = BufferManager::with_policy(Arc::clone(&fm), Arc::clone(&lm), 3, 100, policy);
+            let hot = Arc::clone(bm.pin(block(0)).unwrap().buffer());
+            drop(bm.pin(block(0)).unwrap());
+            for i in 1..5 {
+                drop(bm.pin(block(i)).unwrap());
+            }
+            let page = hot.lock().unwrap();
+            page.block() == Some(&block(0))
+        };
+        assert!(!survives(Box::new(LruPolicy::new())));
+        assert!(survives(Box::new(LruKPolicy::new(2))));
+    }
+
+    #[test]
+    fn test_existing_buffer_lookup() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = BufferManager::new_with_timeout(fm, lm, 2, 100);
+        let block = |i| BlockId::new("test_file1".to_string(), i);
+
+        let buff0 = bm.pin(block(0)).unwrap();
+        let again = bm.pin(block(0)).unwrap();
+        assert!(Arc::ptr_eq(buff0.buffer(), again.buffer()));
+        assert_eq!(bm.available(), 1);
+        drop(again);
+        assert_eq!(bm.available(), 1);
+        let pinned = buff0;
+        let buff0 = Arc::clone(pinned.buffer());
+        drop(pinned);
+        assert_eq!(bm.available(), 2);
+
+        // block 0's buffer is reused for block 1,
+        // so block 0 then goes to the other buffer
+        let buff1 = bm.pin(block(1)).unwrap();
+        assert!(Arc::ptr_eq(&buff0, buff1.buffer()));
+        let buff0 = bm.pin(block(0)).unwrap();
+        assert!(!Arc::ptr_eq(buff0.buffer(), buff1.buffer()));
+        assert_eq!(buff0.page().block(), Some(&block(0)));
+        assert_eq!(bm.available(), 0);
+    }
+
+    #[test]
+    fn test_sharded_pool() {
+        let (_temp_dir, fm, lm) = setup();
+        for i in 5..64 {
+            fm.append("test_file1").unwrap();
+            assert_eq!(fm.length("test_file1").unwrap(), i + 1);
+        }
+        let bm = Arc::new(BufferManager::with_shards(fm, lm, 10, 10_000, 4, || {
+            Box::new(LruPolicy::new())
+        }));
+        assert_eq!(bm.available(), 10);
+
+        // Threads pin and unpin overlapping sets of blocks
+        let threads: Vec<_> = (0..8)
+            .map(|t| {
+                let bm = Arc::clone(&bm);
+                std::thread::spawn(move || {
+                    for n in 0..200 {
+                        let blk = BlockId::new("test_file1".to_string(), (t * 7 + n) % 64);
+                        let buff = bm.pin(blk.clone()).unwrap();
+                        assert_eq!(buff.page().block(), Some(&blk));
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(bm.available(), 10);
+
+        // A pinned block keeps its buffer
+        let blk = BlockId::new("test_file1".to_string(), 3);
+        let buff = bm.pin(blk.clone()).unwrap();
+        assert!(Arc::ptr_eq(buff.buffer(), bm.pin(blk).unwrap().buffer()));
+        assert_eq!(bm.available(), 9);
+    }
+
+    #[test]
+    fn test_stats() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = BufferManager::new_with_timeout(fm, lm, 2, 100);
+        let block = |i| BlockId::new("test_file1".to_string(), i);
+        assert_eq!(
+            bm.stats(),
+            BufferStats {
+                capacity: 2,
+                available: 2,
+                ..BufferStats::default()
+            }
+        );
+
+        let buff0 = bm.pin(block(0)).unwrap();
+        let buff1 = bm.pin(block(1)).unwrap();
+        buff1.page().set_modified(1, None);
+        let again = bm.pin(block(0)).unwrap();
+        assert!(bm.pin(block(2)).is_err());
+
+        let stats = bm.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 2, 0));
+        assert_eq!((stats.waits, stats.timeouts), (1, 1));
+        assert!(stats.wait_time >= Duration::from_millis(100));
+        assert_eq!((stats.available, stats.dirty), (0, 1));
+
+        drop((buff0, again));
+        let _buff2 = bm.pin(block(2)).unwrap();
+        bm.flush_all(1).unwrap();
+        let stats = bm.stats();
+        assert_eq!((stats.misses, stats.evictions, stats.dirty), (3, 1, 0));
+        assert!((stats.hit_ratio() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = BufferManager::new_with_timeout(Arc::clone(&fm), lm, 3, 100);
+        let block = |i| BlockId::new("test_file1".to_string(), i);
+        {
+            let buff = bm.pin(block(0)).unwrap();
+            let mut page = buff.page();
+            page.contents().set_int(80, 123);
+            page.set_modified(1, None);
+        }
+
+        // three buffers for four blocks; the dirty
+        // buffer is written before it is replaced
+        let blocks: Vec<BlockId> = (1..5).map(block).collect();
+        assert_eq!(bm.prefetch(&blocks).unwrap(), 3);
+        let mut page = Page::new(400);
+        fm.read(&block(0), &mut page).unwrap();
+        assert_eq!(page.get_int(80), 123);
+
+        let pinned = bm.pin_all(&blocks[..3]).unwrap();
+        let stats = bm.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (3, 1, 1));
+        assert_eq!(bm.prefetch(&blocks[..3]).unwrap(), 0);
+        drop(pinned);
+    }
+
+    #[test]
+    fn test_pin_all() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = BufferManager::new_with_timeout(fm, lm, 3, 100);
+        let block = |i| BlockId::new("test_file1".to_string(), i);
+
+        // With one buffer pinned, three new blocks
+        // cannot all be pinned, so none of them are
+        let buff0 = bm.pin(block(0)).unwrap();
+        assert!(bm.pin_all(&[block(1), block(2), block(3)]).is_err());
+        assert_eq!(bm.available(), 2);
+        assert_eq!(bm.pin_all(&vec![block(0); 4]).unwrap().len(), 4);
+        assert!(bm
+            .pin_all(&[block(1), block(2), block(3), block(4)])
+            .is_err());
+
+        // A block already pinned needs no new buffer
+        let buffs = bm.pin_all(&[block(2), block(0), block(1)]).unwrap();
+        assert_eq!(bm.available(), 0);
+        assert!(Arc::ptr_eq(buffs[1].buffer(), buff0.buffer()));
+        for (buff, i) in buffs.iter().zip([2, 0, 1]) {
+            assert_eq!(buff.page().block(), Some(&block(i)));
+        }
+        drop((buff0, buffs));
+        assert_eq!(bm.available(), 3);
+    }
+
+    #[test]
+    fn test_pin_all_no_deadlock() {
+        let (_temp_dir, fm, lm) = setup();
+        for _ in 5..16 {
+            fm.append("test_file1").unwrap();
+        }
+        let bm = Arc::new(BufferManager::with_shards(fm, lm, 8, 10_000, 2, || {
+            Box::new(NaivePolicy::new())
+        }));
+
+        // Each thread needs most of the pool at once,
+        // so the threads must take turns
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let bm = Arc::clone(&bm);
+                std::thread::spawn(move || {
+                    for n in 0..20 {
+                        let blocks: Vec<BlockId> = (0..3)
+                            .map(|i| BlockId::new("test_file1".to_string(), (t + n + i * 5) % 16))
+                            .collect();
+                        let buffs = bm.pin_all(&blocks).unwrap();
+                        assert_eq!(buffs.len(), 3);
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(bm.available(), 8);
+    }
+
+    #[test]
+    fn test_work_limit() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = BufferManager::new_with_timeout(Arc::clone(&fm), Arc::clone(&lm), 4, 100);
+        assert_eq!(bm.work_buffers(), 4);
+
+        let bm = BufferManager::new_with_timeout(fm, lm, 4, 100).with_work_limit(Some(2));
+        assert_eq!(bm.work_buffers(), 2);
+        let _buff0 = bm.pin(BlockId::new("test_file1".to_string(), 0)).unwrap();
+        let _buff1 = bm.pin(BlockId::new("test_file1".to_string(), 1)).unwrap();
+        assert_eq!(bm.work_buffers(), 2);
+        // the operators may not claim more buffers than are unpinned
+        let _buff2 = bm.pin(BlockId::new("test_file1".to_string(), 2)).unwrap();
+        assert_eq!((bm.available(), bm.work_buffers()), (1, 1));
+    }
+
+    #[test]
+    fn test_resize() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = BufferManager::new_with_timeout(Arc::clone(&fm), lm, 4, 100);
+        let block = |i| BlockId::new("test_file1".to_string(), i);
+        let buff0 = bm.pin(block(0)).unwrap();
+        let buff1 = bm.pin(block(1)).unwrap();
+        {
+            let buff2 = bm.pin(block(2)).unwrap();
+            let mut page = buff2.page();
+            page.contents().set_int(0, 9);
+            page.set_modified(1, None);
+        }
+
+        // Shrinking removes the unpinned buffers,
+        // writing the dirty one
+        bm.resize(2).unwrap();
+        let stats = bm.stats();
+        assert_eq!((stats.capacity, stats.available, stats.dirty), (2, 0, 0));
+        let mut page = Page::new(fm.block_size());
+        fm.read(&block(2), &mut page).unwrap();
+        assert_eq!(page.get_int(0), 9);
+
+        // A pinned buffer is removed once it is unpinned
+        bm.resize(1).unwrap();
+        assert_eq!(bm.stats().capacity, 2);
+        drop(buff0);
+        assert_eq!((bm.stats().capacity, bm.available()), (1, 0));
+        assert!(bm.pin(block(2)).is_err());
+
+        // Growing adds empty buffers
+        bm.resize(3).unwrap();
+        assert_eq!((bm.stats().capacity, bm.available()), (3, 2));
+        let _buffs = bm.pin_all(&[block(2), block(3)]).unwrap();
+        assert_eq!(buff1.page().block(), Some(&block(1)));
+        assert!(bm.resize(0).is_err());
+    }
+
+    #[test]
+    fn test_dirty_page_table() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = BufferManager::new_with_timeout(fm, lm, 3, 100);
+        let block = |i| BlockId::new("test_file1".to_string(), i);
+        let buffs = bm.pin_all(&[block(0), block(1), block(2)]).unwrap();
+        buffs[0].page().set_modified(1, Some(Lsn(5)));
+        buffs[0].page().set_modified(1, Some(Lsn(8)));
+        buffs[1].page().set_modified(2, Some(Lsn(7)));
+        // an unlogged modification needs no redo
+        buffs[2].page().set_modified(2, None);
+        assert_eq!(
+            bm.dirty_page_table(),
+            HashMap::from([(block(0), Lsn(5)), (block(1), Lsn(7))])
+        );
+
+        bm.flush_all(1).unwrap();
+        assert_eq!(bm.dirty_page_table(), HashMap::from([(block(1), Lsn(7))]));
+    }
+
+    #[test]
+    fn test_discard() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = BufferManager::new_with_timeout(Arc::clone(&fm), lm, 3, 100);
+        let block = |i| BlockId::new("test_file1".to_string(), i);
+        let buffs = bm.pin_all(&[block(1), block(3), block(4)]).unwrap();
+        for buff in &buffs {
+            buff.page().contents().set_int(0, 7);
+            buff.page().set_modified(1, Some(Lsn(5)));
+        }
+        let pinned = buffs.into_iter().last().unwrap();
+        bm.discard("test_file1", 3);
+        fm.truncate("test_file1", 3).unwrap();
+
+        // the cut off blocks are not written back
+        assert_eq!(bm.dirty_page_table(), HashMap::from([(block(1), Lsn(5))]));
+        assert_eq!(bm.flush_all_dirty().unwrap(), 1);
+        assert_eq!(pinned.page().block(), Some(&block(4)));
+        drop(pinned);
+        assert_eq!(fm.length("test_file1").unwrap(), 3);
+
+        // nor found in the pool when the file grows again
+        fm.append("test_file1").unwrap();
+        let buff = bm.pin(block(3)).unwrap();
+        assert_eq!(buff.page().contents().get_int(0), 0);
+    }
+
+    #[test]
+    fn test_fair_waiting() {
+        let (_temp_dir, fm, lm) = setup();
+        let bm = Arc::new(BufferManager::new_with_timeout(fm, lm, 1, 10_000));
+        let buff0 = bm.pin(BlockId::new("test_file1".to_string(), 0)).unwrap();
+
+        // Threads start waiting for the only buffer one after
+        // another, and each holds it for a moment once it gets it
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let waiters: Vec<_> = (1..5)
+            .map(|i| {
+                let bm = Arc::clone(&bm);
+                let order = Arc::clone(&order);
+                let waiter = std::thread::spawn(move || {
+                    let _buff = bm.pin(BlockId::new("test_file1".to_string(), i)).unwrap();
+                    order.lock().unwrap().push(i);
+                    std::thread::sleep(Duration::from_millis(10));
+                });
+                std::thread::sleep(Duration::from_millis(30));
+                waiter
+            })
+            .collect();
+        assert_eq!(bm.stats().waits, 0);
+
+        // They get the buffer in the order they asked for it
+        drop(buff0);
+        for waiter in waiters {
+            waiter.join().unwrap();
         }
+        assert_eq!(*order.lock().unwrap(), [1, 2, 3, 4]);
+        assert_eq!(bm.stats().waits, 4);
     }
 }
@@ -1,5 +1,11 @@
+mod flusher;
 mod manager;
 mod page;
+mod replacement;
 
-pub use manager::BufferManager;
+pub use flusher::BufferFlusher;
+pub use manager::{BufferError, BufferManager, BufferStats, PinnedBuffer};
 pub use page::BufferPage;
+pub use replacement::{
+    ClockPolicy, FifoPolicy, LruKPolicy, LruPolicy, NaivePolicy, ReplacementPolicy,
+};
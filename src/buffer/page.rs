@@ -1,17 +1,18 @@
 use crate::{
-    file::{BlockId, FileManager, Page},
-    log::LogManager,
+    file::{BlockId, BlockStore, FileManager, Page},
+    log::{LogManager, Lsn},
 };
 use std::sync::{Arc, Mutex};
 
-pub struct BufferPage {
-    fm: Arc<FileManager>,
-    lm: Arc<Mutex<LogManager>>,
+pub struct BufferPage<S: BlockStore = FileManager> {
+    fm: Arc<S>,
+    lm: Arc<Mutex<LogManager<S>>>,
     contents: Page,
     block: Option<BlockId>,
     pins: u32,
     txnum: i32,
-    lsn: i32,
+    lsn: Lsn,
+    recovery_lsn: Option<Lsn>,
 }
 
 // An individual buffer. A databuffer wraps a page
@@ -19,9 +20,10 @@ pub struct BufferPage {
 // such as the associated disk block,
 // the number of times the buffer has been pinned,
 // whether its contents have been modified,
-// and if so, the id and lsn of the modifying transaction.
-impl BufferPage {
-    pub fn new(fm: Arc<FileManager>, lm: Arc<Mutex<LogManager>>) -> Self {
+// and if so, the id and lsn of the modifying transaction,
+// and the lsn of the earliest modification not yet on disk.
+impl<S: BlockStore> BufferPage<S> {
+    pub fn new(fm: Arc<S>, lm: Arc<Mutex<LogManager<S>>>) -> Self {
         let block_size = fm.block_size();
         BufferPage {
             fm,
@@ -30,7 +32,8 @@ impl BufferPage {
             block: None,
             pins: 0,
             txnum: -1,
-            lsn: -1,
+            lsn: Lsn(0),
+            recovery_lsn: None,
         }
     }
 
@@ -42,13 +45,31 @@ impl BufferPage {
         self.block.as_ref()
     }
 
-    pub fn set_modified(&mut self, txnum: i32, lsn: i32) {
+    // Mark the buffer as modified by the transaction.
+    // The lsn is that of the modification's log record,
+    // or None if the modification was not logged.
+    pub fn set_modified(&mut self, txnum: i32, lsn: Option<Lsn>) {
         self.txnum = txnum;
-        if lsn >= 0 {
+        if let Some(lsn) = lsn {
             self.lsn = lsn;
+            self.recovery_lsn.get_or_insert(lsn);
         }
     }
 
+    // Return the lsn of the latest logged modification
+    // of the page, which is stored with its block.
+    // Recovery redoes only the records after it.
+    pub fn page_lsn(&self) -> Lsn {
+        self.lsn
+    }
+
+    // Return the lsn of the first logged modification
+    // since the buffer was last written, if there is one.
+    // Redo for the buffer's block can start at this record.
+    pub fn recovery_lsn(&self) -> Option<Lsn> {
+        self.recovery_lsn
+    }
+
     // Return true if the buffer is currently pinned
     // (that is, if it has a nonzero pin count).
     pub fn is_pinned(&self) -> bool {
@@ -63,11 +84,17 @@ impl BufferPage {
     // the contents of the buffer.
     // If the buffer was dirty, then its previous contents
     // are first written to disk.
+    // If the block cannot be read, the buffer is
+    // left unassigned.
     pub fn assign_to_block(&mut self, b: BlockId) -> std::io::Result<()> {
         self.flush()?;
-        self.block = Some(b.clone());
-        self.fm.read(&b, &mut self.contents)?;
         self.pins = 0;
+        if let Err(e) = self.fm.read(&b, &mut self.contents) {
+            self.block = None;
+            return Err(e);
+        }
+        self.lsn = self.contents.lsn();
+        self.block = Some(b);
         Ok(())
     }
 
@@ -76,13 +103,85 @@ impl BufferPage {
         if self.txnum >= 0 {
             self.lm.lock().unwrap().flush(self.lsn)?;
             if let Some(block) = &self.block {
+                self.contents.set_lsn(self.lsn);
                 self.fm.write(block, &mut self.contents)?;
             }
             self.txnum = -1;
+            self.recovery_lsn = None;
         }
         Ok(())
     }
 
+    // Write the dirty buffers among the specified ones
+    // with a single request to the store, once the log is
+    // flushed up to the latest of their modifications.
+    pub(crate) fn flush_batch(pages: &mut [&mut BufferPage<S>]) -> std::io::Result<()> {
+        let mut dirty: Vec<&mut BufferPage<S>> = pages
+            .iter_mut()
+            .map(|page| &mut **page)
+            .filter(|page| page.txnum >= 0)
+            .collect();
+        let Some(lsn) = dirty.iter().map(|page| page.lsn).max() else {
+            return Ok(());
+        };
+        let fm = Arc::clone(&dirty[0].fm);
+        dirty[0].lm.lock().unwrap().flush(lsn)?;
+        let mut writes: Vec<(&BlockId, &mut Page)> = dirty
+            .iter_mut()
+            .filter_map(|page| {
+                page.contents.set_lsn(page.lsn);
+                page.block.as_ref().map(|b| (b, &mut page.contents))
+            })
+            .collect();
+        fm.write_all(&mut writes)?;
+        for page in dirty {
+            page.txnum = -1;
+            page.recovery_lsn = None;
+        }
+        Ok(())
+    }
+
+    // Read the blocks into the buffers, one block per buffer,
+    // with a single request to the store, as assign_to_block
+    // would one at a time.
+    pub(crate) fn assign_batch(
+        pages: &mut [&mut BufferPage<S>],
+        blocks: Vec<BlockId>,
+    ) -> std::io::Result<()> {
+        Self::flush_batch(pages)?;
+        let Some(first) = pages.first() else {
+            return Ok(());
+        };
+        let fm = Arc::clone(&first.fm);
+        for page in pages.iter_mut() {
+            page.pins = 0;
+            page.block = None;
+        }
+        let mut reads: Vec<(&BlockId, &mut Page)> = blocks
+            .iter()
+            .zip(pages.iter_mut())
+            .map(|(block, page)| (block, &mut page.contents))
+            .collect();
+        fm.read_all(&mut reads)?;
+        for (page, block) in pages.iter_mut().zip(blocks) {
+            page.lsn = page.contents.lsn();
+            page.block = Some(block);
+        }
+        Ok(())
+    }
+
+    // Forget the modifications of the buffer without writing
+    // them, as for a block cut off its file. An unpinned
+    // buffer is also unassigned from its block.
+    pub(crate) fn discard(&mut self) {
+        self.txnum = -1;
+        self.recovery_lsn = None;
+        if self.pins == 0 {
+            self.block = None;
+            self.lsn = Lsn(0);
+        }
+    }
+
     pub fn pin(&mut self) {
         self.pins += 1;
     }
@@ -145,7 +244,7 @@ mod tests {
         }
 
         // Mark as modified
-        buffer.set_modified(1, 0);
+        buffer.set_modified(1, Some(Lsn(1)));
         assert_eq!(buffer.modifying_txn(), 1);
 
         // Test flush
@@ -170,18 +269,42 @@ mod tests {
             let page = buffer.contents();
             page.set_int(80, 100);
         }
-        buffer.set_modified(1, 0);
+        buffer.set_modified(1, Some(Lsn(1)));
+        assert_eq!(buffer.recovery_lsn(), Some(Lsn(1)));
         buffer.flush()?;
+        assert_eq!(buffer.recovery_lsn(), None);
 
         // Second modification
         {
             let page = buffer.contents();
             page.set_int(80, 200);
         }
-        buffer.set_modified(2, 1);
+        buffer.set_modified(2, Some(Lsn(2)));
+        buffer.set_modified(2, Some(Lsn(3)));
 
         assert_eq!(buffer.modifying_txn(), 2);
+        assert_eq!(buffer.recovery_lsn(), Some(Lsn(2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_page_lsn_is_stored() -> std::io::Result<()> {
+        let (fm, lm, _temp_dir) = setup();
+        init_file(&fm, "testfile", 1)?;
+        let block = BlockId::new("testfile".to_string(), 1);
+
+        let mut buffer = BufferPage::new(Arc::clone(&fm), Arc::clone(&lm));
+        buffer.assign_to_block(block.clone())?;
+        assert_eq!(buffer.page_lsn(), Lsn(0));
+        buffer.contents().set_int(80, 100);
+        buffer.set_modified(1, Some(Lsn(7)));
+        buffer.flush()?;
 
+        let mut other = BufferPage::new(Arc::clone(&fm), Arc::clone(&lm));
+        other.assign_to_block(block)?;
+        assert_eq!(other.page_lsn(), Lsn(7));
+        assert_eq!(other.contents().get_int(80), 100);
         Ok(())
     }
 }
@@ -0,0 +1,325 @@
+use std::collections::VecDeque;
+
+// Strategies for choosing the buffer to replace when a
+// block that is not in the pool has to be pinned.
+// The buffer manager tells its policy about every pin,
+// and about each buffer that becomes unpinned or is
+// assigned to a new block; buffers are identified by
+// their position in the pool.
+pub trait ReplacementPolicy: Send {
+    // Choose the buffer to replace from the unpinned buffers,
+    // which are listed in pool order.
+    fn choose_victim(&mut self, unpinned: &[usize]) -> Option<usize>;
+
+    // The buffer was assigned to a new block.
+    fn on_assign(&mut self, _buff: usize) {}
+
+    // The buffer was pinned.
+    fn on_pin(&mut self, _buff: usize) {}
+
+    // The buffer's last pin was released.
+    fn on_unpin(&mut self, _buff: usize) {}
+}
+
+// Set the logical time of the buffer, growing the
+// vector of times as needed.
+fn stamp(times: &mut Vec<u64>, buff: usize, time: u64) {
+    if times.len() <= buff {
+        times.resize(buff + 1, 0);
+    }
+    times[buff] = time;
+}
+
+// Choose the buffer having the earliest time;
+// buffers that were never stamped come first.
+fn earliest(times: &[u64], unpinned: &[usize]) -> Option<usize> {
+    unpinned
+        .iter()
+        .copied()
+        .min_by_key(|&buff| times.get(buff).copied().unwrap_or(0))
+}
+
+// Replace the first unpinned buffer in the pool.
+#[derive(Debug, Default)]
+pub struct NaivePolicy;
+
+impl NaivePolicy {
+    pub fn new() -> Self {
+        NaivePolicy
+    }
+}
+
+impl ReplacementPolicy for NaivePolicy {
+    fn choose_victim(&mut self, unpinned: &[usize]) -> Option<usize> {
+        unpinned.first().copied()
+    }
+}
+
+// Replace the unpinned buffer that was assigned
+// to its block the longest time ago.
+#[derive(Debug, Default)]
+pub struct FifoPolicy {
+    clock: u64,
+    assigned: Vec<u64>,
+}
+
+impl FifoPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ReplacementPolicy for FifoPolicy {
+    fn choose_victim(&mut self, unpinned: &[usize]) -> Option<usize> {
+        earliest(&self.assigned, unpinned)
+    }
+
+    fn on_assign(&mut self, buff: usize) {
+        self.clock += 1;
+        stamp(&mut self.assigned, buff, self.clock);
+    }
+}
+
+// Replace the unpinned buffer that has been
+// unpinned the longest.
+#[derive(Debug, Default)]
+pub struct LruPolicy {
+    clock: u64,
+    unpinned: Vec<u64>,
+}
+
+impl LruPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ReplacementPolicy for LruPolicy {
+    fn choose_victim(&mut self, unpinned: &[usize]) -> Option<usize> {
+        earliest(&self.unpinned, unpinned)
+    }
+
+    fn on_unpin(&mut self, buff: usize) {
+        self.clock += 1;
+        stamp(&mut self.unpinned, buff, self.clock);
+    }
+}
+
+// The clock algorithm.
+// The buffers are scanned in a circle, starting after
+// the last one replaced. A pin gives its buffer a
+// second chance: the scan clears the buffer's reference
+// bit and moves on, replacing the first unpinned buffer
+// whose bit is already clear.
+#[derive(Debug, Default)]
+pub struct ClockPolicy {
+    hand: usize,
+    referenced: Vec<bool>,
+}
+
+impl ClockPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ReplacementPolicy for ClockPolicy {
+    fn choose_victim(&mut self, unpinned: &[usize]) -> Option<usize> {
+        // the unpinned buffers in circular order from the hand
+        let start = unpinned.partition_point(|&buff| buff < self.hand);
+        let order: Vec<usize> = unpinned[start..]
+            .iter()
+            .chain(&unpinned[..start])
+            .copied()
+            .collect();
+        // after one pass, every bit is clear
+        for &buff in order.iter().chain(&order) {
+            match self.referenced.get_mut(buff) {
+                Some(bit) if *bit => *bit = false,
+                _ => {
+                    self.hand = buff + 1;
+                    return Some(buff);
+                }
+            }
+        }
+        None
+    }
+
+    fn on_pin(&mut self, buff: usize) {
+        if self.referenced.len() <= buff {
+            self.referenced.resize(buff + 1, false);
+        }
+        self.referenced[buff] = true;
+    }
+}
+
+// The LRU-K algorithm.
+// Replace the unpinned buffer whose K-th most recent pin
+// is the oldest. A buffer pinned fewer than K times since
+// it was assigned counts as older than all the others,
+// so the blocks read once by a sequential scan are replaced
+// before the blocks that are used repeatedly; among such
+// buffers, the least recently pinned one is chosen.
+#[derive(Debug)]
+pub struct LruKPolicy {
+    k: usize,
+    clock: u64,
+    // the times of the last k pins of each buffer, oldest first
+    history: Vec<VecDeque<u64>>,
+}
+
+impl LruKPolicy {
+    pub const DEFAULT_K: usize = 2;
+
+    pub fn new(k: usize) -> Self {
+        assert!(k > 0, "LRU-K needs k of at least 1");
+        LruKPolicy {
+            k,
+            clock: 0,
+            history: Vec::new(),
+        }
+    }
+
+    fn history(&mut self, buff: usize) -> &mut VecDeque<u64> {
+        if self.history.len() <= buff {
+            self.history.resize(buff + 1, VecDeque::new());
+        }
+        &mut self.history[buff]
+    }
+}
+
+impl Default for LruKPolicy {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_K)
+    }
+}
+
+impl ReplacementPolicy for LruKPolicy {
+    fn choose_victim(&mut self, unpinned: &[usize]) -> Option<usize> {
+        unpinned.iter().copied().min_by_key(|&buff| {
+            let pins = self.history.get(buff).filter(|h| !h.is_empty());
+            match pins {
+                Some(pins) if pins.len() == self.k => (1, pins[0]),
+                Some(pins) => (0, pins[pins.len() - 1]),
+                None => (0, 0),
+            }
+        })
+    }
+
+    // A new block starts with no history.
+    fn on_assign(&mut self, buff: usize) {
+        self.history(buff).clear();
+    }
+
+    fn on_pin(&mut self, buff: usize) {
+        self.clock += 1;
+        let (k, time) = (self.k, self.clock);
+        let history = self.history(buff);
+        if history.len() == k {
+            history.pop_front();
+        }
+        history.push_back(time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Replay a sequence of pins and unpins on a pool of
+    // three buffers, then return the policy's victim.
+    fn victim(policy: &mut dyn ReplacementPolicy, ops: &[(char, usize)]) -> Option<usize> {
+        let mut pins = [0; 3];
+        for &(op, buff) in ops {
+            match op {
+                'a' => policy.on_assign(buff),
+                'p' => {
+                    pins[buff] += 1;
+                    policy.on_pin(buff);
+                }
+                _ => {
+                    pins[buff] -= 1;
+                    if pins[buff] == 0 {
+                        policy.on_unpin(buff);
+                    }
+                }
+            }
+        }
+        let unpinned: Vec<usize> = (0..3).filter(|&b| pins[b] == 0).collect();
+        policy.choose_victim(&unpinned)
+    }
+
+    #[test]
+    fn test_policies() {
+        // buffers 0, 1, 2 are assigned in order, then
+        // buffer 0 is reused, and 2 stays pinned
+        let ops = [
+            ('a', 0),
+            ('p', 0),
+            ('a', 1),
+            ('p', 1),
+            ('a', 2),
+            ('p', 2),
+            ('u', 1),
+            ('u', 0),
+            ('p', 0),
+            ('u', 0),
+        ];
+        assert_eq!(victim(&mut NaivePolicy::new(), &ops), Some(0));
+        assert_eq!(victim(&mut FifoPolicy::new(), &ops), Some(0));
+        assert_eq!(victim(&mut LruPolicy::new(), &ops), Some(1));
+        assert_eq!(victim(&mut ClockPolicy::new(), &ops), Some(0));
+        // buffer 0 was pinned twice, buffer 1 only once
+        assert_eq!(victim(&mut LruKPolicy::default(), &ops), Some(1));
+
+        let all_pinned = [('p', 0), ('p', 1), ('p', 2)];
+        for policy in [
+            &mut NaivePolicy::new() as &mut dyn ReplacementPolicy,
+            &mut FifoPolicy::new(),
+            &mut LruPolicy::new(),
+            &mut ClockPolicy::new(),
+            &mut LruKPolicy::default(),
+        ] {
+            assert_eq!(victim(policy, &all_pinned), None);
+        }
+    }
+
+    #[test]
+    fn test_clock_second_chance() {
+        let mut clock = ClockPolicy::new();
+        for buff in 0..3 {
+            clock.on_pin(buff);
+        }
+        // every bit is set, so the first pass clears
+        // them all and the hand comes back to buffer 0
+        assert_eq!(clock.choose_victim(&[0, 1, 2]), Some(0));
+        // the scan continues after the victim
+        clock.on_pin(0);
+        assert_eq!(clock.choose_victim(&[0, 1, 2]), Some(1));
+        assert_eq!(clock.choose_victim(&[0, 2]), Some(2));
+        // buffer 0 was referenced, so it gets a second chance
+        // but is chosen when nothing else is unpinned
+        assert_eq!(clock.choose_victim(&[0]), Some(0));
+    }
+
+    #[test]
+    fn test_lru_k() {
+        let mut lru2 = LruKPolicy::new(2);
+        // buffer 0 is pinned twice, long ago; buffers 1
+        // and 2 are pinned once each, more recently
+        for buff in [0, 0, 1, 2] {
+            lru2.on_pin(buff);
+        }
+        assert_eq!(lru2.choose_victim(&[0, 1, 2]), Some(1));
+        // once 1 and 2 have two pins, the oldest
+        // second-to-last pin loses
+        for buff in [1, 2, 1] {
+            lru2.on_pin(buff);
+        }
+        assert_eq!(lru2.choose_victim(&[0, 1, 2]), Some(0));
+        // a reassigned buffer forgets its pins
+        lru2.on_assign(2);
+        lru2.on_pin(2);
+        assert_eq!(lru2.choose_victim(&[0, 1, 2]), Some(2));
+    }
+}
@@ -1,31 +1,319 @@
-use crate::{buffer::BufferManager, file::FileManager, log::LogManager};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use crate::{
+    buffer::{BufferManager, NaivePolicy, ReplacementPolicy},
+    driver::{Outcome, ResultSet},
+    error::{DbError, DbResult},
+    file::{is_temporary, BlockId, FileManager, Superblock},
+    log::LogManager,
+    metadata::MetadataManager,
+    parse::Parser,
+    plan::{BasicUpdatePlanner, HeuristicQueryPlanner, Planner},
+    tx::{
+        concurrency::{DeadlockPolicy, IsolationLevel, LockStats, LockTable},
+        recovery::{Durability, LogRecord, RecoveryManager, RecoveryTarget},
+        Transaction, TransactionInfo, TransactionRegistry, TxnIdAllocator,
+    },
+};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 pub struct SimpleDB {
     fm: Arc<FileManager>,
     lm: Arc<Mutex<LogManager>>,
-    bm: BufferManager,
+    bm: Arc<BufferManager>,
+    lock_table: Arc<LockTable>,
+    txn_ids: TxnIdAllocator,
+    transactions: Arc<TransactionRegistry>,
+    // held shared while a transaction starts, and exclusively
+    // while a checkpoint finds none active and truncates the log
+    tx_gate: RwLock<()>,
+    // the planner and the metadata manager it plans with
+    planner: Mutex<Option<(Arc<Planner>, Arc<MetadataManager>)>>,
+    durability: Durability,
+    read_only: bool,
+    closed: bool,
+}
+
+// The settings a database is opened with. Those left
+// to their defaults give a database like SimpleDB::new.
+pub struct DbConfig {
+    // The block size of a new database; an existing
+    // database is opened with its own.
+    pub block_size: Option<usize>,
+    pub buffer_size: u32,
+    // The policy choosing the buffers to replace,
+    // NaivePolicy if None.
+    pub replacement_policy: Option<Box<dyn ReplacementPolicy>>,
+    // The most buffers that the sorts, hash joins and other
+    // operators of a query may claim for their work, so that
+    // a big query does not starve the others of buffers;
+    // every unpinned buffer if None.
+    pub work_buffers: Option<usize>,
+    pub lock_timeout: Duration,
+    pub deadlock_policy: DeadlockPolicy,
+    // What the commit of a transaction waits for.
+    pub durability: Durability,
+    pub log_file: String,
+    // Every transaction on the database is read-only, and the
+    // database is not recovered when it is opened; it must
+    // exist, and have been shut down cleanly.
+    pub read_only: bool,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        DbConfig {
+            block_size: None,
+            buffer_size: SimpleDB::BUFFER_SIZE,
+            replacement_policy: None,
+            work_buffers: None,
+            lock_timeout: LockTable::DEFAULT_TIMEOUT,
+            deadlock_policy: DeadlockPolicy::default(),
+            durability: Durability::default(),
+            log_file: SimpleDB::LOG_FILE.to_string(),
+            read_only: false,
+        }
+    }
+}
+
+// Opens a database with settings other than the defaults:
+//     let db = SimpleDB::builder("mydb")
+//         .buffers(64)
+//         .replacement_policy(LruPolicy::new())
+//         .work_buffers(16)
+//         .durability(Durability::NoForce)
+//         .open()?;
+// The settings are checked before any file is touched.
+pub struct SimpleDBBuilder {
+    dirname: PathBuf,
+    config: DbConfig,
+}
+
+impl SimpleDBBuilder {
+    pub fn new(dirname: impl AsRef<Path>) -> Self {
+        SimpleDBBuilder {
+            dirname: dirname.as_ref().to_path_buf(),
+            config: DbConfig::default(),
+        }
+    }
+
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.config.block_size = Some(block_size);
+        self
+    }
+
+    pub fn buffers(mut self, buffer_size: u32) -> Self {
+        self.config.buffer_size = buffer_size;
+        self
+    }
+
+    pub fn replacement_policy(mut self, policy: impl ReplacementPolicy + 'static) -> Self {
+        self.config.replacement_policy = Some(Box::new(policy));
+        self
+    }
+
+    pub fn work_buffers(mut self, work_buffers: usize) -> Self {
+        self.config.work_buffers = Some(work_buffers);
+        self
+    }
+
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.config.lock_timeout = timeout;
+        self
+    }
+
+    pub fn deadlock_policy(mut self, policy: DeadlockPolicy) -> Self {
+        self.config.deadlock_policy = policy;
+        self
+    }
+
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.config.durability = durability;
+        self
+    }
+
+    pub fn log_file(mut self, log_file: impl Into<String>) -> Self {
+        self.config.log_file = log_file.into();
+        self
+    }
+
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.config.read_only = read_only;
+        self
+    }
+
+    pub fn config(&self) -> &DbConfig {
+        &self.config
+    }
+
+    // Check the settings, against the database in the
+    // directory if there is one, then open the database,
+    // creating it if needed.
+    pub fn open(self) -> io::Result<SimpleDB> {
+        let config = &self.config;
+        if config.buffer_size == 0 || config.work_buffers == Some(0) {
+            return Err(invalid_input(DbError::InvalidBufferSize));
+        }
+        if config.log_file.is_empty() || is_temporary(&config.log_file) {
+            return Err(invalid_input(DbError::IncompatibleFormat(format!(
+                "{:?} cannot be the name of the log file",
+                config.log_file
+            ))));
+        }
+        let existing = match Superblock::read(&self.dirname) {
+            Ok(superblock) => Some(superblock.block_size()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let block_size = match (config.block_size, existing) {
+            (Some(block_size), Some(existing)) if block_size != existing => {
+                return Err(invalid_input(DbError::IncompatibleFormat(format!(
+                    "the database has a block size of {}, not {}",
+                    existing, block_size
+                ))));
+            }
+            (_, Some(existing)) => existing,
+            (Some(block_size), None) => block_size,
+            (None, None) => SimpleDB::BLOCK_SIZE,
+        };
+        if block_size < SimpleDB::MIN_BLOCK_SIZE {
+            return Err(invalid_input(DbError::InvalidBlockSize));
+        }
+        if config.read_only && existing.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "a database opened read-only must exist",
+            ));
+        }
+        let fm = FileManager::new(&self.dirname, block_size)?;
+        SimpleDB::start(fm, self.config)
+    }
+}
+
+fn invalid_input(e: DbError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
 }
 
 impl SimpleDB {
     pub const BLOCK_SIZE: usize = 400;
     pub const BUFFER_SIZE: u32 = 8;
     pub const LOG_FILE: &'static str = "simpledb.log";
+    // The smallest block size that a database can have,
+    // leaving room for a catalog record, or a log record
+    // modifying one, in a block.
+    pub const MIN_BLOCK_SIZE: usize = 128;
+
+    // Return a builder opening the database in the directory
+    // with the settings chosen through it.
+    pub fn builder(dirname: impl AsRef<Path>) -> SimpleDBBuilder {
+        SimpleDBBuilder::new(dirname)
+    }
 
     pub fn new(
         dirname: impl AsRef<Path>,
         block_size: usize,
         buffer_size: u32,
     ) -> std::io::Result<SimpleDB> {
-        let fm = Arc::new(FileManager::new(dirname, block_size)?);
+        let fm = FileManager::new(dirname, block_size)?;
+        Self::with_file_manager(fm, buffer_size)
+    }
+
+    // Create a database that lives in memory only,
+    // and is gone when it is dropped.
+    pub fn in_memory(block_size: usize, buffer_size: u32) -> std::io::Result<SimpleDB> {
+        Self::with_file_manager(FileManager::in_memory(block_size), buffer_size)
+    }
+
+    // Create a database on a file manager configured by the
+    // caller, such as one using tablespaces or another I/O mode.
+    // A database that was not shut down cleanly is recovered
+    // from its log before it is returned.
+    pub fn with_file_manager(fm: FileManager, buffer_size: u32) -> std::io::Result<SimpleDB> {
+        let config = DbConfig {
+            buffer_size,
+            ..DbConfig::default()
+        };
+        Self::start(fm, config)
+    }
+
+    // Open the database on the file manager and recover it,
+    // or, if it is read-only, make sure it needs no recovery.
+    fn start(fm: FileManager, config: DbConfig) -> std::io::Result<SimpleDB> {
+        let db = Self::open(fm, config)?;
+        if !db.read_only {
+            RecoveryManager::recover(&db.fm, &db.lm, &db.bm, &db.txn_ids)
+                .map_err(std::io::Error::other)?;
+        } else if RecoveryManager::needs_recovery(&db.fm, &db.lm, &db.txn_ids)
+            .map_err(std::io::Error::other)?
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "the database was not shut down cleanly, and cannot be opened read-only",
+            ));
+        }
+        Ok(db)
+    }
+
+    // Restore a database from a backup of its files to a point
+    // in its history, such as just before a mistaken update.
+    // The directory must hold the backup, which is a copy of
+    // the database files taken while archiving its log, and
+    // the archived log files and the log from the time of the
+    // backup on. The log is replayed up to the target, the
+    // transactions unfinished there are rolled back, and then
+    // the log and its archived files are deleted, so the
+    // restore should be run on a copy of them.
+    pub fn restore(
+        fm: FileManager,
+        buffer_size: u32,
+        target: RecoveryTarget,
+    ) -> std::io::Result<SimpleDB> {
+        let config = DbConfig {
+            buffer_size,
+            ..DbConfig::default()
+        };
+        let db = Self::open(fm, config)?;
+        RecoveryManager::restore(&db.fm, &db.lm, &db.bm, &db.txn_ids, target)
+            .map_err(std::io::Error::other)?;
+        Ok(db)
+    }
+
+    fn open(fm: FileManager, config: DbConfig) -> std::io::Result<SimpleDB> {
+        let fm = Arc::new(fm);
         let lm = Arc::new(Mutex::new(LogManager::new(
             Arc::clone(&fm),
-            Self::LOG_FILE.to_string(),
+            config.log_file,
         )?));
-        let bm = BufferManager::new(Arc::clone(&fm), Arc::clone(&lm), buffer_size as usize);
+        let policy = config
+            .replacement_policy
+            .unwrap_or_else(|| Box::new(NaivePolicy::new()));
+        let bm = Arc::new(
+            BufferManager::with_policy(
+                Arc::clone(&fm),
+                Arc::clone(&lm),
+                config.buffer_size as usize,
+                <BufferManager>::DEFAULT_MAX_TIME,
+                policy,
+            )
+            .with_work_limit(config.work_buffers),
+        );
+        let lock_table =
+            LockTable::with_policy(config.deadlock_policy).with_timeout(config.lock_timeout);
 
-        Ok(SimpleDB { fm, lm, bm })
+        Ok(SimpleDB {
+            fm,
+            lm,
+            bm,
+            lock_table: Arc::new(lock_table),
+            txn_ids: TxnIdAllocator::new(),
+            transactions: Arc::new(TransactionRegistry::new()),
+            tx_gate: RwLock::new(()),
+            planner: Mutex::new(None),
+            durability: config.durability,
+            read_only: config.read_only,
+            closed: false,
+        })
     }
 
     pub fn file_manager(&self) -> Arc<FileManager> {
@@ -36,7 +324,898 @@ impl SimpleDB {
         &self.lm
     }
 
-    pub fn buffer_manager(&self) -> &BufferManager {
+    pub fn buffer_manager(&self) -> &Arc<BufferManager> {
         &self.bm
     }
+
+    // The lock table shared by the transactions on the database.
+    pub fn lock_table(&self) -> &Arc<LockTable> {
+        &self.lock_table
+    }
+
+    // What the commit of a transaction on the database waits for.
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    // Return true if every transaction on the database is read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    // The allocator of the numbers of the transactions on the database.
+    pub fn txn_ids(&self) -> &TxnIdAllocator {
+        &self.txn_ids
+    }
+
+    // The transactions created by the database, for a
+    // TransactionReaper to watch.
+    pub fn transactions(&self) -> &Arc<TransactionRegistry> {
+        &self.transactions
+    }
+
+    // Describe the transactions that have not completed,
+    // oldest first.
+    pub fn active_transactions(&self) -> Vec<TransactionInfo> {
+        self.transactions.info()
+    }
+
+    // Abort the transactions that started longer than max_age
+    // ago, releasing their locks, and return their numbers.
+    pub fn abort_transactions_older_than(&self, max_age: Duration) -> DbResult<Vec<i32>> {
+        self.transactions.abort_older_than(max_age)
+    }
+
+    // Report the contention on the locks of each block that
+    // lock requests have waited for, the hottest blocks first.
+    pub fn lock_stats(&self) -> Vec<(BlockId, LockStats)> {
+        self.lock_table.lock_stats()
+    }
+
+    // Write every dirty buffer and the whole log to disk,
+    // whether or not their transactions have committed.
+    // Afterwards the database files hold the current
+    // state of every block, as needed before a shutdown,
+    // a checkpoint, or a copy of the database directory.
+    pub fn sync(&self) -> DbResult<()> {
+        self.lm.lock().unwrap().flush_all()?;
+        self.bm.flush_all_dirty()?;
+        Ok(())
+    }
+
+    // Take a quiescent checkpoint: write every dirty buffer,
+    // then start the log afresh with a checkpoint record,
+    // which holds the next transaction number.
+    // The records before the checkpoint are no longer needed,
+    // so the old log is deleted, or archived if the log
+    // manager is set to archive.
+    // No transaction may be active, since the undo records
+    // of its updates would be lost with the old log.
+    pub fn checkpoint(&self) -> DbResult<()> {
+        if self.read_only {
+            let logfile = self.lm.lock().unwrap().logfile().to_string();
+            return Err(DbError::ReadOnly(logfile));
+        }
+        let _gate = self.tx_gate.write().unwrap();
+        if !self.is_quiescent() {
+            return Err(DbError::TransactionsActive);
+        }
+        self.write_checkpoint()
+    }
+
+    // Write every dirty buffer, and replace the log with a
+    // checkpoint record. The caller holds the gate exclusively,
+    // so that no transaction starts meanwhile.
+    fn write_checkpoint(&self) -> DbResult<()> {
+        self.bm.flush_all_dirty()?;
+        let mut lm = self.lm.lock().unwrap();
+        lm.truncate()?;
+        let rec = LogRecord::Checkpoint {
+            next_txnum: self.txn_ids.peek(),
+        };
+        let lsn = lm.append(&rec.to_bytes())?;
+        lm.flush(lsn)?;
+        Ok(())
+    }
+
+    // Shut the database down cleanly: write every dirty buffer
+    // and the log, then take a checkpoint, so that opening the
+    // database again needs no recovery. Nothing is committed or
+    // rolled back; if a transaction is still active, there is
+    // no checkpoint, and recovery rolls it back as after a crash.
+    // The files are closed once the database is dropped, which
+    // closes it too, if it was not closed, ignoring any error.
+    pub fn close(mut self) -> DbResult<()> {
+        self.closed = true;
+        self.shut_down()
+    }
+
+    // Drop the database without shutting it down,
+    // leaving its files as a crash would.
+    #[cfg(test)]
+    pub(crate) fn crash(mut self) {
+        self.closed = true;
+    }
+
+    fn shut_down(&self) -> DbResult<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        self.sync()?;
+        let _gate = self.tx_gate.write().unwrap();
+        if self.is_quiescent() {
+            self.write_checkpoint()?;
+        }
+        Ok(())
+    }
+
+    // Return true if no transaction is active,
+    // nor holds any lock.
+    fn is_quiescent(&self) -> bool {
+        self.transactions.active().is_empty() && self.lock_table.is_empty()
+    }
+
+    // Execute an SQL statement in its own transaction, as
+    // execute_query does if it is a query, and execute_update
+    // if it is an update statement.
+    pub fn run(&self, sql: &str) -> DbResult<Outcome> {
+        if Parser::new(sql)?.is_query() {
+            Ok(Outcome::Rows(self.execute_query(sql)?))
+        } else {
+            Ok(Outcome::Count(self.execute_update(sql)?))
+        }
+    }
+
+    // Execute an SQL query in its own transaction.
+    // The output records are read into the returned result set
+    // and the transaction is committed.
+    pub fn execute_query(&self, qry: &str) -> DbResult<ResultSet> {
+        self.run_in_tx(|planner, tx| {
+            let p = planner.create_query_plan(qry, tx)?;
+            ResultSet::from_plan(p.as_ref())
+        })
+    }
+
+    // Execute an SQL update statement in its own transaction,
+    // and return the number of affected records.
+    pub fn execute_update(&self, cmd: &str) -> DbResult<usize> {
+        self.run_in_tx(|planner, tx| planner.execute_update(cmd, tx))
+    }
+
+    // Run f in a new transaction, committing it if f succeeds,
+    // and rolling it back otherwise, so that it releases its locks.
+    fn run_in_tx<T>(
+        &self,
+        f: impl FnOnce(&Planner, &Arc<Transaction>) -> DbResult<T>,
+    ) -> DbResult<T> {
+        let planner = self.planner()?;
+        let tx = self.new_tx()?;
+        match f(&planner, &tx) {
+            Ok(result) => {
+                tx.commit()?;
+                Ok(result)
+            }
+            Err(e) => {
+                tx.rollback()?;
+                Err(e)
+            }
+        }
+    }
+
+    // Compact the table in its own transaction, so that the
+    // space of its deleted records is given back, and return
+    // the number of blocks cut off its file.
+    pub fn vacuum(&self, tblname: &str) -> DbResult<usize> {
+        self.run_in_tx(|planner, tx| planner.vacuum(tblname, tx))
+    }
+
+    // Return the planner used by the embedded driver, for
+    // running statements in transactions of the caller's own.
+    // The metadata manager and planner are created on first use,
+    // together with the catalog tables if the database has none.
+    pub fn planner(&self) -> DbResult<Arc<Planner>> {
+        Ok(self.catalog()?.0)
+    }
+
+    // Return the metadata manager that the planner uses.
+    pub fn metadata_manager(&self) -> DbResult<Arc<MetadataManager>> {
+        Ok(self.catalog()?.1)
+    }
+
+    fn catalog(&self) -> DbResult<(Arc<Planner>, Arc<MetadataManager>)> {
+        let mut planner = self.planner.lock().unwrap();
+        if let Some((planner, mdm)) = planner.as_ref() {
+            return Ok((Arc::clone(planner), Arc::clone(mdm)));
+        }
+        let tx = self.new_tx()?;
+        let is_new = tx.size("tblcat.tbl")? == 0;
+        let mdm = Arc::new(MetadataManager::new(is_new, &tx)?);
+        tx.commit()?;
+        let p = Arc::new(Planner::new(
+            Box::new(HeuristicQueryPlanner::new(Arc::clone(&mdm))),
+            Box::new(BasicUpdatePlanner::new(Arc::clone(&mdm))),
+        ));
+        *planner = Some((Arc::clone(&p), Arc::clone(&mdm)));
+        Ok((p, mdm))
+    }
+
+    // Start a serializable transaction on the database, sharing
+    // its file, log and buffer managers and its lock table.
+    // The transaction is read-only if the database is.
+    pub fn new_tx(&self) -> DbResult<Arc<Transaction>> {
+        self.new_tx_with_isolation(IsolationLevel::default())
+    }
+
+    pub(crate) fn new_read_only_tx(&self) -> Arc<Transaction> {
+        let tx = Arc::new(Transaction::read_only(
+            self.file_manager(),
+            Arc::clone(&self.bm),
+            Arc::clone(&self.lock_table),
+            &self.txn_ids,
+        ));
+        self.transactions.register(&tx);
+        tx
+    }
+
+    // Create a transaction at the isolation level, or a
+    // read-only transaction if the database is read-only.
+    pub(crate) fn new_tx_with_isolation(
+        &self,
+        isolation: IsolationLevel,
+    ) -> DbResult<Arc<Transaction>> {
+        if self.read_only {
+            return Ok(self.new_read_only_tx());
+        }
+        let _gate = self.tx_gate.read().unwrap();
+        let tx = Transaction::with_isolation(
+            self.file_manager(),
+            Arc::clone(&self.lm),
+            Arc::clone(&self.bm),
+            Arc::clone(&self.lock_table),
+            &self.txn_ids,
+            isolation,
+        )?;
+        let tx = Arc::new(tx.with_durability(self.durability));
+        self.transactions.register(&tx);
+        Ok(tx)
+    }
+}
+
+impl Drop for SimpleDB {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.shut_down();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::LruPolicy,
+        file::{Page, Tablespaces},
+        log::dump::dump,
+        query::Constant,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_embedded_driver() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        {
+            let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+            db.execute_update("create table t (a int, b varchar(10))")?;
+            for i in 0..5 {
+                let sql = format!("insert into t (a, b) values ({}, 'b{}')", i, i);
+                assert_eq!(db.execute_update(&sql)?, 1);
+            }
+            assert_eq!(db.execute_update("delete from t where a = 0")?, 1);
+
+            let rs = db.execute_query("select b, a from t where a = 3")?;
+            assert_eq!(rs.columns(), ["b", "a"]);
+            assert_eq!(rs.len(), 1);
+            assert_eq!(rs.get(0, "b"), Some(&Constant::from("b3".to_string())));
+            assert_eq!(rs.get(0, "a"), Some(&Constant::Int(3)));
+            assert_eq!(rs.get(0, "c"), None);
+
+            let result = db.execute_query("select c from t");
+            assert!(matches!(result, Err(DbError::Catalog(_))));
+            let result = db.execute_update("insert into t (a, c) values (9, 9)");
+            assert!(matches!(result, Err(DbError::Catalog(_))));
+
+            // the failed statements released their locks
+            assert!(db.lock_table().is_empty());
+            db.execute_update("create table u (c int)")?;
+            assert_eq!(db.execute_update("update t set a = 2 where a = 1")?, 1);
+            assert_eq!(db.execute_query("select a from t where a = 2")?.len(), 2);
+        }
+
+        // the catalog and the table survive reopening the database
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        assert_eq!(db.execute_query("select a from t")?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let blk = tx.append("testfile")?;
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 0, 42, true)?;
+        assert_eq!(db.buffer_manager().stats().dirty, 1);
+
+        // the pinned, uncommitted modification is written too
+        db.sync()?;
+        assert_eq!(db.buffer_manager().stats().dirty, 0);
+        let mut page = Page::new(db.file_manager().block_size());
+        db.file_manager().read(&blk, &mut page)?;
+        assert_eq!(page.get_int(0), 42);
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_txnums_across_restarts() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let first = db.new_tx()?;
+        let second = db.new_tx()?;
+        assert!(second.txnum() > first.txnum());
+        first.commit()?;
+        second.commit()?;
+        let last = second.txnum();
+        drop((first, second));
+        db.crash();
+
+        // the numbers continue from those in the log
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        assert!(tx.txnum() > last);
+        tx.commit()?;
+        let last = tx.txnum();
+        drop(tx);
+        db.checkpoint()?;
+        drop(db);
+
+        // and from the one kept by the checkpoint
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        assert!(db.new_tx()?.txnum() > last);
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_memory() -> DbResult<()> {
+        let db = SimpleDB::in_memory(400, 8)?;
+        db.execute_update("create table t (a int, b varchar(10))")?;
+        for i in 0..50 {
+            let sql = format!("insert into t (a, b) values ({}, 'b{}')", i, i);
+            db.execute_update(&sql)?;
+        }
+        let rs = db.execute_query("select b from t where a = 42")?;
+        assert_eq!(rs.get(0, "b"), Some(&Constant::from("b42".to_string())));
+        assert!(db.file_manager().length("t.tbl")? > 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tablespaces() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let mut spaces = Tablespaces::new();
+        spaces
+            .add("logs", temp_dir.path().join("logs"))
+            .add("hot", temp_dir.path().join("hot"))
+            .assign(SimpleDB::LOG_FILE, "logs")
+            .assign("t.tbl", "hot");
+        let fm = FileManager::new(temp_dir.path().join("db"), 400)?.with_tablespaces(spaces)?;
+        let db = SimpleDB::with_file_manager(fm, 8)?;
+        db.execute_update("create table t (a int)")?;
+        db.execute_update("create table u (a int)")?;
+        db.execute_update("insert into t (a) values (1)")?;
+        db.execute_update("insert into u (a) values (2)")?;
+        db.sync()?;
+
+        assert!(temp_dir
+            .path()
+            .join("logs")
+            .join(SimpleDB::LOG_FILE)
+            .exists());
+        assert!(temp_dir.path().join("hot/t.tbl").exists());
+        assert!(!temp_dir.path().join("db/t.tbl").exists());
+        assert!(temp_dir.path().join("db/u.tbl").exists());
+        let rs = db.execute_query("select a from t")?;
+        assert_eq!(rs.get(0, "a"), Some(&Constant::Int(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int)")?;
+        for i in 0..30 {
+            db.execute_update(&format!("insert into t (a) values ({})", i))?;
+        }
+        assert!(db.file_manager().length(SimpleDB::LOG_FILE)? > 1);
+
+        db.log_manager().lock().unwrap().set_archive(true);
+        db.checkpoint()?;
+        let records = db
+            .log_manager()
+            .lock()
+            .unwrap()
+            .iter()?
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let rec = LogRecord::Checkpoint {
+            next_txnum: db.txn_ids().peek(),
+        };
+        assert_eq!(records, [rec.to_bytes()]);
+        assert!(temp_dir.path().join("simpledb.log.1").exists());
+        assert_eq!(db.execute_query("select a from t")?.len(), 30);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint_with_active_transaction() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table b (x int, y int)")?;
+        db.execute_update("insert into b (x, y) values (1, 2)")?;
+
+        let tx = db.new_tx()?;
+        db.planner()?.execute_update("update b set y = 99", &tx)?;
+        assert!(matches!(db.checkpoint(), Err(DbError::TransactionsActive)));
+
+        // the log still holds the undo records of the update
+        tx.rollback()?;
+        let rs = db.execute_query("select y from b")?;
+        assert_eq!(rs.get(0, "y"), Some(&Constant::Int(2)));
+        db.checkpoint()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoint_while_starting_transactions() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        // committed buffers stay dirty, for the checkpoint to write
+        let db = SimpleDB::builder(temp_dir.path())
+            .durability(Durability::NoForce)
+            .open()?;
+        let tx = db.new_tx()?;
+        let blk1 = tx.append("testfile")?;
+        let blk2 = tx.append("testfile")?;
+        tx.commit()?;
+
+        // a checkpoint either finds a transaction active, or
+        // takes place before it starts, and so never drops
+        // the undo records of its update, even while another
+        // buffer is being written
+        let done = AtomicBool::new(false);
+        thread::scope(|s| -> DbResult<()> {
+            s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    match db.checkpoint() {
+                        Ok(()) | Err(DbError::TransactionsActive) => {}
+                        Err(e) => panic!("checkpoint failed: {}", e),
+                    }
+                }
+            });
+            let result = (0..500).try_for_each(|i| {
+                let tx = db.new_tx()?;
+                tx.pin(&blk1)?;
+                tx.set_int(&blk1, 80, i, true)?;
+                tx.commit()?;
+                let tx = db.new_tx()?;
+                tx.pin(&blk2)?;
+                tx.set_int(&blk2, 80, i, true)?;
+                tx.rollback()?;
+                let tx = db.new_tx()?;
+                tx.pin(&blk2)?;
+                assert_eq!(tx.get_int(&blk2, 80)?, 0);
+                tx.commit()
+            });
+            done.store(true, Ordering::Relaxed);
+            result
+        })
+    }
+
+    #[test]
+    fn test_builder() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("db");
+        {
+            let db = SimpleDB::builder(&dir)
+                .block_size(200)
+                .buffers(4)
+                .replacement_policy(LruPolicy::new())
+                .work_buffers(3)
+                .lock_timeout(Duration::from_millis(50))
+                .deadlock_policy(DeadlockPolicy::WaitDie)
+                .log_file("db.log")
+                .open()?;
+            assert_eq!(db.file_manager().block_size(), 200);
+            assert_eq!(db.buffer_manager().available(), 4);
+            assert_eq!(db.buffer_manager().work_buffers(), 3);
+            assert_eq!(db.lock_table().timeout(), Duration::from_millis(50));
+            assert_eq!(db.lock_table().policy(), DeadlockPolicy::WaitDie);
+            db.execute_update("create table t (a int)")?;
+            db.execute_update("insert into t (a) values (7)")?;
+            assert!(dir.join("db.log").exists());
+            assert!(!dir.join(SimpleDB::LOG_FILE).exists());
+        }
+
+        // an existing database is opened with its block size
+        let db = SimpleDB::builder(&dir).log_file("db.log").open()?;
+        assert_eq!(db.file_manager().block_size(), 200);
+        let rs = db.execute_query("select a from t")?;
+        assert_eq!(rs.get(0, "a"), Some(&Constant::Int(7)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("db");
+        SimpleDB::builder(&dir).block_size(200).open().unwrap();
+
+        let invalid = |builder: SimpleDBBuilder| {
+            let e = builder.open().err().unwrap();
+            e.kind() == std::io::ErrorKind::InvalidInput
+        };
+        assert!(invalid(SimpleDB::builder(&dir).block_size(400)));
+        assert!(invalid(SimpleDB::builder(&dir).buffers(0)));
+        assert!(invalid(SimpleDB::builder(&dir).work_buffers(0)));
+        assert!(invalid(SimpleDB::builder(&dir).log_file("")));
+        assert!(invalid(SimpleDB::builder(&dir).log_file("temp.log")));
+        let new_dir = temp_dir.path().join("new");
+        assert!(invalid(SimpleDB::builder(&new_dir).block_size(16)));
+        // nothing was created by the refused settings
+        assert!(!new_dir.exists());
+        let result = SimpleDB::builder(&new_dir).read_only(true).open();
+        assert!(result.is_err_and(|e| e.kind() == std::io::ErrorKind::NotFound));
+        assert!(!new_dir.exists());
+    }
+
+    #[test]
+    fn test_work_buffers() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let unlimited = SimpleDB::builder(temp_dir.path().join("unlimited"))
+            .buffers(16)
+            .open()?;
+        let limited = SimpleDB::builder(temp_dir.path().join("limited"))
+            .buffers(16)
+            .work_buffers(4)
+            .open()?;
+        let mut products = Vec::new();
+        for db in [&unlimited, &limited] {
+            db.execute_update("create table a (x int, y varchar(10))")?;
+            db.execute_update("create table b (z int, w varchar(20))")?;
+            for i in 0..100 {
+                let sql = format!("insert into a (x, y) values ({}, 'y{}')", i % 10, i);
+                db.execute_update(&sql)?;
+            }
+            for i in 0..300 {
+                let sql = format!("insert into b (z, w) values ({}, 'w{}')", i, i);
+                db.execute_update(&sql)?;
+            }
+            let tx = db.new_tx()?;
+            assert_eq!(tx.available_buffs(), 16);
+
+            // the operators give the same results within the limit,
+            // and the hash join partitions its input to stay in it
+            let rs = db.execute_query("select x, y, z from a, b where x = z")?;
+            assert_eq!(rs.len(), 100);
+            let rs = db.execute_query("select x, count(y) from a group by x")?;
+            assert_eq!(rs.len(), 10);
+            assert_eq!(rs.get(9, "countofy"), Some(&Constant::Int(10)));
+            let rs = db.execute_query("select z from b order by z desc")?;
+            assert_eq!(rs.get(0, "z"), Some(&Constant::Int(299)));
+
+            // a product reads its RHS in smaller chunks
+            db.execute_update("analyze")?;
+            let planner = db.planner()?;
+            let plan = planner.create_query_plan("select x, z from a, b", &tx)?;
+            products.push((tx.work_buffs(), plan.blocks_accessed()));
+            tx.commit()?;
+        }
+        assert_eq!(products[0].0, 16);
+        assert_eq!(products[1].0, 4);
+        assert!(products[1].1 > products[0].1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        {
+            let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+            db.execute_update("create table t (a int)")?;
+            db.execute_update("insert into t (a) values (1)")?;
+        }
+
+        let db = SimpleDB::builder(temp_dir.path()).read_only(true).open()?;
+        assert!(db.is_read_only());
+        assert_eq!(db.execute_query("select a from t")?.len(), 1);
+        let result = db.execute_update("insert into t (a) values (2)");
+        assert!(matches!(result, Err(DbError::ReadOnly(_))));
+        assert!(matches!(db.checkpoint(), Err(DbError::ReadOnly(_))));
+        drop(db);
+
+        // a database left with an unfinished transaction
+        // must be recovered first
+        {
+            let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+            let tx = db.new_tx()?;
+            let blk = BlockId::new("t.tbl", 0);
+            tx.pin(&blk)?;
+            tx.set_int(&blk, 4, 5, true)?;
+            db.sync()?;
+            tx.crash();
+            db.crash();
+        }
+        let result = SimpleDB::builder(temp_dir.path()).read_only(true).open();
+        assert!(result.is_err_and(|e| e.kind() == std::io::ErrorKind::InvalidData));
+        drop(SimpleDB::new(temp_dir.path(), 400, 8)?);
+        let db = SimpleDB::builder(temp_dir.path()).read_only(true).open()?;
+        assert_eq!(db.execute_query("select a from t")?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_durability() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let blk = BlockId::new("testfile", 0);
+        let commit_at = |durability, n| -> DbResult<usize> {
+            let db = SimpleDB::builder(temp_dir.path())
+                .durability(durability)
+                .open()?;
+            assert_eq!(db.durability(), durability);
+            let tx = db.new_tx()?;
+            if n == 1 {
+                tx.append("testfile")?;
+            }
+            tx.pin(&blk)?;
+            tx.set_int(&blk, 80, n, true)?;
+            tx.commit()?;
+            let dirty = db.buffer_manager().stats().dirty;
+            db.crash();
+            Ok(dirty)
+        };
+        let read = || -> DbResult<i32> {
+            let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+            let tx = db.new_tx()?;
+            tx.pin(&blk)?;
+            let n = tx.get_int(&blk, 80)?;
+            tx.commit()?;
+            Ok(n)
+        };
+
+        assert_eq!(commit_at(Durability::Force, 1)?, 0);
+        assert_eq!(read()?, 1);
+        // the commit is redone from the log
+        assert_eq!(commit_at(Durability::NoForce, 2)?, 1);
+        assert_eq!(read()?, 2);
+        // the commit is lost
+        assert_eq!(commit_at(Durability::Lazy, 3)?, 1);
+        assert_eq!(read()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_close() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let log_records = |db: &SimpleDB| {
+            db.log_manager()
+                .lock()
+                .unwrap()
+                .iter()
+                .unwrap()
+                .collect::<std::io::Result<Vec<_>>>()
+                .unwrap()
+        };
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int)")?;
+        db.execute_update("insert into t (a) values (1)")?;
+        db.close()?;
+
+        // the log is left with a checkpoint only
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let rec = LogRecord::Checkpoint {
+            next_txnum: db.txn_ids().peek(),
+        };
+        assert_eq!(log_records(&db), [rec.to_bytes()]);
+
+        // an active transaction keeps its update from being
+        // checkpointed, and is rolled back when reopened
+        let tx = db.new_tx()?;
+        let blk = BlockId::new("t.tbl", 0);
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 4, 5, true)?;
+        drop(db);
+        tx.crash();
+        drop(tx);
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        assert!(log_records(&db).len() > 1);
+        let rs = db.execute_query("select a from t")?;
+        assert_eq!(rs.get(0, "a"), Some(&Constant::Int(1)));
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_open_twice() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let result = SimpleDB::new(temp_dir.path(), 400, 8);
+        assert!(result.is_err_and(|e| e.kind() == std::io::ErrorKind::ResourceBusy));
+        drop(db);
+        SimpleDB::new(temp_dir.path(), 400, 8)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int, b varchar(10), old int)")?;
+        for i in 0..60 {
+            let old = (5..55).contains(&i) as i32;
+            let sql = format!(
+                "insert into t (a, b, old) values ({}, 'b{}', {})",
+                i, i, old
+            );
+            db.execute_update(&sql)?;
+        }
+        let size = db.file_manager().length("t.tbl")?;
+        db.execute_update("delete from t where old = 1")?;
+        assert!(db.vacuum("t")? > 0);
+        let vacuumed = db.file_manager().length("t.tbl")?;
+        assert!(vacuumed < size);
+        assert_eq!(db.execute_update("vacuum t")?, 0);
+
+        // the freed space is reused by inserts before the file grows
+        db.execute_update("insert into t (a, b) values (100, 'b100')")?;
+        assert_eq!(db.file_manager().length("t.tbl")?, vacuumed);
+        db.close()?;
+
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let rs = db.execute_query("select a from t")?;
+        let mut vals: Vec<_> = (0..rs.len()).map(|i| rs.get(i, "a").cloned()).collect();
+        vals.sort_by_key(|val| match val {
+            Some(Constant::Int(a)) => *a,
+            _ => -1,
+        });
+        let expected: Vec<_> = [0, 1, 2, 3, 4, 55, 56, 57, 58, 59, 100]
+            .into_iter()
+            .map(|a| Some(Constant::Int(a)))
+            .collect();
+        assert_eq!(vals, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_table() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table staging (a int, b varchar(10))")?;
+        for i in 0..50 {
+            let sql = format!("insert into staging (a, b) values ({}, 'b{}')", i, i);
+            db.execute_update(&sql)?;
+        }
+        assert!(db.file_manager().length("staging.tbl")? > 1);
+        let logged = dump(db.file_manager(), SimpleDB::LOG_FILE)?.len();
+        db.execute_update("truncate table staging")?;
+        assert_eq!(db.file_manager().length("staging.tbl")?, 0);
+        // a single record is logged between the start and the commit
+        let records = dump(db.file_manager(), SimpleDB::LOG_FILE)?;
+        let ops: Vec<_> = records[logged..].iter().map(|rec| rec.op).collect();
+        assert_eq!(ops, ["start", "truncate", "commit"]);
+        assert_eq!(records[logged + 1].target.as_deref(), Some("staging.tbl:0"));
+
+        db.execute_update("insert into staging (a, b) values (100, 'b100')")?;
+        db.crash();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let rs = db.execute_query("select a from staging")?;
+        assert_eq!(rs.len(), 1);
+        assert_eq!(rs.get(0, "a"), Some(&Constant::Int(100)));
+
+        // a truncation followed by inserts in the same transaction
+        // keeps the inserted records after a crash
+        let planner = db.planner()?;
+        let tx = db.new_tx()?;
+        planner.execute_update("truncate table staging", &tx)?;
+        planner.execute_update("insert into staging (a, b) values (200, 'b200')", &tx)?;
+        tx.commit()?;
+        drop(tx);
+        drop(planner);
+        db.crash();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let rs = db.execute_query("select a from staging")?;
+        assert_eq!(rs.len(), 1);
+        assert_eq!(rs.get(0, "a"), Some(&Constant::Int(200)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_index_survives_restart() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table emp (id int, dept varchar(10))")?;
+        for i in 0..20 {
+            let sql = format!("insert into emp (id, dept) values ({}, 'd{}')", i, i % 4);
+            db.execute_update(&sql)?;
+        }
+        db.execute_update("create index emp_dept on emp (dept)")?;
+        db.crash();
+
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = MetadataManager::new(false, &tx)?;
+        let indexes = mdm.get_index_info("emp", &tx)?;
+        let mut idx = indexes["dept"].open();
+        idx.before_first(&Constant::Str("d1".to_string()))?;
+        let mut count = 0;
+        while idx.next()? {
+            count += 1;
+        }
+        idx.close();
+        tx.commit()?;
+        assert_eq!(count, 5);
+
+        db.execute_update("drop index emp_dept")?;
+        let tx = db.new_tx()?;
+        assert!(mdm.get_index_info("emp", &tx)?.is_empty());
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_view_survives_restart() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table emp (id int, dept varchar(10))")?;
+        for i in 0..6 {
+            let sql = format!("insert into emp (id, dept) values ({}, 'd{}')", i, i % 2);
+            db.execute_update(&sql)?;
+        }
+        db.execute_update("create view odd as select id from emp where dept = 'd1'")?;
+        db.crash();
+
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let rs = db.execute_query("select id from odd")?;
+        let mut ids: Vec<_> = (0..rs.len()).map(|i| rs.get(i, "id").cloned()).collect();
+        ids.sort();
+        assert_eq!(ids, [1, 3, 5].map(|id| Some(Constant::Int(id))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_increment_survives_restart() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table emp (id int auto_increment, name varchar(10))")?;
+        for name in ["ann", "bob"] {
+            db.execute_update(&format!("insert into emp (name) values ('{}')", name))?;
+        }
+        // the value allocated by an unfinished transaction
+        // is not allocated again after recovery
+        let tx = db.new_tx()?;
+        db.planner()?
+            .execute_update("insert into emp (name) values ('dan')", &tx)?;
+        db.sync()?;
+        db.crash();
+        drop(tx);
+
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("insert into emp (name) values ('cat')")?;
+        let rs = db.execute_query("select id from emp where name = 'cat'")?;
+        assert_eq!(rs.get(0, "id"), Some(&Constant::Int(4)));
+        let rs = db.execute_query("select id from emp where name = 'dan'")?;
+        assert_eq!(rs.len(), 0);
+        Ok(())
+    }
 }
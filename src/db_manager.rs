@@ -0,0 +1,132 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    db::{SimpleDB, SimpleDBBuilder},
+    error::DbResult,
+};
+
+// The databases opened by a process, each under a name.
+// The databases share nothing: each has its own files,
+// buffers, log and locks, and can be closed on its own,
+// so that a server can host several, and each test its own.
+// A database stays open for as long as a reference to it
+// is held, even once it is no longer managed.
+#[derive(Default)]
+pub struct DatabaseManager {
+    databases: Mutex<HashMap<String, Arc<SimpleDB>>>,
+}
+
+impl DatabaseManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Open the database in the directory with the default
+    // settings, and manage it under the name.
+    pub fn open(&self, name: &str, dirname: impl AsRef<Path>) -> io::Result<Arc<SimpleDB>> {
+        self.open_with(name, SimpleDB::builder(dirname))
+    }
+
+    // Open the database configured by the builder, and
+    // manage it under the name. The name must not be in use.
+    pub fn open_with(&self, name: &str, builder: SimpleDBBuilder) -> io::Result<Arc<SimpleDB>> {
+        let mut databases = self.databases.lock().unwrap();
+        if databases.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("a database named {} is already open", name),
+            ));
+        }
+        let db = Arc::new(builder.open()?);
+        databases.insert(name.to_string(), Arc::clone(&db));
+        Ok(db)
+    }
+
+    // Return the database managed under the name.
+    pub fn get(&self, name: &str) -> Option<Arc<SimpleDB>> {
+        self.databases.lock().unwrap().get(name).cloned()
+    }
+
+    // The names of the managed databases, in order.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self.databases.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    // Stop managing the database under the name, and close
+    // it, unless references to it are still held; it is then
+    // closed when the last of them is dropped.
+    // Returns false if no database has the name.
+    pub fn close(&self, name: &str) -> DbResult<bool> {
+        let Some(db) = self.databases.lock().unwrap().remove(name) else {
+            return Ok(false);
+        };
+        if let Ok(db) = Arc::try_unwrap(db) {
+            db.close()?;
+        }
+        Ok(true)
+    }
+
+    // Close every managed database, returning
+    // the first error but closing them all.
+    pub fn close_all(&self) -> DbResult<()> {
+        let mut result = Ok(());
+        for name in self.names() {
+            let closed = self.close(&name);
+            if result.is_ok() {
+                result = closed.map(|_| ());
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Constant;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_databases() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = DatabaseManager::new();
+        let sales = manager.open("sales", temp_dir.path().join("sales"))?;
+        let hr = manager.open_with(
+            "hr",
+            SimpleDB::builder(temp_dir.path().join("hr")).buffers(16),
+        )?;
+        assert_eq!(manager.names(), ["hr", "sales"]);
+
+        // the same table in each holds its own records
+        sales.execute_update("create table t (a int)")?;
+        hr.execute_update("create table t (a int)")?;
+        sales.execute_update("insert into t (a) values (1)")?;
+        assert_eq!(sales.execute_query("select a from t")?.len(), 1);
+        assert_eq!(hr.execute_query("select a from t")?.len(), 0);
+
+        let result = manager.open("sales", temp_dir.path().join("other"));
+        assert!(result.is_err_and(|e| e.kind() == io::ErrorKind::AlreadyExists));
+
+        drop(sales);
+        assert!(manager.close("sales")?);
+        assert!(!manager.close("sales")?);
+        assert!(manager.get("sales").is_none());
+        assert!(manager.get("hr").is_some());
+
+        // a closed database can be opened again
+        let sales = manager.open("sales", temp_dir.path().join("sales"))?;
+        let rs = sales.execute_query("select a from t")?;
+        assert_eq!(rs.get(0, "a"), Some(&Constant::Int(1)));
+        drop((sales, hr));
+        manager.close_all()?;
+        assert!(manager.names().is_empty());
+        Ok(())
+    }
+}
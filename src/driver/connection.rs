@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use super::ResultSet;
+use crate::{
+    db::SimpleDB,
+    error::DbResult,
+    tx::{concurrency::IsolationLevel, Transaction},
+};
+
+// A session with an embedded database.
+// Outside of an explicit transaction, each statement
+// runs in its own transaction, as with SimpleDB::execute_query.
+// After begin, the statements share one transaction
+// until commit or rollback is called.
+pub struct Connection<'a> {
+    db: &'a SimpleDB,
+    tx: Option<Arc<Transaction>>,
+}
+
+impl<'a> Connection<'a> {
+    pub fn new(db: &'a SimpleDB) -> Self {
+        Connection { db, tx: None }
+    }
+
+    // Return true if an explicit transaction is in progress.
+    pub fn in_transaction(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    // Start an explicit transaction.
+    // The call has no effect if one is already in progress.
+    pub fn begin(&mut self) -> DbResult<()> {
+        self.begin_with_isolation(IsolationLevel::default())
+    }
+
+    // Start an explicit transaction at the specified isolation level.
+    pub fn begin_with_isolation(&mut self, isolation: IsolationLevel) -> DbResult<()> {
+        if self.tx.is_none() {
+            self.tx = Some(self.db.new_tx_with_isolation(isolation)?);
+        }
+        Ok(())
+    }
+
+    // Start an explicit transaction that only reads,
+    // as for a report; see Transaction::read_only.
+    pub fn begin_read_only(&mut self) {
+        if self.tx.is_none() {
+            self.tx = Some(self.db.new_read_only_tx());
+        }
+    }
+
+    // Commit the explicit transaction, if any.
+    pub fn commit(&mut self) -> DbResult<()> {
+        match self.tx.take() {
+            Some(tx) => tx.commit(),
+            None => Ok(()),
+        }
+    }
+
+    // Roll back the explicit transaction, if any.
+    pub fn rollback(&mut self) -> DbResult<()> {
+        match self.tx.take() {
+            Some(tx) => tx.rollback(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn execute_query(&mut self, qry: &str) -> DbResult<ResultSet> {
+        match &self.tx {
+            Some(tx) => {
+                let p = self.db.planner()?.create_query_plan(qry, tx)?;
+                ResultSet::from_plan(p.as_ref())
+            }
+            None => self.db.execute_query(qry),
+        }
+    }
+
+    pub fn execute_update(&mut self, cmd: &str) -> DbResult<usize> {
+        match &self.tx {
+            Some(tx) => self.db.planner()?.execute_update(cmd, tx),
+            None => self.db.execute_update(cmd),
+        }
+    }
+
+    // Close the connection, committing the
+    // explicit transaction if there is one.
+    pub fn close(mut self) -> DbResult<()> {
+        self.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::DbError, query::Constant};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_explicit_transaction() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let mut conn = Connection::new(&db);
+        conn.execute_update("create table t (a int)")?;
+
+        conn.begin()?;
+        assert!(conn.in_transaction());
+        for i in 0..3 {
+            conn.execute_update(&format!("insert into t (a) values ({})", i))?;
+        }
+        assert_eq!(conn.execute_query("select a from t")?.len(), 3);
+        conn.commit()?;
+        assert!(!conn.in_transaction());
+
+        conn.begin()?;
+        conn.execute_update("delete from t where a = 0")?;
+        assert_eq!(conn.execute_query("select a from t")?.len(), 2);
+        conn.rollback()?;
+        assert!(!conn.in_transaction());
+        assert_eq!(conn.execute_query("select a from t")?.len(), 3);
+
+        assert_eq!(conn.execute_update("delete from t where a = 1")?, 1);
+        conn.close()?;
+        assert_eq!(db.execute_query("select a from t")?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only_transaction() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let mut conn = Connection::new(&db);
+        conn.execute_update("create table t (a int)")?;
+        for i in [2, 0, 1] {
+            conn.execute_update(&format!("insert into t (a) values ({})", i))?;
+        }
+
+        conn.begin_read_only();
+        let rs = conn.execute_query("select a from t order by a")?;
+        let values: Vec<_> = (0..rs.len()).map(|row| rs.get(row, "a").cloned()).collect();
+        assert_eq!(values, [0, 1, 2].map(|a| Some(Constant::Int(a))));
+        let result = conn.execute_update("delete from t where a = 0");
+        assert!(matches!(result, Err(DbError::ReadOnly(_))));
+        conn.commit()?;
+        assert_eq!(conn.execute_query("select a from t")?.len(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+mod connection;
+mod outcome;
+mod result_set;
+
+pub use connection::Connection;
+pub use outcome::Outcome;
+pub use result_set::ResultSet;
@@ -0,0 +1,30 @@
+use super::ResultSet;
+
+// The result of a statement run by SimpleDB::run,
+// which may be a query or an update statement.
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    // The output records of a query.
+    Rows(ResultSet),
+    // The number of records affected by an update statement.
+    Count(usize),
+}
+
+impl Outcome {
+    // Return the output records, if the statement was a query.
+    pub fn rows(&self) -> Option<&ResultSet> {
+        match self {
+            Outcome::Rows(rs) => Some(rs),
+            Outcome::Count(_) => None,
+        }
+    }
+
+    // Return the number of affected records, if the
+    // statement was an update, or else of output records.
+    pub fn count(&self) -> usize {
+        match self {
+            Outcome::Rows(rs) => rs.len(),
+            Outcome::Count(count) => *count,
+        }
+    }
+}
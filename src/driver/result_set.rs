@@ -0,0 +1,77 @@
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error::DbResult,
+    plan::Plan,
+    query::{Constant, Scan},
+    record::{self, Schema},
+};
+
+// The result of a query run through the embedded driver.
+// The output records are read into memory before the
+// query's transaction commits, so the result set
+// can outlive the transaction.
+#[derive(Debug, Clone)]
+pub struct ResultSet {
+    schema: Schema,
+    rows: Vec<Vec<Constant>>,
+}
+
+impl ResultSet {
+    // Read every output record of the specified plan.
+    // The values of each row are in the order of the
+    // plan's schema.
+    pub(crate) fn from_plan(p: &dyn Plan) -> DbResult<Self> {
+        let schema = p.schema().clone();
+        let mut s = p.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            let row = schema
+                .fields()
+                .iter()
+                .map(|fldname| s.get_val(fldname))
+                .collect::<DbResult<Vec<_>>>()?;
+            rows.push(row);
+        }
+        s.close();
+        Ok(ResultSet { schema, rows })
+    }
+
+    // Return the schema of the query's output table.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    // Return the names of the output fields, in row order.
+    pub fn columns(&self) -> &[String] {
+        self.schema.fields()
+    }
+
+    pub fn rows(&self) -> &[Vec<Constant>] {
+        &self.rows
+    }
+
+    // Read every row into a struct whose fields
+    // are named after the output fields.
+    pub fn rows_as<T: DeserializeOwned>(&self) -> DbResult<Vec<T>> {
+        self.rows
+            .iter()
+            .map(|row| record::from_values(self.columns(), row))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // Return the value of the specified field in the
+    // specified row, or None if either does not exist.
+    pub fn get(&self, row: usize, fldname: &str) -> Option<&Constant> {
+        let col = self.columns().iter().position(|f| f == fldname)?;
+        self.rows.get(row).map(|r| &r[col])
+    }
+}
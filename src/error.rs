@@ -1,10 +1,121 @@
 use std::error::Error;
 use std::fmt;
 
+use crate::buffer::BufferError;
+use crate::file::BlockId;
+use crate::tx::concurrency::lock_table::LockAbortError;
+
 #[derive(Debug)]
 pub enum DbError {
     IoError(std::io::Error),
     InvalidBlockSize,
     InvalidBufferSize,
+    BufferAbort(String),
+    LockAbort,
+    // A lock that was not granted within the lock timeout.
+    LockTimeout(BlockId),
+    // A block that a snapshot transaction cannot modify, as
+    // another transaction modified it since the snapshot.
+    SerializationFailure(BlockId),
+    // A file that a read-only transaction tried to modify.
+    ReadOnly(String),
+    // A transaction that another thread aborted,
+    // such as for running too long.
+    TransactionAborted(i32),
+    Catalog(String),
+    BadSyntax(String),
+    Remote(String),
+    Serde(String),
+    // A block whose checksum does not match its contents.
+    CorruptBlock(BlockId),
+    // A database whose on-disk format cannot be read,
+    // such as one having another block size.
+    IncompatibleFormat(String),
+    // An operation, such as a quiescent checkpoint, that
+    // needs every transaction to be finished.
+    TransactionsActive,
+    // A string longer than its varchar field, in bytes.
+    ValueTooLong {
+        field: String,
+        max: usize,
+        len: usize,
+    },
+}
+
+pub type DbResult<T> = Result<T, DbError>;
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::IoError(e) => write!(f, "I/O error: {}", e),
+            DbError::InvalidBlockSize => write!(f, "invalid block size"),
+            DbError::InvalidBufferSize => write!(f, "invalid buffer size"),
+            DbError::BufferAbort(msg) => write!(f, "buffer abort: {}", msg),
+            DbError::LockAbort => write!(f, "lock abort"),
+            DbError::LockTimeout(blk) => write!(f, "lock wait timed out on block {}", blk),
+            DbError::SerializationFailure(blk) => write!(
+                f,
+                "could not serialize access to block {} due to a concurrent update",
+                blk
+            ),
+            DbError::ReadOnly(filename) => {
+                write!(f, "read-only transaction cannot modify file {}", filename)
+            }
+            DbError::TransactionAborted(txnum) => write!(f, "transaction {} was aborted", txnum),
+            DbError::Catalog(msg) => write!(f, "catalog error: {}", msg),
+            DbError::BadSyntax(msg) => write!(f, "bad syntax: {}", msg),
+            DbError::Remote(msg) => write!(f, "server error: {}", msg),
+            DbError::Serde(msg) => write!(f, "row conversion error: {}", msg),
+            DbError::CorruptBlock(blk) => write!(f, "corrupt block {}: checksum mismatch", blk),
+            DbError::IncompatibleFormat(msg) => write!(f, "incompatible database: {}", msg),
+            DbError::TransactionsActive => write!(f, "transactions are still active"),
+            DbError::ValueTooLong { field, max, len } => write!(
+                f,
+                "value too long for field {}: {} bytes, at most {} allowed",
+                field, len, max
+            ),
+        }
+    }
+}
+
+impl Error for DbError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DbError::IoError(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
+// A database error that was passed through an io::Read or io::Write
+// implementation (such as a blob stream) is unwrapped again.
+impl From<std::io::Error> for DbError {
+    fn from(e: std::io::Error) -> Self {
+        if e.get_ref().is_some_and(|inner| inner.is::<DbError>()) {
+            if let Some(Ok(inner)) = e.into_inner().map(|inner| inner.downcast::<DbError>()) {
+                return *inner;
+            }
+            unreachable!("the inner error is a DbError");
+        }
+        DbError::IoError(e)
+    }
+}
+
+impl From<BufferError> for DbError {
+    fn from(e: BufferError) -> Self {
+        match e {
+            BufferError::Abort(msg) => DbError::BufferAbort(msg),
+            BufferError::Io(e) => DbError::from(e),
+        }
+    }
+}
+
+impl From<LockAbortError> for DbError {
+    fn from(e: LockAbortError) -> Self {
+        match e {
+            LockAbortError::Timeout(blk) => DbError::LockTimeout(blk),
+            LockAbortError::Died(_) | LockAbortError::UpgradeConflict(_) => DbError::LockAbort,
+            LockAbortError::Cancelled(txnum) => DbError::TransactionAborted(txnum),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+
+use crate::file::BlockId;
+
+// The file beside the superblock holding a value sealed
+// with the key of an encrypted database, against which
+// the key given on opening is checked.
+pub(crate) const KEY_CHECK_FILE: &str = "keycheck";
+
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+// Encrypts and authenticates blocks with AES-256-GCM.
+// Every write of a block gets a random nonce, which is
+// stored with it, so that no nonce is used twice; the
+// name of the file and the block number are authenticated
+// too, so that blocks cannot be moved around within their
+// file, nor copied from another file, unnoticed.
+pub(crate) struct BlockCipher {
+    cipher: Aes256Gcm,
+}
+
+impl BlockCipher {
+    // The number of bytes that sealing adds to a block.
+    pub(crate) const OVERHEAD: usize = NONCE_SIZE + TAG_SIZE;
+
+    pub(crate) fn new(key: &[u8; 32]) -> Self {
+        BlockCipher {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    // Seal the bytes of the block into out, which is
+    // OVERHEAD bytes longer: the nonce, then the
+    // ciphertext followed by its tag.
+    pub(crate) fn seal(&self, block: &BlockId, plain: &[u8], out: &mut [u8]) {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let aad = Self::associated_data(block);
+        let sealed = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plain,
+                    aad: &aad,
+                },
+            )
+            .expect("a block is not too long to encrypt");
+        out[..NONCE_SIZE].copy_from_slice(&nonce);
+        out[NONCE_SIZE..].copy_from_slice(&sealed);
+    }
+
+    // The bytes sealed in the block,
+    // or None if they were not sealed for it with this key.
+    pub(crate) fn open(&self, block: &BlockId, sealed: &[u8]) -> Option<Vec<u8>> {
+        let (nonce, ciphertext) = sealed.split_at(NONCE_SIZE);
+        let aad = Self::associated_data(block);
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .ok()
+    }
+
+    // A value sealed with the key, to be checked by verify.
+    pub(crate) fn key_check(&self) -> Vec<u8> {
+        let mut out = vec![0; Self::OVERHEAD];
+        self.seal(&Self::key_check_block(), &[], &mut out);
+        out
+    }
+
+    pub(crate) fn verify(&self, key_check: &[u8]) -> bool {
+        key_check.len() == Self::OVERHEAD
+            && self.open(&Self::key_check_block(), key_check).is_some()
+    }
+
+    // The name of the file, preceded by its length so that
+    // no two blocks have the same data, and the block number.
+    fn associated_data(block: &BlockId) -> Vec<u8> {
+        let filename = block.filename().as_bytes();
+        let mut aad = Vec::with_capacity(4 + filename.len() + 8);
+        aad.extend_from_slice(&(filename.len() as u32).to_be_bytes());
+        aad.extend_from_slice(filename);
+        aad.extend_from_slice(&block.number().to_be_bytes());
+        aad
+    }
+
+    fn key_check_block() -> BlockId {
+        BlockId::new(KEY_CHECK_FILE, u64::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open() {
+        let cipher = BlockCipher::new(&[7; 32]);
+        let block = BlockId::new("t.tbl", 3);
+        let mut sealed = vec![0; 5 + BlockCipher::OVERHEAD];
+        cipher.seal(&block, b"hello", &mut sealed);
+        assert_eq!(cipher.open(&block, &sealed).unwrap(), b"hello");
+
+        // sealing again uses another nonce
+        let mut again = sealed.clone();
+        cipher.seal(&block, b"hello", &mut again);
+        assert_ne!(again, sealed);
+
+        assert!(cipher.open(&BlockId::new("t.tbl", 4), &sealed).is_none());
+        assert!(cipher.open(&BlockId::new("u.tbl", 3), &sealed).is_none());
+        sealed[NONCE_SIZE] ^= 1;
+        assert!(cipher.open(&block, &sealed).is_none());
+
+        let check = cipher.key_check();
+        assert!(cipher.verify(&check));
+        assert!(!BlockCipher::new(&[8; 32]).verify(&check));
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+// The lock file of a database directory. A file manager
+// holds an exclusive advisory lock on it for as long as it
+// is open, so that two of them, in the same process or not,
+// never use the files of a database at the same time.
+// The lock is released by the operating system when the
+// file is closed, so a process that dies leaves no stale
+// lock behind; the file holds the id of the last process
+// to take the lock, for whoever finds the database in use.
+// Locks are taken on Unix only.
+pub(crate) const FILE: &str = "lock";
+
+// Lock the database directory, failing at once
+// if the lock is held already.
+pub(crate) fn acquire(dir: &Path) -> io::Result<File> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(dir.join(FILE))?;
+    if !try_lock(&file)? {
+        return Err(io::Error::new(
+            io::ErrorKind::ResourceBusy,
+            format!("the database in {} is already open", dir.display()),
+        ));
+    }
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?;
+    Ok(file)
+}
+
+#[cfg(unix)]
+fn try_lock(file: &File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: the descriptor is open for the duration of the call.
+    let result = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if result == 0 {
+        return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    if e.kind() == io::ErrorKind::WouldBlock {
+        Ok(false)
+    } else {
+        Err(e)
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_acquire() {
+        let temp_dir = TempDir::new().unwrap();
+        let lock = acquire(temp_dir.path()).unwrap();
+        let e = acquire(temp_dir.path()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::ResourceBusy);
+        let pid = std::fs::read_to_string(temp_dir.path().join(FILE)).unwrap();
+        assert_eq!(pid, std::process::id().to_string());
+
+        // closing the file releases the lock
+        drop(lock);
+        acquire(temp_dir.path()).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+use crate::file::BlockId;
+
+// The double-write area of a file manager is a file holding
+// copies of the blocks being written. Each copy is synced
+// before its block is written in place, so a block torn by
+// a crash during the write can be restored from its copy.
+// Each entry holds the length of its body, the body (the
+// file name, the block number and the stored block) and a
+// CRC32 of the body, so that an entry torn itself is ignored.
+pub(crate) const FILE: &str = "doublewrite";
+
+// The entries for the stored blocks.
+pub(crate) fn encode(blocks: &[(&BlockId, &[u8])]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (block, stored) in blocks {
+        let mut body = Vec::new();
+        body.extend_from_slice(&(block.filename().len() as u16).to_be_bytes());
+        body.extend_from_slice(block.filename().as_bytes());
+        body.extend_from_slice(&block.number().to_be_bytes());
+        body.extend_from_slice(stored);
+        bytes.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&body);
+        bytes.extend_from_slice(&crc32fast::hash(&body).to_be_bytes());
+    }
+    bytes
+}
+
+// The stored blocks of the entries, up to the
+// first one that is incomplete or corrupt.
+pub(crate) fn decode(mut bytes: &[u8]) -> Vec<(BlockId, Vec<u8>)> {
+    let mut blocks = Vec::new();
+    while bytes.len() >= 4 {
+        let len = u32::from_be_bytes(bytes[..4].try_into().unwrap()) as usize;
+        let Some(entry) = bytes.get(4..4 + len + 4) else {
+            break;
+        };
+        let (body, checksum) = entry.split_at(len);
+        if len < 10 || crc32fast::hash(body).to_be_bytes() != checksum {
+            break;
+        }
+        let name_len = u16::from_be_bytes(body[..2].try_into().unwrap()) as usize;
+        let Some(rest) = body.get(2 + name_len..).filter(|rest| rest.len() >= 8) else {
+            break;
+        };
+        let Ok(filename) = std::str::from_utf8(&body[2..2 + name_len]) else {
+            break;
+        };
+        let number = u64::from_be_bytes(rest[..8].try_into().unwrap());
+        blocks.push((BlockId::new(filename, number), rest[8..].to_vec()));
+        bytes = &bytes[4 + len + 4..];
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let (b1, b2) = (BlockId::new("a.tbl", 3), BlockId::new("idx", 0));
+        let bytes = encode(&[(&b1, &[1, 2, 3]), (&b2, &[4; 10])]);
+        assert_eq!(
+            decode(&bytes),
+            [(b1.clone(), vec![1, 2, 3]), (b2, vec![4; 10])]
+        );
+
+        // a torn second entry is ignored
+        let mut torn = bytes.clone();
+        let len = torn.len();
+        torn[len - 6] ^= 0xff;
+        assert_eq!(decode(&torn), [(b1, vec![1, 2, 3])]);
+        assert!(decode(&bytes[..bytes.len() - 1]).len() == 1);
+    }
+}
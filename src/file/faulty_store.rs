@@ -0,0 +1,294 @@
+use std::{collections::HashMap, io, sync::Mutex};
+
+use crate::{
+    error::DbError,
+    file::{BlockId, BlockStore, Page},
+    log::Lsn,
+};
+
+// A fault that a FaultyStore injects at a given write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Fault {
+    // The write fails and the store crashes,
+    // leaving the block as it was on disk.
+    Fail,
+    // The store crashes in the middle of the write, so that
+    // only part of the block reaches the disk; the block
+    // fails its checksum when read, as with a FileManager.
+    Tear,
+    // The write and all later ones succeed, but their syncs
+    // are dropped, as by a disk that lies about them: they
+    // are lost in the next crash.
+    DropSyncs,
+}
+
+// A block store for crash tests, which keeps its blocks in
+// memory and fails as a disk can. The store has two views of
+// each file: the blocks as read and written, and the blocks
+// as on disk, which are all that survive a crash. Each write
+// normally reaches the disk before it returns, like a write
+// of a FileManager, which syncs it; a fault injected at the
+// Nth write can make it fail, tear it, or drop the syncs
+// from there on. Once crashed, the store fails every request
+// until it is restarted, with what was on disk.
+// Writes are counted from the creation of the store, so that
+// a test can run a workload once to count its writes, and
+// then again crashing at each of them in turn.
+pub(crate) struct FaultyStore {
+    block_size: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredBlock {
+    bytes: Vec<u8>,
+    lsn: Lsn,
+    torn: bool,
+}
+
+#[derive(Default)]
+struct State {
+    current: HashMap<String, Vec<StoredBlock>>,
+    durable: HashMap<String, Vec<StoredBlock>>,
+    writes: usize,
+    fault: Option<(usize, Fault)>,
+    drop_syncs: bool,
+    crashed: bool,
+}
+
+impl FaultyStore {
+    pub(crate) fn new(block_size: usize) -> Self {
+        FaultyStore {
+            block_size,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    // The number of writes made so far.
+    pub(crate) fn writes(&self) -> usize {
+        self.state.lock().unwrap().writes
+    }
+
+    // Inject the fault at the write having the number,
+    // counting from 1 for the first write of the store.
+    pub(crate) fn inject(&self, write: usize, fault: Fault) {
+        self.state.lock().unwrap().fault = Some((write, fault));
+    }
+
+    // Lose everything that did not reach the disk,
+    // and fail every request until restarted.
+    pub(crate) fn crash(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.current = state.durable.clone();
+        state.crashed = true;
+    }
+
+    // Bring a crashed store back with what was on disk,
+    // syncing its writes again.
+    pub(crate) fn restart(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.crashed = false;
+        state.drop_syncs = false;
+    }
+
+    fn zeroed(&self) -> StoredBlock {
+        StoredBlock {
+            bytes: vec![0; self.block_size],
+            lsn: Lsn(0),
+            torn: false,
+        }
+    }
+}
+
+impl State {
+    fn check_running(&self) -> io::Result<()> {
+        if self.crashed {
+            return Err(io::Error::other("the store has crashed"));
+        }
+        Ok(())
+    }
+}
+
+// Store the block at its place in the file, extending
+// the file with zeroed blocks as needed.
+fn put(files: &mut HashMap<String, Vec<StoredBlock>>, block: &BlockId, stored: StoredBlock) {
+    let blocks = files.entry(block.filename().to_string()).or_default();
+    let n = block.number() as usize;
+    if blocks.len() <= n {
+        let zeroed = StoredBlock {
+            bytes: vec![0; stored.bytes.len()],
+            lsn: Lsn(0),
+            torn: false,
+        };
+        blocks.resize(n + 1, zeroed);
+    }
+    blocks[n] = stored;
+}
+
+fn truncate(files: &mut HashMap<String, Vec<StoredBlock>>, filename: &str, len: u64) {
+    if let Some(blocks) = files.get_mut(filename) {
+        blocks.truncate(len as usize);
+    }
+}
+
+fn rename(files: &mut HashMap<String, Vec<StoredBlock>>, from: &str, to: &str) {
+    if let Some(blocks) = files.remove(from) {
+        files.insert(to.to_string(), blocks);
+    }
+}
+
+impl BlockStore for FaultyStore {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn read(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        let state = self.state.lock().unwrap();
+        state.check_running()?;
+        let stored = state
+            .current
+            .get(block.filename())
+            .and_then(|blocks| blocks.get(block.number() as usize))
+            .cloned()
+            .unwrap_or_else(|| self.zeroed());
+        if stored.torn {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                DbError::CorruptBlock(block.clone()),
+            ));
+        }
+        page.contents().copy_from_slice(&stored.bytes);
+        page.set_lsn(stored.lsn);
+        Ok(())
+    }
+
+    fn write(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_running()?;
+        state.writes += 1;
+        let mut stored = StoredBlock {
+            bytes: page.contents().to_vec(),
+            lsn: page.lsn(),
+            torn: false,
+        };
+        match state.fault {
+            Some((write, Fault::Fail)) if write == state.writes => {
+                drop(state);
+                self.crash();
+                return Err(io::Error::other("injected write failure"));
+            }
+            Some((write, Fault::Tear)) if write == state.writes => {
+                stored.torn = true;
+                put(&mut state.durable, block, stored);
+                drop(state);
+                self.crash();
+                return Err(io::Error::other("injected torn write"));
+            }
+            Some((write, Fault::DropSyncs)) if write == state.writes => {
+                state.drop_syncs = true;
+            }
+            _ => {}
+        }
+        if !state.drop_syncs {
+            put(&mut state.durable, block, stored.clone());
+        }
+        put(&mut state.current, block, stored);
+        Ok(())
+    }
+
+    fn append(&self, filename: &str) -> io::Result<BlockId> {
+        let mut state = self.state.lock().unwrap();
+        state.check_running()?;
+        let blocks = state.current.entry(filename.to_string()).or_default();
+        let block = BlockId::new(filename, blocks.len() as u64);
+        blocks.push(self.zeroed());
+        if !state.drop_syncs {
+            put(&mut state.durable, &block, self.zeroed());
+        }
+        Ok(block)
+    }
+
+    fn length(&self, filename: &str) -> io::Result<u64> {
+        let state = self.state.lock().unwrap();
+        state.check_running()?;
+        Ok(state.current.get(filename).map_or(0, Vec::len) as u64)
+    }
+
+    fn delete_file(&self, filename: &str) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_running()?;
+        state.current.remove(filename);
+        if !state.drop_syncs {
+            state.durable.remove(filename);
+        }
+        Ok(())
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_running()?;
+        rename(&mut state.current, from, to);
+        if !state.drop_syncs {
+            rename(&mut state.durable, from, to);
+        }
+        Ok(())
+    }
+
+    fn truncate(&self, filename: &str, len: u64) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_running()?;
+        truncate(&mut state.current, filename, len);
+        if !state.drop_syncs {
+            truncate(&mut state.durable, filename, len);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_faults() -> io::Result<()> {
+        let store = FaultyStore::new(400);
+        let blk0 = store.append("testfile")?;
+        let blk1 = store.append("testfile")?;
+        let mut page = Page::new(400);
+        page.set_int(0, 1);
+        page.set_lsn(Lsn(5));
+        store.write(&blk0, &mut page)?;
+
+        // a write whose sync is dropped is read until a crash
+        store.inject(2, Fault::DropSyncs);
+        page.set_int(0, 2);
+        store.write(&blk0, &mut page)?;
+        store.append("testfile")?;
+        store.read(&blk0, &mut page)?;
+        assert_eq!(page.get_int(0), 2);
+        store.crash();
+        assert!(store.read(&blk0, &mut page).is_err());
+        store.restart();
+        store.read(&blk0, &mut page)?;
+        assert_eq!((page.get_int(0), page.lsn()), (1, Lsn(5)));
+        assert_eq!(store.length("testfile")?, 2);
+
+        // a failed write leaves the block as it was
+        store.inject(3, Fault::Fail);
+        page.set_int(0, 3);
+        assert!(store.write(&blk0, &mut page).is_err());
+        assert!(store.write(&blk0, &mut page).is_err());
+        store.restart();
+        store.read(&blk0, &mut page)?;
+        assert_eq!(page.get_int(0), 1);
+
+        // a torn block is corrupt
+        store.inject(4, Fault::Tear);
+        assert!(store.write(&blk1, &mut page).is_err());
+        store.restart();
+        let err = DbError::from(store.read(&blk1, &mut page).unwrap_err());
+        assert!(matches!(err, DbError::CorruptBlock(b) if b == blk1));
+        assert_eq!(store.writes(), 4);
+        Ok(())
+    }
+}
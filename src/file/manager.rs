@@ -1,22 +1,175 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     fs::{self, File, OpenOptions},
     io::{self, Read, Seek, SeekFrom, Write},
+    ops::{Deref, DerefMut},
     path::{Path, PathBuf},
-    sync::Mutex,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
 };
 
-use crate::file::{BlockId, Page};
+use memmap2::MmapMut;
+
+use super::{
+    cipher::{self, BlockCipher},
+    dir_lock, double_write,
+    memory::MemoryFiles,
+    open_files::OpenFiles,
+    Superblock, Tablespaces,
+};
+use crate::{
+    error::DbError,
+    file::{BlockId, BlockStore, Page},
+    log::Lsn,
+};
+
+// Each block is stored on disk followed by a CRC32
+// of its contents, which is checked whenever the
+// block is read back.
+// A block that was never written has all zero bytes,
+// including its checksum, and is accepted as it is.
+const CHECKSUM_SIZE: usize = 4;
+
+// With compression, the contents of a stored block start
+// with the length of the compressed page, which is padded
+// with zeros to the block size; a length of zero means
+// that the page did not compress, and is stored as it is.
+const LENGTH_SIZE: usize = 4;
+
+// With direct I/O, transfers bypass the OS cache and must
+// start at aligned offsets in memory and on disk;
+// each stored block is padded to a multiple of this size.
+const DIRECT_IO_ALIGN: usize = 4096;
+
+// How the file manager transfers blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IoMode {
+    // Reads and writes through the OS cache.
+    Buffered,
+    // Reads and writes that bypass the OS cache.
+    Direct,
+    // Copies to and from memory mappings of the files.
+    Mapped,
+    // No files at all; the blocks are kept on the heap.
+    Memory,
+}
 
 pub struct FileManager {
     db_directory: PathBuf,
+    // The lock file of the directory, locked
+    // for as long as the file manager is open.
+    _dir_lock: Option<File>,
+    // The directories of the files stored elsewhere.
+    tablespaces: Tablespaces,
+    superblock: Superblock,
     block_size: usize,
     is_new: bool,
-    open_files: Mutex<HashMap<String, File>>,
+    mode: IoMode,
+    // The locks on lengths, mappings and open_files
+    // are taken in this order, so that they never deadlock.
+    // The files kept open, up to a maximum number.
+    open_files: Mutex<OpenFiles>,
+    // The number of blocks of each file, once counted.
+    // A file can be longer on disk, since the blocks
+    // past its end may be preallocated.
+    lengths: Mutex<HashMap<String, u64>>,
+    // The number of blocks a file grows by when
+    // an append reaches the end of its space.
+    extent_size: AtomicU64,
+    // The free blocks of each file, once loaded
+    // from the file's bitmap.
+    free: Mutex<HashMap<String, BTreeSet<u64>>>,
+    // The file of the double-write area, if it is enabled.
+    double_write: Mutex<Option<File>>,
+    // The cipher of an encrypted database.
+    cipher: Option<BlockCipher>,
+    // The mappings of the files, in mapped mode.
+    // A mapping covers the file as it was when mapped,
+    // and is replaced when a longer one is needed.
+    mappings: Mutex<HashMap<String, MmapMut>>,
+    // The files, in memory mode.
+    memory: Option<MemoryFiles>,
 }
 
 impl FileManager {
+    // The number of files kept open by default.
+    pub const DEFAULT_MAX_OPEN_FILES: usize = 128;
+
     pub fn new(db_directory: impl AsRef<Path>, block_size: usize) -> io::Result<Self> {
+        Self::open(db_directory, block_size, IoMode::Buffered)
+    }
+
+    // Create a file manager that reads and writes blocks
+    // through memory mappings of the files, with no read
+    // or write system calls; the file format is unchanged.
+    // The files must not be shortened by other programs
+    // while the database is open.
+    pub fn with_mmap(db_directory: impl AsRef<Path>, block_size: usize) -> io::Result<Self> {
+        Self::open(db_directory, block_size, IoMode::Mapped)
+    }
+
+    // Create a file manager whose reads and writes bypass
+    // the operating system's cache, so that the buffer pool
+    // is the only cache of the database's blocks.
+    // Direct I/O is available on Linux (O_DIRECT) and
+    // macOS (F_NOCACHE), and the file system must support it.
+    // Blocks are padded on disk for alignment, so files
+    // written in one mode cannot be read in the other.
+    pub fn with_direct_io(db_directory: impl AsRef<Path>, block_size: usize) -> io::Result<Self> {
+        if cfg!(any(target_os = "linux", target_os = "macos")) {
+            Self::open(db_directory, block_size, IoMode::Direct)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "direct I/O is not supported on this platform",
+            ))
+        }
+    }
+
+    // Store the files as the tablespaces say, instead of
+    // all in the database directory; the directories of
+    // the tablespaces are created if needed.
+    // The same tablespaces must be given every time the
+    // database is opened, since files are not moved.
+    pub fn with_tablespaces(mut self, tablespaces: Tablespaces) -> io::Result<Self> {
+        if self.memory.is_none() {
+            tablespaces.create_dirs()?;
+            for dir in tablespaces.dirs() {
+                remove_temp_files(dir);
+            }
+        }
+        self.tablespaces = tablespaces;
+        self.repair_torn_blocks()?;
+        Ok(self)
+    }
+
+    // Create a file manager that keeps its files in memory.
+    // Nothing is written to disk, and the database is
+    // lost when the file manager is dropped, which suits
+    // tests and caches that need no durability.
+    pub fn in_memory(block_size: usize) -> Self {
+        Self {
+            db_directory: PathBuf::new(),
+            _dir_lock: None,
+            tablespaces: Tablespaces::new(),
+            superblock: Superblock::new(block_size),
+            block_size,
+            is_new: true,
+            mode: IoMode::Memory,
+            open_files: Mutex::new(OpenFiles::new(Self::DEFAULT_MAX_OPEN_FILES)),
+            free: Mutex::new(HashMap::new()),
+            double_write: Mutex::new(None),
+            cipher: None,
+            lengths: Mutex::new(HashMap::new()),
+            extent_size: AtomicU64::new(1),
+            mappings: Mutex::new(HashMap::new()),
+            memory: Some(MemoryFiles::new(block_size)),
+        }
+    }
+
+    fn open(db_directory: impl AsRef<Path>, block_size: usize, mode: IoMode) -> io::Result<Self> {
         let db_directory = db_directory.as_ref().to_path_buf();
         let is_new = !db_directory.exists();
 
@@ -24,65 +177,735 @@ impl FileManager {
             fs::create_dir_all(&db_directory)?;
         }
 
-        // Clean up temp files
-        if let Ok(entries) = fs::read_dir(&db_directory) {
-            for entry in entries.flatten() {
-                let filename = entry.file_name();
-                if filename.to_string_lossy().starts_with("temp") {
-                    let _ = fs::remove_file(entry.path());
-                }
-            }
-        }
+        // the files must not be touched while another uses them
+        let dir_lock = dir_lock::acquire(&db_directory)?;
+        remove_temp_files(&db_directory);
+        let superblock = Superblock::open(&db_directory, block_size)?;
 
-        Ok(Self {
+        let fm = Self {
             db_directory,
+            _dir_lock: Some(dir_lock),
+            tablespaces: Tablespaces::new(),
+            superblock,
             block_size,
             is_new,
-            open_files: Mutex::new(HashMap::new()),
-        })
+            mode,
+            open_files: Mutex::new(OpenFiles::new(Self::DEFAULT_MAX_OPEN_FILES)),
+            free: Mutex::new(HashMap::new()),
+            double_write: Mutex::new(None),
+            cipher: None,
+            lengths: Mutex::new(HashMap::new()),
+            extent_size: AtomicU64::new(1),
+            mappings: Mutex::new(HashMap::new()),
+            memory: None,
+        };
+        fm.repair_torn_blocks()?;
+        Ok(fm)
     }
 
+    // Read the block into the page.
+    // If the block's checksum does not match its contents,
+    // the error wraps a DbError::CorruptBlock.
     pub fn read(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        if let Some(memory) = &self.memory {
+            return memory.read(block, page);
+        }
+        if self.mode == IoMode::Mapped {
+            let start = self.offset(block) as usize;
+            let end = start + self.stored_size() as usize;
+            let mut mappings = self.lock_mappings()?;
+            let mapping = self.mapping(&mut mappings, block.filename(), end, false)?;
+            return self.load(block, &mapping[start..end], page);
+        }
         let mut file = self.get_file(block.filename())?;
-        let offset = block.number() * self.block_size as u64;
-
-        // Seek to correct block position
-        file.seek(SeekFrom::Start(offset))?;
+        file.seek(SeekFrom::Start(self.offset(block)))?;
+        let mut stored = self.stored_block();
+        file.read_exact(&mut stored)?;
+        self.load(block, &stored, page)
+    }
 
-        // Get mutable reference to page's buffer and read directly into it
-        let buf = page.contents();
-        file.read_exact(buf)?;
+    // Check the stored form of the block against
+    // its checksum, and copy its contents to the page.
+    pub(super) fn load(&self, block: &BlockId, stored: &[u8], page: &mut Page) -> io::Result<()> {
+        let corrupt = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                DbError::CorruptBlock(block.clone()),
+            )
+        };
+        if !self.is_intact(stored) {
+            return Err(corrupt());
+        }
+        let (contents, lsn) = stored[..self.content_size()].split_at(self.sealed_size());
+        if contents.iter().all(|&b| b == 0) && lsn.iter().all(|&b| b == 0) {
+            // never written
+            page.contents().fill(0);
+            page.set_lsn(Lsn(0));
+            return Ok(());
+        }
+        page.set_lsn(match lsn.try_into() {
+            Ok(lsn) => Lsn::from_bytes(lsn),
+            Err(_) => Lsn(0),
+        });
+        let opened;
+        let contents = match self.cipher()? {
+            Some(cipher) => {
+                opened = cipher.open(block, contents).ok_or_else(corrupt)?;
+                &opened[..]
+            }
+            None => contents,
+        };
+        if !self.is_compressed() {
+            page.contents().copy_from_slice(contents);
+            return Ok(());
+        }
+        let (len, payload) = contents.split_at(LENGTH_SIZE);
+        let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+        if len == 0 {
+            page.contents().copy_from_slice(payload);
+            return Ok(());
+        }
+        payload
+            .get(..len)
+            .and_then(|compressed| {
+                lz4_flex::block::decompress_into(compressed, page.contents()).ok()
+            })
+            .filter(|&n| n == self.block_size)
+            .map(|_| ())
+            .ok_or_else(corrupt)
+    }
 
-        Ok(())
+    // Return true if the stored block matches its checksum,
+    // or was never written.
+    fn is_intact(&self, stored: &[u8]) -> bool {
+        let (contents, rest) = stored.split_at(self.content_size());
+        let checksum = u32::from_be_bytes(rest[..CHECKSUM_SIZE].try_into().unwrap());
+        let unwritten = checksum == 0 && contents.iter().all(|&b| b == 0);
+        unwritten || checksum == crc32fast::hash(contents)
     }
 
     pub fn write(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        if let Some(memory) = &self.memory {
+            return memory.write(block, page);
+        }
+        let lsn = page.lsn();
+        let stored = self.with_checksum(block, page.contents(), lsn)?;
+        let _area = self.protect(&[(block, &stored)])?;
+        if self.mode == IoMode::Mapped {
+            let start = self.offset(block) as usize;
+            let mut mappings = self.lock_mappings()?;
+            let mapping =
+                self.mapping(&mut mappings, block.filename(), start + stored.len(), true)?;
+            mapping[start..start + stored.len()].copy_from_slice(&stored);
+            mapping.flush_range(start, stored.len())?;
+            drop(mappings);
+            return self.note_written(block);
+        }
         let mut file = self.get_file(block.filename())?;
-        let offset = block.number() * self.block_size as u64;
-
-        file.seek(SeekFrom::Start(offset))?;
-        file.write_all(page.contents())?;
+        file.seek(SeekFrom::Start(self.offset(block)))?;
+        file.write_all(&stored)?;
         file.sync_data()?;
+        self.release_padding(&file, block, &stored);
+        self.note_written(block)
+    }
+
+    // Store the blocks compressed with LZ4, trading CPU time
+    // for disk space on tables of compressible data. The
+    // choice is recorded in the superblock, so it can only be
+    // made for a database that has no files yet; a database
+    // created with compression uses it whenever it is opened.
+    // The space saved is returned to the file system by
+    // punching holes over the padding of each block, which
+    // frees whole file system blocks only, so compression
+    // pays off with blocks several times their size.
+    pub fn with_compression(mut self) -> io::Result<Self> {
+        if self.memory.is_some() || self.is_compressed() {
+            return Ok(self);
+        }
+        self.check_unused("compression")?;
+        self.superblock.set_flag(Superblock::COMPRESSED);
+        self.superblock.save(&self.db_directory)?;
+        Ok(self)
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.superblock.has_flag(Superblock::COMPRESSED)
+    }
+
+    // Encrypt the blocks with the key, so that the files of
+    // the database can be kept on untrusted media.
+    // Like compression, encryption is chosen when the
+    // database is created, and the same key must be given
+    // whenever it is opened; reading or writing an encrypted
+    // database without it fails, and so does opening it with
+    // another key. The log is encrypted too, but the names
+    // and lengths of the files are not hidden, and a block
+    // of zeros reads as one never written.
+    // Compressed blocks take their whole space when encrypted.
+    pub fn with_encryption(mut self, key: [u8; 32]) -> io::Result<Self> {
+        if self.memory.is_some() {
+            return Ok(self);
+        }
+        let cipher = BlockCipher::new(&key);
+        let key_check = self.db_directory.join(cipher::KEY_CHECK_FILE);
+        if self.is_encrypted() {
+            if !cipher.verify(&fs::read(key_check)?) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    DbError::IncompatibleFormat("the encryption key is wrong".to_string()),
+                ));
+            }
+        } else {
+            self.check_unused("encryption")?;
+            let mut file = File::create(key_check)?;
+            file.write_all(&cipher.key_check())?;
+            file.sync_all()?;
+            self.superblock.set_flag(Superblock::ENCRYPTED);
+            self.superblock.save(&self.db_directory)?;
+        }
+        self.cipher = Some(cipher);
+        Ok(self)
+    }
+
+    fn is_encrypted(&self) -> bool {
+        self.superblock.has_flag(Superblock::ENCRYPTED)
+    }
+
+    // The cipher of an encrypted database, failing
+    // if the key was not given.
+    fn cipher(&self) -> io::Result<Option<&BlockCipher>> {
+        match &self.cipher {
+            None if self.is_encrypted() => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                DbError::IncompatibleFormat(
+                    "the database is encrypted, and no key was given".to_string(),
+                ),
+            )),
+            cipher => Ok(cipher.as_ref()),
+        }
+    }
+
+    // Fail unless the database has no files yet,
+    // as the feature can only be chosen then.
+    fn check_unused(&self, feature: &str) -> io::Result<()> {
+        let in_use = fs::read_dir(&self.db_directory)?.flatten().any(|entry| {
+            let name = entry.file_name();
+            name != Superblock::FILE
+                && name != double_write::FILE
+                && name != cipher::KEY_CHECK_FILE
+                && name != dir_lock::FILE
+        });
+        if in_use {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                DbError::IncompatibleFormat(format!(
+                    "{} can only be chosen when the database is created",
+                    feature
+                )),
+            ));
+        }
         Ok(())
     }
 
+    // Release the disk space taken by the zeros padding
+    // a compressed block, if the file system allows.
+    // Failing to do so costs nothing but space.
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    fn release_padding(&self, file: &File, block: &BlockId, stored: &[u8]) {
+        if !self.is_compressed() || self.is_encrypted() {
+            return;
+        }
+        let len = u32::from_be_bytes(stored[..LENGTH_SIZE].try_into().unwrap()) as usize;
+        let start = LENGTH_SIZE + len;
+        let end = self.sealed_size();
+        #[cfg(target_os = "linux")]
+        if len > 0 && end - start >= DIRECT_IO_ALIGN {
+            use std::os::unix::io::AsRawFd;
+            // SAFETY: the descriptor is open for the duration of the call.
+            unsafe {
+                libc::fallocate(
+                    file.as_raw_fd(),
+                    libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                    (self.offset(block) + start as u64) as libc::off_t,
+                    (end - start) as libc::off_t,
+                );
+            }
+        }
+    }
+
+    // Copy the stored blocks to the double-write area, if it
+    // is enabled, before they are written in place.
+    // The returned guard keeps other writers out of the
+    // area until the blocks have been written.
+    pub(super) fn protect(
+        &self,
+        blocks: &[(&BlockId, &[u8])],
+    ) -> io::Result<MutexGuard<'_, Option<File>>> {
+        let mut area = self.lock_double_write()?;
+        if let Some(file) = area.as_mut() {
+            let bytes = double_write::encode(blocks);
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&bytes)?;
+            file.sync_data()?;
+        }
+        Ok(area)
+    }
+
+    // Turn the double-write area on or off.
+    // When on, every block is first written to the area,
+    // which costs a second write and sync, so that a block
+    // torn by a crash in the middle of its write is restored
+    // when the database is next opened. A checksum can only
+    // detect a torn block; the log cannot repair it either,
+    // since its records change parts of blocks.
+    pub fn set_double_write(&self, on: bool) -> io::Result<()> {
+        if self.memory.is_some() {
+            return Ok(());
+        }
+        let mut area = self.lock_double_write()?;
+        let path = self.db_directory.join(double_write::FILE);
+        if !on {
+            *area = None;
+            return remove_if_exists(&path);
+        }
+        if area.is_none() {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?;
+            *area = Some(file);
+        }
+        Ok(())
+    }
+
+    // Restore the blocks torn by a crash while they were
+    // written, from their copies in the double-write area.
+    // A block whose file does not exist is skipped, since
+    // the file may be in a tablespace not given yet.
+    // Returns the number of blocks restored.
+    fn repair_torn_blocks(&self) -> io::Result<usize> {
+        let path = self.db_directory.join(double_write::FILE);
+        let bytes = match fs::read(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            bytes => bytes?,
+        };
+        let mut repaired = 0;
+        for (block, copy) in double_write::decode(&bytes) {
+            if copy.len() != self.stored_size() as usize
+                || !self.is_intact(&copy)
+                || !self.path(block.filename()).exists()
+            {
+                continue;
+            }
+            let mut file = self.get_file(block.filename())?;
+            let mut stored = self.stored_block();
+            file.seek(SeekFrom::Start(self.offset(&block)))?;
+            let torn = match file.read_exact(&mut stored) {
+                Ok(()) => !self.is_intact(&stored),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => true,
+                Err(e) => return Err(e),
+            };
+            if torn {
+                stored.copy_from_slice(&copy);
+                file.seek(SeekFrom::Start(self.offset(&block)))?;
+                file.write_all(&stored)?;
+                file.sync_data()?;
+                repaired += 1;
+            }
+        }
+        Ok(repaired)
+    }
+
+    // Add a zeroed block to the end of the file.
+    // When the file has no space left for the block, it
+    // grows by a whole extent and is synced; an append into
+    // space already allocated is not synced, since a block
+    // lost in a crash was never written, and reads as the
+    // same zeros if another block was written after it.
+    // A free block of the file is reused, if there is one.
     pub fn append(&self, filename: &str) -> io::Result<BlockId> {
-        let new_block_num = self.length(filename)?;
+        if let Some(block) = self.reuse_free_block(filename)? {
+            return Ok(block);
+        }
+        if let Some(memory) = &self.memory {
+            return memory.append(filename);
+        }
+        let mut lengths = self.lock_lengths()?;
+        let new_block_num = self.counted_length(&mut lengths, filename)?;
         let block = BlockId::new(filename.to_string(), new_block_num);
-        let empty_data = vec![0; self.block_size];
+        let empty_data = self.with_checksum(&block, &vec![0; self.block_size], Lsn(0))?;
 
         let mut file = self.get_file(filename)?;
-        file.seek(SeekFrom::End(0))?;
+        let allocated = file.metadata()?.len() / self.stored_size();
+        let extend = new_block_num >= allocated;
+        if extend {
+            let extent = self.extent_size.load(Ordering::Relaxed);
+            self.allocate(&file, new_block_num + extent)?;
+        }
+        file.seek(SeekFrom::Start(self.offset(&block)))?;
         file.write_all(&empty_data)?;
-        file.sync_data()?;
-
+        if extend {
+            file.sync_all()?;
+        }
+        lengths.insert(filename.to_string(), new_block_num + 1);
         Ok(block)
     }
 
     pub fn length(&self, filename: &str) -> io::Result<u64> {
+        if let Some(memory) = &self.memory {
+            return memory.length(filename);
+        }
+        let mut lengths = self.lock_lengths()?;
+        self.counted_length(&mut lengths, filename)
+    }
+
+    // The number of blocks in the file, counting them
+    // the first time: the blocks at the end of the file
+    // that are all zeros, checksum included, were
+    // preallocated and never appended.
+    fn counted_length(
+        &self,
+        lengths: &mut HashMap<String, u64>,
+        filename: &str,
+    ) -> io::Result<u64> {
+        if let Some(&len) = lengths.get(filename) {
+            return Ok(len);
+        }
+        let mut file = self.get_file(filename)?;
+        let mut len = file.metadata()?.len() / self.stored_size();
+        let mut stored = self.stored_block();
+        while len > 0 {
+            file.seek(SeekFrom::Start((len - 1) * self.stored_size()))?;
+            file.read_exact(&mut stored)?;
+            if stored.iter().any(|&b| b != 0) {
+                break;
+            }
+            len -= 1;
+        }
+        lengths.insert(filename.to_string(), len);
+        Ok(len)
+    }
+
+    // Set the number of blocks a file grows by when an
+    // append finds no space left in it. Bulk loads append
+    // faster with larger extents, which also keep the
+    // files less fragmented. The default is one block.
+    pub fn set_extent_size(&self, blocks: u64) {
+        self.extent_size.store(blocks.max(1), Ordering::Relaxed);
+    }
+
+    // Make sure that the file has space for the specified
+    // number of blocks, so that appending up to that many
+    // needs no allocation. The length of the file does
+    // not change.
+    pub fn preallocate(&self, filename: &str, blocks: u64) -> io::Result<()> {
+        if self.memory.is_some() {
+            return Ok(());
+        }
+        let mut lengths = self.lock_lengths()?;
+        self.counted_length(&mut lengths, filename)?;
         let file = self.get_file(filename)?;
-        let len = file.metadata()?.len();
-        Ok(len / self.block_size as u64)
+        if file.metadata()?.len() < blocks * self.stored_size() {
+            self.allocate(&file, blocks)?;
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    // Extend the file with zeros to hold the specified
+    // number of blocks, reserving the disk space for
+    // them where the platform and file system allow.
+    fn allocate(&self, file: &File, blocks: u64) -> io::Result<()> {
+        let len = blocks * self.stored_size();
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+            // SAFETY: the descriptor is open for the duration of the call.
+            let result = unsafe { libc::posix_fallocate(file.as_raw_fd(), 0, len as libc::off_t) };
+            if result == 0 {
+                return Ok(());
+            }
+        }
+        if file.metadata()?.len() < len {
+            file.set_len(len)?;
+        }
+        Ok(())
+    }
+
+    // Mark the block as free, to be reused by a later append.
+    // Its contents are discarded: the disk space is released
+    // where the file system can punch holes, and the block
+    // reads as zeros either way.
+    // The block must not be in use by a buffer or a
+    // transaction, since freeing it cannot be undone.
+    pub fn free_block(&self, block: &BlockId) -> io::Result<()> {
+        if block.number() >= self.length(block.filename())? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("block {} is past the end of its file", block),
+            ));
+        }
+        let mut free = self.lock_free()?;
+        let blocks = self.free_blocks(&mut free, block.filename())?;
+        if blocks.contains(&block.number()) {
+            return Ok(());
+        }
+        if let Some(memory) = &self.memory {
+            memory.write(block, &mut Page::new(self.block_size))?;
+        } else {
+            self.discard(block)?;
+            self.mark_free(block, true)?;
+        }
+        blocks.insert(block.number());
+        Ok(())
+    }
+
+    // The number of free blocks in the file.
+    pub fn free_count(&self, filename: &str) -> io::Result<usize> {
+        let mut free = self.lock_free()?;
+        Ok(self.free_blocks(&mut free, filename)?.len())
+    }
+
+    // Take the first free block of the file, if there is one.
+    // It is marked as used on disk before it is returned,
+    // so that it cannot be handed out twice.
+    fn reuse_free_block(&self, filename: &str) -> io::Result<Option<BlockId>> {
+        let len = self.length(filename)?;
+        let mut free = self.lock_free()?;
+        let blocks = self.free_blocks(&mut free, filename)?;
+        // free blocks at the end of the file are not counted in
+        // its length once it is reopened, and are appended anew
+        for n in blocks.split_off(&len) {
+            self.mark_free(&BlockId::new(filename, n), false)?;
+        }
+        let Some(n) = blocks.pop_first() else {
+            return Ok(None);
+        };
+        let block = BlockId::new(filename, n);
+        if self.memory.is_none() {
+            self.mark_free(&block, false)?;
+        }
+        Ok(Some(block))
+    }
+
+    // The free blocks of the file, loading them
+    // from its bitmap the first time.
+    fn free_blocks<'a>(
+        &self,
+        free: &'a mut HashMap<String, BTreeSet<u64>>,
+        filename: &str,
+    ) -> io::Result<&'a mut BTreeSet<u64>> {
+        if !free.contains_key(filename) {
+            let mut blocks = BTreeSet::new();
+            if self.memory.is_none() {
+                let bitmap = match fs::read(self.bitmap_path(filename)) {
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+                    bitmap => bitmap?,
+                };
+                for (i, byte) in bitmap.iter().enumerate() {
+                    for bit in (0..8).filter(|bit| byte & (1 << bit) != 0) {
+                        blocks.insert(i as u64 * 8 + bit);
+                    }
+                }
+            }
+            free.insert(filename.to_string(), blocks);
+        }
+        Ok(free.get_mut(filename).expect("the free blocks are loaded"))
+    }
+
+    // The bitmap of the free blocks of a file is kept
+    // beside it, with a bit per block.
+    fn bitmap_path(&self, filename: &str) -> PathBuf {
+        let mut path = self.path(filename).into_os_string();
+        path.push(".free");
+        path.into()
+    }
+
+    // Set or clear the block's bit in the bitmap of its file.
+    fn mark_free(&self, block: &BlockId, is_free: bool) -> io::Result<()> {
+        let mut bitmap = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.bitmap_path(block.filename()))?;
+        let pos = block.number() / 8;
+        let mut byte = [0];
+        if bitmap.metadata()?.len() > pos {
+            bitmap.seek(SeekFrom::Start(pos))?;
+            bitmap.read_exact(&mut byte)?;
+        }
+        let bit = 1 << (block.number() % 8);
+        byte[0] = if is_free {
+            byte[0] | bit
+        } else {
+            byte[0] & !bit
+        };
+        bitmap.seek(SeekFrom::Start(pos))?;
+        bitmap.write_all(&byte)?;
+        bitmap.sync_data()
+    }
+
+    // Zero the stored block, releasing its disk space
+    // if the file system allows.
+    fn discard(&self, block: &BlockId) -> io::Result<()> {
+        let mut file = self.get_file(block.filename())?;
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+            // SAFETY: the descriptor is open for the duration of the call.
+            let result = unsafe {
+                libc::fallocate(
+                    file.as_raw_fd(),
+                    libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                    self.offset(block) as libc::off_t,
+                    self.stored_size() as libc::off_t,
+                )
+            };
+            if result == 0 {
+                return file.sync_data();
+            }
+        }
+        file.seek(SeekFrom::Start(self.offset(block)))?;
+        file.write_all(&self.stored_block())?;
+        file.sync_data()
+    }
+
+    // Count the block in the length of its file,
+    // now that it has been written.
+    pub(super) fn note_written(&self, block: &BlockId) -> io::Result<()> {
+        if let Some(len) = self.lock_lengths()?.get_mut(block.filename()) {
+            *len = (*len).max(block.number() + 1);
+        }
+        Ok(())
+    }
+
+    // Delete the file, closing it first if it is open.
+    // Deleting a file that does not exist is not an error,
+    // since files are only created when first used.
+    pub fn delete_file(&self, filename: &str) -> io::Result<()> {
+        self.lock_free()?.remove(filename);
+        if let Some(memory) = &self.memory {
+            return memory.delete_file(filename);
+        }
+        let mut lengths = self.lock_lengths()?;
+        lengths.remove(filename);
+        let mut mappings = self.lock_mappings()?;
+        mappings.remove(filename);
+        self.lock_files()?.remove(filename);
+        remove_if_exists(&self.bitmap_path(filename))?;
+        remove_if_exists(&self.path(filename))
+    }
+
+    // Rename the file, replacing any file having the new name.
+    // Both files are closed first if they are open.
+    // The blocks of an encrypted file are sealed again,
+    // since the name of their file is authenticated with them.
+    pub fn rename_file(&self, from: &str, to: &str) -> io::Result<()> {
+        {
+            let mut free = self.lock_free()?;
+            let blocks = free.remove(from);
+            free.remove(to);
+            if let (Some(blocks), Some(_)) = (blocks, &self.memory) {
+                free.insert(to.to_string(), blocks);
+            }
+        }
+        if let Some(memory) = &self.memory {
+            return memory.rename_file(from, to);
+        }
+        let mut lengths = self.lock_lengths()?;
+        lengths.remove(from);
+        lengths.remove(to);
+        remove_if_exists(&self.bitmap_path(to))?;
+        if self.bitmap_path(from).exists() {
+            fs::rename(self.bitmap_path(from), self.bitmap_path(to))?;
+        }
+        let mut mappings = self.lock_mappings()?;
+        mappings.remove(from);
+        mappings.remove(to);
+        let mut files = self.lock_files()?;
+        files.remove(from);
+        files.remove(to);
+        move_file(&self.path(from), &self.path(to))?;
+        drop((files, mappings, lengths));
+        if self.is_encrypted() {
+            self.reseal(from, to)?;
+        }
+        Ok(())
+    }
+
+    // Seal each block written to the file, renamed from
+    // the other name, for its new name.
+    fn reseal(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut page = Page::new(self.block_size);
+        let mut stored = self.stored_block();
+        for blknum in 0..self.length(to)? {
+            let block = BlockId::new(to, blknum);
+            let mut file = self.get_file(to)?;
+            file.seek(SeekFrom::Start(self.offset(&block)))?;
+            file.read_exact(&mut stored)?;
+            if stored.iter().all(|&b| b == 0) {
+                continue;
+            }
+            self.load(&BlockId::new(from, blknum), &stored, &mut page)?;
+            self.write(&block, &mut page)?;
+        }
+        Ok(())
+    }
+
+    // Cut the file down to its first new_len blocks.
+    // A file that is already that short is left as it is.
+    // The free blocks cut off are forgotten.
+    pub fn truncate(&self, filename: &str, new_len: u64) -> io::Result<()> {
+        {
+            let mut free = self.lock_free()?;
+            let blocks = self.free_blocks(&mut free, filename)?;
+            for n in blocks.split_off(&new_len) {
+                if self.memory.is_none() {
+                    self.mark_free(&BlockId::new(filename, n), false)?;
+                }
+            }
+        }
+        if let Some(memory) = &self.memory {
+            return memory.truncate(filename, new_len);
+        }
+        let mut lengths = self.lock_lengths()?;
+        lengths.remove(filename);
+        let file = self.get_file(filename)?;
+        let len = new_len * self.stored_size();
+        if file.metadata()?.len() > len {
+            // the mapped pages past the new end would become invalid
+            self.lock_mappings()?.remove(filename);
+            file.set_len(len)?;
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    // Set the maximum number of files kept open.
+    // Beyond it, the least recently used files are closed,
+    // and reopened when they are next used, so that a
+    // database of many tables does not run out of
+    // file descriptors.
+    pub fn set_max_open_files(&self, max: usize) -> io::Result<()> {
+        self.lock_files()?.set_max(max);
+        Ok(())
+    }
+
+    // The path of the file, in its tablespace.
+    fn path(&self, filename: &str) -> PathBuf {
+        self.tablespaces
+            .dir_of(filename)
+            .unwrap_or(&self.db_directory)
+            .join(filename)
+    }
+
+    // The description of the database's on-disk format.
+    pub fn superblock(&self) -> &Superblock {
+        &self.superblock
     }
 
     pub fn is_new(&self) -> bool {
@@ -93,28 +916,285 @@ impl FileManager {
         self.block_size
     }
 
-    fn get_file(&self, filename: &str) -> io::Result<File> {
-        let mut files = self
-            .open_files
-            .lock()
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to acquire lock"))?;
+    // The number of bytes a block takes on disk.
+    fn stored_size(&self) -> u64 {
+        let size = self.content_size() + CHECKSUM_SIZE;
+        if self.mode == IoMode::Direct {
+            size.next_multiple_of(DIRECT_IO_ALIGN) as u64
+        } else {
+            size as u64
+        }
+    }
+
+    // The number of bytes of a stored block covered by its
+    // checksum: the page LSN follows the contents, under it.
+    fn content_size(&self) -> usize {
+        self.sealed_size() + Lsn::SIZE
+    }
+
+    // The number of bytes of a stored block holding the
+    // page, after compression and encryption.
+    fn sealed_size(&self) -> usize {
+        if self.is_encrypted() {
+            self.plain_size() + BlockCipher::OVERHEAD
+        } else {
+            self.plain_size()
+        }
+    }
+
+    // The number of bytes of a stored block before encryption.
+    fn plain_size(&self) -> usize {
+        if self.is_compressed() {
+            LENGTH_SIZE + self.block_size
+        } else {
+            self.block_size
+        }
+    }
+
+    pub(super) fn offset(&self, block: &BlockId) -> u64 {
+        block.number() * self.stored_size()
+    }
+
+    // A zeroed buffer for a stored block,
+    // aligned as direct I/O requires.
+    pub(super) fn stored_block(&self) -> AlignedBuf {
+        let align = match self.mode {
+            IoMode::Direct => DIRECT_IO_ALIGN,
+            _ => 1,
+        };
+        AlignedBuf::new(self.stored_size() as usize, align)
+    }
+
+    // The stored form of the block: the contents, compressed
+    // and then encrypted if the database is, and the page LSN,
+    // followed by their checksum.
+    pub(super) fn with_checksum(
+        &self,
+        block: &BlockId,
+        contents: &[u8],
+        lsn: Lsn,
+    ) -> io::Result<AlignedBuf> {
+        let mut stored = self.stored_block();
+        let sealed = self.sealed_size();
+        let size = self.content_size();
+        let cipher = self.cipher()?;
+        let mut plain = vec![0; self.plain_size()];
+        if self.is_compressed() {
+            let compressed = lz4_flex::block::compress(contents);
+            if compressed.len() < contents.len() {
+                plain[..LENGTH_SIZE].copy_from_slice(&(compressed.len() as u32).to_be_bytes());
+                plain[LENGTH_SIZE..LENGTH_SIZE + compressed.len()].copy_from_slice(&compressed);
+            } else {
+                plain[LENGTH_SIZE..].copy_from_slice(contents);
+            }
+        } else {
+            plain.copy_from_slice(contents);
+        }
+        match cipher {
+            Some(cipher) => cipher.seal(block, &plain, &mut stored[..sealed]),
+            None => stored[..sealed].copy_from_slice(&plain),
+        }
+        if size > sealed {
+            stored[sealed..size].copy_from_slice(&lsn.to_bytes());
+        }
+        let checksum = crc32fast::hash(&stored[..size]).to_be_bytes();
+        stored[size..size + CHECKSUM_SIZE].copy_from_slice(&checksum);
+        Ok(stored)
+    }
+
+    pub(super) fn get_file(&self, filename: &str) -> io::Result<File> {
+        let mut files = self.lock_files()?;
 
         if let Some(file) = files.get(filename) {
             Ok(file.try_clone()?)
         } else {
-            let filepath = self.db_directory.join(filename);
-            let file = OpenOptions::new()
-                .read(true)
-                .write(true)
-                .create(true)
-                .truncate(false)
-                .open(filepath)?;
+            let filepath = self.path(filename);
+            let mut options = OpenOptions::new();
+            options.read(true).write(true).create(true).truncate(false);
+            #[cfg(target_os = "linux")]
+            if self.mode == IoMode::Direct {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.custom_flags(libc::O_DIRECT);
+            }
+            let file = options.open(filepath)?;
+            #[cfg(target_os = "macos")]
+            if self.mode == IoMode::Direct {
+                use std::os::unix::io::AsRawFd;
+                // SAFETY: the descriptor is open for the duration of the call.
+                if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_NOCACHE, 1) } == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
 
             let clone = file.try_clone()?;
-            files.insert(filename.to_string(), file);
+            files.insert(filename, file);
             Ok(clone)
         }
     }
+
+    // The mapping of the file, covering at least len bytes.
+    // If grow is set, a shorter file is first extended
+    // with zeros; otherwise reading past its end fails.
+    fn mapping<'a>(
+        &self,
+        mappings: &'a mut HashMap<String, MmapMut>,
+        filename: &str,
+        len: usize,
+        grow: bool,
+    ) -> io::Result<&'a mut MmapMut> {
+        if mappings
+            .get(filename)
+            .is_none_or(|mapping| mapping.len() < len)
+        {
+            let file = self.get_file(filename)?;
+            if (file.metadata()?.len() as usize) < len {
+                if !grow {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "failed to fill whole buffer",
+                    ));
+                }
+                file.set_len(len as u64)?;
+            }
+            // SAFETY: the files are only changed through this file
+            // manager, which drops a mapping before shortening its file.
+            let mapping = unsafe { MmapMut::map_mut(&file)? };
+            mappings.insert(filename.to_string(), mapping);
+        }
+        Ok(mappings.get_mut(filename).expect("the file is mapped"))
+    }
+
+    fn lock_mappings(&self) -> io::Result<MutexGuard<'_, HashMap<String, MmapMut>>> {
+        self.mappings
+            .lock()
+            .map_err(|_| io::Error::other("failed to acquire lock"))
+    }
+
+    fn lock_lengths(&self) -> io::Result<MutexGuard<'_, HashMap<String, u64>>> {
+        self.lengths
+            .lock()
+            .map_err(|_| io::Error::other("failed to acquire lock"))
+    }
+
+    fn lock_free(&self) -> io::Result<MutexGuard<'_, HashMap<String, BTreeSet<u64>>>> {
+        self.free
+            .lock()
+            .map_err(|_| io::Error::other("failed to acquire lock"))
+    }
+
+    fn lock_double_write(&self) -> io::Result<MutexGuard<'_, Option<File>>> {
+        self.double_write
+            .lock()
+            .map_err(|_| io::Error::other("failed to acquire lock"))
+    }
+
+    fn lock_files(&self) -> io::Result<MutexGuard<'_, OpenFiles>> {
+        self.open_files
+            .lock()
+            .map_err(|_| io::Error::other("failed to acquire lock"))
+    }
+}
+
+impl BlockStore for FileManager {
+    fn block_size(&self) -> usize {
+        FileManager::block_size(self)
+    }
+
+    fn read(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        FileManager::read(self, block, page)
+    }
+
+    fn write(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        FileManager::write(self, block, page)
+    }
+
+    fn append(&self, filename: &str) -> io::Result<BlockId> {
+        FileManager::append(self, filename)
+    }
+
+    fn length(&self, filename: &str) -> io::Result<u64> {
+        FileManager::length(self, filename)
+    }
+
+    fn delete_file(&self, filename: &str) -> io::Result<()> {
+        FileManager::delete_file(self, filename)
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> io::Result<()> {
+        FileManager::rename_file(self, from, to)
+    }
+
+    fn truncate(&self, filename: &str, len: u64) -> io::Result<()> {
+        FileManager::truncate(self, filename, len)
+    }
+}
+
+// Return true if the file belongs to a temporary table,
+// which only the transaction that created it uses.
+pub fn is_temporary(filename: &str) -> bool {
+    filename.starts_with("temp")
+}
+
+// Remove the temporary files left in the directory,
+// by a database that was not shut down cleanly.
+fn remove_temp_files(dir: &Path) {
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let filename = entry.file_name();
+            if is_temporary(&filename.to_string_lossy()) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+// Move the file, copying it if a rename cannot,
+// as between file systems.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    File::open(to)?.sync_all()?;
+    fs::remove_file(from)
+}
+
+// Remove the file, unless there is no such file.
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+// A zeroed byte buffer whose start is aligned
+// to the specified power of two.
+pub(super) struct AlignedBuf {
+    bytes: Vec<u8>,
+    start: usize,
+    len: usize,
+}
+
+impl AlignedBuf {
+    fn new(len: usize, align: usize) -> Self {
+        let bytes = vec![0; len + align - 1];
+        let start = bytes.as_ptr().align_offset(align);
+        AlignedBuf { bytes, start, len }
+    }
+}
+
+impl Deref for AlignedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[self.start..self.start + self.len]
+    }
+}
+
+impl DerefMut for AlignedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[self.start..self.start + self.len]
+    }
 }
 
 #[cfg(test)]
@@ -175,4 +1255,386 @@ mod tests {
         let result = fm.read(&block, &mut page);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_max_open_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        fm.set_max_open_files(2).unwrap();
+        let mut page = Page::new(400);
+        for i in 0..5 {
+            page.set_int(0, i);
+            fm.write(&BlockId::new(format!("file{}", i), 0), &mut page)
+                .unwrap();
+        }
+        // the closed files are reopened as needed
+        for i in 0..5 {
+            fm.read(&BlockId::new(format!("file{}", i), 0), &mut page)
+                .unwrap();
+            assert_eq!(page.get_int(0), i);
+        }
+    }
+
+    #[test]
+    fn test_extents() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        let disk_len = |filename: &str| {
+            let len = fs::metadata(temp_dir.path().join(filename)).unwrap().len();
+            len / (400 + CHECKSUM_SIZE) as u64
+        };
+        fm.set_extent_size(8);
+        for i in 0..3 {
+            assert_eq!(fm.append("test.dat").unwrap().number(), i);
+        }
+        assert_eq!(fm.length("test.dat").unwrap(), 3);
+        assert_eq!(disk_len("test.dat"), 8);
+
+        // writing a block past the end extends the file
+        let mut page = Page::new(400);
+        fm.write(&BlockId::new("test.dat", 4), &mut page).unwrap();
+        assert_eq!(fm.length("test.dat").unwrap(), 5);
+
+        fm.preallocate("other.dat", 10).unwrap();
+        assert_eq!(fm.length("other.dat").unwrap(), 0);
+        assert_eq!(disk_len("other.dat"), 10);
+        fm.append("other.dat").unwrap();
+
+        // the preallocated blocks are not counted on reopening
+        drop(fm);
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        assert_eq!(fm.length("test.dat").unwrap(), 5);
+        assert_eq!(fm.length("other.dat").unwrap(), 1);
+        assert_eq!(fm.append("other.dat").unwrap().number(), 1);
+        assert_eq!(disk_len("other.dat"), 10);
+    }
+
+    #[test]
+    fn test_free_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        let mut page = Page::new(400);
+        page.set_int(0, 99);
+        for _ in 0..4 {
+            let block = fm.append("test.dat").unwrap();
+            fm.write(&block, &mut page).unwrap();
+        }
+        fm.free_block(&BlockId::new("test.dat", 2)).unwrap();
+        fm.free_block(&BlockId::new("test.dat", 1)).unwrap();
+        fm.free_block(&BlockId::new("test.dat", 1)).unwrap();
+        assert!(fm.free_block(&BlockId::new("test.dat", 4)).is_err());
+        assert_eq!(fm.free_count("test.dat").unwrap(), 2);
+
+        // a freed block reads as zeros
+        fm.read(&BlockId::new("test.dat", 2), &mut page).unwrap();
+        assert_eq!(page.get_int(0), 0);
+        assert_eq!(fm.append("test.dat").unwrap().number(), 1);
+
+        // the free blocks survive reopening
+        drop(fm);
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        assert_eq!(fm.free_count("test.dat").unwrap(), 1);
+        assert_eq!(fm.append("test.dat").unwrap().number(), 2);
+        assert_eq!(fm.append("test.dat").unwrap().number(), 4);
+        assert_eq!(fm.length("test.dat").unwrap(), 5);
+
+        fm.free_block(&BlockId::new("test.dat", 0)).unwrap();
+        fm.rename_file("test.dat", "other.dat").unwrap();
+        assert_eq!(fm.free_count("other.dat").unwrap(), 1);
+        fm.delete_file("other.dat").unwrap();
+        assert!(!temp_dir.path().join("other.dat.free").exists());
+
+        let fm = FileManager::in_memory(400);
+        let block = fm.append("test.dat").unwrap();
+        fm.append("test.dat").unwrap();
+        fm.free_block(&block).unwrap();
+        assert_eq!(fm.append("test.dat").unwrap(), block);
+        assert_eq!(fm.length("test.dat").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_double_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let block = BlockId::new("test.dat", 1);
+        let tear = |fm: &FileManager| {
+            let mut page = Page::new(400);
+            page.set_int(100, 7);
+            fm.write(&block, &mut page).unwrap();
+            // the crash leaves part of a later write
+            let mut file = fm.get_file("test.dat").unwrap();
+            file.seek(SeekFrom::Start(fm.offset(&block) + 10)).unwrap();
+            file.write_all(&[0xff; 20]).unwrap();
+        };
+
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        fm.set_double_write(true).unwrap();
+        tear(&fm);
+        drop(fm);
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        let mut page = Page::new(400);
+        fm.read(&block, &mut page).unwrap();
+        assert_eq!(page.get_int(100), 7);
+
+        // without the area, the torn block stays corrupt
+        fm.set_double_write(false).unwrap();
+        tear(&fm);
+        drop(fm);
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        let err = DbError::from(fm.read(&block, &mut page).unwrap_err());
+        assert!(matches!(err, DbError::CorruptBlock(b) if b == block));
+    }
+
+    #[test]
+    fn test_compression() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::new(temp_dir.path(), 4096)
+            .unwrap()
+            .with_compression()
+            .unwrap();
+        let mut page = Page::new(4096);
+        page.set_string(0, "compressible");
+        let block = fm.append("test.dat").unwrap();
+        fm.write(&block, &mut page).unwrap();
+        // bytes that do not compress are stored as they are
+        let mut noise = Page::new(4096);
+        let mut x: u32 = 1;
+        for b in noise.contents().iter_mut() {
+            x = x.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            *b = (x >> 24) as u8;
+        }
+        fm.write(&BlockId::new("test.dat", 1), &mut noise).unwrap();
+
+        // the database stays compressed when reopened
+        drop(fm);
+        let fm = FileManager::new(temp_dir.path(), 4096).unwrap();
+        assert!(fm.superblock().has_flag(Superblock::COMPRESSED));
+        let mut read = Page::new(4096);
+        fm.read(&block, &mut read).unwrap();
+        assert_eq!(read.get_string(0), "compressible");
+        fm.read(&BlockId::new("test.dat", 1), &mut read).unwrap();
+        assert_eq!(read.contents(), noise.contents());
+        assert_eq!(fm.length("test.dat").unwrap(), 2);
+
+        // choosing compression again is harmless
+        drop(fm);
+        assert!(FileManager::new(temp_dir.path(), 4096)
+            .unwrap()
+            .with_compression()
+            .is_ok());
+        let other = TempDir::new().unwrap();
+        let fm = FileManager::new(other.path(), 400).unwrap();
+        fm.append("test.dat").unwrap();
+        let err = DbError::from(fm.with_compression().err().unwrap());
+        assert!(matches!(err, DbError::IncompatibleFormat(_)));
+    }
+
+    #[test]
+    fn test_encryption() {
+        let temp_dir = TempDir::new().unwrap();
+        let key = [7; 32];
+        let fm = FileManager::new(temp_dir.path(), 400)
+            .unwrap()
+            .with_encryption(key)
+            .unwrap()
+            .with_compression()
+            .unwrap();
+        let mut page = Page::new(400);
+        page.set_string(0, "a secret");
+        let block = fm.append("test.dat").unwrap();
+        fm.write(&block, &mut page).unwrap();
+        let stored = fs::read(temp_dir.path().join("test.dat")).unwrap();
+        assert!(!stored.windows(6).any(|w| w == b"secret"));
+
+        // the key is needed to open the database again
+        drop(fm);
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        let mut read = Page::new(400);
+        let err = DbError::from(fm.read(&block, &mut read).unwrap_err());
+        assert!(matches!(err, DbError::IncompatibleFormat(_)));
+        let err = DbError::from(fm.with_encryption([8; 32]).err().unwrap());
+        assert!(matches!(err, DbError::IncompatibleFormat(_)));
+        let fm = FileManager::new(temp_dir.path(), 400)
+            .unwrap()
+            .with_encryption(key)
+            .unwrap();
+        fm.read(&block, &mut read).unwrap();
+        assert_eq!(read.get_string(0), "a secret");
+        let empty = fm.append("test.dat").unwrap();
+        fm.read(&empty, &mut read).unwrap();
+        assert!(read.contents().iter().all(|&b| b == 0));
+
+        // a renamed file is sealed again for its new name,
+        // while a block copied from another file does not decrypt
+        fm.rename_file("test.dat", "renamed.dat").unwrap();
+        fm.read(&BlockId::new("renamed.dat", 0), &mut read).unwrap();
+        assert_eq!(read.get_string(0), "a secret");
+        fm.rename_file("renamed.dat", "test.dat").unwrap();
+        let copy = fm.append("copy.dat").unwrap();
+        fs::write(temp_dir.path().join("copy.dat"), &stored).unwrap();
+        let err = DbError::from(fm.read(&copy, &mut read).unwrap_err());
+        assert!(matches!(err, DbError::CorruptBlock(_)));
+        fm.read(&block, &mut read).unwrap();
+        assert_eq!(read.get_string(0), "a secret");
+
+        // a block moved to another position does not decrypt
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(temp_dir.path().join("test.dat"))
+            .unwrap();
+        file.seek(SeekFrom::Start(fm.offset(&empty))).unwrap();
+        file.write_all(&stored).unwrap();
+        let err = DbError::from(fm.read(&empty, &mut read).unwrap_err());
+        assert!(matches!(err, DbError::CorruptBlock(_)));
+
+        let other = TempDir::new().unwrap();
+        let fm = FileManager::new(other.path(), 400).unwrap();
+        fm.append("test.dat").unwrap();
+        let err = DbError::from(fm.with_encryption(key).err().unwrap());
+        assert!(matches!(err, DbError::IncompatibleFormat(_)));
+    }
+
+    #[test]
+    fn test_delete_rename_truncate() {
+        let (temp_dir, fm) = setup();
+        for _ in 0..3 {
+            fm.append("a.dat").unwrap();
+        }
+        let mut page = Page::new(400);
+        page.contents()[0] = 7;
+        fm.write(&BlockId::new("a.dat".to_string(), 1), &mut page)
+            .unwrap();
+
+        fm.truncate("a.dat", 2).unwrap();
+        assert_eq!(fm.length("a.dat").unwrap(), 2);
+        fm.truncate("a.dat", 5).unwrap();
+        assert_eq!(fm.length("a.dat").unwrap(), 2);
+
+        // The open file is closed, so the new name sees the data
+        fm.rename_file("a.dat", "b.dat").unwrap();
+        assert!(!temp_dir.path().join("a.dat").exists());
+        let mut page = Page::new(400);
+        fm.read(&BlockId::new("b.dat".to_string(), 1), &mut page)
+            .unwrap();
+        assert_eq!(page.contents()[0], 7);
+
+        fm.delete_file("b.dat").unwrap();
+        assert!(!temp_dir.path().join("b.dat").exists());
+        assert_eq!(fm.length("b.dat").unwrap(), 0);
+        fm.delete_file("never.dat").unwrap();
+        assert!(fm.rename_file("never.dat", "c.dat").is_err());
+    }
+
+    #[test]
+    fn test_checksum() {
+        let (temp_dir, fm) = setup();
+        let block = BlockId::new("test.dat".to_string(), 1);
+        let mut page = Page::new(400);
+        page.contents()[0..5].copy_from_slice(b"hello");
+        fm.write(&block, &mut page).unwrap();
+
+        // A hole left before the written block reads as zeros
+        let mut page = Page::new(400);
+        fm.read(&BlockId::new("test.dat".to_string(), 0), &mut page)
+            .unwrap();
+        assert!(page.contents().iter().all(|&b| b == 0));
+
+        // Flip a byte of the stored block
+        let path = temp_dir.path().join("test.dat");
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[fm.stored_size() as usize + 2] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+        let err = fm.read(&block, &mut page).unwrap_err();
+        assert!(matches!(
+            DbError::from(err),
+            DbError::CorruptBlock(blk) if blk == block
+        ));
+    }
+
+    #[test]
+    fn test_direct_io() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = match FileManager::with_direct_io(temp_dir.path(), 400) {
+            Ok(fm) => fm,
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => return,
+            Err(e) => panic!("{}", e),
+        };
+        let block = match fm.append("test.dat") {
+            Ok(block) => block,
+            // the file system may not allow direct I/O
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => return,
+            Err(e) => panic!("{}", e),
+        };
+        let mut page = Page::new(400);
+        page.contents()[0..5].copy_from_slice(b"hello");
+        fm.write(&block, &mut page).unwrap();
+        fm.append("test.dat").unwrap();
+
+        let mut page = Page::new(400);
+        fm.read(&block, &mut page).unwrap();
+        assert_eq!(&page.contents()[0..5], b"hello");
+        assert_eq!(fm.length("test.dat").unwrap(), 2);
+        let len = fs::metadata(temp_dir.path().join("test.dat"))
+            .unwrap()
+            .len();
+        assert_eq!(len, 2 * DIRECT_IO_ALIGN as u64);
+    }
+
+    #[test]
+    fn test_mmap() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::with_mmap(temp_dir.path(), 400).unwrap();
+        let block = fm.append("test.dat").unwrap();
+        let mut page = Page::new(400);
+        page.contents()[0..5].copy_from_slice(b"hello");
+        fm.write(&block, &mut page).unwrap();
+
+        // Writing past the end extends the file
+        let far = BlockId::new("test.dat".to_string(), 3);
+        fm.write(&far, &mut page).unwrap();
+        assert_eq!(fm.length("test.dat").unwrap(), 4);
+        let mut page = Page::new(400);
+        fm.read(&BlockId::new("test.dat".to_string(), 2), &mut page)
+            .unwrap();
+        assert!(page.contents().iter().all(|&b| b == 0));
+        fm.truncate("test.dat", 1).unwrap();
+        assert!(fm.read(&far, &mut page).is_err());
+
+        // The files have the usual format
+        drop(fm);
+        let fm = FileManager::new(temp_dir.path(), 400).unwrap();
+        fm.read(&block, &mut page).unwrap();
+        assert_eq!(&page.contents()[0..5], b"hello");
+    }
+
+    #[test]
+    fn test_mmap_concurrent_rename() {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = FileManager::with_mmap(temp_dir.path(), 400).unwrap();
+
+        // Writes and renames take the locks in the same order
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..200 {
+                    let block = BlockId::new(format!("a{}.dat", i), 0);
+                    let mut page = Page::new(400);
+                    page.set_int(0, i);
+                    fm.write(&block, &mut page).unwrap();
+                    let mut page = Page::new(400);
+                    fm.read(&block, &mut page).unwrap();
+                    assert_eq!(page.get_int(0), i);
+                }
+            });
+            s.spawn(|| {
+                for i in 0..200 {
+                    let from = format!("b{}.dat", i);
+                    let to = format!("c{}.dat", i);
+                    let mut page = Page::new(400);
+                    fm.write(&BlockId::new(from.clone(), 0), &mut page).unwrap();
+                    fm.rename_file(&from, &to).unwrap();
+                    fm.read(&BlockId::new(to.clone(), 0), &mut page).unwrap();
+                    fm.delete_file(&to).unwrap();
+                }
+            });
+        });
+    }
 }
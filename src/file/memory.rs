@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    file::{BlockId, Page},
+    log::Lsn,
+};
+
+// Files of blocks held on the heap, for a file manager
+// whose database disappears when it is dropped.
+// The files behave like those on disk: reading past the
+// end of a file fails, and writing past it extends the
+// file with zeroed blocks.
+pub(crate) struct MemoryFiles {
+    block_size: usize,
+    files: Mutex<HashMap<String, Vec<Vec<u8>>>>,
+}
+
+impl MemoryFiles {
+    pub(crate) fn new(block_size: usize) -> Self {
+        MemoryFiles {
+            block_size,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn read(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        let files = self.lock()?;
+        let contents = files
+            .get(block.filename())
+            .and_then(|blocks| blocks.get(block.number() as usize))
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer")
+            })?;
+        page.contents().copy_from_slice(contents);
+        // the page LSN is only kept on disk, for recovery
+        page.set_lsn(Lsn(0));
+        Ok(())
+    }
+
+    pub(crate) fn write(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        let mut files = self.lock()?;
+        let blocks = files.entry(block.filename().to_string()).or_default();
+        let num = block.number() as usize;
+        if blocks.len() <= num {
+            blocks.resize(num + 1, vec![0; self.block_size]);
+        }
+        blocks[num].copy_from_slice(page.contents());
+        Ok(())
+    }
+
+    pub(crate) fn append(&self, filename: &str) -> io::Result<BlockId> {
+        let mut files = self.lock()?;
+        let blocks = files.entry(filename.to_string()).or_default();
+        blocks.push(vec![0; self.block_size]);
+        Ok(BlockId::new(filename, blocks.len() as u64 - 1))
+    }
+
+    pub(crate) fn length(&self, filename: &str) -> io::Result<u64> {
+        let files = self.lock()?;
+        Ok(files.get(filename).map_or(0, |blocks| blocks.len() as u64))
+    }
+
+    pub(crate) fn delete_file(&self, filename: &str) -> io::Result<()> {
+        self.lock()?.remove(filename);
+        Ok(())
+    }
+
+    pub(crate) fn rename_file(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut files = self.lock()?;
+        let blocks = files
+            .remove(from)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no file {}", from)))?;
+        files.insert(to.to_string(), blocks);
+        Ok(())
+    }
+
+    pub(crate) fn truncate(&self, filename: &str, new_len: u64) -> io::Result<()> {
+        if let Some(blocks) = self.lock()?.get_mut(filename) {
+            blocks.truncate(new_len as usize);
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> io::Result<MutexGuard<'_, HashMap<String, Vec<Vec<u8>>>>> {
+        self.files
+            .lock()
+            .map_err(|_| io::Error::other("failed to acquire lock"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::{BlockId, FileManager, Page};
+
+    #[test]
+    fn test_memory_files() {
+        let fm = FileManager::in_memory(400);
+        assert!(fm.is_new());
+        let block = BlockId::new("test.dat".to_string(), 2);
+        let mut page = Page::new(400);
+        assert!(fm.read(&block, &mut page).is_err());
+
+        // Writing past the end adds zeroed blocks
+        page.contents()[0..5].copy_from_slice(b"hello");
+        fm.write(&block, &mut page).unwrap();
+        assert_eq!(fm.length("test.dat").unwrap(), 3);
+        assert_eq!(fm.append("test.dat").unwrap().number(), 3);
+
+        fm.rename_file("test.dat", "other.dat").unwrap();
+        assert_eq!(fm.length("test.dat").unwrap(), 0);
+        let mut page = Page::new(400);
+        fm.read(&BlockId::new("other.dat".to_string(), 2), &mut page)
+            .unwrap();
+        assert_eq!(&page.contents()[0..5], b"hello");
+
+        fm.truncate("other.dat", 1).unwrap();
+        assert_eq!(fm.length("other.dat").unwrap(), 1);
+        fm.delete_file("other.dat").unwrap();
+        assert_eq!(fm.length("other.dat").unwrap(), 0);
+    }
+}
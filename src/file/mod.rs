@@ -1,7 +1,24 @@
 mod block_id;
+mod cipher;
+mod dir_lock;
+mod double_write;
+#[cfg(test)]
+pub(crate) mod faulty_store;
 mod manager;
+mod memory;
+mod open_files;
 mod page;
+mod store;
+mod superblock;
+mod tablespace;
+#[cfg(target_os = "linux")]
+mod uring;
 
 pub use block_id::BlockId;
-pub use manager::FileManager;
+pub use manager::{is_temporary, FileManager};
 pub use page::Page;
+pub use store::BlockStore;
+pub use superblock::Superblock;
+pub use tablespace::Tablespaces;
+#[cfg(target_os = "linux")]
+pub use uring::UringStore;
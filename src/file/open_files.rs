@@ -0,0 +1,90 @@
+use std::{collections::HashMap, fs::File};
+
+// The open files of a file manager, by name.
+// At most max files are kept open; opening another one
+// closes the file that was used least recently.
+// Callers get clones of the cached handles, so a file
+// closed here stays usable by whoever holds a clone.
+pub(crate) struct OpenFiles {
+    max: usize,
+    files: HashMap<String, (File, u64)>,
+    // The logical time of the latest use.
+    clock: u64,
+}
+
+impl OpenFiles {
+    pub(crate) fn new(max: usize) -> Self {
+        OpenFiles {
+            max: max.max(1),
+            files: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    // The file with the name, if it is open,
+    // which becomes the most recently used.
+    pub(crate) fn get(&mut self, filename: &str) -> Option<&File> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.files.get_mut(filename).map(|(file, used)| {
+            *used = clock;
+            &*file
+        })
+    }
+
+    // Add the open file, closing the least recently
+    // used files if there are too many.
+    pub(crate) fn insert(&mut self, filename: &str, file: File) {
+        self.clock += 1;
+        self.files.insert(filename.to_string(), (file, self.clock));
+        self.evict();
+    }
+
+    pub(crate) fn remove(&mut self, filename: &str) {
+        self.files.remove(filename);
+    }
+
+    pub(crate) fn set_max(&mut self, max: usize) {
+        self.max = max.max(1);
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.files.len() > self.max {
+            let lru = self
+                .files
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(name, _)| name.clone())
+                .expect("there are open files");
+            self.files.remove(&lru);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lru_eviction() {
+        let temp_dir = TempDir::new().unwrap();
+        let open = |name: &str| File::create(temp_dir.path().join(name)).unwrap();
+        let mut files = OpenFiles::new(2);
+        files.insert("a", open("a"));
+        files.insert("b", open("b"));
+        assert!(files.get("a").is_some());
+        // b is the least recently used
+        files.insert("c", open("c"));
+        assert!(files.get("b").is_none());
+        assert!(files.get("a").is_some());
+        assert!(files.get("c").is_some());
+
+        files.set_max(1);
+        assert!(files.get("a").is_none());
+        assert!(files.get("c").is_some());
+        files.remove("c");
+        assert!(files.get("c").is_none());
+    }
+}
@@ -1,7 +1,10 @@
 use std::convert::TryInto;
 
+use crate::log::Lsn;
+
 pub struct Page {
     buffer: Vec<u8>,
+    lsn: Lsn,
 }
 
 impl Page {
@@ -9,12 +12,28 @@ impl Page {
     pub fn new(block_size: usize) -> Self {
         Self {
             buffer: vec![0; block_size],
+            lsn: Lsn(0),
         }
     }
 
     // Create a page from existing bytes
     pub fn from_bytes(bytes: Vec<u8>) -> Self {
-        Self { buffer: bytes }
+        Self {
+            buffer: bytes,
+            lsn: Lsn(0),
+        }
+    }
+
+    // The LSN of the latest logged modification of the page.
+    // It is stored with the block, outside of its contents,
+    // so that recovery can tell which modifications the
+    // block on disk already holds.
+    pub fn lsn(&self) -> Lsn {
+        self.lsn
+    }
+
+    pub fn set_lsn(&mut self, lsn: Lsn) {
+        self.lsn = lsn;
     }
 
     pub fn get_int(&self, offset: usize) -> i32 {
@@ -48,10 +67,11 @@ impl Page {
         self.set_bytes(offset, s.as_bytes());
     }
 
-    // get max length needed for a string of given length
+    // The number of bytes needed to store a string
+    // whose UTF-8 encoding is at most strlen bytes long:
+    // 4 bytes for its length, followed by the bytes themselves.
     pub fn max_length(strlen: usize) -> usize {
-        // 4 bytes for length + UTF-8 chars
-        4 + (strlen * 4)
+        4 + strlen
     }
 
     // Similar to Java's contents() but returns mutable slice for direct writing
@@ -86,4 +106,14 @@ mod tests {
         page.set_string(5, test_str);
         assert_eq!(page.get_string(5), test_str);
     }
+
+    #[test]
+    fn test_multibyte_string() {
+        // 2, 3 and 4 byte characters
+        let test_str = "é€😀";
+        assert_eq!(test_str.len(), 9);
+        let mut page = Page::new(Page::max_length(test_str.len()));
+        page.set_string(0, test_str);
+        assert_eq!(page.get_string(0), test_str);
+    }
 }
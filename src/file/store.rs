@@ -0,0 +1,137 @@
+use std::io;
+
+use crate::file::{BlockId, Page};
+
+// The storage that the log and buffer managers read
+// blocks from and write blocks to.
+// FileManager is the storage of a database, whatever its
+// I/O mode; other implementations (such as a remote block
+// server) can be used without changing the managers.
+pub trait BlockStore: Send + Sync {
+    // The size of each block, in bytes.
+    fn block_size(&self) -> usize;
+
+    // Read the block into the page.
+    fn read(&self, block: &BlockId, page: &mut Page) -> io::Result<()>;
+
+    // Write the page to the block, extending the file if needed.
+    fn write(&self, block: &BlockId, page: &mut Page) -> io::Result<()>;
+
+    // Add a zeroed block to the end of the file and return it.
+    fn append(&self, filename: &str) -> io::Result<BlockId>;
+
+    // The number of blocks in the file.
+    fn length(&self, filename: &str) -> io::Result<u64>;
+
+    // Delete the file, if it exists.
+    fn delete_file(&self, filename: &str) -> io::Result<()>;
+
+    // Give the file another name, replacing any file
+    // that has that name.
+    fn rename_file(&self, from: &str, to: &str) -> io::Result<()>;
+
+    // Cut the file down to its first len blocks, durably.
+    fn truncate(&self, filename: &str, len: u64) -> io::Result<()>;
+
+    // Read each block into its page.
+    // A store that can batch requests does so here;
+    // by default the blocks are read one at a time.
+    fn read_all(&self, blocks: &mut [(&BlockId, &mut Page)]) -> io::Result<()> {
+        for (block, page) in blocks {
+            self.read(block, page)?;
+        }
+        Ok(())
+    }
+
+    // Write each page to its block, as by write.
+    fn write_all(&self, blocks: &mut [(&BlockId, &mut Page)]) -> io::Result<()> {
+        for (block, page) in blocks {
+            self.write(block, page)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::BufferManager, file::FileManager, log::LogManager};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    // An in-memory store that counts the blocks written to it.
+    struct CountingStore {
+        inner: FileManager,
+        writes: AtomicUsize,
+    }
+
+    impl BlockStore for CountingStore {
+        fn block_size(&self) -> usize {
+            self.inner.block_size()
+        }
+
+        fn read(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+            self.inner.read(block, page)
+        }
+
+        fn write(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+            self.writes.fetch_add(1, Ordering::Relaxed);
+            self.inner.write(block, page)
+        }
+
+        fn append(&self, filename: &str) -> io::Result<BlockId> {
+            self.inner.append(filename)
+        }
+
+        fn length(&self, filename: &str) -> io::Result<u64> {
+            self.inner.length(filename)
+        }
+
+        fn delete_file(&self, filename: &str) -> io::Result<()> {
+            self.inner.delete_file(filename)
+        }
+
+        fn rename_file(&self, from: &str, to: &str) -> io::Result<()> {
+            self.inner.rename_file(from, to)
+        }
+
+        fn truncate(&self, filename: &str, len: u64) -> io::Result<()> {
+            self.inner.truncate(filename, len)
+        }
+    }
+
+    #[test]
+    fn test_custom_store() -> io::Result<()> {
+        let store = Arc::new(CountingStore {
+            inner: FileManager::in_memory(400),
+            writes: AtomicUsize::new(0),
+        });
+        let lm = Arc::new(Mutex::new(LogManager::new(
+            Arc::clone(&store),
+            "test.log".to_string(),
+        )?));
+        let bm = BufferManager::new(Arc::clone(&store), Arc::clone(&lm), 3);
+
+        let block = store.append("testfile")?;
+        {
+            let buff = bm.pin(block.clone()).unwrap();
+            let mut page = buff.page();
+            page.contents().set_int(80, 42);
+            let lsn = lm.lock().unwrap().append(&[1, 2, 3])?;
+            page.set_modified(1, Some(lsn));
+        }
+        let before = store.writes.load(Ordering::Relaxed);
+        bm.flush_all(1)?;
+        // the log block, then the data block
+        assert_eq!(store.writes.load(Ordering::Relaxed), before + 2);
+
+        let mut page = Page::new(store.block_size());
+        store.read(&block, &mut page)?;
+        assert_eq!(page.get_int(80), 42);
+        let records = lm.lock().unwrap().iter()?.collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(records, [vec![1, 2, 3]]);
+        Ok(())
+    }
+}
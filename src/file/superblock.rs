@@ -0,0 +1,251 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::dir_lock;
+use crate::error::DbError;
+
+// The description of the on-disk format of a database,
+// stored in its own file in the database directory when
+// the database is created, and checked whenever it is
+// opened, so that a database is never read with another
+// block size or by a version that does not understand it.
+// Its layout is the magic number, the format version,
+// the block size, the creation time in seconds and the
+// flags of the optional features used by the database,
+// each big-endian, followed by a CRC32 of those bytes.
+// Version 1 had no flags, before version 3 the stored
+// blocks had no page LSN, and before version 4 the log
+// records had no checksum nor LSN, and encrypted blocks
+// were not bound to their file. A database of a version
+// before 4 cannot be read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Superblock {
+    version: u32,
+    block_size: usize,
+    created: SystemTime,
+    flags: u32,
+}
+
+impl Superblock {
+    pub const FILE: &'static str = "superblock";
+    // The format written by this version.
+    pub const FORMAT_VERSION: u32 = 4;
+    // The oldest format that this version reads.
+    pub const MIN_FORMAT_VERSION: u32 = 4;
+    // The blocks are compressed.
+    pub const COMPRESSED: u32 = 1;
+    // The blocks are encrypted.
+    pub const ENCRYPTED: u32 = 2;
+    const MAGIC: &'static [u8; 8] = b"SIMPLEDB";
+    const SIZE: usize = 8 + 4 + 4 + 8 + 4 + 4;
+    const SIZE_V1: usize = Self::SIZE - 4;
+
+    pub(crate) fn new(block_size: usize) -> Self {
+        // keep whole seconds, as stored
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Superblock {
+            version: Self::FORMAT_VERSION,
+            block_size,
+            created: UNIX_EPOCH + Duration::from_secs(secs),
+            flags: 0,
+        }
+    }
+
+    // Read the superblock of the database in the directory
+    // and check it against the block size, or write a new one
+    // if there is none and the directory is empty. A directory
+    // holding files but no superblock does not hold a database
+    // that this version can read, whatever its block size.
+    pub(crate) fn open(dir: &Path, block_size: usize) -> io::Result<Self> {
+        let path = dir.join(Self::FILE);
+        let superblock = match fs::read(&path) {
+            Ok(bytes) => Self::from_bytes(&bytes).map_err(incompatible)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let in_use = fs::read_dir(dir)?
+                    .flatten()
+                    .any(|entry| entry.file_name() != dir_lock::FILE);
+                if in_use {
+                    return Err(incompatible(DbError::IncompatibleFormat(
+                        "the directory holds files but no superblock".to_string(),
+                    )));
+                }
+                let superblock = Self::new(block_size);
+                superblock.save(dir)?;
+                return Ok(superblock);
+            }
+            Err(e) => return Err(e),
+        };
+        if superblock.block_size != block_size {
+            return Err(incompatible(DbError::IncompatibleFormat(format!(
+                "the database has a block size of {}, not {}",
+                superblock.block_size, block_size
+            ))));
+        }
+        Ok(superblock)
+    }
+
+    // Read the superblock of the database in the directory,
+    // whatever its block size, as tools that inspect a
+    // database need to learn it.
+    pub fn read(dir: &Path) -> io::Result<Self> {
+        let bytes = fs::read(dir.join(Self::FILE))?;
+        Self::from_bytes(&bytes).map_err(incompatible)
+    }
+
+    // Replace the superblock of the database in the directory.
+    pub(crate) fn save(&self, dir: &Path) -> io::Result<()> {
+        // write it whole or not at all
+        let new_path = dir.join(format!("{}.new", Self::FILE));
+        let mut file = File::create(&new_path)?;
+        file.write_all(&self.to_bytes())?;
+        file.sync_all()?;
+        fs::rename(&new_path, dir.join(Self::FILE))
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    // When the database was created.
+    pub fn created(&self) -> SystemTime {
+        self.created
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    // Features are only chosen for an empty database,
+    // which can take the newest format too.
+    pub(crate) fn set_flag(&mut self, flag: u32) {
+        self.flags |= flag;
+        self.version = Self::FORMAT_VERSION;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let secs = self
+            .created
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend_from_slice(Self::MAGIC);
+        // an older superblock is upgraded when it is saved
+        bytes.extend_from_slice(&Self::FORMAT_VERSION.to_be_bytes());
+        bytes.extend_from_slice(&(self.block_size as u32).to_be_bytes());
+        bytes.extend_from_slice(&secs.to_be_bytes());
+        bytes.extend_from_slice(&self.flags.to_be_bytes());
+        let checksum = crc32fast::hash(&bytes);
+        bytes.extend_from_slice(&checksum.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, DbError> {
+        if bytes.len() < Self::MAGIC.len() || &bytes[..Self::MAGIC.len()] != Self::MAGIC {
+            return Err(DbError::IncompatibleFormat(
+                "the directory does not hold a SimpleDB database".to_string(),
+            ));
+        }
+        let int = |pos: usize| u32::from_be_bytes(bytes[pos..pos + 4].try_into().unwrap());
+        let size = match bytes.len() {
+            Self::SIZE_V1 if int(8) == 1 => Self::SIZE_V1,
+            Self::SIZE if int(8) > 1 => Self::SIZE,
+            _ => {
+                return Err(DbError::IncompatibleFormat(
+                    "the superblock is corrupt".to_string(),
+                ))
+            }
+        };
+        if crc32fast::hash(&bytes[..size - 4]) != int(size - 4) {
+            return Err(DbError::IncompatibleFormat(
+                "the superblock is corrupt".to_string(),
+            ));
+        }
+        let version = int(8);
+        if !(Self::MIN_FORMAT_VERSION..=Self::FORMAT_VERSION).contains(&version) {
+            return Err(DbError::IncompatibleFormat(format!(
+                "the database has format version {}, but only {} to {} are supported",
+                version,
+                Self::MIN_FORMAT_VERSION,
+                Self::FORMAT_VERSION
+            )));
+        }
+        let secs = u64::from_be_bytes(bytes[16..24].try_into().unwrap());
+        Ok(Superblock {
+            version,
+            block_size: int(12) as usize,
+            created: UNIX_EPOCH + Duration::from_secs(secs),
+            flags: int(24),
+        })
+    }
+}
+
+fn incompatible(e: DbError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_superblock() {
+        let temp_dir = TempDir::new().unwrap();
+        let superblock = Superblock::open(temp_dir.path(), 400).unwrap();
+        assert_eq!(superblock.version(), Superblock::FORMAT_VERSION);
+        assert_eq!(Superblock::open(temp_dir.path(), 400).unwrap(), superblock);
+
+        let err = DbError::from(Superblock::open(temp_dir.path(), 512).unwrap_err());
+        assert!(matches!(err, DbError::IncompatibleFormat(msg) if msg.contains("400")));
+
+        let mut bytes = superblock.to_bytes();
+        bytes[11] = 9;
+        assert!(Superblock::from_bytes(&bytes).is_err());
+        bytes = superblock.to_bytes();
+        bytes[0] = b'X';
+        assert!(Superblock::from_bytes(&bytes).is_err());
+
+        // the superblocks of older formats are refused
+        let mut v1 = superblock.to_bytes()[..24].to_vec();
+        v1[8..12].copy_from_slice(&1u32.to_be_bytes());
+        v1.extend_from_slice(&crc32fast::hash(&v1).to_be_bytes());
+        let err = Superblock::from_bytes(&v1).unwrap_err();
+        assert!(matches!(err, DbError::IncompatibleFormat(msg) if msg.contains("version 1")));
+        let mut v3 = superblock.to_bytes();
+        v3[8..12].copy_from_slice(&3u32.to_be_bytes());
+        let len = v3.len() - 4;
+        let checksum = crc32fast::hash(&v3[..len]);
+        v3[len..].copy_from_slice(&checksum.to_be_bytes());
+        assert!(Superblock::from_bytes(&v3).is_err());
+
+        let mut superblock = superblock;
+        superblock.set_flag(Superblock::COMPRESSED);
+        superblock.save(temp_dir.path()).unwrap();
+        let reopened = Superblock::open(temp_dir.path(), 400).unwrap();
+        assert!(reopened.has_flag(Superblock::COMPRESSED));
+    }
+
+    #[test]
+    fn test_no_superblock() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(dir_lock::FILE), b"").unwrap();
+        assert!(Superblock::open(temp_dir.path(), 400).is_ok());
+
+        // a directory with files is not given a superblock
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("tblcat.tbl"), [0; 400]).unwrap();
+        let err = DbError::from(Superblock::open(temp_dir.path(), 400).unwrap_err());
+        assert!(matches!(err, DbError::IncompatibleFormat(_)));
+        assert!(!temp_dir.path().join(Superblock::FILE).exists());
+    }
+}
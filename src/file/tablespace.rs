@@ -0,0 +1,106 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Where the files of a database are stored.
+// A tablespace is a named directory, typically on its
+// own disk; each rule assigns the files matching a
+// pattern to a tablespace. A pattern is either a file
+// name, such as "simpledb.log" or "orders.tbl" (the file
+// of table orders), or a prefix followed by '*', such
+// as "temp*" for the temporary tables.
+// The first rule matching a file decides its directory,
+// and the files matching no rule stay in the database
+// directory.
+#[derive(Debug, Clone, Default)]
+pub struct Tablespaces {
+    spaces: Vec<(String, PathBuf)>,
+    rules: Vec<(String, String)>,
+}
+
+impl Tablespaces {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Add a tablespace stored in the directory,
+    // which is created when the file manager opens.
+    pub fn add(&mut self, name: &str, dir: impl AsRef<Path>) -> &mut Self {
+        self.spaces
+            .push((name.to_string(), dir.as_ref().to_path_buf()));
+        self
+    }
+
+    // Store the files matching the pattern in the tablespace.
+    pub fn assign(&mut self, pattern: &str, tablespace: &str) -> &mut Self {
+        self.rules
+            .push((pattern.to_string(), tablespace.to_string()));
+        self
+    }
+
+    // Check that every rule names a tablespace,
+    // and create the directories of the tablespaces.
+    pub(crate) fn create_dirs(&self) -> io::Result<()> {
+        for (pattern, space) in &self.rules {
+            if !self.spaces.iter().any(|(name, _)| name == space) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "the rule for {} names unknown tablespace {}",
+                        pattern, space
+                    ),
+                ));
+            }
+        }
+        for (_, dir) in &self.spaces {
+            fs::create_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    // The directories of the tablespaces.
+    pub(crate) fn dirs(&self) -> impl Iterator<Item = &Path> {
+        self.spaces.iter().map(|(_, dir)| dir.as_path())
+    }
+
+    // The directory of the tablespace of the file,
+    // if a rule assigns it to one.
+    pub(crate) fn dir_of(&self, filename: &str) -> Option<&Path> {
+        let (_, space) =
+            self.rules
+                .iter()
+                .find(|(pattern, _)| match pattern.strip_suffix('*') {
+                    Some(prefix) => filename.starts_with(prefix),
+                    None => filename == pattern,
+                })?;
+        self.spaces
+            .iter()
+            .find(|(name, _)| name == space)
+            .map(|(_, dir)| dir.as_path())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        let mut spaces = Tablespaces::new();
+        spaces
+            .add("fast", "/ssd")
+            .add("scratch", "/tmp/scratch")
+            .assign("orders.tbl", "fast")
+            .assign("temp*", "scratch")
+            .assign("*", "fast");
+        assert_eq!(spaces.dir_of("orders.tbl"), Some(Path::new("/ssd")));
+        assert_eq!(spaces.dir_of("temp3.tbl"), Some(Path::new("/tmp/scratch")));
+        assert_eq!(spaces.dir_of("simpledb.log"), Some(Path::new("/ssd")));
+
+        let mut spaces = Tablespaces::new();
+        spaces.assign("orders.tbl", "fast");
+        assert_eq!(spaces.dir_of("orders.tbl"), None);
+        assert!(spaces.create_dirs().is_err());
+    }
+}
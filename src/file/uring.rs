@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io,
+    os::unix::io::AsRawFd,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+};
+
+use io_uring::{opcode, squeue, types, IoUring};
+
+use crate::file::{manager::AlignedBuf, BlockId, BlockStore, FileManager, Page};
+
+// The number of requests submitted to the kernel at a time.
+const QUEUE_DEPTH: usize = 64;
+
+// A block store that submits batches of reads and writes
+// through an io_uring, so that the blocks of a batch cost
+// one system call per QUEUE_DEPTH of them, plus one sync
+// per file written, instead of a seek, a transfer and a
+// sync apiece. It pays off for the background flusher and
+// for prefetching, which move many blocks at once.
+// The files have the same format as those of a FileManager,
+// which does everything except the batched transfers.
+// Only available on Linux.
+pub struct UringStore {
+    fm: FileManager,
+    ring: Mutex<IoUring>,
+}
+
+impl UringStore {
+    // Create a store for the database in the directory.
+    // Fails if the kernel does not provide io_uring,
+    // or does not allow the process to use it.
+    pub fn new(db_directory: impl AsRef<Path>, block_size: usize) -> io::Result<Self> {
+        let ring = IoUring::new(QUEUE_DEPTH as u32)?;
+        Ok(Self {
+            fm: FileManager::new(db_directory, block_size)?,
+            ring: Mutex::new(ring),
+        })
+    }
+
+    pub fn file_manager(&self) -> &FileManager {
+        &self.fm
+    }
+
+    // Submit the requests, QUEUE_DEPTH at a time, and wait for them.
+    // Each request must transfer the number of bytes in lens
+    // at its position, its user data; a short transfer fails.
+    fn run(&self, requests: &[squeue::Entry], lens: &[usize]) -> io::Result<()> {
+        let mut ring = self.lock_ring()?;
+        for batch in requests.chunks(QUEUE_DEPTH) {
+            for entry in batch {
+                // SAFETY: the buffers and files of the requests
+                // outlive the call, which waits for all of them.
+                unsafe { ring.submission().push(entry) }
+                    .map_err(|_| io::Error::other("submission queue is full"))?;
+            }
+            ring.submit_and_wait(batch.len())?;
+            let mut error = None;
+            for cqe in ring.completion() {
+                let expected = lens[cqe.user_data() as usize];
+                let result = cqe.result();
+                if result < 0 {
+                    error.get_or_insert(io::Error::from_raw_os_error(-result));
+                } else if (result as usize) < expected {
+                    error.get_or_insert(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "failed to transfer whole block",
+                    ));
+                }
+            }
+            if let Some(e) = error {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    // The open files of the blocks, by name.
+    fn files(&self, blocks: &[(&BlockId, &mut Page)]) -> io::Result<HashMap<String, File>> {
+        let mut files = HashMap::new();
+        for (block, _) in blocks {
+            if !files.contains_key(block.filename()) {
+                let file = self.fm.get_file(block.filename())?;
+                files.insert(block.filename().to_string(), file);
+            }
+        }
+        Ok(files)
+    }
+
+    fn lock_ring(&self) -> io::Result<MutexGuard<'_, IoUring>> {
+        self.ring
+            .lock()
+            .map_err(|_| io::Error::other("failed to acquire lock"))
+    }
+}
+
+impl BlockStore for UringStore {
+    fn block_size(&self) -> usize {
+        self.fm.block_size()
+    }
+
+    fn read(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        self.read_all(&mut [(block, page)])
+    }
+
+    fn write(&self, block: &BlockId, page: &mut Page) -> io::Result<()> {
+        self.write_all(&mut [(block, page)])
+    }
+
+    fn append(&self, filename: &str) -> io::Result<BlockId> {
+        self.fm.append(filename)
+    }
+
+    fn length(&self, filename: &str) -> io::Result<u64> {
+        self.fm.length(filename)
+    }
+
+    fn delete_file(&self, filename: &str) -> io::Result<()> {
+        self.fm.delete_file(filename)
+    }
+
+    fn rename_file(&self, from: &str, to: &str) -> io::Result<()> {
+        self.fm.rename_file(from, to)
+    }
+
+    fn truncate(&self, filename: &str, len: u64) -> io::Result<()> {
+        self.fm.truncate(filename, len)
+    }
+
+    fn read_all(&self, blocks: &mut [(&BlockId, &mut Page)]) -> io::Result<()> {
+        let files = self.files(blocks)?;
+        let mut stored: Vec<_> = blocks.iter().map(|_| self.fm.stored_block()).collect();
+        let lens: Vec<usize> = stored.iter().map(|buf| buf.len()).collect();
+        let requests: Vec<squeue::Entry> = blocks
+            .iter()
+            .zip(stored.iter_mut())
+            .enumerate()
+            .map(|(i, ((block, _), buf))| {
+                let fd = types::Fd(files[block.filename()].as_raw_fd());
+                opcode::Read::new(fd, buf.as_mut_ptr(), buf.len() as u32)
+                    .offset(self.fm.offset(block))
+                    .build()
+                    .user_data(i as u64)
+            })
+            .collect();
+        self.run(&requests, &lens)?;
+        for ((block, page), buf) in blocks.iter_mut().zip(&stored) {
+            self.fm.load(block, buf, page)?;
+        }
+        Ok(())
+    }
+
+    // The blocks are written in any order, and then
+    // each of their files is synced.
+    fn write_all(&self, blocks: &mut [(&BlockId, &mut Page)]) -> io::Result<()> {
+        let files = self.files(blocks)?;
+        let stored: Vec<AlignedBuf> = blocks
+            .iter_mut()
+            .map(|(block, page)| {
+                let lsn = page.lsn();
+                self.fm.with_checksum(block, page.contents(), lsn)
+            })
+            .collect::<io::Result<_>>()?;
+        let copies: Vec<(&BlockId, &[u8])> = blocks
+            .iter()
+            .zip(&stored)
+            .map(|((block, _), buf)| (*block, &buf[..]))
+            .collect();
+        let _area = self.fm.protect(&copies)?;
+        let lens: Vec<usize> = stored.iter().map(|buf| buf.len()).collect();
+        let requests: Vec<squeue::Entry> = blocks
+            .iter()
+            .zip(&stored)
+            .enumerate()
+            .map(|(i, ((block, _), buf))| {
+                let fd = types::Fd(files[block.filename()].as_raw_fd());
+                opcode::Write::new(fd, buf.as_ptr(), buf.len() as u32)
+                    .offset(self.fm.offset(block))
+                    .build()
+                    .user_data(i as u64)
+            })
+            .collect();
+        self.run(&requests, &lens)?;
+        for (block, _) in blocks.iter() {
+            self.fm.note_written(block)?;
+        }
+
+        let syncs: Vec<squeue::Entry> = files
+            .values()
+            .enumerate()
+            .map(|(i, file)| {
+                opcode::Fsync::new(types::Fd(file.as_raw_fd()))
+                    .flags(types::FsyncFlags::DATASYNC)
+                    .build()
+                    .user_data(i as u64)
+            })
+            .collect();
+        self.run(&syncs, &vec![0; syncs.len()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buffer::BufferManager, log::LogManager};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_uring_store() -> io::Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let store = match UringStore::new(temp_dir.path(), 400) {
+            Ok(store) => store,
+            // the kernel or a sandbox may not allow io_uring
+            Err(_) => return Ok(()),
+        };
+        let blocks: Vec<BlockId> = (0..100)
+            .map(|i| BlockId::new(format!("file{}", i % 3), i / 3))
+            .collect();
+        let mut pages: Vec<Page> = blocks.iter().map(|_| Page::new(400)).collect();
+        for (i, page) in pages.iter_mut().enumerate() {
+            page.set_int(0, i as i32);
+        }
+        let mut writes: Vec<(&BlockId, &mut Page)> = blocks.iter().zip(&mut pages).collect();
+        store.write_all(&mut writes)?;
+        assert_eq!(store.length("file0")?, 34);
+
+        // the files can be read back by a file manager
+        let mut page = Page::new(400);
+        store.file_manager().read(&blocks[40], &mut page)?;
+        assert_eq!(page.get_int(0), 40);
+
+        let mut pages: Vec<Page> = blocks.iter().map(|_| Page::new(400)).collect();
+        let mut reads: Vec<(&BlockId, &mut Page)> = blocks.iter().zip(&mut pages).collect();
+        store.read_all(&mut reads)?;
+        for (i, page) in pages.iter().enumerate() {
+            assert_eq!(page.get_int(0), i as i32);
+        }
+
+        // reading past the end of a file fails
+        let block = BlockId::new("file0", 34);
+        assert!(store.read(&block, &mut page).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_uring_buffer_pool() -> io::Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let Ok(store) = UringStore::new(temp_dir.path(), 400) else {
+            return Ok(());
+        };
+        let store = Arc::new(store);
+        let lm = Arc::new(Mutex::new(LogManager::new(
+            Arc::clone(&store),
+            "test.log".to_string(),
+        )?));
+        let bm = BufferManager::new(Arc::clone(&store), Arc::clone(&lm), 8);
+        let blocks: Vec<BlockId> = (0..8)
+            .map(|_| store.append("testfile"))
+            .collect::<Result<_, _>>()?;
+        let pinned = bm.pin_all(&blocks).unwrap();
+        for (i, buff) in pinned.iter().enumerate() {
+            let mut page = buff.page();
+            page.contents().set_int(0, i as i32);
+            let lsn = lm.lock().unwrap().append(&[i as u8])?;
+            page.set_modified(1, Some(lsn));
+        }
+        assert_eq!(bm.flush_unpinned()?, 0);
+        drop(pinned);
+        assert_eq!(bm.flush_unpinned()?, 8);
+        assert_eq!(bm.prefetch(&blocks).unwrap(), 0);
+
+        drop((bm, lm, store));
+        let fm = FileManager::new(temp_dir.path(), 400)?;
+        let mut page = Page::new(400);
+        fm.read(&blocks[5], &mut page)?;
+        assert_eq!(page.get_int(0), 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use super::Index;
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan, UpdateScan},
+    record::{Layout, Rid, TableScan},
+    tx::Transaction,
+};
+
+// A static hash implementation of the Index interface.
+// A fixed number of buckets is allocated (currently, 100),
+// and each bucket is implemented as a file of index records.
+pub struct HashIndex {
+    tx: Arc<Transaction>,
+    idxname: String,
+    layout: Layout,
+    search_key: Option<Constant>,
+    ts: Option<TableScan>,
+}
+
+impl HashIndex {
+    pub const NUM_BUCKETS: i32 = 100;
+
+    // Open a hash index for the specified index.
+    pub fn new(tx: Arc<Transaction>, idxname: &str, layout: Layout) -> Self {
+        HashIndex {
+            tx,
+            idxname: idxname.to_string(),
+            layout,
+            search_key: None,
+            ts: None,
+        }
+    }
+
+    // Return the cost of searching an index file having the
+    // specified number of blocks.
+    // The method assumes that all buckets are about the
+    // same size, and so the cost is simply the size of
+    // the bucket.
+    pub fn search_cost(numblocks: u64, _rpb: u64) -> u64 {
+        numblocks / Self::NUM_BUCKETS as u64
+    }
+}
+
+impl Index for HashIndex {
+    // Position the index before the first index record
+    // having the specified search key.
+    // The method hashes the search key to determine the bucket,
+    // and then opens a table scan on the file
+    // corresponding to the bucket.
+    // The table scan for the previous bucket (if any) is closed.
+    fn before_first(&mut self, search_key: &Constant) -> DbResult<()> {
+        self.close();
+        let bucket = search_key.hash_code().rem_euclid(Self::NUM_BUCKETS);
+        let tblname = format!("{}{}", self.idxname, bucket);
+        self.ts = Some(TableScan::new(
+            Arc::clone(&self.tx),
+            &tblname,
+            self.layout.clone(),
+        )?);
+        self.search_key = Some(search_key.clone());
+        Ok(())
+    }
+
+    // Move to the next record having the search key.
+    // The method loops through the table scan for the bucket,
+    // looking for a matching record, and returning false
+    // if there are no more such records.
+    fn next(&mut self) -> DbResult<bool> {
+        let ts = self.ts.as_mut().expect("index is not positioned");
+        let search_key = self.search_key.as_ref().expect("index is not positioned");
+        while ts.next()? {
+            if &ts.get_val("dataval")? == search_key {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // Retrieve the dataRID from the current record
+    // in the table scan for the bucket.
+    fn get_data_rid(&self) -> DbResult<Rid> {
+        let ts = self.ts.as_ref().expect("index is not positioned");
+        let blknum = ts.get_int("block")?;
+        let id = ts.get_int("id")?;
+        Ok(Rid::new(blknum as u64, id))
+    }
+
+    // Insert a new record into the table scan for the bucket.
+    fn insert(&mut self, dataval: &Constant, datarid: Rid) -> DbResult<()> {
+        self.before_first(dataval)?;
+        let ts = self.ts.as_mut().expect("index is not positioned");
+        ts.insert()?;
+        ts.set_int("block", datarid.block_number() as i32)?;
+        ts.set_int("id", datarid.slot())?;
+        ts.set_val("dataval", dataval)?;
+        Ok(())
+    }
+
+    // Delete the specified record from the table scan for
+    // the bucket. The method starts at the beginning of the
+    // scan, and loops through the records until the
+    // specified record is found.
+    fn delete(&mut self, dataval: &Constant, datarid: Rid) -> DbResult<()> {
+        self.before_first(dataval)?;
+        while self.next()? {
+            if self.get_data_rid()? == datarid {
+                let ts = self.ts.as_mut().expect("index is not positioned");
+                ts.delete()?;
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    // Cut the file of each bucket down to nothing when the
+    // transaction commits. The buckets never used have
+    // no blocks to cut off.
+    fn truncate(&mut self) -> DbResult<()> {
+        self.close();
+        for bucket in 0..Self::NUM_BUCKETS {
+            let filename = format!("{}{}.tbl", self.idxname, bucket);
+            if self.tx.size(&filename)? > 0 {
+                self.tx.truncate(&filename, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Close the index by closing the current table scan.
+    fn close(&mut self) {
+        if let Some(mut ts) = self.ts.take() {
+            ts.close();
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use super::Index;
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan, UpdateScan},
+    record::TableScan,
+};
+
+// The scan class corresponding to the indexjoin relational
+// algebra operator.
+// The code is very similar to that of ProductScan,
+// which makes sense because an index join is essentially
+// the product of each LHS record with the matching RHS index records.
+pub struct IndexJoinScan<S> {
+    lhs: S,
+    idx: Box<dyn Index>,
+    joinfield: String,
+    rhs: TableScan,
+    lhs_positioned: bool,
+}
+
+impl<S: Scan> IndexJoinScan<S> {
+    // Create an index join scan for the specified LHS scan and
+    // RHS index.
+    pub fn new(lhs: S, idx: Box<dyn Index>, joinfield: &str, rhs: TableScan) -> DbResult<Self> {
+        let mut scan = IndexJoinScan {
+            lhs,
+            idx,
+            joinfield: joinfield.to_string(),
+            rhs,
+            lhs_positioned: false,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+
+    fn reset_index(&mut self) -> DbResult<()> {
+        let searchkey = self.lhs.get_val(&self.joinfield)?;
+        self.idx.before_first(&searchkey)
+    }
+}
+
+impl<S: Scan> Scan for IndexJoinScan<S> {
+    // Position the scan before the first record.
+    // That is, the LHS scan will be positioned at its
+    // first record, and the index will be positioned
+    // before the first record for the join value.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.lhs.before_first()?;
+        self.lhs_positioned = self.lhs.next()?;
+        if self.lhs_positioned {
+            self.reset_index()?;
+        }
+        Ok(())
+    }
+
+    // Move the scan to the next record.
+    // The method moves to the next index record, if possible.
+    // Otherwise, it moves to the next LHS record and the
+    // first index record.
+    // If there are no more LHS records, the method returns false.
+    fn next(&mut self) -> DbResult<bool> {
+        while self.lhs_positioned {
+            if self.idx.next()? {
+                let rid = self.idx.get_data_rid()?;
+                self.rhs.move_to_rid(rid)?;
+                return Ok(true);
+            }
+            self.lhs_positioned = self.lhs.next()?;
+            if self.lhs_positioned {
+                self.reset_index()?;
+            }
+        }
+        Ok(false)
+    }
+
+    // Return the value of the specified field.
+    // The value is obtained from whichever scan
+    // contains the field.
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        if self.rhs.has_field(fldname) {
+            self.rhs.get_int(fldname)
+        } else {
+            self.lhs.get_int(fldname)
+        }
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        if self.rhs.has_field(fldname) {
+            self.rhs.get_string(fldname)
+        } else {
+            self.lhs.get_string(fldname)
+        }
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        if self.rhs.has_field(fldname) {
+            self.rhs.get_val(fldname)
+        } else {
+            self.lhs.get_val(fldname)
+        }
+    }
+
+    // Return true if the field is in the schema.
+    fn has_field(&self, fldname: &str) -> bool {
+        self.rhs.has_field(fldname) || self.lhs.has_field(fldname)
+    }
+
+    // Close the scan by closing its LHS scan and its RHS index.
+    fn close(&mut self) {
+        self.lhs.close();
+        self.idx.close();
+        self.rhs.close();
+    }
+}
@@ -0,0 +1,73 @@
+use super::Index;
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan, UpdateScan},
+    record::TableScan,
+};
+
+// The scan class corresponding to the select relational
+// algebra operator, when the selection constant is
+// compared against an indexed field.
+pub struct IndexSelectScan {
+    ts: TableScan,
+    idx: Box<dyn Index>,
+    val: Constant,
+}
+
+impl IndexSelectScan {
+    // Create an index select scan for the specified
+    // index and selection constant.
+    pub fn new(ts: TableScan, idx: Box<dyn Index>, val: Constant) -> DbResult<Self> {
+        let mut scan = IndexSelectScan { ts, idx, val };
+        scan.before_first()?;
+        Ok(scan)
+    }
+}
+
+impl Scan for IndexSelectScan {
+    // Position the scan before the first record,
+    // which in this case means positioning the index
+    // before the first instance of the selection constant.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.idx.before_first(&self.val)
+    }
+
+    // Move to the next record, which in this case means
+    // moving the index to the next record satisfying the
+    // selection constant, and returning false if there are
+    // no more such index records.
+    // If there is a next record, the method moves the
+    // table scan to the corresponding data record.
+    fn next(&mut self) -> DbResult<bool> {
+        if !self.idx.next()? {
+            return Ok(false);
+        }
+        let rid = self.idx.get_data_rid()?;
+        self.ts.move_to_rid(rid)?;
+        Ok(true)
+    }
+
+    // Return the value of the field of the current data record.
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.ts.get_int(fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        self.ts.get_string(fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        self.ts.get_val(fldname)
+    }
+
+    // Return whether the data record has the specified field.
+    fn has_field(&self, fldname: &str) -> bool {
+        self.ts.has_field(fldname)
+    }
+
+    // Close the scan by closing the index and the table scan.
+    fn close(&mut self) {
+        self.idx.close();
+        self.ts.close();
+    }
+}
@@ -0,0 +1,38 @@
+mod hash_index;
+mod index_join_scan;
+mod index_select_scan;
+
+pub use hash_index::HashIndex;
+pub use index_join_scan::IndexJoinScan;
+pub use index_select_scan::IndexSelectScan;
+
+use crate::{error::DbResult, query::Constant, record::Rid};
+
+// The interface that contains methods to traverse an index.
+pub trait Index {
+    // Position the index before the first record
+    // having the specified search key.
+    fn before_first(&mut self, search_key: &Constant) -> DbResult<()>;
+
+    // Move the index to the next record having the
+    // search key specified in the before_first method.
+    // Return false if there are no more such index records.
+    fn next(&mut self) -> DbResult<bool>;
+
+    // Return the dataRID value stored in the current index record.
+    fn get_data_rid(&self) -> DbResult<Rid>;
+
+    // Insert an index record having the specified
+    // dataval and dataRID values.
+    fn insert(&mut self, dataval: &Constant, datarid: Rid) -> DbResult<()>;
+
+    // Delete the index record having the specified
+    // dataval and dataRID values.
+    fn delete(&mut self, dataval: &Constant, datarid: Rid) -> DbResult<()>;
+
+    // Remove every index record, once the transaction commits.
+    fn truncate(&mut self) -> DbResult<()>;
+
+    // Close the index.
+    fn close(&mut self);
+}
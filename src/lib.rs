@@ -1,10 +1,24 @@
 pub mod buffer;
 pub mod db;
+pub mod db_manager;
+pub mod driver;
+pub mod error;
 pub mod file;
+pub mod index;
 pub mod log;
+pub mod materialize;
+pub mod metadata;
+pub mod multibuffer;
+pub mod network;
+pub mod parse;
+pub mod plan;
+pub mod query;
+pub mod record;
 pub mod tx;
 
 pub use db::SimpleDB;
+pub use db_manager::DatabaseManager;
+pub use error::{DbError, DbResult};
 pub use file::{BlockId, FileManager};
 
 #[cfg(test)]
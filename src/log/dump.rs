@@ -0,0 +1,233 @@
+use std::{fmt, io, sync::Arc};
+
+use serde::Serialize;
+
+use crate::{
+    file::{BlockId, BlockStore},
+    log::{ForwardLogIterator, Lsn},
+    tx::recovery::LogRecord,
+};
+
+/// A log record decoded for reading by a person,
+/// as printed by the log-dump command.
+/// The fields that a kind of record lacks are None.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DumpedRecord {
+    pub lsn: u64,
+    /// The log block holding the record.
+    pub block: u64,
+    /// The kind of record, such as "start" or "setint",
+    /// or "unknown" if its operator is not recognized.
+    pub op: &'static str,
+    pub txnum: Option<i32>,
+    /// The modified block, as "file:number",
+    /// or the first block that a truncate cuts off.
+    pub target: Option<String>,
+    pub offset: Option<usize>,
+    /// The value before the modification.
+    pub value: Option<String>,
+    /// The value after the modification.
+    pub new_value: Option<String>,
+    /// The LSN of the update undone by a compensation record.
+    pub undone: Option<u64>,
+    /// The time of a commit, in milliseconds since the Unix epoch.
+    pub time: Option<u64>,
+}
+
+impl DumpedRecord {
+    pub fn new(lsn: Lsn, block: u64, bytes: Vec<u8>) -> Self {
+        let mut dumped = DumpedRecord {
+            lsn: lsn.0,
+            block,
+            op: "unknown",
+            txnum: None,
+            target: None,
+            offset: None,
+            value: None,
+            new_value: None,
+            undone: None,
+            time: None,
+        };
+        let Some(rec) = LogRecord::from_bytes(bytes) else {
+            return dumped;
+        };
+        dumped.op = match &rec {
+            LogRecord::Checkpoint { .. } => "checkpoint",
+            LogRecord::Start { .. } => "start",
+            LogRecord::Commit { time, .. } => {
+                dumped.time = Some(*time);
+                "commit"
+            }
+            LogRecord::Rollback { .. } => "rollback",
+            LogRecord::SetInt { .. } => "setint",
+            LogRecord::SetString { .. } => "setstring",
+            LogRecord::SetBytes { .. } => "setbytes",
+            LogRecord::Compensation { undone, update, .. } => {
+                dumped.undone = Some(undone.0);
+                dumped.describe_update(update);
+                "compensation"
+            }
+            LogRecord::Truncate { .. } => "truncate",
+        };
+        dumped.describe_update(&rec);
+        if !matches!(rec, LogRecord::Checkpoint { .. }) {
+            dumped.txnum = Some(rec.txnum());
+        }
+        dumped
+    }
+
+    // Fill in the modified block, the offset and the values
+    // of an update record.
+    fn describe_update(&mut self, rec: &LogRecord) {
+        let hex = |val: &[u8]| val.iter().map(|b| format!("{:02x}", b)).collect();
+        let (block, offset, old, new) = match rec {
+            LogRecord::SetInt {
+                block,
+                offset,
+                old,
+                new,
+                ..
+            } => (block, offset, old.to_string(), new.to_string()),
+            LogRecord::SetString {
+                block,
+                offset,
+                old,
+                new,
+                ..
+            } => (block, offset, format!("{:?}", old), format!("{:?}", new)),
+            LogRecord::SetBytes {
+                block,
+                offset,
+                old,
+                new,
+                ..
+            } => (block, offset, hex(old), hex(new)),
+            LogRecord::Truncate { filename, len, .. } => {
+                self.target = Some(format!("{}:{}", filename, len));
+                return;
+            }
+            _ => return,
+        };
+        self.target = Some(format!("{}:{}", block.filename(), block.number()));
+        self.offset = Some(*offset);
+        self.value = Some(old);
+        self.new_value = Some(new);
+    }
+
+    /// The record as a line of JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a dumped record serializes")
+    }
+}
+
+impl fmt::Display for DumpedRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>8} {:>6}  {:<10}", self.lsn, self.block, self.op)?;
+        if let Some(txnum) = self.txnum {
+            write!(f, " tx={}", txnum)?;
+        }
+        if let Some(target) = &self.target {
+            write!(f, " block={}", target)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " offset={}", offset)?;
+        }
+        if let Some(value) = &self.value {
+            write!(f, " old={}", value)?;
+        }
+        if let Some(value) = &self.new_value {
+            write!(f, " new={}", value)?;
+        }
+        if let Some(undone) = self.undone {
+            write!(f, " undone={}", undone)?;
+        }
+        if let Some(time) = self.time {
+            write!(f, " time={}", time)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes every record of the log file, oldest first.
+/// The log is only read, so a database that is not
+/// running can be inspected without changing it.
+pub fn dump<S: BlockStore>(fm: Arc<S>, logfile: &str) -> io::Result<Vec<DumpedRecord>> {
+    let len = fm.length(logfile)?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let mut iter = ForwardLogIterator::new(fm, BlockId::new(logfile, 0), len - 1, Lsn(0))?;
+    let mut records = Vec::new();
+    while let Some(bytes) = iter.next() {
+        records.push(DumpedRecord::new(iter.lsn(), iter.block(), bytes?));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SimpleDB;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_dump() -> io::Result<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx().unwrap();
+        let blk = tx.append("testfile").unwrap();
+        tx.pin(&blk).unwrap();
+        tx.set_int(&blk, 80, 42, true).unwrap();
+        tx.set_string(&blk, 40, "one", true).unwrap();
+        tx.set_string(&blk, 40, "two", true).unwrap();
+        tx.commit().unwrap();
+        let tx = db.new_tx().unwrap();
+        tx.pin(&blk).unwrap();
+        tx.set_int(&blk, 80, 7, true).unwrap();
+        tx.rollback().unwrap();
+        db.log_manager().lock().unwrap().append(&[9, 9, 9, 9])?;
+        db.log_manager().lock().unwrap().flush_all()?;
+
+        let records = dump(db.file_manager(), SimpleDB::LOG_FILE)?;
+        let ops: Vec<_> = records.iter().map(|rec| rec.op).collect();
+        assert_eq!(
+            ops,
+            [
+                "start",
+                "setint",
+                "setstring",
+                "setstring",
+                "commit",
+                "start",
+                "setint",
+                "compensation",
+                "rollback",
+                "unknown"
+            ]
+        );
+        assert_eq!(records[3].value.as_deref(), Some("\"one\""));
+        assert_eq!(records[3].new_value.as_deref(), Some("\"two\""));
+        assert_eq!(records[7].undone, Some(records[6].lsn));
+        assert_eq!(records[7].value.as_deref(), Some("7"));
+        assert_eq!(records[7].new_value.as_deref(), Some("42"));
+        assert!(records[4].time.is_some_and(|time| time > 0));
+        assert_eq!(records[1].target.as_deref(), Some("testfile:0"));
+        assert_eq!(records[1].to_string(), {
+            let txnum = records[1].txnum.unwrap();
+            format!(
+                "{:>8} {:>6}  setint     tx={} block=testfile:0 offset=80 old=0 new=42",
+                records[1].lsn, 0, txnum
+            )
+        });
+        assert_eq!(
+            records[9].to_json(),
+            format!(
+                "{{\"lsn\":{},\"block\":{},\"op\":\"unknown\",\"txnum\":null,\
+                 \"target\":null,\"offset\":null,\"value\":null,\
+                 \"new_value\":null,\"undone\":null,\"time\":null}}",
+                records[9].lsn, records[9].block
+            )
+        );
+        Ok(())
+    }
+}
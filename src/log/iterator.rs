@@ -1,19 +1,32 @@
-use crate::file::{BlockId, FileManager, Page};
+use crate::file::{BlockId, BlockStore, FileManager, Page};
+use crate::log::Lsn;
 use std::io;
 use std::sync::Arc;
 
-pub struct LogIterator {
-    fm: Arc<FileManager>,
+const INT_SIZE: usize = std::mem::size_of::<i32>();
+pub(super) const CHECKSUM_SIZE: usize = std::mem::size_of::<u32>();
+// Each log block starts with the position of its newest
+// record (the boundary), followed by the LSN of its first
+// record, which is what a search for an LSN reads.
+pub(super) const HEADER_SIZE: usize = INT_SIZE + Lsn::SIZE;
+// The bit set in the length of a record whose bytes are
+// compressed; the other bits are the length of the bytes
+// as stored.
+pub(super) const COMPRESSED: u32 = 1 << 31;
+
+pub struct LogIterator<S: BlockStore = FileManager> {
+    fm: Arc<S>,
     block: BlockId,
     page: Page,
     current_pos: usize,
     boundary: usize,
+    lsn: Lsn,
 }
 
 /// A class that provides the ability to move through the
 /// records of the log file in reverse order
-impl LogIterator {
-    pub fn new(fm: Arc<FileManager>, block: BlockId) -> Result<Self, io::Error> {
+impl<S: BlockStore> LogIterator<S> {
+    pub fn new(fm: Arc<S>, block: BlockId) -> Result<Self, io::Error> {
         let page = Page::new(fm.block_size());
 
         let mut iterator = Self {
@@ -22,24 +35,79 @@ impl LogIterator {
             page,
             current_pos: 0,
             boundary: 0,
+            lsn: Lsn(0),
         };
 
         iterator.move_to_block()?;
         Ok(iterator)
     }
 
+    /// Returns the LSN of the record last returned,
+    /// or LSN 0 if none has been.
+    pub fn lsn(&self) -> Lsn {
+        self.lsn
+    }
+
+    /// Passes over the records of the current block
+    /// that are newer than the LSN.
+    pub(super) fn skip_after(&mut self, lsn: Lsn) {
+        while self.current_pos < self.fm.block_size() {
+            if record_lsn(self.page.contents(), self.current_pos) <= lsn {
+                break;
+            }
+            self.current_pos = record_end_pos(self.page.contents(), self.current_pos);
+        }
+    }
+
     /// Moves the specified log block
     /// and positions it at the first record in that block
     /// (i.e., the most recent one)
     fn move_to_block(&mut self) -> Result<(), io::Error> {
         self.fm.read(&self.block, &mut self.page)?;
-        self.boundary = self.page.get_int(0) as usize;
+        self.boundary = intact_start(self.page.contents());
         self.current_pos = self.boundary;
         Ok(())
     }
 }
 
-impl Iterator for LogIterator {
+/// Returns the position of the most recent intact record
+/// in a log page, or the page size if there is none.
+/// Each record is its LSN, length and bytes, followed by a CRC of them.
+/// If the write of the page was torn, the newest records
+/// can fail their checksum; they are passed over, and the
+/// records after them, which are intact, are kept.
+/// The records after a position are intact when each one
+/// passes its checksum and they exactly fill the page.
+pub(super) fn intact_start(buf: &[u8]) -> usize {
+    let end = buf.len();
+    let boundary = i32::from_be_bytes(buf[..INT_SIZE].try_into().unwrap());
+    let boundary = usize::try_from(boundary).map_or(end, |b| b.clamp(HEADER_SIZE, end));
+    let mut intact = vec![false; end + 1];
+    intact[end] = true;
+    let mut start = end;
+    for pos in (boundary..end).rev() {
+        if record_end(buf, pos, &intact).is_some() {
+            intact[pos] = true;
+            start = pos;
+        }
+    }
+    start
+}
+
+// The end of the record at pos, if it passes its checksum
+// and is followed by intact records.
+fn record_end(buf: &[u8], pos: usize, intact: &[bool]) -> Option<usize> {
+    let (len, _) = stored_length(buf, pos)?;
+    let crc_pos = pos.checked_add(Lsn::SIZE + INT_SIZE + len)?;
+    let end = crc_pos.checked_add(CHECKSUM_SIZE)?;
+    if !intact.get(end).copied().unwrap_or(false) {
+        return None;
+    }
+    let checksum = crc32fast::hash(&buf[pos..crc_pos]).to_be_bytes();
+    (buf[crc_pos..end] == checksum).then_some(end)
+}
+
+impl<S: BlockStore> Iterator for LogIterator<S> {
     type Item = Result<Vec<u8>, std::io::Error>;
 
     /// Move to the next log record in the block
@@ -47,7 +115,7 @@ impl Iterator for LogIterator {
     /// then move to the previous block
     /// and return the log record from there.
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_pos >= self.fm.block_size() {
+        while self.current_pos >= self.fm.block_size() {
             if self.block.number() == 0 {
                 return None;
             }
@@ -57,8 +125,135 @@ impl Iterator for LogIterator {
             }
         }
 
-        let bytes = self.page.get_bytes(self.current_pos);
-        self.current_pos += std::mem::size_of::<i32>() + bytes.len();
+        let (lsn, bytes, end) = read_record(&mut self.page, self.current_pos);
+        self.lsn = lsn;
+        self.current_pos = end;
         Some(Ok(bytes))
     }
 }
+
+/// Returns the LSN of the first record of a log page,
+/// or of the record that will be first if it has none.
+pub(super) fn first_lsn(page: &mut Page) -> Lsn {
+    let bytes = &page.contents()[INT_SIZE..HEADER_SIZE];
+    Lsn::from_bytes(bytes.try_into().unwrap())
+}
+
+// Read the record at pos in the page, returning its LSN,
+// its bytes and the position where it ends.
+pub(super) fn read_record(page: &mut Page, pos: usize) -> (Lsn, Vec<u8>, usize) {
+    let buf = page.contents();
+    let (len, compressed) = stored_length(buf, pos).expect("record is intact");
+    let start = pos + Lsn::SIZE + INT_SIZE;
+    let stored = &buf[start..start + len];
+    let bytes = if compressed {
+        // the checksum passed, so the bytes decompress
+        lz4_flex::decompress_size_prepended(stored).unwrap_or_default()
+    } else {
+        stored.to_vec()
+    };
+    (record_lsn(buf, pos), bytes, record_end_pos(buf, pos))
+}
+
+fn record_lsn(buf: &[u8], pos: usize) -> Lsn {
+    Lsn::from_bytes(buf[pos..pos + Lsn::SIZE].try_into().unwrap())
+}
+
+// The position where the intact record at pos ends.
+fn record_end_pos(buf: &[u8], pos: usize) -> usize {
+    let (len, _) = stored_length(buf, pos).expect("record is intact");
+    pos + Lsn::SIZE + INT_SIZE + len + CHECKSUM_SIZE
+}
+
+// The length of the bytes of the record at pos,
+// as stored, and whether they are compressed.
+fn stored_length(buf: &[u8], pos: usize) -> Option<(usize, bool)> {
+    let len = buf.get(pos + Lsn::SIZE..pos + Lsn::SIZE + INT_SIZE)?;
+    let len = u32::from_be_bytes(len.try_into().unwrap());
+    Some(((len & !COMPRESSED) as usize, len & COMPRESSED != 0))
+}
+
+/// Moves through the records of the log file in the order
+/// they were appended, from the specified block to the
+/// specified last block.
+/// Records before the starting LSN are skipped.
+pub struct ForwardLogIterator<S: BlockStore = FileManager> {
+    fm: Arc<S>,
+    block: BlockId,
+    last: u64,
+    page: Page,
+    // the positions of the records not yet returned
+    // from the current block, the oldest last
+    positions: Vec<usize>,
+    start: Lsn,
+    lsn: Lsn,
+}
+
+impl<S: BlockStore> ForwardLogIterator<S> {
+    pub fn new(fm: Arc<S>, first: BlockId, last: u64, start: Lsn) -> Result<Self, io::Error> {
+        let page = Page::new(fm.block_size());
+        let mut iterator = Self {
+            fm,
+            block: first,
+            last,
+            page,
+            positions: Vec::new(),
+            start,
+            lsn: Lsn(0),
+        };
+        iterator.move_to_block()?;
+        Ok(iterator)
+    }
+
+    /// Returns the LSN of the record last returned,
+    /// or LSN 0 if none has been.
+    pub fn lsn(&self) -> Lsn {
+        self.lsn
+    }
+
+    /// Returns the number of the block holding
+    /// the record last returned.
+    pub fn block(&self) -> u64 {
+        self.block.number()
+    }
+
+    /// Reads the current block and finds its records.
+    /// As their lengths precede them, the records are found
+    /// newest first and then returned in reverse.
+    fn move_to_block(&mut self) -> Result<(), io::Error> {
+        self.fm.read(&self.block, &mut self.page)?;
+        self.positions.clear();
+        let mut pos = intact_start(self.page.contents());
+        while pos < self.fm.block_size() {
+            self.positions.push(pos);
+            pos = record_end_pos(self.page.contents(), pos);
+        }
+        Ok(())
+    }
+}
+
+impl<S: BlockStore> Iterator for ForwardLogIterator<S> {
+    type Item = Result<Vec<u8>, std::io::Error>;
+
+    /// Return the oldest record of the block not yet returned,
+    /// moving on to the following blocks when the block has none.
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(pos) = self.positions.pop() else {
+                if self.block.number() >= self.last {
+                    return None;
+                }
+                self.block = BlockId::new(self.block.filename(), self.block.number() + 1);
+                if let Err(e) = self.move_to_block() {
+                    return Some(Err(e));
+                }
+                continue;
+            };
+            let (lsn, bytes, _) = read_record(&mut self.page, pos);
+            if lsn >= self.start {
+                self.lsn = lsn;
+                return Some(Ok(bytes));
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt;
+
+/// A log sequence number: the position of a record in the
+/// sequence of records appended to the log.
+/// The first record appended has LSN 1, so that LSN 0
+/// precedes every record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lsn(pub u64);
+
+impl Lsn {
+    /// The number of bytes in the encoding of an LSN.
+    pub const SIZE: usize = std::mem::size_of::<u64>();
+
+    /// The LSN of the record appended after this one.
+    pub fn next(self) -> Lsn {
+        Lsn(self.0 + 1)
+    }
+
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Lsn {
+        Lsn(u64::from_be_bytes(bytes))
+    }
+}
+
+impl fmt::Display for Lsn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -1,34 +1,57 @@
-use crate::file::{BlockId, FileManager, Page};
-use crate::log::LogIterator;
+use crate::file::{BlockId, BlockStore, FileManager, Page};
+use crate::log::{
+    iterator::{first_lsn, intact_start, read_record, CHECKSUM_SIZE, COMPRESSED, HEADER_SIZE},
+    ForwardLogIterator, LogIterator, Lsn,
+};
 use std::io;
 use std::sync::Arc;
 
 const INT_SIZE: usize = std::mem::size_of::<i32>();
 
-pub struct LogManager {
-    fm: Arc<FileManager>,
+pub struct LogManager<S: BlockStore = FileManager> {
+    fm: Arc<S>,
     logfile: String,
     logpage: Page,
     current_blk: BlockId,
-    latest_lsn: i32,
-    last_saved_lsn: i32,
+    // the full pages that are not yet written, oldest first
+    pending: Vec<(BlockId, Page)>,
+    max_pages: usize,
+    compress_above: Option<usize>,
+    latest_lsn: Lsn,
+    last_saved_lsn: Lsn,
+    archive: bool,
 }
 
-impl LogManager {
+impl<S: BlockStore> LogManager<S> {
+    /// The number of log pages held in memory by default.
+    pub const BUFFER_PAGES: usize = 8;
+
     /// Creates a new log manager instance.
     ///
     /// If the log file does not yet exist, it is created
     /// with an empty first block.
-    pub fn new(fm: Arc<FileManager>, logfile: String) -> io::Result<Self> {
+    /// Otherwise the numbering of records continues from
+    /// the newest record of the last block, or from the
+    /// first LSN in its header if it has no records.
+    pub fn new(fm: Arc<S>, logfile: String) -> io::Result<Self> {
         let mut logpage = Page::new(fm.block_size());
         let logsize = fm.length(&logfile)?;
 
-        let current_blk = if logsize == 0 {
-            Self::append_new_block(&fm, &logfile, &mut logpage)?
+        let (current_blk, latest_lsn) = if logsize == 0 {
+            let blk = Self::append_new_block(&fm, &logfile, &mut logpage, Lsn(1))?;
+            (blk, Lsn(0))
         } else {
             let blk = BlockId::new(&logfile, logsize - 1);
             fm.read(&blk, &mut logpage)?;
-            blk
+            // drop any damaged records at the tail of the log
+            let start = intact_start(logpage.contents());
+            logpage.set_int(0, start as i32);
+            let latest = if start < logpage.length() {
+                read_record(&mut logpage, start).0
+            } else {
+                Lsn(first_lsn(&mut logpage).0.saturating_sub(1))
+            };
+            (blk, latest)
         };
 
         Ok(LogManager {
@@ -36,35 +59,193 @@ impl LogManager {
             logfile,
             logpage,
             current_blk,
-            latest_lsn: 0,
-            last_saved_lsn: 0,
+            pending: Vec::new(),
+            max_pages: Self::BUFFER_PAGES,
+            compress_above: None,
+            latest_lsn,
+            last_saved_lsn: latest_lsn,
+            archive: false,
         })
     }
 
     /// Ensures the log record for the specified LSN is written to disk
     /// All earlier log records will also be written to disk
-    pub fn flush(&mut self, lsn: i32) -> Result<(), io::Error> {
+    pub fn flush(&mut self, lsn: Lsn) -> Result<(), io::Error> {
         if lsn >= self.last_saved_lsn {
             self.flush_internal()?;
         }
         Ok(())
     }
 
-    pub fn iter(&mut self) -> Result<LogIterator, io::Error> {
+    /// Writes every log record appended so far to disk.
+    pub fn flush_all(&mut self) -> Result<(), io::Error> {
+        self.flush_internal()
+    }
+
+    /// Sets the number of log pages held in memory.
+    /// A page that fills up is kept until the pages are
+    /// written together, when a record is flushed or when
+    /// this many pages are held; with a single page, each
+    /// page is written as soon as it is full.
+    pub fn set_buffer_pages(&mut self, n: usize) -> Result<(), io::Error> {
+        self.max_pages = n.max(1);
+        if self.pending.len() >= self.max_pages {
+            self.flush_internal()?;
+        }
+        Ok(())
+    }
+
+    /// Compresses the records appended from now on whose
+    /// bytes are longer than the threshold, if that makes
+    /// them smaller; None turns compression off.
+    /// A compressed record is marked in its header, so the
+    /// log can hold both kinds, and iterators return the
+    /// bytes as they were appended.
+    pub fn set_compression(&mut self, threshold: Option<usize>) {
+        self.compress_above = threshold;
+    }
+
+    /// Chooses what truncate does with the old log file:
+    /// it is deleted unless archiving is on, in which case
+    /// it is kept under the name of the log followed by
+    /// the number of the archive, as in "simpledb.log.1".
+    pub fn set_archive(&mut self, on: bool) {
+        self.archive = on;
+    }
+
+    /// Discards every record in the log, by starting a new
+    /// log file and deleting or archiving the old one.
+    /// This is only correct when none of the records can be
+    /// needed again, as after a quiescent checkpoint: the
+    /// buffers of every transaction have been written and
+    /// no transaction is active.
+    pub fn truncate(&mut self) -> Result<(), io::Error> {
+        self.flush_internal()?;
+        if self.archive {
+            let n = self.archives()?.len() as u64 + 1;
+            self.fm
+                .rename_file(&self.logfile, &archive_name(&self.logfile, n))?;
+        } else {
+            self.fm.delete_file(&self.logfile)?;
+        }
+        self.current_blk = Self::append_new_block(
+            &self.fm,
+            &self.logfile,
+            &mut self.logpage,
+            self.latest_lsn.next(),
+        )?;
+        Ok(())
+    }
+
+    /// Returns the names of the archived log files, oldest
+    /// first. Together with the log, they hold every record
+    /// appended since the first was archived.
+    pub fn archives(&self) -> Result<Vec<String>, io::Error> {
+        let mut names = Vec::new();
+        while self
+            .fm
+            .length(&archive_name(&self.logfile, names.len() as u64 + 1))?
+            > 0
+        {
+            names.push(archive_name(&self.logfile, names.len() as u64 + 1));
+        }
+        Ok(names)
+    }
+
+    /// Returns the LSN of the newest record appended,
+    /// or LSN 0 if the log has never had one.
+    pub fn latest_lsn(&self) -> Lsn {
+        self.latest_lsn
+    }
+
+    /// Returns the name of the log file.
+    pub fn logfile(&self) -> &str {
+        &self.logfile
+    }
+
+    /// Deletes the log and its archived files, whatever the
+    /// archive setting, and starts a new log whose numbering
+    /// continues from the old one. A restore abandons the
+    /// history after its target this way.
+    pub fn discard_history(&mut self) -> Result<(), io::Error> {
+        for name in self.archives()? {
+            self.fm.delete_file(&name)?;
+        }
+        self.fm.delete_file(&self.logfile)?;
+        self.pending.clear();
+        self.current_blk = Self::append_new_block(
+            &self.fm,
+            &self.logfile,
+            &mut self.logpage,
+            self.latest_lsn.next(),
+        )?;
+        self.last_saved_lsn = self.latest_lsn;
+        Ok(())
+    }
+
+    pub fn iter(&mut self) -> Result<LogIterator<S>, io::Error> {
         self.flush_internal()?;
         LogIterator::new(Arc::clone(&self.fm), self.current_blk.clone())
     }
 
+    /// Returns an iterator over the records appended so far,
+    /// oldest first, starting with the record having the
+    /// specified LSN; LSN 0 starts at the first record.
+    /// Redo and log shipping read the log this way.
+    pub fn iter_forward(&mut self, start: Lsn) -> Result<ForwardLogIterator<S>, io::Error> {
+        self.flush_internal()?;
+        let first = self.find_block(start)?;
+        ForwardLogIterator::new(
+            Arc::clone(&self.fm),
+            first,
+            self.current_blk.number(),
+            start,
+        )
+    }
+
+    /// Returns an iterator over the records appended so far,
+    /// newest first, starting with the record having the
+    /// specified LSN.
+    pub fn iter_from(&mut self, lsn: Lsn) -> Result<LogIterator<S>, io::Error> {
+        self.flush_internal()?;
+        let block = self.find_block(lsn)?;
+        let mut iter = LogIterator::new(Arc::clone(&self.fm), block)?;
+        iter.skip_after(lsn);
+        Ok(iter)
+    }
+
+    /// Finds the block holding the record with the LSN,
+    /// by a binary search on the first LSN of the blocks.
+    fn find_block(&self, lsn: Lsn) -> Result<BlockId, io::Error> {
+        let mut page = Page::new(self.fm.block_size());
+        // the block is in lo..=hi
+        let (mut lo, mut hi) = (0, self.current_blk.number());
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            self.fm.read(&BlockId::new(&self.logfile, mid), &mut page)?;
+            if first_lsn(&mut page) <= lsn {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        Ok(BlockId::new(&self.logfile, lo))
+    }
+
     /// Appends a log record to the log buffer.
     /// The record consists of an arbitrary array of bytes.
     /// Log records are written right to left in the buffer.
-    /// The size of the record is written before the bytes.
+    /// The LSN of the record and its size are written
+    /// before the bytes, and a checksum of all three after
+    /// them, so that a record damaged by a torn write is
+    /// not mistaken for one.
     /// The beginning of the buffer contains the location
     /// of the last-written record (the "boundary").
     /// Storing the records backwards makes it easy to read
     /// them in reverse order.
     ///
-    /// The boundary value is stored as the first 4 bytes of the page's buffer
+    /// The boundary value is stored as the first 4 bytes of the page's buffer,
+    /// followed by the 8-byte LSN of the first record in the block
     ///
     /// Initial empty block (after appendNewBlock):
     /// +----------------+----------------------------------+
@@ -89,52 +270,108 @@ impl LogManager {
     /// 0                              3946     3996      4096
     ///                                 ↑
     ///                                 New boundary points here
-    pub fn append(&mut self, logrec: &[u8]) -> Result<i32, io::Error> {
-        let boundary = self.logpage.get_int(0);
-
-        let recsize = logrec.len();
-        let bytes_needed = (recsize + INT_SIZE) as i32;
+    pub fn append(&mut self, logrec: &[u8]) -> Result<Lsn, io::Error> {
+        self.append_all(&[logrec])
+    }
 
-        // check if record fits in block
-        if boundary - bytes_needed < (INT_SIZE as i32) {
-            // if log record doesn't fit, move to the next block
-            self.flush_internal()?;
-            self.current_blk = Self::append_new_block(&self.fm, &self.logfile, &mut self.logpage)?;
+    /// Appends several log records, in order, and returns
+    /// the LSN of the last one (or the latest LSN if there
+    /// are none). The boundary is read and stored once per
+    /// page rather than once per record, and whether the
+    /// full pages must be written is checked once, at the end,
+    /// so that compound operations are cheap to log.
+    pub fn append_all(&mut self, logrecs: &[&[u8]]) -> Result<Lsn, io::Error> {
+        let mut boundary = self.logpage.get_int(0) as usize;
+        for logrec in logrecs {
+            let compressed;
+            let (logrec, flag) = match self.compress_above {
+                Some(threshold) if logrec.len() > threshold => {
+                    compressed = lz4_flex::compress_prepend_size(logrec);
+                    if compressed.len() < logrec.len() {
+                        (compressed.as_slice(), COMPRESSED)
+                    } else {
+                        (*logrec, 0)
+                    }
+                }
+                _ => (*logrec, 0),
+            };
+            let recsize = logrec.len();
+            let bytes_needed = Lsn::SIZE + INT_SIZE + recsize + CHECKSUM_SIZE;
 
-            let boundary = self.logpage.get_int(0);
-            let recpos = boundary - bytes_needed;
+            // check if record fits in block
+            if boundary < HEADER_SIZE + bytes_needed {
+                // if log record doesn't fit, move to the next block,
+                // keeping the full page until the pages are written
+                self.logpage.set_int(0, boundary as i32);
+                let next = BlockId::new(&self.logfile, self.current_blk.number() + 1);
+                let full = std::mem::replace(&mut self.logpage, Page::new(self.fm.block_size()));
+                self.pending
+                    .push((std::mem::replace(&mut self.current_blk, next), full));
+                Self::init_page(&mut self.logpage, self.latest_lsn.next());
+                boundary = self.logpage.length();
+            }
 
-            self.logpage.set_bytes(recpos as usize, logrec);
-            // Update boundary to point to new record start
-            self.logpage.set_int(0, recpos);
-        } else {
+            let lsn = self.latest_lsn.next();
             let recpos = boundary - bytes_needed;
-            self.logpage.set_bytes(recpos as usize, logrec);
-            self.logpage.set_int(0, recpos);
+            self.logpage.contents()[recpos..recpos + Lsn::SIZE].copy_from_slice(&lsn.to_bytes());
+            self.logpage.set_bytes(recpos + Lsn::SIZE, logrec);
+            self.logpage
+                .set_int(recpos + Lsn::SIZE, (recsize as u32 | flag) as i32);
+            let crc_pos = recpos + Lsn::SIZE + INT_SIZE + recsize;
+            let checksum = crc32fast::hash(&self.logpage.contents()[recpos..crc_pos]);
+            self.logpage.set_int(crc_pos, checksum as i32);
+            boundary = recpos;
+            self.latest_lsn = lsn;
         }
+        // Update boundary to point to the newest record
+        self.logpage.set_int(0, boundary as i32);
 
-        self.latest_lsn += 1;
+        if self.pending.len() >= self.max_pages {
+            self.flush_internal()?;
+        }
         Ok(self.latest_lsn)
     }
 
     fn append_new_block(
-        fm: &FileManager,
+        fm: &S,
         logfile: &str,
         logpage: &mut Page,
+        first: Lsn,
     ) -> Result<BlockId, io::Error> {
         let blk = fm.append(logfile)?;
-        logpage.set_int(0, fm.block_size() as i32);
+        Self::init_page(logpage, first);
         fm.write(&blk, logpage)?;
         Ok(blk)
     }
 
+    // Make the page an empty log block whose first record
+    // will have the specified LSN.
+    fn init_page(logpage: &mut Page, first: Lsn) {
+        logpage.set_int(0, logpage.length() as i32);
+        logpage.contents()[INT_SIZE..HEADER_SIZE].copy_from_slice(&first.to_bytes());
+    }
+
+    // Write the pending pages and the current page,
+    // in a single call to the store.
     fn flush_internal(&mut self) -> Result<(), io::Error> {
-        self.fm.write(&self.current_blk, &mut self.logpage)?;
+        let mut pages: Vec<(&BlockId, &mut Page)> = self
+            .pending
+            .iter_mut()
+            .map(|(blk, page)| (&*blk, page))
+            .collect();
+        pages.push((&self.current_blk, &mut self.logpage));
+        self.fm.write_all(&mut pages)?;
+        self.pending.clear();
         self.last_saved_lsn = self.latest_lsn;
         Ok(())
     }
 }
 
+// The name of the nth archived log file.
+fn archive_name(logfile: &str, n: u64) -> String {
+    format!("{}.{}", logfile, n)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,7 +387,7 @@ mod tests {
         page.to_vec()
     }
 
-    fn create_records(lm: &mut LogManager, start: i32, end: i32) -> Vec<i32> {
+    fn create_records(lm: &mut LogManager, start: i32, end: i32) -> Vec<Lsn> {
         let mut lsns = Vec::new();
         for i in start..=end {
             let rec = create_log_record(&format!("record{}", i), i + 100);
@@ -198,12 +435,20 @@ mod tests {
         assert_eq!(records[0].0, "record35");
         assert_eq!(records[0].1, 135);
 
+        // Each record carries its lsn
+        assert_eq!(lsn1[0], Lsn(1));
+        let mut iter = lm.iter().unwrap();
+        iter.next().unwrap().unwrap();
+        assert_eq!(iter.lsn(), Lsn(35));
+        let lsns: Vec<_> = std::iter::from_fn(|| iter.next().map(|_| iter.lsn())).collect();
+        assert_eq!(lsns, (1..35).rev().map(Lsn).collect::<Vec<_>>());
+
         // Create second batch of records
         let lsns2 = create_records(&mut lm, 36, 70);
         assert_eq!(lsns2.len(), 35);
 
         // Flush up to record 65
-        lm.flush(65).unwrap();
+        lm.flush(Lsn(65)).unwrap();
 
         // Verify all records
         let records = print_log_records(&mut lm);
@@ -213,4 +458,219 @@ mod tests {
         assert_eq!(records[69].0, "record1");
         assert_eq!(records[69].1, 101);
     }
+
+    #[test]
+    fn test_damaged_tail() {
+        let temp_dir = tempdir().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+        let fm = db.file_manager();
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        create_records(&mut lm, 1, 5);
+        lm.flush_all().unwrap();
+
+        // damage the newest record, as a torn write would
+        let blk = BlockId::new("testlog", 0);
+        let mut page = Page::new(400);
+        fm.read(&blk, &mut page).unwrap();
+        let boundary = page.get_int(0) as usize;
+        page.contents()[boundary + Lsn::SIZE + INT_SIZE] ^= 0xff;
+        fm.write(&blk, &mut page).unwrap();
+
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        let records = print_log_records(&mut lm);
+        let names: Vec<_> = records.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(names, ["record4", "record3", "record2", "record1"]);
+
+        // new records replace the damaged one
+        create_records(&mut lm, 6, 6);
+        let records = print_log_records(&mut lm);
+        assert_eq!(records.len(), 5);
+        assert_eq!(records[0], ("record6".to_string(), 106));
+        assert_eq!(records[1].0, "record4");
+    }
+
+    #[test]
+    fn test_truncate() {
+        let temp_dir = tempdir().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+        let fm = db.file_manager();
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        create_records(&mut lm, 1, 40);
+        lm.flush_all().unwrap();
+        assert!(fm.length("testlog").unwrap() > 1);
+
+        lm.truncate().unwrap();
+        assert_eq!(fm.length("testlog").unwrap(), 1);
+        assert!(print_log_records(&mut lm).is_empty());
+        create_records(&mut lm, 41, 42);
+
+        // archived files keep the old records
+        lm.set_archive(true);
+        lm.truncate().unwrap();
+        create_records(&mut lm, 43, 43);
+        lm.truncate().unwrap();
+        let mut archived = LogManager::new(Arc::clone(&fm), "testlog.1".to_string()).unwrap();
+        let records = print_log_records(&mut archived);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].0, "record42");
+        let mut archived = LogManager::new(Arc::clone(&fm), "testlog.2".to_string()).unwrap();
+        assert_eq!(print_log_records(&mut archived)[0].0, "record43");
+        assert!(print_log_records(&mut lm).is_empty());
+        assert_eq!(lm.archives().unwrap(), ["testlog.1", "testlog.2"]);
+
+        create_records(&mut lm, 44, 45);
+        lm.discard_history().unwrap();
+        assert!(lm.archives().unwrap().is_empty());
+        assert!(print_log_records(&mut lm).is_empty());
+        assert_eq!(lm.append(b"next").unwrap(), Lsn(46));
+    }
+
+    #[test]
+    fn test_forward_iteration() {
+        let temp_dir = tempdir().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+        let mut lm = LogManager::new(db.file_manager(), "testlog".to_string()).unwrap();
+        assert_eq!(lm.iter_forward(Lsn(0)).unwrap().count(), 0);
+        create_records(&mut lm, 1, 40);
+
+        let mut backward = print_log_records(&mut lm);
+        backward.reverse();
+        let forward: Vec<_> = lm
+            .iter_forward(Lsn(0))
+            .unwrap()
+            .map(|rec| {
+                let page = Page::from_bytes(rec.unwrap());
+                let s = page.get_string(0);
+                let val = page.get_int(Page::max_length(s.len()));
+                (s, val)
+            })
+            .collect();
+        assert_eq!(forward, backward);
+
+        let mut iter = lm.iter_forward(Lsn(30)).unwrap();
+        let lsns: Vec<_> = std::iter::from_fn(|| iter.next().map(|_| iter.lsn())).collect();
+        assert_eq!(lsns, (30..=40).map(Lsn).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_iter_from() {
+        let temp_dir = tempdir().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+        let fm = db.file_manager();
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        create_records(&mut lm, 1, 100);
+        assert!(fm.length("testlog").unwrap() > 5);
+
+        for start in [1, 2, 37, 64, 100] {
+            let mut iter = lm.iter_from(Lsn(start)).unwrap();
+            let lsns: Vec<_> = std::iter::from_fn(|| iter.next().map(|_| iter.lsn())).collect();
+            assert_eq!(lsns, (1..=start).rev().map(Lsn).collect::<Vec<_>>());
+            let records = print_log_records(&mut lm);
+            let first = lm.iter_from(Lsn(start)).unwrap().next().unwrap().unwrap();
+            let page = Page::from_bytes(first);
+            assert_eq!(page.get_string(0), records[(100 - start) as usize].0);
+        }
+        // an LSN past the end starts at the newest record
+        let mut iter = lm.iter_from(Lsn(500)).unwrap();
+        iter.next();
+        assert_eq!(iter.lsn(), Lsn(100));
+    }
+
+    #[test]
+    fn test_buffer_pages() {
+        let temp_dir = tempdir().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+        let fm = db.file_manager();
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        lm.set_buffer_pages(4).unwrap();
+
+        // three pages fill up without being written
+        create_records(&mut lm, 1, 40);
+        assert_eq!(fm.length("testlog").unwrap(), 1);
+        lm.flush(Lsn(40)).unwrap();
+        assert_eq!(fm.length("testlog").unwrap(), 4);
+
+        // the fourth full page writes them all
+        create_records(&mut lm, 41, 80);
+        assert_eq!(fm.length("testlog").unwrap(), 4);
+        create_records(&mut lm, 81, 100);
+        assert_eq!(fm.length("testlog").unwrap(), 8);
+        assert_eq!(print_log_records(&mut lm).len(), 100);
+
+        lm.set_buffer_pages(1).unwrap();
+        create_records(&mut lm, 101, 120);
+        assert!(fm.length("testlog").unwrap() > 8);
+    }
+
+    #[test]
+    fn test_append_all() {
+        let temp_dir = tempdir().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+        let mut lm = LogManager::new(db.file_manager(), "testlog".to_string()).unwrap();
+        assert_eq!(lm.append_all(&[]).unwrap(), Lsn(0));
+        create_records(&mut lm, 1, 5);
+
+        let recs: Vec<Vec<u8>> = (6..=40)
+            .map(|i| create_log_record(&format!("record{}", i), i + 100))
+            .collect();
+        let recs: Vec<&[u8]> = recs.iter().map(|rec| rec.as_slice()).collect();
+        assert_eq!(lm.append_all(&recs).unwrap(), Lsn(40));
+        create_records(&mut lm, 41, 41);
+
+        let records = print_log_records(&mut lm);
+        let expected: Vec<_> = (1..=41)
+            .rev()
+            .map(|i| (format!("record{}", i), i + 100))
+            .collect();
+        assert_eq!(records, expected);
+    }
+
+    #[test]
+    fn test_lsn_survives_restart() {
+        let temp_dir = tempdir().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+        let fm = db.file_manager();
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        create_records(&mut lm, 1, 30);
+        lm.flush_all().unwrap();
+
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        assert_eq!(create_records(&mut lm, 31, 31), [Lsn(31)]);
+        let mut iter = lm.iter_from(Lsn(31)).unwrap();
+        iter.next();
+        assert_eq!(iter.lsn(), Lsn(31));
+
+        // a truncated log keeps counting
+        lm.truncate().unwrap();
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        assert_eq!(create_records(&mut lm, 32, 32), [Lsn(32)]);
+    }
+
+    #[test]
+    fn test_compression() {
+        let temp_dir = tempdir().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+        let fm = db.file_manager();
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        lm.set_compression(Some(64));
+        let long = create_log_record(&"abcd".repeat(50), 7);
+        let noise: Vec<u8> = (0..100u32).map(|i| (i * 7919 % 251) as u8).collect();
+        for _ in 0..10 {
+            lm.append(&long).unwrap();
+        }
+        lm.append(&noise).unwrap();
+        lm.append(&[1, 2, 3]).unwrap();
+        lm.flush_all().unwrap();
+        // the ten long records would need six blocks uncompressed
+        assert!(fm.length("testlog").unwrap() <= 2);
+
+        let mut lm = LogManager::new(Arc::clone(&fm), "testlog".to_string()).unwrap();
+        let records = lm.iter().unwrap().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(records.len(), 12);
+        assert_eq!(records[0], [1, 2, 3]);
+        assert_eq!(records[1], noise);
+        assert!(records[2..].iter().all(|rec| *rec == long));
+        let forward = lm.iter_forward(Lsn(0)).unwrap().count();
+        assert_eq!(forward, 12);
+    }
 }
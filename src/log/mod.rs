@@ -1,6 +1,8 @@
+pub mod dump;
 mod iterator;
+mod lsn;
 mod manager;
 
-pub use iterator::LogIterator;
+pub use iterator::{ForwardLogIterator, LogIterator};
+pub use lsn::Lsn;
 pub use manager::LogManager;
-
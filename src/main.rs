@@ -1,7 +1,331 @@
-use simpledb::SimpleDB;
+use std::{
+    env,
+    path::Path,
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
 
-fn main() -> std::io::Result<()> {
-    let _db = SimpleDB::new("filetest", 400, 8)?;
+use rustyline::{error::ReadlineError, DefaultEditor};
+use simpledb::{
+    driver::{Connection, ResultSet},
+    file::Superblock,
+    log::{dump::dump, Lsn},
+    parse::Parser,
+    record::FieldType,
+    tx::recovery::RecoveryTarget,
+    DbError, DbResult, FileManager, SimpleDB,
+};
 
+const HISTORY_FILE: &str = ".simpledb_history";
+
+const HELP: &str = "\
+Statements end with a semicolon and may span several lines.
+  begin;            start a transaction
+  commit;           commit the current transaction
+  rollback;         undo the current transaction
+  show tables;      list the tables and views
+  describe <name>;  show the fields of a table or view
+  analyze [table];  recount the statistics of one or all tables
+  .tables           list the tables
+  .schema [table]   show the fields of one or all tables
+  .history          show the statement history
+  .help             show this message
+  .quit             exit";
+
+// An interactive SQL client for an embedded database.
+// Usage: simpledb [dbdir]
+//        simpledb log-dump <dbdir> [--json]
+//        simpledb restore <dbdir> (--lsn <lsn> | --time <unix seconds>)
+fn main() -> DbResult<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("log-dump") => return log_dump(&args[1..]),
+        Some("restore") => return restore(&args[1..]),
+        _ => {}
+    }
+    let dirname = args
+        .first()
+        .cloned()
+        .unwrap_or_else(|| "studentdb".to_string());
+    let db = SimpleDB::new(&dirname, SimpleDB::BLOCK_SIZE, SimpleDB::BUFFER_SIZE)?;
+    let mut conn = Connection::new(&db);
+
+    let mut editor = DefaultEditor::new().map_err(std::io::Error::other)?;
+    let history = Path::new(&dirname).join(HISTORY_FILE);
+    let _ = editor.load_history(&history);
+    println!("SimpleDB on {}. Type .help for help.", dirname);
+
+    let mut stmt = String::new();
+    loop {
+        let prompt = match (stmt.is_empty(), conn.in_transaction()) {
+            (false, _) => "   ...> ",
+            (true, false) => "simpledb> ",
+            (true, true) => "simpledb*> ",
+        };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                stmt.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(std::io::Error::other(e).into()),
+        };
+        let line = line.trim();
+        if stmt.is_empty() && line.starts_with('.') {
+            let _ = editor.add_history_entry(line);
+            if !meta_command(line, &mut conn, &editor) {
+                break;
+            }
+            continue;
+        }
+        if !stmt.is_empty() {
+            stmt.push(' ');
+        }
+        stmt.push_str(line);
+        if !stmt.ends_with(';') {
+            continue;
+        }
+        let _ = editor.add_history_entry(stmt.as_str());
+        let sql = stmt.trim_end_matches(';').trim().to_string();
+        stmt.clear();
+        if let Err(e) = run_statement(&sql, &mut conn) {
+            println!("error: {}", e);
+        }
+    }
+
+    let _ = editor.save_history(&history);
+    if conn.in_transaction() {
+        println!("committing the open transaction");
+    }
+    conn.close()
+}
+
+// Print the records of the database's log, oldest first,
+// one per line, as text or as JSON.
+fn log_dump(args: &[String]) -> DbResult<()> {
+    let Some(dirname) = args.first() else {
+        println!("usage: simpledb log-dump <dbdir> [--json]");
+        return Ok(());
+    };
+    let json = args[1..].iter().any(|arg| arg == "--json");
+    let block_size = Superblock::read(Path::new(dirname))?.block_size();
+    let fm = Arc::new(FileManager::new(dirname, block_size)?);
+    let records = dump(fm, SimpleDB::LOG_FILE)?;
+    if !json {
+        println!("{:>8} {:>6}  op", "lsn", "block");
+    }
+    for rec in records {
+        if json {
+            println!("{}", rec.to_json());
+        } else {
+            println!("{}", rec);
+        }
+    }
     Ok(())
 }
+
+// Restore the database in the directory to an LSN or
+// a time, from the backup and log files it holds.
+fn restore(args: &[String]) -> DbResult<()> {
+    let target = match args.get(1..3) {
+        Some([flag, value]) => match (flag.as_str(), value.parse::<u64>()) {
+            ("--lsn", Ok(lsn)) => Some(RecoveryTarget::Lsn(Lsn(lsn))),
+            ("--time", Ok(secs)) => {
+                Some(RecoveryTarget::Time(UNIX_EPOCH + Duration::from_secs(secs)))
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some(target) = target else {
+        println!("usage: simpledb restore <dbdir> (--lsn <lsn> | --time <unix seconds>)");
+        return Ok(());
+    };
+    let dirname = &args[0];
+    let block_size = Superblock::read(Path::new(dirname))?.block_size();
+    let fm = FileManager::new(dirname, block_size)?;
+    SimpleDB::restore(fm, SimpleDB::BUFFER_SIZE, target)?;
+    println!("restored {}", dirname);
+    Ok(())
+}
+
+// Run a meta-command.
+// Return false if the REPL should exit.
+fn meta_command(line: &str, conn: &mut Connection, editor: &DefaultEditor) -> bool {
+    let mut words = line.split_whitespace();
+    let result = match words.next().unwrap_or_default() {
+        ".quit" | ".exit" => return false,
+        ".help" => {
+            println!("{}", HELP);
+            Ok(())
+        }
+        ".history" => {
+            for (i, entry) in editor.history().iter().enumerate() {
+                println!("{:5}  {}", i + 1, entry);
+            }
+            Ok(())
+        }
+        ".tables" => table_names(conn).map(|tables| {
+            for tblname in tables {
+                println!("{}", tblname);
+            }
+        }),
+        ".schema" => show_schema(conn, words.next()),
+        other => {
+            println!("unknown command {}; type .help for help", other);
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        println!("error: {}", e);
+    }
+    true
+}
+
+fn run_statement(sql: &str, conn: &mut Connection) -> DbResult<()> {
+    match sql.to_lowercase().as_str() {
+        "begin" | "begin transaction" | "start transaction" => return conn.begin(),
+        "commit" => return conn.commit(),
+        "rollback" => return conn.rollback(),
+        _ => {}
+    }
+    if Parser::new(sql)?.is_query() {
+        let rs = conn.execute_query(sql)?;
+        print!("{}", format_table(&rs));
+        println!("({} row{})", rs.len(), if rs.len() == 1 { "" } else { "s" });
+    } else {
+        let count = conn.execute_update(sql)?;
+        println!("{} record(s) affected", count);
+    }
+    Ok(())
+}
+
+// Return the names of the user tables, from the catalog.
+fn table_names(conn: &mut Connection) -> DbResult<Vec<String>> {
+    let rs = conn.execute_query("show tables")?;
+    let tables = rs
+        .rows()
+        .iter()
+        .filter(|row| row[1].to_string() == "table")
+        .map(|row| row[0].to_string())
+        .collect();
+    Ok(tables)
+}
+
+// Print the fields of the table, or of every table,
+// as described by the catalog.
+fn show_schema(conn: &mut Connection, tblname: Option<&str>) -> DbResult<()> {
+    let tables = match tblname {
+        Some(tblname) => vec![tblname.to_lowercase()],
+        None => table_names(conn)?,
+    };
+    for tblname in tables {
+        let fields = match conn.execute_query(&format!("describe {}", tblname)) {
+            Ok(rs) => rs.rows().to_vec(),
+            Err(DbError::Catalog(_)) => {
+                println!("no such table {}", tblname);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|row| match row[2].as_int() {
+                Some(length) => format!("{} {}({})", row[0], row[1], length),
+                None => format!("{} {}", row[0], row[1]),
+            })
+            .collect();
+        println!("create table {} ({});", tblname, fields.join(", "));
+    }
+    Ok(())
+}
+
+// Format the result set as a table with a header line.
+// Integer columns are right-aligned.
+fn format_table(rs: &ResultSet) -> String {
+    let cells: Vec<Vec<String>> = rs
+        .rows()
+        .iter()
+        .map(|row| row.iter().map(|val| val.to_string()).collect())
+        .collect();
+    let widths: Vec<usize> = rs
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, col)| {
+            cells
+                .iter()
+                .map(|row| row[i].len())
+                .fold(col.len(), usize::max)
+        })
+        .collect();
+    let right: Vec<bool> = rs
+        .columns()
+        .iter()
+        .map(|col| rs.schema().field_type(col) == FieldType::Integer)
+        .collect();
+
+    let mut out = String::new();
+    let header: Vec<String> = rs
+        .columns()
+        .iter()
+        .zip(&widths)
+        .map(|(col, w)| format!("{:<w$}", col, w = *w))
+        .collect();
+    out.push_str(header.join(" | ").trim_end());
+    out.push('\n');
+    let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    out.push_str(&rule.join("-+-"));
+    out.push('\n');
+    for row in &cells {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths.iter().zip(&right))
+            .map(|(val, (w, right))| {
+                if *right {
+                    format!("{:>w$}", val, w = *w)
+                } else {
+                    format!("{:<w$}", val, w = *w)
+                }
+            })
+            .collect();
+        out.push_str(line.join(" | ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_table() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let mut conn = Connection::new(&db);
+        run_statement("create table emp (id int, name varchar(10))", &mut conn)?;
+        run_statement("insert into emp (id, name) values (7, 'ann')", &mut conn)?;
+        run_statement(
+            "insert into emp (id, name) values (12, 'barnabas')",
+            &mut conn,
+        )?;
+
+        let rs = conn.execute_query("select name, id from emp")?;
+        let mut lines: Vec<String> = format_table(&rs).lines().map(String::from).collect();
+        lines[2..].sort();
+        assert_eq!(
+            lines,
+            [
+                "name     | id",
+                "---------+---",
+                "ann      |  7",
+                "barnabas | 12",
+            ]
+        );
+        assert_eq!(table_names(&mut conn)?, ["emp"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use super::{AvgFn, CountFn, MaxFn, MinFn, SumFn};
+use crate::{
+    error::{DbError, DbResult},
+    query::{Constant, Scan},
+    record::{FieldType, Schema},
+};
+
+// The interface implemented by aggregation functions.
+// Aggregation functions are used by the groupby operator,
+// whose threads each aggregate a partition of the records.
+pub trait AggregationFn: Send + Sync {
+    // Use the current record of the specified scan
+    // to be the first record in the group.
+    fn process_first(&mut self, s: &dyn Scan) -> DbResult<()>;
+
+    // Use the current record of the specified scan
+    // to be the next record in the group.
+    fn process_next(&mut self, s: &dyn Scan) -> DbResult<()>;
+
+    // Return the name of the new aggregation field.
+    fn field_name(&self) -> &str;
+
+    // Return the computed aggregation value.
+    fn value(&self) -> Constant;
+
+    // Add the aggregation field to the specified schema,
+    // checking that the aggregated field exists in the
+    // source schema and has a suitable type.
+    fn add_to_schema(&self, sch: &mut Schema, source: &Schema) -> DbResult<()>;
+
+    // Return a fresh copy of this function, so that
+    // each scan can keep its own running value.
+    fn box_clone(&self) -> Box<dyn AggregationFn>;
+}
+
+// The aggregation functions that may appear in a select list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunc {
+    Count,
+    Max,
+    Min,
+    Sum,
+    Avg,
+}
+
+impl AggregateFunc {
+    pub const NAMES: &'static [&'static str] = &["count", "max", "min", "sum", "avg"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "count" => Some(AggregateFunc::Count),
+            "max" => Some(AggregateFunc::Max),
+            "min" => Some(AggregateFunc::Min),
+            "sum" => Some(AggregateFunc::Sum),
+            "avg" => Some(AggregateFunc::Avg),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AggregateFunc::Count => "count",
+            AggregateFunc::Max => "max",
+            AggregateFunc::Min => "min",
+            AggregateFunc::Sum => "sum",
+            AggregateFunc::Avg => "avg",
+        }
+    }
+
+    // Return the name of the aggregation field that the
+    // function computes over the specified field.
+    pub fn field_name(self, fldname: &str) -> String {
+        format!("{}of{}", self.name(), fldname)
+    }
+
+    // Create an aggregation function over the specified field.
+    pub fn create(self, fldname: &str) -> Box<dyn AggregationFn> {
+        match self {
+            AggregateFunc::Count => Box::new(CountFn::new(fldname)),
+            AggregateFunc::Max => Box::new(MaxFn::new(fldname)),
+            AggregateFunc::Min => Box::new(MinFn::new(fldname)),
+            AggregateFunc::Sum => Box::new(SumFn::new(fldname)),
+            AggregateFunc::Avg => Box::new(AvgFn::new(fldname)),
+        }
+    }
+}
+
+// Check that the aggregated field exists in the source schema
+// and, if required, that it is an integer field.
+pub(super) fn check_field(source: &Schema, fldname: &str, needs_int: bool) -> DbResult<()> {
+    if !source.has_field(fldname) {
+        return Err(DbError::Catalog(format!("field {} not found", fldname)));
+    }
+    if needs_int && source.field_type(fldname) != FieldType::Integer {
+        return Err(DbError::Catalog(format!(
+            "cannot aggregate non-integer field {}",
+            fldname
+        )));
+    }
+    Ok(())
+}
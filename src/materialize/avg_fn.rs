@@ -0,0 +1,74 @@
+use super::{aggregation_fn::check_field, AggregateFunc, AggregationFn};
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+    record::Schema,
+};
+
+// The avg aggregation function.
+// Only integer fields can be averaged, and since
+// there are no fractional values the average is
+// truncated towards zero.
+#[derive(Debug, Clone)]
+pub struct AvgFn {
+    fldname: String,
+    name: String,
+    sum: i64,
+    count: i64,
+}
+
+impl AvgFn {
+    // Create an avg aggregation function for the specified field.
+    pub fn new(fldname: &str) -> Self {
+        AvgFn {
+            fldname: fldname.to_string(),
+            name: AggregateFunc::Avg.field_name(fldname),
+            sum: 0,
+            count: 0,
+        }
+    }
+}
+
+impl AggregationFn for AvgFn {
+    // Start a new running total and count.
+    fn process_first(&mut self, s: &dyn Scan) -> DbResult<()> {
+        self.sum = 0;
+        self.count = 0;
+        self.process_next(s)
+    }
+
+    // Add the field value in the current record
+    // to the running total.
+    // Null values are ignored.
+    fn process_next(&mut self, s: &dyn Scan) -> DbResult<()> {
+        if let Some(val) = s.get_val(&self.fldname)?.as_int() {
+            self.sum += val as i64;
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    // Return the field's name, prepended by "avgof".
+    fn field_name(&self) -> &str {
+        &self.name
+    }
+
+    // Return the current average,
+    // or null if every value was null.
+    fn value(&self) -> Constant {
+        if self.count == 0 {
+            return Constant::Null;
+        }
+        Constant::Int((self.sum / self.count) as i32)
+    }
+
+    fn add_to_schema(&self, sch: &mut Schema, source: &Schema) -> DbResult<()> {
+        check_field(source, &self.fldname, true)?;
+        sch.add_int_field(&self.name);
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
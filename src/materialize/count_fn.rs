@@ -0,0 +1,63 @@
+use super::{aggregation_fn::check_field, AggregateFunc, AggregationFn};
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+    record::Schema,
+};
+
+// The count aggregation function.
+#[derive(Debug, Clone)]
+pub struct CountFn {
+    fldname: String,
+    name: String,
+    count: i32,
+}
+
+impl CountFn {
+    // Create a count aggregation function for the specified field.
+    pub fn new(fldname: &str) -> Self {
+        CountFn {
+            fldname: fldname.to_string(),
+            name: AggregateFunc::Count.field_name(fldname),
+            count: 0,
+        }
+    }
+}
+
+impl AggregationFn for CountFn {
+    // Start a new count.
+    // A record is counted only if the field is not null.
+    fn process_first(&mut self, s: &dyn Scan) -> DbResult<()> {
+        self.count = 0;
+        self.process_next(s)
+    }
+
+    // Increment the count if the field
+    // in the current record is not null.
+    fn process_next(&mut self, s: &dyn Scan) -> DbResult<()> {
+        if !s.get_val(&self.fldname)?.is_null() {
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    // Return the field's name, prepended by "countof".
+    fn field_name(&self) -> &str {
+        &self.name
+    }
+
+    // Return the current count.
+    fn value(&self) -> Constant {
+        Constant::Int(self.count)
+    }
+
+    fn add_to_schema(&self, sch: &mut Schema, source: &Schema) -> DbResult<()> {
+        check_field(source, &self.fldname, false)?;
+        sch.add_int_field(&self.name);
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use super::{DistinctScan, SortOrder, SortPlan};
+use crate::{error::DbResult, plan::Plan, query::Scan, record::Schema, tx::Transaction};
+
+// The Plan class for the duplicate-elimination operator.
+// The underlying records are sorted on all of their fields,
+// so that duplicate records are adjacent and can be skipped.
+pub struct DistinctPlan {
+    p: SortPlan,
+    fields: Vec<String>,
+}
+
+impl DistinctPlan {
+    // Create a duplicate-elimination plan for the underlying query.
+    // The output is ordered by the specified keys, and then
+    // by the remaining fields in ascending order.
+    pub fn new(
+        tx: Arc<Transaction>,
+        p: Box<dyn Plan>,
+        orderkeys: Vec<(String, SortOrder)>,
+    ) -> Self {
+        let fields = p.schema().fields().to_vec();
+        let mut sortkeys = orderkeys;
+        for fldname in &fields {
+            if !sortkeys.iter().any(|(f, _)| f == fldname) {
+                sortkeys.push((fldname.clone(), SortOrder::Asc));
+            }
+        }
+        DistinctPlan {
+            p: SortPlan::with_orders(tx, p, sortkeys),
+            fields,
+        }
+    }
+}
+
+impl Plan for DistinctPlan {
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let s = self.p.open_sort_scan()?;
+        Ok(Box::new(DistinctScan::new(s, self.fields.clone())))
+    }
+
+    // Return the number of blocks required to
+    // read the sorted records, which is one pass
+    // through the sorted table.
+    fn blocks_accessed(&self) -> u64 {
+        self.p.blocks_accessed()
+    }
+
+    // Estimate the number of distinct records as the
+    // product of the distinct values of each field,
+    // but not more than the number of input records.
+    fn records_output(&self) -> u64 {
+        let combinations = self.fields.iter().fold(1u64, |n, fldname| {
+            n.saturating_mul(self.p.distinct_values(fldname))
+        });
+        combinations.min(self.p.records_output())
+    }
+
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        self.p.distinct_values(fldname)
+    }
+
+    fn schema(&self) -> &Schema {
+        self.p.schema()
+    }
+
+    fn description(&self) -> String {
+        "Distinct".to_string()
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![&self.p]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::SimpleDB,
+        error::{DbError, DbResult},
+        plan::run_query,
+    };
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_distinct() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int, b varchar(5), c int)")?;
+        for i in 0..20 {
+            let sql = format!(
+                "insert into t (a, b, c) values ({}, 'b{}', {})",
+                i % 3,
+                i % 2,
+                i
+            );
+            db.execute_update(&sql)?;
+        }
+        let planner = db.planner()?;
+        let tx = db.new_tx()?;
+
+        assert_eq!(
+            run_query(&planner, "select distinct a from t", &tx)?,
+            ["0", "1", "2"]
+        );
+        assert_eq!(
+            run_query(&planner, "select distinct a, b from t", &tx)?.len(),
+            6
+        );
+        assert_eq!(
+            run_query(&planner, "select distinct b, a from t order by a desc", &tx)?,
+            ["b0 2", "b1 2", "b0 1", "b1 1", "b0 0", "b1 0"]
+        );
+        assert_eq!(run_query(&planner, "select a from t", &tx)?.len(), 20);
+
+        // a distinct query can only be ordered by its output fields
+        let result = planner.create_query_plan("select distinct a from t order by c", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use super::GroupValue;
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+};
+
+// The Scan class for the duplicate-elimination operator.
+// The underlying scan must be sorted on all of the
+// distinct fields, so that duplicates are adjacent.
+pub struct DistinctScan<S> {
+    s: S,
+    fields: Vec<String>,
+    prev: Option<GroupValue>,
+}
+
+impl<S: Scan> DistinctScan<S> {
+    pub fn new(s: S, fields: Vec<String>) -> Self {
+        DistinctScan {
+            s,
+            fields,
+            prev: None,
+        }
+    }
+}
+
+impl<S: Scan> Scan for DistinctScan<S> {
+    fn before_first(&mut self) -> DbResult<()> {
+        self.prev = None;
+        self.s.before_first()
+    }
+
+    // Move to the next record whose values differ
+    // from those of the previous output record.
+    fn next(&mut self) -> DbResult<bool> {
+        while self.s.next()? {
+            let val = GroupValue::new(&self.s, &self.fields)?;
+            if self.prev.as_ref() != Some(&val) {
+                self.prev = Some(val);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.s.get_int(fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        self.s.get_string(fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        self.s.get_val(fldname)
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        self.s.has_field(fldname)
+    }
+
+    fn close(&mut self) {
+        self.s.close();
+    }
+}
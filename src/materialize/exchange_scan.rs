@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+
+use super::{RecordComparator, TempTable};
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+    record::TableScan,
+};
+
+// The Scan class for the exchange operator, which gathers
+// the outputs of the threads of a parallel operator.
+// Each output is a temporary table sorted by the comparator,
+// and the scan merges them into a single sorted stream,
+// like a sort scan merging its final runs.
+pub struct ExchangeScan {
+    scans: Vec<TableScan>,
+    hasmore: Vec<bool>,
+    current: Option<usize>,
+    comp: RecordComparator,
+}
+
+impl ExchangeScan {
+    // Create an exchange scan of the specified sorted tables.
+    pub fn new(tables: &[TempTable], comp: RecordComparator) -> DbResult<Self> {
+        let scans = tables
+            .iter()
+            .map(|t| t.open())
+            .collect::<DbResult<Vec<_>>>()?;
+        let mut scan = ExchangeScan {
+            hasmore: vec![false; scans.len()],
+            scans,
+            current: None,
+            comp,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+
+    fn current_scan(&self) -> &TableScan {
+        &self.scans[self.current.expect("exchange scan is not positioned")]
+    }
+}
+
+impl Scan for ExchangeScan {
+    // Position the scan before the first record,
+    // by moving to the first record of each table.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.current = None;
+        for (s, hasmore) in self.scans.iter_mut().zip(self.hasmore.iter_mut()) {
+            s.before_first()?;
+            *hasmore = s.next()?;
+        }
+        Ok(())
+    }
+
+    // Move the current table to its next record, and then
+    // choose the table having the lowest record as the
+    // new current table.
+    fn next(&mut self) -> DbResult<bool> {
+        if let Some(i) = self.current {
+            self.hasmore[i] = self.scans[i].next()?;
+        }
+        let mut lowest: Option<usize> = None;
+        for i in (0..self.scans.len()).filter(|&i| self.hasmore[i]) {
+            if let Some(j) = lowest {
+                if self.comp.compare(&self.scans[i], &self.scans[j])? != Ordering::Less {
+                    continue;
+                }
+            }
+            lowest = Some(i);
+        }
+        self.current = lowest;
+        Ok(lowest.is_some())
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.current_scan().get_int(fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        self.current_scan().get_string(fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        self.current_scan().get_val(fldname)
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        self.scans.first().is_some_and(|s| s.has_field(fldname))
+    }
+
+    fn close(&mut self) {
+        for s in self.scans.iter_mut() {
+            s.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, query::UpdateScan, record::Schema};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_exchange() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mut sch = Schema::new();
+        sch.add_int_field("a");
+        let tables: Vec<TempTable> = [vec![1, 4, 4, 9], vec![], vec![2, 3, 4, 10, 11]]
+            .into_iter()
+            .map(|vals| {
+                let t = TempTable::new(Arc::clone(&tx), &sch);
+                let mut s = t.open()?;
+                for val in vals {
+                    s.insert()?;
+                    s.set_int("a", val)?;
+                }
+                s.close();
+                Ok(t)
+            })
+            .collect::<DbResult<_>>()?;
+
+        let mut s = ExchangeScan::new(&tables, RecordComparator::new(vec!["a".to_string()]))?;
+        for _ in 0..2 {
+            let mut vals = Vec::new();
+            while s.next()? {
+                vals.push(s.get_int("a")?);
+            }
+            assert_eq!(vals, [1, 2, 3, 4, 4, 4, 9, 10, 11]);
+            s.before_first()?;
+        }
+        assert!(s.has_field("a"));
+        s.close();
+        tx.commit()?;
+        Ok(())
+    }
+}
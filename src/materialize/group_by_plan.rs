@@ -0,0 +1,322 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use super::{
+    AggregationFn, ExchangeScan, GroupByScan, RecordComparator, SortPlan, SortScan, TempTable,
+    WorkerPool,
+};
+use crate::{
+    error::{DbError, DbResult},
+    plan::Plan,
+    query::{Scan, UpdateScan},
+    record::Schema,
+    tx::Transaction,
+};
+
+// The Plan class for the groupby operator.
+// When the input is large and enough buffers are free, the
+// groups are computed on the threads of a worker pool: the
+// records are split into a partition per thread by the hash of
+// their group fields, so that each group lies in one partition,
+// and each thread sorts and groups its partition into a
+// temporary table. An ExchangeScan merges the tables in the
+// order of the group fields, as the sorted groups would be.
+pub struct GroupByPlan {
+    tx: Arc<Transaction>,
+    src: Arc<dyn Plan>,
+    p: SortPlan,
+    groupfields: Vec<String>,
+    aggfns: Vec<Box<dyn AggregationFn>>,
+    sch: Schema,
+}
+
+impl GroupByPlan {
+    // Create a groupby plan for the underlying query.
+    // The grouping is determined by the specified
+    // collection of group fields,
+    // and the aggregation is computed by the
+    // specified collection of aggregation functions.
+    pub fn new(
+        tx: Arc<Transaction>,
+        p: Box<dyn Plan>,
+        groupfields: Vec<String>,
+        aggfns: Vec<Box<dyn AggregationFn>>,
+    ) -> DbResult<Self> {
+        let mut sch = Schema::new();
+        for fldname in &groupfields {
+            if !p.schema().has_field(fldname) {
+                return Err(DbError::Catalog(format!("field {} not found", fldname)));
+            }
+            sch.add(fldname, p.schema());
+        }
+        for f in &aggfns {
+            f.add_to_schema(&mut sch, p.schema())?;
+        }
+        let src: Arc<dyn Plan> = Arc::from(p);
+        let p = SortPlan::new(
+            Arc::clone(&tx),
+            Box::new(Arc::clone(&src)),
+            groupfields.clone(),
+        );
+        Ok(GroupByPlan {
+            tx,
+            src,
+            p,
+            groupfields,
+            aggfns,
+            sch,
+        })
+    }
+
+    // Compute the groups using the threads of the specified pool.
+    pub fn open_parallel(&self, pool: &WorkerPool) -> DbResult<ExchangeScan> {
+        let partitions = self.partition(pool.workers())?;
+        let sorter = self.p.sorter();
+        let (tx, groupfields, aggfns, sch) = (&self.tx, &self.groupfields, &self.aggfns, &self.sch);
+        let tables = pool.map(partitions, |partition| {
+            let mut src = partition.open()?;
+            let runs = sorter.split_into_runs(&mut src)?;
+            src.close();
+            let runs = sorter.merge_runs(runs, 2)?;
+            let mut s = GroupByScan::new(
+                SortScan::new(&runs, sorter.comparator().clone())?,
+                groupfields.clone(),
+                aggfns.iter().map(|f| f.box_clone()).collect(),
+            )?;
+            let result = TempTable::new(Arc::clone(tx), sch);
+            let mut dest = result.open()?;
+            while s.next()? {
+                dest.insert()?;
+                for fldname in sch.fields() {
+                    dest.set_val(fldname, &s.get_val(fldname)?)?;
+                }
+            }
+            s.close();
+            dest.close();
+            Ok(result)
+        })?;
+        ExchangeScan::new(&tables, RecordComparator::new(self.groupfields.clone()))
+    }
+
+    // Split the input records into k partitions,
+    // by the hash of their group values.
+    fn partition(&self, k: usize) -> DbResult<Vec<TempTable>> {
+        let sch = self.src.schema();
+        let partitions: Vec<TempTable> = (0..k)
+            .map(|_| TempTable::new(Arc::clone(&self.tx), sch))
+            .collect();
+        let mut dests = partitions
+            .iter()
+            .map(|t| t.open())
+            .collect::<DbResult<Vec<_>>>()?;
+        let mut src = self.src.open()?;
+        while src.next()? {
+            let mut hasher = DefaultHasher::new();
+            for fldname in &self.groupfields {
+                src.get_val(fldname)?.hash(&mut hasher);
+            }
+            let dest = &mut dests[(hasher.finish() % k as u64) as usize];
+            dest.insert()?;
+            for fldname in sch.fields() {
+                dest.set_val(fldname, &src.get_val(fldname)?)?;
+            }
+        }
+        src.close();
+        for dest in dests.iter_mut() {
+            dest.close();
+        }
+        Ok(partitions)
+    }
+}
+
+impl Plan for GroupByPlan {
+    // This method opens a sort scan for the specified plan.
+    // The sort ensures that the underlying records
+    // will be appropriately grouped.
+    // Records with group fields are grouped in parallel instead,
+    // if the input is large enough to share out among the
+    // threads that the free buffers allow; without group fields,
+    // there is only one group, which a thread would compute alone.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        if !self.groupfields.is_empty() {
+            let blocks = self.p.blocks_accessed();
+            if let Some(pool) = WorkerPool::for_input(self.tx.work_buffs(), blocks) {
+                return Ok(Box::new(self.open_parallel(&pool)?));
+            }
+        }
+        let s = self.p.open_sort_scan()?;
+        let aggfns = self.aggfns.iter().map(|f| f.box_clone()).collect();
+        Ok(Box::new(GroupByScan::new(
+            s,
+            self.groupfields.clone(),
+            aggfns,
+        )?))
+    }
+
+    fn blocks_accessed(&self) -> u64 {
+        self.p.blocks_accessed()
+    }
+
+    // Return the number of groups. Assuming equal distribution,
+    // this is the product of the distinct values
+    // for each grouping field.
+    fn records_output(&self) -> u64 {
+        self.groupfields.iter().fold(1, |numgroups, fldname| {
+            numgroups.saturating_mul(self.p.distinct_values(fldname))
+        })
+    }
+
+    // Return the number of distinct values for the
+    // specified field. If the field is a grouping field,
+    // then the number of distinct values is the same
+    // as in the underlying query.
+    // If the field is an aggregate field, then we
+    // assume that all values are distinct.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        if self.p.schema().has_field(fldname) {
+            self.p.distinct_values(fldname)
+        } else {
+            self.records_output()
+        }
+    }
+
+    // Return the schema of the output table.
+    // The schema consists of the group fields,
+    // plus one field for each aggregation function.
+    fn schema(&self) -> &Schema {
+        &self.sch
+    }
+
+    fn description(&self) -> String {
+        let aggs: Vec<&str> = self.aggfns.iter().map(|f| f.field_name()).collect();
+        format!(
+            "GroupBy({}; {})",
+            self.groupfields.join(", "),
+            aggs.join(", ")
+        )
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![&self.p]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::SimpleDB,
+        materialize::AggregateFunc,
+        plan::{run_query, TablePlan},
+    };
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_group_by_aggregates() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update(
+            "create table emp (ename varchar(10), dept int, sal int)",
+            &tx,
+        )?;
+        for (i, (dept, sal)) in [(2, 10), (1, 40), (2, 30), (1, 20), (3, 5), (2, 20)]
+            .into_iter()
+            .enumerate()
+        {
+            let sql = format!(
+                "insert into emp (ename, dept, sal) values ('e{}', {}, {})",
+                i, dept, sal
+            );
+            planner.execute_update(&sql, &tx)?;
+        }
+
+        let qry = "select dept, count(ename), min(sal), max(sal), sum(sal), avg(sal) \
+                   from emp group by dept";
+        assert_eq!(
+            run_query(&planner, qry, &tx)?,
+            ["1 2 20 40 60 30", "2 3 10 30 60 20", "3 1 5 5 5 5"]
+        );
+
+        let grouped = qry;
+        let qry = "select count(sal), max(ename) from emp where sal = 20";
+        assert_eq!(run_query(&planner, qry, &tx)?, ["2 e5"]);
+
+        // the threads of a pool each group a partition of the
+        // records, and the exchange merges the groups in order
+        for i in 0..60 {
+            let sql = format!(
+                "insert into emp (ename, dept, sal) values ('x{}', {}, {})",
+                i,
+                i % 7 + 4,
+                i
+            );
+            planner.execute_update(&sql, &tx)?;
+        }
+        let mut expected = vec![
+            "1 2 20 40 60 30".to_string(),
+            "2 3 10 30 60 20".to_string(),
+            "3 1 5 5 5 5".to_string(),
+        ];
+        for dept in 4..11 {
+            let sals: Vec<i32> = (0..60).filter(|i| i % 7 + 4 == dept).collect();
+            let sum: i32 = sals.iter().sum();
+            expected.push(format!(
+                "{} {} {} {} {} {}",
+                dept,
+                sals.len(),
+                sals[0],
+                sals[sals.len() - 1],
+                sum,
+                sum / sals.len() as i32
+            ));
+        }
+        let src = TablePlan::new(Arc::clone(&tx), "emp", &mdm)?;
+        let aggfns = [
+            AggregateFunc::Count,
+            AggregateFunc::Min,
+            AggregateFunc::Max,
+            AggregateFunc::Sum,
+            AggregateFunc::Avg,
+        ]
+        .into_iter()
+        .map(|func| func.create("sal"))
+        .collect();
+        let plan = GroupByPlan::new(
+            Arc::clone(&tx),
+            Box::new(src),
+            vec!["dept".to_string()],
+            aggfns,
+        )?;
+        let mut s = plan.open_parallel(&WorkerPool::new(2))?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            let vals = plan
+                .schema()
+                .fields()
+                .iter()
+                .map(|fldname| s.get_val(fldname).map(|val| val.to_string()))
+                .collect::<DbResult<Vec<_>>>()?;
+            rows.push(vals.join(" "));
+        }
+        s.close();
+        assert_eq!(rows, expected);
+        assert_eq!(run_query(&planner, grouped, &tx)?, expected);
+
+        for qry in [
+            "select sum(ename) from emp",
+            "select ename, count(sal) from emp group by dept",
+            "select max(nosuchfield) from emp",
+        ] {
+            let result = planner.create_query_plan(qry, &tx);
+            assert!(matches!(result, Err(DbError::Catalog(_))), "{}", qry);
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use super::{AggregationFn, GroupValue};
+use crate::{
+    error::{DbError, DbResult},
+    query::{Constant, Scan},
+};
+
+// The Scan class for the groupby operator.
+pub struct GroupByScan<S> {
+    s: S,
+    groupfields: Vec<String>,
+    aggfns: Vec<Box<dyn AggregationFn>>,
+    groupval: Option<GroupValue>,
+    moregroups: bool,
+}
+
+impl<S: Scan> GroupByScan<S> {
+    // Create a groupby scan, given a grouped table scan.
+    pub fn new(
+        s: S,
+        groupfields: Vec<String>,
+        aggfns: Vec<Box<dyn AggregationFn>>,
+    ) -> DbResult<Self> {
+        let mut scan = GroupByScan {
+            s,
+            groupfields,
+            aggfns,
+            groupval: None,
+            moregroups: false,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+}
+
+impl<S: Scan> Scan for GroupByScan<S> {
+    // Position the scan before the first group.
+    // Internally, the underlying scan is always
+    // positioned at the first record of a group, which
+    // means that this method moves to the
+    // first underlying record.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.groupval = None;
+        self.s.before_first()?;
+        self.moregroups = self.s.next()?;
+        Ok(())
+    }
+
+    // Move to the next group.
+    // The key of the group is determined by the
+    // group values at the current record.
+    // The method repeatedly reads underlying records until
+    // it encounters a record having a different key.
+    // The aggregation functions are called for each record
+    // in the group.
+    // The values of the grouping fields for the group are saved.
+    fn next(&mut self) -> DbResult<bool> {
+        if !self.moregroups {
+            return Ok(false);
+        }
+        for f in self.aggfns.iter_mut() {
+            f.process_first(&self.s)?;
+        }
+        let groupval = GroupValue::new(&self.s, &self.groupfields)?;
+        loop {
+            self.moregroups = self.s.next()?;
+            if !self.moregroups || GroupValue::new(&self.s, &self.groupfields)? != groupval {
+                break;
+            }
+            for f in self.aggfns.iter_mut() {
+                f.process_next(&self.s)?;
+            }
+        }
+        self.groupval = Some(groupval);
+        Ok(true)
+    }
+
+    // As with a table scan, a null is read as 0.
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        match self.get_val(fldname)? {
+            Constant::Int(val) => Ok(val),
+            Constant::Null => Ok(0),
+            Constant::Str(_) | Constant::Blob(_) => Err(DbError::Catalog(format!(
+                "field {} is not an integer",
+                fldname
+            ))),
+        }
+    }
+
+    // As with a table scan, a null is read as the empty string.
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        match self.get_val(fldname)? {
+            Constant::Str(val) => Ok(val),
+            Constant::Null => Ok(String::new()),
+            Constant::Int(_) | Constant::Blob(_) => Err(DbError::Catalog(format!(
+                "field {} is not a string",
+                fldname
+            ))),
+        }
+    }
+
+    // Get the Constant value of the specified field.
+    // If the field is a group field, then its value can
+    // be obtained from the saved group value.
+    // Otherwise, the value is obtained from the
+    // appropriate aggregation function.
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        if self.groupfields.iter().any(|f| f == fldname) {
+            let groupval = self.groupval.as_ref().expect("scan is not positioned");
+            if let Some(val) = groupval.get_val(fldname) {
+                return Ok(val.clone());
+            }
+        }
+        self.aggfns
+            .iter()
+            .find(|f| f.field_name() == fldname)
+            .map(|f| f.value())
+            .ok_or_else(|| DbError::Catalog(format!("field {} not found", fldname)))
+    }
+
+    // Return true if the specified field is either a
+    // grouping field or created by an aggregation function.
+    fn has_field(&self, fldname: &str) -> bool {
+        self.groupfields.iter().any(|f| f == fldname)
+            || self.aggfns.iter().any(|f| f.field_name() == fldname)
+    }
+
+    fn close(&mut self) {
+        self.s.close();
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+};
+
+// An object that holds the values of the grouping fields
+// for the current record of a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupValue {
+    vals: HashMap<String, Constant>,
+}
+
+impl GroupValue {
+    // Create a new group value, given the specified scan
+    // and list of fields.
+    // The values in the current record of each field are
+    // stored.
+    pub fn new(s: &dyn Scan, fields: &[String]) -> DbResult<Self> {
+        let mut vals = HashMap::new();
+        for fldname in fields {
+            vals.insert(fldname.clone(), s.get_val(fldname)?);
+        }
+        Ok(GroupValue { vals })
+    }
+
+    // Return the Constant value of the specified field in the group.
+    pub fn get_val(&self, fldname: &str) -> Option<&Constant> {
+        self.vals.get(fldname)
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use super::{HashJoinScan, MaterializePlan, TempTable};
+use crate::{
+    error::DbResult,
+    plan::Plan,
+    query::{Constant, Scan, UpdateScan},
+    record::{Schema, TableScan},
+    tx::Transaction,
+};
+
+// The Plan class for the grace hash join operator.
+// The RHS is the build input, whose records are held in
+// memory in a hash table, and the LHS is the probe input,
+// whose records are looked up in it.
+// When the RHS does not fit in the available buffers, both
+// inputs are first split into partitions, stored in temporary
+// tables, by the hash of their join value, so that matching
+// records land in the same partition; the partitions are then
+// joined one at a time. A partition of the RHS that is still
+// too large is split again, using a different hash.
+pub struct HashJoinPlan {
+    tx: Arc<Transaction>,
+    p1: Arc<dyn Plan>,
+    p2: Arc<dyn Plan>,
+    fldname1: String,
+    fldname2: String,
+    sch: Schema,
+}
+
+impl HashJoinPlan {
+    // The number of times a partition is split again,
+    // which does not help when most of its records
+    // have the same join value.
+    const MAX_DEPTH: u32 = 3;
+
+    // Create a hash join plan for the two specified queries.
+    pub fn new(
+        tx: Arc<Transaction>,
+        p1: Box<dyn Plan>,
+        p2: Box<dyn Plan>,
+        fldname1: &str,
+        fldname2: &str,
+    ) -> Self {
+        let mut sch = Schema::new();
+        sch.add_all(p1.schema());
+        sch.add_all(p2.schema());
+        HashJoinPlan {
+            tx,
+            p1: Arc::from(p1),
+            p2: Arc::from(p2),
+            fldname1: fldname1.to_string(),
+            fldname2: fldname2.to_string(),
+            sch,
+        }
+    }
+
+    // Return the number of blocks of the RHS that can be held
+    // in memory, which is also the number of partitions that
+    // can be written at once. It is limited by the buffers that
+    // the query may claim, so that a tighter work limit makes
+    // the join partition sooner. As in a multibuffer product,
+    // a couple of buffers are reserved.
+    fn budget(&self) -> u64 {
+        self.tx.work_buffs().saturating_sub(2).max(1) as u64
+    }
+
+    // Return the number of partitions into which to split
+    // an RHS of the specified size, so that each fits in
+    // the budget, if possible.
+    fn num_partitions(size: u64, budget: u64) -> u64 {
+        size.div_ceil(budget).clamp(2, budget.max(2))
+    }
+
+    // Split the records of each scan into k partitions,
+    // by the hash of their join value. The records having
+    // a null join value join with nothing, and are dropped.
+    fn partition(
+        &self,
+        s1: &mut dyn Scan,
+        s2: &mut dyn Scan,
+        k: u64,
+        depth: u32,
+    ) -> DbResult<Vec<(TempTable, TempTable)>> {
+        let temps1 = self.split(s1, self.p1.schema(), &self.fldname1, k, depth)?;
+        let temps2 = self.split(s2, self.p2.schema(), &self.fldname2, k, depth)?;
+        Ok(temps1.into_iter().zip(temps2).collect())
+    }
+
+    fn split(
+        &self,
+        src: &mut dyn Scan,
+        sch: &Schema,
+        fldname: &str,
+        k: u64,
+        depth: u32,
+    ) -> DbResult<Vec<TempTable>> {
+        let temps: Vec<TempTable> = (0..k)
+            .map(|_| TempTable::new(Arc::clone(&self.tx), sch))
+            .collect();
+        let mut dests = temps
+            .iter()
+            .map(TempTable::open)
+            .collect::<DbResult<Vec<TableScan>>>()?;
+        src.before_first()?;
+        while src.next()? {
+            let val = src.get_val(fldname)?;
+            if val.is_null() {
+                continue;
+            }
+            let dest = &mut dests[bucket(&val, depth, k)];
+            dest.insert()?;
+            for fldname in sch.fields() {
+                dest.set_val(fldname, &src.get_val(fldname)?)?;
+            }
+        }
+        for mut dest in dests {
+            dest.close();
+        }
+        Ok(temps)
+    }
+}
+
+// Return the partition of the join value, out of k.
+// The depth is hashed along with the value, so that
+// the records of a partition are spread out when
+// it is split again.
+fn bucket(val: &Constant, depth: u32, k: u64) -> usize {
+    let mut hasher = DefaultHasher::new();
+    depth.hash(&mut hasher);
+    val.hash(&mut hasher);
+    (hasher.finish() % k) as usize
+}
+
+impl Plan for HashJoinPlan {
+    // The method builds the hash table from the RHS, if it
+    // fits in the available buffers, and returns a scan that
+    // probes it with the records of the LHS. Otherwise,
+    // it partitions the two inputs, and returns a scan that
+    // joins the partitions one after another.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let budget = self.budget();
+        let mut s1 = self.p1.open()?;
+        let mut s2 = self.p2.open()?;
+        if self.p2.blocks_accessed() <= budget {
+            let fields2 = self.p2.schema().fields().to_vec();
+            return Ok(Box::new(HashJoinScan::new(
+                s1,
+                s2,
+                &self.fldname1,
+                &self.fldname2,
+                fields2,
+            )?));
+        }
+
+        let k = Self::num_partitions(self.p2.blocks_accessed(), budget);
+        let mut pending: Vec<_> = self
+            .partition(&mut *s1, &mut *s2, k, 0)?
+            .into_iter()
+            .map(|(t1, t2)| (t1, t2, 0))
+            .collect();
+        s1.close();
+        s2.close();
+        let mut partitions = Vec::new();
+        while let Some((t1, t2, depth)) = pending.pop() {
+            let size = self.tx.size(&format!("{}.tbl", t2.table_name()))?;
+            if size <= budget || depth == Self::MAX_DEPTH {
+                partitions.push((t1, t2));
+                continue;
+            }
+            let mut s1 = t1.open()?;
+            let mut s2 = t2.open()?;
+            let k = Self::num_partitions(size, budget);
+            for (t1, t2) in self.partition(&mut s1, &mut s2, k, depth + 1)? {
+                pending.push((t1, t2, depth + 1));
+            }
+            s1.close();
+            s2.close();
+        }
+        let fields2 = self.p2.schema().fields().to_vec();
+        Ok(Box::new(HashJoinScan::partitioned(
+            partitions,
+            &self.fldname1,
+            &self.fldname2,
+            fields2,
+        )?))
+    }
+
+    // Return the number of block accesses required to
+    // hash join the queries. Each input is read once;
+    // when the RHS does not fit in the available buffers,
+    // the materialized partitions are also written and
+    // read back once. The method uses the current number of
+    // buffers that the query may claim, and so this value may differ
+    // when the query scan is opened.
+    fn blocks_accessed(&self) -> u64 {
+        let reads = self
+            .p1
+            .blocks_accessed()
+            .saturating_add(self.p2.blocks_accessed());
+        if self.p2.blocks_accessed() <= self.budget() {
+            return reads;
+        }
+        let mp1 = MaterializePlan::new(Arc::clone(&self.tx), Box::new(Arc::clone(&self.p1)));
+        let mp2 = MaterializePlan::new(Arc::clone(&self.tx), Box::new(Arc::clone(&self.p2)));
+        let partitions = mp1.blocks_accessed().saturating_add(mp2.blocks_accessed());
+        reads.saturating_add(partitions.saturating_mul(2))
+    }
+
+    // Return the number of records in the join.
+    // Assuming uniform distribution, the formula is:
+    // R(join(p1,p2)) = R(p1)*R(p2)/max{V(p1,F1),V(p2,F2)}
+    fn records_output(&self) -> u64 {
+        let maxvals = self
+            .p1
+            .distinct_values(&self.fldname1)
+            .max(self.p2.distinct_values(&self.fldname2))
+            .max(1);
+        self.p1
+            .records_output()
+            .saturating_mul(self.p2.records_output())
+            / maxvals
+    }
+
+    // Estimate the distinct number of field values in the join.
+    // Since the join does not increase or decrease field values,
+    // the estimate is the same as in the appropriate underlying query.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        if self.p1.schema().has_field(fldname) {
+            self.p1.distinct_values(fldname)
+        } else {
+            self.p2.distinct_values(fldname)
+        }
+    }
+
+    // Return the schema of the join,
+    // which is the union of the schemas of the underlying queries.
+    fn schema(&self) -> &Schema {
+        &self.sch
+    }
+
+    fn description(&self) -> String {
+        format!("HashJoin({}={})", self.fldname1, self.fldname2)
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![self.p1.as_ref(), self.p2.as_ref()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, plan::TablePlan};
+    use tempfile::TempDir;
+
+    fn joined_rows(s: &mut dyn Scan) -> DbResult<Vec<(i32, i32)>> {
+        let mut rows = Vec::new();
+        while s.next()? {
+            assert_eq!(s.get_int("la")?, s.get_int("ra")?);
+            rows.push((s.get_int("lid")?, s.get_int("rid")?));
+        }
+        rows.sort();
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_hash_join() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table l (la int, lid int)", &tx)?;
+        planner.execute_update("create table small (ra int, rid int)", &tx)?;
+        planner.execute_update("create table big (ra int, rid int, pad varchar(20))", &tx)?;
+        // every 7th record of l has a null join value
+        let lvals: Vec<Option<i32>> = (0..60).map(|i| (i % 7 != 0).then_some(i % 60)).collect();
+        for (i, v) in lvals.iter().enumerate() {
+            let la = v.map_or("null".to_string(), |v| v.to_string());
+            let sql = format!("insert into l (la, lid) values ({}, {})", la, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+        let smallvals = [3, 1, 3, 8, 5, 3, 0];
+        for (i, v) in smallvals.iter().enumerate() {
+            let sql = format!("insert into small (ra, rid) values ({}, {})", v, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+        let bigvals: Vec<i32> = (0..400).map(|i| i % 50).collect();
+        for (i, v) in bigvals.iter().enumerate() {
+            let sql = format!(
+                "insert into big (ra, rid, pad) values ({}, {}, 'padding{}')",
+                v, i, i
+            );
+            planner.execute_update(&sql, &tx)?;
+        }
+        let expected = |rvals: &[i32]| {
+            let mut expected = Vec::new();
+            for (i, lv) in lvals.iter().enumerate() {
+                for (j, rv) in rvals.iter().enumerate() {
+                    if *lv == Some(*rv) {
+                        expected.push((i as i32, j as i32));
+                    }
+                }
+            }
+            expected.sort();
+            expected
+        };
+
+        // the small table is held in memory, and the big one
+        // is partitioned, with some partitions split again
+        for (tblname, rvals) in [("small", &smallvals[..]), ("big", &bigvals[..])] {
+            let p1 = TablePlan::new(Arc::clone(&tx), "l", &mdm)?;
+            let p2 = TablePlan::new(Arc::clone(&tx), tblname, &mdm)?;
+            let fits = p2.blocks_accessed() <= tx.work_buffs() as u64 - 2;
+            assert_eq!(fits, tblname == "small");
+            let plan = HashJoinPlan::new(Arc::clone(&tx), Box::new(p1), Box::new(p2), "la", "ra");
+            assert_eq!(plan.description(), "HashJoin(la=ra)");
+            let mut s = plan.open()?;
+            assert_eq!(joined_rows(&mut *s)?, expected(rvals), "{}", tblname);
+
+            // the scan can be rescanned from the start
+            s.before_first()?;
+            assert_eq!(joined_rows(&mut *s)?, expected(rvals), "{}", tblname);
+            s.close();
+        }
+
+        // the planner hash joins tables that have no index
+        let qry = "select lid, rid from l, small where la = ra";
+        assert!(planner.explain(qry, &tx)?.contains("HashJoin("));
+        let plan = planner.create_query_plan(qry, &tx)?;
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            rows.push((s.get_int("lid")?, s.get_int("rid")?));
+        }
+        s.close();
+        rows.sort();
+        assert_eq!(rows, expected(&smallvals));
+        tx.commit()?;
+        Ok(())
+    }
+}
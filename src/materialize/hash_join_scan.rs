@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use super::TempTable;
+use crate::{
+    error::{DbError, DbResult},
+    query::{Constant, Scan},
+};
+
+// The Scan class for the grace hash join operator.
+// The records of the RHS, or of its current partition, are
+// held in a hash table keyed by their join value; the LHS,
+// or its current partition, is scanned once, and each of its
+// records is joined with the RHS records having its join value.
+pub struct HashJoinScan {
+    probe: Box<dyn Scan>,
+    // The pairs of LHS and RHS partitions, which are empty
+    // when the RHS was not partitioned.
+    partitions: Vec<(TempTable, TempTable)>,
+    current: usize,
+    fldname1: String,
+    fldname2: String,
+    fields2: Vec<String>,
+    table: HashMap<Constant, Vec<Vec<Constant>>>,
+    // The join value of the current LHS record, and the
+    // position of the current RHS record among those having it.
+    joinval: Option<Constant>,
+    pos: usize,
+}
+
+impl HashJoinScan {
+    // Create a hash join scan of the LHS scan and the records
+    // of the RHS scan, which are read into memory at once.
+    pub fn new(
+        probe: Box<dyn Scan>,
+        mut build: Box<dyn Scan>,
+        fldname1: &str,
+        fldname2: &str,
+        fields2: Vec<String>,
+    ) -> DbResult<Self> {
+        let mut scan = HashJoinScan {
+            probe,
+            partitions: Vec::new(),
+            current: 0,
+            fldname1: fldname1.to_string(),
+            fldname2: fldname2.to_string(),
+            fields2,
+            table: HashMap::new(),
+            joinval: None,
+            pos: 0,
+        };
+        scan.build(&mut *build)?;
+        build.close();
+        scan.probe.before_first()?;
+        Ok(scan)
+    }
+
+    // Create a hash join scan of the specified pairs of
+    // LHS and RHS partitions, which are joined in order.
+    pub fn partitioned(
+        partitions: Vec<(TempTable, TempTable)>,
+        fldname1: &str,
+        fldname2: &str,
+        fields2: Vec<String>,
+    ) -> DbResult<Self> {
+        let (t1, t2) = partitions.first().expect("no partitions to join");
+        let probe = Box::new(t1.open()?);
+        let mut build = t2.open()?;
+        let mut scan = HashJoinScan {
+            probe,
+            partitions,
+            current: 0,
+            fldname1: fldname1.to_string(),
+            fldname2: fldname2.to_string(),
+            fields2,
+            table: HashMap::new(),
+            joinval: None,
+            pos: 0,
+        };
+        scan.build(&mut build)?;
+        build.close();
+        Ok(scan)
+    }
+
+    // Read the records of the specified RHS scan
+    // into the hash table, in place of the previous ones.
+    // The records having a null join value are left out,
+    // since they join with nothing.
+    fn build(&mut self, src: &mut dyn Scan) -> DbResult<()> {
+        self.table.clear();
+        src.before_first()?;
+        while src.next()? {
+            let val = src.get_val(&self.fldname2)?;
+            if val.is_null() {
+                continue;
+            }
+            let row = self
+                .fields2
+                .iter()
+                .map(|fldname| src.get_val(fldname))
+                .collect::<DbResult<Vec<_>>>()?;
+            self.table.entry(val).or_default().push(row);
+        }
+        Ok(())
+    }
+
+    // Build the hash table from the specified RHS partition,
+    // and scan the matching LHS partition.
+    fn use_partition(&mut self, i: usize) -> DbResult<()> {
+        let (t1, t2) = &self.partitions[i];
+        let probe = Box::new(t1.open()?);
+        let mut build = t2.open()?;
+        self.probe.close();
+        self.probe = probe;
+        self.build(&mut build)?;
+        build.close();
+        self.current = i;
+        self.joinval = None;
+        Ok(())
+    }
+
+    fn current_row(&self) -> &[Constant] {
+        let joinval = self.joinval.as_ref().expect("scan is not positioned");
+        &self.table[joinval][self.pos]
+    }
+}
+
+impl Scan for HashJoinScan {
+    // Position the scan before the first record,
+    // by going back to the first partition, if any.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.joinval = None;
+        if self.partitions.is_empty() {
+            self.probe.before_first()
+        } else {
+            self.use_partition(0)
+        }
+    }
+
+    // Move to the next RHS record having the join value of
+    // the current LHS record, if there is one. Otherwise,
+    // move the LHS scan to its next record that has a match,
+    // going on to the next partition when the current one
+    // runs out. Return false when the last one does.
+    fn next(&mut self) -> DbResult<bool> {
+        if let Some(joinval) = &self.joinval {
+            if self.pos + 1 < self.table[joinval].len() {
+                self.pos += 1;
+                return Ok(true);
+            }
+            self.joinval = None;
+        }
+        loop {
+            if self.probe.next()? {
+                let val = self.probe.get_val(&self.fldname1)?;
+                if self.table.contains_key(&val) {
+                    self.joinval = Some(val);
+                    self.pos = 0;
+                    return Ok(true);
+                }
+            } else if self.current + 1 < self.partitions.len() {
+                self.use_partition(self.current + 1)?;
+            } else {
+                return Ok(false);
+            }
+        }
+    }
+
+    // Return the value of the specified field, from the
+    // LHS scan or from the current RHS record.
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        if self.probe.has_field(fldname) {
+            return self.probe.get_val(fldname);
+        }
+        let i = self
+            .fields2
+            .iter()
+            .position(|f| f == fldname)
+            .ok_or_else(|| DbError::Catalog(format!("field {} not found", fldname)))?;
+        Ok(self.current_row()[i].clone())
+    }
+
+    // As with a table scan, a null is read as 0.
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        match self.get_val(fldname)? {
+            Constant::Int(val) => Ok(val),
+            Constant::Null => Ok(0),
+            Constant::Str(_) | Constant::Blob(_) => Err(DbError::Catalog(format!(
+                "field {} is not an integer",
+                fldname
+            ))),
+        }
+    }
+
+    // As with a table scan, a null is read as the empty string.
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        match self.get_val(fldname)? {
+            Constant::Str(val) => Ok(val),
+            Constant::Null => Ok(String::new()),
+            Constant::Int(_) | Constant::Blob(_) => Err(DbError::Catalog(format!(
+                "field {} is not a string",
+                fldname
+            ))),
+        }
+    }
+
+    // Return true if the specified field is in
+    // either of the underlying inputs.
+    fn has_field(&self, fldname: &str) -> bool {
+        self.probe.has_field(fldname) || self.fields2.iter().any(|f| f == fldname)
+    }
+
+    fn close(&mut self) {
+        self.probe.close();
+        self.table.clear();
+    }
+}
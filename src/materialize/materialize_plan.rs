@@ -0,0 +1,149 @@
+use std::sync::Arc;
+
+use super::TempTable;
+use crate::{
+    error::DbResult,
+    plan::Plan,
+    query::{Scan, UpdateScan},
+    record::{Layout, Schema},
+    tx::Transaction,
+};
+
+// The Plan class for the materialize operator.
+pub struct MaterializePlan {
+    srcplan: Box<dyn Plan>,
+    tx: Arc<Transaction>,
+}
+
+impl MaterializePlan {
+    // Create a materialize plan for the specified query.
+    pub fn new(tx: Arc<Transaction>, srcplan: Box<dyn Plan>) -> Self {
+        MaterializePlan { srcplan, tx }
+    }
+
+    // Copy the output of the underlying query
+    // into a new temporary table, and return that table.
+    pub fn materialize(&self) -> DbResult<TempTable> {
+        let sch = self.srcplan.schema();
+        let temp = TempTable::new(Arc::clone(&self.tx), sch);
+        let mut src = self.srcplan.open()?;
+        let mut dest = temp.open()?;
+        while src.next()? {
+            dest.insert()?;
+            for fldname in sch.fields() {
+                dest.set_val(fldname, &src.get_val(fldname)?)?;
+            }
+        }
+        src.close();
+        dest.close();
+        Ok(temp)
+    }
+}
+
+impl Plan for MaterializePlan {
+    // This method loops through the underlying query,
+    // copying its output records into a temporary table.
+    // It then returns a table scan for that table.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let temp = self.materialize()?;
+        Ok(Box::new(temp.open()?))
+    }
+
+    // Return the estimated number of blocks in the
+    // materialized table.
+    // It does not include the one-time cost
+    // of materializing the records.
+    fn blocks_accessed(&self) -> u64 {
+        // create a dummy Layout object to calculate record length
+        let layout = Layout::new(self.srcplan.schema().clone());
+        let rpb = (self.tx.block_size() / layout.slot_size()).max(1) as u64;
+        self.srcplan.records_output().div_ceil(rpb)
+    }
+
+    // Return the number of records in the materialized table,
+    // which is the same as in the underlying plan.
+    fn records_output(&self) -> u64 {
+        self.srcplan.records_output()
+    }
+
+    // Return the number of distinct field values,
+    // which is the same as in the underlying plan.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        self.srcplan.distinct_values(fldname)
+    }
+
+    // Return the schema of the materialized table,
+    // which is the same as in the underlying plan.
+    fn schema(&self) -> &Schema {
+        self.srcplan.schema()
+    }
+
+    fn description(&self) -> String {
+        "Materialize".to_string()
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![self.srcplan.as_ref()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::SimpleDB,
+        plan::{SelectPlan, TablePlan},
+        query::{Constant, Expression, Predicate, Term},
+    };
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_materialize() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (a int, b varchar(10))", &tx)?;
+        for i in 0..20 {
+            let sql = format!("insert into t (a, b) values ({}, 'r{}')", i % 4, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+
+        let p = TablePlan::new(Arc::clone(&tx), "t", &mdm)?;
+        let pred = Predicate::from_term(Term::new(
+            Expression::Field("a".to_string()),
+            Expression::Constant(Constant::Int(1)),
+        ));
+        let plan = MaterializePlan::new(
+            Arc::clone(&tx),
+            Box::new(SelectPlan::new(Box::new(p), pred)),
+        );
+        assert_eq!(plan.schema().fields(), ["a", "b"]);
+
+        let temp = plan.materialize()?;
+        assert!(temp.table_name().starts_with("temp"));
+
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            assert_eq!(s.get_int("a")?, 1);
+            rows.push(s.get_string("b")?);
+        }
+        s.close();
+        assert_eq!(rows, ["r1", "r5", "r9", "r13", "r17"]);
+
+        // changes to the source are not seen by an existing temp table
+        planner.execute_update("delete from t where a = 1", &tx)?;
+        let mut ts = temp.open()?;
+        let mut count = 0;
+        while ts.next()? {
+            count += 1;
+        }
+        ts.close();
+        assert_eq!(count, 5);
+
+        tx.commit()?;
+        Ok(())
+    }
+}
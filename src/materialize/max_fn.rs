@@ -0,0 +1,74 @@
+use super::{aggregation_fn::check_field, AggregateFunc, AggregationFn};
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+    record::Schema,
+};
+
+// The max aggregation function.
+#[derive(Debug, Clone)]
+pub struct MaxFn {
+    fldname: String,
+    name: String,
+    val: Option<Constant>,
+}
+
+impl MaxFn {
+    // Create a max aggregation function for the specified field.
+    pub fn new(fldname: &str) -> Self {
+        MaxFn {
+            fldname: fldname.to_string(),
+            name: AggregateFunc::Max.field_name(fldname),
+            val: None,
+        }
+    }
+}
+
+impl AggregationFn for MaxFn {
+    // Start a new max to be the
+    // field value in the current record.
+    fn process_first(&mut self, s: &dyn Scan) -> DbResult<()> {
+        self.val = None;
+        self.process_next(s)
+    }
+
+    // Replace the current max by the field value
+    // in the current record, if it is higher.
+    // Null values are ignored.
+    fn process_next(&mut self, s: &dyn Scan) -> DbResult<()> {
+        let newval = s.get_val(&self.fldname)?;
+        if newval.is_null() {
+            return Ok(());
+        }
+        if self.val.as_ref().is_none_or(|val| &newval > val) {
+            self.val = Some(newval);
+        }
+        Ok(())
+    }
+
+    // Return the field's name, prepended by "maxof".
+    fn field_name(&self) -> &str {
+        &self.name
+    }
+
+    // Return the current maximum,
+    // or null if every value was null.
+    fn value(&self) -> Constant {
+        self.val.clone().unwrap_or(Constant::Null)
+    }
+
+    // The max has the same type as the aggregated field.
+    fn add_to_schema(&self, sch: &mut Schema, source: &Schema) -> DbResult<()> {
+        check_field(source, &self.fldname, false)?;
+        sch.add_field(
+            &self.name,
+            source.field_type(&self.fldname),
+            source.length(&self.fldname),
+        );
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use super::{MergeJoinScan, SortPlan};
+use crate::{error::DbResult, plan::Plan, query::Scan, record::Schema, tx::Transaction};
+
+// The Plan class for the mergejoin operator.
+pub struct MergeJoinPlan {
+    p1: SortPlan,
+    p2: SortPlan,
+    fldname1: String,
+    fldname2: String,
+    sch: Schema,
+}
+
+impl MergeJoinPlan {
+    // Create a mergejoin plan for the two specified queries.
+    // The RHS must be materialized after it is sorted,
+    // in order to deal with possible duplicates.
+    pub fn new(
+        tx: Arc<Transaction>,
+        p1: Box<dyn Plan>,
+        p2: Box<dyn Plan>,
+        fldname1: &str,
+        fldname2: &str,
+    ) -> Self {
+        let mut sch = Schema::new();
+        sch.add_all(p1.schema());
+        sch.add_all(p2.schema());
+        let p1 = SortPlan::new(Arc::clone(&tx), p1, vec![fldname1.to_string()]);
+        let p2 = SortPlan::new(tx, p2, vec![fldname2.to_string()]);
+        MergeJoinPlan {
+            p1,
+            p2,
+            fldname1: fldname1.to_string(),
+            fldname2: fldname2.to_string(),
+            sch,
+        }
+    }
+}
+
+impl Plan for MergeJoinPlan {
+    // The method first sorts its two underlying scans
+    // on their join field. It then returns a mergejoin scan
+    // of the two sorted table scans.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let s1 = self.p1.open()?;
+        let s2 = self.p2.open_sort_scan()?;
+        Ok(Box::new(MergeJoinScan::new(
+            s1,
+            s2,
+            &self.fldname1,
+            &self.fldname2,
+        )?))
+    }
+
+    // Return the number of block accesses required to
+    // mergejoin the sorted tables.
+    // Since a mergejoin can be performed with a single
+    // pass through each table, the method returns
+    // the sum of the block accesses of the
+    // materialized sorted tables.
+    // It does not include the one-time cost
+    // of materializing and sorting the records.
+    fn blocks_accessed(&self) -> u64 {
+        self.p1
+            .blocks_accessed()
+            .saturating_add(self.p2.blocks_accessed())
+    }
+
+    // Return the number of records in the join.
+    // Assuming uniform distribution, the formula is:
+    // R(join(p1,p2)) = R(p1)*R(p2)/max{V(p1,F1),V(p2,F2)}
+    fn records_output(&self) -> u64 {
+        let maxvals = self
+            .p1
+            .distinct_values(&self.fldname1)
+            .max(self.p2.distinct_values(&self.fldname2))
+            .max(1);
+        self.p1
+            .records_output()
+            .saturating_mul(self.p2.records_output())
+            / maxvals
+    }
+
+    // Estimate the distinct number of field values in the join.
+    // Since the join does not increase or decrease field values,
+    // the estimate is the same as in the appropriate underlying query.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        if self.p1.schema().has_field(fldname) {
+            self.p1.distinct_values(fldname)
+        } else {
+            self.p2.distinct_values(fldname)
+        }
+    }
+
+    // Return the schema of the join,
+    // which is the union of the schemas of the underlying queries.
+    fn schema(&self) -> &Schema {
+        &self.sch
+    }
+
+    fn description(&self) -> String {
+        format!("MergeJoin({}={})", self.fldname1, self.fldname2)
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![&self.p1, &self.p2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, plan::TablePlan};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_merge_join_with_duplicates() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table l (la int, lid int)", &tx)?;
+        planner.execute_update("create table r (ra int, rid int)", &tx)?;
+        let lvals = [5, 1, 3, 3, 7, 1, 9];
+        let rvals = [3, 1, 3, 8, 5, 3, 0];
+        for (i, v) in lvals.iter().enumerate() {
+            let sql = format!("insert into l (la, lid) values ({}, {})", v, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+        for (i, v) in rvals.iter().enumerate() {
+            let sql = format!("insert into r (ra, rid) values ({}, {})", v, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+
+        let mut expected = Vec::new();
+        for (i, lv) in lvals.iter().enumerate() {
+            for (j, rv) in rvals.iter().enumerate() {
+                if lv == rv {
+                    expected.push((i as i32, j as i32));
+                }
+            }
+        }
+        expected.sort();
+
+        let p1 = TablePlan::new(Arc::clone(&tx), "l", &mdm)?;
+        let p2 = TablePlan::new(Arc::clone(&tx), "r", &mdm)?;
+        let plan = MergeJoinPlan::new(Arc::clone(&tx), Box::new(p1), Box::new(p2), "la", "ra");
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            assert_eq!(s.get_int("la")?, s.get_int("ra")?);
+            rows.push((s.get_int("lid")?, s.get_int("rid")?));
+        }
+        rows.sort();
+        assert_eq!(rows, expected);
+
+        // the scan can be rescanned from the start
+        s.before_first()?;
+        let mut count = 0;
+        while s.next()? {
+            count += 1;
+        }
+        assert_eq!(count, expected.len());
+        s.close();
+
+        // the planner's join of the tables, when neither has an index, is the same
+        let plan = planner.create_query_plan("select lid, rid from l, r where la = ra", &tx)?;
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            rows.push((s.get_int("lid")?, s.get_int("rid")?));
+        }
+        s.close();
+        rows.sort();
+        assert_eq!(rows, expected);
+
+        tx.commit()?;
+        Ok(())
+    }
+}
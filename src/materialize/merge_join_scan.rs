@@ -0,0 +1,127 @@
+use std::cmp::Ordering;
+
+use super::SortScan;
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+};
+
+// The Scan class for the mergejoin operator.
+pub struct MergeJoinScan<S> {
+    s1: S,
+    s2: SortScan,
+    fldname1: String,
+    fldname2: String,
+    joinval: Option<Constant>,
+}
+
+impl<S: Scan> MergeJoinScan<S> {
+    // Create a mergejoin scan for the two underlying sorted scans.
+    pub fn new(s1: S, s2: SortScan, fldname1: &str, fldname2: &str) -> DbResult<Self> {
+        let mut scan = MergeJoinScan {
+            s1,
+            s2,
+            fldname1: fldname1.to_string(),
+            fldname2: fldname2.to_string(),
+            joinval: None,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+}
+
+impl<S: Scan> Scan for MergeJoinScan<S> {
+    // Position the scan before the first record,
+    // by positioning each underlying scan before
+    // their first records.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.joinval = None;
+        self.s1.before_first()?;
+        self.s2.before_first()
+    }
+
+    // Move to the next record. This is where the action is.
+    // If the next RHS record has the same join value,
+    // then move to it.
+    // Otherwise, if the next LHS record has the same join value,
+    // then reposition the RHS scan back to the first record
+    // having that join value.
+    // Otherwise, repeatedly move the scan having the smallest
+    // value until a common join value is found.
+    // Nulls join with nothing, so they are skipped.
+    // When one of the scans runs out of records, return false.
+    fn next(&mut self) -> DbResult<bool> {
+        let mut hasmore2 = self.s2.next()?;
+        if hasmore2 && self.joinval.as_ref() == Some(&self.s2.get_val(&self.fldname2)?) {
+            return Ok(true);
+        }
+
+        let mut hasmore1 = self.s1.next()?;
+        if hasmore1 && self.joinval.as_ref() == Some(&self.s1.get_val(&self.fldname1)?) {
+            self.s2.restore_position()?;
+            return Ok(true);
+        }
+
+        while hasmore1 && hasmore2 {
+            let v1 = self.s1.get_val(&self.fldname1)?;
+            let v2 = self.s2.get_val(&self.fldname2)?;
+            if v1.is_null() {
+                hasmore1 = self.s1.next()?;
+                continue;
+            }
+            if v2.is_null() {
+                hasmore2 = self.s2.next()?;
+                continue;
+            }
+            match v1.cmp(&v2) {
+                Ordering::Less => hasmore1 = self.s1.next()?,
+                Ordering::Greater => hasmore2 = self.s2.next()?,
+                Ordering::Equal => {
+                    self.s2.save_position();
+                    self.joinval = Some(v2);
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    // Return the value of the specified field.
+    // The value is obtained from whichever scan
+    // contains the field.
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        if self.s1.has_field(fldname) {
+            self.s1.get_int(fldname)
+        } else {
+            self.s2.get_int(fldname)
+        }
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        if self.s1.has_field(fldname) {
+            self.s1.get_string(fldname)
+        } else {
+            self.s2.get_string(fldname)
+        }
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        if self.s1.has_field(fldname) {
+            self.s1.get_val(fldname)
+        } else {
+            self.s2.get_val(fldname)
+        }
+    }
+
+    // Return true if the specified field is in
+    // either of the underlying scans.
+    fn has_field(&self, fldname: &str) -> bool {
+        self.s1.has_field(fldname) || self.s2.has_field(fldname)
+    }
+
+    // Close the scan by closing the two underlying scans.
+    fn close(&mut self) {
+        self.s1.close();
+        self.s2.close();
+    }
+}
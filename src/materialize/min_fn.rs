@@ -0,0 +1,74 @@
+use super::{aggregation_fn::check_field, AggregateFunc, AggregationFn};
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+    record::Schema,
+};
+
+// The min aggregation function.
+#[derive(Debug, Clone)]
+pub struct MinFn {
+    fldname: String,
+    name: String,
+    val: Option<Constant>,
+}
+
+impl MinFn {
+    // Create a min aggregation function for the specified field.
+    pub fn new(fldname: &str) -> Self {
+        MinFn {
+            fldname: fldname.to_string(),
+            name: AggregateFunc::Min.field_name(fldname),
+            val: None,
+        }
+    }
+}
+
+impl AggregationFn for MinFn {
+    // Start a new min to be the
+    // field value in the current record.
+    fn process_first(&mut self, s: &dyn Scan) -> DbResult<()> {
+        self.val = None;
+        self.process_next(s)
+    }
+
+    // Replace the current min by the field value
+    // in the current record, if it is lower.
+    // Null values are ignored.
+    fn process_next(&mut self, s: &dyn Scan) -> DbResult<()> {
+        let newval = s.get_val(&self.fldname)?;
+        if newval.is_null() {
+            return Ok(());
+        }
+        if self.val.as_ref().is_none_or(|val| &newval < val) {
+            self.val = Some(newval);
+        }
+        Ok(())
+    }
+
+    // Return the field's name, prepended by "minof".
+    fn field_name(&self) -> &str {
+        &self.name
+    }
+
+    // Return the current minimum,
+    // or null if every value was null.
+    fn value(&self) -> Constant {
+        self.val.clone().unwrap_or(Constant::Null)
+    }
+
+    // The min has the same type as the aggregated field.
+    fn add_to_schema(&self, sch: &mut Schema, source: &Schema) -> DbResult<()> {
+        check_field(source, &self.fldname, false)?;
+        sch.add_field(
+            &self.name,
+            source.field_type(&self.fldname),
+            source.length(&self.fldname),
+        );
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
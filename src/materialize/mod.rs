@@ -0,0 +1,45 @@
+mod aggregation_fn;
+mod avg_fn;
+mod count_fn;
+mod distinct_plan;
+mod distinct_scan;
+mod exchange_scan;
+mod group_by_plan;
+mod group_by_scan;
+mod group_value;
+mod hash_join_plan;
+mod hash_join_scan;
+mod materialize_plan;
+mod max_fn;
+mod merge_join_plan;
+mod merge_join_scan;
+mod min_fn;
+mod record_comparator;
+mod sort_plan;
+mod sort_scan;
+mod sum_fn;
+mod temp_table;
+mod worker_pool;
+
+pub use aggregation_fn::{AggregateFunc, AggregationFn};
+pub use avg_fn::AvgFn;
+pub use count_fn::CountFn;
+pub use distinct_plan::DistinctPlan;
+pub use distinct_scan::DistinctScan;
+pub use exchange_scan::ExchangeScan;
+pub use group_by_plan::GroupByPlan;
+pub use group_by_scan::GroupByScan;
+pub use group_value::GroupValue;
+pub use hash_join_plan::HashJoinPlan;
+pub use hash_join_scan::HashJoinScan;
+pub use materialize_plan::MaterializePlan;
+pub use max_fn::MaxFn;
+pub use merge_join_plan::MergeJoinPlan;
+pub use merge_join_scan::MergeJoinScan;
+pub use min_fn::MinFn;
+pub use record_comparator::{RecordComparator, SortOrder};
+pub use sort_plan::SortPlan;
+pub use sort_scan::SortScan;
+pub use sum_fn::SumFn;
+pub use temp_table::TempTable;
+pub use worker_pool::WorkerPool;
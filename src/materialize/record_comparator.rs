@@ -0,0 +1,68 @@
+use std::cmp::Ordering;
+
+use crate::{error::DbResult, query::Scan};
+
+// The direction in which a sort field is ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn name(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+// A comparator for scans.
+#[derive(Debug, Clone)]
+pub struct RecordComparator {
+    keys: Vec<(String, SortOrder)>,
+}
+
+impl RecordComparator {
+    // Create a comparator using the specified fields,
+    // using the ordering implied by its iterator.
+    // Each field is sorted in ascending order.
+    pub fn new(fields: Vec<String>) -> Self {
+        let keys = fields.into_iter().map(|f| (f, SortOrder::Asc)).collect();
+        RecordComparator { keys }
+    }
+
+    // Create a comparator using the specified sort keys,
+    // each of which pairs a field with its direction.
+    pub fn with_orders(keys: Vec<(String, SortOrder)>) -> Self {
+        RecordComparator { keys }
+    }
+
+    pub fn keys(&self) -> &[(String, SortOrder)] {
+        &self.keys
+    }
+
+    // Compare the current records of the two specified scans.
+    // The sort fields are considered in turn.
+    // When a field is encountered for which the records have
+    // different values, those values are used as the result
+    // of the comparison.
+    // A descending field reverses the comparison of its values.
+    // If the two records have the same values for all
+    // sort fields, then the method returns Equal.
+    pub fn compare(&self, s1: &dyn Scan, s2: &dyn Scan) -> DbResult<Ordering> {
+        for (fldname, order) in &self.keys {
+            let val1 = s1.get_val(fldname)?;
+            let val2 = s2.get_val(fldname)?;
+            let result = match order {
+                SortOrder::Asc => val1.cmp(&val2),
+                SortOrder::Desc => val2.cmp(&val1),
+            };
+            if result != Ordering::Equal {
+                return Ok(result);
+            }
+        }
+        Ok(Ordering::Equal)
+    }
+}
@@ -0,0 +1,338 @@
+use std::{cmp::Ordering, iter, sync::Arc};
+
+use super::{
+    ExchangeScan, MaterializePlan, RecordComparator, SortOrder, SortScan, TempTable, WorkerPool,
+};
+use crate::{
+    error::DbResult,
+    plan::Plan,
+    query::{Scan, UpdateScan},
+    record::{Schema, TableScan},
+    tx::Transaction,
+};
+
+// The Plan class for the sort operator.
+// The sort is an external merge sort: the input is split
+// into sorted runs stored in temporary tables, and the runs
+// are merged pairwise until at most two remain.
+// The final two runs are merged on the fly by the SortScan.
+// When the input is large and enough buffers are free, the
+// runs are instead merged on the threads of a worker pool,
+// each merging its share of them into one, and the threads'
+// runs are merged on the fly by an ExchangeScan.
+pub struct SortPlan {
+    tx: Arc<Transaction>,
+    p: Arc<dyn Plan>,
+    mp: MaterializePlan,
+    sorter: Sorter,
+}
+
+impl SortPlan {
+    // Create a sort plan for the specified query,
+    // sorting on the specified fields in ascending order.
+    pub fn new(tx: Arc<Transaction>, p: Box<dyn Plan>, sortfields: Vec<String>) -> Self {
+        Self::with_comparator(tx, p, RecordComparator::new(sortfields))
+    }
+
+    // Create a sort plan for the specified query,
+    // sorting on the specified keys, each ascending or descending.
+    pub fn with_orders(
+        tx: Arc<Transaction>,
+        p: Box<dyn Plan>,
+        sortkeys: Vec<(String, SortOrder)>,
+    ) -> Self {
+        Self::with_comparator(tx, p, RecordComparator::with_orders(sortkeys))
+    }
+
+    fn with_comparator(tx: Arc<Transaction>, p: Box<dyn Plan>, comp: RecordComparator) -> Self {
+        let p: Arc<dyn Plan> = Arc::from(p);
+        let mp = MaterializePlan::new(Arc::clone(&tx), Box::new(Arc::clone(&p)));
+        let sorter = Sorter::new(Arc::clone(&tx), p.schema(), comp);
+        SortPlan { tx, p, mp, sorter }
+    }
+
+    // This method is where most of the action is.
+    // Up to 2 sorted temporary tables are created,
+    // and are passed into SortScan for final merging.
+    // The merge join calls it directly, since it needs
+    // to save and restore positions in the sorted scan.
+    pub fn open_sort_scan(&self) -> DbResult<SortScan> {
+        let mut src = self.p.open()?;
+        let runs = self.sorter.split_into_runs(&mut *src)?;
+        src.close();
+        let runs = self.sorter.merge_runs(runs, 2)?;
+        SortScan::new(&runs, self.sorter.comparator().clone())
+    }
+
+    // Sort the records using the threads of the specified pool.
+    // The runs are dealt out to the threads in contiguous shares.
+    pub fn open_parallel(&self, pool: &WorkerPool) -> DbResult<ExchangeScan> {
+        let mut src = self.p.open()?;
+        let runs = self.sorter.split_into_runs(&mut *src)?;
+        src.close();
+        let size = runs.len().div_ceil(pool.workers());
+        let mut runs = runs.into_iter();
+        let shares: Vec<Vec<TempTable>> = iter::from_fn(|| {
+            let share: Vec<TempTable> = runs.by_ref().take(size).collect();
+            (!share.is_empty()).then_some(share)
+        })
+        .collect();
+        let sorter = &self.sorter;
+        let runs = pool.map(shares, |share| Ok(sorter.merge_runs(share, 1)?.remove(0)))?;
+        ExchangeScan::new(&runs, self.sorter.comparator().clone())
+    }
+
+    pub(super) fn sorter(&self) -> &Sorter {
+        &self.sorter
+    }
+}
+
+// The steps of an external merge sort of the records of
+// a schema. Unlike the plan, a sorter can be
+// shared by the threads of a worker pool.
+pub(super) struct Sorter {
+    tx: Arc<Transaction>,
+    sch: Schema,
+    comp: RecordComparator,
+}
+
+impl Sorter {
+    fn new(tx: Arc<Transaction>, sch: &Schema, comp: RecordComparator) -> Self {
+        Sorter {
+            tx,
+            sch: sch.clone(),
+            comp,
+        }
+    }
+
+    pub(super) fn comparator(&self) -> &RecordComparator {
+        &self.comp
+    }
+
+    // Copy the records of the scan into temporary tables,
+    // starting a new one whenever a record is lower than
+    // the previous one. There is always at least one run.
+    pub(super) fn split_into_runs(&self, src: &mut dyn Scan) -> DbResult<Vec<TempTable>> {
+        let mut temps = Vec::new();
+        let mut currenttemp = TempTable::new(Arc::clone(&self.tx), &self.sch);
+        let mut currentscan = currenttemp.open()?;
+        src.before_first()?;
+        if src.next()? {
+            while self.copy(src, &mut currentscan)? {
+                if self.comp.compare(src, &currentscan)? == Ordering::Less {
+                    // start a new run
+                    currentscan.close();
+                    temps.push(currenttemp);
+                    currenttemp = TempTable::new(Arc::clone(&self.tx), &self.sch);
+                    currentscan = currenttemp.open()?;
+                }
+            }
+        }
+        currentscan.close();
+        temps.push(currenttemp);
+        Ok(temps)
+    }
+
+    // Merge the runs pairwise until at most
+    // the specified number of them remain.
+    pub(super) fn merge_runs(
+        &self,
+        mut runs: Vec<TempTable>,
+        max: usize,
+    ) -> DbResult<Vec<TempTable>> {
+        while runs.len() > max {
+            runs = self.do_a_merge_iteration(runs)?;
+        }
+        Ok(runs)
+    }
+
+    fn do_a_merge_iteration(&self, runs: Vec<TempTable>) -> DbResult<Vec<TempTable>> {
+        let mut result = Vec::new();
+        let mut runs = runs.into_iter();
+        while let Some(p1) = runs.next() {
+            match runs.next() {
+                Some(p2) => result.push(self.merge_two_runs(&p1, &p2)?),
+                None => result.push(p1),
+            }
+        }
+        Ok(result)
+    }
+
+    fn merge_two_runs(&self, p1: &TempTable, p2: &TempTable) -> DbResult<TempTable> {
+        let mut src1 = p1.open()?;
+        let mut src2 = p2.open()?;
+        let result = TempTable::new(Arc::clone(&self.tx), &self.sch);
+        let mut dest = result.open()?;
+
+        let mut hasmore1 = src1.next()?;
+        let mut hasmore2 = src2.next()?;
+        while hasmore1 && hasmore2 {
+            if self.comp.compare(&src1, &src2)? == Ordering::Less {
+                hasmore1 = self.copy(&mut src1, &mut dest)?;
+            } else {
+                hasmore2 = self.copy(&mut src2, &mut dest)?;
+            }
+        }
+        while hasmore1 {
+            hasmore1 = self.copy(&mut src1, &mut dest)?;
+        }
+        while hasmore2 {
+            hasmore2 = self.copy(&mut src2, &mut dest)?;
+        }
+        src1.close();
+        src2.close();
+        dest.close();
+        Ok(result)
+    }
+
+    // Copy the current record of src into a new record of dest,
+    // and move src to its next record.
+    fn copy(&self, src: &mut dyn Scan, dest: &mut TableScan) -> DbResult<bool> {
+        dest.insert()?;
+        for fldname in self.sch.fields() {
+            dest.set_val(fldname, &src.get_val(fldname)?)?;
+        }
+        src.next()
+    }
+}
+
+impl Plan for SortPlan {
+    // Open a parallel sort if the input is large enough to
+    // share out among the threads that the free buffers allow,
+    // and a sort scan otherwise.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        match WorkerPool::for_input(self.tx.work_buffs(), self.mp.blocks_accessed()) {
+            Some(pool) => Ok(Box::new(self.open_parallel(&pool)?)),
+            None => Ok(Box::new(self.open_sort_scan()?)),
+        }
+    }
+
+    // Return the number of blocks in the sorted table,
+    // which is the same as it would be in a
+    // materialized table.
+    // It does not include the one-time cost
+    // of materializing and sorting the records.
+    fn blocks_accessed(&self) -> u64 {
+        self.mp.blocks_accessed()
+    }
+
+    // Return the number of records in the sorted table,
+    // which is the same as in the underlying query.
+    fn records_output(&self) -> u64 {
+        self.mp.records_output()
+    }
+
+    // Return the number of distinct field values in
+    // the sorted table, which is the same as in
+    // the underlying query.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        self.mp.distinct_values(fldname)
+    }
+
+    // Return the schema of the sorted table, which
+    // is the same as in the underlying query.
+    fn schema(&self) -> &Schema {
+        self.p.schema()
+    }
+
+    fn description(&self) -> String {
+        let keys: Vec<String> = self
+            .sorter
+            .comparator()
+            .keys()
+            .iter()
+            .map(|(fldname, order)| match order {
+                SortOrder::Asc => fldname.clone(),
+                SortOrder::Desc => format!("{} desc", fldname),
+            })
+            .collect();
+        format!("Sort({})", keys.join(", "))
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![self.p.as_ref()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, plan::TablePlan};
+    use tempfile::TempDir;
+
+    fn sorted_rows(plan: &dyn Plan) -> DbResult<Vec<(i32, String)>> {
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            rows.push((s.get_int("a")?, s.get_string("b")?));
+        }
+        s.close();
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_sort() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (a int, b varchar(10))", &tx)?;
+        planner.execute_update("create table empty (a int, b varchar(10))", &tx)?;
+
+        // scattered keys produce many short runs,
+        // which forces several merge iterations
+        let mut expected = Vec::new();
+        for i in 0..60 {
+            let a = (60 - i) % 7;
+            let b = format!("r{}", i);
+            let sql = format!("insert into t (a, b) values ({}, '{}')", a, b);
+            planner.execute_update(&sql, &tx)?;
+            expected.push((a, b));
+        }
+        expected.sort();
+
+        let p = TablePlan::new(Arc::clone(&tx), "t", &mdm)?;
+        let sp = SortPlan::new(
+            Arc::clone(&tx),
+            Box::new(p),
+            vec!["a".to_string(), "b".to_string()],
+        );
+        assert_eq!(sorted_rows(&sp)?, expected);
+
+        let p = TablePlan::new(Arc::clone(&tx), "empty", &mdm)?;
+        let sp = SortPlan::new(Arc::clone(&tx), Box::new(p), vec!["a".to_string()]);
+        assert!(sorted_rows(&sp)?.is_empty());
+
+        // the threads of a pool each merge a share of the runs,
+        // and the exchange merges their results
+        let pool = WorkerPool::new(2);
+        let p = TablePlan::new(Arc::clone(&tx), "t", &mdm)?;
+        let sp = SortPlan::with_orders(
+            Arc::clone(&tx),
+            Box::new(p),
+            vec![
+                ("a".to_string(), SortOrder::Desc),
+                ("b".to_string(), SortOrder::Asc),
+            ],
+        );
+        let mut s = sp.open_parallel(&pool)?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            rows.push((s.get_int("a")?, s.get_string("b")?));
+        }
+        s.close();
+        let mut desc = expected.clone();
+        desc.sort_by(|(a1, b1), (a2, b2)| a2.cmp(a1).then(b1.cmp(b2)));
+        assert_eq!(rows, desc);
+
+        let p = TablePlan::new(Arc::clone(&tx), "empty", &mdm)?;
+        let sp = SortPlan::new(Arc::clone(&tx), Box::new(p), vec!["a".to_string()]);
+        let mut s = sp.open_parallel(&pool)?;
+        assert!(!s.next()?);
+        s.close();
+
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::cmp::Ordering;
+
+use super::{RecordComparator, TempTable};
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan, UpdateScan},
+    record::{Rid, TableScan},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Current {
+    None,
+    S1,
+    S2,
+}
+
+// The position of a sort scan, as saved by save_position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SortPosition {
+    rid1: Rid,
+    rid2: Option<Rid>,
+    current: Current,
+    hasmore1: bool,
+    hasmore2: bool,
+}
+
+// The Scan class for the sort operator.
+pub struct SortScan {
+    s1: TableScan,
+    s2: Option<TableScan>,
+    current: Current,
+    comp: RecordComparator,
+    hasmore1: bool,
+    hasmore2: bool,
+    savedposition: Option<SortPosition>,
+}
+
+impl SortScan {
+    // Create a sort scan, given a list of 1 or 2 runs.
+    // If there is only 1 run, then s2 will be None and
+    // hasmore2 will be false.
+    pub fn new(runs: &[TempTable], comp: RecordComparator) -> DbResult<Self> {
+        let s1 = runs[0].open()?;
+        let s2 = runs.get(1).map(|run| run.open()).transpose()?;
+        let mut scan = SortScan {
+            s1,
+            s2,
+            current: Current::None,
+            comp,
+            hasmore1: false,
+            hasmore2: false,
+            savedposition: None,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+
+    // Save the position of the current record,
+    // so that it can be restored at a later time.
+    pub fn save_position(&mut self) {
+        self.savedposition = Some(SortPosition {
+            rid1: self.s1.get_rid(),
+            rid2: self.s2.as_ref().map(|s2| s2.get_rid()),
+            current: self.current,
+            hasmore1: self.hasmore1,
+            hasmore2: self.hasmore2,
+        });
+    }
+
+    // Move the scan to its previously-saved position.
+    pub fn restore_position(&mut self) -> DbResult<()> {
+        let pos = self.savedposition.expect("no saved position");
+        self.s1.move_to_rid(pos.rid1)?;
+        if let (Some(s2), Some(rid2)) = (self.s2.as_mut(), pos.rid2) {
+            s2.move_to_rid(rid2)?;
+        }
+        self.current = pos.current;
+        self.hasmore1 = pos.hasmore1;
+        self.hasmore2 = pos.hasmore2;
+        Ok(())
+    }
+
+    fn current_scan(&self) -> &TableScan {
+        match self.current {
+            Current::S1 => &self.s1,
+            Current::S2 => self.s2.as_ref().expect("second run exists"),
+            Current::None => panic!("sort scan is not positioned"),
+        }
+    }
+}
+
+impl Scan for SortScan {
+    // Position the scan before the first record in sorted order.
+    // Internally, it moves to the first record of each underlying scan.
+    // The variable current is None, indicating that there is
+    // no current scan.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.current = Current::None;
+        self.s1.before_first()?;
+        self.hasmore1 = self.s1.next()?;
+        if let Some(s2) = self.s2.as_mut() {
+            s2.before_first()?;
+            self.hasmore2 = s2.next()?;
+        }
+        Ok(())
+    }
+
+    // Move to the next record in sorted order.
+    // First, the current scan is moved to the next record.
+    // Then the lowest record of the two scans is found, and that
+    // scan is chosen to be the new current scan.
+    fn next(&mut self) -> DbResult<bool> {
+        match self.current {
+            Current::S1 => self.hasmore1 = self.s1.next()?,
+            Current::S2 => {
+                self.hasmore2 = self.s2.as_mut().expect("second run exists").next()?;
+            }
+            Current::None => {}
+        }
+
+        self.current = match (self.hasmore1, self.hasmore2) {
+            (false, false) => return Ok(false),
+            (true, true) => {
+                let s2 = self.s2.as_ref().expect("second run exists");
+                if self.comp.compare(&self.s1, s2)? == Ordering::Less {
+                    Current::S1
+                } else {
+                    Current::S2
+                }
+            }
+            (true, false) => Current::S1,
+            (false, true) => Current::S2,
+        };
+        Ok(true)
+    }
+
+    // Get the value of the specified field in the current scan.
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.current_scan().get_int(fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        self.current_scan().get_string(fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        self.current_scan().get_val(fldname)
+    }
+
+    // Return true if the specified field is in the current scan.
+    fn has_field(&self, fldname: &str) -> bool {
+        self.s1.has_field(fldname)
+    }
+
+    // Close the two underlying scans.
+    fn close(&mut self) {
+        self.s1.close();
+        if let Some(s2) = self.s2.as_mut() {
+            s2.close();
+        }
+    }
+}
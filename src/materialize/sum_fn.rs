@@ -0,0 +1,64 @@
+use super::{aggregation_fn::check_field, AggregateFunc, AggregationFn};
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+    record::Schema,
+};
+
+// The sum aggregation function.
+// Only integer fields can be summed.
+#[derive(Debug, Clone)]
+pub struct SumFn {
+    fldname: String,
+    name: String,
+    sum: Option<i32>,
+}
+
+impl SumFn {
+    // Create a sum aggregation function for the specified field.
+    pub fn new(fldname: &str) -> Self {
+        SumFn {
+            fldname: fldname.to_string(),
+            name: AggregateFunc::Sum.field_name(fldname),
+            sum: None,
+        }
+    }
+}
+
+impl AggregationFn for SumFn {
+    // Start a new sum at the field value in the current record.
+    fn process_first(&mut self, s: &dyn Scan) -> DbResult<()> {
+        self.sum = None;
+        self.process_next(s)
+    }
+
+    // Add the field value in the current record to the sum.
+    // Null values are ignored.
+    fn process_next(&mut self, s: &dyn Scan) -> DbResult<()> {
+        if let Some(val) = s.get_val(&self.fldname)?.as_int() {
+            self.sum = Some(self.sum.unwrap_or(0).wrapping_add(val));
+        }
+        Ok(())
+    }
+
+    // Return the field's name, prepended by "sumof".
+    fn field_name(&self) -> &str {
+        &self.name
+    }
+
+    // Return the current sum,
+    // or null if every value was null.
+    fn value(&self) -> Constant {
+        self.sum.map_or(Constant::Null, Constant::Int)
+    }
+
+    fn add_to_schema(&self, sch: &mut Schema, source: &Schema) -> DbResult<()> {
+        check_field(source, &self.fldname, true)?;
+        sch.add_int_field(&self.name);
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn AggregationFn> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::{
+    error::DbResult,
+    record::{Layout, Schema, TableScan},
+    tx::Transaction,
+};
+
+static NEXT_TABLE_NUM: AtomicUsize = AtomicUsize::new(0);
+
+// A class that creates temporary tables.
+// A temporary table is not registered in the catalog.
+// The class therefore has a method table_name to
+// return the table's name.
+pub struct TempTable {
+    tx: Arc<Transaction>,
+    tblname: String,
+    layout: Layout,
+}
+
+impl TempTable {
+    // Allocate a name for a new temporary table
+    // having the specified schema.
+    pub fn new(tx: Arc<Transaction>, sch: &Schema) -> Self {
+        TempTable {
+            tx,
+            tblname: Self::next_table_name(),
+            layout: Layout::new(sch.clone()),
+        }
+    }
+
+    // Open a table scan for the temporary table.
+    pub fn open(&self) -> DbResult<TableScan> {
+        TableScan::new(Arc::clone(&self.tx), &self.tblname, self.layout.clone())
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.tblname
+    }
+
+    // Return the table's metadata.
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    fn next_table_name() -> String {
+        let num = NEXT_TABLE_NUM.fetch_add(1, Ordering::SeqCst) + 1;
+        format!("temp{}", num)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SimpleDB;
+    use std::{collections::HashSet, thread};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_unique_names() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mut sch = Schema::new();
+        sch.add_int_field("a");
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let tx = Arc::clone(&tx);
+                let sch = sch.clone();
+                thread::spawn(move || {
+                    (0..25)
+                        .map(|_| {
+                            TempTable::new(Arc::clone(&tx), &sch)
+                                .table_name()
+                                .to_string()
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut names = HashSet::new();
+        for handle in handles {
+            for name in handle.join().unwrap() {
+                assert!(name.starts_with("temp"));
+                assert!(names.insert(name));
+            }
+        }
+        assert_eq!(names.len(), 100);
+        tx.commit()?;
+        Ok(())
+    }
+}
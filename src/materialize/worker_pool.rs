@@ -0,0 +1,133 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use crate::error::DbResult;
+
+// A pool of threads that runs the sub-operations of a
+// blocking operator, such as the sorting of each partition
+// of its input. The threads are scoped to a call of map,
+// and their number is limited both by the processors and
+// by the buffers that the sub-operations may pin at once.
+pub struct WorkerPool {
+    workers: usize,
+}
+
+impl WorkerPool {
+    // The number of buffers that a sub-operation pins at
+    // once: two runs being merged, and the run they are
+    // merged into.
+    pub const BUFFERS_PER_WORKER: usize = 3;
+
+    // The number of blocks of input that makes it worth
+    // starting another thread.
+    pub const BLOCKS_PER_WORKER: u64 = 8;
+
+    // Create a pool of the specified number of threads.
+    pub fn new(workers: usize) -> Self {
+        WorkerPool {
+            workers: workers.max(1),
+        }
+    }
+
+    // Return a pool for an input of the specified size,
+    // given the number of available buffers, or None if the
+    // input is too small or the buffers too few to run more
+    // than one sub-operation at a time.
+    // As in a multibuffer product, a couple of buffers are
+    // reserved.
+    pub fn for_input(available: usize, blocks: u64) -> Option<Self> {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        let workers = (available.saturating_sub(2) / Self::BUFFERS_PER_WORKER)
+            .min(cpus)
+            .min(usize::try_from(blocks / Self::BLOCKS_PER_WORKER).unwrap_or(usize::MAX));
+        (workers > 1).then(|| WorkerPool::new(workers))
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    // Apply the specified function to each task, on as many
+    // threads as the pool has, and return the results in the
+    // order of the tasks. If a task fails, the tasks not yet
+    // started are skipped, and the first error is returned.
+    pub fn map<T, R, F>(&self, tasks: Vec<T>, f: F) -> DbResult<Vec<R>>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(T) -> DbResult<R> + Sync,
+    {
+        let numtasks = tasks.len();
+        let tasks: Vec<Mutex<Option<T>>> = tasks.into_iter().map(|t| Mutex::new(Some(t))).collect();
+        let results: Vec<Mutex<Option<DbResult<R>>>> =
+            (0..numtasks).map(|_| Mutex::new(None)).collect();
+        let next = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..self.workers.min(numtasks) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= numtasks {
+                        break;
+                    }
+                    let task = tasks[i].lock().unwrap().take().expect("task taken twice");
+                    let result = f(task);
+                    let failed = result.is_err();
+                    *results[i].lock().unwrap() = Some(result);
+                    if failed {
+                        next.store(numtasks, Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+        results
+            .into_iter()
+            .map_while(|result| result.into_inner().unwrap())
+            .collect::<DbResult<Vec<_>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DbError;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_map() -> DbResult<()> {
+        let pool = WorkerPool::new(3);
+        let threads = Mutex::new(HashSet::new());
+        let results = pool.map((0..20).collect(), |i: i32| {
+            threads.lock().unwrap().insert(thread::current().id());
+            Ok(i * i)
+        })?;
+        assert_eq!(results, (0..20).map(|i| i * i).collect::<Vec<_>>());
+        let threads = threads.into_inner().unwrap();
+        assert!(!threads.is_empty() && threads.len() <= 3);
+        assert!(!threads.contains(&thread::current().id()));
+
+        assert!(pool.map(Vec::<i32>::new(), Ok)?.is_empty());
+
+        let result = pool.map((0..20).collect(), |i: i32| {
+            if i == 7 {
+                Err(DbError::Catalog("task failed".to_string()))
+            } else {
+                Ok(i)
+            }
+        });
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_for_input() {
+        assert!(WorkerPool::for_input(8, 1000).is_none_or(|pool| pool.workers() == 2));
+        assert!(WorkerPool::for_input(7, 1000).is_none());
+        assert!(WorkerPool::for_input(100, 15).is_none());
+        assert_eq!(WorkerPool::new(0).workers(), 1);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::{StatInfo, StatManager, TableManager};
+use crate::{
+    error::{DbError, DbResult},
+    index::{HashIndex, Index},
+    query::{Scan, UpdateScan},
+    record::{FieldType, Layout, Schema, TableScan},
+    tx::Transaction,
+};
+
+// The information about an index.
+// This information is used by the query planner in order to
+// estimate the costs of using the index,
+// and to obtain the layout of the index records.
+// Its methods are essentially the same as those of Plan.
+#[derive(Clone)]
+pub struct IndexInfo {
+    idxname: String,
+    fldname: String,
+    tx: Arc<Transaction>,
+    idx_layout: Layout,
+    si: StatInfo,
+}
+
+impl IndexInfo {
+    // Create an IndexInfo object for the specified index.
+    pub fn new(
+        idxname: &str,
+        fldname: &str,
+        tbl_schema: &Schema,
+        tx: Arc<Transaction>,
+        si: StatInfo,
+    ) -> Self {
+        let idx_layout = Self::create_idx_layout(tbl_schema, fldname);
+        IndexInfo {
+            idxname: idxname.to_string(),
+            fldname: fldname.to_string(),
+            tx,
+            idx_layout,
+            si,
+        }
+    }
+
+    pub fn index_name(&self) -> &str {
+        &self.idxname
+    }
+
+    pub fn field_name(&self) -> &str {
+        &self.fldname
+    }
+
+    // Open the index described by this object.
+    pub fn open(&self) -> Box<dyn Index> {
+        Box::new(HashIndex::new(
+            Arc::clone(&self.tx),
+            &self.idxname,
+            self.idx_layout.clone(),
+        ))
+    }
+
+    // Estimate the number of block accesses required to
+    // find all index records having a particular search key.
+    // The method uses the table's metadata to estimate the
+    // size of the index file and the number of index records
+    // per block.
+    // It then passes this information to the traversal_cost
+    // method of the appropriate index type,
+    // which provides the estimate.
+    pub fn blocks_accessed(&self) -> u64 {
+        let rpb = (self.tx.block_size() / self.idx_layout.slot_size()) as u64;
+        let numblocks = self.si.records_output() / rpb;
+        HashIndex::search_cost(numblocks, rpb)
+    }
+
+    // Return the estimated number of records having a
+    // search key. This value is the same as doing a select
+    // query; that is, it is the number of records in the table
+    // divided by the number of distinct values of the indexed field.
+    pub fn records_output(&self) -> u64 {
+        self.si.records_output() / self.si.distinct_values(&self.fldname)
+    }
+
+    // Return the distinct values for a specified field
+    // in the underlying table, or 1 for the indexed field.
+    pub fn distinct_values(&self, fname: &str) -> u64 {
+        if self.fldname == fname {
+            1
+        } else {
+            self.si.distinct_values(fname)
+        }
+    }
+
+    // Return the layout of the index records.
+    // The schema consists of the dataRID (which is
+    // represented as two integers, the block number and the
+    // record ID) and the dataval (which is the indexed field).
+    // Schema information about the indexed field is obtained
+    // via the table's schema.
+    fn create_idx_layout(tbl_schema: &Schema, fldname: &str) -> Layout {
+        let mut sch = Schema::new();
+        sch.add_int_field("block");
+        sch.add_int_field("id");
+        match tbl_schema.field_type(fldname) {
+            FieldType::Integer => sch.add_int_field("dataval"),
+            FieldType::Varchar => sch.add_string_field("dataval", tbl_schema.length(fldname)),
+            FieldType::Blob => sch.add_blob_field("dataval"),
+        }
+        Layout::new(sch)
+    }
+}
+
+// The index manager.
+// The index manager has similar functionality to the table manager.
+pub struct IndexManager {
+    layout: Layout,
+    tbl_mgr: Arc<TableManager>,
+    stat_mgr: Arc<StatManager>,
+}
+
+impl IndexManager {
+    // Create the index manager.
+    // This constructor is called during system startup.
+    // If the database is new, then the idxcat table is created.
+    pub fn new(
+        is_new: bool,
+        tbl_mgr: Arc<TableManager>,
+        stat_mgr: Arc<StatManager>,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<Self> {
+        if is_new {
+            let mut sch = Schema::new();
+            sch.add_string_field("indexname", TableManager::MAX_NAME);
+            sch.add_string_field("tablename", TableManager::MAX_NAME);
+            sch.add_string_field("fieldname", TableManager::MAX_NAME);
+            tbl_mgr.create_table("idxcat", &sch, tx)?;
+        }
+        let layout = tbl_mgr.get_layout("idxcat", tx)?;
+
+        Ok(IndexManager {
+            layout,
+            tbl_mgr,
+            stat_mgr,
+        })
+    }
+
+    // Create an index of the specified type for the specified field.
+    // A unique ID is assigned to this index, and its information
+    // is stored in the idxcat table.
+    // The name of the index must not be taken by another.
+    pub fn create_index(
+        &self,
+        idxname: &str,
+        tblname: &str,
+        fldname: &str,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<()> {
+        let mut ts = TableScan::new(Arc::clone(tx), "idxcat", self.layout.clone())?;
+        while ts.next()? {
+            if ts.get_string("indexname")? == idxname {
+                ts.close();
+                return Err(DbError::Catalog(format!(
+                    "index {} already exists",
+                    idxname
+                )));
+            }
+        }
+        ts.insert()?;
+        ts.set_string("indexname", idxname)?;
+        ts.set_string("tablename", tblname)?;
+        ts.set_string("fieldname", fldname)?;
+        ts.close();
+        Ok(())
+    }
+
+    // Remove the index from the idxcat table, and return
+    // the information it had, so that its files can be
+    // emptied.
+    pub fn drop_index(&self, idxname: &str, tx: &Arc<Transaction>) -> DbResult<IndexInfo> {
+        let mut entry = None;
+        let mut ts = TableScan::new(Arc::clone(tx), "idxcat", self.layout.clone())?;
+        while ts.next()? {
+            if ts.get_string("indexname")? == idxname {
+                entry = Some((ts.get_string("tablename")?, ts.get_string("fieldname")?));
+                ts.delete()?;
+                break;
+            }
+        }
+        ts.close();
+        let Some((tblname, fldname)) = entry else {
+            return Err(DbError::Catalog(format!("index {} not found", idxname)));
+        };
+
+        let tbl_layout = self.tbl_mgr.get_layout(&tblname, tx)?;
+        let tblsi = self.stat_mgr.get_stat_info(&tblname, &tbl_layout, tx)?;
+        Ok(IndexInfo::new(
+            idxname,
+            &fldname,
+            tbl_layout.schema(),
+            Arc::clone(tx),
+            tblsi,
+        ))
+    }
+
+    // Return a map containing the index info for all indexes
+    // on the specified table, keyed by the indexed field.
+    pub fn get_index_info(
+        &self,
+        tblname: &str,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<HashMap<String, IndexInfo>> {
+        let mut entries = Vec::new();
+        let mut ts = TableScan::new(Arc::clone(tx), "idxcat", self.layout.clone())?;
+        while ts.next()? {
+            if ts.get_string("tablename")? == tblname {
+                entries.push((ts.get_string("indexname")?, ts.get_string("fieldname")?));
+            }
+        }
+        ts.close();
+
+        let mut result = HashMap::new();
+        if entries.is_empty() {
+            return Ok(result);
+        }
+
+        let tbl_layout = self.tbl_mgr.get_layout(tblname, tx)?;
+        let tblsi = self.stat_mgr.get_stat_info(tblname, &tbl_layout, tx)?;
+        for (idxname, fldname) in entries {
+            let ii = IndexInfo::new(
+                &idxname,
+                &fldname,
+                tbl_layout.schema(),
+                Arc::clone(tx),
+                tblsi.clone(),
+            );
+            result.insert(fldname, ii);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, query::Constant};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_create_index_and_lookup() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+
+        let tm = Arc::new(TableManager::new(true, &tx)?);
+        let sm = Arc::new(StatManager::new(true, Arc::clone(&tm), &tx)?);
+        let im = IndexManager::new(true, Arc::clone(&tm), Arc::clone(&sm), &tx)?;
+
+        let mut sch = Schema::new();
+        sch.add_int_field("A");
+        sch.add_string_field("B", 9);
+        tm.create_table("MyTable", &sch, &tx)?;
+        im.create_index("indexA", "MyTable", "A", &tx)?;
+        im.create_index("indexB", "MyTable", "B", &tx)?;
+
+        let layout = tm.get_layout("MyTable", &tx)?;
+        let mut ts = TableScan::new(Arc::clone(&tx), "MyTable", layout)?;
+        for n in 0..20 {
+            ts.insert()?;
+            ts.set_int("A", n % 5)?;
+            ts.set_string("B", &format!("rec{}", n))?;
+        }
+
+        let indexes = im.get_index_info("MyTable", &tx)?;
+        assert_eq!(indexes.len(), 2);
+        let ii = &indexes["A"];
+        assert_eq!(ii.index_name(), "indexA");
+        assert_eq!(ii.distinct_values("A"), 1);
+
+        // Populate index A and look up one of its keys
+        let mut idx = ii.open();
+        ts.before_first()?;
+        while ts.next()? {
+            idx.insert(&ts.get_val("A")?, ts.get_rid())?;
+        }
+
+        let mut found = Vec::new();
+        idx.before_first(&Constant::Int(3))?;
+        while idx.next()? {
+            ts.move_to_rid(idx.get_data_rid()?)?;
+            found.push(ts.get_string("B")?);
+        }
+        idx.close();
+        ts.close();
+
+        found.sort();
+        assert_eq!(found, vec!["rec13", "rec18", "rec3", "rec8"]);
+        assert!(im.get_index_info("Other", &tx)?.is_empty());
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_info_estimates() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mut sch = Schema::new();
+        sch.add_int_field("A");
+        sch.add_string_field("B", 9);
+        let si = StatInfo::new(10, 100)
+            .with_distinct_values(HashMap::from([("A".to_string(), 5), ("B".to_string(), 50)]));
+        let ii = IndexInfo::new("indexA", "A", &sch, Arc::clone(&tx), si);
+        assert_eq!(ii.distinct_values("A"), 1);
+        assert_eq!(ii.distinct_values("B"), 50);
+        assert_eq!(ii.records_output(), 20);
+        tx.commit()?;
+        Ok(())
+    }
+}
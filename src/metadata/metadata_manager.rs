@@ -0,0 +1,172 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::{
+    IndexInfo, IndexManager, SequenceManager, StatInfo, StatManager, TableManager, ViewManager,
+};
+use crate::{
+    error::DbResult,
+    record::{Layout, Schema},
+    tx::Transaction,
+};
+
+// A single entry point to the table, view, sequence,
+// statistics and index catalogs.
+pub struct MetadataManager {
+    tblmgr: Arc<TableManager>,
+    viewmgr: ViewManager,
+    seqmgr: SequenceManager,
+    statmgr: Arc<StatManager>,
+    idxmgr: IndexManager,
+}
+
+impl MetadataManager {
+    // The tables that hold the catalog itself.
+    pub const CATALOG_TABLES: &'static [&'static str] =
+        &["tblcat", "fldcat", "viewcat", "seqcat", "statcat", "idxcat"];
+
+    pub fn new(is_new: bool, tx: &Arc<Transaction>) -> DbResult<Self> {
+        let tblmgr = Arc::new(TableManager::new(is_new, tx)?);
+        let viewmgr = ViewManager::new(is_new, &tblmgr, tx)?;
+        let seqmgr = SequenceManager::new(is_new, &tblmgr, tx)?;
+        let statmgr = Arc::new(StatManager::new(is_new, Arc::clone(&tblmgr), tx)?);
+        let idxmgr = IndexManager::new(is_new, Arc::clone(&tblmgr), Arc::clone(&statmgr), tx)?;
+
+        Ok(MetadataManager {
+            tblmgr,
+            viewmgr,
+            seqmgr,
+            statmgr,
+            idxmgr,
+        })
+    }
+
+    pub fn create_table(&self, tblname: &str, sch: &Schema, tx: &Arc<Transaction>) -> DbResult<()> {
+        self.tblmgr.create_table(tblname, sch, tx)
+    }
+
+    pub fn get_layout(&self, tblname: &str, tx: &Arc<Transaction>) -> DbResult<Layout> {
+        self.tblmgr.get_layout(tblname, tx)
+    }
+
+    // Return the names of the user tables, leaving out the catalog.
+    pub fn table_names(&self, tx: &Arc<Transaction>) -> DbResult<Vec<String>> {
+        let mut names = self.tblmgr.table_names(tx)?;
+        names.retain(|tblname| !Self::CATALOG_TABLES.contains(&tblname.as_str()));
+        Ok(names)
+    }
+
+    pub fn create_view(
+        &self,
+        viewname: &str,
+        viewdef: &str,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<()> {
+        self.viewmgr.create_view(viewname, viewdef, tx)
+    }
+
+    pub fn get_view_def(&self, viewname: &str, tx: &Arc<Transaction>) -> DbResult<Option<String>> {
+        self.viewmgr.get_view_def(viewname, tx)
+    }
+
+    pub fn view_names(&self, tx: &Arc<Transaction>) -> DbResult<Vec<String>> {
+        self.viewmgr.view_names(tx)
+    }
+
+    pub fn create_sequences(
+        &self,
+        tblname: &str,
+        fldnames: &[String],
+        tx: &Arc<Transaction>,
+    ) -> DbResult<()> {
+        self.seqmgr.create_sequences(tblname, fldnames, tx)
+    }
+
+    pub fn sequence_fields(&self, tblname: &str) -> Vec<String> {
+        self.seqmgr.sequence_fields(tblname)
+    }
+
+    pub fn next_value(&self, tblname: &str, fldname: &str, tx: &Arc<Transaction>) -> DbResult<i32> {
+        self.seqmgr.next_value(tblname, fldname, tx)
+    }
+
+    pub fn advance_past(
+        &self,
+        tblname: &str,
+        fldname: &str,
+        val: i32,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<()> {
+        self.seqmgr.advance_past(tblname, fldname, val, tx)
+    }
+
+    pub fn create_index(
+        &self,
+        idxname: &str,
+        tblname: &str,
+        fldname: &str,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<()> {
+        self.idxmgr.create_index(idxname, tblname, fldname, tx)
+    }
+
+    pub fn drop_index(&self, idxname: &str, tx: &Arc<Transaction>) -> DbResult<IndexInfo> {
+        self.idxmgr.drop_index(idxname, tx)
+    }
+
+    pub fn get_index_info(
+        &self,
+        tblname: &str,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<HashMap<String, IndexInfo>> {
+        self.idxmgr.get_index_info(tblname, tx)
+    }
+
+    pub fn get_stat_info(
+        &self,
+        tblname: &str,
+        layout: &Layout,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<StatInfo> {
+        self.statmgr.get_stat_info(tblname, layout, tx)
+    }
+
+    pub fn analyze(
+        &self,
+        tblname: &str,
+        layout: &Layout,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<StatInfo> {
+        self.statmgr.analyze(tblname, layout, tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, parse::Parser, record::FieldType};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_create_table_from_sql() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = MetadataManager::new(true, &tx)?;
+
+        let data =
+            Parser::new("create table student (sid int, sname varchar(10))")?.create_table()?;
+        mdm.create_table(data.table_name(), data.new_schema(), &tx)?;
+
+        let layout = mdm.get_layout("student", &tx)?;
+        assert_eq!(layout.schema(), data.new_schema());
+        assert_eq!(layout.schema().field_type("sid"), FieldType::Integer);
+        assert_eq!(layout.schema().length("sname"), 10);
+        assert_eq!(
+            layout.slot_size(),
+            Layout::new(data.new_schema().clone()).slot_size()
+        );
+        tx.commit()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+mod index_manager;
+mod metadata_manager;
+mod sequence_manager;
+mod stat_manager;
+mod table_manager;
+mod view_manager;
+
+pub use index_manager::{IndexInfo, IndexManager};
+pub use metadata_manager::MetadataManager;
+pub use sequence_manager::SequenceManager;
+pub use stat_manager::{StatInfo, StatManager};
+pub use table_manager::TableManager;
+pub use view_manager::ViewManager;
@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use super::TableManager;
+use crate::{
+    error::{DbError, DbResult},
+    file::BlockId,
+    query::{Scan, UpdateScan},
+    record::{Layout, RecordPage, Rid, Schema, TableScan},
+    tx::Transaction,
+};
+
+// The sequence manager.
+// Each auto-increment field has a sequence, whose next
+// value is saved in the seqcat table. The auto-increment
+// fields of each table, and where their records are, are
+// read from seqcat once, when the manager is created.
+// A value is allocated under a latch of the manager rather
+// than a lock of the inserting transaction, so that
+// concurrent inserts do not wait for each other to complete.
+// The change to the sequence is logged, and so recovered,
+// but it is not undone if the transaction rolls back:
+// the values it allocated are lost, leaving a gap.
+pub struct SequenceManager {
+    layout: Layout,
+    // the auto-increment fields of each table, with the
+    // record of their sequence; the mutex is the latch
+    // under which values are allocated
+    fields: Mutex<HashMap<String, Vec<(String, Rid)>>>,
+}
+
+impl SequenceManager {
+    // Create the sequence manager.
+    // If the database is new, then the seqcat table is created;
+    // otherwise the sequences are read from it.
+    pub fn new(is_new: bool, tbl_mgr: &TableManager, tx: &Arc<Transaction>) -> DbResult<Self> {
+        let mut sch = Schema::new();
+        sch.add_string_field("tblname", TableManager::MAX_NAME);
+        sch.add_string_field("fldname", TableManager::MAX_NAME);
+        sch.add_int_field("nextval");
+        if is_new {
+            tbl_mgr.create_table("seqcat", &sch, tx)?;
+        }
+        let layout = Layout::new(sch);
+        let mut fields: HashMap<String, Vec<(String, Rid)>> = HashMap::new();
+        // an empty catalog is not scanned,
+        // which would append a block to it
+        if !is_new && tx.size("seqcat.tbl")? > 0 {
+            let mut ts = TableScan::new(Arc::clone(tx), "seqcat", layout.clone())?;
+            while ts.next()? {
+                fields
+                    .entry(ts.get_string("tblname")?)
+                    .or_default()
+                    .push((ts.get_string("fldname")?, ts.get_rid()));
+            }
+            ts.close();
+        }
+        Ok(SequenceManager {
+            layout,
+            fields: Mutex::new(fields),
+        })
+    }
+
+    // Create the sequences of the specified auto-increment
+    // fields of a new table, whose first value is 1.
+    // The fields replace any that a table of the same name
+    // had, such as one created by a transaction that rolled back.
+    pub fn create_sequences(
+        &self,
+        tblname: &str,
+        fldnames: &[String],
+        tx: &Arc<Transaction>,
+    ) -> DbResult<()> {
+        let mut sequences = Vec::new();
+        if !fldnames.is_empty() {
+            let mut ts = TableScan::new(Arc::clone(tx), "seqcat", self.layout.clone())?;
+            for fldname in fldnames {
+                ts.insert()?;
+                ts.set_string("tblname", tblname)?;
+                ts.set_string("fldname", fldname)?;
+                ts.set_int("nextval", 1)?;
+                sequences.push((fldname.clone(), ts.get_rid()));
+            }
+            ts.close();
+        }
+        let mut fields = self.fields.lock().unwrap();
+        if sequences.is_empty() {
+            fields.remove(tblname);
+        } else {
+            fields.insert(tblname.to_string(), sequences);
+        }
+        Ok(())
+    }
+
+    // Return the names of the auto-increment fields of the table.
+    pub fn sequence_fields(&self, tblname: &str) -> Vec<String> {
+        self.fields
+            .lock()
+            .unwrap()
+            .get(tblname)
+            .map_or_else(Vec::new, |fields| {
+                fields.iter().map(|(fldname, _)| fldname.clone()).collect()
+            })
+    }
+
+    // Allocate the next value of the sequence of the field.
+    // The largest int is never allocated, and marks
+    // a sequence that has run out of values.
+    pub fn next_value(&self, tblname: &str, fldname: &str, tx: &Arc<Transaction>) -> DbResult<i32> {
+        self.update(tblname, fldname, tx, |nextval| {
+            if nextval == i32::MAX {
+                return Err(DbError::Catalog(format!(
+                    "sequence of field {} of table {} is exhausted",
+                    fldname, tblname
+                )));
+            }
+            Ok(nextval + 1)
+        })
+    }
+
+    // Make sure the sequence of the field does not
+    // allocate the specified value, which was inserted
+    // explicitly, or any value below it.
+    pub fn advance_past(
+        &self,
+        tblname: &str,
+        fldname: &str,
+        val: i32,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<()> {
+        self.update(tblname, fldname, tx, |nextval| {
+            Ok(nextval.max(val.saturating_add(1)))
+        })?;
+        Ok(())
+    }
+
+    // Replace the next value of the sequence by the value
+    // that f returns, as a nested top action of the
+    // transaction, and return the value replaced.
+    fn update(
+        &self,
+        tblname: &str,
+        fldname: &str,
+        tx: &Arc<Transaction>,
+        f: impl FnOnce(i32) -> DbResult<i32>,
+    ) -> DbResult<i32> {
+        let fields = self.fields.lock().unwrap();
+        let rid = fields
+            .get(tblname)
+            .and_then(|fields| fields.iter().find(|(f, _)| f == fldname))
+            .map(|(_, rid)| *rid)
+            .ok_or_else(|| {
+                DbError::Catalog(format!(
+                    "field {} of table {} has no sequence",
+                    fldname, tblname
+                ))
+            })?;
+        let blk = BlockId::new("seqcat.tbl", rid.block_number());
+        let rp = RecordPage::new(Arc::clone(tx), blk, self.layout.clone())?;
+        let result = rp.update_int_nested(rid.slot(), "nextval", f);
+        rp.close();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SimpleDB;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sequences() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let tblmgr = TableManager::new(true, &tx)?;
+        let seqmgr = SequenceManager::new(true, &tblmgr, &tx)?;
+        assert!(seqmgr.sequence_fields("t").is_empty());
+
+        seqmgr.create_sequences("t", &["id".to_string()], &tx)?;
+        seqmgr.create_sequences("u", &["id".to_string()], &tx)?;
+        assert_eq!(seqmgr.sequence_fields("t"), ["id"]);
+        assert_eq!(seqmgr.next_value("t", "id", &tx)?, 1);
+        assert_eq!(seqmgr.next_value("t", "id", &tx)?, 2);
+        assert_eq!(seqmgr.next_value("u", "id", &tx)?, 1);
+
+        // an explicit value moves the sequence past it, never back
+        seqmgr.advance_past("t", "id", 10, &tx)?;
+        seqmgr.advance_past("t", "id", 5, &tx)?;
+        assert_eq!(seqmgr.next_value("t", "id", &tx)?, 11);
+        tx.commit()?;
+
+        // the values allocated by a rolled back transaction
+        // are not allocated again
+        let tx = db.new_tx()?;
+        assert_eq!(seqmgr.next_value("t", "id", &tx)?, 12);
+        tx.rollback()?;
+        let tx = db.new_tx()?;
+        assert_eq!(seqmgr.next_value("t", "id", &tx)?, 13);
+
+        seqmgr.advance_past("u", "id", i32::MAX, &tx)?;
+        let result = seqmgr.next_value("u", "id", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        let result = seqmgr.next_value("t", "name", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+
+        // the sequences are read back from the catalog
+        let tx = db.new_tx()?;
+        let seqmgr = SequenceManager::new(false, &tblmgr, &tx)?;
+        assert_eq!(seqmgr.sequence_fields("u"), ["id"]);
+        assert_eq!(seqmgr.next_value("t", "id", &tx)?, 14);
+
+        // the sequences of a table created by a rolled back
+        // transaction are replaced when it is created again
+        seqmgr.create_sequences("v", &["id".to_string()], &tx)?;
+        tx.rollback()?;
+        let tx = db.new_tx()?;
+        seqmgr.create_sequences("v", &[], &tx)?;
+        assert!(seqmgr.sequence_fields("v").is_empty());
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequences_take_no_locks() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let tblmgr = TableManager::new(true, &tx)?;
+        let seqmgr = SequenceManager::new(true, &tblmgr, &tx)?;
+        seqmgr.create_sequences("t", &["id".to_string()], &tx)?;
+        tx.commit()?;
+
+        // a transaction allocating a value does not keep
+        // another from allocating the next one
+        let tx1 = db.new_tx()?;
+        let tx2 = db.new_tx()?;
+        tx2.set_lock_timeout(Duration::from_millis(100));
+        assert_eq!(seqmgr.next_value("t", "id", &tx1)?, 1);
+        assert_eq!(seqmgr.next_value("t", "id", &tx2)?, 2);
+        assert!(tx1.held_locks().is_empty());
+        tx1.rollback()?;
+        tx2.commit()?;
+        let tx = db.new_tx()?;
+        assert_eq!(seqmgr.next_value("t", "id", &tx)?, 3);
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use super::TableManager;
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan, UpdateScan},
+    record::{Layout, Schema, TableScan},
+    tx::Transaction,
+};
+
+// Holds three pieces of statistical information about a table:
+// the number of blocks, the number of records,
+// and the number of distinct values for each field.
+// The distinct values are only counted by ANALYZE.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatInfo {
+    num_blocks: u64,
+    num_recs: u64,
+    num_values: HashMap<String, u64>,
+}
+
+impl StatInfo {
+    pub fn new(num_blocks: u64, num_recs: u64) -> Self {
+        StatInfo {
+            num_blocks,
+            num_recs,
+            num_values: HashMap::new(),
+        }
+    }
+
+    // Add the number of distinct values of each field,
+    // as counted by ANALYZE.
+    pub fn with_distinct_values(mut self, num_values: HashMap<String, u64>) -> Self {
+        self.num_values = num_values;
+        self
+    }
+
+    pub fn blocks_accessed(&self) -> u64 {
+        self.num_blocks
+    }
+
+    pub fn records_output(&self) -> u64 {
+        self.num_recs
+    }
+
+    // Return the estimated number of distinct values
+    // for the specified field.
+    // Unless the table has been analyzed, this estimate
+    // is a complete guess, because doing something
+    // reasonable is beyond the scope of this system.
+    pub fn distinct_values(&self, fldname: &str) -> u64 {
+        match self.num_values.get(fldname) {
+            Some(&n) => n.clamp(1, self.num_recs.max(1)),
+            None => 1 + (self.num_recs / 3),
+        }
+    }
+}
+
+struct StatState {
+    table_stats: HashMap<String, StatInfo>,
+    num_calls: usize,
+}
+
+// The statistics manager is responsible for
+// keeping statistical information about each table.
+// The manager calculates this information on system startup,
+// and periodically refreshes it, by scanning each table.
+// The statistics of an analyzed table are instead saved in
+// the statcat table, with a record for each of its fields,
+// and are scaled to the current size of the table when
+// refreshed, so that it is not scanned again.
+pub struct StatManager {
+    tbl_mgr: Arc<TableManager>,
+    layout: Layout,
+    state: Mutex<StatState>,
+}
+
+impl StatManager {
+    const REFRESH_CALLS: usize = 100;
+
+    // Create the statistics manager.
+    // If the database is new, then the statcat table is created.
+    // The initial statistics are calculated by
+    // traversing the entire database.
+    pub fn new(is_new: bool, tbl_mgr: Arc<TableManager>, tx: &Arc<Transaction>) -> DbResult<Self> {
+        let mut sch = Schema::new();
+        sch.add_string_field("tblname", TableManager::MAX_NAME);
+        sch.add_string_field("fldname", TableManager::MAX_NAME);
+        sch.add_int_field("numblocks");
+        sch.add_int_field("numrecs");
+        sch.add_int_field("numvalues");
+        if is_new {
+            tbl_mgr.create_table("statcat", &sch, tx)?;
+        }
+        let sm = StatManager {
+            tbl_mgr,
+            layout: Layout::new(sch),
+            state: Mutex::new(StatState {
+                table_stats: HashMap::new(),
+                num_calls: 0,
+            }),
+        };
+        sm.refresh_statistics(&mut sm.state.lock().unwrap(), tx)?;
+        Ok(sm)
+    }
+
+    // Return the statistical information about the specified table.
+    pub fn get_stat_info(
+        &self,
+        tblname: &str,
+        layout: &Layout,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<StatInfo> {
+        let mut state = self.state.lock().unwrap();
+        state.num_calls += 1;
+        if state.num_calls > Self::REFRESH_CALLS {
+            self.refresh_statistics(&mut state, tx)?;
+        }
+
+        if let Some(si) = state.table_stats.get(tblname) {
+            return Ok(si.clone());
+        }
+        let saved = self.saved_stats(tx)?.remove(tblname);
+        let si = Self::calc_table_stats(tblname, layout, saved, tx)?;
+        // an empty table is likely about to be loaded,
+        // so its statistics are calculated again next time
+        if si.records_output() > 0 {
+            state.table_stats.insert(tblname.to_string(), si.clone());
+        }
+        Ok(si)
+    }
+
+    // Scan the table, counting its blocks, its records,
+    // and the distinct non-null values of each field, and save
+    // the counts in statcat, replacing those of an earlier analysis.
+    pub fn analyze(
+        &self,
+        tblname: &str,
+        layout: &Layout,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<StatInfo> {
+        let mut num_recs = 0;
+        let mut num_blocks = 0;
+        let mut values: HashMap<&str, HashSet<Constant>> = HashMap::new();
+        let mut ts = TableScan::new(Arc::clone(tx), tblname, layout.clone())?;
+        while ts.next()? {
+            num_recs += 1;
+            num_blocks = ts.get_rid().block_number() + 1;
+            for fldname in layout.schema().fields() {
+                let val = ts.get_val(fldname)?;
+                let fldvalues = values.entry(fldname).or_default();
+                if !val.is_null() {
+                    fldvalues.insert(val);
+                }
+            }
+        }
+        ts.close();
+        let num_values: HashMap<String, u64> = layout
+            .schema()
+            .fields()
+            .iter()
+            .map(|fldname| {
+                let n = values.get(fldname.as_str()).map_or(0, HashSet::len);
+                (fldname.clone(), n as u64)
+            })
+            .collect();
+
+        let mut scat = TableScan::new(Arc::clone(tx), "statcat", self.layout.clone())?;
+        while scat.next()? {
+            if scat.get_string("tblname")? == tblname {
+                scat.delete()?;
+            }
+        }
+        for fldname in layout.schema().fields() {
+            scat.insert()?;
+            scat.set_string("tblname", tblname)?;
+            scat.set_string("fldname", fldname)?;
+            scat.set_int("numblocks", clamp(num_blocks))?;
+            scat.set_int("numrecs", clamp(num_recs))?;
+            scat.set_int("numvalues", clamp(num_values[fldname]))?;
+        }
+        scat.close();
+
+        let si = StatInfo::new(num_blocks, num_recs).with_distinct_values(num_values);
+        let mut state = self.state.lock().unwrap();
+        state.table_stats.insert(tblname.to_string(), si.clone());
+        Ok(si)
+    }
+
+    fn refresh_statistics(&self, state: &mut StatState, tx: &Arc<Transaction>) -> DbResult<()> {
+        state.table_stats.clear();
+        state.num_calls = 0;
+
+        let tcat_layout = self.tbl_mgr.get_layout("tblcat", tx)?;
+        let mut tcat = TableScan::new(Arc::clone(tx), "tblcat", tcat_layout)?;
+        let mut tblnames = Vec::new();
+        while tcat.next()? {
+            tblnames.push(tcat.get_string("tblname")?);
+        }
+        tcat.close();
+
+        let mut saved = self.saved_stats(tx)?;
+        for tblname in tblnames {
+            let layout = self.tbl_mgr.get_layout(&tblname, tx)?;
+            let si = Self::calc_table_stats(&tblname, &layout, saved.remove(&tblname), tx)?;
+            state.table_stats.insert(tblname, si);
+        }
+        Ok(())
+    }
+
+    // Return the statistics of the table, from its saved
+    // statistics if it has been analyzed, and otherwise by
+    // scanning it. The saved number of records is scaled by
+    // the growth of the table since; the distinct values
+    // are kept as counted.
+    fn calc_table_stats(
+        tblname: &str,
+        layout: &Layout,
+        saved: Option<StatInfo>,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<StatInfo> {
+        if let Some(saved) = saved {
+            let num_blocks = tx.size(&format!("{}.tbl", tblname))?;
+            let num_recs = match saved.num_blocks {
+                0 => saved.num_recs,
+                old => saved.num_recs * num_blocks / old,
+            };
+            return Ok(StatInfo::new(num_blocks, num_recs).with_distinct_values(saved.num_values));
+        }
+        let mut num_recs = 0;
+        let mut num_blocks = 0;
+        let mut ts = TableScan::new(Arc::clone(tx), tblname, layout.clone())?;
+        while ts.next()? {
+            num_recs += 1;
+            num_blocks = ts.get_rid().block_number() + 1;
+        }
+        ts.close();
+        Ok(StatInfo::new(num_blocks, num_recs))
+    }
+
+    // Return the saved statistics of each analyzed table.
+    // An empty catalog is not scanned, which would
+    // append a block to it.
+    fn saved_stats(&self, tx: &Arc<Transaction>) -> DbResult<HashMap<String, StatInfo>> {
+        let mut saved: HashMap<String, StatInfo> = HashMap::new();
+        if tx.size("statcat.tbl")? == 0 {
+            return Ok(saved);
+        }
+        let mut scat = TableScan::new(Arc::clone(tx), "statcat", self.layout.clone())?;
+        while scat.next()? {
+            let num_blocks = scat.get_int("numblocks")? as u64;
+            let num_recs = scat.get_int("numrecs")? as u64;
+            let si = saved
+                .entry(scat.get_string("tblname")?)
+                .or_insert_with(|| StatInfo::new(num_blocks, num_recs));
+            let num_values = scat.get_int("numvalues")? as u64;
+            si.num_values
+                .insert(scat.get_string("fldname")?, num_values);
+        }
+        scat.close();
+        Ok(saved)
+    }
+}
+
+// An integer field holds at most i32::MAX.
+fn clamp(n: u64) -> i32 {
+    i32::try_from(n).unwrap_or(i32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SimpleDB;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_analyze() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let tm = Arc::new(TableManager::new(true, &tx)?);
+        let sm = StatManager::new(true, Arc::clone(&tm), &tx)?;
+
+        let mut sch = Schema::new();
+        sch.add_int_field("a");
+        sch.add_string_field("b", 9);
+        tm.create_table("t", &sch, &tx)?;
+        let layout = tm.get_layout("t", &tx)?;
+        let mut ts = TableScan::new(Arc::clone(&tx), "t", layout.clone())?;
+        for n in 0..90 {
+            ts.insert()?;
+            ts.set_int("a", n % 5)?;
+            if n % 2 == 0 {
+                ts.set_string("b", &format!("rec{}", n))?;
+            } else {
+                ts.set_val("b", &Constant::Null)?;
+            }
+        }
+        ts.close();
+
+        let si = sm.get_stat_info("t", &layout, &tx)?;
+        assert_eq!(si.records_output(), 90);
+        assert_eq!(si.distinct_values("a"), 31);
+
+        // the nulls are not counted as values
+        let si = sm.analyze("t", &layout, &tx)?;
+        assert_eq!(si.records_output(), 90);
+        assert_eq!(si.distinct_values("a"), 5);
+        assert_eq!(si.distinct_values("b"), 45);
+        assert_eq!(sm.get_stat_info("t", &layout, &tx)?, si);
+        let num_blocks = si.blocks_accessed();
+        assert!(num_blocks > 1);
+
+        // analyzing again replaces the saved counts
+        sm.analyze("t", &layout, &tx)?;
+        tx.commit()?;
+
+        // a new manager reads the saved counts, and scales
+        // the records by the blocks added since
+        let tx = db.new_tx()?;
+        let sm = StatManager::new(false, Arc::clone(&tm), &tx)?;
+        assert_eq!(sm.get_stat_info("t", &layout, &tx)?, si);
+        for _ in 0..num_blocks {
+            tx.append("t.tbl")?;
+        }
+        let sm = StatManager::new(false, Arc::clone(&tm), &tx)?;
+        let si = sm.get_stat_info("t", &layout, &tx)?;
+        assert_eq!(si.blocks_accessed(), 2 * num_blocks);
+        assert_eq!(si.records_output(), 180);
+        assert_eq!(si.distinct_values("a"), 5);
+        tx.commit()?;
+        Ok(())
+    }
+}
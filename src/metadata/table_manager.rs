@@ -0,0 +1,123 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    error::{DbError, DbResult},
+    query::{Scan, UpdateScan},
+    record::{FieldType, Layout, Schema, TableScan},
+    tx::Transaction,
+};
+
+// The table manager.
+// There are methods to create a table, save the metadata
+// in the catalog, and obtain the metadata of a
+// previously-created table.
+pub struct TableManager {
+    tcat_layout: Layout,
+    fcat_layout: Layout,
+}
+
+impl TableManager {
+    // The max bytes a tablename or fieldname can have.
+    pub const MAX_NAME: usize = 16;
+
+    // Create a new catalog manager for the database system.
+    // If the database is new, the two catalog tables
+    // are created.
+    pub fn new(is_new: bool, tx: &Arc<Transaction>) -> DbResult<Self> {
+        let mut tcat_schema = Schema::new();
+        tcat_schema.add_string_field("tblname", Self::MAX_NAME);
+        tcat_schema.add_int_field("slotsize");
+        let tcat_layout = Layout::new(tcat_schema);
+
+        let mut fcat_schema = Schema::new();
+        fcat_schema.add_string_field("tblname", Self::MAX_NAME);
+        fcat_schema.add_string_field("fldname", Self::MAX_NAME);
+        fcat_schema.add_int_field("type");
+        fcat_schema.add_int_field("length");
+        fcat_schema.add_int_field("offset");
+        let fcat_layout = Layout::new(fcat_schema);
+
+        let tm = TableManager {
+            tcat_layout,
+            fcat_layout,
+        };
+
+        if is_new {
+            tm.create_table("tblcat", tm.tcat_layout.schema(), tx)?;
+            tm.create_table("fldcat", tm.fcat_layout.schema(), tx)?;
+        }
+        Ok(tm)
+    }
+
+    // Create a new table having the specified name and schema.
+    pub fn create_table(&self, tblname: &str, sch: &Schema, tx: &Arc<Transaction>) -> DbResult<()> {
+        let layout = Layout::new(sch.clone());
+
+        // insert one record into tblcat
+        let mut tcat = TableScan::new(Arc::clone(tx), "tblcat", self.tcat_layout.clone())?;
+        tcat.insert()?;
+        tcat.set_string("tblname", tblname)?;
+        tcat.set_int("slotsize", layout.slot_size() as i32)?;
+        tcat.close();
+
+        // insert a record into fldcat for each field
+        let mut fcat = TableScan::new(Arc::clone(tx), "fldcat", self.fcat_layout.clone())?;
+        for fldname in sch.fields() {
+            fcat.insert()?;
+            fcat.set_string("tblname", tblname)?;
+            fcat.set_string("fldname", fldname)?;
+            fcat.set_int("type", sch.field_type(fldname).code())?;
+            fcat.set_int("length", sch.length(fldname) as i32)?;
+            fcat.set_int("offset", layout.offset(fldname) as i32)?;
+        }
+        fcat.close();
+        Ok(())
+    }
+
+    // Retrieve the layout of the specified table
+    // from the catalog.
+    pub fn get_layout(&self, tblname: &str, tx: &Arc<Transaction>) -> DbResult<Layout> {
+        let mut size = None;
+        let mut tcat = TableScan::new(Arc::clone(tx), "tblcat", self.tcat_layout.clone())?;
+        while tcat.next()? {
+            if tcat.get_string("tblname")? == tblname {
+                size = Some(tcat.get_int("slotsize")? as usize);
+                break;
+            }
+        }
+        tcat.close();
+
+        let mut sch = Schema::new();
+        let mut offsets = HashMap::new();
+        let mut fcat = TableScan::new(Arc::clone(tx), "fldcat", self.fcat_layout.clone())?;
+        while fcat.next()? {
+            if fcat.get_string("tblname")? == tblname {
+                let fldname = fcat.get_string("fldname")?;
+                let fldtype = fcat.get_int("type")?;
+                let fldlen = fcat.get_int("length")? as usize;
+                let offset = fcat.get_int("offset")? as usize;
+                let fldtype = FieldType::from_code(fldtype).ok_or_else(|| {
+                    DbError::Catalog(format!("unknown type {} for field {}", fldtype, fldname))
+                })?;
+                offsets.insert(fldname.clone(), offset);
+                sch.add_field(&fldname, fldtype, fldlen);
+            }
+        }
+        fcat.close();
+
+        let size = size.ok_or_else(|| DbError::Catalog(format!("table {} not found", tblname)))?;
+        Ok(Layout::from_metadata(sch, offsets, size))
+    }
+
+    // Return the names of every table in the catalog,
+    // including the catalog tables themselves.
+    pub fn table_names(&self, tx: &Arc<Transaction>) -> DbResult<Vec<String>> {
+        let mut names = Vec::new();
+        let mut tcat = TableScan::new(Arc::clone(tx), "tblcat", self.tcat_layout.clone())?;
+        while tcat.next()? {
+            names.push(tcat.get_string("tblname")?);
+        }
+        tcat.close();
+        Ok(names)
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use super::TableManager;
+use crate::{
+    error::{DbError, DbResult},
+    query::{Scan, UpdateScan},
+    record::{Layout, Schema, TableScan},
+    tx::Transaction,
+};
+
+// The view manager.
+// The definition of each view is the text of its query,
+// saved in the viewcat table.
+pub struct ViewManager {
+    layout: Layout,
+}
+
+impl ViewManager {
+    // The max bytes a view definition can have.
+    pub const MAX_VIEWDEF: usize = 100;
+
+    // Create the view manager.
+    // If the database is new, then the viewcat table is created.
+    pub fn new(is_new: bool, tbl_mgr: &TableManager, tx: &Arc<Transaction>) -> DbResult<Self> {
+        let mut sch = Schema::new();
+        sch.add_string_field("viewname", TableManager::MAX_NAME);
+        sch.add_string_field("viewdef", Self::MAX_VIEWDEF);
+        if is_new {
+            tbl_mgr.create_table("viewcat", &sch, tx)?;
+        }
+        Ok(ViewManager {
+            layout: Layout::new(sch),
+        })
+    }
+
+    // Save the definition of a new view in the viewcat table.
+    // The name of the view must not be taken by another.
+    pub fn create_view(&self, vname: &str, vdef: &str, tx: &Arc<Transaction>) -> DbResult<()> {
+        if vdef.len() > Self::MAX_VIEWDEF {
+            return Err(DbError::ValueTooLong {
+                field: "viewdef".to_string(),
+                max: Self::MAX_VIEWDEF,
+                len: vdef.len(),
+            });
+        }
+        if self.get_view_def(vname, tx)?.is_some() {
+            return Err(DbError::Catalog(format!("view {} already exists", vname)));
+        }
+        let mut ts = TableScan::new(Arc::clone(tx), "viewcat", self.layout.clone())?;
+        ts.insert()?;
+        ts.set_string("viewname", vname)?;
+        ts.set_string("viewdef", vdef)?;
+        ts.close();
+        Ok(())
+    }
+
+    // Return the definition of the specified view,
+    // or None if there is no such view.
+    // Every table of a query is looked up here, so an empty
+    // catalog is not scanned, which would append a block to it.
+    pub fn get_view_def(&self, vname: &str, tx: &Arc<Transaction>) -> DbResult<Option<String>> {
+        if tx.size("viewcat.tbl")? == 0 {
+            return Ok(None);
+        }
+        let mut result = None;
+        let mut ts = TableScan::new(Arc::clone(tx), "viewcat", self.layout.clone())?;
+        while ts.next()? {
+            if ts.get_string("viewname")? == vname {
+                result = Some(ts.get_string("viewdef")?);
+                break;
+            }
+        }
+        ts.close();
+        Ok(result)
+    }
+
+    // Return the names of every view.
+    pub fn view_names(&self, tx: &Arc<Transaction>) -> DbResult<Vec<String>> {
+        let mut names = Vec::new();
+        if tx.size("viewcat.tbl")? == 0 {
+            return Ok(names);
+        }
+        let mut ts = TableScan::new(Arc::clone(tx), "viewcat", self.layout.clone())?;
+        while ts.next()? {
+            names.push(ts.get_string("viewname")?);
+        }
+        ts.close();
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SimpleDB;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_view_defs() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let tblmgr = TableManager::new(true, &tx)?;
+        let viewmgr = ViewManager::new(true, &tblmgr, &tx)?;
+
+        viewmgr.create_view("v1", "select a from t", &tx)?;
+        viewmgr.create_view("v2", "select b from t where a=1", &tx)?;
+        assert_eq!(
+            viewmgr.get_view_def("v2", &tx)?.as_deref(),
+            Some("select b from t where a=1")
+        );
+        assert_eq!(viewmgr.get_view_def("t", &tx)?, None);
+        assert_eq!(viewmgr.view_names(&tx)?, ["v1", "v2"]);
+        assert!(tblmgr
+            .get_layout("viewcat", &tx)?
+            .schema()
+            .has_field("viewdef"));
+
+        let result = viewmgr.create_view("v1", "select b from t", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        let long = format!("select {} from t", "a".repeat(ViewManager::MAX_VIEWDEF));
+        let result = viewmgr.create_view("v3", &long, &tx);
+        assert!(matches!(result, Err(DbError::ValueTooLong { .. })));
+        assert_eq!(viewmgr.get_view_def("v3", &tx)?, None);
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+// A class containing static methods,
+// which estimate the optimal number of buffers
+// to allocate for a scan.
+pub struct BufferNeeds;
+
+impl BufferNeeds {
+    // This method considers the various roots
+    // of the specified output size (in blocks),
+    // and returns the highest root that is less than
+    // the number of available buffers.
+    // We reserve a couple of buffers so that we don't run completely out.
+    pub fn best_root(available: usize, size: u64) -> u64 {
+        let avail = available.saturating_sub(2) as u64;
+        if avail <= 1 {
+            return 1;
+        }
+        let mut k = u64::MAX;
+        let mut i = 1.0;
+        while k > avail {
+            i += 1.0;
+            k = (size as f64).powf(1.0 / i).ceil() as u64;
+        }
+        k
+    }
+
+    // This method considers the various factors
+    // of the specified output size (in blocks),
+    // and returns the highest factor that is less than
+    // the number of available buffers.
+    // We reserve a couple of buffers so that we don't run completely out.
+    pub fn best_factor(available: usize, size: u64) -> u64 {
+        let avail = available.saturating_sub(2) as u64;
+        if avail <= 1 {
+            return 1;
+        }
+        let mut k = size;
+        let mut i = 1;
+        while k > avail {
+            i += 1;
+            k = size.div_ceil(i);
+        }
+        k
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_needs() {
+        assert_eq!(BufferNeeds::best_factor(8, 4), 4);
+        assert_eq!(BufferNeeds::best_factor(8, 100), 6);
+        assert_eq!(BufferNeeds::best_factor(3, 100), 1);
+        assert_eq!(BufferNeeds::best_root(8, 4), 2);
+        assert_eq!(BufferNeeds::best_root(8, 1000), 6);
+        assert_eq!(BufferNeeds::best_root(2, 1000), 1);
+    }
+}
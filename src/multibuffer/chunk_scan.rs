@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use crate::{
+    error::DbResult,
+    file::BlockId,
+    query::{Constant, Scan},
+    record::{FieldType, Layout, RecordPage},
+    tx::Transaction,
+};
+
+// The class for the chunk operator.
+// A chunk is a range of consecutive blocks of a file,
+// all of which are pinned for the lifetime of the scan.
+pub struct ChunkScan {
+    buffs: Vec<RecordPage>,
+    layout: Layout,
+    startbnum: u64,
+    endbnum: u64,
+    currentbnum: u64,
+    currentslot: i32,
+}
+
+impl ChunkScan {
+    // Create a chunk consisting of the specified pages.
+    pub fn new(
+        tx: Arc<Transaction>,
+        filename: &str,
+        layout: Layout,
+        startbnum: u64,
+        endbnum: u64,
+    ) -> DbResult<Self> {
+        let mut buffs = Vec::new();
+        for i in startbnum..=endbnum {
+            let blk = BlockId::new(filename.to_string(), i);
+            buffs.push(RecordPage::new(Arc::clone(&tx), blk, layout.clone())?);
+        }
+        let mut scan = ChunkScan {
+            buffs,
+            layout,
+            startbnum,
+            endbnum,
+            currentbnum: startbnum,
+            currentslot: -1,
+        };
+        scan.move_to_block(startbnum);
+        Ok(scan)
+    }
+
+    fn rp(&self) -> &RecordPage {
+        &self.buffs[(self.currentbnum - self.startbnum) as usize]
+    }
+
+    fn move_to_block(&mut self, blknum: u64) {
+        self.currentbnum = blknum;
+        self.currentslot = -1;
+    }
+}
+
+impl Scan for ChunkScan {
+    fn before_first(&mut self) -> DbResult<()> {
+        self.move_to_block(self.startbnum);
+        Ok(())
+    }
+
+    // Move to the next record in the current block of the chunk.
+    // If there are no more records, then make
+    // the next block be current.
+    // If there are no more blocks in the chunk, return false.
+    fn next(&mut self) -> DbResult<bool> {
+        loop {
+            self.currentslot = self.rp().next_after(self.currentslot)?;
+            if self.currentslot >= 0 {
+                return Ok(true);
+            }
+            if self.currentbnum == self.endbnum {
+                return Ok(false);
+            }
+            self.move_to_block(self.currentbnum + 1);
+        }
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.rp().get_int(self.currentslot, fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        self.rp().get_string(self.currentslot, fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        if self.rp().is_null(self.currentslot, fldname)? {
+            return Ok(Constant::Null);
+        }
+        match self.layout.schema().field_type(fldname) {
+            FieldType::Integer => Ok(Constant::Int(self.get_int(fldname)?)),
+            FieldType::Varchar => Ok(Constant::Str(self.get_string(fldname)?)),
+            FieldType::Blob => {
+                let reader = self.rp().blob_reader(self.currentslot, fldname)?;
+                Ok(Constant::Blob(reader.read_all()?))
+            }
+        }
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        self.layout.schema().has_field(fldname)
+    }
+
+    // Close the chunk by unpinning all of its buffers.
+    fn close(&mut self) {
+        for rp in self.buffs.drain(..) {
+            rp.close();
+        }
+    }
+}
@@ -0,0 +1,9 @@
+mod buffer_needs;
+mod chunk_scan;
+mod multibuffer_product_plan;
+mod multibuffer_product_scan;
+
+pub use buffer_needs::BufferNeeds;
+pub use chunk_scan::ChunkScan;
+pub use multibuffer_product_plan::MultibufferProductPlan;
+pub use multibuffer_product_scan::MultibufferProductScan;
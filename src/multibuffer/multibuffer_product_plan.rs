@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use super::MultibufferProductScan;
+use crate::{
+    error::DbResult, materialize::MaterializePlan, plan::Plan, query::Scan, record::Schema,
+    tx::Transaction,
+};
+
+// The Plan class for the multi-buffer version of the
+// product operator.
+pub struct MultibufferProductPlan {
+    tx: Arc<Transaction>,
+    lhs: MaterializePlan,
+    rhs: MaterializePlan,
+    schema: Schema,
+}
+
+impl MultibufferProductPlan {
+    // Create a product plan for the specified queries.
+    // The LHS is materialized, since it is rescanned
+    // once for each chunk of the RHS.
+    pub fn new(tx: Arc<Transaction>, lhs: Box<dyn Plan>, rhs: Box<dyn Plan>) -> Self {
+        let mut schema = Schema::new();
+        schema.add_all(lhs.schema());
+        schema.add_all(rhs.schema());
+        MultibufferProductPlan {
+            lhs: MaterializePlan::new(Arc::clone(&tx), lhs),
+            rhs: MaterializePlan::new(Arc::clone(&tx), rhs),
+            tx,
+            schema,
+        }
+    }
+}
+
+impl Plan for MultibufferProductPlan {
+    // A scan for this query is created and returned, as follows.
+    // First, the method materializes its RHS query
+    // into a temporary table.
+    // The scan then reads that table one chunk at a time,
+    // choosing the chunk size from the size of the
+    // materialized file and the number of available buffers.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let tt = self.rhs.materialize()?;
+        let leftscan = self.lhs.open()?;
+        Ok(Box::new(MultibufferProductScan::new(
+            Arc::clone(&self.tx),
+            leftscan,
+            tt.table_name(),
+            tt.layout().clone(),
+        )?))
+    }
+
+    // Return an estimate of the number of block accesses
+    // required to execute the query. The formula is:
+    // B(product(p1,p2)) = B(p2) + B(p1)*C(p2)
+    // where C(p2) is the number of chunks of p2.
+    // The method uses the current number of buffers that the
+    // query may claim to calculate C(p2), and so this value may
+    // differ when the query scan is opened.
+    fn blocks_accessed(&self) -> u64 {
+        // this guesses at the # of chunks
+        let avail = self.tx.work_buffs() as u64;
+        let size = self.rhs.blocks_accessed();
+        let numchunks = size / avail.max(1);
+        size.saturating_add(self.lhs.blocks_accessed().saturating_mul(numchunks))
+    }
+
+    // Estimate the number of output records in the product.
+    // The formula is:
+    // R(product(p1,p2)) = R(p1)*R(p2)
+    fn records_output(&self) -> u64 {
+        self.lhs
+            .records_output()
+            .saturating_mul(self.rhs.records_output())
+    }
+
+    // Estimate the distinct number of field values in the product.
+    // Since the product does not increase or decrease field values,
+    // the estimate is the same as in the appropriate underlying query.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        if self.lhs.schema().has_field(fldname) {
+            self.lhs.distinct_values(fldname)
+        } else {
+            self.rhs.distinct_values(fldname)
+        }
+    }
+
+    // Return the schema of the product,
+    // which is the union of the schemas of the underlying queries.
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn description(&self) -> String {
+        "MultibufferProduct".to_string()
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![&self.lhs, &self.rhs]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, plan::TablePlan};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_multibuffer_product() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table l (la int)", &tx)?;
+        planner.execute_update("create table r (ra int, rb varchar(20))", &tx)?;
+        for i in 0..5 {
+            planner.execute_update(&format!("insert into l (la) values ({})", i), &tx)?;
+        }
+        // wide records spread the RHS over many more blocks than a single chunk holds
+        for i in 0..60 {
+            let sql = format!("insert into r (ra, rb) values ({}, 'rec{}')", i, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+
+        let p1 = TablePlan::new(Arc::clone(&tx), "l", &mdm)?;
+        let p2 = TablePlan::new(Arc::clone(&tx), "r", &mdm)?;
+        let plan = MultibufferProductPlan::new(Arc::clone(&tx), Box::new(p1), Box::new(p2));
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            rows.push((s.get_int("la")?, s.get_int("ra")?));
+        }
+        s.close();
+        assert_eq!(tx.available_buffs(), 8);
+
+        rows.sort();
+        let expected: Vec<_> = (0..5)
+            .flat_map(|la| (0..60).map(move |ra| (la, ra)))
+            .collect();
+        assert_eq!(rows, expected);
+
+        let plan = planner.create_query_plan("select la, rb from l, r where ra = 7", &tx)?;
+        let mut s = plan.open()?;
+        let mut count = 0;
+        while s.next()? {
+            assert_eq!(s.get_string("rb")?, "rec7");
+            count += 1;
+        }
+        s.close();
+        assert_eq!(count, 5);
+
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use super::{BufferNeeds, ChunkScan};
+use crate::{
+    error::DbResult,
+    query::{Constant, Scan},
+    record::Layout,
+    tx::Transaction,
+};
+
+// The Scan class for the multi-buffer version of the
+// product operator.
+// The RHS table is read one chunk at a time, and the
+// LHS scan is rescanned once per chunk rather than
+// once per RHS block.
+pub struct MultibufferProductScan<S> {
+    tx: Arc<Transaction>,
+    lhsscan: S,
+    rhsscan: Option<ChunkScan>,
+    filename: String,
+    layout: Layout,
+    chunksize: u64,
+    nextblknum: u64,
+    filesize: u64,
+    lhs_positioned: bool,
+}
+
+impl<S: Scan> MultibufferProductScan<S> {
+    // Create the scan class for the product of the LHS scan and a table.
+    pub fn new(tx: Arc<Transaction>, lhsscan: S, tblname: &str, layout: Layout) -> DbResult<Self> {
+        let filename = format!("{}.tbl", tblname);
+        let filesize = tx.size(&filename)?;
+        let available = tx.work_buffs();
+        let chunksize = BufferNeeds::best_factor(available, filesize);
+        let mut scan = MultibufferProductScan {
+            tx,
+            lhsscan,
+            rhsscan: None,
+            filename,
+            layout,
+            chunksize,
+            nextblknum: 0,
+            filesize,
+            lhs_positioned: false,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+
+    fn use_next_chunk(&mut self) -> DbResult<bool> {
+        if let Some(mut rhsscan) = self.rhsscan.take() {
+            rhsscan.close();
+        }
+        if self.nextblknum >= self.filesize {
+            return Ok(false);
+        }
+        let end = (self.nextblknum + self.chunksize - 1).min(self.filesize - 1);
+        self.rhsscan = Some(ChunkScan::new(
+            Arc::clone(&self.tx),
+            &self.filename,
+            self.layout.clone(),
+            self.nextblknum,
+            end,
+        )?);
+        self.lhsscan.before_first()?;
+        self.lhs_positioned = self.lhsscan.next()?;
+        self.nextblknum = end + 1;
+        Ok(true)
+    }
+
+    // Move to the next record in the product of the
+    // LHS scan and the current chunk.
+    fn next_in_chunk(&mut self) -> DbResult<bool> {
+        let Some(rhsscan) = self.rhsscan.as_mut() else {
+            return Ok(false);
+        };
+        while self.lhs_positioned {
+            if rhsscan.next()? {
+                return Ok(true);
+            }
+            rhsscan.before_first()?;
+            self.lhs_positioned = self.lhsscan.next()?;
+        }
+        Ok(false)
+    }
+
+    fn rhs(&self) -> &ChunkScan {
+        self.rhsscan.as_ref().expect("scan is not positioned")
+    }
+}
+
+impl<S: Scan> Scan for MultibufferProductScan<S> {
+    // Position the scan before the first record.
+    // That is, the LHS scan is positioned at its first record,
+    // and the RHS scan is positioned before the first record of the first chunk.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.nextblknum = 0;
+        self.use_next_chunk()?;
+        Ok(())
+    }
+
+    // Move to the next record in the current scan.
+    // If there are no more records in the current chunk,
+    // then move to the next LHS record and the beginning of that chunk.
+    // If there are no more LHS records, then move to the next chunk
+    // and begin again.
+    fn next(&mut self) -> DbResult<bool> {
+        while !self.next_in_chunk()? {
+            if !self.use_next_chunk()? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Return the value of the specified field.
+    // The value is obtained from whichever scan
+    // contains the field.
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        if self.lhsscan.has_field(fldname) {
+            self.lhsscan.get_int(fldname)
+        } else {
+            self.rhs().get_int(fldname)
+        }
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        if self.lhsscan.has_field(fldname) {
+            self.lhsscan.get_string(fldname)
+        } else {
+            self.rhs().get_string(fldname)
+        }
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        if self.lhsscan.has_field(fldname) {
+            self.lhsscan.get_val(fldname)
+        } else {
+            self.rhs().get_val(fldname)
+        }
+    }
+
+    // Return true if the specified field is in
+    // either of the underlying scans.
+    fn has_field(&self, fldname: &str) -> bool {
+        self.lhsscan.has_field(fldname) || self.layout.schema().has_field(fldname)
+    }
+
+    // Close the current scans.
+    fn close(&mut self) {
+        self.lhsscan.close();
+        if let Some(mut rhsscan) = self.rhsscan.take() {
+            rhsscan.close();
+        }
+    }
+}
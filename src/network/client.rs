@@ -0,0 +1,136 @@
+use std::{
+    io::{self, BufReader, BufWriter, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use super::Message;
+use crate::error::{DbError, DbResult};
+
+// The server's answer to a statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    // The output of a query: its field names,
+    // and the values of each output record.
+    Rows {
+        columns: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    // The number of records affected by an update statement.
+    Count(usize),
+}
+
+// A connection to a SimpleDB server.
+pub struct Client {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl Client {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(Client {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        })
+    }
+
+    // Send the SQL statement to the server and
+    // read the server's response.
+    // An error reported by the server is returned as DbError::Remote.
+    pub fn execute(&mut self, sql: &str) -> DbResult<Response> {
+        Message::Query(sql.to_string()).write_to(&mut self.writer)?;
+        self.writer.flush()?;
+
+        let mut columns = None;
+        let mut rows = Vec::new();
+        loop {
+            match self.read_message()? {
+                Message::RowDescription(names) => columns = Some(names),
+                Message::DataRow(vals) => rows.push(vals),
+                Message::Complete(count) => {
+                    return Ok(match columns {
+                        Some(columns) => Response::Rows { columns, rows },
+                        None => Response::Count(count),
+                    });
+                }
+                Message::Error(msg) => return Err(DbError::Remote(msg)),
+                Message::Query(_) => {
+                    let msg = "unexpected query from server";
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, msg).into());
+                }
+            }
+        }
+    }
+
+    fn read_message(&mut self) -> DbResult<Message> {
+        Message::read_from(&mut self.reader)?.ok_or_else(|| {
+            let msg = "server closed the connection";
+            io::Error::new(io::ErrorKind::UnexpectedEof, msg).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, network::Server};
+    use std::{sync::Arc, thread};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_client_server() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Arc::new(SimpleDB::new(temp_dir.path(), 400, 8)?);
+        let server = Server::bind(db, "127.0.0.1:0")?;
+        let addr = server.local_addr()?;
+        thread::spawn(move || server.serve());
+
+        let mut client = Client::connect(addr)?;
+        assert_eq!(
+            client.execute("create table t (a int, b varchar(10))")?,
+            Response::Count(0)
+        );
+        for i in 0..3 {
+            let sql = format!("insert into t (a, b) values ({}, 'b{}')", i, i);
+            assert_eq!(client.execute(&sql)?, Response::Count(1));
+        }
+        assert_eq!(
+            client.execute("select b, a from t where a = 1")?,
+            Response::Rows {
+                columns: vec!["b".to_string(), "a".to_string()],
+                rows: vec![vec!["b1".to_string(), "1".to_string()]],
+            }
+        );
+
+        // errors are reported without closing the connection
+        let result = client.execute("select c from t");
+        assert!(matches!(result, Err(DbError::Remote(_))));
+        let result = client.execute("select from t");
+        assert!(matches!(result, Err(DbError::Remote(_))));
+
+        // a second client sees the committed records, and can
+        // modify them, as the failed statements left no locks
+        let mut client2 = Client::connect(addr)?;
+        match client2.execute("select a from t")? {
+            Response::Rows { rows, .. } => assert_eq!(rows.len(), 3),
+            other => panic!("unexpected response {:?}", other),
+        }
+        assert_eq!(
+            client2.execute("insert into t (a, b) values (3, 'b3')")?,
+            Response::Count(1)
+        );
+        assert_eq!(
+            client2.execute("update t set b = 'new' where a = 1")?,
+            Response::Count(1)
+        );
+        assert_eq!(
+            client.execute("delete from t where a = 0")?,
+            Response::Count(1)
+        );
+        assert_eq!(
+            client2.execute("create table u (c int)")?,
+            Response::Count(0)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+mod client;
+mod postgres;
+mod protocol;
+mod server;
+
+pub use client::{Client, Response};
+pub use postgres::PgServer;
+pub use protocol::Message;
+pub use server::Server;
+
+use crate::{error::DbResult, parse::Parser};
+
+// Return true if the SQL statement is a query,
+// and false if it is an update statement.
+fn is_query(sql: &str) -> DbResult<bool> {
+    Ok(Parser::new(sql)?.is_query())
+}
@@ -0,0 +1,467 @@
+use std::{
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::Arc,
+    thread,
+};
+
+use crate::{
+    db::SimpleDB,
+    error::{DbError, DbResult},
+    parse::Lexer,
+    query::Constant,
+    record::FieldType,
+};
+
+// A server that speaks the PostgreSQL v3 frontend/backend
+// protocol, so that tools such as psql can connect.
+// Only the parts of the protocol needed for simple queries
+// are implemented: startup without authentication,
+// and the simple query cycle of RowDescription, DataRow,
+// CommandComplete and ReadyForQuery messages.
+// Each statement runs in its own transaction.
+pub struct PgServer {
+    db: Arc<SimpleDB>,
+    listener: TcpListener,
+}
+
+impl PgServer {
+    pub const DEFAULT_ADDR: &'static str = "127.0.0.1:5432";
+
+    const PROTOCOL_V3: i32 = 196608;
+    const SSL_REQUEST: i32 = 80877103;
+    const GSSENC_REQUEST: i32 = 80877104;
+    const CANCEL_REQUEST: i32 = 80877102;
+
+    // The type OIDs of the Postgres int4, varchar and bytea types.
+    const INT4_OID: i32 = 23;
+    const VARCHAR_OID: i32 = 1043;
+    const BYTEA_OID: i32 = 17;
+
+    // Create a server for the database, listening
+    // on the specified address.
+    pub fn bind(db: Arc<SimpleDB>, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(PgServer {
+            db,
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Accept connections until the listener fails.
+    pub fn serve(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let db = Arc::clone(&self.db);
+            thread::spawn(move || {
+                // a broken connection only ends its own thread
+                let _ = Self::handle_connection(&db, stream);
+            });
+        }
+        Ok(())
+    }
+
+    fn handle_connection(db: &SimpleDB, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        if !Self::startup(&mut reader, &mut writer)? {
+            return Ok(());
+        }
+        while let Some((tag, body)) = read_message(&mut reader)? {
+            match tag {
+                b'Q' => {
+                    let sql = String::from_utf8_lossy(body.strip_suffix(&[0]).unwrap_or(&body));
+                    Self::simple_query(db, &sql, &mut writer)?;
+                    ready_for_query(&mut writer)?;
+                }
+                b'X' => return Ok(()),
+                // the Sync that ends an extended query
+                b'S' => ready_for_query(&mut writer)?,
+                _ => error_response(
+                    &mut writer,
+                    "0A000",
+                    "only the simple query protocol is supported",
+                )?,
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    // Handle the startup phase of a connection.
+    // SSL and GSSAPI encryption requests are declined,
+    // and a v3 startup message is accepted without
+    // authentication.
+    // Return false if the connection should be closed.
+    fn startup(r: &mut impl Read, w: &mut impl Write) -> io::Result<bool> {
+        loop {
+            let len = read_i32(r)?;
+            if !(8..=10000).contains(&len) {
+                return Ok(false);
+            }
+            let mut body = vec![0; len as usize - 4];
+            r.read_exact(&mut body)?;
+            let code = i32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+            match code {
+                Self::SSL_REQUEST | Self::GSSENC_REQUEST => {
+                    w.write_all(b"N")?;
+                    w.flush()?;
+                }
+                Self::PROTOCOL_V3 => break,
+                Self::CANCEL_REQUEST => return Ok(false),
+                _ => {
+                    error_response(w, "08P01", "unsupported frontend protocol")?;
+                    w.flush()?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        // AuthenticationOk
+        write_message(w, b'R', &0i32.to_be_bytes())?;
+        for (name, val) in [
+            ("server_version", "14.0"),
+            ("server_encoding", "UTF8"),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            ("standard_conforming_strings", "on"),
+        ] {
+            let mut body = Vec::new();
+            put_cstring(&mut body, name);
+            put_cstring(&mut body, val);
+            write_message(w, b'S', &body)?;
+        }
+        ready_for_query(w)?;
+        w.flush()?;
+        Ok(true)
+    }
+
+    // Execute each statement of a simple query.
+    // As in Postgres, the first failing statement
+    // ends the query.
+    fn simple_query(db: &SimpleDB, sql: &str, w: &mut impl Write) -> io::Result<()> {
+        let stmts = split_statements(sql);
+        if stmts.is_empty() {
+            // EmptyQueryResponse
+            return write_message(w, b'I', &[]);
+        }
+        for stmt in stmts {
+            if let Err(e) = Self::execute(db, stmt, w)? {
+                return error_response(w, sqlstate(&e), &e.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    // Execute the statement and write its response.
+    // A database error is returned to the caller, to be sent
+    // to the client; an I/O error ends the connection.
+    fn execute(db: &SimpleDB, sql: &str, w: &mut impl Write) -> io::Result<DbResult<()>> {
+        let is_query = match super::is_query(sql) {
+            Ok(is_query) => is_query,
+            Err(e) => return Ok(Err(e)),
+        };
+        if !is_query {
+            let count = match db.execute_update(sql) {
+                Ok(count) => count,
+                Err(e) => return Ok(Err(e)),
+            };
+            command_complete(w, &update_tag(sql, count))?;
+            return Ok(Ok(()));
+        }
+        let rs = match db.execute_query(sql) {
+            Ok(rs) => rs,
+            Err(e) => return Ok(Err(e)),
+        };
+
+        let sch = rs.schema();
+        let mut body = Vec::new();
+        body.extend_from_slice(&(rs.columns().len() as i16).to_be_bytes());
+        for fldname in rs.columns() {
+            let (oid, typlen) = match sch.field_type(fldname) {
+                FieldType::Integer => (Self::INT4_OID, 4i16),
+                FieldType::Varchar => (Self::VARCHAR_OID, -1i16),
+                FieldType::Blob => (Self::BYTEA_OID, -1i16),
+            };
+            put_cstring(&mut body, fldname);
+            body.extend_from_slice(&0i32.to_be_bytes()); // table oid
+            body.extend_from_slice(&0i16.to_be_bytes()); // column number
+            body.extend_from_slice(&oid.to_be_bytes());
+            body.extend_from_slice(&typlen.to_be_bytes());
+            body.extend_from_slice(&(-1i32).to_be_bytes()); // type modifier
+            body.extend_from_slice(&0i16.to_be_bytes()); // text format
+        }
+        write_message(w, b'T', &body)?;
+
+        for row in rs.rows() {
+            let mut body = Vec::new();
+            body.extend_from_slice(&(row.len() as i16).to_be_bytes());
+            for val in row {
+                // a null column is sent as length -1, with no bytes
+                if val.is_null() {
+                    body.extend_from_slice(&(-1i32).to_be_bytes());
+                    continue;
+                }
+                // bytea is sent in its hex text format
+                let val = match val {
+                    Constant::Blob(_) => format!("\\x{}", val),
+                    _ => val.to_string(),
+                };
+                body.extend_from_slice(&(val.len() as i32).to_be_bytes());
+                body.extend_from_slice(val.as_bytes());
+            }
+            write_message(w, b'D', &body)?;
+        }
+        let tag = if Lexer::new(sql).is_ok_and(|lex| lex.match_keyword("explain")) {
+            "EXPLAIN".to_string()
+        } else {
+            format!("SELECT {}", rs.len())
+        };
+        command_complete(w, &tag)?;
+        Ok(Ok(()))
+    }
+}
+
+// Split a query string into its statements, which are
+// separated by semicolons outside of string constants.
+// Blank statements are dropped.
+fn split_statements(sql: &str) -> Vec<&str> {
+    let mut stmts = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    for (i, c) in sql.char_indices() {
+        match c {
+            '\'' => in_string = !in_string,
+            ';' if !in_string => {
+                stmts.push(&sql[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    stmts.push(&sql[start..]);
+    stmts.retain(|s| !s.trim().is_empty());
+    stmts
+}
+
+// Return the CommandComplete tag of an update statement.
+fn update_tag(sql: &str, count: usize) -> String {
+    let lex = match Lexer::new(sql) {
+        Ok(lex) => lex,
+        Err(_) => return format!("UPDATE {}", count),
+    };
+    if lex.match_keyword("insert") {
+        format!("INSERT 0 {}", count)
+    } else if lex.match_keyword("delete") {
+        format!("DELETE {}", count)
+    } else if lex.match_keyword("create") && lex.match_next_keyword("index") {
+        "CREATE INDEX".to_string()
+    } else if lex.match_keyword("create") && lex.match_next_keyword("view") {
+        "CREATE VIEW".to_string()
+    } else if lex.match_keyword("create") {
+        "CREATE TABLE".to_string()
+    } else if lex.match_keyword("drop") {
+        "DROP INDEX".to_string()
+    } else if lex.match_keyword("truncate") {
+        "TRUNCATE TABLE".to_string()
+    } else if lex.match_keyword("analyze") {
+        "ANALYZE".to_string()
+    } else {
+        format!("UPDATE {}", count)
+    }
+}
+
+// Return the SQLSTATE code reported for the error.
+fn sqlstate(e: &DbError) -> &'static str {
+    match e {
+        DbError::BadSyntax(_) => "42601",
+        DbError::Catalog(_) => "42P01",
+        DbError::ValueTooLong { .. } => "22001",
+        DbError::LockAbort => "40P01",
+        DbError::LockTimeout(_) => "55P03",
+        DbError::SerializationFailure(_) => "40001",
+        DbError::ReadOnly(_) => "25006",
+        DbError::TransactionAborted(_) => "25P04",
+        DbError::BufferAbort(_) => "53000",
+        DbError::IoError(_) => "58030",
+        DbError::CorruptBlock(_) => "XX001",
+        _ => "XX000",
+    }
+}
+
+fn read_i32(r: &mut impl Read) -> io::Result<i32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(i32::from_be_bytes(bytes))
+}
+
+// Read the next message sent after startup.
+// Return None if the stream is closed between messages.
+fn read_message(r: &mut impl Read) -> io::Result<Option<(u8, Vec<u8>)>> {
+    let mut tag = [0; 1];
+    match r.read_exact(&mut tag) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = read_i32(r)?;
+    if !(4..=1 << 24).contains(&len) {
+        let msg = format!("bad message length {}", len);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    let mut body = vec![0; len as usize - 4];
+    r.read_exact(&mut body)?;
+    Ok(Some((tag[0], body)))
+}
+
+fn write_message(w: &mut impl Write, tag: u8, body: &[u8]) -> io::Result<()> {
+    w.write_all(&[tag])?;
+    w.write_all(&(body.len() as i32 + 4).to_be_bytes())?;
+    w.write_all(body)
+}
+
+fn put_cstring(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+}
+
+fn ready_for_query(w: &mut impl Write) -> io::Result<()> {
+    write_message(w, b'Z', b"I")
+}
+
+fn command_complete(w: &mut impl Write, tag: &str) -> io::Result<()> {
+    let mut body = Vec::new();
+    put_cstring(&mut body, tag);
+    write_message(w, b'C', &body)
+}
+
+fn error_response(w: &mut impl Write, code: &str, msg: &str) -> io::Result<()> {
+    let mut body = Vec::new();
+    for (field, val) in [(b'S', "ERROR"), (b'V', "ERROR"), (b'C', code), (b'M', msg)] {
+        body.push(field);
+        put_cstring(&mut body, val);
+    }
+    body.push(0);
+    write_message(w, b'E', &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // Send a simple query and collect the response
+    // messages, up to ReadyForQuery.
+    fn query(stream: &mut TcpStream, sql: &str) -> io::Result<Vec<(u8, Vec<u8>)>> {
+        let mut body = Vec::new();
+        put_cstring(&mut body, sql);
+        write_message(stream, b'Q', &body)?;
+        let mut msgs = Vec::new();
+        while let Some((tag, body)) = read_message(stream)? {
+            if tag == b'Z' {
+                return Ok(msgs);
+            }
+            msgs.push((tag, body));
+        }
+        panic!("connection closed before ReadyForQuery");
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split_statements("select a from t; insert into t (b) values ('x;y');"),
+            ["select a from t", " insert into t (b) values ('x;y')"]
+        );
+        assert!(split_statements(" ; ").is_empty());
+    }
+
+    #[test]
+    fn test_simple_query() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Arc::new(SimpleDB::new(temp_dir.path(), 400, 8)?);
+        let server = PgServer::bind(db, "127.0.0.1:0")?;
+        let addr = server.local_addr()?;
+        thread::spawn(move || server.serve());
+
+        let mut stream = TcpStream::connect(addr)?;
+
+        // the SSL request is declined
+        stream.write_all(&8i32.to_be_bytes())?;
+        stream.write_all(&PgServer::SSL_REQUEST.to_be_bytes())?;
+        let mut answer = [0; 1];
+        stream.read_exact(&mut answer)?;
+        assert_eq!(&answer, b"N");
+
+        let mut body = PgServer::PROTOCOL_V3.to_be_bytes().to_vec();
+        put_cstring(&mut body, "user");
+        put_cstring(&mut body, "simpledb");
+        body.push(0);
+        stream.write_all(&(body.len() as i32 + 4).to_be_bytes())?;
+        stream.write_all(&body)?;
+        let mut tags = Vec::new();
+        while let Some((tag, body)) = read_message(&mut stream)? {
+            tags.push(tag);
+            if tag == b'R' {
+                assert_eq!(body, 0i32.to_be_bytes());
+            }
+            if tag == b'Z' {
+                break;
+            }
+        }
+        assert_eq!(tags.first(), Some(&b'R'));
+        assert_eq!(tags.last(), Some(&b'Z'));
+
+        let msgs = query(
+            &mut stream,
+            "create table t (a int, b varchar(5)); insert into t (a, b) values (7, 'x')",
+        )?;
+        assert_eq!(
+            msgs,
+            [
+                (b'C', b"CREATE TABLE\0".to_vec()),
+                (b'C', b"INSERT 0 1\0".to_vec())
+            ]
+        );
+
+        let msgs = query(&mut stream, "select a, b from t;")?;
+        let tags: Vec<u8> = msgs.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, b"TDC");
+        assert_eq!(&msgs[0].1[..2], &2i16.to_be_bytes());
+        assert!(msgs[0].1.windows(2).any(|w| w == b"a\0"));
+        let mut row = 2i16.to_be_bytes().to_vec();
+        row.extend_from_slice(&1i32.to_be_bytes());
+        row.extend_from_slice(b"7");
+        row.extend_from_slice(&1i32.to_be_bytes());
+        row.extend_from_slice(b"x");
+        assert_eq!(msgs[1].1, row);
+        assert_eq!(msgs[2].1, b"SELECT 1\0");
+
+        // an error ends the query, and the session goes on
+        let msgs = query(&mut stream, "select c from t; delete from t")?;
+        assert_eq!(msgs.len(), 1);
+        assert_eq!(msgs[0].0, b'E');
+        assert_eq!(query(&mut stream, "")?, [(b'I', Vec::new())]);
+        let msgs = query(&mut stream, "select a from t")?;
+        assert_eq!(msgs.last().unwrap().1, b"SELECT 1\0");
+
+        // the failed statement left no locks behind
+        let msgs = query(
+            &mut stream,
+            "update t set b = 'y' where a = 7; create table u (c int)",
+        )?;
+        assert_eq!(
+            msgs,
+            [
+                (b'C', b"UPDATE 1\0".to_vec()),
+                (b'C', b"CREATE TABLE\0".to_vec())
+            ]
+        );
+
+        write_message(&mut stream, b'X', &[])?;
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use std::io::{self, Read, Write};
+
+// The messages of the SimpleDB wire protocol.
+// Every message is sent as a frame: a 4-byte big-endian
+// length, followed by that many bytes of payload.
+// The first payload byte identifies the message, and the
+// rest holds its strings, each one length-prefixed in
+// the same way as the frame.
+//
+// A client sends a Query for each SQL statement.
+// The server answers a query with a RowDescription,
+// one DataRow per output record, and a Complete;
+// it answers an update statement with a Complete
+// holding the number of affected records.
+// Any failure is answered with an Error instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Query(String),
+    RowDescription(Vec<String>),
+    DataRow(Vec<String>),
+    Complete(usize),
+    Error(String),
+}
+
+impl Message {
+    // The largest frame that will be accepted.
+    pub const MAX_FRAME: usize = 1 << 24;
+
+    // Write this message to the specified stream.
+    // The stream is not flushed.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        let mut payload = Vec::new();
+        match self {
+            Message::Query(sql) => {
+                payload.push(b'Q');
+                put_string(&mut payload, sql);
+            }
+            Message::RowDescription(vals) | Message::DataRow(vals) => {
+                let tag = if matches!(self, Message::RowDescription(_)) {
+                    b'T'
+                } else {
+                    b'D'
+                };
+                payload.push(tag);
+                payload.extend_from_slice(&(vals.len() as u32).to_be_bytes());
+                for val in vals {
+                    put_string(&mut payload, val);
+                }
+            }
+            Message::Complete(count) => {
+                payload.push(b'C');
+                payload.extend_from_slice(&(*count as u32).to_be_bytes());
+            }
+            Message::Error(msg) => {
+                payload.push(b'E');
+                put_string(&mut payload, msg);
+            }
+        }
+        w.write_all(&(payload.len() as u32).to_be_bytes())?;
+        w.write_all(&payload)
+    }
+
+    // Read the next message from the specified stream.
+    // Return None if the stream is closed between messages.
+    pub fn read_from(r: &mut impl Read) -> io::Result<Option<Message>> {
+        let mut len = [0; 4];
+        match r.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 || len > Self::MAX_FRAME {
+            return Err(invalid(format!("bad frame length {}", len)));
+        }
+        let mut payload = vec![0; len];
+        r.read_exact(&mut payload)?;
+
+        let mut body = &payload[1..];
+        let msg = match payload[0] {
+            b'Q' => Message::Query(get_string(&mut body)?),
+            b'T' => Message::RowDescription(get_strings(&mut body)?),
+            b'D' => Message::DataRow(get_strings(&mut body)?),
+            b'C' => Message::Complete(get_u32(&mut body)? as usize),
+            b'E' => Message::Error(get_string(&mut body)?),
+            tag => return Err(invalid(format!("unknown message type {}", tag))),
+        };
+        if !body.is_empty() {
+            return Err(invalid("trailing bytes in message".to_string()));
+        }
+        Ok(Some(msg))
+    }
+}
+
+fn put_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn get_u32(body: &mut &[u8]) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    body.read_exact(&mut bytes)
+        .map_err(|_| invalid("truncated message".to_string()))?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn get_string(body: &mut &[u8]) -> io::Result<String> {
+    let len = get_u32(body)? as usize;
+    if len > body.len() {
+        return Err(invalid("truncated message".to_string()));
+    }
+    let (s, rest) = body.split_at(len);
+    *body = rest;
+    String::from_utf8(s.to_vec()).map_err(|_| invalid("string is not UTF-8".to_string()))
+}
+
+fn get_strings(body: &mut &[u8]) -> io::Result<Vec<String>> {
+    let count = get_u32(body)?;
+    (0..count).map(|_| get_string(body)).collect()
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> io::Result<()> {
+        let msgs = vec![
+            Message::Query("select a from t".to_string()),
+            Message::RowDescription(vec!["a".to_string(), "b".to_string()]),
+            Message::DataRow(vec!["1".to_string(), "".to_string()]),
+            Message::Complete(3),
+            Message::Error("bad syntax: near from".to_string()),
+        ];
+        let mut buf = Vec::new();
+        for msg in &msgs {
+            msg.write_to(&mut buf)?;
+        }
+        let mut r = buf.as_slice();
+        for msg in &msgs {
+            assert_eq!(Message::read_from(&mut r)?.as_ref(), Some(msg));
+        }
+        assert_eq!(Message::read_from(&mut r)?, None);
+
+        // a frame with an unknown type is rejected
+        let mut r: &[u8] = &[0, 0, 0, 1, b'X'];
+        assert!(Message::read_from(&mut r).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    io::{self, BufReader, BufWriter, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::Arc,
+    thread,
+};
+
+use super::Message;
+use crate::{db::SimpleDB, error::DbResult};
+
+// A server that gives remote clients access to a database.
+// Each connection is served by its own thread, and each
+// statement a client sends runs in its own transaction.
+pub struct Server {
+    db: Arc<SimpleDB>,
+    listener: TcpListener,
+}
+
+impl Server {
+    pub const DEFAULT_ADDR: &'static str = "127.0.0.1:1099";
+
+    // Create a server for the database, listening
+    // on the specified address.
+    pub fn bind(db: Arc<SimpleDB>, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Server {
+            db,
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Accept connections until the listener fails.
+    pub fn serve(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let db = Arc::clone(&self.db);
+            thread::spawn(move || {
+                // a broken connection only ends its own thread
+                let _ = Self::handle_connection(&db, stream);
+            });
+        }
+        Ok(())
+    }
+
+    // Answer the client's statements until it disconnects.
+    fn handle_connection(db: &SimpleDB, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        while let Some(msg) = Message::read_from(&mut reader)? {
+            match msg {
+                Message::Query(sql) => {
+                    if let Err(e) = Self::execute(db, &sql, &mut writer)? {
+                        Message::Error(e.to_string()).write_to(&mut writer)?;
+                    }
+                }
+                other => {
+                    let msg = format!("unexpected message {:?}", other);
+                    Message::Error(msg).write_to(&mut writer)?;
+                }
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    // Execute the statement and write its response.
+    // A database error is returned to the caller, to be sent
+    // to the client; an I/O error ends the connection.
+    fn execute(db: &SimpleDB, sql: &str, w: &mut impl Write) -> io::Result<DbResult<()>> {
+        let is_query = match super::is_query(sql) {
+            Ok(is_query) => is_query,
+            Err(e) => return Ok(Err(e)),
+        };
+        if !is_query {
+            let count = match db.execute_update(sql) {
+                Ok(count) => count,
+                Err(e) => return Ok(Err(e)),
+            };
+            Message::Complete(count).write_to(w)?;
+            return Ok(Ok(()));
+        }
+        let rs = match db.execute_query(sql) {
+            Ok(rs) => rs,
+            Err(e) => return Ok(Err(e)),
+        };
+        Message::RowDescription(rs.columns().to_vec()).write_to(w)?;
+        for row in rs.rows() {
+            let vals = row.iter().map(|val| val.to_string()).collect();
+            Message::DataRow(vals).write_to(w)?;
+        }
+        Message::Complete(rs.len()).write_to(w)?;
+        Ok(Ok(()))
+    }
+}
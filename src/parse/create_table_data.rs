@@ -0,0 +1,33 @@
+use crate::record::Schema;
+
+// Data for the SQL create table statement.
+// An auto-increment field is an int field whose
+// value is generated when an insert leaves it null.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateTableData {
+    tblname: String,
+    sch: Schema,
+    auto_fields: Vec<String>,
+}
+
+impl CreateTableData {
+    pub fn new(tblname: String, sch: Schema, auto_fields: Vec<String>) -> Self {
+        CreateTableData {
+            tblname,
+            sch,
+            auto_fields,
+        }
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.tblname
+    }
+
+    pub fn new_schema(&self) -> &Schema {
+        &self.sch
+    }
+
+    pub fn auto_increment_fields(&self) -> &[String] {
+        &self.auto_fields
+    }
+}
@@ -0,0 +1,28 @@
+use super::QueryData;
+
+// Data for the SQL create view statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateViewData {
+    viewname: String,
+    qrydata: QueryData,
+}
+
+impl CreateViewData {
+    pub fn new(viewname: String, qrydata: QueryData) -> Self {
+        CreateViewData { viewname, qrydata }
+    }
+
+    pub fn view_name(&self) -> &str {
+        &self.viewname
+    }
+
+    pub fn query_data(&self) -> &QueryData {
+        &self.qrydata
+    }
+
+    // Return the definition of the view, as the
+    // text of its query.
+    pub fn view_def(&self) -> String {
+        self.qrydata.to_string()
+    }
+}
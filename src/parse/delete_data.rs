@@ -0,0 +1,24 @@
+use crate::query::Predicate;
+
+// Data for the SQL delete statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeleteData {
+    tblname: String,
+    pred: Predicate,
+}
+
+impl DeleteData {
+    pub fn new(tblname: String, pred: Predicate) -> Self {
+        DeleteData { tblname, pred }
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.tblname
+    }
+
+    // Return the predicate that describes which
+    // records should be deleted.
+    pub fn pred(&self) -> &Predicate {
+        &self.pred
+    }
+}
@@ -0,0 +1,36 @@
+use crate::query::Constant;
+
+// Data for the SQL insert statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsertData {
+    tblname: String,
+    flds: Vec<String>,
+    vals: Vec<Constant>,
+}
+
+impl InsertData {
+    pub fn new(tblname: String, flds: Vec<String>, vals: Vec<Constant>) -> Self {
+        InsertData {
+            tblname,
+            flds,
+            vals,
+        }
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.tblname
+    }
+
+    // Return a list of fields for which
+    // values will be specified in the new record.
+    pub fn fields(&self) -> &[String] {
+        &self.flds
+    }
+
+    // Return a list of values for the specified fields.
+    // There is a one-one correspondence between this
+    // list of values and the list of fields.
+    pub fn vals(&self) -> &[Constant] {
+        &self.vals
+    }
+}
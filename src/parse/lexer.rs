@@ -0,0 +1,359 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::error::{DbError, DbResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Delim(char),
+    IntConstant(i32),
+    StringConstant(String),
+    BlobConstant(Vec<u8>),
+    Keyword(String),
+    Id(String),
+    Eof,
+}
+
+// The lexical analyzer.
+// The input string is split into tokens up front;
+// identifiers and keywords are case-insensitive and
+// are converted to lower case.
+// A blob constant is written in hex, as in x'cafe'.
+pub struct Lexer {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Lexer {
+    const KEYWORDS: &'static [&'static str] = &[
+        "explain",
+        "select",
+        "distinct",
+        "from",
+        "where",
+        "and",
+        "is",
+        "not",
+        "null",
+        "group",
+        "by",
+        "order",
+        "asc",
+        "desc",
+        "count",
+        "max",
+        "min",
+        "sum",
+        "avg",
+        "insert",
+        "into",
+        "values",
+        "delete",
+        "update",
+        "set",
+        "create",
+        "table",
+        "int",
+        "varchar",
+        "blob",
+        "vacuum",
+        "truncate",
+        "index",
+        "on",
+        "drop",
+        "view",
+        "as",
+        "show",
+        "tables",
+        "describe",
+        "analyze",
+        "auto_increment",
+    ];
+
+    // Create a new lexical analyzer for SQL statement s.
+    pub fn new(s: &str) -> DbResult<Self> {
+        Ok(Lexer {
+            tokens: Self::tokenize(s)?,
+            pos: 0,
+        })
+    }
+
+    // Return true if the current token is
+    // the specified delimiter character.
+    pub fn match_delim(&self, d: char) -> bool {
+        self.current() == &Token::Delim(d)
+    }
+
+    // Return true if the current token is an integer.
+    pub fn match_int_constant(&self) -> bool {
+        matches!(self.current(), Token::IntConstant(_))
+    }
+
+    // Return true if the current token is a string.
+    pub fn match_string_constant(&self) -> bool {
+        matches!(self.current(), Token::StringConstant(_))
+    }
+
+    // Return true if the current token is a blob.
+    pub fn match_blob_constant(&self) -> bool {
+        matches!(self.current(), Token::BlobConstant(_))
+    }
+
+    // Return true if the current token is the specified keyword.
+    pub fn match_keyword(&self, w: &str) -> bool {
+        matches!(self.current(), Token::Keyword(k) if k == w)
+    }
+
+    // Return true if the token after the current one
+    // is the specified keyword.
+    pub fn match_next_keyword(&self, w: &str) -> bool {
+        matches!(self.tokens.get(self.pos + 1), Some(Token::Keyword(k)) if k == w)
+    }
+
+    // Return true if the current token is a legal identifier.
+    pub fn match_id(&self) -> bool {
+        matches!(self.current(), Token::Id(_))
+    }
+
+    // Return true if the statement has been fully consumed.
+    pub fn match_eof(&self) -> bool {
+        self.current() == &Token::Eof
+    }
+
+    // Throw an exception if the current token is not the
+    // specified delimiter.
+    // Otherwise, move to the next token.
+    pub fn eat_delim(&mut self, d: char) -> DbResult<()> {
+        if !self.match_delim(d) {
+            return Err(self.syntax_error(&format!("'{}'", d)));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    // Throw an exception if the current token is not
+    // an integer.
+    // Otherwise, return that integer and move to the next token.
+    pub fn eat_int_constant(&mut self) -> DbResult<i32> {
+        match self.current() {
+            Token::IntConstant(i) => {
+                let i = *i;
+                self.advance();
+                Ok(i)
+            }
+            _ => Err(self.syntax_error("an integer")),
+        }
+    }
+
+    // Throw an exception if the current token is not
+    // a string.
+    // Otherwise, return that string and move to the next token.
+    pub fn eat_string_constant(&mut self) -> DbResult<String> {
+        match self.current() {
+            Token::StringConstant(s) => {
+                let s = s.clone();
+                self.advance();
+                Ok(s)
+            }
+            _ => Err(self.syntax_error("a string")),
+        }
+    }
+
+    // Throw an exception if the current token is not
+    // a blob.
+    // Otherwise, return its bytes and move to the next token.
+    pub fn eat_blob_constant(&mut self) -> DbResult<Vec<u8>> {
+        match self.current() {
+            Token::BlobConstant(b) => {
+                let b = b.clone();
+                self.advance();
+                Ok(b)
+            }
+            _ => Err(self.syntax_error("a blob")),
+        }
+    }
+
+    // Throw an exception if the current token is not the
+    // specified keyword.
+    // Otherwise, move to the next token.
+    pub fn eat_keyword(&mut self, w: &str) -> DbResult<()> {
+        if !self.match_keyword(w) {
+            return Err(self.syntax_error(w));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    // Throw an exception if the current token is not
+    // an identifier.
+    // Otherwise, return the identifier string
+    // and move to the next token.
+    pub fn eat_id(&mut self) -> DbResult<String> {
+        match self.current() {
+            Token::Id(s) => {
+                let s = s.clone();
+                self.advance();
+                Ok(s)
+            }
+            _ => Err(self.syntax_error("an identifier")),
+        }
+    }
+
+    // Throw an exception if the current token is neither
+    // an identifier nor a keyword.
+    // Otherwise, return it and move to the next token.
+    // This reads the parts of a qualified name after the
+    // first, where a keyword cannot be mistaken for one.
+    pub fn eat_name(&mut self) -> DbResult<String> {
+        match self.current() {
+            Token::Id(s) | Token::Keyword(s) => {
+                let s = s.clone();
+                self.advance();
+                Ok(s)
+            }
+            _ => Err(self.syntax_error("a name")),
+        }
+    }
+
+    // Throw an exception unless the whole statement was consumed.
+    pub fn eat_eof(&mut self) -> DbResult<()> {
+        if !self.match_eof() {
+            return Err(self.syntax_error("end of statement"));
+        }
+        Ok(())
+    }
+
+    fn current(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn advance(&mut self) {
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+    }
+
+    fn syntax_error(&self, expected: &str) -> DbError {
+        let found = match self.current() {
+            Token::Delim(d) => format!("'{}'", d),
+            Token::IntConstant(i) => i.to_string(),
+            Token::StringConstant(s) => format!("'{}'", s),
+            Token::BlobConstant(b) => {
+                let hex: String = b.iter().map(|b| format!("{:02x}", b)).collect();
+                format!("x'{}'", hex)
+            }
+            Token::Keyword(w) | Token::Id(w) => w.clone(),
+            Token::Eof => "end of statement".to_string(),
+        };
+        DbError::BadSyntax(format!("expected {}, found {}", expected, found))
+    }
+
+    fn tokenize(s: &str) -> DbResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c.is_ascii_digit() {
+                let mut num = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    num.push(d);
+                    chars.next();
+                }
+                let val = num
+                    .parse()
+                    .map_err(|_| DbError::BadSyntax(format!("integer out of range: {}", num)))?;
+                tokens.push(Token::IntConstant(val));
+            } else if c == '\'' {
+                tokens.push(Token::StringConstant(Self::quoted(&mut chars)?));
+            } else if c.is_alphabetic() || c == '_' {
+                let mut word = String::new();
+                while let Some(&ch) = chars
+                    .peek()
+                    .filter(|ch| ch.is_alphanumeric() || **ch == '_')
+                {
+                    word.push(ch);
+                    chars.next();
+                }
+                let word = word.to_lowercase();
+                if word == "x" && chars.peek() == Some(&'\'') {
+                    let hex = Self::quoted(&mut chars)?;
+                    tokens.push(Token::BlobConstant(Self::decode_hex(&hex)?));
+                } else if Self::KEYWORDS.contains(&word.as_str()) {
+                    tokens.push(Token::Keyword(word));
+                } else {
+                    tokens.push(Token::Id(word));
+                }
+            } else {
+                chars.next();
+                tokens.push(Token::Delim(c));
+            }
+        }
+
+        tokens.push(Token::Eof);
+        Ok(tokens)
+    }
+
+    // Read a quoted string, starting at its opening quote.
+    fn quoted(chars: &mut Peekable<Chars>) -> DbResult<String> {
+        chars.next();
+        let mut val = String::new();
+        loop {
+            match chars.next() {
+                Some('\'') => return Ok(val),
+                Some(ch) => val.push(ch),
+                None => return Err(DbError::BadSyntax("unterminated string".to_string())),
+            }
+        }
+    }
+
+    fn decode_hex(hex: &str) -> DbResult<Vec<u8>> {
+        let invalid = || DbError::BadSyntax(format!("invalid blob constant x'{}'", hex));
+        if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() -> DbResult<()> {
+        let mut lex = Lexer::new("CREATE Table T1 (a int, 'it is', 42)")?;
+        lex.eat_keyword("create")?;
+        lex.eat_keyword("table")?;
+        assert_eq!(lex.eat_id()?, "t1");
+        lex.eat_delim('(')?;
+        assert_eq!(lex.eat_id()?, "a");
+        lex.eat_keyword("int")?;
+        lex.eat_delim(',')?;
+        assert_eq!(lex.eat_string_constant()?, "it is");
+        lex.eat_delim(',')?;
+        assert_eq!(lex.eat_int_constant()?, 42);
+        lex.eat_delim(')')?;
+        lex.eat_eof()?;
+
+        let mut lex = Lexer::new("X'00fF', x")?;
+        assert_eq!(lex.eat_blob_constant()?, [0x00, 0xff]);
+        lex.eat_delim(',')?;
+        assert_eq!(lex.eat_id()?, "x");
+        lex.eat_eof()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_unexpected_token() -> DbResult<()> {
+        let mut lex = Lexer::new("table")?;
+        assert!(matches!(lex.eat_id(), Err(DbError::BadSyntax(_))));
+        assert!(Lexer::new("'unterminated").is_err());
+        assert!(Lexer::new("x'abc'").is_err());
+        assert!(Lexer::new("x'zz'").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,19 @@
+mod create_index_data;
+mod create_table_data;
+mod create_view_data;
+mod delete_data;
+mod insert_data;
+mod lexer;
+mod modify_data;
+mod parser;
+mod query_data;
+
+pub use create_index_data::CreateIndexData;
+pub use create_table_data::CreateTableData;
+pub use create_view_data::CreateViewData;
+pub use delete_data::DeleteData;
+pub use insert_data::InsertData;
+pub use lexer::Lexer;
+pub use modify_data::ModifyData;
+pub use parser::{Parser, QueryCommand, UpdateCommand};
+pub use query_data::QueryData;
@@ -0,0 +1,43 @@
+use crate::query::{Expression, Predicate};
+
+// Data for the SQL update statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModifyData {
+    tblname: String,
+    fldname: String,
+    newval: Expression,
+    pred: Predicate,
+}
+
+impl ModifyData {
+    pub fn new(tblname: String, fldname: String, newval: Expression, pred: Predicate) -> Self {
+        ModifyData {
+            tblname,
+            fldname,
+            newval,
+            pred,
+        }
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.tblname
+    }
+
+    // Return the field whose values will be modified
+    pub fn target_field(&self) -> &str {
+        &self.fldname
+    }
+
+    // Return an expression.
+    // Evaluating this expression for a record produces
+    // the value that will be stored in the record's target field.
+    pub fn new_value(&self) -> &Expression {
+        &self.newval
+    }
+
+    // Return the predicate that describes which
+    // records should be modified.
+    pub fn pred(&self) -> &Predicate {
+        &self.pred
+    }
+}
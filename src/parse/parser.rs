@@ -0,0 +1,748 @@
+use super::{
+    CreateIndexData, CreateTableData, CreateViewData, DeleteData, InsertData, Lexer, ModifyData,
+    QueryData,
+};
+use crate::{
+    error::{DbError, DbResult},
+    materialize::{AggregateFunc, SortOrder},
+    query::{Constant, Expression, Predicate, Term},
+    record::{FieldType, Schema},
+};
+
+// The statements that read the database,
+// as returned by Parser::query_cmd.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryCommand {
+    Select(QueryData),
+    Explain(QueryData),
+    // List the tables and views.
+    ShowTables,
+    // List the fields of the named table or view.
+    Describe(String),
+}
+
+// The statements that modify the database,
+// as returned by Parser::update_cmd.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateCommand {
+    Insert(InsertData),
+    Delete(DeleteData),
+    Modify(ModifyData),
+    CreateTable(CreateTableData),
+    CreateIndex(CreateIndexData),
+    CreateView(CreateViewData),
+    // Drop the named index.
+    DropIndex(String),
+    // Compact the named table.
+    Vacuum(String),
+    // Remove every record of the named table.
+    Truncate(String),
+    // Recount the statistics of the named table,
+    // or of every table.
+    Analyze(Option<String>),
+}
+
+// The SimpleDB parser.
+pub struct Parser {
+    lex: Lexer,
+}
+
+impl Parser {
+    pub fn new(s: &str) -> DbResult<Self> {
+        Ok(Parser {
+            lex: Lexer::new(s)?,
+        })
+    }
+
+    // Return true if the statement is a query,
+    // and false if it is an update statement.
+    pub fn is_query(&self) -> bool {
+        ["select", "explain", "show", "describe"]
+            .iter()
+            .any(|w| self.lex.match_keyword(w))
+    }
+
+    // Methods for parsing predicates, terms, expressions, constants, and fields
+
+    pub fn field(&mut self) -> DbResult<String> {
+        self.lex.eat_id()
+    }
+
+    pub fn constant(&mut self) -> DbResult<Constant> {
+        if self.lex.match_keyword("null") {
+            self.lex.eat_keyword("null")?;
+            Ok(Constant::Null)
+        } else if self.lex.match_string_constant() {
+            Ok(Constant::Str(self.lex.eat_string_constant()?))
+        } else if self.lex.match_blob_constant() {
+            Ok(Constant::Blob(self.lex.eat_blob_constant()?))
+        } else {
+            Ok(Constant::Int(self.lex.eat_int_constant()?))
+        }
+    }
+
+    pub fn expression(&mut self) -> DbResult<Expression> {
+        if self.lex.match_id() {
+            Ok(Expression::Field(self.field()?))
+        } else {
+            Ok(Expression::Constant(self.constant()?))
+        }
+    }
+
+    // A term is either an equality between two expressions,
+    // or a test of the form "<expression> is [not] null".
+    pub fn term(&mut self) -> DbResult<Term> {
+        let lhs = self.expression()?;
+        if self.lex.match_keyword("is") {
+            self.lex.eat_keyword("is")?;
+            let negated = self.lex.match_keyword("not");
+            if negated {
+                self.lex.eat_keyword("not")?;
+            }
+            self.lex.eat_keyword("null")?;
+            return Ok(Term::null_test(lhs, negated));
+        }
+        self.lex.eat_delim('=')?;
+        let rhs = self.expression()?;
+        Ok(Term::new(lhs, rhs))
+    }
+
+    pub fn predicate(&mut self) -> DbResult<Predicate> {
+        let mut pred = Predicate::from_term(self.term()?);
+        if self.lex.match_keyword("and") {
+            self.lex.eat_keyword("and")?;
+            pred.conjoin_with(self.predicate()?);
+        }
+        Ok(pred)
+    }
+
+    // Methods for parsing queries
+
+    pub fn query_cmd(&mut self) -> DbResult<QueryCommand> {
+        if self.lex.match_keyword("explain") {
+            self.lex.eat_keyword("explain")?;
+            Ok(QueryCommand::Explain(self.query()?))
+        } else if self.lex.match_keyword("show") {
+            self.lex.eat_keyword("show")?;
+            self.lex.eat_keyword("tables")?;
+            self.lex.eat_eof()?;
+            Ok(QueryCommand::ShowTables)
+        } else if self.lex.match_keyword("describe") {
+            self.lex.eat_keyword("describe")?;
+            let tblname = self.table_name()?;
+            self.lex.eat_eof()?;
+            Ok(QueryCommand::Describe(tblname))
+        } else {
+            Ok(QueryCommand::Select(self.query()?))
+        }
+    }
+
+    pub fn query(&mut self) -> DbResult<QueryData> {
+        self.lex.eat_keyword("select")?;
+        let distinct = self.lex.match_keyword("distinct");
+        if distinct {
+            self.lex.eat_keyword("distinct")?;
+        }
+        let mut fields = Vec::new();
+        let mut aggregates = Vec::new();
+        for (fldname, func) in self.select_list()? {
+            match func {
+                Some(func) => {
+                    fields.push(func.field_name(&fldname));
+                    aggregates.push((func, fldname));
+                }
+                None => fields.push(fldname),
+            }
+        }
+        self.lex.eat_keyword("from")?;
+        let tables = self.table_list()?;
+        let pred = self.optional_where()?;
+        let groupfields = if self.lex.match_keyword("group") {
+            self.lex.eat_keyword("group")?;
+            self.lex.eat_keyword("by")?;
+            self.field_list()?
+        } else {
+            Vec::new()
+        };
+        let orderkeys = if self.lex.match_keyword("order") {
+            self.lex.eat_keyword("order")?;
+            self.lex.eat_keyword("by")?;
+            self.order_list()?
+        } else {
+            Vec::new()
+        };
+        self.lex.eat_eof()?;
+        Ok(QueryData::new(
+            distinct,
+            fields,
+            tables,
+            pred,
+            groupfields,
+            aggregates,
+            orderkeys,
+        ))
+    }
+
+    fn order_list(&mut self) -> DbResult<Vec<(String, SortOrder)>> {
+        let mut list = vec![self.order_key()?];
+        while self.lex.match_delim(',') {
+            self.lex.eat_delim(',')?;
+            list.push(self.order_key()?);
+        }
+        Ok(list)
+    }
+
+    // An order key is a field, optionally followed by
+    // asc or desc; the default is ascending.
+    fn order_key(&mut self) -> DbResult<(String, SortOrder)> {
+        let fldname = self.field()?;
+        if self.lex.match_keyword("desc") {
+            self.lex.eat_keyword("desc")?;
+            return Ok((fldname, SortOrder::Desc));
+        }
+        if self.lex.match_keyword("asc") {
+            self.lex.eat_keyword("asc")?;
+        }
+        Ok((fldname, SortOrder::Asc))
+    }
+
+    fn select_list(&mut self) -> DbResult<Vec<(String, Option<AggregateFunc>)>> {
+        let mut list = vec![self.select_item()?];
+        while self.lex.match_delim(',') {
+            self.lex.eat_delim(',')?;
+            list.push(self.select_item()?);
+        }
+        Ok(list)
+    }
+
+    // A select item is either a field or an aggregation
+    // function applied to a field.
+    fn select_item(&mut self) -> DbResult<(String, Option<AggregateFunc>)> {
+        match self.aggregate_func() {
+            Some(func) => {
+                self.lex.eat_keyword(func.name())?;
+                self.lex.eat_delim('(')?;
+                let fldname = self.field()?;
+                self.lex.eat_delim(')')?;
+                Ok((fldname, Some(func)))
+            }
+            None => Ok((self.field()?, None)),
+        }
+    }
+
+    fn aggregate_func(&self) -> Option<AggregateFunc> {
+        AggregateFunc::NAMES
+            .iter()
+            .find(|name| self.lex.match_keyword(name))
+            .and_then(|name| AggregateFunc::from_name(name))
+    }
+
+    fn table_list(&mut self) -> DbResult<Vec<String>> {
+        let mut list = vec![self.table_name()?];
+        while self.lex.match_delim(',') {
+            self.lex.eat_delim(',')?;
+            list.push(self.table_name()?);
+        }
+        Ok(list)
+    }
+
+    // A table read by a query may have a qualified name,
+    // such as information_schema.tables, which is kept
+    // with its dot.
+    fn table_name(&mut self) -> DbResult<String> {
+        let mut name = self.lex.eat_id()?;
+        while self.lex.match_delim('.') {
+            self.lex.eat_delim('.')?;
+            name.push('.');
+            name.push_str(&self.lex.eat_name()?);
+        }
+        Ok(name)
+    }
+
+    // Methods for parsing the various update commands
+
+    pub fn update_cmd(&mut self) -> DbResult<UpdateCommand> {
+        if self.lex.match_keyword("insert") {
+            Ok(UpdateCommand::Insert(self.insert()?))
+        } else if self.lex.match_keyword("delete") {
+            Ok(UpdateCommand::Delete(self.delete()?))
+        } else if self.lex.match_keyword("update") {
+            Ok(UpdateCommand::Modify(self.modify()?))
+        } else if self.lex.match_keyword("vacuum") {
+            Ok(UpdateCommand::Vacuum(self.vacuum()?))
+        } else if self.lex.match_keyword("truncate") {
+            Ok(UpdateCommand::Truncate(self.truncate()?))
+        } else if self.lex.match_keyword("analyze") {
+            Ok(UpdateCommand::Analyze(self.analyze()?))
+        } else if self.lex.match_keyword("drop") {
+            Ok(UpdateCommand::DropIndex(self.drop_index()?))
+        } else if self.lex.match_next_keyword("index") {
+            Ok(UpdateCommand::CreateIndex(self.create_index()?))
+        } else if self.lex.match_next_keyword("view") {
+            Ok(UpdateCommand::CreateView(self.create_view()?))
+        } else {
+            Ok(UpdateCommand::CreateTable(self.create_table()?))
+        }
+    }
+
+    // Method for parsing delete commands
+
+    pub fn delete(&mut self) -> DbResult<DeleteData> {
+        self.lex.eat_keyword("delete")?;
+        self.lex.eat_keyword("from")?;
+        let tblname = self.lex.eat_id()?;
+        let pred = self.optional_where()?;
+        self.lex.eat_eof()?;
+        Ok(DeleteData::new(tblname, pred))
+    }
+
+    // Methods for parsing insert commands
+
+    pub fn insert(&mut self) -> DbResult<InsertData> {
+        self.lex.eat_keyword("insert")?;
+        self.lex.eat_keyword("into")?;
+        let tblname = self.lex.eat_id()?;
+        self.lex.eat_delim('(')?;
+        let flds = self.field_list()?;
+        self.lex.eat_delim(')')?;
+        self.lex.eat_keyword("values")?;
+        self.lex.eat_delim('(')?;
+        let vals = self.const_list()?;
+        self.lex.eat_delim(')')?;
+        self.lex.eat_eof()?;
+        Ok(InsertData::new(tblname, flds, vals))
+    }
+
+    fn field_list(&mut self) -> DbResult<Vec<String>> {
+        let mut list = vec![self.field()?];
+        while self.lex.match_delim(',') {
+            self.lex.eat_delim(',')?;
+            list.push(self.field()?);
+        }
+        Ok(list)
+    }
+
+    fn const_list(&mut self) -> DbResult<Vec<Constant>> {
+        let mut list = vec![self.constant()?];
+        while self.lex.match_delim(',') {
+            self.lex.eat_delim(',')?;
+            list.push(self.constant()?);
+        }
+        Ok(list)
+    }
+
+    // Method for parsing modify commands
+
+    pub fn modify(&mut self) -> DbResult<ModifyData> {
+        self.lex.eat_keyword("update")?;
+        let tblname = self.lex.eat_id()?;
+        self.lex.eat_keyword("set")?;
+        let fldname = self.field()?;
+        self.lex.eat_delim('=')?;
+        let newval = self.expression()?;
+        let pred = self.optional_where()?;
+        self.lex.eat_eof()?;
+        Ok(ModifyData::new(tblname, fldname, newval, pred))
+    }
+
+    fn optional_where(&mut self) -> DbResult<Predicate> {
+        if self.lex.match_keyword("where") {
+            self.lex.eat_keyword("where")?;
+            self.predicate()
+        } else {
+            Ok(Predicate::new())
+        }
+    }
+
+    // Method for parsing vacuum commands, which
+    // return the name of the table
+
+    pub fn vacuum(&mut self) -> DbResult<String> {
+        self.lex.eat_keyword("vacuum")?;
+        let tblname = self.lex.eat_id()?;
+        self.lex.eat_eof()?;
+        Ok(tblname)
+    }
+
+    // Method for parsing truncate commands, which
+    // return the name of the table
+
+    pub fn truncate(&mut self) -> DbResult<String> {
+        self.lex.eat_keyword("truncate")?;
+        self.lex.eat_keyword("table")?;
+        let tblname = self.lex.eat_id()?;
+        self.lex.eat_eof()?;
+        Ok(tblname)
+    }
+
+    // Method for parsing analyze commands, which
+    // return the name of the table, if there is one
+
+    pub fn analyze(&mut self) -> DbResult<Option<String>> {
+        self.lex.eat_keyword("analyze")?;
+        let tblname = if self.lex.match_id() {
+            Some(self.lex.eat_id()?)
+        } else {
+            None
+        };
+        self.lex.eat_eof()?;
+        Ok(tblname)
+    }
+
+    // Method for parsing drop index commands, which
+    // return the name of the index
+
+    pub fn drop_index(&mut self) -> DbResult<String> {
+        self.lex.eat_keyword("drop")?;
+        self.lex.eat_keyword("index")?;
+        let idxname = self.lex.eat_id()?;
+        self.lex.eat_eof()?;
+        Ok(idxname)
+    }
+
+    // Methods for parsing the various create commands
+
+    pub fn create_table(&mut self) -> DbResult<CreateTableData> {
+        self.lex.eat_keyword("create")?;
+        self.lex.eat_keyword("table")?;
+        let tblname = self.lex.eat_id()?;
+        self.lex.eat_delim('(')?;
+        let (sch, auto_fields) = self.field_defs()?;
+        self.lex.eat_delim(')')?;
+        self.lex.eat_eof()?;
+        Ok(CreateTableData::new(tblname, sch, auto_fields))
+    }
+
+    pub fn create_index(&mut self) -> DbResult<CreateIndexData> {
+        self.lex.eat_keyword("create")?;
+        self.lex.eat_keyword("index")?;
+        let idxname = self.lex.eat_id()?;
+        self.lex.eat_keyword("on")?;
+        let tblname = self.lex.eat_id()?;
+        self.lex.eat_delim('(')?;
+        let fldname = self.field()?;
+        self.lex.eat_delim(')')?;
+        self.lex.eat_eof()?;
+        Ok(CreateIndexData::new(idxname, tblname, fldname))
+    }
+
+    pub fn create_view(&mut self) -> DbResult<CreateViewData> {
+        self.lex.eat_keyword("create")?;
+        self.lex.eat_keyword("view")?;
+        let viewname = self.lex.eat_id()?;
+        self.lex.eat_keyword("as")?;
+        let qrydata = self.query()?;
+        Ok(CreateViewData::new(viewname, qrydata))
+    }
+
+    // Return the schema of the field definitions, and the
+    // names of the fields declared auto_increment.
+    fn field_defs(&mut self) -> DbResult<(Schema, Vec<String>)> {
+        let mut schema = Schema::new();
+        let mut auto_fields = Vec::new();
+        loop {
+            let fldname = self.field()?;
+            schema.add_all(&self.field_type(&fldname)?);
+            if self.lex.match_keyword("auto_increment") {
+                self.lex.eat_keyword("auto_increment")?;
+                if schema.field_type(&fldname) != FieldType::Integer {
+                    return Err(DbError::BadSyntax(format!(
+                        "auto_increment field {} is not an int",
+                        fldname
+                    )));
+                }
+                auto_fields.push(fldname);
+            }
+            if !self.lex.match_delim(',') {
+                return Ok((schema, auto_fields));
+            }
+            self.lex.eat_delim(',')?;
+        }
+    }
+
+    fn field_type(&mut self, fldname: &str) -> DbResult<Schema> {
+        let mut schema = Schema::new();
+        if self.lex.match_keyword("int") {
+            self.lex.eat_keyword("int")?;
+            schema.add_int_field(fldname);
+        } else if self.lex.match_keyword("blob") {
+            self.lex.eat_keyword("blob")?;
+            schema.add_blob_field(fldname);
+        } else {
+            self.lex.eat_keyword("varchar")?;
+            self.lex.eat_delim('(')?;
+            let str_len = self.lex.eat_int_constant()?;
+            self.lex.eat_delim(')')?;
+            schema.add_string_field(fldname, str_len as usize);
+        }
+        Ok(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query() -> DbResult<()> {
+        let data = Parser::new("select a, b from t1, t2 where a = c and b = 'x'")?.query()?;
+        assert_eq!(data.fields(), ["a", "b"]);
+        assert_eq!(data.tables(), ["t1", "t2"]);
+        assert_eq!(data.pred().to_string(), "a=c and b='x'");
+        assert_eq!(
+            data.to_string(),
+            "select a, b from t1, t2 where a=c and b='x'"
+        );
+
+        // a qualified name may end in a keyword
+        let data = Parser::new("select a from information_schema.tables, t")?.query()?;
+        assert_eq!(data.tables(), ["information_schema.tables", "t"]);
+        assert_eq!(
+            data.to_string(),
+            "select a from information_schema.tables, t"
+        );
+        let result = Parser::new("select a from s.")?.query();
+        assert!(matches!(result, Err(DbError::BadSyntax(_))));
+
+        let cmd = Parser::new("show tables")?.query_cmd()?;
+        assert_eq!(cmd, QueryCommand::ShowTables);
+        let cmd = Parser::new("DESCRIBE T")?.query_cmd()?;
+        assert_eq!(cmd, QueryCommand::Describe("t".to_string()));
+        assert!(Parser::new("describe t")?.is_query());
+        for sql in ["show", "show tables t", "describe", "describe t, u"] {
+            let result = Parser::new(sql)?.query_cmd();
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+
+        let cmd = Parser::new("explain select a from t")?.query_cmd()?;
+        assert!(matches!(cmd, QueryCommand::Explain(data) if data.tables() == ["t"]));
+
+        let data = Parser::new("select a from t")?.query()?;
+        assert!(data.pred().terms().is_empty());
+        assert_eq!(data.to_string(), "select a from t");
+
+        let sql = "select a, count(b), max(c) from t where d=1 group by a";
+        let data = Parser::new(sql)?.query()?;
+        assert_eq!(data.fields(), ["a", "countofb", "maxofc"]);
+        assert_eq!(data.group_fields(), ["a"]);
+        assert_eq!(
+            data.aggregates(),
+            [
+                (AggregateFunc::Count, "b".to_string()),
+                (AggregateFunc::Max, "c".to_string())
+            ]
+        );
+        assert_eq!(data.to_string(), sql);
+
+        let data = Parser::new("select a from t where a is null and b is not null")?.query()?;
+        assert_eq!(data.pred().to_string(), "a is null and b is not null");
+
+        let data = Parser::new("select distinct a from t")?.query()?;
+        assert!(data.is_distinct());
+        assert_eq!(data.to_string(), "select distinct a from t");
+
+        let data = Parser::new("select a, b from t order by b desc, a")?.query()?;
+        assert_eq!(
+            data.order_keys(),
+            [
+                ("b".to_string(), SortOrder::Desc),
+                ("a".to_string(), SortOrder::Asc)
+            ]
+        );
+        assert_eq!(
+            data.to_string(),
+            "select a, b from t order by b desc, a asc"
+        );
+
+        for sql in [
+            "select from t",
+            "select a from",
+            "select a t",
+            "select a from t,",
+            "select a from t order a",
+            "select a from t order by a asc desc",
+            "select a from t where a is 1",
+            "select a from t where a not null",
+        ] {
+            let result = Parser::new(sql)?.query();
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_table() -> DbResult<()> {
+        let data = Parser::new("create table T (a int, b varchar(20), c blob)")?.create_table()?;
+        assert_eq!(data.table_name(), "t");
+
+        let sch = data.new_schema();
+        assert_eq!(sch.fields(), ["a", "b", "c"]);
+        assert_eq!(sch.field_type("c"), FieldType::Blob);
+        assert_eq!(sch.field_type("a"), FieldType::Integer);
+        assert_eq!(sch.field_type("b"), FieldType::Varchar);
+        assert_eq!(sch.length("b"), 20);
+        assert!(data.auto_increment_fields().is_empty());
+
+        let data =
+            Parser::new("create table t (id int auto_increment, b varchar(20))")?.create_table()?;
+        assert_eq!(data.new_schema().fields(), ["id", "b"]);
+        assert_eq!(data.auto_increment_fields(), ["id"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_and_drop_index() -> DbResult<()> {
+        let cmd = Parser::new("CREATE INDEX t_a ON T (A)")?.update_cmd()?;
+        let data = CreateIndexData::new("t_a".to_string(), "t".to_string(), "a".to_string());
+        assert_eq!(cmd, UpdateCommand::CreateIndex(data));
+        let cmd = Parser::new("drop index t_a")?.update_cmd()?;
+        assert_eq!(cmd, UpdateCommand::DropIndex("t_a".to_string()));
+        for sql in [
+            "create index t_a t (a)",
+            "create index on t (a)",
+            "create index t_a on t (a, b)",
+            "create index t_a on t a",
+            "drop index",
+            "drop table t",
+        ] {
+            let result = Parser::new(sql)?.update_cmd();
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_view() -> DbResult<()> {
+        let cmd = Parser::new("create view v as select a, b from t where a = 'x'")?.update_cmd()?;
+        let UpdateCommand::CreateView(data) = cmd else {
+            panic!("expected a create view");
+        };
+        assert_eq!(data.view_name(), "v");
+        assert_eq!(data.query_data().tables(), ["t"]);
+        assert_eq!(data.view_def(), "select a, b from t where a='x'");
+        // the definition parses back to the same query
+        assert_eq!(&Parser::new(&data.view_def())?.query()?, data.query_data());
+        for sql in [
+            "create view v select a from t",
+            "create view as select a from t",
+            "create view v as a from t",
+            "create view v as select a from t extra",
+        ] {
+            let result = Parser::new(sql)?.update_cmd();
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_table_bad_syntax() -> DbResult<()> {
+        for sql in [
+            "create table t a int",
+            "create table t (a integer)",
+            "create table t (a varchar)",
+            "create table t (a int) extra",
+            "create table t (a varchar(10) auto_increment)",
+            "create table t (a auto_increment)",
+        ] {
+            let result = Parser::new(sql)?.create_table();
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert() -> DbResult<()> {
+        let cmd = Parser::new("insert into t (a, b) values (1, 'one')")?.update_cmd()?;
+        let UpdateCommand::Insert(data) = cmd else {
+            panic!("expected an insert");
+        };
+        assert_eq!(data.table_name(), "t");
+        assert_eq!(data.fields(), ["a", "b"]);
+        assert_eq!(data.vals(), [Constant::Int(1), Constant::from("one")]);
+
+        let data = Parser::new("insert into t (a, b) values (null, x'0aff')")?.insert()?;
+        assert_eq!(
+            data.vals(),
+            [Constant::Null, Constant::Blob(vec![0x0a, 0xff])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete() -> DbResult<()> {
+        let cmd = Parser::new("delete from t where a = 1 and b = c")?.update_cmd()?;
+        let UpdateCommand::Delete(data) = cmd else {
+            panic!("expected a delete");
+        };
+        assert_eq!(data.table_name(), "t");
+        assert_eq!(data.pred().to_string(), "a=1 and b=c");
+
+        let data = Parser::new("delete from t")?.delete()?;
+        assert!(data.pred().terms().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_modify() -> DbResult<()> {
+        let cmd = Parser::new("update t set b = 'two' where a = 2")?.update_cmd()?;
+        let UpdateCommand::Modify(data) = cmd else {
+            panic!("expected an update");
+        };
+        assert_eq!(data.table_name(), "t");
+        assert_eq!(data.target_field(), "b");
+        assert_eq!(
+            data.new_value(),
+            &Expression::Constant(Constant::from("two"))
+        );
+        assert_eq!(data.pred().to_string(), "a=2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum() -> DbResult<()> {
+        let cmd = Parser::new("VACUUM t")?.update_cmd()?;
+        assert_eq!(cmd, UpdateCommand::Vacuum("t".to_string()));
+        let result = Parser::new("vacuum t, u")?.update_cmd();
+        assert!(matches!(result, Err(DbError::BadSyntax(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate() -> DbResult<()> {
+        let cmd = Parser::new("TRUNCATE TABLE t")?.update_cmd()?;
+        assert_eq!(cmd, UpdateCommand::Truncate("t".to_string()));
+        for sql in ["truncate t", "truncate table t where a = 1"] {
+            let result = Parser::new(sql)?.update_cmd();
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze() -> DbResult<()> {
+        let cmd = Parser::new("ANALYZE t")?.update_cmd()?;
+        assert_eq!(cmd, UpdateCommand::Analyze(Some("t".to_string())));
+        let cmd = Parser::new("analyze")?.update_cmd()?;
+        assert_eq!(cmd, UpdateCommand::Analyze(None));
+        for sql in ["analyze t, u", "analyze table t"] {
+            let result = Parser::new(sql)?.update_cmd();
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_dml_bad_syntax() -> DbResult<()> {
+        for sql in [
+            "insert into t (a) values ()",
+            "insert into t values (1)",
+            "delete t where a = 1",
+            "update t set where a = 1",
+            "update t set a = 1 where",
+        ] {
+            let result = Parser::new(sql)?.update_cmd();
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+        Ok(())
+    }
+}
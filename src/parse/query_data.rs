@@ -0,0 +1,120 @@
+use std::fmt;
+
+use crate::{
+    materialize::{AggregateFunc, SortOrder},
+    query::Predicate,
+};
+
+// Data for the SQL select statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryData {
+    distinct: bool,
+    fields: Vec<String>,
+    tables: Vec<String>,
+    pred: Predicate,
+    groupfields: Vec<String>,
+    aggregates: Vec<(AggregateFunc, String)>,
+    orderkeys: Vec<(String, SortOrder)>,
+}
+
+impl QueryData {
+    // Save the field and table list and predicate,
+    // along with the grouping fields, the aggregates
+    // computed for each group, and the sort keys.
+    // If distinct is true, duplicate output records are removed.
+    pub fn new(
+        distinct: bool,
+        fields: Vec<String>,
+        tables: Vec<String>,
+        pred: Predicate,
+        groupfields: Vec<String>,
+        aggregates: Vec<(AggregateFunc, String)>,
+        orderkeys: Vec<(String, SortOrder)>,
+    ) -> Self {
+        QueryData {
+            distinct,
+            fields,
+            tables,
+            pred,
+            groupfields,
+            aggregates,
+            orderkeys,
+        }
+    }
+
+    // Return true if the select clause asks for distinct records.
+    pub fn is_distinct(&self) -> bool {
+        self.distinct
+    }
+
+    // Return the fields mentioned in the select clause.
+    // An aggregate appears under the name of its
+    // aggregation field, such as "countofsid".
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    // Return the tables mentioned in the from clause.
+    pub fn tables(&self) -> &[String] {
+        &self.tables
+    }
+
+    // Return the predicate that describes which
+    // records should be in the output table.
+    pub fn pred(&self) -> &Predicate {
+        &self.pred
+    }
+
+    // Return the fields mentioned in the group by clause.
+    pub fn group_fields(&self) -> &[String] {
+        &self.groupfields
+    }
+
+    // Return the aggregation functions in the select clause,
+    // each paired with the field it aggregates.
+    pub fn aggregates(&self) -> &[(AggregateFunc, String)] {
+        &self.aggregates
+    }
+
+    // Return the keys of the order by clause,
+    // in order of significance.
+    pub fn order_keys(&self) -> &[(String, SortOrder)] {
+        &self.orderkeys
+    }
+
+    fn select_item(&self, fldname: &str) -> String {
+        self.aggregates
+            .iter()
+            .find(|(func, aggfld)| func.field_name(aggfld) == fldname)
+            .map(|(func, aggfld)| format!("{}({})", func.name(), aggfld))
+            .unwrap_or_else(|| fldname.to_string())
+    }
+}
+
+impl fmt::Display for QueryData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = self.fields.iter().map(|f| self.select_item(f)).collect();
+        write!(
+            f,
+            "select {}{} from {}",
+            if self.distinct { "distinct " } else { "" },
+            items.join(", "),
+            self.tables.join(", ")
+        )?;
+        if !self.pred.terms().is_empty() {
+            write!(f, " where {}", self.pred)?;
+        }
+        if !self.groupfields.is_empty() {
+            write!(f, " group by {}", self.groupfields.join(", "))?;
+        }
+        if !self.orderkeys.is_empty() {
+            let keys: Vec<String> = self
+                .orderkeys
+                .iter()
+                .map(|(fldname, order)| format!("{} {}", fldname, order.name()))
+                .collect();
+            write!(f, " order by {}", keys.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,852 @@
+use std::sync::Arc;
+
+use super::UpdatePlanner;
+use crate::{
+    error::{DbError, DbResult},
+    index::Index,
+    metadata::MetadataManager,
+    parse::{CreateIndexData, CreateTableData, CreateViewData, DeleteData, InsertData, ModifyData},
+    query::{Constant, Scan, SelectScan, UpdateScan},
+    record::{FieldType, Layout, TableScan},
+    tx::Transaction,
+};
+
+// The basic planner for SQL update statements.
+// Each statement is executed against a table scan of
+// the target table, filtered by a select scan when
+// the statement has a where clause.
+// The indexes on the target table are updated along with
+// its records, so that index-based query plans see every change.
+// Null values are not indexed, since no equality
+// selection or join can match them.
+pub struct BasicUpdatePlanner {
+    mdm: Arc<MetadataManager>,
+}
+
+impl BasicUpdatePlanner {
+    pub fn new(mdm: Arc<MetadataManager>) -> Self {
+        BasicUpdatePlanner { mdm }
+    }
+
+    // Check that the field exists in the table and
+    // that the value has the field's type.
+    // A null is allowed in a field of any type, and
+    // a string must fit in its varchar field.
+    fn check_field(tblname: &str, layout: &Layout, fldname: &str, val: &Constant) -> DbResult<()> {
+        let sch = layout.schema();
+        if !sch.has_field(fldname) {
+            return Err(DbError::Catalog(format!(
+                "field {} not found in table {}",
+                fldname, tblname
+            )));
+        }
+        let matches = match val {
+            Constant::Null => true,
+            Constant::Int(_) => sch.field_type(fldname) == FieldType::Integer,
+            Constant::Str(_) => sch.field_type(fldname) == FieldType::Varchar,
+            Constant::Blob(_) => sch.field_type(fldname) == FieldType::Blob,
+        };
+        if !matches {
+            return Err(DbError::Catalog(format!(
+                "type mismatch for field {} of table {}",
+                fldname, tblname
+            )));
+        }
+        if let Constant::Str(s) = val {
+            let max = sch.length(fldname);
+            if s.len() > max {
+                return Err(DbError::ValueTooLong {
+                    field: fldname.to_string(),
+                    max,
+                    len: s.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Open every index on the specified table, keyed by
+    // the indexed field.
+    fn open_indexes(
+        &self,
+        tblname: &str,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<Vec<(String, Box<dyn Index>)>> {
+        Ok(self
+            .mdm
+            .get_index_info(tblname, tx)?
+            .into_iter()
+            .map(|(fldname, ii)| (fldname, ii.open()))
+            .collect())
+    }
+}
+
+impl UpdatePlanner for BasicUpdatePlanner {
+    // An auto-increment field that the statement leaves
+    // out, or sets to null, takes the next value of its
+    // sequence; an explicit value moves the sequence past it.
+    fn execute_insert(&self, data: &InsertData, tx: &Arc<Transaction>) -> DbResult<usize> {
+        let tblname = data.table_name();
+        let layout = self.mdm.get_layout(tblname, tx)?;
+        if data.fields().len() != data.vals().len() {
+            return Err(DbError::Catalog(format!(
+                "{} fields but {} values",
+                data.fields().len(),
+                data.vals().len()
+            )));
+        }
+        for (fldname, val) in data.fields().iter().zip(data.vals()) {
+            Self::check_field(tblname, &layout, fldname, val)?;
+        }
+        let mut vals: Vec<(String, Constant)> = data
+            .fields()
+            .iter()
+            .cloned()
+            .zip(data.vals().iter().cloned())
+            .collect();
+        for fldname in self.mdm.sequence_fields(tblname) {
+            match vals.iter_mut().find(|(f, _)| *f == fldname) {
+                Some((_, Constant::Int(val))) => {
+                    self.mdm.advance_past(tblname, &fldname, *val, tx)?;
+                }
+                Some((_, val)) => {
+                    *val = Constant::Int(self.mdm.next_value(tblname, &fldname, tx)?);
+                }
+                None => {
+                    let val = Constant::Int(self.mdm.next_value(tblname, &fldname, tx)?);
+                    vals.push((fldname, val));
+                }
+            }
+        }
+
+        let indexes = self.mdm.get_index_info(tblname, tx)?;
+        let mut ts = TableScan::new(Arc::clone(tx), tblname, layout.clone())?;
+        ts.insert()?;
+        let rid = ts.get_rid();
+        for (fldname, val) in &vals {
+            ts.set_val(fldname, val)?;
+            if let Some(ii) = indexes.get(fldname).filter(|_| !val.is_null()) {
+                let mut idx = ii.open();
+                idx.insert(val, rid)?;
+                idx.close();
+            }
+        }
+        // the other fields are null
+        for fldname in layout.schema().fields() {
+            if !vals.iter().any(|(f, _)| f == fldname) {
+                ts.set_val(fldname, &Constant::Null)?;
+            }
+        }
+        ts.close();
+        Ok(1)
+    }
+
+    fn execute_delete(&self, data: &DeleteData, tx: &Arc<Transaction>) -> DbResult<usize> {
+        let layout = self.mdm.get_layout(data.table_name(), tx)?;
+        let mut indexes = self.open_indexes(data.table_name(), tx)?;
+        let ts = TableScan::new(Arc::clone(tx), data.table_name(), layout)?;
+        let mut us = SelectScan::new(ts, data.pred().clone());
+        let mut count = 0;
+        while us.next()? {
+            // first, delete the record's RID from every index
+            let rid = us.get_rid();
+            for (fldname, idx) in indexes.iter_mut() {
+                let val = us.get_val(fldname)?;
+                if !val.is_null() {
+                    idx.delete(&val, rid)?;
+                }
+            }
+            // then delete the record
+            us.delete()?;
+            count += 1;
+        }
+        us.close();
+        for (_, mut idx) in indexes {
+            idx.close();
+        }
+        Ok(count)
+    }
+
+    fn execute_modify(&self, data: &ModifyData, tx: &Arc<Transaction>) -> DbResult<usize> {
+        let layout = self.mdm.get_layout(data.table_name(), tx)?;
+        let mut idx = self
+            .mdm
+            .get_index_info(data.table_name(), tx)?
+            .get(data.target_field())
+            .map(|ii| ii.open());
+        let ts = TableScan::new(Arc::clone(tx), data.table_name(), layout.clone())?;
+        let mut us = SelectScan::new(ts, data.pred().clone());
+        let mut count = 0;
+        while us.next()? {
+            let newval = data.new_value().evaluate(&us)?;
+            Self::check_field(data.table_name(), &layout, data.target_field(), &newval)?;
+            let oldval = us.get_val(data.target_field())?;
+            us.set_val(data.target_field(), &newval)?;
+
+            // update the appropriate index, if it exists
+            if let Some(idx) = idx.as_mut() {
+                let rid = us.get_rid();
+                if !oldval.is_null() {
+                    idx.delete(&oldval, rid)?;
+                }
+                if !newval.is_null() {
+                    idx.insert(&newval, rid)?;
+                }
+            }
+            count += 1;
+        }
+        us.close();
+        if let Some(mut idx) = idx {
+            idx.close();
+        }
+        Ok(count)
+    }
+
+    // A table cannot take the name of a view,
+    // which would hide it from queries, or of another table.
+    // Each auto-increment field gets a sequence.
+    fn execute_create_table(
+        &self,
+        data: &CreateTableData,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<usize> {
+        if self.mdm.get_layout(data.table_name(), tx).is_ok() {
+            return Err(DbError::Catalog(format!(
+                "table {} already exists",
+                data.table_name()
+            )));
+        }
+        if self.mdm.get_view_def(data.table_name(), tx)?.is_some() {
+            return Err(DbError::Catalog(format!(
+                "view {} already exists",
+                data.table_name()
+            )));
+        }
+        self.mdm
+            .create_table(data.table_name(), data.new_schema(), tx)?;
+        self.mdm
+            .create_sequences(data.table_name(), data.auto_increment_fields(), tx)?;
+        Ok(0)
+    }
+
+    // Save the definition of the view, whose name
+    // must not be taken by a table.
+    fn execute_create_view(&self, data: &CreateViewData, tx: &Arc<Transaction>) -> DbResult<usize> {
+        if self.mdm.get_layout(data.view_name(), tx).is_ok() {
+            return Err(DbError::Catalog(format!(
+                "table {} already exists",
+                data.view_name()
+            )));
+        }
+        self.mdm
+            .create_view(data.view_name(), &data.view_def(), tx)?;
+        Ok(0)
+    }
+
+    // Register the index in the catalog, then insert an
+    // index record for each non-null value of the field.
+    // A field has at most one index.
+    fn execute_create_index(
+        &self,
+        data: &CreateIndexData,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<usize> {
+        let tblname = data.table_name();
+        let fldname = data.field_name();
+        let layout = self.mdm.get_layout(tblname, tx)?;
+        if !layout.schema().has_field(fldname) {
+            return Err(DbError::Catalog(format!(
+                "field {} not found in table {}",
+                fldname, tblname
+            )));
+        }
+        if self.mdm.get_index_info(tblname, tx)?.contains_key(fldname) {
+            return Err(DbError::Catalog(format!(
+                "field {} of table {} is already indexed",
+                fldname, tblname
+            )));
+        }
+        self.mdm
+            .create_index(data.index_name(), tblname, fldname, tx)?;
+
+        let mut idx = self.mdm.get_index_info(tblname, tx)?[fldname].open();
+        let mut ts = TableScan::new(Arc::clone(tx), tblname, layout)?;
+        while ts.next()? {
+            let val = ts.get_val(fldname)?;
+            if !val.is_null() {
+                idx.insert(&val, ts.get_rid())?;
+            }
+        }
+        ts.close();
+        idx.close();
+        Ok(0)
+    }
+
+    // Remove the index from the catalog, and cut its
+    // files down to nothing when the transaction commits.
+    fn execute_drop_index(&self, idxname: &str, tx: &Arc<Transaction>) -> DbResult<usize> {
+        let ii = self.mdm.drop_index(idxname, tx)?;
+        let mut idx = ii.open();
+        idx.truncate()?;
+        idx.close();
+        Ok(0)
+    }
+
+    // Compact the table, then point the index entries
+    // of each moved record at its new RID.
+    fn execute_vacuum(&self, tblname: &str, tx: &Arc<Transaction>) -> DbResult<usize> {
+        let layout = self.mdm.get_layout(tblname, tx)?;
+        let mut indexes = self.open_indexes(tblname, tx)?;
+        let mut ts = TableScan::new(Arc::clone(tx), tblname, layout)?;
+        let compaction = ts.compact()?;
+        for &(from, to) in &compaction.moved {
+            ts.move_to_rid(to)?;
+            for (fldname, idx) in indexes.iter_mut() {
+                let val = ts.get_val(fldname)?;
+                if !val.is_null() {
+                    idx.delete(&val, from)?;
+                    idx.insert(&val, to)?;
+                }
+            }
+        }
+        ts.close();
+        for (_, mut idx) in indexes {
+            idx.close();
+        }
+        Ok(compaction.freed as usize)
+    }
+
+    // Cut the table file and the files of its indexes down to
+    // nothing when the transaction commits, rather than deleting
+    // the records one at a time. The blob file is left as it is,
+    // as after a delete, since its blocks are written unlogged and
+    // recovery could not tell whether it was cut down already.
+    fn execute_truncate(&self, tblname: &str, tx: &Arc<Transaction>) -> DbResult<usize> {
+        self.mdm.get_layout(tblname, tx)?;
+        for (_, mut idx) in self.open_indexes(tblname, tx)? {
+            idx.truncate()?;
+        }
+        let filename = format!("{}.tbl", tblname);
+        if tx.size(&filename)? > 0 {
+            tx.truncate(&filename, 0)?;
+        }
+        Ok(0)
+    }
+
+    fn execute_analyze(&self, tblname: Option<&str>, tx: &Arc<Transaction>) -> DbResult<usize> {
+        let tblnames = match tblname {
+            Some(tblname) => vec![tblname.to_string()],
+            None => self.mdm.table_names(tx)?,
+        };
+        for tblname in &tblnames {
+            let layout = self.mdm.get_layout(tblname, tx)?;
+            self.mdm.analyze(tblname, &layout, tx)?;
+        }
+        Ok(tblnames.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, index::HashIndex};
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_update_statements() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+
+        planner.execute_update("create table t (a int, b varchar(10))", &tx)?;
+        for i in 0..10 {
+            let sql = format!("insert into t (a, b) values ({}, 'rec{}')", i % 3, i);
+            assert_eq!(planner.execute_update(&sql, &tx)?, 1);
+        }
+        assert_eq!(
+            planner.execute_update("update t set b = 'two' where a = 2", &tx)?,
+            3
+        );
+        assert_eq!(planner.execute_update("delete from t where a = 0", &tx)?, 4);
+
+        let layout = mdm.get_layout("t", &tx)?;
+        let mut ts = TableScan::new(Arc::clone(&tx), "t", layout)?;
+        let mut rows = Vec::new();
+        while ts.next()? {
+            rows.push((ts.get_int("a")?, ts.get_string("b")?));
+        }
+        ts.close();
+        rows.sort();
+        assert_eq!(
+            rows,
+            vec![
+                (1, "rec1".to_string()),
+                (1, "rec4".to_string()),
+                (1, "rec7".to_string()),
+                (2, "two".to_string()),
+                (2, "two".to_string()),
+                (2, "two".to_string()),
+            ]
+        );
+
+        assert_eq!(planner.execute_update("delete from t", &tx)?, 6);
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_insert() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let planner = db.planner()?;
+
+        planner.execute_update("create table t (a int)", &tx)?;
+        for sql in [
+            "insert into t (c) values (1)",
+            "insert into t (a) values ('one')",
+            "insert into t (a) values (1, 2)",
+            "insert into nosuchtable (a) values (1)",
+        ] {
+            let result = planner.execute_update(sql, &tx);
+            assert!(matches!(result, Err(DbError::Catalog(_))), "{}", sql);
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_statement_is_undone() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+
+        planner.execute_update("create table t (a int, b varchar(10), c varchar(5))", &tx)?;
+        for (a, b) in [(1, "one"), (2, "two"), (3, "three!!"), (4, "four")] {
+            let sql = format!("insert into t (a, b, c) values ({}, '{}', 'x')", a, b);
+            planner.execute_update(&sql, &tx)?;
+        }
+        // the third record's value is too long for the field,
+        // after the first two have been modified
+        let result = planner.execute_update("update t set c = b", &tx);
+        assert!(matches!(result, Err(DbError::ValueTooLong { .. })));
+        planner.execute_update("update t set a = 0 where a = 4", &tx)?;
+
+        let layout = mdm.get_layout("t", &tx)?;
+        let mut ts = TableScan::new(Arc::clone(&tx), "t", layout)?;
+        let mut rows = Vec::new();
+        while ts.next()? {
+            rows.push((ts.get_int("a")?, ts.get_string("c")?));
+        }
+        ts.close();
+        rows.sort();
+        assert_eq!(
+            rows,
+            [
+                (0, "x".to_string()),
+                (1, "x".to_string()),
+                (2, "x".to_string()),
+                (3, "x".to_string())
+            ]
+        );
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_values() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int, b varchar(10))")?;
+        db.execute_update("insert into t (a, b) values (1, 'one')")?;
+        db.execute_update("insert into t (b) values ('none')")?;
+        db.execute_update("insert into t (a, b) values (null, 'nil')")?;
+        db.execute_update("insert into t (a) values (3)")?;
+
+        let rows = |sql: &str| -> DbResult<Vec<Vec<Constant>>> {
+            let mut rows = db.execute_query(sql)?.rows().to_vec();
+            rows.sort();
+            Ok(rows)
+        };
+        assert_eq!(
+            rows("select b from t where a is null")?,
+            [[Constant::from("nil")], [Constant::from("none")]]
+        );
+        assert_eq!(
+            rows("select a from t where b is null")?,
+            [[Constant::Int(3)]]
+        );
+        assert_eq!(
+            rows("select a from t where a is not null")?,
+            [[Constant::Int(1)], [Constant::Int(3)]]
+        );
+        // a comparison with null is never true
+        assert!(rows("select a from t where a = null")?.is_empty());
+        assert!(rows("select b from t where a = a and a is null")?.is_empty());
+
+        // aggregates ignore nulls
+        assert_eq!(
+            rows("select count(a), sum(a), max(b) from t")?,
+            [[Constant::Int(2), Constant::Int(4), Constant::from("one")]]
+        );
+
+        assert_eq!(db.execute_update("update t set a = null where a = 1")?, 1);
+        assert_eq!(db.execute_update("update t set a = 5 where b = 'nil'")?, 1);
+        assert_eq!(
+            rows("select b, a from t where a is not null")?,
+            [
+                [Constant::Null, Constant::Int(3)],
+                [Constant::from("nil"), Constant::Int(5)]
+            ]
+        );
+        assert_eq!(db.execute_update("delete from t where a is null")?, 2);
+        assert_eq!(rows("select a from t")?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_varchar_length() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int, b varchar(5))")?;
+
+        // the limit counts bytes, not characters
+        for b in ["hello", "hé", "日", "😀"] {
+            let sql = format!("insert into t (a, b) values (1, '{}')", b);
+            assert_eq!(db.execute_update(&sql)?, 1, "{}", b);
+        }
+        for (b, len) in [("hello!", 6), ("héllo", 6), ("日本", 6)] {
+            let sql = format!("insert into t (a, b) values (2, '{}')", b);
+            let result = db.execute_update(&sql);
+            assert!(
+                matches!(result, Err(DbError::ValueTooLong { max: 5, len: l, .. }) if l == len),
+                "{}",
+                b
+            );
+        }
+        let result = db.execute_update("update t set b = 'toolong' where a = 1");
+        assert!(matches!(result, Err(DbError::ValueTooLong { .. })));
+
+        // no partial record was left behind by a rejected insert
+        let rs = db.execute_query("select b from t")?;
+        let mut vals: Vec<String> = rs.rows().iter().map(|row| row[0].to_string()).collect();
+        vals.sort();
+        assert_eq!(vals, ["hello", "hé", "日", "😀"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+
+        planner.execute_update("create table t (a int, b varchar(10), old int)", &tx)?;
+        mdm.create_index("t_a", "t", "a", &tx)?;
+        for i in 0..60 {
+            let sql = format!(
+                "insert into t (a, b, old) values ({}, 'rec{}', {})",
+                i,
+                i,
+                (i < 50) as i32
+            );
+            planner.execute_update(&sql, &tx)?;
+        }
+        planner.execute_update("delete from t where old = 1", &tx)?;
+        let size = tx.size("t.tbl")?;
+        assert!(size > 1);
+        assert_eq!(planner.execute_update("vacuum t", &tx)?, size as usize - 1);
+
+        // the index finds each record at its new place
+        let layout = mdm.get_layout("t", &tx)?;
+        let mut ts = TableScan::new(Arc::clone(&tx), "t", layout)?;
+        let mut idx = mdm.get_index_info("t", &tx)?["a"].open();
+        for i in 40..60 {
+            idx.before_first(&Constant::Int(i))?;
+            let mut found = Vec::new();
+            while idx.next()? {
+                let rid = idx.get_data_rid()?;
+                assert_eq!(rid.block_number(), 0);
+                ts.move_to_rid(rid)?;
+                found.push(ts.get_string("b")?);
+            }
+            let expected = if i < 50 {
+                vec![]
+            } else {
+                vec![format!("rec{}", i)]
+            };
+            assert_eq!(found, expected);
+        }
+        idx.close();
+        ts.close();
+
+        let result = planner.execute_update("vacuum nosuchtable", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+        assert_eq!(db.file_manager().length("t.tbl")?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_and_drop_index() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (a int, b varchar(40))", &tx)?;
+        for i in 0..60 {
+            let sql = format!("insert into t (a, b) values ({}, 'rec{}')", i % 3, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+        planner.execute_update("insert into t (b) values ('null')", &tx)?;
+        let lookup = |tx: &Arc<Transaction>, a: i32| -> DbResult<(String, usize)> {
+            let qry = format!("select b from t where a = {}", a);
+            let plan = planner.create_query_plan(&qry, tx)?;
+            let mut scan = plan.open()?;
+            let mut count = 0;
+            while scan.next()? {
+                count += 1;
+            }
+            scan.close();
+            Ok((planner.explain(&qry, tx)?, count))
+        };
+
+        // the index holds the records already in the table,
+        // and those inserted afterwards
+        assert_eq!(planner.execute_update("create index t_a on t (a)", &tx)?, 0);
+        planner.execute_update("insert into t (a, b) values (1, 'new')", &tx)?;
+        let (plan, count) = lookup(&tx, 1)?;
+        assert!(plan.contains("IndexSelect(t_a on t: a=1)"), "{}", plan);
+        assert_eq!(count, 21);
+        let ii = &mdm.get_index_info("t", &tx)?["a"];
+        let mut idx = ii.open();
+        let mut indexed = 0;
+        for a in 0..3 {
+            idx.before_first(&Constant::Int(a))?;
+            while idx.next()? {
+                indexed += 1;
+            }
+        }
+        idx.close();
+        assert_eq!(indexed, 61);
+
+        for (sql, error) in [
+            ("create index t_a on t (b)", "index t_a already exists"),
+            (
+                "create index t_a2 on t (a)",
+                "field a of table t is already indexed",
+            ),
+            ("create index t_c on t (c)", "field c not found in table t"),
+            ("drop index t_b", "index t_b not found"),
+        ] {
+            let result = planner.execute_update(sql, &tx);
+            assert!(
+                matches!(&result, Err(DbError::Catalog(msg)) if msg == error),
+                "{}: {:?}",
+                sql,
+                result
+            );
+        }
+        let result = planner.execute_update("create index u_a on u (a)", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+
+        // a dropped index is no longer used, and its files are emptied
+        let tx = db.new_tx()?;
+        assert_eq!(planner.execute_update("drop index t_a", &tx)?, 0);
+        let (plan, count) = lookup(&tx, 1)?;
+        assert!(!plan.contains("IndexSelect"), "{}", plan);
+        assert_eq!(count, 21);
+        tx.commit()?;
+        let fm = db.file_manager();
+        assert!(
+            (0..HashIndex::NUM_BUCKETS).all(|b| fm.length(&format!("t_a{}.tbl", b)).unwrap() == 0)
+        );
+        let tx = db.new_tx()?;
+        assert!(mdm.get_index_info("t", &tx)?.is_empty());
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (a int, b varchar(10))", &tx)?;
+        mdm.create_index("t_a", "t", "a", &tx)?;
+        for i in 0..40 {
+            let sql = format!("insert into t (a, b) values ({}, 'rec{}')", i % 4, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+        tx.commit()?;
+        let count = |tx: &Arc<Transaction>| -> DbResult<usize> {
+            let plan = planner.create_query_plan("select b from t where a = 1", tx)?;
+            let mut scan = plan.open()?;
+            let mut count = 0;
+            while scan.next()? {
+                count += 1;
+            }
+            scan.close();
+            Ok(count)
+        };
+
+        // a rollback keeps the records, even those of the blocks
+        // reused by inserting after the truncation
+        let tx = db.new_tx()?;
+        assert_eq!(planner.execute_update("truncate table t", &tx)?, 0);
+        planner.execute_update("insert into t (a, b) values (1, 'new')", &tx)?;
+        assert_eq!(count(&tx)?, 1);
+        tx.rollback()?;
+        let tx = db.new_tx()?;
+        assert_eq!(count(&tx)?, 10);
+        tx.commit()?;
+
+        // the table is locked as a whole until the truncation
+        // commits
+        let tx = db.new_tx()?;
+        assert_eq!(planner.execute_update("truncate table t", &tx)?, 0);
+        assert_eq!(tx.size("t.tbl")?, 0);
+        let other = db.new_tx()?;
+        other.set_lock_timeout(Duration::from_millis(10));
+        let result = planner.execute_update("insert into t (a, b) values (1, 'other')", &other);
+        assert!(matches!(result, Err(DbError::LockTimeout(_))));
+        other.rollback()?;
+        let result = planner.execute_update("truncate table nosuchtable", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+
+        // the transaction can insert records after the truncation,
+        // into the blocks that are no longer cut off
+        planner.execute_update("insert into t (a, b) values (1, 'new')", &tx)?;
+        assert_eq!(count(&tx)?, 1);
+        assert_eq!(tx.size("t.tbl")?, 1);
+        tx.commit()?;
+        let fm = db.file_manager();
+        assert_eq!(fm.length("t.tbl")?, 1);
+        assert_eq!(
+            (0..HashIndex::NUM_BUCKETS)
+                .map(|b| fm.length(&format!("t_a{}.tbl", b)).unwrap())
+                .sum::<u64>(),
+            1
+        );
+
+        // and the table and its index are used from then on
+        let tx = db.new_tx()?;
+        assert_eq!(count(&tx)?, 1);
+        planner.execute_update("insert into t (a, b) values (1, 'newer')", &tx)?;
+        assert_eq!(count(&tx)?, 2);
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (a int, b varchar(10))", &tx)?;
+        planner.execute_update("create table u (c int)", &tx)?;
+        for i in 0..60 {
+            let sql = format!("insert into t (a, b) values ({}, 'rec{}')", i % 4, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+        let records = |tx: &Arc<Transaction>| -> DbResult<u64> {
+            let plan = planner.create_query_plan("select b from t where a = 1", tx)?;
+            Ok(plan.records_output())
+        };
+
+        // without counts, a is guessed to have 1 + 60/3 values
+        assert_eq!(records(&tx)?, 60 / 21);
+        assert_eq!(planner.execute_update("analyze t", &tx)?, 1);
+        assert_eq!(records(&tx)?, 15);
+        let layout = mdm.get_layout("t", &tx)?;
+        let si = mdm.get_stat_info("t", &layout, &tx)?;
+        assert_eq!(si.records_output(), 60);
+        assert_eq!(si.distinct_values("b"), 60);
+
+        assert_eq!(planner.execute_update("analyze", &tx)?, 2);
+        let result = planner.execute_update("analyze nosuchtable", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_increment() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (id int auto_increment, b varchar(10))", &tx)?;
+        mdm.create_index("t_id", "t", "id", &tx)?;
+        let result = planner.execute_update("create table t (b varchar(10))", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(msg)) if msg == "table t already exists"));
+        for sql in [
+            "insert into t (b) values ('one')",
+            "insert into t (id, b) values (null, 'two')",
+            "insert into t (id, b) values (10, 'ten')",
+            "insert into t (b) values ('eleven')",
+            "insert into t (id, b) values (5, 'five')",
+            "insert into t (b) values ('twelve')",
+        ] {
+            assert_eq!(planner.execute_update(sql, &tx)?, 1);
+        }
+        tx.commit()?;
+
+        let tx = db.new_tx()?;
+        let plan = planner.create_query_plan("select id, b from t", &tx)?;
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            rows.push((s.get_int("id")?, s.get_string("b")?));
+        }
+        s.close();
+        rows.sort();
+        let expected = [
+            (1, "one"),
+            (2, "two"),
+            (5, "five"),
+            (10, "ten"),
+            (11, "eleven"),
+            (12, "twelve"),
+        ];
+        assert_eq!(rows, expected.map(|(id, b)| (id, b.to_string())));
+
+        // the generated values are indexed too
+        let ii = &mdm.get_index_info("t", &tx)?["id"];
+        let mut s = ii.open();
+        s.before_first(&Constant::Int(11))?;
+        assert!(s.next()?);
+        let rid = s.get_data_rid()?;
+        s.close();
+        let mut s = TableScan::new(Arc::clone(&tx), "t", mdm.get_layout("t", &tx)?)?;
+        s.move_to_rid(rid)?;
+        assert_eq!(s.get_string("b")?, "eleven");
+        s.close();
+
+        // a rejected insert allocates no value
+        let result = planner.execute_update("insert into t (b) values ('far too long')", &tx);
+        assert!(matches!(result, Err(DbError::ValueTooLong { .. })));
+        planner.execute_update("insert into t (b) values ('thirteen')", &tx)?;
+        let plan = planner.create_query_plan("select id from t where b = 'thirteen'", &tx)?;
+        let mut s = plan.open()?;
+        assert!(s.next()?);
+        assert_eq!(s.get_int("id")?, 13);
+        s.close();
+        tx.commit()?;
+        Ok(())
+    }
+}
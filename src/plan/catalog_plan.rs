@@ -0,0 +1,256 @@
+use std::sync::Arc;
+
+use super::Plan;
+use crate::{
+    error::{DbError, DbResult},
+    metadata::MetadataManager,
+    query::{Constant, Scan},
+    record::{FieldType, Schema},
+    tx::Transaction,
+};
+
+// The plan for a SHOW TABLES or DESCRIBE statement,
+// or for a virtual table of the information schema.
+// Its records are read from the catalog when the plan
+// is created, and held in memory.
+pub struct CatalogPlan {
+    description: String,
+    schema: Schema,
+    rows: Vec<Vec<Constant>>,
+}
+
+impl CatalogPlan {
+    // Create a plan over the specified records, having
+    // a value for each of the fields, in order.
+    // Each varchar field is as long as its longest value,
+    // so that the records fit in a temporary table.
+    pub fn new(description: &str, fields: &[(&str, FieldType)], rows: Vec<Vec<Constant>>) -> Self {
+        let mut schema = Schema::new();
+        for (i, &(fldname, fldtype)) in fields.iter().enumerate() {
+            let length = rows
+                .iter()
+                .map(|row| match &row[i] {
+                    Constant::Str(val) => val.len(),
+                    _ => 0,
+                })
+                .fold(1, usize::max);
+            schema.add_field(fldname, fldtype, length);
+        }
+        CatalogPlan {
+            description: description.to_string(),
+            schema,
+            rows,
+        }
+    }
+
+    // Create the plan for SHOW TABLES, having a record
+    // for each table and view, sorted by name.
+    // The field "kind" is either "table" or "view".
+    pub fn show_tables(mdm: &MetadataManager, tx: &Arc<Transaction>) -> DbResult<Self> {
+        let fields = [
+            ("tblname", FieldType::Varchar),
+            ("kind", FieldType::Varchar),
+        ];
+        Ok(Self::new("ShowTables", &fields, Self::table_rows(mdm, tx)?))
+    }
+
+    // Return the records listed by SHOW TABLES.
+    pub(super) fn table_rows(
+        mdm: &MetadataManager,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<Vec<Vec<Constant>>> {
+        let tables = mdm.table_names(tx)?.into_iter().map(|t| (t, "table"));
+        let views = mdm.view_names(tx)?.into_iter().map(|v| (v, "view"));
+        let mut rows: Vec<Vec<Constant>> = tables
+            .chain(views)
+            .map(|(name, kind)| vec![Constant::Str(name), Constant::Str(kind.to_string())])
+            .collect();
+        rows.sort();
+        Ok(rows)
+    }
+
+    // Create the plan for DESCRIBE, having a record for
+    // each field of the schema, in order.
+    pub fn describe(tblname: &str, sch: &Schema) -> Self {
+        let rows = sch
+            .fields()
+            .iter()
+            .map(|fldname| {
+                let (fldtype, length) = Self::type_of(sch, fldname);
+                vec![
+                    Constant::Str(fldname.clone()),
+                    Constant::Str(fldtype.to_string()),
+                    length,
+                ]
+            })
+            .collect();
+        let fields = [
+            ("fldname", FieldType::Varchar),
+            ("type", FieldType::Varchar),
+            ("length", FieldType::Integer),
+        ];
+        Self::new(&format!("Describe({})", tblname), &fields, rows)
+    }
+
+    // Return the name of the type of the field, and its length,
+    // which is that of a varchar field, and null otherwise.
+    pub fn type_of(sch: &Schema, fldname: &str) -> (&'static str, Constant) {
+        match sch.field_type(fldname) {
+            FieldType::Integer => ("int", Constant::Null),
+            FieldType::Varchar => ("varchar", Constant::Int(sch.length(fldname) as i32)),
+            FieldType::Blob => ("blob", Constant::Null),
+        }
+    }
+}
+
+impl Plan for CatalogPlan {
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        Ok(Box::new(CatalogScan {
+            fields: self.schema.fields().to_vec(),
+            rows: self.rows.clone(),
+            pos: None,
+        }))
+    }
+
+    // The records are held in memory, so no blocks are accessed.
+    fn blocks_accessed(&self) -> u64 {
+        0
+    }
+
+    fn records_output(&self) -> u64 {
+        self.rows.len() as u64
+    }
+
+    fn distinct_values(&self, _fldname: &str) -> u64 {
+        self.rows.len() as u64
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+}
+
+// A scan over the records of a catalog plan.
+struct CatalogScan {
+    fields: Vec<String>,
+    rows: Vec<Vec<Constant>>,
+    pos: Option<usize>,
+}
+
+impl Scan for CatalogScan {
+    fn before_first(&mut self) -> DbResult<()> {
+        self.pos = None;
+        Ok(())
+    }
+
+    fn next(&mut self) -> DbResult<bool> {
+        let next = self.pos.map_or(0, |pos| pos + 1);
+        self.pos = Some(next.min(self.rows.len()));
+        Ok(next < self.rows.len())
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        match self.get_val(fldname)? {
+            Constant::Int(val) => Ok(val),
+            Constant::Null => Ok(0),
+            _ => Err(DbError::Catalog(format!("field {} is not an int", fldname))),
+        }
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        match self.get_val(fldname)? {
+            Constant::Str(val) => Ok(val),
+            _ => Err(DbError::Catalog(format!(
+                "field {} is not a string",
+                fldname
+            ))),
+        }
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        let i = self
+            .fields
+            .iter()
+            .position(|f| f == fldname)
+            .ok_or_else(|| DbError::Catalog(format!("field {} not found", fldname)))?;
+        let row = self
+            .pos
+            .and_then(|pos| self.rows.get(pos))
+            .expect("scan is not positioned");
+        Ok(row[i].clone())
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        self.fields.iter().any(|f| f == fldname)
+    }
+
+    fn close(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, plan::Planner};
+    use tempfile::TempDir;
+
+    fn rows(planner: &Planner, qry: &str, tx: &Arc<Transaction>) -> DbResult<Vec<String>> {
+        let plan = planner.create_query_plan(qry, tx)?;
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            let vals = plan
+                .schema()
+                .fields()
+                .iter()
+                .map(|fldname| s.get_val(fldname).map(|val| val.to_string()))
+                .collect::<DbResult<Vec<_>>>()?;
+            rows.push(vals.join(" "));
+        }
+        s.close();
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_show_tables_and_describe() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let planner = db.planner()?;
+        assert!(rows(&planner, "show tables", &tx)?.is_empty());
+
+        planner.execute_update("create table student (sid int, sname varchar(10))", &tx)?;
+        planner.execute_update("create table photo (sid int, img blob)", &tx)?;
+        planner.execute_update("create view names as select sname from student", &tx)?;
+        let plan = planner.create_query_plan("show tables", &tx)?;
+        assert_eq!(plan.schema().fields(), ["tblname", "kind"]);
+        assert_eq!(
+            rows(&planner, "SHOW TABLES", &tx)?,
+            ["names view", "photo table", "student table"]
+        );
+
+        let plan = planner.create_query_plan("describe student", &tx)?;
+        assert_eq!(plan.schema().fields(), ["fldname", "type", "length"]);
+        assert_eq!(
+            rows(&planner, "describe student", &tx)?,
+            ["sid int null", "sname varchar 10"]
+        );
+        assert_eq!(
+            rows(&planner, "describe photo", &tx)?,
+            ["sid int null", "img blob null"]
+        );
+        assert_eq!(rows(&planner, "describe names", &tx)?, ["sname varchar 10"]);
+        assert_eq!(
+            planner.explain("describe names", &tx)?,
+            "Describe(names)  (blocks=0, records=1)"
+        );
+
+        let result = planner.create_query_plan("describe nosuchtable", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+        Ok(())
+    }
+}
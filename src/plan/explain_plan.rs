@@ -0,0 +1,181 @@
+use super::Plan;
+use crate::{
+    error::{DbError, DbResult},
+    query::{Constant, Scan},
+    record::Schema,
+};
+
+// Render the plan tree rooted at p, one node per line.
+// Each node is indented below its parent and shows
+// the planner's cost estimates for it.
+pub fn explain(p: &dyn Plan) -> String {
+    let mut lines = Vec::new();
+    explain_node(p, 0, &mut lines);
+    lines.join("\n")
+}
+
+fn explain_node(p: &dyn Plan, depth: usize, lines: &mut Vec<String>) {
+    lines.push(format!(
+        "{}{}  (blocks={}, records={})",
+        "  ".repeat(depth),
+        p.description(),
+        p.blocks_accessed(),
+        p.records_output()
+    ));
+    for child in p.children() {
+        explain_node(child, depth + 1, lines);
+    }
+}
+
+// The plan for an EXPLAIN statement.
+// Its output table has a single field "plan",
+// having one record for each line of the rendered
+// plan tree of the explained query.
+pub struct ExplainPlan {
+    p: Box<dyn Plan>,
+    lines: Vec<String>,
+    schema: Schema,
+}
+
+impl ExplainPlan {
+    pub const FIELD_NAME: &'static str = "plan";
+
+    // Create an explain node for the specified query.
+    pub fn new(p: Box<dyn Plan>) -> Self {
+        let lines: Vec<String> = explain(p.as_ref()).lines().map(String::from).collect();
+        let width = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+        let mut schema = Schema::new();
+        schema.add_string_field(Self::FIELD_NAME, width);
+        ExplainPlan { p, lines, schema }
+    }
+}
+
+impl Plan for ExplainPlan {
+    // Open a scan over the rendered lines.
+    // The explained query itself is not executed.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        Ok(Box::new(ExplainScan {
+            lines: self.lines.clone(),
+            pos: None,
+        }))
+    }
+
+    // The lines are held in memory, so no blocks are accessed.
+    fn blocks_accessed(&self) -> u64 {
+        0
+    }
+
+    fn records_output(&self) -> u64 {
+        self.lines.len() as u64
+    }
+
+    fn distinct_values(&self, _fldname: &str) -> u64 {
+        self.lines.len() as u64
+    }
+
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn description(&self) -> String {
+        "Explain".to_string()
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![self.p.as_ref()]
+    }
+}
+
+// A scan over the lines of an explained plan.
+struct ExplainScan {
+    lines: Vec<String>,
+    pos: Option<usize>,
+}
+
+impl ExplainScan {
+    fn current(&self, fldname: &str) -> DbResult<&str> {
+        if fldname != ExplainPlan::FIELD_NAME {
+            return Err(DbError::Catalog(format!("field {} not found", fldname)));
+        }
+        let line = self
+            .pos
+            .and_then(|pos| self.lines.get(pos))
+            .expect("scan is not positioned");
+        Ok(line)
+    }
+}
+
+impl Scan for ExplainScan {
+    fn before_first(&mut self) -> DbResult<()> {
+        self.pos = None;
+        Ok(())
+    }
+
+    fn next(&mut self) -> DbResult<bool> {
+        let next = self.pos.map_or(0, |pos| pos + 1);
+        self.pos = Some(next.min(self.lines.len()));
+        Ok(next < self.lines.len())
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.current(fldname)?;
+        Err(DbError::Catalog(format!("field {} is not an int", fldname)))
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        Ok(self.current(fldname)?.to_string())
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        Ok(Constant::Str(self.get_string(fldname)?))
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        fldname == ExplainPlan::FIELD_NAME
+    }
+
+    fn close(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SimpleDB;
+
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_explain() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (a int, b varchar(10))", &tx)?;
+        for i in 0..20 {
+            let sql = format!("insert into t (a, b) values ({}, 'b{}')", i % 5, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+
+        let text = planner.explain("select b from t where a = 3", &tx)?;
+        assert_eq!(
+            text,
+            "Project(b)  (blocks=2, records=2)\n\
+             \x20 Select(a=3)  (blocks=2, records=2)\n\
+             \x20   Table(t)  (blocks=2, records=20)"
+        );
+
+        // EXPLAIN SELECT returns the same lines as records
+        let plan = planner.create_query_plan("explain select b from t where a = 3", &tx)?;
+        assert_eq!(plan.schema().fields(), [ExplainPlan::FIELD_NAME]);
+        let mut s = plan.open()?;
+        let mut lines = Vec::new();
+        while s.next()? {
+            lines.push(s.get_string(ExplainPlan::FIELD_NAME)?);
+        }
+        assert!(!s.next()?);
+        s.close();
+        assert_eq!(lines.join("\n"), text);
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,522 @@
+use std::sync::Arc;
+
+use super::{virtual_table, CatalogPlan, Plan, ProjectPlan, QueryPlanner, TablePlanner};
+use crate::{
+    error::{DbError, DbResult},
+    materialize::{DistinctPlan, GroupByPlan, SortPlan},
+    metadata::MetadataManager,
+    parse::{Parser, QueryData},
+    query::Predicate,
+    tx::Transaction,
+};
+
+// A query planner that optimizes using a heuristic-based algorithm.
+// Each table is planned by a TablePlanner, which uses an index
+// when that is cheaper than a table scan, or than a hash join
+// when the table is joined.
+// A view in the from clause is replaced by the plan of its
+// definition, which then takes part in the join order like a table,
+// and so is a virtual table of the information schema.
+// The join order is chosen greedily, starting with the table
+// having the smallest output and repeatedly adding the join
+// (or, failing that, the product) with the smallest output.
+pub struct HeuristicQueryPlanner {
+    mdm: Arc<MetadataManager>,
+}
+
+impl HeuristicQueryPlanner {
+    pub fn new(mdm: Arc<MetadataManager>) -> Self {
+        HeuristicQueryPlanner { mdm }
+    }
+
+    // Create a table planner for the table or view of the
+    // specified name. The definition of a view is planned
+    // in turn; a view can only mention tables and views
+    // that existed before it, so this terminates.
+    fn table_planner(
+        &self,
+        tblname: &str,
+        pred: &Predicate,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<TablePlanner> {
+        if let Some(plan) = virtual_table(tblname, &self.mdm, tx)? {
+            return Ok(TablePlanner::for_view(Box::new(plan), pred.clone(), tx));
+        }
+        match self.mdm.get_view_def(tblname, tx)? {
+            Some(viewdef) => {
+                let viewdata = Parser::new(&viewdef)?.query()?;
+                let viewplan = self.create_plan(&viewdata, tx)?;
+                Ok(TablePlanner::for_view(viewplan, pred.clone(), tx))
+            }
+            None => TablePlanner::new(tblname, pred.clone(), tx, &self.mdm),
+        }
+    }
+
+    // Remove the table planner whose plan, as built by the
+    // specified function, has the fewest output records,
+    // and return that plan.
+    fn lowest_plan<F>(tableplanners: &mut Vec<TablePlanner>, make_plan: F) -> Option<Arc<dyn Plan>>
+    where
+        F: Fn(&TablePlanner) -> Option<Box<dyn Plan>>,
+    {
+        let mut best: Option<(usize, Box<dyn Plan>)> = None;
+        for (i, tp) in tableplanners.iter().enumerate() {
+            if let Some(plan) = make_plan(tp) {
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, bestplan)| plan.records_output() < bestplan.records_output())
+                {
+                    best = Some((i, plan));
+                }
+            }
+        }
+        let (i, plan) = best?;
+        tableplanners.remove(i);
+        Some(Arc::from(plan))
+    }
+
+    fn check_field(p: &dyn Plan, fldname: &str) -> DbResult<()> {
+        if p.schema().has_field(fldname) {
+            Ok(())
+        } else {
+            Err(DbError::Catalog(format!("field {} not found", fldname)))
+        }
+    }
+}
+
+impl QueryPlanner for HeuristicQueryPlanner {
+    // Create an optimized left-deep query plan using the following
+    // heuristics.
+    // H1. Choose the smallest table (considering selection predicates)
+    // to be first in the join order.
+    // H2. Add the table to the join order which
+    // results in the smallest output.
+    fn create_plan(&self, data: &QueryData, tx: &Arc<Transaction>) -> DbResult<Box<dyn Plan>> {
+        // Step 1: Create a TablePlanner object for each mentioned table or view
+        let mut tableplanners = data
+            .tables()
+            .iter()
+            .map(|tblname| self.table_planner(tblname, data.pred(), tx))
+            .collect::<DbResult<Vec<_>>>()?;
+
+        // Step 2: Choose the lowest-size plan to begin the join order
+        let mut currentplan =
+            Self::lowest_plan(&mut tableplanners, |tp| Some(tp.make_select_plan()))
+                .expect("query has at least one table");
+
+        // Step 3: Repeatedly add a plan to the join order
+        while !tableplanners.is_empty() {
+            currentplan =
+                match Self::lowest_plan(&mut tableplanners, |tp| tp.make_join_plan(&currentplan)) {
+                    Some(p) => p,
+                    None => Self::lowest_plan(&mut tableplanners, |tp| {
+                        Some(tp.make_product_plan(&currentplan))
+                    })
+                    .expect("a table remains to be planned"),
+                };
+        }
+        let mut currentplan: Box<dyn Plan> = Box::new(currentplan);
+
+        // Step 4: Group the records and compute the aggregates, if requested
+        if !data.group_fields().is_empty() || !data.aggregates().is_empty() {
+            let aggfns = data
+                .aggregates()
+                .iter()
+                .map(|(func, fldname)| func.create(fldname))
+                .collect();
+            currentplan = Box::new(GroupByPlan::new(
+                Arc::clone(tx),
+                currentplan,
+                data.group_fields().to_vec(),
+                aggfns,
+            )?);
+        }
+
+        // Step 5: Sort the records, if requested.
+        // The sort precedes the projection, so that records
+        // can be ordered by fields that are not output.
+        // A distinct query is sorted by its duplicate elimination instead.
+        if !data.order_keys().is_empty() && !data.is_distinct() {
+            for (fldname, _) in data.order_keys() {
+                Self::check_field(currentplan.as_ref(), fldname)?;
+            }
+            currentplan = Box::new(SortPlan::with_orders(
+                Arc::clone(tx),
+                currentplan,
+                data.order_keys().to_vec(),
+            ));
+        }
+
+        // Step 6: Project on the field names
+        for fldname in data.fields() {
+            Self::check_field(currentplan.as_ref(), fldname)?;
+        }
+        let projectplan = Box::new(ProjectPlan::new(currentplan, data.fields()));
+
+        // Step 7: Remove duplicate records, if requested.
+        // The records can then only be ordered by output fields.
+        if !data.is_distinct() {
+            return Ok(projectplan);
+        }
+        for (fldname, _) in data.order_keys() {
+            Self::check_field(projectplan.as_ref(), fldname)?;
+        }
+        Ok(Box::new(DistinctPlan::new(
+            Arc::clone(tx),
+            projectplan,
+            data.order_keys().to_vec(),
+        )))
+    }
+
+    fn create_show_tables_plan(&self, tx: &Arc<Transaction>) -> DbResult<Box<dyn Plan>> {
+        Ok(Box::new(CatalogPlan::show_tables(&self.mdm, tx)?))
+    }
+
+    // The fields of a view are those of the
+    // plan of its definition.
+    fn create_describe_plan(
+        &self,
+        tblname: &str,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<Box<dyn Plan>> {
+        if let Some(plan) = virtual_table(tblname, &self.mdm, tx)? {
+            return Ok(Box::new(CatalogPlan::describe(tblname, plan.schema())));
+        }
+        let schema = match self.mdm.get_view_def(tblname, tx)? {
+            Some(viewdef) => {
+                let viewdata = Parser::new(&viewdef)?.query()?;
+                self.create_plan(&viewdata, tx)?.schema().clone()
+            }
+            None => self.mdm.get_layout(tblname, tx)?.schema().clone(),
+        };
+        Ok(Box::new(CatalogPlan::describe(tblname, &schema)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::SimpleDB,
+        plan::Planner,
+        query::{Scan, UpdateScan},
+        record::TableScan,
+    };
+    use tempfile::TempDir;
+
+    // Run the query, in the order of its output values.
+    fn run_query(planner: &Planner, qry: &str, tx: &Arc<Transaction>) -> DbResult<Vec<String>> {
+        let mut rows = crate::plan::run_query(planner, qry, tx)?;
+        rows.sort();
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_index_select_and_join() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+
+        planner.execute_update("create table dept (did int, dname varchar(10))", &tx)?;
+        planner.execute_update(
+            "create table emp (eid int, ename varchar(10), edept int)",
+            &tx,
+        )?;
+        mdm.create_index("edeptidx", "emp", "edept", &tx)?;
+        for d in 0..3 {
+            let sql = format!("insert into dept (did, dname) values ({}, 'd{}')", d, d);
+            planner.execute_update(&sql, &tx)?;
+        }
+        for e in 0..12 {
+            let sql = format!(
+                "insert into emp (eid, ename, edept) values ({}, 'e{}', {})",
+                e,
+                e,
+                e % 3
+            );
+            planner.execute_update(&sql, &tx)?;
+        }
+        planner.execute_update("update emp set edept = 2 where eid = 0", &tx)?;
+        planner.execute_update("delete from emp where eid = 3", &tx)?;
+
+        // a record inserted behind the index's back is invisible to
+        // index-based plans, which shows which plans use the index
+        let layout = mdm.get_layout("emp", &tx)?;
+        let mut ts = TableScan::new(Arc::clone(&tx), "emp", layout)?;
+        ts.insert()?;
+        ts.set_int("eid", 99)?;
+        ts.set_string("ename", "hidden")?;
+        ts.set_int("edept", 0)?;
+        ts.close();
+
+        assert_eq!(
+            run_query(&planner, "select ename from emp where edept = 0", &tx)?,
+            ["e6", "e9"]
+        );
+        assert_eq!(
+            run_query(&planner, "select ename from emp where eid = 99", &tx)?,
+            ["hidden"]
+        );
+        assert_eq!(
+            run_query(
+                &planner,
+                "select dname, ename from dept, emp where did = edept and did = 0",
+                &tx
+            )?,
+            ["d0 e6", "d0 e9"]
+        );
+        assert_eq!(
+            run_query(
+                &planner,
+                "select ename from emp, dept where edept = did and dname = 'd2'",
+                &tx
+            )?,
+            ["e0", "e11", "e2", "e5", "e8"]
+        );
+        assert_eq!(
+            run_query(&planner, "select did from dept, emp", &tx)?.len(),
+            36
+        );
+
+        let result = planner.create_query_plan("select nosuchfield from dept", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_plans_by_cost() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+
+        planner.execute_update("create table dept (did int, dname varchar(10))", &tx)?;
+        planner.execute_update(
+            "create table emp (eid int, ename varchar(10), edept int)",
+            &tx,
+        )?;
+        mdm.create_index("edeptidx", "emp", "edept", &tx)?;
+        for d in 0..30 {
+            let sql = format!("insert into dept (did, dname) values ({}, 'd{}')", d, d);
+            planner.execute_update(&sql, &tx)?;
+        }
+        for e in 0..150 {
+            let sql = format!(
+                "insert into emp (eid, ename, edept) values ({}, 'e{}', {})",
+                e,
+                e,
+                e % 30
+            );
+            planner.execute_update(&sql, &tx)?;
+        }
+        planner.execute_update("update emp set edept = 2 where eid = 0", &tx)?;
+        planner.execute_update("delete from emp where eid = 30", &tx)?;
+
+        // a record inserted behind the index's back is invisible to
+        // index-based plans, which shows which plans use the index
+        let layout = mdm.get_layout("emp", &tx)?;
+        let mut ts = TableScan::new(Arc::clone(&tx), "emp", layout)?;
+        ts.insert()?;
+        ts.set_int("eid", 999)?;
+        ts.set_string("ename", "hidden")?;
+        ts.set_int("edept", 0)?;
+        ts.close();
+
+        // the index is cheaper than scanning the whole table
+        assert_eq!(
+            run_query(&planner, "select ename from emp where edept = 0", &tx)?,
+            ["e120", "e60", "e90"]
+        );
+        let plan = planner.create_query_plan("select ename from emp where edept = 0", &tx)?;
+        let scan = planner.create_query_plan("select ename from emp", &tx)?;
+        assert!(plan.blocks_accessed() < scan.blocks_accessed());
+        assert!(plan.records_output() < scan.records_output());
+
+        // there is no index on eid, so the table is scanned
+        assert_eq!(
+            run_query(&planner, "select ename from emp where eid = 999", &tx)?,
+            ["hidden"]
+        );
+
+        // the selective dept table comes first, and emp is joined through its index
+        assert_eq!(
+            run_query(
+                &planner,
+                "select dname, ename from emp, dept where did = edept and did = 0",
+                &tx
+            )?,
+            ["d0 e120", "d0 e60", "d0 e90"]
+        );
+        assert_eq!(
+            run_query(
+                &planner,
+                "select ename from emp, dept where edept = did and dname = 'd2'",
+                &tx
+            )?,
+            ["e0", "e122", "e2", "e32", "e62", "e92"]
+        );
+        let qry = "select ename from emp, dept where did = edept and did = 0";
+        assert!(planner.explain(qry, &tx)?.contains("IndexJoin(edeptidx"));
+
+        // joining every department probes the index too often,
+        // so emp is hash joined instead, hidden record and all
+        let qry = "select ename from emp, dept where did = edept";
+        let explain = planner.explain(qry, &tx)?;
+        assert!(explain.contains("HashJoin("));
+        assert!(!explain.contains("IndexJoin("));
+        let rows = run_query(&planner, qry, &tx)?;
+        assert_eq!(rows.len(), 150);
+        assert!(rows.iter().any(|r| r == "hidden"));
+        assert_eq!(
+            run_query(&planner, "select did from dept, emp", &tx)?.len(),
+            30 * 150
+        );
+
+        let result = planner.create_query_plan("select nosuchfield from dept", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (a int, b varchar(5), c int)", &tx)?;
+        for i in 0..12 {
+            let sql = format!(
+                "insert into t (a, b, c) values ({}, 'b{}', {})",
+                i % 3,
+                i % 4,
+                i
+            );
+            planner.execute_update(&sql, &tx)?;
+        }
+
+        // run the query, keeping the output order
+        let ordered = |qry: &str| -> DbResult<Vec<i32>> {
+            let plan = planner.create_query_plan(qry, &tx)?;
+            let mut s = plan.open()?;
+            let mut vals = Vec::new();
+            while s.next()? {
+                vals.push(s.get_int("c")?);
+            }
+            s.close();
+            Ok(vals)
+        };
+
+        assert_eq!(
+            ordered("select c from t where a = 1 order by c desc")?,
+            [10, 7, 4, 1]
+        );
+        // sort on a field that is not output, then on c within each group
+        assert_eq!(
+            ordered("select c from t order by b desc, c asc")?,
+            [3, 7, 11, 2, 6, 10, 1, 5, 9, 0, 4, 8]
+        );
+
+        let result = planner.create_query_plan("select c from t order by d", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_views() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mdm = db.metadata_manager()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table dept (did int, dname varchar(10))", &tx)?;
+        planner.execute_update(
+            "create table emp (eid int, ename varchar(10), edept int)",
+            &tx,
+        )?;
+        for d in 0..3 {
+            let sql = format!("insert into dept (did, dname) values ({}, 'd{}')", d, d);
+            planner.execute_update(&sql, &tx)?;
+        }
+        for e in 0..9 {
+            let sql = format!(
+                "insert into emp (eid, ename, edept) values ({}, 'e{}', {})",
+                e,
+                e,
+                e % 3
+            );
+            planner.execute_update(&sql, &tx)?;
+        }
+
+        planner.execute_update(
+            "create view staff as select ename, dname from emp, dept where edept = did",
+            &tx,
+        )?;
+        planner.execute_update(
+            "create view headcount as select dname, count(ename) from staff group by dname",
+            &tx,
+        )?;
+        assert_eq!(
+            mdm.get_view_def("staff", &tx)?.as_deref(),
+            Some("select ename, dname from emp, dept where edept=did")
+        );
+
+        // a view is queried like a table, and only has the fields it selects
+        assert_eq!(
+            run_query(&planner, "select ename from staff where dname = 'd1'", &tx)?,
+            ["e1", "e4", "e7"]
+        );
+        let result = planner.create_query_plan("select eid from staff", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+
+        // views can be joined with tables, and defined on other views
+        assert_eq!(
+            run_query(
+                &planner,
+                "select ename, did from staff, dept where dname = 'd2' and did = 2",
+                &tx
+            )?,
+            ["e2 2", "e5 2", "e8 2"]
+        );
+        assert_eq!(
+            run_query(&planner, "select dname, countofename from headcount", &tx)?,
+            ["d0 3", "d1 3", "d2 3"]
+        );
+
+        // the view reflects later changes to its tables
+        planner.execute_update(
+            "insert into emp (eid, ename, edept) values (9, 'e9', 1)",
+            &tx,
+        )?;
+        assert_eq!(
+            run_query(&planner, "select ename from staff where dname = 'd1'", &tx)?,
+            ["e1", "e4", "e7", "e9"]
+        );
+
+        for sql in [
+            "create view staff as select did from dept",
+            "create view dept as select eid from emp",
+            "create table staff (a int)",
+            "create view v as select a from nosuchtable",
+            "create view v as select nosuchfield from emp",
+            "create view v as select v from v",
+            "insert into staff (ename, dname) values ('e10', 'd0')",
+        ] {
+            let result = planner.execute_update(sql, &tx);
+            assert!(
+                matches!(result, Err(DbError::Catalog(_))),
+                "{}: {:?}",
+                sql,
+                result
+            );
+        }
+        assert_eq!(mdm.get_view_def("v", &tx)?, None);
+        tx.commit()?;
+        Ok(())
+    }
+}
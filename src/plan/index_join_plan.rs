@@ -0,0 +1,96 @@
+use super::{Plan, TablePlan};
+use crate::{
+    error::DbResult, index::IndexJoinScan, metadata::IndexInfo, query::Scan, record::Schema,
+};
+
+// The Plan class corresponding to the indexjoin
+// relational algebra operator.
+pub struct IndexJoinPlan {
+    p1: Box<dyn Plan>,
+    p2: TablePlan,
+    ii: IndexInfo,
+    joinfield: String,
+    schema: Schema,
+}
+
+impl IndexJoinPlan {
+    // Implement the join operator,
+    // using the specified LHS and RHS plans.
+    // The RHS plan must be a table, since the index
+    // records refer to its records by rid.
+    pub fn new(p1: Box<dyn Plan>, p2: TablePlan, ii: IndexInfo, joinfield: &str) -> Self {
+        let mut schema = Schema::new();
+        schema.add_all(p1.schema());
+        schema.add_all(p2.schema());
+        IndexJoinPlan {
+            p1,
+            p2,
+            ii,
+            joinfield: joinfield.to_string(),
+            schema,
+        }
+    }
+}
+
+impl Plan for IndexJoinPlan {
+    // Open an indexjoin scan for this query.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let s = self.p1.open()?;
+        let ts = self.p2.open_table_scan()?;
+        let idx = self.ii.open();
+        Ok(Box::new(IndexJoinScan::new(s, idx, &self.joinfield, ts)?))
+    }
+
+    // Estimate the number of block accesses to compute the join.
+    // The formula is:
+    // B(indexjoin(p1,p2,idx)) = B(p1) + R(p1)*B(idx)
+    //       + R(p1)*min(R(idx), B(p2))
+    // since the records matching a key are in at most
+    // every block of the table.
+    fn blocks_accessed(&self) -> u64 {
+        let matches = self.ii.records_output().min(self.p2.blocks_accessed());
+        self.p1.blocks_accessed().saturating_add(
+            self.p1
+                .records_output()
+                .saturating_mul(self.ii.blocks_accessed().saturating_add(matches)),
+        )
+    }
+
+    // Estimate the number of output records in the join.
+    // The formula is:
+    // R(indexjoin(p1,p2,idx)) = R(p1)*R(idx)
+    fn records_output(&self) -> u64 {
+        self.p1
+            .records_output()
+            .saturating_mul(self.ii.records_output())
+    }
+
+    // Estimate the number of distinct values for the
+    // specified field.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        if self.p1.schema().has_field(fldname) {
+            self.p1.distinct_values(fldname)
+        } else {
+            self.p2.distinct_values(fldname)
+        }
+    }
+
+    // Return the schema of the index join.
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn description(&self) -> String {
+        format!(
+            "IndexJoin({} on {}: {}={})",
+            self.ii.index_name(),
+            self.p2.table_name(),
+            self.ii.field_name(),
+            self.joinfield
+        )
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![self.p1.as_ref()]
+    }
+}
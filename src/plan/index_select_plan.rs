@@ -0,0 +1,70 @@
+use super::{Plan, TablePlan};
+use crate::{
+    error::DbResult,
+    index::IndexSelectScan,
+    metadata::IndexInfo,
+    query::{Constant, Scan},
+    record::Schema,
+};
+
+// The Plan class corresponding to the indexselect
+// relational algebra operator.
+pub struct IndexSelectPlan {
+    p: TablePlan,
+    ii: IndexInfo,
+    val: Constant,
+}
+
+impl IndexSelectPlan {
+    // Create a new indexselect node in the query tree
+    // for the specified index and selection constant.
+    pub fn new(p: TablePlan, ii: IndexInfo, val: Constant) -> Self {
+        IndexSelectPlan { p, ii, val }
+    }
+}
+
+impl Plan for IndexSelectPlan {
+    // Create a new indexselect scan for this query.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let ts = self.p.open_table_scan()?;
+        let idx = self.ii.open();
+        Ok(Box::new(IndexSelectScan::new(ts, idx, self.val.clone())?))
+    }
+
+    // Estimate the number of block accesses to compute the
+    // index selection, which is the same as the
+    // index traversal cost plus the number of matching data records,
+    // though no more than the blocks of the table.
+    fn blocks_accessed(&self) -> u64 {
+        self.ii.blocks_accessed() + self.records_output().min(self.p.blocks_accessed())
+    }
+
+    // Estimate the number of output records in the index selection,
+    // which is the same as the number of search key values
+    // for the index.
+    fn records_output(&self) -> u64 {
+        self.ii.records_output()
+    }
+
+    // Return the distinct values as defined by the index.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        self.ii.distinct_values(fldname)
+    }
+
+    // Return the schema of the data table.
+    fn schema(&self) -> &Schema {
+        self.p.schema()
+    }
+
+    // The underlying table plan is not shown as a child,
+    // since the table is accessed only through the index.
+    fn description(&self) -> String {
+        format!(
+            "IndexSelect({} on {}: {}={})",
+            self.ii.index_name(),
+            self.p.table_name(),
+            self.ii.field_name(),
+            self.val
+        )
+    }
+}
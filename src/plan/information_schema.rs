@@ -0,0 +1,322 @@
+use std::sync::Arc;
+
+use super::CatalogPlan;
+use crate::{
+    error::{DbError, DbResult},
+    metadata::MetadataManager,
+    query::Constant,
+    record::FieldType::{self, Integer, Varchar},
+    tx::Transaction,
+};
+
+// The virtual tables of the information schema, named
+// "information_schema.<table>". Their records are computed
+// when a query mentions them, from the catalog or from the
+// state of the database, and they cannot be modified:
+// only the from clause of a query accepts a qualified name.
+//   tables(tblname, kind)
+//   columns(tblname, fldname, type, length, offset)
+//   views(viewname, viewdef)
+//   indexes(idxname, tblname, fldname)
+//   table_stats(tblname, numblocks, numrecs)
+//   buffer_pool(capacity, available, dirty, hits, misses, evictions, waits, timeouts)
+//   locks(filename, blknum, txnum, mode)
+// The lock on the end of a file has a null blknum.
+const SCHEMA_NAME: &str = "information_schema";
+
+const TABLES: &[(&str, FieldType)] = &[("tblname", Varchar), ("kind", Varchar)];
+const COLUMNS: &[(&str, FieldType)] = &[
+    ("tblname", Varchar),
+    ("fldname", Varchar),
+    ("type", Varchar),
+    ("length", Integer),
+    ("offset", Integer),
+];
+const VIEWS: &[(&str, FieldType)] = &[("viewname", Varchar), ("viewdef", Varchar)];
+const INDEXES: &[(&str, FieldType)] = &[
+    ("idxname", Varchar),
+    ("tblname", Varchar),
+    ("fldname", Varchar),
+];
+const TABLE_STATS: &[(&str, FieldType)] = &[
+    ("tblname", Varchar),
+    ("numblocks", Integer),
+    ("numrecs", Integer),
+];
+const BUFFER_POOL: &[(&str, FieldType)] = &[
+    ("capacity", Integer),
+    ("available", Integer),
+    ("dirty", Integer),
+    ("hits", Integer),
+    ("misses", Integer),
+    ("evictions", Integer),
+    ("waits", Integer),
+    ("timeouts", Integer),
+];
+const LOCKS: &[(&str, FieldType)] = &[
+    ("filename", Varchar),
+    ("blknum", Integer),
+    ("txnum", Integer),
+    ("mode", Varchar),
+];
+
+// Return the plan of the virtual table of the specified
+// name, or None if the name is not in the information schema.
+pub fn virtual_table(
+    tblname: &str,
+    mdm: &MetadataManager,
+    tx: &Arc<Transaction>,
+) -> DbResult<Option<CatalogPlan>> {
+    let Some(name) = tblname
+        .strip_prefix(SCHEMA_NAME)
+        .and_then(|name| name.strip_prefix('.'))
+    else {
+        return Ok(None);
+    };
+    let (fields, rows) = match name {
+        "tables" => (TABLES, CatalogPlan::table_rows(mdm, tx)?),
+        "columns" => (COLUMNS, columns(mdm, tx)?),
+        "views" => (VIEWS, views(mdm, tx)?),
+        "indexes" => (INDEXES, indexes(mdm, tx)?),
+        "table_stats" => (TABLE_STATS, table_stats(mdm, tx)?),
+        "buffer_pool" => (BUFFER_POOL, buffer_pool(tx)),
+        "locks" => (LOCKS, locks(tx)),
+        _ => return Err(DbError::Catalog(format!("table {} not found", tblname))),
+    };
+    let description = format!("VirtualTable({})", tblname);
+    Ok(Some(CatalogPlan::new(&description, fields, rows)))
+}
+
+fn columns(mdm: &MetadataManager, tx: &Arc<Transaction>) -> DbResult<Vec<Vec<Constant>>> {
+    let mut rows = Vec::new();
+    for tblname in sorted(mdm.table_names(tx)?) {
+        let layout = mdm.get_layout(&tblname, tx)?;
+        for fldname in layout.schema().fields() {
+            let (fldtype, length) = CatalogPlan::type_of(layout.schema(), fldname);
+            rows.push(vec![
+                Constant::Str(tblname.clone()),
+                Constant::Str(fldname.clone()),
+                Constant::Str(fldtype.to_string()),
+                length,
+                int(layout.offset(fldname) as u64),
+            ]);
+        }
+    }
+    Ok(rows)
+}
+
+fn views(mdm: &MetadataManager, tx: &Arc<Transaction>) -> DbResult<Vec<Vec<Constant>>> {
+    let mut rows = Vec::new();
+    for viewname in sorted(mdm.view_names(tx)?) {
+        let viewdef = mdm.get_view_def(&viewname, tx)?.unwrap_or_default();
+        rows.push(vec![Constant::Str(viewname), Constant::Str(viewdef)]);
+    }
+    Ok(rows)
+}
+
+fn indexes(mdm: &MetadataManager, tx: &Arc<Transaction>) -> DbResult<Vec<Vec<Constant>>> {
+    let mut rows = Vec::new();
+    for tblname in mdm.table_names(tx)? {
+        for (fldname, ii) in mdm.get_index_info(&tblname, tx)? {
+            rows.push(vec![
+                Constant::Str(ii.index_name().to_string()),
+                Constant::Str(tblname.clone()),
+                Constant::Str(fldname),
+            ]);
+        }
+    }
+    rows.sort();
+    Ok(rows)
+}
+
+fn table_stats(mdm: &MetadataManager, tx: &Arc<Transaction>) -> DbResult<Vec<Vec<Constant>>> {
+    let mut rows = Vec::new();
+    for tblname in sorted(mdm.table_names(tx)?) {
+        let layout = mdm.get_layout(&tblname, tx)?;
+        let si = mdm.get_stat_info(&tblname, &layout, tx)?;
+        rows.push(vec![
+            Constant::Str(tblname),
+            int(si.blocks_accessed()),
+            int(si.records_output()),
+        ]);
+    }
+    Ok(rows)
+}
+
+fn buffer_pool(tx: &Transaction) -> Vec<Vec<Constant>> {
+    let stats = tx.buffer_stats();
+    vec![vec![
+        int(stats.capacity as u64),
+        int(stats.available as u64),
+        int(stats.dirty as u64),
+        int(stats.hits),
+        int(stats.misses),
+        int(stats.evictions),
+        int(stats.waits),
+        int(stats.timeouts),
+    ]]
+}
+
+fn locks(tx: &Transaction) -> Vec<Vec<Constant>> {
+    tx.held_locks()
+        .into_iter()
+        .map(|(blk, txnum, mode)| {
+            let blknum = match i32::try_from(blk.number()) {
+                Ok(blknum) => Constant::Int(blknum),
+                Err(_) => Constant::Null,
+            };
+            vec![
+                Constant::Str(blk.filename().to_string()),
+                blknum,
+                Constant::Int(txnum),
+                Constant::Str(mode.to_string()),
+            ]
+        })
+        .collect()
+}
+
+fn sorted(mut names: Vec<String>) -> Vec<String> {
+    names.sort();
+    names
+}
+
+// An integer field holds at most i32::MAX.
+fn int(n: u64) -> Constant {
+    Constant::Int(i32::try_from(n).unwrap_or(i32::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, plan::Planner};
+    use tempfile::TempDir;
+
+    fn rows(planner: &Planner, qry: &str, tx: &Arc<Transaction>) -> DbResult<Vec<String>> {
+        let plan = planner.create_query_plan(qry, tx)?;
+        let mut s = plan.open()?;
+        let mut rows = Vec::new();
+        while s.next()? {
+            let vals = plan
+                .schema()
+                .fields()
+                .iter()
+                .map(|fldname| s.get_val(fldname).map(|val| val.to_string()))
+                .collect::<DbResult<Vec<_>>>()?;
+            rows.push(vals.join(" "));
+        }
+        s.close();
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_virtual_tables() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let planner = db.planner()?;
+        planner.execute_update("create table t (a int, b varchar(10))", &tx)?;
+        planner.execute_update("create table u (c blob)", &tx)?;
+        for i in 0..5 {
+            let sql = format!("insert into t (a, b) values ({}, 'b{}')", i, i);
+            planner.execute_update(&sql, &tx)?;
+        }
+        planner.execute_update("create index t_a on t (a)", &tx)?;
+        planner.execute_update("create view v as select b from t where a = 1", &tx)?;
+
+        assert_eq!(
+            rows(
+                &planner,
+                "select tblname, kind from information_schema.tables",
+                &tx
+            )?,
+            ["t table", "u table", "v view"]
+        );
+        assert_eq!(
+            rows(
+                &planner,
+                "select fldname, type, length, offset from information_schema.columns",
+                &tx
+            )?,
+            ["a int null 4", "b varchar 10 8", "c blob null 4"]
+        );
+        // a virtual table can be selected from, sorted and joined
+        assert_eq!(
+            rows(
+                &planner,
+                "select fldname from information_schema.columns where tblname = 't' \
+                 order by fldname desc",
+                &tx
+            )?,
+            ["b", "a"]
+        );
+        assert_eq!(
+            rows(
+                &planner,
+                "select a, tblname from t, information_schema.table_stats where a = numrecs",
+                &tx
+            )?,
+            ["0 u"]
+        );
+        assert_eq!(
+            rows(
+                &planner,
+                "select idxname, tblname, fldname from information_schema.indexes",
+                &tx
+            )?,
+            ["t_a t a"]
+        );
+        assert_eq!(
+            rows(
+                &planner,
+                "select viewname, viewdef from information_schema.views",
+                &tx
+            )?,
+            ["v select b from t where a=1"]
+        );
+        assert_eq!(
+            rows(&planner, "select b from v, information_schema.views", &tx)?,
+            ["b1"]
+        );
+        assert_eq!(
+            rows(
+                &planner,
+                "select tblname, numrecs from information_schema.table_stats",
+                &tx
+            )?,
+            ["t 5", "u 0"]
+        );
+        assert_eq!(
+            rows(
+                &planner,
+                "select capacity from information_schema.buffer_pool",
+                &tx
+            )?,
+            ["8"]
+        );
+
+        // the transaction holds an XLock on the blocks it modified
+        let locks = rows(
+            &planner,
+            "select filename, blknum, txnum, mode from information_schema.locks",
+            &tx,
+        )?;
+        assert!(locks.contains(&format!("t.tbl 0 {} X", tx.txnum())));
+        assert!(locks.contains(&format!("t.tbl null {} X", tx.txnum())));
+
+        assert_eq!(
+            rows(&planner, "describe information_schema.tables", &tx)?,
+            ["tblname varchar 1", "kind varchar 5"]
+        );
+        for sql in [
+            "insert into information_schema.tables (tblname) values ('x')",
+            "delete from information_schema.tables",
+        ] {
+            let result = planner.execute_update(sql, &tx);
+            assert!(matches!(result, Err(DbError::BadSyntax(_))), "{}", sql);
+        }
+        let result = planner.create_query_plan("select a from information_schema.nosuch", &tx);
+        assert!(matches!(result, Err(DbError::Catalog(_))));
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+mod basic_update_planner;
+mod catalog_plan;
+mod explain_plan;
+mod heuristic_query_planner;
+mod index_join_plan;
+mod index_select_plan;
+mod information_schema;
+mod planner;
+mod product_plan;
+mod project_plan;
+mod query_planner;
+mod select_plan;
+mod table_plan;
+mod table_planner;
+mod update_planner;
+
+pub use basic_update_planner::BasicUpdatePlanner;
+pub use catalog_plan::CatalogPlan;
+pub use explain_plan::{explain, ExplainPlan};
+pub use heuristic_query_planner::HeuristicQueryPlanner;
+pub use index_join_plan::IndexJoinPlan;
+pub use index_select_plan::IndexSelectPlan;
+pub use information_schema::virtual_table;
+pub use planner::Planner;
+pub use product_plan::ProductPlan;
+pub use project_plan::ProjectPlan;
+pub use query_planner::QueryPlanner;
+pub use select_plan::SelectPlan;
+pub use table_plan::TablePlan;
+pub use table_planner::TablePlanner;
+pub use update_planner::UpdatePlanner;
+
+use std::sync::Arc;
+
+use crate::{error::DbResult, query::Scan, record::Schema};
+
+// The interface implemented by each query plan.
+// There is a Plan class for each relational algebra operator.
+pub trait Plan {
+    // Open a scan corresponding to this plan.
+    // The scan will be positioned before its first record.
+    fn open(&self) -> DbResult<Box<dyn Scan>>;
+
+    // Return an estimate of the number of block accesses
+    // that will occur when the scan is read to completion.
+    fn blocks_accessed(&self) -> u64;
+
+    // Return an estimate of the number of records
+    // in the query's output table.
+    fn records_output(&self) -> u64;
+
+    // Return an estimate of the number of distinct values
+    // for the specified field in the query's output table.
+    fn distinct_values(&self, fldname: &str) -> u64;
+
+    // Return the schema of the query.
+    fn schema(&self) -> &Schema;
+
+    // Return a one-line description of this node,
+    // as shown by EXPLAIN.
+    fn description(&self) -> String;
+
+    // Return the subqueries of this node.
+    // Leaf nodes have none.
+    fn children(&self) -> Vec<&dyn Plan> {
+        Vec::new()
+    }
+}
+
+// A shared plan, so that the planner can build several
+// candidate plans over the same subquery and keep the cheapest.
+impl<P: Plan + ?Sized> Plan for Arc<P> {
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        (**self).open()
+    }
+
+    fn blocks_accessed(&self) -> u64 {
+        (**self).blocks_accessed()
+    }
+
+    fn records_output(&self) -> u64 {
+        (**self).records_output()
+    }
+
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        (**self).distinct_values(fldname)
+    }
+
+    fn schema(&self) -> &Schema {
+        (**self).schema()
+    }
+
+    fn description(&self) -> String {
+        (**self).description()
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        (**self).children()
+    }
+}
+
+// Run the specified query in the transaction, and return
+// each output record as its values separated by spaces,
+// in the order of the output.
+#[cfg(test)]
+pub(crate) fn run_query(
+    planner: &Planner,
+    qry: &str,
+    tx: &Arc<crate::tx::Transaction>,
+) -> DbResult<Vec<String>> {
+    let p = planner.create_query_plan(qry, tx)?;
+    let rs = crate::driver::ResultSet::from_plan(p.as_ref())?;
+    Ok(rs
+        .rows()
+        .iter()
+        .map(|row| {
+            let vals: Vec<String> = row.iter().map(|val| val.to_string()).collect();
+            vals.join(" ")
+        })
+        .collect())
+}
@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use super::{ExplainPlan, Plan, QueryPlanner, UpdatePlanner};
+use crate::{
+    error::DbResult,
+    parse::{Parser, QueryCommand, UpdateCommand},
+    tx::Transaction,
+};
+
+// The object that executes SQL statements.
+pub struct Planner {
+    qplanner: Box<dyn QueryPlanner + Send + Sync>,
+    uplanner: Box<dyn UpdatePlanner + Send + Sync>,
+}
+
+impl Planner {
+    pub fn new(
+        qplanner: Box<dyn QueryPlanner + Send + Sync>,
+        uplanner: Box<dyn UpdatePlanner + Send + Sync>,
+    ) -> Self {
+        Planner { qplanner, uplanner }
+    }
+
+    // Create a plan for an SQL select statement, using the supplied planner.
+    // An explain statement yields a plan whose records are
+    // the lines of the explained query's plan.
+    // The show tables and describe statements yield
+    // plans whose records are read from the catalog.
+    pub fn create_query_plan(&self, qry: &str, tx: &Arc<Transaction>) -> DbResult<Box<dyn Plan>> {
+        let mut parser = Parser::new(qry)?;
+        match parser.query_cmd()? {
+            QueryCommand::Select(data) => self.qplanner.create_plan(&data, tx),
+            QueryCommand::Explain(data) => {
+                let p = self.qplanner.create_plan(&data, tx)?;
+                Ok(Box::new(ExplainPlan::new(p)))
+            }
+            QueryCommand::ShowTables => self.qplanner.create_show_tables_plan(tx),
+            QueryCommand::Describe(tblname) => self.qplanner.create_describe_plan(&tblname, tx),
+        }
+    }
+
+    // Render the plan that the supplied planner chooses
+    // for an SQL select statement, with the cost
+    // estimates of each node.
+    pub fn explain(&self, qry: &str, tx: &Arc<Transaction>) -> DbResult<String> {
+        let mut parser = Parser::new(qry)?;
+        let p = match parser.query_cmd()? {
+            QueryCommand::Select(data) | QueryCommand::Explain(data) => {
+                self.qplanner.create_plan(&data, tx)?
+            }
+            QueryCommand::ShowTables => self.qplanner.create_show_tables_plan(tx)?,
+            QueryCommand::Describe(tblname) => self.qplanner.create_describe_plan(&tblname, tx)?,
+        };
+        Ok(super::explain(p.as_ref()))
+    }
+
+    // Execute an SQL insert, delete, modify, create table,
+    // create view, create index, drop index, vacuum, truncate,
+    // or analyze statement.
+    // The method dispatches to the appropriate method of the
+    // supplied update planner,
+    // depending on what the parser returns.
+    // The query of a new view is planned first, so that
+    // a view of missing tables or fields is rejected.
+    // A statement that fails part way has its modifications
+    // undone, back to a savepoint taken before it started,
+    // so that it either happens completely or not at all;
+    // the transaction stays active either way.
+    pub fn execute_update(&self, cmd: &str, tx: &Arc<Transaction>) -> DbResult<usize> {
+        let mut parser = Parser::new(cmd)?;
+        self.execute(parser.update_cmd()?, tx)
+    }
+
+    // Compact the table, as the statement "vacuum tblname" does,
+    // and return the number of blocks freed. The blocks are
+    // cut off the table file once the transaction commits.
+    pub fn vacuum(&self, tblname: &str, tx: &Arc<Transaction>) -> DbResult<usize> {
+        self.execute(UpdateCommand::Vacuum(tblname.to_string()), tx)
+    }
+
+    fn execute(&self, cmd: UpdateCommand, tx: &Arc<Transaction>) -> DbResult<usize> {
+        let savepoint = tx.savepoint();
+        let result = match cmd {
+            UpdateCommand::Insert(data) => self.uplanner.execute_insert(&data, tx),
+            UpdateCommand::Delete(data) => self.uplanner.execute_delete(&data, tx),
+            UpdateCommand::Modify(data) => self.uplanner.execute_modify(&data, tx),
+            UpdateCommand::CreateTable(data) => self.uplanner.execute_create_table(&data, tx),
+            UpdateCommand::CreateView(data) => self
+                .qplanner
+                .create_plan(data.query_data(), tx)
+                .and_then(|_| self.uplanner.execute_create_view(&data, tx)),
+            UpdateCommand::CreateIndex(data) => self.uplanner.execute_create_index(&data, tx),
+            UpdateCommand::DropIndex(idxname) => self.uplanner.execute_drop_index(&idxname, tx),
+            UpdateCommand::Vacuum(tblname) => self.uplanner.execute_vacuum(&tblname, tx),
+            UpdateCommand::Truncate(tblname) => self.uplanner.execute_truncate(&tblname, tx),
+            UpdateCommand::Analyze(tblname) => {
+                self.uplanner.execute_analyze(tblname.as_deref(), tx)
+            }
+        };
+        if result.is_err() {
+            tx.rollback_to(savepoint)?;
+        }
+        result
+    }
+}
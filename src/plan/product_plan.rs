@@ -0,0 +1,80 @@
+use super::Plan;
+use crate::{
+    error::DbResult,
+    query::{ProductScan, Scan},
+    record::Schema,
+};
+
+// The Plan class corresponding to the product
+// relational algebra operator.
+pub struct ProductPlan {
+    p1: Box<dyn Plan>,
+    p2: Box<dyn Plan>,
+    schema: Schema,
+}
+
+impl ProductPlan {
+    // Create a new product node in the query tree,
+    // having the two specified subqueries.
+    pub fn new(p1: Box<dyn Plan>, p2: Box<dyn Plan>) -> Self {
+        let mut schema = Schema::new();
+        schema.add_all(p1.schema());
+        schema.add_all(p2.schema());
+        ProductPlan { p1, p2, schema }
+    }
+}
+
+impl Plan for ProductPlan {
+    // Create a product scan for this query.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let s1 = self.p1.open()?;
+        let s2 = self.p2.open()?;
+        Ok(Box::new(ProductScan::new(s1, s2)?))
+    }
+
+    // Estimate the required block accesses
+    // for the product.
+    // The formula is:
+    // B(product(p1,p2)) = B(p1) + R(p1)*B(p2)
+    fn blocks_accessed(&self) -> u64 {
+        self.p1.blocks_accessed().saturating_add(
+            self.p1
+                .records_output()
+                .saturating_mul(self.p2.blocks_accessed()),
+        )
+    }
+
+    // Estimate the number of output records in the product.
+    // The formula is:
+    // R(product(p1,p2)) = R(p1)*R(p2)
+    fn records_output(&self) -> u64 {
+        self.p1
+            .records_output()
+            .saturating_mul(self.p2.records_output())
+    }
+
+    // Estimate the distinct number of field values in the product.
+    // Since the product does not increase or decrease field values,
+    // the estimate is the same as in the appropriate underlying query.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        if self.p1.schema().has_field(fldname) {
+            self.p1.distinct_values(fldname)
+        } else {
+            self.p2.distinct_values(fldname)
+        }
+    }
+
+    // Return the schema of the product,
+    // which is the union of the schemas of the underlying queries.
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn description(&self) -> String {
+        "Product".to_string()
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![self.p1.as_ref(), self.p2.as_ref()]
+    }
+}
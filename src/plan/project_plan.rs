@@ -0,0 +1,66 @@
+use super::Plan;
+use crate::{
+    error::DbResult,
+    query::{ProjectScan, Scan},
+    record::Schema,
+};
+
+// The Plan class corresponding to the project
+// relational algebra operator.
+pub struct ProjectPlan {
+    p: Box<dyn Plan>,
+    schema: Schema,
+}
+
+impl ProjectPlan {
+    // Create a new project node in the query tree,
+    // having the specified subquery and field list.
+    pub fn new(p: Box<dyn Plan>, fieldlist: &[String]) -> Self {
+        let mut schema = Schema::new();
+        for fldname in fieldlist {
+            schema.add(fldname, p.schema());
+        }
+        ProjectPlan { p, schema }
+    }
+}
+
+impl Plan for ProjectPlan {
+    // Create a project scan for this query.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let s = self.p.open()?;
+        Ok(Box::new(ProjectScan::new(s, self.schema.fields().to_vec())))
+    }
+
+    // Estimate the number of block accesses in the projection,
+    // which is the same as in the underlying query.
+    fn blocks_accessed(&self) -> u64 {
+        self.p.blocks_accessed()
+    }
+
+    // Estimate the number of output records in the projection,
+    // which is the same as in the underlying query.
+    fn records_output(&self) -> u64 {
+        self.p.records_output()
+    }
+
+    // Estimate the number of distinct field values
+    // in the projection,
+    // which is the same as in the underlying query.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        self.p.distinct_values(fldname)
+    }
+
+    // Return the schema of the projection,
+    // which is taken from the field list.
+    fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    fn description(&self) -> String {
+        format!("Project({})", self.schema.fields().join(", "))
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![self.p.as_ref()]
+    }
+}
@@ -0,0 +1,20 @@
+use std::sync::Arc;
+
+use super::Plan;
+use crate::{error::DbResult, parse::QueryData, tx::Transaction};
+
+// The interface implemented by planners for
+// the SQL select statement, and for the statements
+// that read the catalog.
+pub trait QueryPlanner {
+    // Create a plan for the parsed query.
+    fn create_plan(&self, data: &QueryData, tx: &Arc<Transaction>) -> DbResult<Box<dyn Plan>>;
+
+    // Create a plan listing the tables and views.
+    fn create_show_tables_plan(&self, tx: &Arc<Transaction>) -> DbResult<Box<dyn Plan>>;
+
+    // Create a plan listing the fields of the
+    // specified table or view.
+    fn create_describe_plan(&self, tblname: &str, tx: &Arc<Transaction>)
+        -> DbResult<Box<dyn Plan>>;
+}
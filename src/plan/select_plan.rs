@@ -0,0 +1,76 @@
+use super::Plan;
+use crate::{
+    error::DbResult,
+    query::{Predicate, Scan, SelectScan},
+    record::Schema,
+};
+
+// The Plan class corresponding to the select
+// relational algebra operator.
+pub struct SelectPlan {
+    p: Box<dyn Plan>,
+    pred: Predicate,
+}
+
+impl SelectPlan {
+    // Create a new select node in the query tree,
+    // having the specified subquery and predicate.
+    pub fn new(p: Box<dyn Plan>, pred: Predicate) -> Self {
+        SelectPlan { p, pred }
+    }
+}
+
+impl Plan for SelectPlan {
+    // Create a select scan for this query.
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        let s = self.p.open()?;
+        Ok(Box::new(SelectScan::new(s, self.pred.clone())))
+    }
+
+    // Estimate the number of block accesses in the selection,
+    // which is the same as in the underlying query.
+    fn blocks_accessed(&self) -> u64 {
+        self.p.blocks_accessed()
+    }
+
+    // Estimate the number of output records in the selection,
+    // which is determined by the
+    // reduction factor of the predicate.
+    fn records_output(&self) -> u64 {
+        self.p.records_output() / self.pred.reduction_factor(self.p.as_ref()).max(1)
+    }
+
+    // Estimate the number of distinct field values
+    // in the projection.
+    // If the predicate contains a term equating the specified
+    // field to a constant, then this value will be 1.
+    // Otherwise, it will be the number of the distinct values
+    // in the underlying query
+    // (but not more than the size of the output table).
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        if self.pred.equates_with_constant(fldname).is_some() {
+            return 1;
+        }
+        match self.pred.equates_with_field(fldname) {
+            Some(fldname2) => self
+                .p
+                .distinct_values(fldname)
+                .min(self.p.distinct_values(fldname2)),
+            None => self.p.distinct_values(fldname),
+        }
+    }
+
+    // Return the schema of the selection,
+    // which is the same as in the underlying query.
+    fn schema(&self) -> &Schema {
+        self.p.schema()
+    }
+
+    fn description(&self) -> String {
+        format!("Select({})", self.pred)
+    }
+
+    fn children(&self) -> Vec<&dyn Plan> {
+        vec![self.p.as_ref()]
+    }
+}
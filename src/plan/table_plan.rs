@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use super::Plan;
+use crate::{
+    error::DbResult,
+    metadata::{MetadataManager, StatInfo},
+    query::Scan,
+    record::{Layout, Schema, TableScan},
+    tx::Transaction,
+};
+
+// The Plan class corresponding to a table.
+#[derive(Clone)]
+pub struct TablePlan {
+    tblname: String,
+    tx: Arc<Transaction>,
+    layout: Layout,
+    si: StatInfo,
+}
+
+impl TablePlan {
+    // Create a leaf node in the query tree corresponding
+    // to the specified table.
+    pub fn new(tx: Arc<Transaction>, tblname: &str, md: &MetadataManager) -> DbResult<Self> {
+        let layout = md.get_layout(tblname, &tx)?;
+        let si = md.get_stat_info(tblname, &layout, &tx)?;
+        Ok(TablePlan {
+            tblname: tblname.to_string(),
+            tx,
+            layout,
+            si,
+        })
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.tblname
+    }
+
+    // Open a table scan for the table.
+    // The index plans use this directly, since they need
+    // to position the scan by record id.
+    pub fn open_table_scan(&self) -> DbResult<TableScan> {
+        TableScan::new(Arc::clone(&self.tx), &self.tblname, self.layout.clone())
+    }
+}
+
+impl Plan for TablePlan {
+    fn open(&self) -> DbResult<Box<dyn Scan>> {
+        Ok(Box::new(self.open_table_scan()?))
+    }
+
+    // Estimate the number of block accesses for the table,
+    // which is obtainable from the statistics manager.
+    fn blocks_accessed(&self) -> u64 {
+        self.si.blocks_accessed()
+    }
+
+    // Estimate the number of records in the table,
+    // which is obtainable from the statistics manager.
+    fn records_output(&self) -> u64 {
+        self.si.records_output()
+    }
+
+    // Estimate the number of distinct field values in the table,
+    // which is obtainable from the statistics manager.
+    fn distinct_values(&self, fldname: &str) -> u64 {
+        self.si.distinct_values(fldname)
+    }
+
+    fn schema(&self) -> &Schema {
+        self.layout.schema()
+    }
+
+    fn description(&self) -> String {
+        format!("Table({})", self.tblname)
+    }
+}
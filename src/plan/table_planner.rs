@@ -0,0 +1,322 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::{IndexJoinPlan, IndexSelectPlan, Plan, SelectPlan, TablePlan};
+use crate::{
+    error::DbResult,
+    materialize::HashJoinPlan,
+    metadata::{IndexInfo, MetadataManager},
+    multibuffer::MultibufferProductPlan,
+    query::Predicate,
+    record::Schema,
+    tx::Transaction,
+};
+
+// This class contains methods for planning a single table,
+// or a view, which is planned like a table without indexes.
+pub struct TablePlanner {
+    myplan: Arc<dyn Plan>,
+    // The stored table, which the index plans read;
+    // None for a view.
+    table: Option<TablePlan>,
+    mypred: Predicate,
+    indexes: HashMap<String, IndexInfo>,
+    tx: Arc<Transaction>,
+}
+
+impl TablePlanner {
+    // Create a new table planner.
+    // The specified predicate applies to the entire query.
+    // The table planner is responsible for determining
+    // which portion of the predicate is useful to the table,
+    // and when indexes are useful.
+    pub fn new(
+        tblname: &str,
+        mypred: Predicate,
+        tx: &Arc<Transaction>,
+        mdm: &MetadataManager,
+    ) -> DbResult<Self> {
+        let table = TablePlan::new(Arc::clone(tx), tblname, mdm)?;
+        let indexes = mdm.get_index_info(tblname, tx)?;
+        Ok(TablePlanner {
+            myplan: Arc::new(table.clone()),
+            table: Some(table),
+            mypred,
+            indexes,
+            tx: Arc::clone(tx),
+        })
+    }
+
+    // Create a new planner for a view, given the
+    // plan of its definition.
+    pub fn for_view(viewplan: Box<dyn Plan>, mypred: Predicate, tx: &Arc<Transaction>) -> Self {
+        TablePlanner {
+            myplan: Arc::from(viewplan),
+            table: None,
+            mypred,
+            indexes: HashMap::new(),
+            tx: Arc::clone(tx),
+        }
+    }
+
+    // Construct a select plan for the table.
+    // The plan will use an indexselect, if that is
+    // no more expensive than scanning the table.
+    pub fn make_select_plan(&self) -> Box<dyn Plan> {
+        let tableplan: Box<dyn Plan> = Box::new(Arc::clone(&self.myplan));
+        let p = self
+            .make_index_selects()
+            .into_iter()
+            .fold(tableplan, |best, p| {
+                if p.blocks_accessed() <= best.blocks_accessed() {
+                    p
+                } else {
+                    best
+                }
+            });
+        self.add_select_pred(p)
+    }
+
+    // Construct a join plan of the specified plan
+    // and the table. The candidates are an indexjoin through
+    // each index on a field equated with the current plan,
+    // and a hash join (or, failing that, a product); the plan
+    // accessing the fewest blocks is chosen, an indexjoin
+    // in case of a tie.
+    // The method returns None if no join is possible.
+    pub fn make_join_plan(&self, current: &Arc<dyn Plan>) -> Option<Box<dyn Plan>> {
+        let currsch = current.schema();
+        self.mypred.join_sub_pred(self.myplan.schema(), currsch)?;
+        let p = self
+            .make_hash_join(current, currsch)
+            .unwrap_or_else(|| self.make_product_plan(current));
+        let p = self
+            .make_index_joins(current, currsch)
+            .into_iter()
+            .fold(p, |best, p| {
+                if p.blocks_accessed() <= best.blocks_accessed() {
+                    p
+                } else {
+                    best
+                }
+            });
+        Some(self.add_join_pred(p, currsch))
+    }
+
+    // Construct a product plan of the specified plan and
+    // this table.
+    pub fn make_product_plan(&self, current: &Arc<dyn Plan>) -> Box<dyn Plan> {
+        let p = self.add_select_pred(Box::new(Arc::clone(&self.myplan)));
+        Box::new(MultibufferProductPlan::new(
+            Arc::clone(&self.tx),
+            Box::new(Arc::clone(current)),
+            p,
+        ))
+    }
+
+    // Return an indexselect plan for each indexed field
+    // that the predicate equates with a constant.
+    fn make_index_selects(&self) -> Vec<Box<dyn Plan>> {
+        let Some(table) = &self.table else {
+            return Vec::new();
+        };
+        self.indexes
+            .iter()
+            .filter_map(|(fldname, ii)| {
+                let val = self.mypred.equates_with_constant(fldname)?;
+                Some(
+                    Box::new(IndexSelectPlan::new(table.clone(), ii.clone(), val.clone()))
+                        as Box<dyn Plan>,
+                )
+            })
+            .collect()
+    }
+
+    // Return an indexjoin plan for each of the table's indexed
+    // fields that the predicate equates with a field of the
+    // current plan.
+    fn make_index_joins(&self, current: &Arc<dyn Plan>, currsch: &Schema) -> Vec<Box<dyn Plan>> {
+        let Some(table) = &self.table else {
+            return Vec::new();
+        };
+        self.indexes
+            .iter()
+            .filter_map(|(fldname, ii)| {
+                let outerfield = self.mypred.equates_with_field(fldname)?;
+                if !currsch.has_field(outerfield) {
+                    return None;
+                }
+                let p = IndexJoinPlan::new(
+                    Box::new(Arc::clone(current)),
+                    table.clone(),
+                    ii.clone(),
+                    outerfield,
+                );
+                Some(self.add_select_pred(Box::new(p)))
+            })
+            .collect()
+    }
+
+    // Use a hash join if one of the table's fields is equated
+    // with a field of the current plan. The table is the build
+    // input. Unlike a mergejoin, which sorts both inputs, the
+    // join reads each of them once, or once more to partition
+    // them when the table does not fit in the available buffers.
+    fn make_hash_join(&self, current: &Arc<dyn Plan>, currsch: &Schema) -> Option<Box<dyn Plan>> {
+        let (fldname, outerfield) = self.myplan.schema().fields().iter().find_map(|fldname| {
+            let outerfield = self.mypred.equates_with_field(fldname)?;
+            currsch
+                .has_field(outerfield)
+                .then_some((fldname, outerfield))
+        })?;
+        let p = self.add_select_pred(Box::new(Arc::clone(&self.myplan)));
+        Some(Box::new(HashJoinPlan::new(
+            Arc::clone(&self.tx),
+            Box::new(Arc::clone(current)),
+            p,
+            outerfield,
+            fldname,
+        )))
+    }
+
+    fn add_select_pred(&self, p: Box<dyn Plan>) -> Box<dyn Plan> {
+        match self.mypred.select_sub_pred(p.schema()) {
+            Some(selectpred) => Box::new(SelectPlan::new(p, selectpred)),
+            None => p,
+        }
+    }
+
+    fn add_join_pred(&self, p: Box<dyn Plan>, currsch: &Schema) -> Box<dyn Plan> {
+        match self.mypred.join_sub_pred(currsch, self.myplan.schema()) {
+            Some(joinpred) => Box::new(SelectPlan::new(p, joinpred)),
+            None => p,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::SimpleDB,
+        plan::{explain, ProductPlan, ProjectPlan},
+        query::{Constant, Expression, Term},
+    };
+    use tempfile::TempDir;
+
+    fn equals(fldname: &str, val: i32) -> Predicate {
+        Predicate::from_term(Term::new(
+            Expression::Field(fldname.to_string()),
+            Expression::Constant(Constant::Int(val)),
+        ))
+    }
+
+    // A database holding t, of 1000 records whose field a
+    // has 100 values, and u, of 20 records, both analyzed
+    // so that their statistics are exact.
+    fn new_db(temp_dir: &TempDir) -> DbResult<SimpleDB> {
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int, b int)")?;
+        db.execute_update("create index t_a on t (a)")?;
+        db.execute_update("create table u (c int)")?;
+        for i in 0..1000 {
+            db.execute_update(&format!(
+                "insert into t (a, b) values ({}, {})",
+                i % 100,
+                i % 4
+            ))?;
+        }
+        for i in 0..20 {
+            db.execute_update(&format!("insert into u (c) values ({})", i))?;
+        }
+        db.execute_update("analyze")?;
+        Ok(db)
+    }
+
+    #[test]
+    fn test_plan_estimates() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = new_db(&temp_dir)?;
+        let mdm = db.metadata_manager()?;
+        let tx = db.new_tx()?;
+        let tblocks = tx.size("t.tbl")?;
+        let ublocks = tx.size("u.tbl")?;
+        assert!(tblocks > 20);
+
+        let t = TablePlan::new(Arc::clone(&tx), "t", &mdm)?;
+        assert_eq!(t.blocks_accessed(), tblocks);
+        assert_eq!(t.records_output(), 1000);
+        assert_eq!((t.distinct_values("a"), t.distinct_values("b")), (100, 4));
+
+        // a selection reads the whole table, and keeps
+        // the records of one of the values
+        let select = SelectPlan::new(Box::new(t.clone()), equals("a", 7));
+        assert_eq!(select.blocks_accessed(), tblocks);
+        assert_eq!(select.records_output(), 10);
+        assert_eq!(
+            (select.distinct_values("a"), select.distinct_values("b")),
+            (1, 4)
+        );
+
+        let project = ProjectPlan::new(Box::new(select), &["b".to_string()]);
+        assert_eq!(project.blocks_accessed(), tblocks);
+        assert_eq!(project.records_output(), 10);
+        assert_eq!(project.distinct_values("b"), 4);
+
+        // through the index, only the blocks of the matching
+        // records are read
+        let ii = mdm.get_index_info("t", &tx)?.remove("a").unwrap();
+        let select = IndexSelectPlan::new(t.clone(), ii.clone(), Constant::Int(7));
+        assert_eq!(select.blocks_accessed(), ii.blocks_accessed() + 10);
+        assert_eq!(select.records_output(), 10);
+        assert_eq!(
+            (select.distinct_values("a"), select.distinct_values("b")),
+            (1, 4)
+        );
+
+        let u = TablePlan::new(Arc::clone(&tx), "u", &mdm)?;
+        let product = ProductPlan::new(Box::new(u.clone()), Box::new(t.clone()));
+        assert_eq!(product.blocks_accessed(), ublocks + 20 * tblocks);
+        assert_eq!(product.records_output(), 20 * 1000);
+        assert_eq!(
+            (product.distinct_values("c"), product.distinct_values("a")),
+            (20, 100)
+        );
+
+        // each record of u is looked up in the index
+        let join = IndexJoinPlan::new(Box::new(u), t, ii.clone(), "c");
+        assert_eq!(
+            join.blocks_accessed(),
+            ublocks + 20 * (ii.blocks_accessed() + 10)
+        );
+        assert_eq!(join.records_output(), 20 * 10);
+        assert_eq!(
+            (join.distinct_values("c"), join.distinct_values("a")),
+            (20, 100)
+        );
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_select_choice() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = new_db(&temp_dir)?;
+        let mdm = db.metadata_manager()?;
+        let tx = db.new_tx()?;
+
+        // the index reads fewer blocks than a scan of t
+        let tp = TablePlanner::new("t", equals("a", 7), &tx, &mdm)?;
+        let plan = tp.make_select_plan();
+        let scan = TablePlan::new(Arc::clone(&tx), "t", &mdm)?;
+        assert!(plan.blocks_accessed() < scan.blocks_accessed());
+        assert!(explain(plan.as_ref()).contains("IndexSelect(t_a on t: a=7)"));
+
+        // there is no index on b, so t is scanned
+        let tp = TablePlanner::new("t", equals("b", 1), &tx, &mdm)?;
+        let plan = tp.make_select_plan();
+        assert_eq!(plan.blocks_accessed(), scan.blocks_accessed());
+        assert!(!explain(plan.as_ref()).contains("IndexSelect"));
+        tx.commit()?;
+        Ok(())
+    }
+}
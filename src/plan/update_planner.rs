@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use crate::{
+    error::DbResult,
+    parse::{CreateIndexData, CreateTableData, CreateViewData, DeleteData, InsertData, ModifyData},
+    tx::Transaction,
+};
+
+// The interface implemented by the planners for
+// SQL insert, delete, and modify statements.
+// Each method returns the number of affected records,
+// except execute_vacuum, which returns the number of
+// blocks freed, execute_truncate, which does not
+// count the records it removes, and returns 0, and
+// execute_analyze, which returns the number of tables
+// analyzed.
+pub trait UpdatePlanner {
+    // Execute the specified insert statement.
+    fn execute_insert(&self, data: &InsertData, tx: &Arc<Transaction>) -> DbResult<usize>;
+
+    // Execute the specified delete statement.
+    fn execute_delete(&self, data: &DeleteData, tx: &Arc<Transaction>) -> DbResult<usize>;
+
+    // Execute the specified modify statement.
+    fn execute_modify(&self, data: &ModifyData, tx: &Arc<Transaction>) -> DbResult<usize>;
+
+    // Execute the specified create table statement.
+    fn execute_create_table(
+        &self,
+        data: &CreateTableData,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<usize>;
+
+    // Execute the specified create view statement,
+    // saving the definition of the view.
+    fn execute_create_view(&self, data: &CreateViewData, tx: &Arc<Transaction>) -> DbResult<usize>;
+
+    // Execute the specified create index statement,
+    // building the index from the records of the table.
+    fn execute_create_index(
+        &self,
+        data: &CreateIndexData,
+        tx: &Arc<Transaction>,
+    ) -> DbResult<usize>;
+
+    // Drop the specified index, emptying its files.
+    fn execute_drop_index(&self, idxname: &str, tx: &Arc<Transaction>) -> DbResult<usize>;
+
+    // Compact the specified table, and update its indexes.
+    fn execute_vacuum(&self, tblname: &str, tx: &Arc<Transaction>) -> DbResult<usize>;
+
+    // Remove every record of the specified table
+    // and of its indexes.
+    fn execute_truncate(&self, tblname: &str, tx: &Arc<Transaction>) -> DbResult<usize>;
+
+    // Recount the statistics of the specified table,
+    // or of every table if there is none, and save them.
+    fn execute_analyze(&self, tblname: Option<&str>, tx: &Arc<Transaction>) -> DbResult<usize>;
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+
+// The value of a database field:
+// either an integer, a string, a blob, or null.
+// Null sorts before every other value, and is equal to
+// itself so that nulls group together; comparisons in
+// predicates treat null specially (see Term).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Constant {
+    Null,
+    Int(i32),
+    Str(String),
+    Blob(Vec<u8>),
+}
+
+impl Constant {
+    pub fn as_int(&self) -> Option<i32> {
+        match self {
+            Constant::Int(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Constant::Str(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Constant::Blob(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Constant::Null)
+    }
+
+    // A hash code that is stable across runs,
+    // computed the same way as Java's hashCode
+    // so that on-disk structures keyed by it never move.
+    pub fn hash_code(&self) -> i32 {
+        match self {
+            Constant::Null => 0,
+            Constant::Int(val) => *val,
+            Constant::Str(val) => val
+                .encode_utf16()
+                .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32)),
+            Constant::Blob(val) => val.iter().fold(1i32, |h, b| {
+                h.wrapping_mul(31).wrapping_add(*b as i8 as i32)
+            }),
+        }
+    }
+}
+
+impl From<i32> for Constant {
+    fn from(val: i32) -> Self {
+        Constant::Int(val)
+    }
+}
+
+impl From<&str> for Constant {
+    fn from(val: &str) -> Self {
+        Constant::Str(val.to_string())
+    }
+}
+
+impl From<String> for Constant {
+    fn from(val: String) -> Self {
+        Constant::Str(val)
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Null => write!(f, "null"),
+            Constant::Int(val) => write!(f, "{}", val),
+            Constant::Str(val) => write!(f, "{}", val),
+            Constant::Blob(val) => val.iter().try_for_each(|b| write!(f, "{:02x}", b)),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+use super::Constant;
+use super::Scan;
+use crate::{
+    error::{DbError, DbResult},
+    record::Schema,
+};
+
+// The interface corresponding to SQL expressions:
+// either a constant or a field name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Constant(Constant),
+    Field(String),
+}
+
+impl Expression {
+    // Evaluate the expression with respect to the
+    // current record of the specified scan.
+    pub fn evaluate(&self, s: &dyn Scan) -> DbResult<Constant> {
+        match self {
+            Expression::Constant(val) => Ok(val.clone()),
+            Expression::Field(fldname) => {
+                if !s.has_field(fldname) {
+                    return Err(DbError::Catalog(format!("field {} not found", fldname)));
+                }
+                s.get_val(fldname)
+            }
+        }
+    }
+
+    pub fn is_field_name(&self) -> bool {
+        matches!(self, Expression::Field(_))
+    }
+
+    pub fn as_constant(&self) -> Option<&Constant> {
+        match self {
+            Expression::Constant(val) => Some(val),
+            Expression::Field(_) => None,
+        }
+    }
+
+    // Determine if all of the fields mentioned in this expression
+    // are contained in the specified schema.
+    pub fn applies_to(&self, sch: &Schema) -> bool {
+        match self {
+            Expression::Constant(_) => true,
+            Expression::Field(fldname) => sch.has_field(fldname),
+        }
+    }
+
+    pub fn as_field_name(&self) -> Option<&str> {
+        match self {
+            Expression::Constant(_) => None,
+            Expression::Field(fldname) => Some(fldname),
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Constant(Constant::Str(val)) => write!(f, "'{}'", val),
+            Expression::Constant(val @ Constant::Blob(_)) => write!(f, "x'{}'", val),
+            Expression::Constant(val) => write!(f, "{}", val),
+            Expression::Field(fldname) => write!(f, "{}", fldname),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+
+use super::{Constant, Scan, Term};
+use crate::{error::DbResult, plan::Plan, record::Schema};
+
+// A predicate is a Boolean combination of terms.
+// An empty predicate is always satisfied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Predicate {
+    terms: Vec<Term>,
+}
+
+impl Predicate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_term(t: Term) -> Self {
+        Predicate { terms: vec![t] }
+    }
+
+    // Modifies the predicate to be the conjunction of
+    // itself and the specified predicate.
+    pub fn conjoin_with(&mut self, pred: Predicate) {
+        self.terms.extend(pred.terms);
+    }
+
+    // Return true if the predicate evaluates to true
+    // with respect to the specified scan.
+    pub fn is_satisfied(&self, s: &dyn Scan) -> DbResult<bool> {
+        for t in &self.terms {
+            if !t.is_satisfied(s)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // Calculate the extent to which selecting on the predicate
+    // reduces the number of records output by a query.
+    // For example if the reduction factor is 2, then the
+    // predicate cuts the size of the output in half.
+    pub fn reduction_factor(&self, p: &dyn Plan) -> u64 {
+        self.terms
+            .iter()
+            .fold(1, |factor, t| factor.saturating_mul(t.reduction_factor(p)))
+    }
+
+    // Return the sub-predicate that applies to the specified schema.
+    pub fn select_sub_pred(&self, sch: &Schema) -> Option<Predicate> {
+        let terms: Vec<Term> = self
+            .terms
+            .iter()
+            .filter(|t| t.applies_to(sch))
+            .cloned()
+            .collect();
+        Self::non_empty(terms)
+    }
+
+    // Return the sub-predicate consisting of terms that apply
+    // to the union of the two specified schemas,
+    // but not to either schema separately.
+    pub fn join_sub_pred(&self, sch1: &Schema, sch2: &Schema) -> Option<Predicate> {
+        let mut newsch = Schema::new();
+        newsch.add_all(sch1);
+        newsch.add_all(sch2);
+        let terms: Vec<Term> = self
+            .terms
+            .iter()
+            .filter(|t| !t.applies_to(sch1) && !t.applies_to(sch2) && t.applies_to(&newsch))
+            .cloned()
+            .collect();
+        Self::non_empty(terms)
+    }
+
+    // Determine if there is a term of the form "F=c"
+    // where F is the specified field and c is some constant.
+    // If so, the method returns that constant.
+    pub fn equates_with_constant(&self, fldname: &str) -> Option<&Constant> {
+        self.terms
+            .iter()
+            .find_map(|t| t.equates_with_constant(fldname))
+    }
+
+    // Determine if there is a term of the form "F1=F2"
+    // where F1 is the specified field and F2 is another field.
+    // If so, the method returns the name of that field.
+    pub fn equates_with_field(&self, fldname: &str) -> Option<&str> {
+        self.terms
+            .iter()
+            .find_map(|t| t.equates_with_field(fldname))
+    }
+
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+}
+
+impl Predicate {
+    fn non_empty(terms: Vec<Term>) -> Option<Predicate> {
+        if terms.is_empty() {
+            None
+        } else {
+            Some(Predicate { terms })
+        }
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self.terms.iter().map(|t| t.to_string()).collect();
+        write!(f, "{}", terms.join(" and "))
+    }
+}
@@ -0,0 +1,177 @@
+use super::{Constant, Scan};
+use crate::error::DbResult;
+
+// The scan class corresponding to the product relational
+// algebra operator.
+pub struct ProductScan<S1, S2> {
+    s1: S1,
+    s2: S2,
+    s1_positioned: bool,
+}
+
+impl<S1: Scan, S2: Scan> ProductScan<S1, S2> {
+    // Create a product scan having the two underlying scans.
+    pub fn new(s1: S1, s2: S2) -> DbResult<Self> {
+        let mut scan = ProductScan {
+            s1,
+            s2,
+            s1_positioned: false,
+        };
+        scan.before_first()?;
+        Ok(scan)
+    }
+}
+
+impl<S1: Scan, S2: Scan> Scan for ProductScan<S1, S2> {
+    // Position the scan before its first record.
+    // In particular, the LHS scan is positioned at
+    // its first record, and the RHS scan
+    // is positioned before its first record.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.s1.before_first()?;
+        self.s1_positioned = self.s1.next()?;
+        self.s2.before_first()
+    }
+
+    // Move the scan to the next record.
+    // The method moves to the next RHS record, if possible.
+    // Otherwise, it moves to the next LHS record and the
+    // first RHS record.
+    // If there are no more LHS records, the method returns false.
+    fn next(&mut self) -> DbResult<bool> {
+        if !self.s1_positioned {
+            return Ok(false);
+        }
+        if self.s2.next()? {
+            return Ok(true);
+        }
+        self.s2.before_first()?;
+        self.s1_positioned = self.s1.next()?;
+        Ok(self.s1_positioned && self.s2.next()?)
+    }
+
+    // Return the integer value of the specified field.
+    // The value is obtained from whichever scan
+    // contains the field.
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        if self.s1.has_field(fldname) {
+            self.s1.get_int(fldname)
+        } else {
+            self.s2.get_int(fldname)
+        }
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        if self.s1.has_field(fldname) {
+            self.s1.get_string(fldname)
+        } else {
+            self.s2.get_string(fldname)
+        }
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        if self.s1.has_field(fldname) {
+            self.s1.get_val(fldname)
+        } else {
+            self.s2.get_val(fldname)
+        }
+    }
+
+    // Return true if the specified field is in
+    // either of the underlying scans.
+    fn has_field(&self, fldname: &str) -> bool {
+        self.s1.has_field(fldname) || self.s2.has_field(fldname)
+    }
+
+    fn close(&mut self) {
+        self.s1.close();
+        self.s2.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::SimpleDB,
+        query::{Expression, Predicate, ProjectScan, SelectScan, Term, UpdateScan},
+        record::{Layout, Schema, TableScan},
+        tx::Transaction,
+    };
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn fill(tx: &Arc<Transaction>, tblname: &str, prefix: &str, n: i32) -> DbResult<Layout> {
+        let mut sch = Schema::new();
+        sch.add_int_field(&format!("{}a", prefix));
+        sch.add_string_field(&format!("{}b", prefix), 10);
+        let layout = Layout::new(sch);
+
+        let mut ts = TableScan::new(Arc::clone(tx), tblname, layout.clone())?;
+        for i in 0..n {
+            ts.insert()?;
+            ts.set_int(&format!("{}a", prefix), i)?;
+            ts.set_string(&format!("{}b", prefix), &format!("{}{}", prefix, i))?;
+        }
+        ts.close();
+        Ok(layout)
+    }
+
+    #[test]
+    fn test_select_project_product() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let layout1 = fill(&tx, "t1", "x", 20)?;
+        let layout2 = fill(&tx, "t2", "y", 10)?;
+
+        let s1 = TableScan::new(Arc::clone(&tx), "t1", layout1)?;
+        let s2 = TableScan::new(Arc::clone(&tx), "t2", layout2)?;
+        let product = ProductScan::new(s1, s2)?;
+
+        // select xa, yb from t1, t2 where xa = ya
+        let pred = Predicate::from_term(Term::new(
+            Expression::Field("xa".to_string()),
+            Expression::Field("ya".to_string()),
+        ));
+        let select = SelectScan::new(product, pred);
+        let mut project = ProjectScan::new(select, vec!["xa".to_string(), "yb".to_string()]);
+
+        let mut rows = Vec::new();
+        while project.next()? {
+            rows.push((project.get_int("xa")?, project.get_string("yb")?));
+        }
+        assert!(!project.has_field("xb"));
+        assert!(project.get_string("xb").is_err());
+        project.close();
+
+        let expected: Vec<_> = (0..10).map(|i| (i, format!("y{}", i))).collect();
+        assert_eq!(rows, expected);
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_product_with_empty_side() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let layout1 = fill(&tx, "t1", "x", 0)?;
+        let layout2 = fill(&tx, "t2", "y", 3)?;
+
+        let s1 = TableScan::new(Arc::clone(&tx), "t1", layout1.clone())?;
+        let s2 = TableScan::new(Arc::clone(&tx), "t2", layout2.clone())?;
+        let mut product = ProductScan::new(s1, s2)?;
+        assert!(!product.next()?);
+        product.close();
+
+        let s1 = TableScan::new(Arc::clone(&tx), "t2", layout2)?;
+        let s2 = TableScan::new(Arc::clone(&tx), "t1", layout1)?;
+        let mut product = ProductScan::new(s1, s2)?;
+        assert!(!product.next()?);
+        product.close();
+
+        tx.commit()?;
+        Ok(())
+    }
+}
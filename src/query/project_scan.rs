@@ -0,0 +1,60 @@
+use super::{Constant, Scan};
+use crate::error::{DbError, DbResult};
+
+// The scan class corresponding to the project relational
+// algebra operator.
+// All methods except has_field delegate their work to the
+// underlying scan.
+pub struct ProjectScan<S> {
+    s: S,
+    fieldlist: Vec<String>,
+}
+
+impl<S: Scan> ProjectScan<S> {
+    // Create a project scan having the specified
+    // underlying scan and field list.
+    pub fn new(s: S, fieldlist: Vec<String>) -> Self {
+        ProjectScan { s, fieldlist }
+    }
+
+    fn check_field(&self, fldname: &str) -> DbResult<()> {
+        if self.has_field(fldname) {
+            Ok(())
+        } else {
+            Err(DbError::Catalog(format!("field {} not found", fldname)))
+        }
+    }
+}
+
+impl<S: Scan> Scan for ProjectScan<S> {
+    fn before_first(&mut self) -> DbResult<()> {
+        self.s.before_first()
+    }
+
+    fn next(&mut self) -> DbResult<bool> {
+        self.s.next()
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.check_field(fldname)?;
+        self.s.get_int(fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        self.check_field(fldname)?;
+        self.s.get_string(fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        self.check_field(fldname)?;
+        self.s.get_val(fldname)
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        self.fieldlist.iter().any(|f| f == fldname)
+    }
+
+    fn close(&mut self) {
+        self.s.close()
+    }
+}
@@ -0,0 +1,116 @@
+use super::Constant;
+use crate::{error::DbResult, record::Rid};
+
+// The interface implemented by all query scans.
+pub trait Scan {
+    // Position the scan before its first record. A
+    // subsequent call to next() will return the first record.
+    fn before_first(&mut self) -> DbResult<()>;
+
+    // Move the scan to the next record.
+    // Return false if there is no next record.
+    fn next(&mut self) -> DbResult<bool>;
+
+    // Return the value of the specified integer field
+    // in the current record.
+    fn get_int(&self, fldname: &str) -> DbResult<i32>;
+
+    // Return the value of the specified string field
+    // in the current record.
+    fn get_string(&self, fldname: &str) -> DbResult<String>;
+
+    // Return the value of the specified field in the current record.
+    // The value is expressed as a Constant.
+    fn get_val(&self, fldname: &str) -> DbResult<Constant>;
+
+    // Return true if the scan has the specified field.
+    fn has_field(&self, fldname: &str) -> bool;
+
+    // Close the scan and its subscans, if any.
+    fn close(&mut self);
+}
+
+// The interface implemented by all updateable scans.
+pub trait UpdateScan: Scan {
+    // Modify the field value of the current record.
+    fn set_val(&mut self, fldname: &str, val: &Constant) -> DbResult<()>;
+
+    // Modify the field value of the current record.
+    fn set_int(&mut self, fldname: &str, val: i32) -> DbResult<()>;
+
+    // Modify the field value of the current record.
+    fn set_string(&mut self, fldname: &str, val: &str) -> DbResult<()>;
+
+    // Insert a new record somewhere in the scan.
+    fn insert(&mut self) -> DbResult<()>;
+
+    // Delete the current record from the scan.
+    fn delete(&mut self) -> DbResult<()>;
+
+    // Return the id of the current record.
+    fn get_rid(&self) -> Rid;
+
+    // Position the scan so that the current record has
+    // the specified id.
+    fn move_to_rid(&mut self, rid: Rid) -> DbResult<()>;
+}
+
+impl<S: Scan + ?Sized> Scan for Box<S> {
+    fn before_first(&mut self) -> DbResult<()> {
+        (**self).before_first()
+    }
+
+    fn next(&mut self) -> DbResult<bool> {
+        (**self).next()
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        (**self).get_int(fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        (**self).get_string(fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        (**self).get_val(fldname)
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        (**self).has_field(fldname)
+    }
+
+    fn close(&mut self) {
+        (**self).close()
+    }
+}
+
+impl<S: UpdateScan + ?Sized> UpdateScan for Box<S> {
+    fn set_val(&mut self, fldname: &str, val: &Constant) -> DbResult<()> {
+        (**self).set_val(fldname, val)
+    }
+
+    fn set_int(&mut self, fldname: &str, val: i32) -> DbResult<()> {
+        (**self).set_int(fldname, val)
+    }
+
+    fn set_string(&mut self, fldname: &str, val: &str) -> DbResult<()> {
+        (**self).set_string(fldname, val)
+    }
+
+    fn insert(&mut self) -> DbResult<()> {
+        (**self).insert()
+    }
+
+    fn delete(&mut self) -> DbResult<()> {
+        (**self).delete()
+    }
+
+    fn get_rid(&self) -> Rid {
+        (**self).get_rid()
+    }
+
+    fn move_to_rid(&mut self, rid: Rid) -> DbResult<()> {
+        (**self).move_to_rid(rid)
+    }
+}
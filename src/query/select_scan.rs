@@ -0,0 +1,85 @@
+use super::{Constant, Predicate, Scan, UpdateScan};
+use crate::{error::DbResult, record::Rid};
+
+// The scan class corresponding to the select relational
+// algebra operator.
+// All methods except next delegate their work to the
+// underlying scan.
+pub struct SelectScan<S> {
+    s: S,
+    pred: Predicate,
+}
+
+impl<S: Scan> SelectScan<S> {
+    // Create a select scan having the specified underlying
+    // scan and predicate.
+    pub fn new(s: S, pred: Predicate) -> Self {
+        SelectScan { s, pred }
+    }
+}
+
+impl<S: Scan> Scan for SelectScan<S> {
+    fn before_first(&mut self) -> DbResult<()> {
+        self.s.before_first()
+    }
+
+    fn next(&mut self) -> DbResult<bool> {
+        while self.s.next()? {
+            if self.pred.is_satisfied(&self.s)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.s.get_int(fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        self.s.get_string(fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        self.s.get_val(fldname)
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        self.s.has_field(fldname)
+    }
+
+    fn close(&mut self) {
+        self.s.close()
+    }
+}
+
+// A select scan is updatable whenever its underlying scan is.
+impl<S: UpdateScan> UpdateScan for SelectScan<S> {
+    fn set_val(&mut self, fldname: &str, val: &Constant) -> DbResult<()> {
+        self.s.set_val(fldname, val)
+    }
+
+    fn set_int(&mut self, fldname: &str, val: i32) -> DbResult<()> {
+        self.s.set_int(fldname, val)
+    }
+
+    fn set_string(&mut self, fldname: &str, val: &str) -> DbResult<()> {
+        self.s.set_string(fldname, val)
+    }
+
+    fn insert(&mut self) -> DbResult<()> {
+        self.s.insert()
+    }
+
+    fn delete(&mut self) -> DbResult<()> {
+        self.s.delete()
+    }
+
+    fn get_rid(&self) -> Rid {
+        self.s.get_rid()
+    }
+
+    fn move_to_rid(&mut self, rid: Rid) -> DbResult<()> {
+        self.s.move_to_rid(rid)
+    }
+}
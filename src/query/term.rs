@@ -0,0 +1,155 @@
+use std::fmt;
+
+use super::{Constant, Expression, Scan};
+use crate::{error::DbResult, plan::Plan, record::Schema};
+
+// The comparison made by a term.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equals,
+    IsNull,
+    IsNotNull,
+}
+
+// A term is a comparison between two expressions,
+// or a test of whether an expression is null.
+// A null test is stored with a null constant
+// as its right-hand side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    lhs: Expression,
+    rhs: Expression,
+    op: Operator,
+}
+
+impl Term {
+    pub fn new(lhs: Expression, rhs: Expression) -> Self {
+        Term {
+            lhs,
+            rhs,
+            op: Operator::Equals,
+        }
+    }
+
+    // Create a term that tests whether the expression
+    // is null or, if negated, not null.
+    pub fn null_test(expr: Expression, negated: bool) -> Self {
+        Term {
+            lhs: expr,
+            rhs: Expression::Constant(Constant::Null),
+            op: if negated {
+                Operator::IsNotNull
+            } else {
+                Operator::IsNull
+            },
+        }
+    }
+
+    // Return true if the term holds for the current record
+    // of the specified scan.
+    // An equality involving a null is neither true nor false
+    // but unknown; since a predicate is a conjunction of terms,
+    // an unknown term fails the predicate just as a false one does.
+    pub fn is_satisfied(&self, s: &dyn Scan) -> DbResult<bool> {
+        let lhsval = self.lhs.evaluate(s)?;
+        match self.op {
+            Operator::IsNull => Ok(lhsval.is_null()),
+            Operator::IsNotNull => Ok(!lhsval.is_null()),
+            Operator::Equals => {
+                let rhsval = self.rhs.evaluate(s)?;
+                Ok(!lhsval.is_null() && lhsval == rhsval)
+            }
+        }
+    }
+
+    // Calculate the extent to which selecting on the term reduces
+    // the number of records output by a query.
+    // For example if the reduction factor is 2, then the
+    // term cuts the size of the output in half.
+    // A null test is estimated like an equality with a constant
+    // when it selects nulls, and as not reducing the output
+    // when it selects non-nulls.
+    pub fn reduction_factor(&self, p: &dyn Plan) -> u64 {
+        match self.op {
+            Operator::IsNull => {
+                return self.lhs.as_field_name().map_or(1, |f| p.distinct_values(f))
+            }
+            Operator::IsNotNull => return 1,
+            Operator::Equals => {}
+        }
+        match (&self.lhs, &self.rhs) {
+            (Expression::Field(l), Expression::Field(r)) => {
+                p.distinct_values(l).max(p.distinct_values(r))
+            }
+            (Expression::Field(f), _) | (_, Expression::Field(f)) => p.distinct_values(f),
+            // otherwise, the term equates constants
+            (Expression::Constant(l), Expression::Constant(r)) => {
+                if l == r && !l.is_null() {
+                    1
+                } else {
+                    u64::MAX
+                }
+            }
+        }
+    }
+
+    // Determine if this term is of the form "F=c"
+    // where F is the specified field and c is some constant.
+    // If so, the method returns that constant.
+    pub fn equates_with_constant(&self, fldname: &str) -> Option<&Constant> {
+        if self.op != Operator::Equals {
+            return None;
+        }
+        match (&self.lhs, &self.rhs) {
+            (Expression::Field(f), Expression::Constant(c))
+            | (Expression::Constant(c), Expression::Field(f))
+                if f == fldname =>
+            {
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    // Determine if this term is of the form "F1=F2"
+    // where F1 is the specified field and F2 is another field.
+    // If so, the method returns the name of that field.
+    pub fn equates_with_field(&self, fldname: &str) -> Option<&str> {
+        if self.op != Operator::Equals {
+            return None;
+        }
+        match (&self.lhs, &self.rhs) {
+            (Expression::Field(f1), Expression::Field(f2)) if f1 == fldname => Some(f2),
+            (Expression::Field(f1), Expression::Field(f2)) if f2 == fldname => Some(f1),
+            _ => None,
+        }
+    }
+
+    // Return true if both of the term's expressions
+    // apply to the specified schema.
+    pub fn applies_to(&self, sch: &Schema) -> bool {
+        self.lhs.applies_to(sch) && self.rhs.applies_to(sch)
+    }
+
+    pub fn lhs(&self) -> &Expression {
+        &self.lhs
+    }
+
+    pub fn rhs(&self) -> &Expression {
+        &self.rhs
+    }
+
+    pub fn operator(&self) -> Operator {
+        self.op
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.op {
+            Operator::Equals => write!(f, "{}={}", self.lhs, self.rhs),
+            Operator::IsNull => write!(f, "{} is null", self.lhs),
+            Operator::IsNotNull => write!(f, "{} is not null", self.lhs),
+        }
+    }
+}
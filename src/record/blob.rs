@@ -0,0 +1,255 @@
+use std::{io, sync::Arc};
+
+use crate::{error::DbResult, file::BlockId, tx::Transaction};
+
+const INT_SIZE: usize = std::mem::size_of::<i32>();
+
+// The data of a blob field is kept outside its record,
+// as a chain of overflow blocks in the table's blob file.
+// Each block holds the number of the next block of the chain
+// (or -1 for the last one), followed by a chunk of the data.
+// The record stores the number of the first block,
+// or -1 if the blob is empty.
+//
+// Overflow blocks are written once: storing a new value in a
+// blob field builds a new chain, and the blocks of the old value
+// are not reused. Since the blocks are new, writing them is not
+// logged; the record's pointer to the chain is.
+pub(crate) fn blob_file(tblfile: &str) -> String {
+    let tblname = tblfile.strip_suffix(".tbl").unwrap_or(tblfile);
+    format!("{}.blob", tblname)
+}
+
+// Streams the data of a blob, one overflow block at a time.
+pub struct BlobReader {
+    tx: Arc<Transaction>,
+    filename: String,
+    next: i32,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl BlobReader {
+    pub(crate) fn new(tx: Arc<Transaction>, filename: String, head: i32) -> Self {
+        BlobReader {
+            tx,
+            filename,
+            next: head,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    // Read the rest of the blob into memory.
+    pub fn read_all(mut self) -> DbResult<Vec<u8>> {
+        let mut data = self.chunk.split_off(self.pos);
+        while self.next_chunk()? {
+            data.append(&mut self.chunk);
+        }
+        Ok(data)
+    }
+
+    // Load the next block of the chain.
+    // Return false if there is none.
+    fn next_chunk(&mut self) -> DbResult<bool> {
+        if self.next < 0 {
+            return Ok(false);
+        }
+        let blk = BlockId::new(self.filename.clone(), self.next as u64);
+        self.tx.pin(&blk)?;
+        let result = self.tx.get_int(&blk, 0).and_then(|next| {
+            let chunk = self.tx.get_bytes(&blk, INT_SIZE)?;
+            Ok((next, chunk))
+        });
+        self.tx.unpin(&blk);
+        (self.next, self.chunk) = result?;
+        self.pos = 0;
+        Ok(true)
+    }
+}
+
+impl io::Read for BlobReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            if !self.next_chunk().map_err(io::Error::other)? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+// Writes a new blob to the end of a blob file.
+// The data is buffered until a block's worth has been written,
+// and then the block is appended to the chain.
+// The writer must be finished to obtain the first block of the chain.
+pub struct BlobWriter {
+    tx: Arc<Transaction>,
+    filename: String,
+    head: i32,
+    current: Option<BlockId>,
+    chunk: Vec<u8>,
+}
+
+impl BlobWriter {
+    pub(crate) fn new(tx: Arc<Transaction>, filename: String) -> Self {
+        BlobWriter {
+            tx,
+            filename,
+            head: -1,
+            current: None,
+            chunk: Vec::new(),
+        }
+    }
+
+    // Write the last block of the chain, and return
+    // the number of its first block, or -1 if nothing was written.
+    pub fn finish(mut self) -> DbResult<i32> {
+        if self.current.is_some() {
+            self.write_chunk(-1)?;
+        }
+        Ok(self.head)
+    }
+
+    // The number of data bytes that fit in one block.
+    fn capacity(&self) -> usize {
+        self.tx.block_size() - 2 * INT_SIZE
+    }
+
+    // Add the bytes to the blob, moving on to a new
+    // block whenever the current one is full.
+    fn append(&mut self, mut data: &[u8]) -> DbResult<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if self.current.is_none() {
+            let blk = self.tx.append(&self.filename)?;
+            self.head = blk.number() as i32;
+            self.current = Some(blk);
+        }
+        while !data.is_empty() {
+            if self.chunk.len() == self.capacity() {
+                let next = self.tx.append(&self.filename)?;
+                self.write_chunk(next.number() as i32)?;
+                self.current = Some(next);
+                self.chunk.clear();
+            }
+            let n = data.len().min(self.capacity() - self.chunk.len());
+            self.chunk.extend_from_slice(&data[..n]);
+            data = &data[n..];
+        }
+        Ok(())
+    }
+
+    // Write the buffered chunk into the current block,
+    // linking it to the specified next block.
+    fn write_chunk(&mut self, next: i32) -> DbResult<()> {
+        let blk = self.current.as_ref().expect("no current blob block");
+        self.tx.pin(blk)?;
+        let result = self
+            .tx
+            .set_int(blk, 0, next, false)
+            .and_then(|_| self.tx.set_bytes(blk, INT_SIZE, &self.chunk, false));
+        self.tx.unpin(blk);
+        result
+    }
+}
+
+impl io::Write for BlobWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.append(buf).map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    // The buffered chunk is written when the block is
+    // full or the writer is finished.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::SimpleDB,
+        query::{Constant, Scan, UpdateScan},
+        record::{Layout, Schema, TableScan},
+    };
+    use std::io::{Read, Write};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_blob_streams() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+
+        let mut sch = Schema::new();
+        sch.add_int_field("A");
+        sch.add_blob_field("B");
+        let layout = Layout::new(sch);
+
+        // A blob much larger than a block
+        let data: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+        let mut ts = TableScan::new(Arc::clone(&tx), "T", layout)?;
+        ts.insert()?;
+        ts.set_int("A", 1)?;
+        assert_eq!(ts.write_blob("B", &mut data.as_slice())?, 5000);
+        ts.insert()?;
+        ts.set_int("A", 2)?;
+        ts.set_val("B", &Constant::Blob(Vec::new()))?;
+        assert_eq!(tx.size("T.blob")?, 13);
+
+        ts.before_first()?;
+        assert!(ts.next()?);
+        let mut read = Vec::new();
+        let mut buf = [0; 100];
+        let mut reader = ts.blob_reader("B")?;
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            read.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(read, data);
+        assert!(ts.next()?);
+        assert_eq!(ts.get_val("B")?, Constant::Blob(Vec::new()));
+        ts.close();
+
+        // A blob written by hand
+        let mut writer = BlobWriter::new(Arc::clone(&tx), "T.blob".to_string());
+        writer.write_all(b"hello, ").unwrap();
+        writer.write_all(b"world").unwrap();
+        let head = writer.finish()?;
+        assert_eq!(head, 13);
+        let reader = BlobReader::new(Arc::clone(&tx), "T.blob".to_string(), head);
+        assert_eq!(reader.read_all()?, b"hello, world");
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob_sql() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int, b blob)")?;
+        db.execute_update("insert into t (a, b) values (1, x'cafe')")?;
+        db.execute_update("insert into t (a) values (2)")?;
+        let big = "ab".repeat(1000);
+        let sql = format!("update t set b = x'{}' where a = 2", big);
+        assert_eq!(db.execute_update(&sql)?, 1);
+
+        let rs = db.execute_query("select a from t where b = x'cafe'")?;
+        assert_eq!(rs.rows(), [[Constant::Int(1)]]);
+        let rs = db.execute_query("select b from t where a = 2")?;
+        assert_eq!(rs.rows(), [[Constant::Blob(vec![0xab; 1000])]]);
+        let result = db.execute_update("insert into t (a, b) values (3, 'cafe')");
+        assert!(result.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use super::{FieldType, Schema};
+use crate::file::Page;
+
+const INT_SIZE: usize = std::mem::size_of::<i32>();
+
+// Description of the structure of a record.
+// It contains the name, type, length and offset of
+// each field of the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    schema: Schema,
+    offsets: HashMap<String, usize>,
+    slot_size: usize,
+}
+
+impl Layout {
+    // This constructor creates a Layout object from a schema.
+    // It is used when a table is created.
+    // It determines the physical offset of each field
+    // within the record; the first four bytes of each
+    // slot hold its empty/in-use flag.
+    pub fn new(schema: Schema) -> Self {
+        let mut offsets = HashMap::new();
+        let mut pos = INT_SIZE;
+        for fldname in schema.fields() {
+            offsets.insert(fldname.clone(), pos);
+            pos += Self::length_in_bytes(&schema, fldname);
+        }
+
+        Layout {
+            schema,
+            offsets,
+            slot_size: pos,
+        }
+    }
+
+    // Create a Layout object from the specified metadata.
+    // This constructor is used when the metadata
+    // is retrieved from the catalog.
+    pub fn from_metadata(
+        schema: Schema,
+        offsets: HashMap<String, usize>,
+        slot_size: usize,
+    ) -> Self {
+        Layout {
+            schema,
+            offsets,
+            slot_size,
+        }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn offset(&self, fldname: &str) -> usize {
+        self.offsets[fldname]
+    }
+
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
+
+    fn length_in_bytes(schema: &Schema, fldname: &str) -> usize {
+        match schema.field_type(fldname) {
+            // a blob field holds the number of its first overflow block
+            FieldType::Integer | FieldType::Blob => INT_SIZE,
+            FieldType::Varchar => Page::max_length(schema.length(fldname)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_offsets() {
+        let mut sch = Schema::new();
+        sch.add_int_field("A");
+        sch.add_string_field("B", 9);
+        let layout = Layout::new(sch);
+
+        assert_eq!(layout.offset("A"), 4);
+        assert_eq!(layout.offset("B"), 8);
+        assert_eq!(layout.slot_size(), 8 + Page::max_length(9));
+    }
+}
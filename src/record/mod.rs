@@ -0,0 +1,15 @@
+mod blob;
+mod layout;
+mod record_page;
+mod rid;
+mod row;
+mod schema;
+mod table_scan;
+
+pub use blob::{BlobReader, BlobWriter};
+pub use layout::Layout;
+pub use record_page::RecordPage;
+pub use rid::Rid;
+pub use row::{from_scan, from_values, to_values};
+pub use schema::{FieldType, Schema};
+pub use table_scan::{Compaction, TableScan};
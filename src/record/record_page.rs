@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use super::{
+    blob::{blob_file, BlobReader, BlobWriter},
+    FieldType, Layout,
+};
+use crate::{
+    error::{DbError, DbResult},
+    file::BlockId,
+    tx::Transaction,
+};
+
+// Store a record at a given location in a block.
+// Each slot begins with a flag saying whether it is
+// empty or in use, followed by the fields of the record
+// at the offsets given by the layout.
+// The remaining bits of the flag are the slot's null bitmap:
+// bit i+1 is set when the i-th field of the schema is null.
+// A blob field holds the first block of the blob's data,
+// which is stored in the table's blob file (see blob.rs).
+pub struct RecordPage {
+    tx: Arc<Transaction>,
+    blk: BlockId,
+    layout: Layout,
+}
+
+impl RecordPage {
+    pub const EMPTY: i32 = 0;
+    pub const USED: i32 = 1;
+
+    // The number of fields that can be null,
+    // one per bit of the flag after the in-use bit.
+    pub const MAX_NULLABLE: usize = 31;
+
+    pub fn new(tx: Arc<Transaction>, blk: BlockId, layout: Layout) -> DbResult<Self> {
+        tx.pin(&blk)?;
+        Ok(RecordPage { tx, blk, layout })
+    }
+
+    // Return the integer value stored for the
+    // specified field of a specified slot.
+    pub fn get_int(&self, slot: i32, fldname: &str) -> DbResult<i32> {
+        let fldpos = self.offset(slot) + self.layout.offset(fldname);
+        self.tx.get_int(&self.blk, fldpos)
+    }
+
+    // Return the string value stored for the
+    // specified field of the specified slot.
+    pub fn get_string(&self, slot: i32, fldname: &str) -> DbResult<String> {
+        let fldpos = self.offset(slot) + self.layout.offset(fldname);
+        self.tx.get_string(&self.blk, fldpos)
+    }
+
+    // Return a reader over the blob stored in the
+    // specified field of the specified slot.
+    pub fn blob_reader(&self, slot: i32, fldname: &str) -> DbResult<BlobReader> {
+        let head = self.get_int(slot, fldname)?;
+        Ok(BlobReader::new(
+            Arc::clone(&self.tx),
+            blob_file(self.blk.filename()),
+            head,
+        ))
+    }
+
+    // Return a writer for a new blob in the table's blob file.
+    // The number returned by the finished writer is then
+    // stored in the blob field with set_int.
+    pub fn blob_writer(&self) -> BlobWriter {
+        BlobWriter::new(Arc::clone(&self.tx), blob_file(self.blk.filename()))
+    }
+
+    // Return true if the specified field of the
+    // specified slot is null.
+    pub fn is_null(&self, slot: i32, fldname: &str) -> DbResult<bool> {
+        match self.null_bit(fldname) {
+            Some(bit) => Ok(self.get_flag(slot)? & bit != 0),
+            None => Ok(false),
+        }
+    }
+
+    // Store an integer at the specified field
+    // of the specified slot, which is then not null.
+    pub fn set_int(&self, slot: i32, fldname: &str, val: i32) -> DbResult<()> {
+        let fldpos = self.offset(slot) + self.layout.offset(fldname);
+        self.tx.set_int(&self.blk, fldpos, val, true)?;
+        self.clear_null(slot, fldname)
+    }
+
+    // Replace the integer of the specified field of the
+    // specified slot as a nested top action of the
+    // transaction, and return the value replaced.
+    pub fn update_int_nested(
+        &self,
+        slot: i32,
+        fldname: &str,
+        f: impl FnOnce(i32) -> DbResult<i32>,
+    ) -> DbResult<i32> {
+        let fldpos = self.offset(slot) + self.layout.offset(fldname);
+        self.tx.update_int_nested(&self.blk, fldpos, f)
+    }
+
+    // Store a string at the specified field
+    // of the specified slot, which is then not null.
+    // A string too long for the field is rejected,
+    // since it would overwrite the field after it.
+    pub fn set_string(&self, slot: i32, fldname: &str, val: &str) -> DbResult<()> {
+        let max = self.layout.schema().length(fldname);
+        if val.len() > max {
+            return Err(DbError::ValueTooLong {
+                field: fldname.to_string(),
+                max,
+                len: val.len(),
+            });
+        }
+        let fldpos = self.offset(slot) + self.layout.offset(fldname);
+        self.tx.set_string(&self.blk, fldpos, val, true)?;
+        self.clear_null(slot, fldname)
+    }
+
+    // Mark the specified field of the specified slot as null.
+    // The field's value is reset to 0 or the empty string,
+    // which is what get_int and get_string return for a null;
+    // a blob field is reset to the empty blob.
+    pub fn set_null(&self, slot: i32, fldname: &str) -> DbResult<()> {
+        let bit = self
+            .null_bit(fldname)
+            .ok_or_else(|| DbError::Catalog(format!("field {} cannot be null", fldname)))?;
+        let fldpos = self.offset(slot) + self.layout.offset(fldname);
+        match self.layout.schema().field_type(fldname) {
+            FieldType::Integer => self.tx.set_int(&self.blk, fldpos, 0, true)?,
+            FieldType::Varchar => self.tx.set_string(&self.blk, fldpos, "", true)?,
+            FieldType::Blob => self.tx.set_int(&self.blk, fldpos, -1, true)?,
+        }
+        let flag = self.get_flag(slot)?;
+        if flag & bit == 0 {
+            self.set_flag(slot, flag | bit)?;
+        }
+        Ok(())
+    }
+
+    // Copy the record in the specified slot, nulls included,
+    // to a slot of another page of the same table.
+    // A blob is not copied: both records hold the same one.
+    pub fn copy_record(&self, slot: i32, dest: &RecordPage, dest_slot: i32) -> DbResult<()> {
+        let sch = self.layout.schema();
+        for fldname in sch.fields() {
+            let from = self.offset(slot) + self.layout.offset(fldname);
+            let to = dest.offset(dest_slot) + self.layout.offset(fldname);
+            match sch.field_type(fldname) {
+                FieldType::Integer | FieldType::Blob => {
+                    let val = self.tx.get_int(&self.blk, from)?;
+                    self.tx.set_int(&dest.blk, to, val, true)?;
+                }
+                FieldType::Varchar => {
+                    let val = self.tx.get_string(&self.blk, from)?;
+                    self.tx.set_string(&dest.blk, to, &val, true)?;
+                }
+            }
+        }
+        dest.set_flag(dest_slot, self.get_flag(slot)?)
+    }
+
+    pub fn delete(&self, slot: i32) -> DbResult<()> {
+        self.set_flag(slot, Self::EMPTY)
+    }
+
+    // Use the layout to format a new block of records.
+    // These values should not be logged
+    // (because the old values are meaningless).
+    pub fn format(&self) -> DbResult<()> {
+        let mut slot = 0;
+        while self.is_valid_slot(slot) {
+            self.tx
+                .set_int(&self.blk, self.offset(slot), Self::EMPTY, false)?;
+            let sch = self.layout.schema();
+            for fldname in sch.fields() {
+                let fldpos = self.offset(slot) + self.layout.offset(fldname);
+                match sch.field_type(fldname) {
+                    FieldType::Integer => self.tx.set_int(&self.blk, fldpos, 0, false)?,
+                    FieldType::Varchar => self.tx.set_string(&self.blk, fldpos, "", false)?,
+                    FieldType::Blob => self.tx.set_int(&self.blk, fldpos, -1, false)?,
+                }
+            }
+            slot += 1;
+        }
+        Ok(())
+    }
+
+    // Return the next used slot after the specified one,
+    // or -1 if there is none.
+    pub fn next_after(&self, slot: i32) -> DbResult<i32> {
+        self.search_after(slot, Self::USED)
+    }
+
+    // Find an empty slot after the specified one, mark it
+    // as used and return it, or return -1 if there is none.
+    pub fn insert_after(&self, slot: i32) -> DbResult<i32> {
+        let newslot = self.search_after(slot, Self::EMPTY)?;
+        if newslot >= 0 {
+            self.set_flag(newslot, Self::USED)?;
+        }
+        Ok(newslot)
+    }
+
+    pub fn block(&self) -> &BlockId {
+        &self.blk
+    }
+
+    // Unpin the page's block.
+    pub fn close(&self) {
+        self.tx.unpin(&self.blk);
+    }
+
+    fn get_flag(&self, slot: i32) -> DbResult<i32> {
+        self.tx.get_int(&self.blk, self.offset(slot))
+    }
+
+    fn set_flag(&self, slot: i32, flag: i32) -> DbResult<()> {
+        self.tx.set_int(&self.blk, self.offset(slot), flag, true)
+    }
+
+    fn clear_null(&self, slot: i32, fldname: &str) -> DbResult<()> {
+        if let Some(bit) = self.null_bit(fldname) {
+            let flag = self.get_flag(slot)?;
+            if flag & bit != 0 {
+                self.set_flag(slot, flag & !bit)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Return the bit of the null bitmap for the specified field,
+    // or None if the field is past the last nullable position.
+    fn null_bit(&self, fldname: &str) -> Option<i32> {
+        let pos = self
+            .layout
+            .schema()
+            .fields()
+            .iter()
+            .position(|f| f == fldname)?;
+        (pos < Self::MAX_NULLABLE).then(|| 1 << (pos + 1))
+    }
+
+    fn search_after(&self, slot: i32, flag: i32) -> DbResult<i32> {
+        let mut slot = slot + 1;
+        while self.is_valid_slot(slot) {
+            if self.get_flag(slot)? & Self::USED == flag {
+                return Ok(slot);
+            }
+            slot += 1;
+        }
+        Ok(-1)
+    }
+
+    fn is_valid_slot(&self, slot: i32) -> bool {
+        self.offset(slot + 1) <= self.tx.block_size()
+    }
+
+    fn offset(&self, slot: i32) -> usize {
+        slot as usize * self.layout.slot_size()
+    }
+}
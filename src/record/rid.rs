@@ -0,0 +1,28 @@
+// An identifier for a record within a file.
+// A RID consists of the block number in the file,
+// and the location of the record in that block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rid {
+    blknum: u64,
+    slot: i32,
+}
+
+impl Rid {
+    pub fn new(blknum: u64, slot: i32) -> Self {
+        Rid { blknum, slot }
+    }
+
+    pub fn block_number(&self) -> u64 {
+        self.blknum
+    }
+
+    pub fn slot(&self) -> i32 {
+        self.slot
+    }
+}
+
+impl std::fmt::Display for Rid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.blknum, self.slot)
+    }
+}
@@ -0,0 +1,736 @@
+use std::fmt::Display;
+
+use serde::{
+    de::{self, value::SeqDeserializer, DeserializeOwned, IntoDeserializer, MapAccess, Visitor},
+    forward_to_deserialize_any,
+    ser::{self, Impossible, Serialize, SerializeSeq, SerializeStruct},
+};
+
+use crate::{
+    error::{DbError, DbResult},
+    query::{Constant, Scan},
+};
+
+// Conversions between Rust structs and database records,
+// built on serde, so that rows can be read and written as
+// typed values instead of field by field.
+//
+// Each field of the struct maps to the field of the record
+// having the same name. The values map as follows:
+//   integers and bools <-> int (an integer must fit in an i32)
+//   String, &str and char <-> varchar
+//   Vec<u8> and byte buffers <-> blob
+//   Option<T> <-> null or the value of T
+//   unit enum variants <-> varchar holding the variant's name
+// Fields of the struct that the record lacks are not read,
+// so they must be optional or have a serde default.
+
+impl ser::Error for DbError {
+    fn custom<T: Display>(msg: T) -> Self {
+        DbError::Serde(msg.to_string())
+    }
+}
+
+impl de::Error for DbError {
+    fn custom<T: Display>(msg: T) -> Self {
+        DbError::Serde(msg.to_string())
+    }
+}
+
+// Convert a struct into the values of its fields, in order.
+pub fn to_values<T: Serialize + ?Sized>(row: &T) -> DbResult<Vec<(String, Constant)>> {
+    row.serialize(RowSerializer)
+}
+
+// Read the current record of the scan into a struct.
+pub fn from_scan<T: DeserializeOwned>(s: &dyn Scan) -> DbResult<T> {
+    T::deserialize(RowDeserializer {
+        get: |fldname: &str| {
+            if s.has_field(fldname) {
+                s.get_val(fldname).map(Some)
+            } else {
+                Ok(None)
+            }
+        },
+    })
+}
+
+// Read a row of values into a struct, where
+// columns gives the field name of each value.
+pub fn from_values<T: DeserializeOwned>(columns: &[String], row: &[Constant]) -> DbResult<T> {
+    T::deserialize(RowDeserializer {
+        get: |fldname: &str| {
+            let val = columns
+                .iter()
+                .position(|col| col == fldname)
+                .map(|i| row[i].clone());
+            Ok(val)
+        },
+    })
+}
+
+fn unsupported(what: &str) -> DbError {
+    DbError::Serde(format!("{} cannot be stored in a field", what))
+}
+
+// Serializes a struct into its field values.
+struct RowSerializer;
+
+struct RowFields(Vec<(String, Constant)>);
+
+impl ser::Serializer for RowSerializer {
+    type Ok = Vec<(String, Constant)>;
+    type Error = DbError;
+    type SerializeSeq = Impossible<Self::Ok, DbError>;
+    type SerializeTuple = Impossible<Self::Ok, DbError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, DbError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, DbError>;
+    type SerializeMap = Impossible<Self::Ok, DbError>;
+    type SerializeStruct = RowFields;
+    type SerializeStructVariant = Impossible<Self::Ok, DbError>;
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> DbResult<RowFields> {
+        Ok(RowFields(Vec::with_capacity(len)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> DbResult<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i8(self, _v: i8) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i16(self, _v: i16) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i32(self, _v: i32) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i64(self, _v: i64) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u8(self, _v: u8) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u16(self, _v: u16) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u32(self, _v: u32) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u64(self, _v: u64) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_f32(self, _v: f32) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_f64(self, _v: f64) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_char(self, _v: char) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_str(self, _v: &str) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_none(self) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_unit(self) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+    ) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> DbResult<Self::Ok> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> DbResult<Self::SerializeSeq> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> DbResult<Self::SerializeTuple> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> DbResult<Self::SerializeTupleStruct> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> DbResult<Self::SerializeTupleVariant> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> DbResult<Self::SerializeMap> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> DbResult<Self::SerializeStructVariant> {
+        Err(not_a_struct())
+    }
+}
+
+fn not_a_struct() -> DbError {
+    DbError::Serde("a row must be a struct".to_string())
+}
+
+impl SerializeStruct for RowFields {
+    type Ok = Vec<(String, Constant)>;
+    type Error = DbError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> DbResult<()> {
+        let val = value.serialize(ValueSerializer)?;
+        self.0.push((key.to_string(), val));
+        Ok(())
+    }
+
+    fn end(self) -> DbResult<Self::Ok> {
+        Ok(self.0)
+    }
+}
+
+// Serializes the value of a single field.
+struct ValueSerializer;
+
+impl ValueSerializer {
+    fn int(v: impl TryInto<i32>) -> DbResult<Constant> {
+        v.try_into()
+            .map(Constant::Int)
+            .map_err(|_| DbError::Serde("integer out of range".to_string()))
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Constant;
+    type Error = DbError;
+    type SerializeSeq = BytesSerializer;
+    type SerializeTuple = Impossible<Constant, DbError>;
+    type SerializeTupleStruct = Impossible<Constant, DbError>;
+    type SerializeTupleVariant = Impossible<Constant, DbError>;
+    type SerializeMap = Impossible<Constant, DbError>;
+    type SerializeStruct = Impossible<Constant, DbError>;
+    type SerializeStructVariant = Impossible<Constant, DbError>;
+
+    fn serialize_bool(self, v: bool) -> DbResult<Constant> {
+        Ok(Constant::Int(v as i32))
+    }
+
+    fn serialize_i8(self, v: i8) -> DbResult<Constant> {
+        Self::int(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> DbResult<Constant> {
+        Self::int(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> DbResult<Constant> {
+        Self::int(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> DbResult<Constant> {
+        Self::int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> DbResult<Constant> {
+        Self::int(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> DbResult<Constant> {
+        Self::int(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> DbResult<Constant> {
+        Self::int(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> DbResult<Constant> {
+        Self::int(v)
+    }
+
+    fn serialize_f32(self, _v: f32) -> DbResult<Constant> {
+        Err(unsupported("a float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> DbResult<Constant> {
+        Err(unsupported("a float"))
+    }
+
+    fn serialize_char(self, v: char) -> DbResult<Constant> {
+        Ok(Constant::Str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> DbResult<Constant> {
+        Ok(Constant::from(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> DbResult<Constant> {
+        Ok(Constant::Blob(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> DbResult<Constant> {
+        Ok(Constant::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> DbResult<Constant> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> DbResult<Constant> {
+        Err(unsupported("a unit value"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> DbResult<Constant> {
+        Err(unsupported("a unit struct"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> DbResult<Constant> {
+        Ok(Constant::from(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> DbResult<Constant> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> DbResult<Constant> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    // Only a sequence of bytes is supported, as a blob.
+    fn serialize_seq(self, len: Option<usize>) -> DbResult<BytesSerializer> {
+        Ok(BytesSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> DbResult<Self::SerializeTuple> {
+        Err(unsupported("a tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> DbResult<Self::SerializeTupleStruct> {
+        Err(unsupported("a tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> DbResult<Self::SerializeTupleVariant> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> DbResult<Self::SerializeMap> {
+        Err(unsupported("a map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> DbResult<Self::SerializeStruct> {
+        Err(unsupported("a nested struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> DbResult<Self::SerializeStructVariant> {
+        Err(unsupported("an enum variant with data"))
+    }
+}
+
+// Collects the elements of a Vec<u8> into a blob.
+struct BytesSerializer(Vec<u8>);
+
+impl SerializeSeq for BytesSerializer {
+    type Ok = Constant;
+    type Error = DbError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> DbResult<()> {
+        match value.serialize(ValueSerializer)? {
+            Constant::Int(b) if (0..=255).contains(&b) => {
+                self.0.push(b as u8);
+                Ok(())
+            }
+            _ => Err(unsupported("a sequence other than bytes")),
+        }
+    }
+
+    fn end(self) -> DbResult<Constant> {
+        Ok(Constant::Blob(self.0))
+    }
+}
+
+// Deserializes a struct from the values returned by get,
+// which returns None for a field that does not exist.
+struct RowDeserializer<F> {
+    get: F,
+}
+
+impl<'de, F> de::Deserializer<'de> for RowDeserializer<F>
+where
+    F: Fn(&str) -> DbResult<Option<Constant>>,
+{
+    type Error = DbError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> DbResult<V::Value> {
+        Err(DbError::Serde(
+            "a row can only be read into a struct".to_string(),
+        ))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> DbResult<V::Value> {
+        visitor.visit_map(RowFieldsAccess {
+            get: self.get,
+            fields: fields.iter(),
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+struct RowFieldsAccess<F> {
+    get: F,
+    fields: std::slice::Iter<'static, &'static str>,
+    value: Option<Constant>,
+}
+
+impl<'de, F> MapAccess<'de> for RowFieldsAccess<F>
+where
+    F: Fn(&str) -> DbResult<Option<Constant>>,
+{
+    type Error = DbError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> DbResult<Option<K::Value>> {
+        for fldname in self.fields.by_ref() {
+            if let Some(val) = (self.get)(fldname)? {
+                self.value = Some(val);
+                return seed.deserialize(fldname.into_deserializer()).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> DbResult<V::Value> {
+        let val = self.value.take().expect("value read before its key");
+        seed.deserialize(ValueDeserializer(val))
+    }
+}
+
+// Deserializes the value of a single field.
+struct ValueDeserializer(Constant);
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = DbError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> DbResult<V::Value> {
+        match self.0 {
+            Constant::Null => visitor.visit_none(),
+            Constant::Int(v) => visitor.visit_i32(v),
+            Constant::Str(v) => visitor.visit_string(v),
+            Constant::Blob(v) => visitor.visit_byte_buf(v),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> DbResult<V::Value> {
+        match self.0 {
+            Constant::Int(v) => visitor.visit_bool(v != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> DbResult<V::Value> {
+        match self.0 {
+            Constant::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    // A Vec<u8> is read from a blob one byte at a time.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> DbResult<V::Value> {
+        match self.0 {
+            Constant::Blob(v) => visitor.visit_seq(SeqDeserializer::new(v.into_iter())),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> DbResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> DbResult<V::Value> {
+        match self.0 {
+            Constant::Str(v) => visitor.visit_enum(v.into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::SimpleDB,
+        record::{Layout, Schema, TableScan},
+    };
+    use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Grade {
+        Pass,
+        Fail,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Student {
+        id: u16,
+        name: String,
+        active: bool,
+        grade: Grade,
+        email: Option<String>,
+        photo: Vec<u8>,
+    }
+
+    #[test]
+    fn test_values() -> DbResult<()> {
+        let s = Student {
+            id: 7,
+            name: "ann".to_string(),
+            active: true,
+            grade: Grade::Fail,
+            email: None,
+            photo: vec![1, 2],
+        };
+        let vals = to_values(&s)?;
+        assert_eq!(
+            vals,
+            [
+                ("id".to_string(), Constant::Int(7)),
+                ("name".to_string(), Constant::from("ann")),
+                ("active".to_string(), Constant::Int(1)),
+                ("grade".to_string(), Constant::from("Fail")),
+                ("email".to_string(), Constant::Null),
+                ("photo".to_string(), Constant::Blob(vec![1, 2])),
+            ]
+        );
+        let (columns, row): (Vec<String>, Vec<Constant>) = vals.into_iter().unzip();
+        assert_eq!(from_values::<Student>(&columns, &row)?, s);
+
+        #[derive(Serialize)]
+        struct Wide {
+            n: i64,
+        }
+        assert!(matches!(
+            to_values(&Wide { n: 1 << 40 }),
+            Err(DbError::Serde(_))
+        ));
+        assert!(matches!(to_values(&5), Err(DbError::Serde(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_rows() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+
+        let mut sch = Schema::new();
+        sch.add_int_field("id");
+        sch.add_string_field("name", 10);
+        sch.add_int_field("active");
+        sch.add_string_field("grade", 4);
+        sch.add_string_field("email", 20);
+        sch.add_blob_field("photo");
+        sch.add_int_field("extra");
+        let layout = Layout::new(sch);
+
+        let students: Vec<Student> = (0..20)
+            .map(|i| Student {
+                id: i,
+                name: format!("s{}", i),
+                active: i % 2 == 0,
+                grade: if i < 10 { Grade::Pass } else { Grade::Fail },
+                email: (i % 3 == 0).then(|| format!("s{}@x.org", i)),
+                photo: vec![i as u8; i as usize],
+            })
+            .collect();
+        let mut ts = TableScan::new(Arc::clone(&tx), "students", layout)?;
+        for s in &students {
+            ts.insert_row(s)?;
+        }
+
+        ts.before_first()?;
+        let mut read = Vec::new();
+        while ts.next()? {
+            // the fields the struct lacks are null
+            assert!(ts.get_val("extra")?.is_null());
+            read.push(ts.get_row::<Student>()?);
+        }
+        assert_eq!(read, students);
+
+        #[derive(Serialize)]
+        struct BadType {
+            name: i32,
+        }
+        #[derive(Serialize)]
+        struct BadField {
+            nosuchfield: i32,
+        }
+        assert!(matches!(
+            ts.insert_row(&BadType { name: 1 }),
+            Err(DbError::Catalog(_))
+        ));
+        assert!(matches!(
+            ts.insert_row(&BadField { nosuchfield: 1 }),
+            Err(DbError::Catalog(_))
+        ));
+        ts.close();
+        tx.commit()?;
+
+        // rows of a query result
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Total {
+            b: String,
+            countofa: i32,
+            maxofa: Option<i32>,
+        }
+        db.execute_update("create table t (a int, b varchar(5))")?;
+        for (a, b) in [("1", "x"), ("2", "x"), ("null", "y")] {
+            let sql = format!("insert into t (a, b) values ({}, '{}')", a, b);
+            db.execute_update(&sql)?;
+        }
+        let rs = db.execute_query("select b, count(a), max(a) from t group by b")?;
+        let mut totals = rs.rows_as::<Total>()?;
+        totals.sort_by(|t1, t2| t1.b.cmp(&t2.b));
+        assert_eq!(
+            totals,
+            [
+                Total {
+                    b: "x".to_string(),
+                    countofa: 2,
+                    maxofa: Some(2)
+                },
+                Total {
+                    b: "y".to_string(),
+                    countofa: 0,
+                    maxofa: None
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+// The type of a field, stored in the catalog
+// using the same codes as java.sql.Types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Integer = 4,
+    Varchar = 12,
+    Blob = 2004,
+}
+
+impl FieldType {
+    pub fn from_code(code: i32) -> Option<Self> {
+        match code {
+            4 => Some(FieldType::Integer),
+            12 => Some(FieldType::Varchar),
+            2004 => Some(FieldType::Blob),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FieldInfo {
+    field_type: FieldType,
+    length: usize,
+}
+
+// The record schema of a table.
+// A schema contains the name and type of
+// each field of the table, as well as the length
+// of each varchar field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    fields: Vec<String>,
+    info: HashMap<String, FieldInfo>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Add a field to the schema having a specified
+    // name, type, and length.
+    // If the field type is "integer", then the length
+    // value is irrelevant.
+    pub fn add_field(&mut self, fldname: &str, field_type: FieldType, length: usize) {
+        if !self.info.contains_key(fldname) {
+            self.fields.push(fldname.to_string());
+        }
+        self.info
+            .insert(fldname.to_string(), FieldInfo { field_type, length });
+    }
+
+    pub fn add_int_field(&mut self, fldname: &str) {
+        self.add_field(fldname, FieldType::Integer, 0);
+    }
+
+    // Add a string field to the schema.
+    // The length is the conceptual length of the field,
+    // counted in bytes of UTF-8.
+    // For example, if the field is defined as varchar(8),
+    // then its length is 8, which is 8 ASCII characters
+    // but only 4 two-byte characters such as 'é'.
+    pub fn add_string_field(&mut self, fldname: &str, length: usize) {
+        self.add_field(fldname, FieldType::Varchar, length);
+    }
+
+    // Add a blob field to the schema.
+    // Its data is kept outside the record, so it has no length.
+    pub fn add_blob_field(&mut self, fldname: &str) {
+        self.add_field(fldname, FieldType::Blob, 0);
+    }
+
+    // Add a field to the schema having the same
+    // type and length as the corresponding field
+    // in another schema.
+    pub fn add(&mut self, fldname: &str, sch: &Schema) {
+        let info = sch.info[fldname];
+        self.add_field(fldname, info.field_type, info.length);
+    }
+
+    // Add all of the fields in the specified schema
+    // to the current schema.
+    pub fn add_all(&mut self, sch: &Schema) {
+        for fldname in sch.fields() {
+            self.add(fldname, sch);
+        }
+    }
+
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    pub fn has_field(&self, fldname: &str) -> bool {
+        self.info.contains_key(fldname)
+    }
+
+    pub fn field_type(&self, fldname: &str) -> FieldType {
+        self.info[fldname].field_type
+    }
+
+    pub fn length(&self, fldname: &str) -> usize {
+        self.info[fldname].length
+    }
+}
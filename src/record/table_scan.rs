@@ -0,0 +1,475 @@
+use std::{io::Read, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{row, BlobReader, FieldType, Layout, RecordPage, Rid};
+use crate::{
+    error::{DbError, DbResult},
+    file::BlockId,
+    query::{Constant, Scan, UpdateScan},
+    tx::Transaction,
+};
+
+// What compacting a table did: the records moved, as the
+// old and new RID of each, and the blocks cut off its file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Compaction {
+    pub moved: Vec<(Rid, Rid)>,
+    pub freed: u64,
+}
+
+// Provides the abstraction of an arbitrarily large array
+// of records, stored in a file with one record page per block.
+pub struct TableScan {
+    tx: Arc<Transaction>,
+    layout: Layout,
+    rp: Option<RecordPage>,
+    filename: String,
+    current_slot: i32,
+}
+
+impl TableScan {
+    pub fn new(tx: Arc<Transaction>, tblname: &str, layout: Layout) -> DbResult<Self> {
+        let mut scan = TableScan {
+            tx,
+            layout,
+            rp: None,
+            filename: format!("{}.tbl", tblname),
+            current_slot: -1,
+        };
+
+        if scan.tx.size(&scan.filename)? == 0 {
+            scan.move_to_new_block()?;
+        } else {
+            scan.move_to_block(0)?;
+        }
+        Ok(scan)
+    }
+
+    fn record_page(&self) -> &RecordPage {
+        self.rp.as_ref().expect("table scan is closed")
+    }
+
+    fn move_to_block(&mut self, blknum: u64) -> DbResult<()> {
+        self.close();
+        let blk = BlockId::new(self.filename.clone(), blknum);
+        self.rp = Some(RecordPage::new(
+            Arc::clone(&self.tx),
+            blk,
+            self.layout.clone(),
+        )?);
+        self.current_slot = -1;
+        Ok(())
+    }
+
+    fn move_to_new_block(&mut self) -> DbResult<()> {
+        self.close();
+        let blk = self.tx.append(&self.filename)?;
+        let rp = RecordPage::new(Arc::clone(&self.tx), blk, self.layout.clone())?;
+        rp.format()?;
+        self.rp = Some(rp);
+        self.current_slot = -1;
+        Ok(())
+    }
+
+    fn at_last_block(&self) -> DbResult<bool> {
+        Ok(self.record_page().block().number() == self.tx.size(&self.filename)? - 1)
+    }
+
+    // Return a reader over the current record's
+    // value of the specified blob field.
+    pub fn blob_reader(&self, fldname: &str) -> DbResult<BlobReader> {
+        self.record_page().blob_reader(self.current_slot, fldname)
+    }
+
+    // Store the contents of src as the current record's
+    // value of the specified blob field, and return
+    // the number of bytes written.
+    pub fn write_blob(&mut self, fldname: &str, src: &mut impl Read) -> DbResult<u64> {
+        let rp = self.record_page();
+        let mut writer = rp.blob_writer();
+        let n = std::io::copy(src, &mut writer)?;
+        let head = writer.finish()?;
+        rp.set_int(self.current_slot, fldname, head)?;
+        Ok(n)
+    }
+
+    // Insert a new record holding the fields of the struct.
+    // The fields of the table that the struct lacks are null.
+    // Each value is checked against the table's schema
+    // before the record is inserted.
+    pub fn insert_row<T: Serialize + ?Sized>(&mut self, row: &T) -> DbResult<()> {
+        let vals = row::to_values(row)?;
+        let sch = self.layout.schema();
+        for (fldname, val) in &vals {
+            if !sch.has_field(fldname) {
+                return Err(DbError::Catalog(format!("field {} not found", fldname)));
+            }
+            let fldtype = sch.field_type(fldname);
+            let matches = match val {
+                Constant::Null => true,
+                Constant::Int(_) => fldtype == FieldType::Integer,
+                Constant::Str(_) => fldtype == FieldType::Varchar,
+                Constant::Blob(_) => fldtype == FieldType::Blob,
+            };
+            if !matches {
+                return Err(DbError::Catalog(format!(
+                    "type mismatch for field {}",
+                    fldname
+                )));
+            }
+            if let Constant::Str(s) = val {
+                let max = sch.length(fldname);
+                if s.len() > max {
+                    return Err(DbError::ValueTooLong {
+                        field: fldname.clone(),
+                        max,
+                        len: s.len(),
+                    });
+                }
+            }
+        }
+        self.insert()?;
+        for fldname in self.layout.schema().fields().to_vec() {
+            match vals.iter().find(|(f, _)| *f == fldname) {
+                Some((_, val)) => self.set_val(&fldname, val)?,
+                None => self.set_val(&fldname, &Constant::Null)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Move the records of the last blocks into the empty slots
+    // nearest the start of the table, until the blocks still
+    // holding records come first, and cut the file down to
+    // them when the transaction commits. Deleted records
+    // otherwise keep their space for good: an insert fills the
+    // empty slots it finds, but the file never shrinks.
+    // The file is cut rather than its empty blocks freed, since
+    // a scan would still insert into a free block, which a later
+    // append could then hand out again.
+    // The RIDs of the moved records change, so the indexes
+    // of the table must be updated with them; the scan is
+    // left before the first record.
+    pub fn compact(&mut self) -> DbResult<Compaction> {
+        let size = self.tx.size(&self.filename)?;
+        let page = |blknum| {
+            let blk = BlockId::new(self.filename.clone(), blknum);
+            RecordPage::new(Arc::clone(&self.tx), blk, self.layout.clone())
+        };
+        let mut moved = Vec::new();
+        let mut dest = page(0)?;
+        let mut dest_slot = -1;
+        let mut last = size - 1;
+        'blocks: while last > dest.block().number() {
+            let src = page(last)?;
+            let mut slot = src.next_after(-1)?;
+            while slot >= 0 {
+                dest_slot = dest.insert_after(dest_slot)?;
+                while dest_slot < 0 {
+                    let next = dest.block().number() + 1;
+                    if next == last {
+                        src.close();
+                        break 'blocks;
+                    }
+                    dest.close();
+                    dest = page(next)?;
+                    dest_slot = dest.insert_after(-1)?;
+                }
+                src.copy_record(slot, &dest, dest_slot)?;
+                src.delete(slot)?;
+                moved.push((
+                    Rid::new(last, slot),
+                    Rid::new(dest.block().number(), dest_slot),
+                ));
+                slot = src.next_after(slot)?;
+            }
+            src.close();
+            last -= 1;
+        }
+        dest.close();
+
+        // the table keeps a block even when it is empty
+        let freed = size - (last + 1);
+        if freed > 0 {
+            self.tx.truncate(&self.filename, last + 1)?;
+        }
+        self.move_to_block(0)?;
+        Ok(Compaction { moved, freed })
+    }
+
+    // Read the current record into a struct.
+    pub fn get_row<T: DeserializeOwned>(&self) -> DbResult<T> {
+        row::from_scan(self)
+    }
+}
+
+impl Scan for TableScan {
+    // Position the scan before the first record of the table.
+    fn before_first(&mut self) -> DbResult<()> {
+        self.move_to_block(0)
+    }
+
+    // Move to the next record, moving on to the following
+    // blocks as each one is exhausted.
+    // Returns false once there are no more records.
+    fn next(&mut self) -> DbResult<bool> {
+        self.current_slot = self.record_page().next_after(self.current_slot)?;
+        while self.current_slot < 0 {
+            if self.at_last_block()? {
+                return Ok(false);
+            }
+            let next = self.record_page().block().number() + 1;
+            self.move_to_block(next)?;
+            self.current_slot = self.record_page().next_after(self.current_slot)?;
+        }
+        Ok(true)
+    }
+
+    fn get_int(&self, fldname: &str) -> DbResult<i32> {
+        self.record_page().get_int(self.current_slot, fldname)
+    }
+
+    fn get_string(&self, fldname: &str) -> DbResult<String> {
+        self.record_page().get_string(self.current_slot, fldname)
+    }
+
+    fn get_val(&self, fldname: &str) -> DbResult<Constant> {
+        if self.record_page().is_null(self.current_slot, fldname)? {
+            return Ok(Constant::Null);
+        }
+        match self.layout.schema().field_type(fldname) {
+            FieldType::Integer => Ok(Constant::Int(self.get_int(fldname)?)),
+            FieldType::Varchar => Ok(Constant::Str(self.get_string(fldname)?)),
+            FieldType::Blob => Ok(Constant::Blob(self.blob_reader(fldname)?.read_all()?)),
+        }
+    }
+
+    fn has_field(&self, fldname: &str) -> bool {
+        self.layout.schema().has_field(fldname)
+    }
+
+    // Unpin the current block. The scan must not be used afterwards.
+    fn close(&mut self) {
+        if let Some(rp) = self.rp.take() {
+            rp.close();
+        }
+    }
+}
+
+impl UpdateScan for TableScan {
+    fn set_val(&mut self, fldname: &str, val: &Constant) -> DbResult<()> {
+        match val {
+            Constant::Null => self.record_page().set_null(self.current_slot, fldname),
+            Constant::Int(v) => self.set_int(fldname, *v),
+            Constant::Str(v) => self.set_string(fldname, v),
+            Constant::Blob(v) => self.write_blob(fldname, &mut v.as_slice()).map(|_| ()),
+        }
+    }
+
+    fn set_int(&mut self, fldname: &str, val: i32) -> DbResult<()> {
+        self.record_page().set_int(self.current_slot, fldname, val)
+    }
+
+    fn set_string(&mut self, fldname: &str, val: &str) -> DbResult<()> {
+        self.record_page()
+            .set_string(self.current_slot, fldname, val)
+    }
+
+    // Move to an empty slot and mark it as in use,
+    // appending a new block to the file if every
+    // existing block is full.
+    fn insert(&mut self) -> DbResult<()> {
+        self.current_slot = self.record_page().insert_after(self.current_slot)?;
+        while self.current_slot < 0 {
+            if self.at_last_block()? {
+                self.move_to_new_block()?;
+            } else {
+                let next = self.record_page().block().number() + 1;
+                self.move_to_block(next)?;
+            }
+            self.current_slot = self.record_page().insert_after(self.current_slot)?;
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self) -> DbResult<()> {
+        self.record_page().delete(self.current_slot)
+    }
+
+    fn get_rid(&self) -> Rid {
+        Rid::new(self.record_page().block().number(), self.current_slot)
+    }
+
+    fn move_to_rid(&mut self, rid: Rid) -> DbResult<()> {
+        self.close();
+        let blk = BlockId::new(self.filename.clone(), rid.block_number());
+        self.rp = Some(RecordPage::new(
+            Arc::clone(&self.tx),
+            blk,
+            self.layout.clone(),
+        )?);
+        self.current_slot = rid.slot();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, record::Schema};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_insert_scan_and_delete() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+
+        let mut sch = Schema::new();
+        sch.add_int_field("A");
+        sch.add_string_field("B", 9);
+        let layout = Layout::new(sch);
+
+        // Enough records to spill over several blocks
+        let mut ts = TableScan::new(Arc::clone(&tx), "T", layout.clone())?;
+        for n in 0..50 {
+            ts.insert()?;
+            ts.set_int("A", n)?;
+            ts.set_string("B", &format!("rec{}", n))?;
+        }
+        assert!(tx.size("T.tbl")? > 1);
+
+        // Delete the records whose A-value is less than 25
+        ts.before_first()?;
+        let mut deleted = 0;
+        while ts.next()? {
+            if ts.get_int("A")? < 25 {
+                deleted += 1;
+                ts.delete()?;
+            }
+        }
+        assert_eq!(deleted, 25);
+
+        ts.before_first()?;
+        let mut remaining = Vec::new();
+        while ts.next()? {
+            let a = ts.get_int("A")?;
+            assert_eq!(ts.get_val("B")?, Constant::Str(format!("rec{}", a)));
+            remaining.push(a);
+        }
+        assert_eq!(remaining, (25..50).collect::<Vec<_>>());
+        ts.close();
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_values() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+
+        let mut sch = Schema::new();
+        sch.add_int_field("A");
+        sch.add_string_field("B", 9);
+        let layout = Layout::new(sch);
+
+        let mut ts = TableScan::new(Arc::clone(&tx), "T", layout)?;
+        for n in 0..10 {
+            ts.insert()?;
+            let a = if n % 2 == 0 {
+                Constant::Null
+            } else {
+                Constant::Int(n)
+            };
+            ts.set_val("A", &a)?;
+            ts.set_val("B", &Constant::Null)?;
+        }
+
+        // Overwriting a null clears it
+        ts.before_first()?;
+        let mut vals = Vec::new();
+        while ts.next()? {
+            vals.push(ts.get_val("A")?);
+            assert!(ts.get_val("B")?.is_null());
+            ts.set_string("B", "set")?;
+        }
+        assert_eq!(vals.iter().filter(|v| v.is_null()).count(), 5);
+        assert!(vals.contains(&Constant::Int(3)));
+
+        ts.before_first()?;
+        while ts.next()? {
+            assert_eq!(ts.get_val("B")?, Constant::from("set"));
+        }
+        ts.close();
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compact() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        let mut sch = Schema::new();
+        sch.add_int_field("A");
+        sch.add_string_field("B", 9);
+        let layout = Layout::new(sch);
+
+        let mut ts = TableScan::new(Arc::clone(&tx), "T", layout.clone())?;
+        for n in 0..50 {
+            ts.insert()?;
+            ts.set_int("A", n)?;
+            if n % 5 == 0 {
+                ts.set_val("B", &Constant::Null)?;
+            } else {
+                ts.set_string("B", &format!("rec{}", n))?;
+            }
+        }
+        let size = tx.size("T.tbl")?;
+        ts.before_first()?;
+        while ts.next()? {
+            if ts.get_int("A")? < 40 {
+                ts.delete()?;
+            }
+        }
+        let compaction = ts.compact()?;
+        assert_eq!(compaction.moved.len(), 10);
+        assert!(compaction
+            .moved
+            .iter()
+            .all(|(from, to)| to.block_number() < from.block_number()));
+        assert_eq!(compaction.freed, size - 1);
+
+        let mut remaining = Vec::new();
+        while ts.next()? {
+            assert_eq!(ts.get_rid().block_number(), 0);
+            let a = ts.get_int("A")?;
+            let b = if a % 5 == 0 {
+                Constant::Null
+            } else {
+                Constant::Str(format!("rec{}", a))
+            };
+            assert_eq!(ts.get_val("B")?, b);
+            remaining.push(a);
+        }
+        assert_eq!(remaining, (40..50).collect::<Vec<_>>());
+        ts.close();
+
+        // the file is cut once the transaction commits
+        assert_eq!(db.file_manager().length("T.tbl")?, size);
+        tx.commit()?;
+        assert_eq!(db.file_manager().length("T.tbl")?, 1);
+
+        // a compact table is left as it is
+        let tx = db.new_tx()?;
+        let mut ts = TableScan::new(Arc::clone(&tx), "T", layout)?;
+        assert_eq!(ts.compact()?, Compaction::default());
+        ts.close();
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    buffer::{BufferManager, BufferPage, PinnedBuffer},
+    error::DbResult,
+    file::BlockId,
+};
+
+// Manage the transaction's currently-pinned buffers.
+// A block can be pinned several times, and stays pinned
+// until it has been unpinned as many times.
+// Each pin is held by a guard of the buffer manager, so
+// whatever is still pinned is unpinned when the list is
+// cleared or dropped.
+pub struct BufferList {
+    bm: Arc<BufferManager>,
+    // the guards of the pins of each block
+    buffers: HashMap<BlockId, Vec<PinnedBuffer>>,
+}
+
+impl BufferList {
+    pub fn new(bm: Arc<BufferManager>) -> Self {
+        BufferList {
+            bm,
+            buffers: HashMap::new(),
+        }
+    }
+
+    // Return the buffer pinned to the specified block,
+    // or None if the transaction has not pinned it.
+    pub fn buffer(&self, blk: &BlockId) -> Option<&Arc<Mutex<BufferPage>>> {
+        self.buffers
+            .get(blk)
+            .and_then(|pins| pins.first())
+            .map(|buff| buff.buffer())
+    }
+
+    // The number of times the block is pinned.
+    pub fn pin_count(&self, blk: &BlockId) -> usize {
+        self.buffers.get(blk).map_or(0, Vec::len)
+    }
+
+    // Pin the block and keep track of the buffer.
+    pub fn pin(&mut self, blk: &BlockId) -> DbResult<()> {
+        let buff = self.bm.pin(blk.clone())?;
+        self.buffers.entry(blk.clone()).or_default().push(buff);
+        Ok(())
+    }
+
+    // Pin all of the blocks, or none of them.
+    pub fn pin_all(&mut self, blks: &[BlockId]) -> DbResult<()> {
+        let buffs = self.bm.pin_all(blks)?;
+        for (blk, buff) in blks.iter().zip(buffs) {
+            self.buffers.entry(blk.clone()).or_default().push(buff);
+        }
+        Ok(())
+    }
+
+    // Unpin the block once. Returns the number of pins
+    // of the block that are left.
+    pub fn unpin(&mut self, blk: &BlockId) -> usize {
+        let Some(pins) = self.buffers.get_mut(blk) else {
+            return 0;
+        };
+        pins.pop();
+        let left = pins.len();
+        if left == 0 {
+            self.buffers.remove(blk);
+        }
+        left
+    }
+
+    // Unpin any buffers still pinned by this transaction.
+    pub fn unpin_all(&mut self) {
+        self.buffers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SimpleDB;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pin_counts() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk1 = db.file_manager().append("testfile")?;
+        let blk2 = db.file_manager().append("testfile")?;
+        let bm = Arc::clone(db.buffer_manager());
+
+        let mut buffers = BufferList::new(Arc::clone(&bm));
+        buffers.pin(&blk1)?;
+        buffers.pin(&blk1)?;
+        buffers.pin_all(&[blk1.clone(), blk2.clone()])?;
+        assert_eq!(buffers.pin_count(&blk1), 3);
+        assert_eq!(buffers.pin_count(&blk2), 1);
+        assert_eq!(bm.available(), 6);
+
+        assert_eq!(buffers.unpin(&blk1), 2);
+        assert_eq!(buffers.unpin(&blk2), 0);
+        assert!(buffers.buffer(&blk1).is_some());
+        assert!(buffers.buffer(&blk2).is_none());
+        assert_eq!(buffers.unpin(&blk2), 0);
+        assert_eq!(bm.available(), 7);
+
+        buffers.unpin_all();
+        assert_eq!(buffers.pin_count(&blk1), 0);
+        assert_eq!(bm.available(), 8);
+
+        // dropping the list unpins what it holds
+        let mut buffers = BufferList::new(Arc::clone(&bm));
+        buffers.pin(&blk2)?;
+        drop(buffers);
+        assert_eq!(bm.available(), 8);
+        Ok(())
+    }
+}
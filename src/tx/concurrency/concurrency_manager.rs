@@ -1,40 +1,150 @@
-use std::{collections::HashMap, sync::Mutex};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use super::{lock_table::LockAbortError, LockTable};
 use crate::file::BlockId;
 
+// How much a transaction is isolated from the others
+// running at the same time, from least to most.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IsolationLevel {
+    // Reads take no locks, and so can see modifications
+    // that are not committed.
+    ReadUncommitted,
+    // A read waits for the modifications of the block
+    // to be committed, but does not keep its SLock, so that
+    // reading the block again can return another value.
+    ReadCommitted,
+    // SLocks are kept until the transaction completes,
+    // but the end of a file is not locked, so that another
+    // transaction can append records (phantoms) to it.
+    RepeatableRead,
+    // Reads take no locks, and see the database as it was
+    // when the transaction started (see Snapshot).
+    // A block modified by another transaction since then
+    // cannot be modified.
+    Snapshot,
+    // SLocks are kept until the transaction completes,
+    // including the one on the end of a file.
+    #[default]
+    Serializable,
+}
+
 // The concurrency manager for the transaction.
 // Each transaction has its own concurrency manager.
 // The concurrency manager keeps track of which locks the
 // transaction currently has, and interacts with the
-// global lock table as needed.
+// global lock table as needed. The lock table is shared
+// by the concurrency managers of all the transactions
+// on a database, so that their locks conflict.
+// Which SLocks are taken, and how long they are kept,
+// depend on the isolation level of the transaction.
 pub struct ConcurrencyManager {
-    lock_table: LockTable,
+    lock_table: Arc<LockTable>,
+    txnum: i32,
+    isolation: IsolationLevel,
+    timeout: Mutex<Option<Duration>>,
     locks: Mutex<HashMap<BlockId, String>>,
 }
 
 impl ConcurrencyManager {
-    pub fn new() -> Self {
+    pub fn new(lock_table: Arc<LockTable>, txnum: i32) -> Self {
+        Self::with_isolation(lock_table, txnum, IsolationLevel::default())
+    }
+
+    pub fn with_isolation(
+        lock_table: Arc<LockTable>,
+        txnum: i32,
+        isolation: IsolationLevel,
+    ) -> Self {
         ConcurrencyManager {
-            lock_table: LockTable::new(),
+            lock_table,
+            txnum,
+            isolation,
+            timeout: Mutex::new(None),
             locks: Mutex::new(HashMap::new()),
         }
     }
 
+    pub fn isolation(&self) -> IsolationLevel {
+        self.isolation
+    }
+
+    // Set how long a lock request of the transaction waits,
+    // overriding the timeout of the lock table.
+    pub fn set_timeout(&self, timeout: Duration) {
+        *self.timeout.lock().unwrap() = Some(timeout);
+    }
+
     // Obtain an SLock on the block, if necessary.
     // The method will ask the lock table for an SLock
-    // if the transaction currently has no locks on that block.
+    // if the transaction currently has no locks on that block,
+    // unless the transaction reads without locks.
     pub fn slock(&self, blk: BlockId) -> Result<(), LockAbortError> {
+        if matches!(
+            self.isolation,
+            IsolationLevel::ReadUncommitted | IsolationLevel::Snapshot
+        ) {
+            return Ok(());
+        }
         let mut locks = self.locks.lock().unwrap();
 
         if locks.get(&blk).is_none() {
-            self.lock_table.slock(blk.clone())?;
+            self.lock_table
+                .slock(blk.clone(), self.txnum, self.timeout())?;
             locks.insert(blk, "S".into());
         }
 
         Ok(())
     }
 
+    // Obtain an SLock on the dummy block standing for the
+    // end of a file, which keeps other transactions from
+    // appending to the file. Only a serializable transaction
+    // needs it, as the others may see phantoms.
+    pub fn slock_end_of_file(&self, blk: BlockId) -> Result<(), LockAbortError> {
+        if self.isolation < IsolationLevel::Serializable {
+            return Ok(());
+        }
+        self.slock(blk)
+    }
+
+    // Obtain an SLock on the dummy block standing for a whole
+    // file, before modifying one of its blocks, whatever the
+    // isolation level, so that no other transaction can
+    // truncate the file meanwhile.
+    pub fn slock_file(&self, blk: BlockId) -> Result<(), LockAbortError> {
+        let mut locks = self.locks.lock().unwrap();
+
+        if let Entry::Vacant(entry) = locks.entry(blk) {
+            self.lock_table
+                .slock(entry.key().clone(), self.txnum, self.timeout())?;
+            entry.insert("S".into());
+        }
+
+        Ok(())
+    }
+
+    // Release the SLock on the block once it has been read,
+    // if the transaction does not keep its SLocks until it
+    // completes. An XLock on the block is kept.
+    pub fn end_read(&self, blk: &BlockId) {
+        if self.isolation != IsolationLevel::ReadCommitted {
+            return;
+        }
+        let mut locks = self.locks.lock().unwrap();
+        if locks
+            .get(blk)
+            .is_some_and(|locktype| locktype.as_str() == "S")
+        {
+            locks.remove(blk);
+            self.lock_table.unlock(blk.clone(), self.txnum);
+        }
+    }
+
     // Obtain an XLock on the block, if necessary.
     // If the transaction does not have an XLock on that block,
     // then the method first gets an SLock on that block
@@ -42,9 +152,13 @@ impl ConcurrencyManager {
     pub fn xlock(&self, blk: BlockId) -> Result<(), LockAbortError> {
         let mut locks = self.locks.lock().unwrap();
 
-        if !self.has_xlock(&blk) {
-            self.slock(blk.clone())?;
-            self.lock_table.x_lock(&blk)?;
+        if !Self::has_xlock(&locks, &blk) {
+            if !locks.contains_key(&blk) {
+                self.lock_table
+                    .slock(blk.clone(), self.txnum, self.timeout())?;
+                locks.insert(blk.clone(), "S".into());
+            }
+            self.lock_table.x_lock(&blk, self.txnum, self.timeout())?;
             locks.insert(blk, "X".into());
         }
 
@@ -59,22 +173,38 @@ impl ConcurrencyManager {
         let keys: Vec<_> = locks.keys().cloned().collect();
 
         for blk in keys {
-            self.lock_table.unlock(blk);
+            self.lock_table.unlock(blk, self.txnum);
         }
 
         locks.clear();
+        self.lock_table.release(self.txnum);
+    }
+
+    // Make the lock request the transaction is waiting on,
+    // if any, and those it makes until it releases its locks,
+    // fail rather than wait (see LockTable::cancel).
+    pub fn cancel(&self) {
+        self.lock_table.cancel(self.txnum);
     }
 
-    fn has_xlock(&self, blk: &BlockId) -> bool {
-        let locks = self.locks.lock().unwrap();
+    // Return the locks held by every transaction,
+    // from the shared lock table.
+    pub fn held_locks(&self) -> Vec<(BlockId, i32, &'static str)> {
+        self.lock_table.held_locks()
+    }
+
+    // The number of blocks the transaction holds locks on.
+    pub fn lock_count(&self) -> usize {
+        self.locks.lock().unwrap().len()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        *self.timeout.lock().unwrap()
+    }
+
+    fn has_xlock(locks: &HashMap<BlockId, String>, blk: &BlockId) -> bool {
         locks
             .get(blk)
             .is_some_and(|locktype| locktype.as_str() == "X")
     }
 }
-
-impl Default for ConcurrencyManager {
-    fn default() -> Self {
-        Self::new()
-    }
-}
@@ -1,24 +1,113 @@
-use std::collections::HashMap;
-use std::sync::{Condvar, Mutex, MutexGuard};
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 use std::time::{Duration, Instant};
 
 use crate::file::BlockId;
 
-#[derive(Debug)]
-pub struct LockAbortError;
+// The reason a lock request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockAbortError {
+    // The lock was not granted within the wait timeout.
+    Timeout(BlockId),
+    // The transaction was refused the lock rather than
+    // made to wait, as the wait-die policy requires.
+    Died(BlockId),
+    // The transaction asked to upgrade its SLock while another
+    // holder of an SLock was waiting to upgrade its own, so
+    // that each would wait for the other.
+    UpgradeConflict(BlockId),
+    // The transaction was aborted while it was waiting.
+    Cancelled(i32),
+}
 
 impl std::fmt::Display for LockAbortError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Lock acquisition aborted due to timeout")
+        match self {
+            LockAbortError::Timeout(blk) => write!(f, "Lock wait on block {} timed out", blk),
+            LockAbortError::Died(blk) => {
+                write!(f, "Lock on block {} is held by an older transaction", blk)
+            }
+            LockAbortError::UpgradeConflict(blk) => {
+                write!(f, "Lock on block {} is already being upgraded", blk)
+            }
+            LockAbortError::Cancelled(txnum) => {
+                write!(
+                    f,
+                    "Transaction {} was aborted while waiting for a lock",
+                    txnum
+                )
+            }
+        }
     }
 }
 
 impl std::error::Error for LockAbortError {}
 
+// How the lock table keeps transactions from waiting
+// on each other forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeadlockPolicy {
+    // A transaction waits for a conflicting lock until
+    // the lock is released or the wait times out.
+    #[default]
+    Timeout,
+    // A transaction waits for a conflicting lock only if
+    // it is older than every transaction holding the lock,
+    // and aborts at once otherwise. Transaction numbers
+    // serve as timestamps: the smaller, the older.
+    // Waits can then never form a cycle, so there is
+    // no timeout.
+    WaitDie,
+}
+
+// The transactions holding locks on a block: either
+// any number of transactions hold an SLock, or one
+// transaction holds an XLock; and the wait list of the
+// transactions waiting for them.
+// A transaction holding an XLock may hold an SLock too,
+// from before it upgraded.
+#[derive(Debug, Default)]
+struct Holders {
+    shared: Vec<i32>,
+    exclusive: Option<i32>,
+    // the holder of an SLock waiting to upgrade it
+    upgrader: Option<i32>,
+    waiters: usize,
+    cond_var: Arc<Condvar>,
+}
+
+impl Holders {
+    fn is_unused(&self) -> bool {
+        self.shared.is_empty() && self.exclusive.is_none() && self.waiters == 0
+    }
+}
+
+// The contention on the locks of a block, as recorded by
+// the lock table since it was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LockStats {
+    // The number of lock requests that had to wait.
+    pub waits: u64,
+    // The total time those requests spent waiting.
+    pub wait_time: Duration,
+    // The number of lock requests that were refused.
+    pub aborts: u64,
+}
+
+// The locks are partitioned into shards, each block's
+// locks belonging to the shard picked by its hash, so that
+// requests for blocks of different shards do not wait
+// for each other's access to the table.
 pub struct LockTable {
-    locks: Mutex<HashMap<BlockId, i32>>,
-    cond_var: Condvar,
-    max_time: Duration,
+    shards: Vec<Mutex<HashMap<BlockId, Holders>>>,
+    // the contention on the blocks of each shard
+    stats: Vec<Mutex<HashMap<BlockId, LockStats>>>,
+    // the transactions aborted by another thread, whose
+    // lock requests are refused instead of waiting
+    cancelled: Mutex<HashSet<i32>>,
+    timeout: Duration,
+    policy: DeadlockPolicy,
 }
 
 impl Default for LockTable {
@@ -30,97 +119,624 @@ impl Default for LockTable {
 // The lock table, which provides methods to lock and unlock blocks.
 // If a transaction requests a lock that causes a conflict with an
 // existing lock, then that transaction is placed on a wait list.
-// There is only one wait list for all blocks.
-// When the last lock on a block is unlocked, then all transactions
-// are removed from the wait list and rescheduled.
+// Each locked block has its own wait list, so that unlocking
+// a block only wakes the transactions waiting for it.
+// When a lock on a block is unlocked, then all transactions
+// are removed from the block's wait list and rescheduled.
 // If one of those transactions discovers that the lock it is waiting for
 // is still locked, it will place itself back on the wait list.
+// The table records which transactions hold each lock, so that
+// a transaction's own locks never conflict with its requests.
+// An XLock requested by a holder of an SLock upgrades it: the
+// request waits for the other readers only, and new readers
+// wait behind it, so that it is not starved. Only one upgrade
+// of a block can wait at a time, since two would wait for
+// each other's SLocks forever; the second is refused.
 impl LockTable {
+    // How long a lock request waits, unless the table or
+    // the transaction sets another timeout.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    // The number of shards of a lock table by default.
+    pub const DEFAULT_SHARDS: usize = 16;
+
     pub fn new() -> Self {
+        Self::with_policy(DeadlockPolicy::Timeout)
+    }
+
+    pub fn with_policy(policy: DeadlockPolicy) -> Self {
         LockTable {
-            locks: Mutex::new(HashMap::new()),
-            cond_var: Condvar::new(),
-            max_time: Duration::from_secs(10),
+            shards: Self::new_shards(Self::DEFAULT_SHARDS),
+            stats: Self::new_shards(Self::DEFAULT_SHARDS),
+            cancelled: Mutex::new(HashSet::new()),
+            timeout: Self::DEFAULT_TIMEOUT,
+            policy,
         }
     }
 
-    // Grant an SLock on the specified block
-    // If an XLock exists when the method is called,
-    // then the calling thread will be placed on a wait list
-    // until the lock is released.
-    // If the thread remains on the wait list for a certain
-    // amount of time (currently 10 seconds),
-    // then an exception is thrown.
-    pub fn slock(&self, blk: BlockId) -> Result<(), LockAbortError> {
-        let start_time = Instant::now();
-        let mut locks = self.locks.lock().unwrap();
+    // Set how long a lock request waits by default,
+    // under the timeout policy.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // Partition the table into the specified number of shards.
+    pub fn with_shards(mut self, num_shards: usize) -> Self {
+        assert!(num_shards > 0, "a lock table needs a shard");
+        self.shards = Self::new_shards(num_shards);
+        self.stats = Self::new_shards(num_shards);
+        self
+    }
 
-        while self.has_xlock(&locks, &blk) && !self.waiting_too_long(start_time) {
-            let result = self.cond_var.wait_timeout(locks, self.max_time).unwrap();
-            locks = result.0;
+    fn new_shards<T>(num_shards: usize) -> Vec<Mutex<HashMap<BlockId, T>>> {
+        (0..num_shards)
+            .map(|_| Mutex::new(HashMap::new()))
+            .collect()
+    }
+
+    // The index of the shard holding the locks of the block.
+    fn shard_index(&self, blk: &BlockId) -> usize {
+        let mut hasher = DefaultHasher::new();
+        blk.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+
+    // The shard holding the locks of the block.
+    fn shard(&self, blk: &BlockId) -> &Mutex<HashMap<BlockId, Holders>> {
+        &self.shards[self.shard_index(blk)]
+    }
+
+    // Return the contention on every block whose lock requests
+    // have waited or been refused, the longest waits first,
+    // so that the hot blocks of a workload can be found.
+    pub fn lock_stats(&self) -> Vec<(BlockId, LockStats)> {
+        let mut stats: Vec<_> = self
+            .stats
+            .iter()
+            .flat_map(|shard| {
+                let shard = shard.lock().unwrap();
+                shard
+                    .iter()
+                    .map(|(blk, stats)| (blk.clone(), *stats))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        stats.sort_by_key(|(_, stats)| std::cmp::Reverse((stats.wait_time, stats.aborts)));
+        stats
+    }
+
+    // Return the contention on the block.
+    pub fn block_stats(&self, blk: &BlockId) -> LockStats {
+        let stats = self.stats[self.shard_index(blk)].lock().unwrap();
+        stats.get(blk).copied().unwrap_or_default()
+    }
+
+    // Forget the contention recorded so far.
+    pub fn reset_lock_stats(&self) {
+        for shard in &self.stats {
+            shard.lock().unwrap().clear();
         }
+    }
 
-        if self.has_xlock(&locks, &blk) {
-            return Err(LockAbortError);
+    // Record a lock request on the block that waited for the
+    // specified time, if any, and was refused if aborted.
+    fn record(&self, blk: &BlockId, waited: Option<Duration>, aborted: bool) {
+        if waited.is_none() && !aborted {
+            return;
         }
+        let mut stats = self.stats[self.shard_index(blk)].lock().unwrap();
+        let stats = stats.entry(blk.clone()).or_default();
+        if let Some(wait_time) = waited {
+            stats.waits += 1;
+            stats.wait_time += wait_time;
+        }
+        if aborted {
+            stats.aborts += 1;
+        }
+    }
 
-        let val = self.get_lock_value(&locks, &blk);
-        locks.insert(blk.clone(), val + 1);
+    // Return every lock that is held, as its block, the
+    // holding transaction, and "S" or "X", ordered by block.
+    // A transaction holding both locks of a block, having
+    // upgraded its SLock, is listed with its XLock only.
+    pub fn held_locks(&self) -> Vec<(BlockId, i32, &'static str)> {
+        let mut locks: Vec<_> = self
+            .shards
+            .iter()
+            .flat_map(|shard| {
+                let shard = shard.lock().unwrap();
+                let mut locks = Vec::new();
+                for (blk, holders) in shard.iter() {
+                    if let Some(txnum) = holders.exclusive {
+                        locks.push((blk.clone(), txnum, "X"));
+                    }
+                    for &txnum in &holders.shared {
+                        if holders.exclusive != Some(txnum) {
+                            locks.push((blk.clone(), txnum, "S"));
+                        }
+                    }
+                }
+                locks
+            })
+            .collect();
+        locks.sort_by(|(b1, t1, _), (b2, t2, _)| {
+            (b1.filename(), b1.number(), t1).cmp(&(b2.filename(), b2.number(), t2))
+        });
+        locks
+    }
 
-        Ok(())
+    // Return true if no transaction holds or waits for a lock.
+    pub fn is_empty(&self) -> bool {
+        self.shards
+            .iter()
+            .all(|shard| shard.lock().unwrap().is_empty())
     }
 
-    // Grant an XLock on the specified block.
-    // If a lock of any type exists when the method is called,
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn policy(&self) -> DeadlockPolicy {
+        self.policy
+    }
+
+    // Grant an SLock on the specified block to the transaction.
+    // If another transaction has an XLock when the method is called,
     // then the calling thread will be placed on a wait list
-    // until the locks are released.
-    // If the thread remains on the wait list for a certain
-    // amount of time (currently 10 seconds)
-    // then an exception is thrown.
-    pub fn x_lock(&self, blk: &BlockId) -> Result<(), LockAbortError> {
-        let start_time = Instant::now();
-        let mut locks = self.locks.lock().unwrap();
+    // until the lock is released.
+    // If the thread remains on the wait list for longer than
+    // the timeout (the table's timeout if it is None), or must
+    // not wait under the wait-die policy, then an error is returned.
+    pub fn slock(
+        &self,
+        blk: BlockId,
+        txnum: i32,
+        timeout: Option<Duration>,
+    ) -> Result<(), LockAbortError> {
+        let mut locks = self.wait_for(&blk, txnum, timeout, |holders| {
+            if holders.shared.contains(&txnum) {
+                return Vec::new();
+            }
+            holders
+                .exclusive
+                .into_iter()
+                .chain(holders.upgrader)
+                .filter(|&tx| tx != txnum)
+                .collect()
+        })?;
+        let holders = locks.entry(blk).or_default();
+        if !holders.shared.contains(&txnum) {
+            holders.shared.push(txnum);
+        }
+        Ok(())
+    }
 
-        while self.has_other_s_locks(&locks, blk) && !self.waiting_too_long(start_time) {
-            // Wait until notified or timeout occurs
-            let result = self.cond_var.wait_timeout(locks, self.max_time).unwrap();
-            locks = result.0;
+    // Grant an XLock on the specified block to the transaction.
+    // If another transaction has a lock of any type when the
+    // method is called, then the calling thread will be placed
+    // on a wait list until the locks are released, as for slock.
+    pub fn x_lock(
+        &self,
+        blk: &BlockId,
+        txnum: i32,
+        timeout: Option<Duration>,
+    ) -> Result<(), LockAbortError> {
+        let upgrade = {
+            let mut locks = self.shard(blk).lock().unwrap();
+            match locks.get_mut(blk) {
+                Some(holders)
+                    if holders.shared.contains(&txnum) && holders.exclusive != Some(txnum) =>
+                {
+                    if holders.upgrader.is_some_and(|tx| tx != txnum) {
+                        self.record(blk, None, true);
+                        return Err(LockAbortError::UpgradeConflict(blk.clone()));
+                    }
+                    holders.upgrader = Some(txnum);
+                    true
+                }
+                _ => false,
+            }
+        };
+        let result = self.wait_for(blk, txnum, timeout, |holders| {
+            holders
+                .shared
+                .iter()
+                .copied()
+                .chain(holders.exclusive)
+                .filter(|&tx| tx != txnum)
+                .collect()
+        });
+        let mut locks = match result {
+            Ok(locks) => locks,
+            Err(e) => {
+                if upgrade {
+                    let mut locks = self.shard(blk).lock().unwrap();
+                    if let Some(holders) = locks.get_mut(blk) {
+                        // readers waiting behind the upgrade can go on
+                        holders.upgrader = None;
+                        holders.cond_var.notify_all();
+                    }
+                }
+                return Err(e);
+            }
+        };
+        let holders = locks.entry(blk.clone()).or_default();
+        holders.exclusive = Some(txnum);
+        if upgrade {
+            holders.upgrader = None;
         }
+        Ok(())
+    }
 
-        // Check if we still have other S-locks after waiting
-        if self.has_other_s_locks(&locks, blk) {
-            return Err(LockAbortError);
+    // Release the transaction's locks on the block.
+    pub fn unlock(&self, blk: BlockId, txnum: i32) {
+        let mut locks = self.shard(&blk).lock().unwrap();
+        let Some(holders) = locks.get_mut(&blk) else {
+            return;
+        };
+        holders.shared.retain(|&tx| tx != txnum);
+        if holders.exclusive == Some(txnum) {
+            holders.exclusive = None;
         }
+        holders.cond_var.notify_all();
+        if holders.is_unused() {
+            locks.remove(&blk);
+        }
+    }
 
-        locks.insert(blk.clone(), -1);
+    // Refuse the lock requests of the transaction that have
+    // to wait, until release is called for it, whatever the
+    // policy. The request it is waiting on, if any, is woken
+    // up and fails with LockAbortError::Cancelled, so that a
+    // transaction aborted from another thread is not left
+    // waiting for a lock that may never be released.
+    pub fn cancel(&self, txnum: i32) {
+        self.cancelled.lock().unwrap().insert(txnum);
+        for shard in &self.shards {
+            for holders in shard.lock().unwrap().values() {
+                if holders.waiters > 0 {
+                    holders.cond_var.notify_all();
+                }
+            }
+        }
+    }
 
-        Ok(())
+    // Forget that the transaction was cancelled,
+    // once it has released its locks.
+    pub fn release(&self, txnum: i32) {
+        self.cancelled.lock().unwrap().remove(&txnum);
     }
 
-    pub fn unlock(&self, blk: BlockId) {
-        let mut locks = self.locks.lock().unwrap();
-        let val = *locks.get(&blk).unwrap_or(&0);
-        if val > 1 {
-            locks.insert(blk, val - 1);
-        } else {
-            locks.remove(&blk);
-            self.cond_var.notify_all();
+    // Wait until none of the transactions returned by
+    // conflicts holds a lock on the block, and return the
+    // locked shard of the table, so that the lock can be granted.
+    fn wait_for(
+        &self,
+        blk: &BlockId,
+        txnum: i32,
+        timeout: Option<Duration>,
+        conflicts: impl Fn(&Holders) -> Vec<i32>,
+    ) -> Result<MutexGuard<'_, HashMap<BlockId, Holders>>, LockAbortError> {
+        let timeout = timeout.unwrap_or(self.timeout);
+        let start_time = Instant::now();
+        let mut waited = false;
+        let mut locks = self.shard(blk).lock().unwrap();
+        loop {
+            let wait_time = waited.then(|| start_time.elapsed());
+            let Some(entry) = locks.get_mut(blk) else {
+                self.record(blk, wait_time, false);
+                return Ok(locks);
+            };
+            let holders = conflicts(entry);
+            if holders.is_empty() {
+                self.record(blk, wait_time, false);
+                return Ok(locks);
+            }
+            let refusal = match self.policy {
+                _ if self.cancelled.lock().unwrap().contains(&txnum) => {
+                    Some(LockAbortError::Cancelled(txnum))
+                }
+                DeadlockPolicy::Timeout if start_time.elapsed() >= timeout => {
+                    Some(LockAbortError::Timeout(blk.clone()))
+                }
+                DeadlockPolicy::WaitDie if holders.iter().any(|&holder| holder < txnum) => {
+                    Some(LockAbortError::Died(blk.clone()))
+                }
+                _ => None,
+            };
+            if let Some(refusal) = refusal {
+                if entry.is_unused() {
+                    locks.remove(blk);
+                }
+                self.record(blk, wait_time, true);
+                return Err(refusal);
+            }
+            // the entry stays in the table while it has waiters
+            entry.waiters += 1;
+            waited = true;
+            let cond_var = Arc::clone(&entry.cond_var);
+            locks = match self.policy {
+                DeadlockPolicy::Timeout => {
+                    let remaining = timeout.saturating_sub(start_time.elapsed());
+                    cond_var.wait_timeout(locks, remaining).unwrap().0
+                }
+                DeadlockPolicy::WaitDie => cond_var.wait(locks).unwrap(),
+            };
+            locks.get_mut(blk).unwrap().waiters -= 1;
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_own_locks_do_not_conflict() {
+        let table = LockTable::new();
+        let blk = BlockId::new("testfile", 0);
+        table.slock(blk.clone(), 1, None).unwrap();
+        table.x_lock(&blk, 1, None).unwrap();
+        table.slock(blk.clone(), 1, None).unwrap();
+        table.unlock(blk.clone(), 1);
+        table.x_lock(&blk, 2, None).unwrap();
+    }
+
+    #[test]
+    fn test_held_locks() {
+        let table = LockTable::new();
+        let blk0 = BlockId::new("testfile", 0);
+        let blk1 = BlockId::new("testfile", 1);
+        table.slock(blk1.clone(), 2, None).unwrap();
+        table.slock(blk1.clone(), 1, None).unwrap();
+        table.slock(blk0.clone(), 3, None).unwrap();
+        table.x_lock(&blk0, 3, None).unwrap();
+        assert_eq!(
+            table.held_locks(),
+            [
+                (blk0.clone(), 3, "X"),
+                (blk1.clone(), 1, "S"),
+                (blk1, 2, "S")
+            ]
+        );
+        table.unlock(blk0, 3);
+        assert_eq!(table.held_locks().len(), 2);
+    }
+
+    #[test]
+    fn test_wait_die() {
+        let table = Arc::new(LockTable::with_policy(DeadlockPolicy::WaitDie));
+        let blk = BlockId::new("testfile", 0);
+        table.slock(blk.clone(), 2, None).unwrap();
+
+        // a younger transaction dies at once
+        let start = Instant::now();
+        assert_eq!(
+            table.x_lock(&blk, 3, None),
+            Err(LockAbortError::Died(blk.clone()))
+        );
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // an older one waits until the lock is released
+        let waiter = {
+            let table = Arc::clone(&table);
+            let blk = blk.clone();
+            std::thread::spawn(move || table.x_lock(&blk, 1, None))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        table.unlock(blk.clone(), 2);
+        assert!(waiter.join().unwrap().is_ok());
+
+        // readers do not conflict with each other
+        table.unlock(blk.clone(), 1);
+        table.slock(blk.clone(), 5, None).unwrap();
+        table.slock(blk, 4, None).unwrap();
+    }
+
+    #[test]
+    fn test_cancel() {
+        let table = Arc::new(LockTable::with_policy(DeadlockPolicy::WaitDie));
+        let blk = BlockId::new("testfile", 0);
+        table.x_lock(&blk, 2, None).unwrap();
+        let waiter = {
+            let table = Arc::clone(&table);
+            let blk = blk.clone();
+            std::thread::spawn(move || table.slock(blk, 1, None))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+
+        // the waiting transaction is woken up and refused
+        table.cancel(1);
+        assert_eq!(waiter.join().unwrap(), Err(LockAbortError::Cancelled(1)));
+        assert_eq!(
+            table.slock(blk.clone(), 1, None),
+            Err(LockAbortError::Cancelled(1))
+        );
 
-    fn has_xlock(&self, locks: &MutexGuard<HashMap<BlockId, i32>>, blk: &BlockId) -> bool {
-        self.get_lock_value(locks, blk) < 0
+        // until it has released its locks
+        table.release(1);
+        table.unlock(blk.clone(), 2);
+        table.slock(blk.clone(), 1, None).unwrap();
+        table.unlock(blk, 1);
+        assert!(table.is_empty());
     }
 
-    fn has_other_s_locks(&self, locks: &MutexGuard<HashMap<BlockId, i32>>, blk: &BlockId) -> bool {
-        self.get_lock_value(locks, blk) > 1
+    #[test]
+    fn test_waits_are_per_block() {
+        let table = Arc::new(LockTable::new());
+        let (blk1, blk2) = (BlockId::new("testfile", 1), BlockId::new("testfile", 2));
+        table.x_lock(&blk1, 1, None).unwrap();
+        table.x_lock(&blk2, 1, None).unwrap();
+        let waiter = {
+            let table = Arc::clone(&table);
+            let blk1 = blk1.clone();
+            std::thread::spawn(move || table.slock(blk1, 2, None))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        table.unlock(blk2, 1);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        table.unlock(blk1.clone(), 1);
+        assert!(waiter.join().unwrap().is_ok());
+        table.unlock(blk1, 2);
+        assert!(table.is_empty());
     }
 
-    fn waiting_too_long(&self, start_time: Instant) -> bool {
-        start_time.elapsed() > self.max_time
+    #[test]
+    fn test_timeout() {
+        let table = LockTable::new().with_timeout(Duration::from_millis(50));
+        let blk = BlockId::new("testfile", 0);
+        table.x_lock(&blk, 1, None).unwrap();
+        let start = Instant::now();
+        assert_eq!(
+            table.slock(blk.clone(), 2, None),
+            Err(LockAbortError::Timeout(blk.clone()))
+        );
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        let result = table.slock(blk.clone(), 2, Some(Duration::ZERO));
+        assert_eq!(result, Err(LockAbortError::Timeout(blk)));
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let table = Arc::new(LockTable::new());
+        let blk = BlockId::new("testfile", 0);
+        table.slock(blk.clone(), 1, None).unwrap();
+        table.slock(blk.clone(), 2, None).unwrap();
+
+        // the upgrade waits for the other reader only
+        let upgrader = {
+            let table = Arc::clone(&table);
+            let blk = blk.clone();
+            std::thread::spawn(move || table.x_lock(&blk, 1, None))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!upgrader.is_finished());
+
+        // a second upgrade would wait for the first forever
+        assert_eq!(
+            table.x_lock(&blk, 2, None),
+            Err(LockAbortError::UpgradeConflict(blk.clone()))
+        );
+        // a new reader waits behind the upgrade
+        let reader = {
+            let table = Arc::clone(&table);
+            let blk = blk.clone();
+            std::thread::spawn(move || table.slock(blk, 3, None))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!reader.is_finished());
+
+        table.unlock(blk.clone(), 2);
+        assert!(upgrader.join().unwrap().is_ok());
+        assert!(!reader.is_finished());
+        table.unlock(blk.clone(), 1);
+        assert!(reader.join().unwrap().is_ok());
     }
 
-    fn get_lock_value(&self, locks: &MutexGuard<HashMap<BlockId, i32>>, blk: &BlockId) -> i32 {
-        *locks.get(blk).unwrap_or(&0)
+    #[test]
+    fn test_concurrent_upgrades() {
+        let table = Arc::new(LockTable::new());
+        let blk = BlockId::new("testfile", 0);
+        let start = Instant::now();
+        let readers: Vec<i32> = (1..=4).collect();
+        for &tx in &readers {
+            table.slock(blk.clone(), tx, None).unwrap();
+        }
+        // every reader tries to upgrade, and releases its
+        // lock if refused, as a rolled back transaction would
+        let handles: Vec<_> = readers
+            .iter()
+            .map(|&tx| {
+                let table = Arc::clone(&table);
+                let blk = blk.clone();
+                std::thread::spawn(move || {
+                    let result = table.x_lock(&blk, tx, None);
+                    table.unlock(blk, tx);
+                    result.is_ok()
+                })
+            })
+            .collect();
+        let granted = handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .filter(|&ok| ok)
+            .count();
+        assert!(granted >= 1);
+        // no upgrade waited for the timeout
+        assert!(start.elapsed() < LockTable::DEFAULT_TIMEOUT / 2);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_shards() {
+        let table = LockTable::new().with_shards(4);
+        let blocks: Vec<_> = (0..32).map(|n| BlockId::new("testfile", n)).collect();
+        for blk in &blocks {
+            table.x_lock(blk, 1, None).unwrap();
+        }
+        let used = table
+            .shards
+            .iter()
+            .filter(|shard| !shard.lock().unwrap().is_empty())
+            .count();
+        assert!(used > 1);
+        let result = table.slock(blocks[7].clone(), 2, Some(Duration::ZERO));
+        assert_eq!(result, Err(LockAbortError::Timeout(blocks[7].clone())));
+        for blk in blocks {
+            table.unlock(blk, 1);
+        }
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_lock_stats() {
+        let table = Arc::new(LockTable::new());
+        let hot = BlockId::new("testfile", 0);
+        let cold = BlockId::new("testfile", 1);
+        table.slock(cold.clone(), 1, None).unwrap();
+        table.slock(cold.clone(), 2, None).unwrap();
+        table.x_lock(&hot, 1, None).unwrap();
+
+        // a request granted at once is not recorded
+        assert_eq!(table.block_stats(&cold), LockStats::default());
+        let waiter = {
+            let table = Arc::clone(&table);
+            let hot = hot.clone();
+            std::thread::spawn(move || table.slock(hot, 2, None))
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        table.unlock(hot.clone(), 1);
+        waiter.join().unwrap().unwrap();
+        let result = table.x_lock(&hot, 3, Some(Duration::from_millis(10)));
+        assert_eq!(result, Err(LockAbortError::Timeout(hot.clone())));
+        let result = table.x_lock(&cold, 3, Some(Duration::ZERO));
+        assert_eq!(result, Err(LockAbortError::Timeout(cold.clone())));
+
+        let stats = table.lock_stats();
+        assert_eq!(stats.len(), 2);
+        let (blk, hot_stats) = &stats[0];
+        assert_eq!(blk, &hot);
+        assert_eq!(hot_stats.waits, 2);
+        assert_eq!(hot_stats.aborts, 1);
+        assert!(hot_stats.wait_time >= Duration::from_millis(60));
+        assert_eq!(
+            stats[1],
+            (
+                cold.clone(),
+                LockStats {
+                    waits: 0,
+                    wait_time: Duration::ZERO,
+                    aborts: 1
+                }
+            )
+        );
+
+        table.reset_lock_stats();
+        assert!(table.lock_stats().is_empty());
     }
 }
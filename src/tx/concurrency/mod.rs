@@ -1,4 +1,5 @@
 pub mod concurrency_manager;
 pub mod lock_table;
 
-pub use lock_table::LockTable;
+pub use concurrency_manager::{ConcurrencyManager, IsolationLevel};
+pub use lock_table::{DeadlockPolicy, LockStats, LockTable};
@@ -1,3 +1,12 @@
+pub mod buffer_list;
 pub mod concurrency;
+pub mod monitor;
 pub mod recovery;
+pub mod snapshot;
 pub mod transaction;
+pub mod txn_id_allocator;
+
+pub use buffer_list::BufferList;
+pub use monitor::{TransactionInfo, TransactionReaper, TransactionRegistry};
+pub use transaction::Transaction;
+pub use txn_id_allocator::TxnIdAllocator;
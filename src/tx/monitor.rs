@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex, Weak},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use super::{concurrency::IsolationLevel, Transaction};
+use crate::error::DbResult;
+
+// A description of an active transaction, as reported
+// by SimpleDB::active_transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionInfo {
+    pub txnum: i32,
+    // How long ago the transaction started.
+    pub age: Duration,
+    // The number of blocks it holds locks on.
+    pub locks: usize,
+    pub isolation: IsolationLevel,
+    pub read_only: bool,
+}
+
+// The transactions created by a database that have not
+// completed, so that they can be listed, and those that
+// have run for too long aborted.
+// A transaction is forgotten once it completes or its
+// last reference is dropped, which rolls it back.
+#[derive(Default)]
+pub struct TransactionRegistry {
+    active: Mutex<HashMap<i32, Weak<Transaction>>>,
+}
+
+impl TransactionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&self, tx: &Arc<Transaction>) {
+        let mut active = self.active.lock().unwrap();
+        Self::forget_completed(&mut active);
+        active.insert(tx.txnum(), Arc::downgrade(tx));
+    }
+
+    // Return the active transactions, oldest first.
+    pub fn active(&self) -> Vec<Arc<Transaction>> {
+        let mut active = self.active.lock().unwrap();
+        Self::forget_completed(&mut active);
+        let mut txs: Vec<_> = active.values().filter_map(Weak::upgrade).collect();
+        txs.sort_by_key(|tx| tx.txnum());
+        txs
+    }
+
+    // Describe the active transactions, oldest first.
+    pub fn info(&self) -> Vec<TransactionInfo> {
+        self.active()
+            .iter()
+            .map(|tx| TransactionInfo {
+                txnum: tx.txnum(),
+                age: tx.age(),
+                locks: tx.lock_count(),
+                isolation: tx.isolation(),
+                read_only: tx.is_read_only(),
+            })
+            .collect()
+    }
+
+    // Abort the active transactions that started longer
+    // than max_age ago, and return their numbers.
+    pub fn abort_older_than(&self, max_age: Duration) -> DbResult<Vec<i32>> {
+        let mut aborted = Vec::new();
+        for tx in self.active() {
+            if tx.age() > max_age && tx.abort()? {
+                aborted.push(tx.txnum());
+            }
+        }
+        Ok(aborted)
+    }
+
+    fn forget_completed(active: &mut HashMap<i32, Weak<Transaction>>) {
+        active.retain(|_, tx| tx.upgrade().is_some_and(|tx| !tx.is_completed()));
+    }
+}
+
+// A background thread enforcing a maximum age for the
+// transactions of a registry, so that a client that
+// stops in the middle of a transaction cannot keep its
+// locks forever. The thread wakes up periodically and
+// aborts the transactions older than the maximum age.
+// The thread stops when the reaper is dropped.
+pub struct TransactionReaper {
+    stopped: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl TransactionReaper {
+    pub fn start(
+        registry: Arc<TransactionRegistry>,
+        max_age: Duration,
+        interval: Duration,
+    ) -> Self {
+        let stopped = Arc::new((Mutex::new(false), Condvar::new()));
+        let handle = {
+            let stopped = Arc::clone(&stopped);
+            thread::spawn(move || {
+                let (lock, cvar) = &*stopped;
+                let mut stop = lock.lock().unwrap();
+                while !*stop {
+                    stop = cvar.wait_timeout(stop, interval).unwrap().0;
+                    if *stop {
+                        break;
+                    }
+                    // a failed rollback is retried on the next round
+                    let _ = registry.abort_older_than(max_age);
+                }
+            })
+        };
+        TransactionReaper {
+            stopped,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for TransactionReaper {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.stopped;
+        *lock.lock().unwrap() = true;
+        cvar.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, error::DbError, tx::concurrency::DeadlockPolicy};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_active_transactions() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+
+        let tx1 = db.new_tx()?;
+        tx1.pin(&blk)?;
+        tx1.set_int(&blk, 80, 1, true)?;
+        let tx2 = db.new_read_only_tx();
+        let tx3 = db.new_tx()?;
+        tx3.commit()?;
+        let active = db.active_transactions();
+        assert_eq!(
+            active.iter().map(|info| info.txnum).collect::<Vec<_>>(),
+            [tx1.txnum(), tx2.txnum()]
+        );
+        // the block, and the file it is in
+        assert_eq!(active[0].locks, 2);
+        assert!(!active[0].read_only);
+        assert!(active[1].read_only);
+        assert!(active[0].age >= active[1].age);
+
+        tx1.commit()?;
+        drop(tx2);
+        assert!(db.active_transactions().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_abort_old_transactions() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+
+        let stuck = db.new_tx()?;
+        stuck.pin(&blk)?;
+        stuck.set_int(&blk, 80, 1, true)?;
+        std::thread::sleep(Duration::from_millis(30));
+        let young = db.new_tx()?;
+        let aborted = db.abort_transactions_older_than(Duration::from_millis(20))?;
+        assert_eq!(aborted, [stuck.txnum()]);
+        let result = stuck.get_int(&blk, 80);
+        assert!(matches!(result, Err(DbError::TransactionAborted(n)) if n == stuck.txnum()));
+        assert!(matches!(
+            stuck.commit(),
+            Err(DbError::TransactionAborted(_))
+        ));
+        stuck.rollback()?;
+
+        // its update was undone and its xlock released
+        young.set_lock_timeout(Duration::from_millis(20));
+        young.pin(&blk)?;
+        assert_eq!(young.get_int(&blk, 80)?, 0);
+        young.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_abort_waiting_transaction() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::builder(temp_dir.path())
+            .deadlock_policy(DeadlockPolicy::WaitDie)
+            .open()?;
+        let blk = db.file_manager().append("testfile")?;
+
+        // under wait-die, the older transaction waits
+        // for the lock without a timeout
+        let old = db.new_tx()?;
+        let young = db.new_tx()?;
+        young.pin(&blk)?;
+        young.set_int(&blk, 80, 1, true)?;
+        let waiter = {
+            let old = Arc::clone(&old);
+            let blk = blk.clone();
+            thread::spawn(move || {
+                old.pin(&blk)?;
+                old.get_int(&blk, 80)
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+
+        let aborted = db.abort_transactions_older_than(Duration::ZERO)?;
+        assert_eq!(aborted, [old.txnum(), young.txnum()]);
+        let result = waiter.join().unwrap();
+        assert!(matches!(result, Err(DbError::TransactionAborted(n)) if n == old.txnum()));
+        assert!(db.lock_table().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reaper() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+        let _reaper = TransactionReaper::start(
+            Arc::clone(db.transactions()),
+            Duration::from_millis(200),
+            Duration::from_millis(10),
+        );
+
+        let stuck = db.new_tx()?;
+        stuck.pin(&blk)?;
+        stuck.set_int(&blk, 80, 1, true)?;
+        std::thread::sleep(Duration::from_millis(100));
+        // waits until the reaper aborts the stuck transaction,
+        // before reaching the maximum age itself
+        let tx = db.new_tx()?;
+        tx.set_lock_timeout(Duration::from_secs(5));
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 80, 2, true)?;
+        tx.commit()?;
+        assert!(stuck.is_completed());
+        assert!(matches!(
+            stuck.get_int(&blk, 80),
+            Err(DbError::TransactionAborted(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use std::sync::{Arc, Mutex};
+
+use super::{Durability, RecoveryManager};
+use crate::{
+    buffer::BufferManager,
+    error::{DbError, DbResult},
+    file::{
+        faulty_store::{Fault, FaultyStore},
+        BlockId, BlockStore,
+    },
+    log::LogManager,
+    tx::TxnIdAllocator,
+};
+
+// Crash tests of recovery. A workload of transactions runs
+// on a FaultyStore that crashes at a chosen write, and the
+// store is then recovered and its contents checked; each
+// test crashes the workload at every one of its writes.
+// Each transaction stores its number at two places, in
+// different blocks, so that half a transaction shows; one
+// in four rolls back, and the last is in progress when the
+// workload ends. Recovery must leave the store with both
+// numbers of a transaction or neither, with none of those
+// that rolled back or did not finish, and with all of those
+// whose commit returned, unless the commit or the disk did
+// not promise durability. A torn block may make recovery
+// fail, but only by reporting the corrupt block.
+
+const DATA: &str = "data";
+const LOG: &str = "log";
+// The transactions of the workload, the last
+// of which is in progress when it ends.
+const TXS: i32 = 13;
+
+type Managers = (
+    Arc<Mutex<LogManager<FaultyStore>>>,
+    Arc<BufferManager<FaultyStore>>,
+);
+
+// A store holding three zeroed data blocks.
+fn new_store() -> DbResult<Arc<FaultyStore>> {
+    let store = Arc::new(FaultyStore::new(400));
+    for _ in 0..3 {
+        store.append(DATA)?;
+    }
+    Ok(store)
+}
+
+// Open the log and buffer managers of the store, with too
+// few buffers for all the data blocks, so that buffers
+// holding uncommitted modifications get written.
+fn open(store: &Arc<FaultyStore>) -> DbResult<Managers> {
+    let lm = Arc::new(Mutex::new(LogManager::new(
+        Arc::clone(store),
+        LOG.to_string(),
+    )?));
+    let bm = Arc::new(BufferManager::new(Arc::clone(store), Arc::clone(&lm), 2));
+    Ok((lm, bm))
+}
+
+// The places where the transaction stores its number.
+fn slots(txnum: i32) -> [(BlockId, usize); 2] {
+    let offset = 4 * txnum as usize;
+    [
+        (BlockId::new(DATA, (txnum % 3) as u64), offset),
+        (BlockId::new(DATA, ((txnum + 1) % 3) as u64), 200 + offset),
+    ]
+}
+
+// Run the workload until it ends or the store fails,
+// adding the transactions whose commit returned.
+fn run(store: &Arc<FaultyStore>, durability: Durability, committed: &mut Vec<i32>) -> DbResult<()> {
+    let (lm, bm) = open(store)?;
+    for txnum in 1..=TXS {
+        let mut rm = RecoveryManager::new(txnum, Arc::clone(&lm), Arc::clone(&bm))?;
+        rm.set_durability(durability);
+        for (blk, offset) in slots(txnum) {
+            let buff = bm.pin(blk)?;
+            let mut page = buff.page();
+            let lsn = rm.set_int(&mut page, offset, txnum)?;
+            page.contents().set_int(offset, txnum);
+            page.set_modified(txnum, Some(lsn));
+        }
+        if txnum == TXS {
+            break;
+        } else if txnum % 4 == 0 {
+            rm.rollback()?;
+        } else {
+            rm.commit()?;
+            committed.push(txnum);
+        }
+    }
+    Ok(())
+}
+
+// Restart the crashed store, recover it, and return
+// what each transaction stored.
+fn recover(store: &Arc<FaultyStore>) -> DbResult<Vec<[i32; 2]>> {
+    store.restart();
+    let (lm, bm) = open(store)?;
+    RecoveryManager::recover(store, &lm, &bm, &TxnIdAllocator::new())?;
+    let mut values = Vec::new();
+    for txnum in 1..=TXS {
+        let mut stored = [0; 2];
+        for (value, (blk, offset)) in stored.iter_mut().zip(slots(txnum)) {
+            let buff = bm.pin(blk)?;
+            *value = buff.page().contents().get_int(offset);
+        }
+        values.push(stored);
+    }
+    Ok(values)
+}
+
+// Check what the recovered store holds, given the transactions
+// whose commit returned, and whether their commits are durable.
+fn check(values: &[[i32; 2]], committed: &[i32], durable: bool, context: &str) {
+    for (txnum, stored) in (1..).zip(values) {
+        let present = *stored == [txnum, txnum];
+        assert!(
+            present || *stored == [0, 0],
+            "{}: transaction {} is partly present: {:?}",
+            context,
+            txnum,
+            stored
+        );
+        if txnum % 4 == 0 || txnum == TXS {
+            assert!(!present, "{}: transaction {} is present", context, txnum);
+        }
+        if durable && committed.contains(&txnum) {
+            assert!(present, "{}: transaction {} is lost", context, txnum);
+        }
+    }
+}
+
+// Recover the store and check it, allowing recovery to
+// fail if a block was torn, but only by saying which.
+fn recover_and_check(
+    store: &Arc<FaultyStore>,
+    fault: Fault,
+    committed: &[i32],
+    durable: bool,
+    context: &str,
+) {
+    match recover(store) {
+        Ok(values) => {
+            check(&values, committed, durable, context);
+            // crashing after recovery changes nothing
+            store.crash();
+            let again = recover(store).unwrap();
+            assert_eq!(again, values, "{}: recovered twice", context);
+        }
+        Err(DbError::CorruptBlock(_)) if fault == Fault::Tear => {}
+        Err(e) => panic!("{}: recovery failed: {}", context, e),
+    }
+}
+
+// The number of writes the workload makes on a new store,
+// after those creating the store.
+fn count_writes(durability: Durability) -> DbResult<(usize, usize)> {
+    let store = new_store()?;
+    let start = store.writes();
+    run(&store, durability, &mut Vec::new())?;
+    Ok((start, store.writes() - start))
+}
+
+#[test]
+fn test_crash_at_every_write() -> DbResult<()> {
+    for durability in [Durability::Force, Durability::NoForce, Durability::Lazy] {
+        let (start, writes) = count_writes(durability)?;
+        assert!(writes > 0);
+        for n in 1..=writes {
+            for fault in [Fault::Fail, Fault::Tear] {
+                let context = format!("{:?} at write {} under {:?}", fault, n, durability);
+                let store = new_store()?;
+                store.inject(start + n, fault);
+                let mut committed = Vec::new();
+                assert!(
+                    run(&store, durability, &mut committed).is_err(),
+                    "{}",
+                    context
+                );
+                store.crash();
+                let durable = durability != Durability::Lazy;
+                recover_and_check(&store, fault, &committed, durable, &context);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dropped_syncs() -> DbResult<()> {
+    let (start, writes) = count_writes(Durability::Force)?;
+    for n in 1..=writes {
+        let context = format!("syncs dropped from write {}", n);
+        let store = new_store()?;
+        store.inject(start + n, Fault::DropSyncs);
+        let mut committed = Vec::new();
+        run(&store, Durability::Force, &mut committed)?;
+        assert_eq!(committed.len(), 9);
+        store.crash();
+        // the commits after the dropped syncs can be lost,
+        // but the store is still consistent
+        recover_and_check(&store, Fault::DropSyncs, &committed, false, &context);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_crash_during_recovery() -> DbResult<()> {
+    let crashed = || -> DbResult<(Arc<FaultyStore>, Vec<i32>)> {
+        let store = new_store()?;
+        let mut committed = Vec::new();
+        run(&store, Durability::NoForce, &mut committed)?;
+        store.crash();
+        Ok((store, committed))
+    };
+    let (store, _) = crashed()?;
+    let start = store.writes();
+    recover(&store)?;
+    let writes = store.writes() - start;
+    assert!(writes > 0);
+
+    for n in 1..=writes {
+        for fault in [Fault::Fail, Fault::Tear] {
+            let context = format!("{:?} at write {} of recovery", fault, n);
+            let (store, committed) = crashed()?;
+            store.inject(start + n, fault);
+            assert!(recover(&store).is_err(), "{}", context);
+            store.crash();
+            recover_and_check(&store, fault, &committed, true, &context);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,503 @@
+use crate::{
+    file::{BlockId, Page},
+    log::Lsn,
+};
+
+const INT_SIZE: usize = std::mem::size_of::<i32>();
+
+// A log record written by the recovery manager.
+// Every record starts with its operator and, but for a
+// checkpoint, the id of the transaction that wrote it; update records additionally
+// store the modified block, the offset within the block,
+// and the values there before and after the update,
+// so that the update can be both undone and redone.
+// A compensation record is written whenever an update is
+// undone. It holds the update that restores the old value
+// and the LSN of the record undone, so that an undo is
+// redone rather than repeated after a crash; one holding a
+// truncate record cancels the truncation at that LSN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogRecord {
+    // A checkpoint holds the next transaction number,
+    // since the records before it are discarded.
+    Checkpoint {
+        next_txnum: i32,
+    },
+    Start {
+        txnum: i32,
+    },
+    // The time of a commit is in milliseconds since the
+    // Unix epoch, so that a restore can stop at a time.
+    Commit {
+        txnum: i32,
+        time: u64,
+    },
+    Rollback {
+        txnum: i32,
+    },
+    SetInt {
+        txnum: i32,
+        block: BlockId,
+        offset: usize,
+        old: i32,
+        new: i32,
+    },
+    SetString {
+        txnum: i32,
+        block: BlockId,
+        offset: usize,
+        old: String,
+        new: String,
+    },
+    SetBytes {
+        txnum: i32,
+        block: BlockId,
+        offset: usize,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+    Compensation {
+        txnum: i32,
+        undone: Lsn,
+        update: Box<LogRecord>,
+    },
+    // A truncate record says that the file is cut down to
+    // its first len blocks when the transaction commits,
+    // unless a compensation record cancels it first.
+    Truncate {
+        txnum: i32,
+        filename: String,
+        len: u64,
+    },
+}
+
+impl LogRecord {
+    pub const CHECKPOINT: i32 = 0;
+    pub const START: i32 = 1;
+    pub const COMMIT: i32 = 2;
+    pub const ROLLBACK: i32 = 3;
+    pub const SETINT: i32 = 4;
+    pub const SETSTRING: i32 = 5;
+    pub const SETBYTES: i32 = 6;
+    pub const COMPENSATION: i32 = 7;
+    pub const TRUNCATE: i32 = 8;
+
+    // Interprets the bytes returned by the log iterator.
+    // Returns None if the operator is not recognized.
+    pub fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        let page = Page::from_bytes(bytes);
+        if page.length() < INT_SIZE {
+            return None;
+        }
+        if page.get_int(0) == Self::CHECKPOINT {
+            // a checkpoint written before transaction
+            // numbers were kept holds its operator only
+            let next_txnum = if page.length() < 2 * INT_SIZE {
+                0
+            } else {
+                page.get_int(INT_SIZE)
+            };
+            return Some(LogRecord::Checkpoint { next_txnum });
+        }
+        if page.length() < 2 * INT_SIZE {
+            return None;
+        }
+        let txnum = page.get_int(INT_SIZE);
+
+        match page.get_int(0) {
+            Self::START => Some(LogRecord::Start { txnum }),
+            Self::COMMIT => {
+                let bytes = page.to_vec();
+                let time = bytes.get(2 * INT_SIZE..2 * INT_SIZE + 8)?;
+                Some(LogRecord::Commit {
+                    txnum,
+                    time: u64::from_be_bytes(time.try_into().ok()?),
+                })
+            }
+            Self::ROLLBACK => Some(LogRecord::Rollback { txnum }),
+            Self::SETINT => {
+                let (block, offset, vpos) = Self::read_target(&page);
+                Some(LogRecord::SetInt {
+                    txnum,
+                    block,
+                    offset,
+                    old: page.get_int(vpos),
+                    new: page.get_int(vpos + INT_SIZE),
+                })
+            }
+            Self::SETSTRING => {
+                let (block, offset, vpos) = Self::read_target(&page);
+                let old = page.get_string(vpos);
+                let new = page.get_string(vpos + Page::max_length(old.len()));
+                Some(LogRecord::SetString {
+                    txnum,
+                    block,
+                    offset,
+                    old,
+                    new,
+                })
+            }
+            Self::SETBYTES => {
+                let (block, offset, vpos) = Self::read_target(&page);
+                let old = page.get_bytes(vpos);
+                let new = page.get_bytes(vpos + INT_SIZE + old.len());
+                Some(LogRecord::SetBytes {
+                    txnum,
+                    block,
+                    offset,
+                    old,
+                    new,
+                })
+            }
+            Self::COMPENSATION => {
+                let bytes = page.to_vec();
+                let upos = 2 * INT_SIZE + Lsn::SIZE;
+                let undone = Lsn::from_bytes(bytes.get(2 * INT_SIZE..upos)?.try_into().ok()?);
+                let update = LogRecord::from_bytes(bytes[upos..].to_vec())?;
+                if update.block().is_none() && update.op() != Self::TRUNCATE {
+                    return None;
+                }
+                Some(LogRecord::Compensation {
+                    txnum,
+                    undone,
+                    update: Box::new(update),
+                })
+            }
+            Self::TRUNCATE => {
+                let filename = page.get_string(2 * INT_SIZE);
+                let bytes = page.to_vec();
+                let lpos = 2 * INT_SIZE + Page::max_length(filename.len());
+                let len = bytes.get(lpos..lpos + 8)?;
+                Some(LogRecord::Truncate {
+                    txnum,
+                    filename,
+                    len: u64::from_be_bytes(len.try_into().ok()?),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn op(&self) -> i32 {
+        match self {
+            LogRecord::Checkpoint { .. } => Self::CHECKPOINT,
+            LogRecord::Start { .. } => Self::START,
+            LogRecord::Commit { .. } => Self::COMMIT,
+            LogRecord::Rollback { .. } => Self::ROLLBACK,
+            LogRecord::SetInt { .. } => Self::SETINT,
+            LogRecord::SetString { .. } => Self::SETSTRING,
+            LogRecord::SetBytes { .. } => Self::SETBYTES,
+            LogRecord::Compensation { .. } => Self::COMPENSATION,
+            LogRecord::Truncate { .. } => Self::TRUNCATE,
+        }
+    }
+
+    // The transaction that wrote the record,
+    // or -1 for a checkpoint, which belongs to none.
+    pub fn txnum(&self) -> i32 {
+        match self {
+            LogRecord::Checkpoint { .. } => -1,
+            LogRecord::Start { txnum }
+            | LogRecord::Commit { txnum, .. }
+            | LogRecord::Rollback { txnum }
+            | LogRecord::SetInt { txnum, .. }
+            | LogRecord::SetString { txnum, .. }
+            | LogRecord::SetBytes { txnum, .. }
+            | LogRecord::Compensation { txnum, .. }
+            | LogRecord::Truncate { txnum, .. } => *txnum,
+        }
+    }
+
+    // The block modified by an update or compensation record.
+    pub fn block(&self) -> Option<&BlockId> {
+        match self {
+            LogRecord::SetInt { block, .. }
+            | LogRecord::SetString { block, .. }
+            | LogRecord::SetBytes { block, .. } => Some(block),
+            LogRecord::Compensation { update, .. } => update.block(),
+            _ => None,
+        }
+    }
+
+    // Serializes the record into the format expected by from_bytes.
+    //
+    // +----+-------+----------+--------+--------+-----+-----+
+    // | op | txnum | filename | blknum | offset | old | new |
+    // +----+-------+----------+--------+--------+-----+-----+
+    //
+    // Start and rollback records only contain the first two fields,
+    // a commit record those and its time, and a checkpoint record
+    // its operator and the next transaction number. A compensation record
+    // holds the LSN of the undone record after the first two fields,
+    // followed by its update record, and a truncate record holds
+    // the filename and the new length of the file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            LogRecord::Checkpoint { next_txnum: txnum }
+            | LogRecord::Start { txnum }
+            | LogRecord::Rollback { txnum } => {
+                let mut page = Page::new(2 * INT_SIZE);
+                page.set_int(0, self.op());
+                page.set_int(INT_SIZE, *txnum);
+                page.to_vec()
+            }
+            LogRecord::Commit { txnum, time } => {
+                let mut page = Page::new(2 * INT_SIZE);
+                page.set_int(0, self.op());
+                page.set_int(INT_SIZE, *txnum);
+                let mut bytes = page.to_vec();
+                bytes.extend_from_slice(&time.to_be_bytes());
+                bytes
+            }
+            LogRecord::SetInt {
+                txnum,
+                block,
+                offset,
+                old,
+                new,
+            } => {
+                let vpos = Self::value_pos(block);
+                let mut page = Page::new(vpos + 2 * INT_SIZE);
+                Self::write_target(&mut page, self.op(), *txnum, block, *offset);
+                page.set_int(vpos, *old);
+                page.set_int(vpos + INT_SIZE, *new);
+                page.to_vec()
+            }
+            LogRecord::SetString {
+                txnum,
+                block,
+                offset,
+                old,
+                new,
+            } => {
+                let vpos = Self::value_pos(block);
+                let npos = vpos + Page::max_length(old.len());
+                let mut page = Page::new(npos + Page::max_length(new.len()));
+                Self::write_target(&mut page, self.op(), *txnum, block, *offset);
+                page.set_string(vpos, old);
+                page.set_string(npos, new);
+                page.to_vec()
+            }
+            LogRecord::SetBytes {
+                txnum,
+                block,
+                offset,
+                old,
+                new,
+            } => {
+                let vpos = Self::value_pos(block);
+                let npos = vpos + INT_SIZE + old.len();
+                let mut page = Page::new(npos + INT_SIZE + new.len());
+                Self::write_target(&mut page, self.op(), *txnum, block, *offset);
+                page.set_bytes(vpos, old);
+                page.set_bytes(npos, new);
+                page.to_vec()
+            }
+            LogRecord::Compensation {
+                txnum,
+                undone,
+                update,
+            } => {
+                let mut page = Page::new(2 * INT_SIZE);
+                page.set_int(0, self.op());
+                page.set_int(INT_SIZE, *txnum);
+                let mut bytes = page.to_vec();
+                bytes.extend_from_slice(&undone.to_bytes());
+                bytes.extend_from_slice(&update.to_bytes());
+                bytes
+            }
+            LogRecord::Truncate {
+                txnum,
+                filename,
+                len,
+            } => {
+                let mut page = Page::new(2 * INT_SIZE + Page::max_length(filename.len()));
+                page.set_int(0, self.op());
+                page.set_int(INT_SIZE, *txnum);
+                page.set_string(2 * INT_SIZE, filename);
+                let mut bytes = page.to_vec();
+                bytes.extend_from_slice(&len.to_be_bytes());
+                bytes
+            }
+        }
+    }
+
+    // Apply the modification described by an update or
+    // compensation record to the page of its block, by
+    // writing the new value it holds.
+    // Other records modify no block.
+    pub fn redo(&self, page: &mut Page) {
+        match self {
+            LogRecord::SetInt { offset, new, .. } => page.set_int(*offset, *new),
+            LogRecord::SetString { offset, new, .. } => page.set_string(*offset, new),
+            LogRecord::SetBytes { offset, new, .. } => page.set_bytes(*offset, new),
+            LogRecord::Compensation { update, .. } => update.redo(page),
+            _ => {}
+        }
+    }
+
+    // The update that undoes an update record, which swaps
+    // its old and new values. Other records are not undone.
+    pub fn undo_update(&self) -> Option<LogRecord> {
+        match self.clone() {
+            LogRecord::SetInt {
+                txnum,
+                block,
+                offset,
+                old,
+                new,
+            } => Some(LogRecord::SetInt {
+                txnum,
+                block,
+                offset,
+                old: new,
+                new: old,
+            }),
+            LogRecord::SetString {
+                txnum,
+                block,
+                offset,
+                old,
+                new,
+            } => Some(LogRecord::SetString {
+                txnum,
+                block,
+                offset,
+                old: new,
+                new: old,
+            }),
+            LogRecord::SetBytes {
+                txnum,
+                block,
+                offset,
+                old,
+                new,
+            } => Some(LogRecord::SetBytes {
+                txnum,
+                block,
+                offset,
+                old: new,
+                new: old,
+            }),
+            _ => None,
+        }
+    }
+
+    fn value_pos(block: &BlockId) -> usize {
+        let fpos = 2 * INT_SIZE;
+        fpos + Page::max_length(block.filename().len()) + 2 * INT_SIZE
+    }
+
+    fn write_target(page: &mut Page, op: i32, txnum: i32, block: &BlockId, offset: usize) {
+        let fpos = 2 * INT_SIZE;
+        let bpos = fpos + Page::max_length(block.filename().len());
+        page.set_int(0, op);
+        page.set_int(INT_SIZE, txnum);
+        page.set_string(fpos, block.filename());
+        page.set_int(bpos, block.number() as i32);
+        page.set_int(bpos + INT_SIZE, offset as i32);
+    }
+
+    fn read_target(page: &Page) -> (BlockId, usize, usize) {
+        let fpos = 2 * INT_SIZE;
+        let filename = page.get_string(fpos);
+        let bpos = fpos + Page::max_length(filename.len());
+        let blknum = page.get_int(bpos) as u64;
+        let offset = page.get_int(bpos + INT_SIZE) as usize;
+        (BlockId::new(filename, blknum), offset, bpos + 2 * INT_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_record_roundtrip() {
+        let records = vec![
+            LogRecord::Checkpoint { next_txnum: 4 },
+            LogRecord::Start { txnum: 3 },
+            LogRecord::Commit {
+                txnum: 3,
+                time: 1_700_000_000_000,
+            },
+            LogRecord::Rollback { txnum: 3 },
+            LogRecord::SetInt {
+                txnum: 3,
+                block: BlockId::new("testfile", 7),
+                offset: 80,
+                old: 42,
+                new: -1,
+            },
+            LogRecord::SetString {
+                txnum: 3,
+                block: BlockId::new("testfile", 2),
+                offset: 40,
+                old: "hello".to_string(),
+                new: "goodbye".to_string(),
+            },
+            LogRecord::SetBytes {
+                txnum: 3,
+                block: BlockId::new("testfile", 5),
+                offset: 8,
+                old: vec![0, 1, 254, 255],
+                new: vec![],
+            },
+            LogRecord::Compensation {
+                txnum: 3,
+                undone: Lsn(12),
+                update: Box::new(LogRecord::SetString {
+                    txnum: 3,
+                    block: BlockId::new("testfile", 2),
+                    offset: 40,
+                    old: "goodbye".to_string(),
+                    new: "hello".to_string(),
+                }),
+            },
+            LogRecord::Truncate {
+                txnum: 3,
+                filename: "testfile".to_string(),
+                len: 2,
+            },
+            LogRecord::Compensation {
+                txnum: 3,
+                undone: Lsn(14),
+                update: Box::new(LogRecord::Truncate {
+                    txnum: 3,
+                    filename: "testfile".to_string(),
+                    len: 0,
+                }),
+            },
+        ];
+
+        for rec in records {
+            let bytes = rec.to_bytes();
+            assert_eq!(LogRecord::from_bytes(bytes), Some(rec));
+        }
+    }
+
+    #[test]
+    fn test_redo_and_undo() {
+        let rec = LogRecord::SetString {
+            txnum: 3,
+            block: BlockId::new("testfile", 2),
+            offset: 40,
+            old: "one".to_string(),
+            new: "two".to_string(),
+        };
+        let mut page = Page::new(100);
+        page.set_string(40, "one");
+        rec.redo(&mut page);
+        assert_eq!(page.get_string(40), "two");
+
+        let clr = LogRecord::Compensation {
+            txnum: 3,
+            undone: Lsn(5),
+            update: Box::new(rec.undo_update().unwrap()),
+        };
+        assert_eq!(clr.block(), rec.block());
+        clr.redo(&mut page);
+        assert_eq!(page.get_string(40), "one");
+        assert_eq!(LogRecord::Rollback { txnum: 3 }.undo_update(), None);
+    }
+}
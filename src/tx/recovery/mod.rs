@@ -0,0 +1,7 @@
+#[cfg(test)]
+mod crash_tests;
+mod log_record;
+mod recovery_manager;
+
+pub use log_record::LogRecord;
+pub use recovery_manager::{Durability, RecoveryManager, RecoveryTarget};
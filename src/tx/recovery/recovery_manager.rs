@@ -0,0 +1,923 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::LogRecord;
+use crate::{
+    buffer::{BufferManager, BufferPage},
+    error::DbResult,
+    file::{BlockId, BlockStore, FileManager, Page},
+    log::{ForwardLogIterator, LogManager, Lsn},
+    tx::TxnIdAllocator,
+};
+
+// The point in the history of a database to which
+// a restore brings it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryTarget {
+    // Up to and including the record with the LSN.
+    Lsn(Lsn),
+    // Up to the last commit made at or before the time.
+    Time(SystemTime),
+}
+
+impl RecoveryTarget {
+    // Return true if the record with the lsn
+    // is after the target.
+    fn excludes(&self, lsn: Lsn, rec: Option<&LogRecord>) -> bool {
+        match self {
+            RecoveryTarget::Lsn(target) => lsn > *target,
+            RecoveryTarget::Time(target) => {
+                let target = target
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_millis() as u64);
+                matches!(rec, Some(LogRecord::Commit { time, .. }) if *time > target)
+            }
+        }
+    }
+}
+
+// What a commit waits for before it returns, trading the
+// durability of the latest commits for their speed.
+// Whichever is chosen, a crash never leaves the database
+// inconsistent, since a buffer is only written once the
+// log holds its modifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Durability {
+    // The transaction's modified buffers and the commit
+    // record are written, so that a committed transaction
+    // needs no redo.
+    #[default]
+    Force,
+    // Only the log is written up to the commit record; the
+    // buffers are written later, and recovery redoes the
+    // modifications they are missing.
+    NoForce,
+    // Nothing is written: the commit record reaches the disk
+    // when the log next does, and a crash before that loses
+    // the transaction, as if it had never committed.
+    Lazy,
+}
+
+// The recovery manager. Each transaction has its own recovery manager.
+// It writes a log record for every modification, so that the
+// old values can be restored if the transaction rolls back,
+// and the new values reapplied if the database crashes
+// before they reach the disk.
+pub struct RecoveryManager<S: BlockStore = FileManager> {
+    lm: Arc<Mutex<LogManager<S>>>,
+    bm: Arc<BufferManager<S>>,
+    txnum: i32,
+    durability: Durability,
+}
+
+impl<S: BlockStore> RecoveryManager<S> {
+    // Create a recovery manager for the specified transaction
+    // and write a start record to the log.
+    pub fn new(
+        txnum: i32,
+        lm: Arc<Mutex<LogManager<S>>>,
+        bm: Arc<BufferManager<S>>,
+    ) -> DbResult<Self> {
+        let rm = RecoveryManager {
+            lm,
+            bm,
+            txnum,
+            durability: Durability::default(),
+        };
+        rm.write(LogRecord::Start { txnum })?;
+        Ok(rm)
+    }
+
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
+
+    // Write a commit record to the log, and flush it to disk.
+    // The transaction's modified buffers are flushed first,
+    // so that the commit record is never on disk before the data.
+    // Less is written under a weaker durability.
+    pub fn commit(&self) -> DbResult<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        self.complete(LogRecord::Commit {
+            txnum: self.txnum,
+            time,
+        })
+    }
+
+    // Undo the transaction's modifications, then write
+    // a rollback record to the log and flush it.
+    // The restored buffers are flushed first, as for a commit.
+    pub fn rollback(&self) -> DbResult<()> {
+        self.undo_all(Lsn(0))?;
+        self.complete(LogRecord::Rollback { txnum: self.txnum })
+    }
+
+    // Write the record completing the transaction, and
+    // what the durability requires before it.
+    fn complete(&self, rec: LogRecord) -> DbResult<()> {
+        if self.durability == Durability::Force {
+            self.bm.flush_all(self.txnum)?;
+        }
+        let lsn = self.write(rec)?;
+        if self.durability != Durability::Lazy {
+            self.lm.lock().unwrap().flush(lsn)?;
+        }
+        Ok(())
+    }
+
+    // Write a truncate record to the log and return its lsn.
+    // The truncation takes effect when the transaction
+    // commits, so that recovery can cut the file down
+    // again if the crash came first.
+    pub fn truncate(&self, filename: &str, len: u64) -> DbResult<Lsn> {
+        self.write(LogRecord::Truncate {
+            txnum: self.txnum,
+            filename: filename.to_string(),
+            len,
+        })
+    }
+
+    // Write a compensation record cancelling the truncation
+    // logged at the lsn, as when rolling back to a savepoint
+    // before it.
+    pub fn cancel_truncate(&self, lsn: Lsn, filename: &str, len: u64) -> DbResult<()> {
+        self.write(LogRecord::Compensation {
+            txnum: self.txnum,
+            undone: lsn,
+            update: Box::new(LogRecord::Truncate {
+                txnum: self.txnum,
+                filename: filename.to_string(),
+                len,
+            }),
+        })?;
+        Ok(())
+    }
+
+    // Flush the whole log, whatever the durability.
+    // The commit record of a transaction cutting a file down
+    // must reach the disk before the file is cut, since
+    // the blocks cut off cannot be brought back.
+    pub fn flush(&self) -> DbResult<()> {
+        self.lm.lock().unwrap().flush_all()?;
+        Ok(())
+    }
+
+    // Return the LSN of the newest record in the log,
+    // to which rollback_to can later return.
+    pub fn savepoint(&self) -> Lsn {
+        self.lm.lock().unwrap().latest_lsn()
+    }
+
+    // Undo the modifications the transaction made after
+    // the savepoint, leaving it active. The undoing is
+    // logged with compensation records, as for a rollback.
+    pub fn rollback_to(&self, savepoint: Lsn) -> DbResult<()> {
+        self.undo_all(savepoint)
+    }
+
+    // Read the log backwards from its end, undoing each
+    // update record of the transaction, until its start
+    // record or the record at the savepoint is reached.
+    // Updates already undone by an earlier, interrupted
+    // rollback are passed over.
+    // The log is not locked while undoing, since pinning
+    // a buffer can flush the log.
+    fn undo_all(&self, savepoint: Lsn) -> DbResult<()> {
+        let mut iter = self.lm.lock().unwrap().iter()?;
+        let mut undone_from = None;
+        while let Some(bytes) = iter.next() {
+            if iter.lsn() <= savepoint {
+                break;
+            }
+            let Some(rec) = LogRecord::from_bytes(bytes?) else {
+                continue;
+            };
+            if rec.txnum() != self.txnum {
+                continue;
+            }
+            match rec {
+                LogRecord::Start { .. } => break,
+                LogRecord::Compensation { undone, .. } => {
+                    undone_from = Some(undone_from.map_or(undone, |from: Lsn| from.min(undone)));
+                }
+                _ if undone_from.is_none_or(|from| iter.lsn() < from) => {
+                    undo(&self.lm, &self.bm, iter.lsn(), &rec)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Bring the database back to a consistent state after
+    // a crash, in three passes over the log:
+    // - analysis finds the transactions that had neither
+    //   committed nor rolled back;
+    // - redo repeats every update and compensation record
+    //   that the block on disk is missing, as told by the
+    //   page LSN stored with the block;
+    // - undo rolls back the unfinished transactions,
+    //   writing compensation records as a rollback does,
+    //   so that a crash during recovery loses no progress.
+    // The first two passes are a single forward scan, after
+    // which the files that committed transactions truncated
+    // are cut down again, in case the crash came first.
+    // Records of blocks no longer in their files, such as
+    // those of deleted temporary tables, are passed over.
+    // The allocator of transaction numbers is advanced past
+    // the numbers in the log, so that none is used again.
+    // Returns the number of transactions rolled back.
+    pub fn recover(
+        fm: &Arc<S>,
+        lm: &Arc<Mutex<LogManager<S>>>,
+        bm: &BufferManager<S>,
+        txn_ids: &TxnIdAllocator,
+    ) -> DbResult<usize> {
+        let mut unfinished = HashSet::new();
+        let mut truncations = Truncations::default();
+        let mut iter = lm.lock().unwrap().iter_forward(Lsn(0))?;
+        while let Some(bytes) = iter.next() {
+            let Some(rec) = LogRecord::from_bytes(bytes?) else {
+                continue;
+            };
+            advance_txn_ids(txn_ids, &rec);
+            truncations.note(iter.lsn(), &rec);
+            match &rec {
+                LogRecord::Checkpoint { .. } => unfinished.clear(),
+                LogRecord::Start { txnum } => {
+                    unfinished.insert(*txnum);
+                }
+                LogRecord::Commit { txnum, .. } | LogRecord::Rollback { txnum } => {
+                    unfinished.remove(txnum);
+                }
+                _ => redo(fm.as_ref(), bm, iter.lsn(), &rec, false)?,
+            }
+        }
+        truncations.apply(fm.as_ref(), bm)?;
+
+        let rolled_back = unfinished.len();
+        let mut undone_from: HashMap<i32, Lsn> = HashMap::new();
+        let mut iter = lm.lock().unwrap().iter()?;
+        while !unfinished.is_empty() {
+            let Some(bytes) = iter.next() else {
+                break;
+            };
+            let Some(rec) = LogRecord::from_bytes(bytes?) else {
+                continue;
+            };
+            let txnum = rec.txnum();
+            if !unfinished.contains(&txnum) {
+                continue;
+            }
+            match rec {
+                LogRecord::Start { .. } => {
+                    unfinished.remove(&txnum);
+                    let rec = LogRecord::Rollback { txnum };
+                    lm.lock().unwrap().append(&rec.to_bytes())?;
+                }
+                LogRecord::Compensation { undone, .. } => {
+                    let from = undone_from.entry(txnum).or_insert(undone);
+                    *from = (*from).min(undone);
+                }
+                _ if undone_from
+                    .get(&txnum)
+                    .is_none_or(|&from| iter.lsn() < from)
+                    && rec.block().is_some_and(|block| exists(fm.as_ref(), block)) =>
+                {
+                    undo(lm, bm, iter.lsn(), &rec)?;
+                }
+                _ => {}
+            }
+        }
+        bm.flush_all_dirty()?;
+        lm.lock().unwrap().flush_all()?;
+        Ok(rolled_back)
+    }
+
+    // Return true if the database must be recovered, as
+    // recover would modify it: some transaction in the log is
+    // unfinished, some block on disk is missing a logged
+    // modification, or some file is yet to be truncated.
+    // The database itself is only read.
+    // The allocator of transaction numbers is advanced
+    // past the numbers in the log, as by recover.
+    pub fn needs_recovery(
+        fm: &Arc<S>,
+        lm: &Mutex<LogManager<S>>,
+        txn_ids: &TxnIdAllocator,
+    ) -> DbResult<bool> {
+        let mut unfinished = HashSet::new();
+        let mut truncations = Truncations::default();
+        let mut missing = false;
+        let mut page = Page::new(fm.block_size());
+        let mut iter = lm.lock().unwrap().iter_forward(Lsn(0))?;
+        while let Some(bytes) = iter.next() {
+            let Some(rec) = LogRecord::from_bytes(bytes?) else {
+                continue;
+            };
+            advance_txn_ids(txn_ids, &rec);
+            truncations.note(iter.lsn(), &rec);
+            match &rec {
+                LogRecord::Checkpoint { .. } => unfinished.clear(),
+                LogRecord::Start { txnum } => {
+                    unfinished.insert(*txnum);
+                }
+                LogRecord::Commit { txnum, .. } | LogRecord::Rollback { txnum } => {
+                    unfinished.remove(txnum);
+                }
+                _ => match rec.block() {
+                    Some(block) if !missing && exists(fm.as_ref(), block) => {
+                        fm.read(block, &mut page)?;
+                        missing = page.lsn() < iter.lsn();
+                    }
+                    _ => {}
+                },
+            }
+        }
+        let untruncated = truncations
+            .pending
+            .iter()
+            .any(|(filename, &len)| fm.length(filename).is_ok_and(|n| n > len));
+        Ok(missing || untruncated || !unfinished.is_empty())
+    }
+
+    // Restore a database from a backup of its files to the
+    // target, by replaying its archived log files and then
+    // its log up to the target, as the redo pass of recover
+    // does. The blocks appended since the backup are
+    // appended again, and the files truncated since then are
+    // truncated again as each truncation commits. The
+    // transactions unfinished at the
+    // target are then rolled back, without logging, since
+    // the log after the target is discarded afterwards,
+    // with the archived files: the restored database starts
+    // a new history, and a restore that fails part way can
+    // be run again on the same files. The new log starts with
+    // a checkpoint, which keeps the transaction numbers
+    // increasing past those replayed.
+    // Returns the LSN of the last record replayed.
+    pub fn restore(
+        fm: &Arc<S>,
+        lm: &Arc<Mutex<LogManager<S>>>,
+        bm: &BufferManager<S>,
+        txn_ids: &TxnIdAllocator,
+        target: RecoveryTarget,
+    ) -> DbResult<Lsn> {
+        let mut segments = lm.lock().unwrap().archives()?;
+        segments.push(lm.lock().unwrap().logfile().to_string());
+        // the update and compensation records of each
+        // unfinished transaction, oldest first
+        let mut unfinished: HashMap<i32, Vec<(Lsn, LogRecord)>> = HashMap::new();
+        let mut truncations = Truncations::default();
+        let mut end = Lsn(0);
+        'replay: for segment in segments {
+            let len = fm.length(&segment)?;
+            if len == 0 {
+                continue;
+            }
+            let first = BlockId::new(&segment, 0);
+            let mut iter = ForwardLogIterator::new(Arc::clone(fm), first, len - 1, Lsn(0))?;
+            while let Some(bytes) = iter.next() {
+                let rec = LogRecord::from_bytes(bytes?);
+                if target.excludes(iter.lsn(), rec.as_ref()) {
+                    break 'replay;
+                }
+                end = iter.lsn();
+                let Some(rec) = rec else {
+                    continue;
+                };
+                advance_txn_ids(txn_ids, &rec);
+                truncations.note(iter.lsn(), &rec);
+                match &rec {
+                    LogRecord::Checkpoint { .. } => unfinished.clear(),
+                    LogRecord::Start { txnum } => {
+                        unfinished.insert(*txnum, Vec::new());
+                    }
+                    LogRecord::Commit { txnum, .. } => {
+                        unfinished.remove(txnum);
+                        truncations.apply(fm.as_ref(), bm)?;
+                    }
+                    LogRecord::Rollback { txnum } => {
+                        unfinished.remove(txnum);
+                    }
+                    _ => {
+                        redo(fm.as_ref(), bm, end, &rec, true)?;
+                        if let Some(updates) = unfinished.get_mut(&rec.txnum()) {
+                            updates.push((end, rec));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (txnum, updates) in unfinished {
+            let mut undone_from = None;
+            for (lsn, rec) in updates.into_iter().rev() {
+                match rec {
+                    LogRecord::Compensation { undone, .. } => {
+                        undone_from =
+                            Some(undone_from.map_or(undone, |from: Lsn| from.min(undone)));
+                    }
+                    _ if undone_from.is_none_or(|from| lsn < from) => {
+                        let Some(update) = rec.undo_update() else {
+                            continue;
+                        };
+                        let buff = bm.pin(update.block().unwrap().clone())?;
+                        let mut page = buff.page();
+                        update.redo(page.contents());
+                        page.set_modified(txnum, None);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        bm.flush_all_dirty()?;
+        let mut lm = lm.lock().unwrap();
+        lm.discard_history()?;
+        let rec = LogRecord::Checkpoint {
+            next_txnum: txn_ids.peek(),
+        };
+        let lsn = lm.append(&rec.to_bytes())?;
+        lm.flush(lsn)?;
+        Ok(end)
+    }
+
+    // Write a setint record to the log and return its lsn.
+    // The record holds the value currently stored at the offset,
+    // which is what an undo would restore, and the new value.
+    pub fn set_int(&self, buff: &mut BufferPage<S>, offset: usize, new: i32) -> DbResult<Lsn> {
+        let old = buff.contents().get_int(offset);
+        let block = buff
+            .block()
+            .expect("buffer is not assigned to a block")
+            .clone();
+        self.write(LogRecord::SetInt {
+            txnum: self.txnum,
+            block,
+            offset,
+            old,
+            new,
+        })
+    }
+
+    // Write the records of a nested top action setting the
+    // integer at the offset: a setint record, then a
+    // compensation record marking it as undone, which is
+    // what keeps a rollback from undoing it. The compensation
+    // record repeats the update, so that it is redone after
+    // a crash, and its lsn is returned.
+    pub fn set_int_nested(
+        &self,
+        buff: &mut BufferPage<S>,
+        offset: usize,
+        new: i32,
+    ) -> DbResult<Lsn> {
+        let lsn = self.set_int(buff, offset, new)?;
+        let old = buff.contents().get_int(offset);
+        let block = buff
+            .block()
+            .expect("buffer is not assigned to a block")
+            .clone();
+        self.write(LogRecord::Compensation {
+            txnum: self.txnum,
+            undone: lsn,
+            update: Box::new(LogRecord::SetInt {
+                txnum: self.txnum,
+                block,
+                offset,
+                old,
+                new,
+            }),
+        })
+    }
+
+    // Write a setstring record to the log and return its lsn.
+    pub fn set_string(&self, buff: &mut BufferPage<S>, offset: usize, new: &str) -> DbResult<Lsn> {
+        let old = buff.contents().get_string(offset);
+        let block = buff
+            .block()
+            .expect("buffer is not assigned to a block")
+            .clone();
+        self.write(LogRecord::SetString {
+            txnum: self.txnum,
+            block,
+            offset,
+            old,
+            new: new.to_string(),
+        })
+    }
+
+    // Write a setbytes record to the log and return its lsn.
+    pub fn set_bytes(&self, buff: &mut BufferPage<S>, offset: usize, new: &[u8]) -> DbResult<Lsn> {
+        let old = buff.contents().get_bytes(offset);
+        let block = buff
+            .block()
+            .expect("buffer is not assigned to a block")
+            .clone();
+        self.write(LogRecord::SetBytes {
+            txnum: self.txnum,
+            block,
+            offset,
+            old,
+            new: new.to_vec(),
+        })
+    }
+
+    fn write(&self, rec: LogRecord) -> DbResult<Lsn> {
+        let lsn = self.lm.lock().unwrap().append(&rec.to_bytes())?;
+        Ok(lsn)
+    }
+}
+
+// Undo the update record having the lsn: write a compensation
+// record, then restore the old value in the buffer, which is
+// marked as modified by the compensation record.
+fn undo<S: BlockStore>(
+    lm: &Mutex<LogManager<S>>,
+    bm: &BufferManager<S>,
+    lsn: Lsn,
+    rec: &LogRecord,
+) -> DbResult<()> {
+    let Some(update) = rec.undo_update() else {
+        return Ok(());
+    };
+    let buff = bm.pin(update.block().unwrap().clone())?;
+    let clr = LogRecord::Compensation {
+        txnum: rec.txnum(),
+        undone: lsn,
+        update: Box::new(update),
+    };
+    let clr_lsn = lm.lock().unwrap().append(&clr.to_bytes())?;
+    let mut page = buff.page();
+    clr.redo(page.contents());
+    page.set_modified(rec.txnum(), Some(clr_lsn));
+    Ok(())
+}
+
+// Redo the update or compensation record having the lsn,
+// unless its block already holds the modification.
+// A block missing from its file is passed over, or
+// appended if extend is true.
+fn redo<S: BlockStore>(
+    fm: &S,
+    bm: &BufferManager<S>,
+    lsn: Lsn,
+    rec: &LogRecord,
+    extend: bool,
+) -> DbResult<()> {
+    let Some(block) = rec.block() else {
+        return Ok(());
+    };
+    if extend {
+        while !exists(fm, block) {
+            fm.append(block.filename())?;
+        }
+    } else if !exists(fm, block) {
+        return Ok(());
+    }
+    let buff = bm.pin(block.clone())?;
+    let mut page = buff.page();
+    if page.page_lsn() < lsn {
+        rec.redo(page.contents());
+        page.set_modified(rec.txnum(), Some(lsn));
+    }
+    Ok(())
+}
+
+// The truncations found by a forward scan of the log.
+// Those a transaction logs take effect when it commits,
+// unless cancelled before, and are then pending, the
+// last of each file, until a later record shows that
+// the file was cut down, by modifying one of the blocks
+// cut off: the truncating transaction holds the lock on
+// the file until it is cut down, so no other transaction
+// can modify them before. A checkpoint is only taken once
+// every earlier truncation is made.
+#[derive(Default)]
+struct Truncations {
+    logged: HashMap<i32, Vec<(Lsn, String, u64)>>,
+    pending: HashMap<String, u64>,
+}
+
+impl Truncations {
+    fn note(&mut self, lsn: Lsn, rec: &LogRecord) {
+        match rec {
+            LogRecord::Checkpoint { .. } => {
+                self.logged.clear();
+                self.pending.clear();
+            }
+            LogRecord::Truncate {
+                txnum,
+                filename,
+                len,
+            } => self
+                .logged
+                .entry(*txnum)
+                .or_default()
+                .push((lsn, filename.clone(), *len)),
+            LogRecord::Compensation { txnum, undone, .. } => {
+                if let Some(logged) = self.logged.get_mut(txnum) {
+                    logged.retain(|(asked, _, _)| asked != undone);
+                }
+                self.note_block(rec);
+            }
+            LogRecord::Commit { txnum, .. } => {
+                for (_, filename, len) in self.logged.remove(txnum).unwrap_or_default() {
+                    self.pending.insert(filename, len);
+                }
+            }
+            LogRecord::Rollback { txnum } => {
+                self.logged.remove(txnum);
+            }
+            _ => self.note_block(rec),
+        }
+    }
+
+    // Forget the pending truncation that the record
+    // shows to have been made.
+    fn note_block(&mut self, rec: &LogRecord) {
+        if let Some(block) = rec.block() {
+            let filename = block.filename();
+            if self
+                .pending
+                .get(filename)
+                .is_some_and(|&len| block.number() >= len)
+            {
+                self.pending.remove(filename);
+            }
+        }
+    }
+
+    // Cut down the files of the pending truncations, forgetting
+    // the buffers of the blocks cut off, which redo may have
+    // modified.
+    fn apply<S: BlockStore>(&mut self, fm: &S, bm: &BufferManager<S>) -> DbResult<()> {
+        for (filename, len) in self.pending.drain() {
+            bm.discard(&filename, len);
+            fm.truncate(&filename, len)?;
+        }
+        Ok(())
+    }
+}
+
+fn exists(fm: &impl BlockStore, block: &BlockId) -> bool {
+    fm.length(block.filename())
+        .is_ok_and(|len| block.number() < len)
+}
+
+// Keep the allocator from handing out a transaction
+// number that the record shows to have been used.
+fn advance_txn_ids(txn_ids: &TxnIdAllocator, rec: &LogRecord) {
+    match rec {
+        LogRecord::Checkpoint { next_txnum } => txn_ids.advance_to(*next_txnum),
+        _ => txn_ids.advance_to(rec.txnum().saturating_add(1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, file::Page, log::dump::dump};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_recover() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let (committed, unfinished) = {
+            let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+            let committed = db.file_manager().append("testfile")?;
+            let unfinished = db.file_manager().append("testfile")?;
+
+            let tx1 = db.new_tx()?;
+            tx1.pin(&committed)?;
+            tx1.set_int(&committed, 80, 1, true)?;
+            tx1.set_string(&committed, 40, "one", true)?;
+            tx1.commit()?;
+            // the committed update is lost from the disk
+            db.file_manager().write(&committed, &mut Page::new(400))?;
+
+            let tx2 = db.new_tx()?;
+            tx2.pin(&unfinished)?;
+            tx2.set_int(&unfinished, 80, 2, true)?;
+            tx2.set_bytes(&unfinished, 100, &[5, 6], true)?;
+            // the unfinished update reaches the disk
+            db.sync()?;
+            tx2.crash();
+            db.crash();
+            (committed, unfinished)
+        };
+
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx = db.new_tx()?;
+        tx.pin(&committed)?;
+        tx.pin(&unfinished)?;
+        assert_eq!(tx.get_int(&committed, 80)?, 1);
+        assert_eq!(tx.get_string(&committed, 40)?, "one");
+        assert_eq!(tx.get_int(&unfinished, 80)?, 0);
+        assert_eq!(tx.get_bytes(&unfinished, 100)?, Vec::<u8>::new());
+        tx.commit()?;
+        let ops = |db: &SimpleDB| -> DbResult<Vec<&str>> {
+            let records = dump(db.file_manager(), SimpleDB::LOG_FILE)?;
+            Ok(records.into_iter().map(|rec| rec.op).collect())
+        };
+        assert_eq!(
+            ops(&db)?[7..],
+            [
+                "compensation",
+                "compensation",
+                "rollback",
+                "start",
+                "commit"
+            ]
+        );
+        drop(tx);
+        db.crash();
+
+        // recovering again has nothing left to undo
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        assert_eq!(ops(&db)?.len(), 12);
+        Ok(())
+    }
+
+    // Copy the files of the database directory to another.
+    fn copy_files(from: &std::path::Path, to: &std::path::Path, prefix: &str) {
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_name().to_string_lossy().starts_with(prefix) {
+                std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_restore() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let backup_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.log_manager().lock().unwrap().set_archive(true);
+        let blk = db.file_manager().append("testfile")?;
+        let tx = db.new_tx()?;
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 80, 1, true)?;
+        tx.commit()?;
+        db.checkpoint()?;
+        copy_files(temp_dir.path(), backup_dir.path(), "");
+
+        let tx = db.new_tx()?;
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 80, 2, true)?;
+        tx.commit()?;
+        // blocks appended after the backup are restored too
+        let added = db.file_manager().append("testfile")?;
+        let tx = db.new_tx()?;
+        tx.pin(&added)?;
+        tx.set_string(&added, 0, "added", true)?;
+        tx.commit()?;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let before_mistake = SystemTime::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        db.checkpoint()?;
+        let tx = db.new_tx()?;
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 80, 3, true)?;
+        tx.commit()?;
+        let mistake = dump(db.file_manager(), SimpleDB::LOG_FILE)?
+            .into_iter()
+            .find(|rec| rec.op == "setint")
+            .map(|rec| Lsn(rec.lsn))
+            .unwrap();
+        db.sync()?;
+        drop(db);
+
+        for target in [
+            RecoveryTarget::Time(before_mistake),
+            RecoveryTarget::Lsn(mistake),
+        ] {
+            let dir = TempDir::new().unwrap();
+            copy_files(backup_dir.path(), dir.path(), "");
+            copy_files(temp_dir.path(), dir.path(), SimpleDB::LOG_FILE);
+            let db = SimpleDB::restore(FileManager::new(dir.path(), 400)?, 8, target)?;
+            let tx = db.new_tx()?;
+            tx.pin(&blk)?;
+            tx.pin(&added)?;
+            assert_eq!(tx.get_int(&blk, 80)?, 2);
+            assert_eq!(tx.get_string(&added, 0)?, "added");
+            tx.commit()?;
+            assert!(db.log_manager().lock().unwrap().archives()?.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_recover_interrupted_rollback() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = Arc::new(FileManager::new(temp_dir.path(), 400)?);
+        let blk = fm.append("testfile")?;
+        let lm = Arc::new(Mutex::new(LogManager::new(
+            Arc::clone(&fm),
+            SimpleDB::LOG_FILE.to_string(),
+        )?));
+        let bm = BufferManager::new(Arc::clone(&fm), Arc::clone(&lm), 8);
+
+        // a transaction set the value twice, and its rollback
+        // undid the second update before the crash
+        let update = |old, new| LogRecord::SetInt {
+            txnum: 1,
+            block: blk.clone(),
+            offset: 80,
+            old,
+            new,
+        };
+        let mut log = lm.lock().unwrap();
+        log.append(&LogRecord::Start { txnum: 1 }.to_bytes())?;
+        log.append(&update(0, 1).to_bytes())?;
+        let second = log.append(&update(1, 2).to_bytes())?;
+        let clr = LogRecord::Compensation {
+            txnum: 1,
+            undone: second,
+            update: Box::new(update(2, 1)),
+        };
+        log.append(&clr.to_bytes())?;
+        drop(log);
+
+        let txn_ids = TxnIdAllocator::new();
+        assert_eq!(RecoveryManager::recover(&fm, &lm, &bm, &txn_ids)?, 1);
+        assert_eq!(txn_ids.peek(), 2);
+        let mut page = Page::new(400);
+        fm.read(&blk, &mut page)?;
+        assert_eq!(page.get_int(80), 0);
+        let records = dump(Arc::clone(&fm), SimpleDB::LOG_FILE)?;
+        let undone: Vec<_> = records.iter().filter_map(|rec| rec.undone).collect();
+        assert_eq!(undone, [second.0, second.0 - 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recover_truncation() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let fm = Arc::new(FileManager::new(temp_dir.path(), 400)?);
+        for (filename, len) in [("cut", 4), ("regrown", 3), ("kept", 2), ("reused", 3)] {
+            for _ in 0..len {
+                fm.append(filename)?;
+            }
+        }
+        let lm = Arc::new(Mutex::new(LogManager::new(
+            Arc::clone(&fm),
+            SimpleDB::LOG_FILE.to_string(),
+        )?));
+        let bm = Arc::new(BufferManager::new(Arc::clone(&fm), Arc::clone(&lm), 8));
+        let new_rm = |txnum| RecoveryManager::new(txnum, Arc::clone(&lm), Arc::clone(&bm));
+
+        // a transaction committed two truncations, one of them
+        // after cancelling a shorter one, and the crash came
+        // before the files were cut down
+        let rm = new_rm(1)?;
+        rm.truncate("cut", 1)?;
+        let asked = rm.truncate("cut", 0)?;
+        rm.cancel_truncate(asked, "cut", 0)?;
+        rm.truncate("regrown", 0)?;
+        // the last truncation of a file counts, as when the
+        // transaction appended a block after cutting it down
+        rm.truncate("reused", 0)?;
+        rm.truncate("reused", 1)?;
+        rm.commit()?;
+        // a later transaction modified a block cut off,
+        // so that file was cut down and has grown again
+        let rm = new_rm(2)?;
+        let buff = bm.pin(BlockId::new("regrown", 2))?;
+        let mut page = buff.page();
+        let lsn = rm.set_int(&mut page, 80, 7)?;
+        page.contents().set_int(80, 7);
+        page.set_modified(2, Some(lsn));
+        drop(page);
+        drop(buff);
+        rm.commit()?;
+        // and the truncation of a rolled back transaction is not made
+        let rm = new_rm(3)?;
+        rm.truncate("kept", 0)?;
+        rm.rollback()?;
+
+        let txn_ids = TxnIdAllocator::new();
+        assert!(RecoveryManager::needs_recovery(&fm, &lm, &txn_ids)?);
+        RecoveryManager::recover(&fm, &lm, &bm, &txn_ids)?;
+        assert!(!RecoveryManager::needs_recovery(&fm, &lm, &txn_ids)?);
+        let lengths: Vec<u64> = ["cut", "regrown", "kept", "reused"]
+            .iter()
+            .map(|filename| fm.length(filename).unwrap())
+            .collect();
+        assert_eq!(lengths, [1, 3, 2, 1]);
+        let mut page = Page::new(400);
+        fm.read(&BlockId::new("regrown", 2), &mut page)?;
+        assert_eq!(page.get_int(80), 7);
+        Ok(())
+    }
+}
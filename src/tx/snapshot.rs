@@ -0,0 +1,289 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use super::recovery::LogRecord;
+use crate::{
+    error::{DbError, DbResult},
+    file::{BlockId, Page},
+    log::{LogManager, Lsn},
+};
+
+// The database as it was when a snapshot transaction started,
+// for multi-version reads.
+// A block is read as of the snapshot by taking its current
+// contents and undoing, newest first, the logged modifications
+// that the snapshot must not see: those made after it started,
+// and those of the transactions that had not completed by then.
+// The log is read back to the latest checkpoint, before which
+// every transaction had completed, the first time a block is
+// read or written, and the modifications found are indexed by
+// block; later reads only go over the records appended since.
+// Reading a snapshot takes no locks, so readers never wait
+// for writers, nor writers for them.
+pub struct Snapshot {
+    lm: Arc<Mutex<LogManager>>,
+    txnum: i32,
+    lsn: Lsn,
+    // the versions built for the blocks pinned by the transaction
+    versions: Mutex<HashMap<BlockId, Page>>,
+    // the blocks modified by the transaction, whose current
+    // contents are what it reads
+    written: Mutex<HashSet<BlockId>>,
+    unseen: Mutex<UnseenUpdates>,
+}
+
+// The logged modifications that the snapshot does not see,
+// as read from the log so far.
+#[derive(Default)]
+struct UnseenUpdates {
+    // the LSN of the newest record read, if the log has been read
+    end: Option<Lsn>,
+    // the modifications of each block, oldest first
+    blocks: HashMap<BlockId, Vec<LogRecord>>,
+}
+
+impl Snapshot {
+    pub fn new(lm: Arc<Mutex<LogManager>>, txnum: i32) -> Self {
+        let lsn = lm.lock().unwrap().latest_lsn();
+        Snapshot {
+            lm,
+            txnum,
+            lsn,
+            versions: Mutex::new(HashMap::new()),
+            written: Mutex::new(HashSet::new()),
+            unseen: Mutex::new(UnseenUpdates::default()),
+        }
+    }
+
+    // The LSN of the last record that the snapshot sees.
+    pub fn lsn(&self) -> Lsn {
+        self.lsn
+    }
+
+    // Read from the version of the block in the snapshot.
+    // If it has not been built, it is built from the current
+    // contents of the block, as returned by current.
+    pub fn read<T>(
+        &self,
+        blk: &BlockId,
+        current: impl FnOnce() -> DbResult<Page>,
+        f: impl FnOnce(&mut Page) -> T,
+    ) -> DbResult<T> {
+        if self.written.lock().unwrap().contains(blk) {
+            return Ok(f(&mut current()?));
+        }
+        let mut versions = self.versions.lock().unwrap();
+        if !versions.contains_key(blk) {
+            let mut page = current()?;
+            for rec in self.unseen_updates(blk)? {
+                let update = match &rec {
+                    LogRecord::Compensation { update, .. } => update.undo_update(),
+                    _ => rec.undo_update(),
+                };
+                if let Some(update) = update {
+                    update.redo(&mut page);
+                }
+            }
+            versions.insert(blk.clone(), page);
+        }
+        Ok(f(versions.get_mut(blk).unwrap()))
+    }
+
+    // Prepare for the transaction to modify the block, on
+    // which it holds an XLock. A block that another transaction
+    // has modified since the snapshot cannot be, as that
+    // modification would be overwritten without having been
+    // seen; the first transaction to modify the block wins.
+    pub fn write(&self, blk: &BlockId) -> DbResult<()> {
+        let mut written = self.written.lock().unwrap();
+        if written.contains(blk) {
+            return Ok(());
+        }
+        if !self.unseen_updates(blk)?.is_empty() {
+            return Err(DbError::SerializationFailure(blk.clone()));
+        }
+        self.versions.lock().unwrap().remove(blk);
+        written.insert(blk.clone());
+        Ok(())
+    }
+
+    // Drop the version of a block that is no longer pinned.
+    pub fn forget(&self, blk: &BlockId) {
+        self.versions.lock().unwrap().remove(blk);
+    }
+
+    // Return the logged modifications of the block by other
+    // transactions that the snapshot does not see, newest first.
+    // The records appended since the log was last read are
+    // read first, back to the checkpoint the first time.
+    fn unseen_updates(&self, blk: &BlockId) -> DbResult<Vec<LogRecord>> {
+        let mut unseen = self.unseen.lock().unwrap();
+        let (end, mut iter) = {
+            let mut lm = self.lm.lock().unwrap();
+            (lm.latest_lsn(), lm.iter()?)
+        };
+        if unseen.end != Some(end) {
+            // the transactions completed before the snapshot
+            let mut completed = HashSet::new();
+            let mut updates = Vec::new();
+            while let Some(bytes) = iter.next() {
+                if unseen.end.is_some_and(|read| iter.lsn() <= read) {
+                    break;
+                }
+                let Some(rec) = LogRecord::from_bytes(bytes?) else {
+                    continue;
+                };
+                let before = iter.lsn() <= self.lsn;
+                match &rec {
+                    LogRecord::Checkpoint { .. } => break,
+                    LogRecord::Commit { txnum, .. } | LogRecord::Rollback { txnum } if before => {
+                        completed.insert(*txnum);
+                    }
+                    _ if rec.block().is_some()
+                        && rec.txnum() != self.txnum
+                        && !(before && completed.contains(&rec.txnum())) =>
+                    {
+                        updates.push(rec);
+                    }
+                    _ => {}
+                }
+            }
+            for rec in updates.into_iter().rev() {
+                let block = rec.block().unwrap().clone();
+                unseen.blocks.entry(block).or_default().push(rec);
+            }
+            unseen.end = Some(end);
+        }
+        Ok(unseen
+            .blocks
+            .get(blk)
+            .map_or_else(Vec::new, |updates| updates.iter().rev().cloned().collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::SimpleDB, driver::Connection, tx::concurrency::IsolationLevel};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_snapshot_reads() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+        let new_tx_at = |isolation| {
+            let tx = db.new_tx_with_isolation(isolation).unwrap();
+            tx.pin(&blk).unwrap();
+            tx
+        };
+
+        let tx1 = new_tx_at(IsolationLevel::Serializable);
+        tx1.set_int(&blk, 80, 1, true)?;
+        tx1.commit()?;
+        let tx2 = new_tx_at(IsolationLevel::Serializable);
+        tx2.set_int(&blk, 80, 2, true)?;
+        tx2.set_string(&blk, 40, "two", true)?;
+
+        // the update of tx2 is not committed at the snapshot,
+        // and reading does not wait for its xlock
+        let reader = new_tx_at(IsolationLevel::Snapshot);
+        assert_eq!(reader.get_int(&blk, 80)?, 1);
+        tx2.commit()?;
+        let tx3 = new_tx_at(IsolationLevel::Serializable);
+        tx3.set_int(&blk, 80, 3, true)?;
+        tx3.rollback()?;
+        let tx4 = new_tx_at(IsolationLevel::Serializable);
+        tx4.set_int(&blk, 80, 4, true)?;
+        tx4.commit()?;
+
+        // a version built again skips the later updates
+        reader.unpin(&blk);
+        reader.pin(&blk)?;
+        assert_eq!(reader.get_int(&blk, 80)?, 1);
+        assert_eq!(reader.get_string(&blk, 40)?, "");
+        reader.commit()?;
+
+        let reader = new_tx_at(IsolationLevel::Snapshot);
+        assert_eq!(reader.get_int(&blk, 80)?, 4);
+        assert_eq!(reader.get_string(&blk, 40)?, "two");
+        reader.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_writes() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk1 = db.file_manager().append("testfile")?;
+        let blk2 = db.file_manager().append("testfile")?;
+
+        let writer = db.new_tx_with_isolation(IsolationLevel::Snapshot)?;
+        writer.pin(&blk1)?;
+        writer.pin(&blk2)?;
+        let tx = db.new_tx()?;
+        tx.pin(&blk1)?;
+        tx.set_int(&blk1, 80, 1, true)?;
+        tx.commit()?;
+
+        // the first update of the block wins
+        let result = writer.set_int(&blk1, 80, 2, true);
+        assert!(matches!(result, Err(DbError::SerializationFailure(b)) if b == blk1));
+        assert_eq!(writer.get_int(&blk1, 80)?, 0);
+        writer.set_int(&blk2, 80, 5, true)?;
+        assert_eq!(writer.get_int(&blk2, 80)?, 5);
+        writer.rollback()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_updates_after_log_read() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk1 = db.file_manager().append("testfile")?;
+        let blk2 = db.file_manager().append("testfile")?;
+
+        // the first read indexes the log as it is
+        let reader = db.new_tx_with_isolation(IsolationLevel::Snapshot)?;
+        reader.pin(&blk1)?;
+        reader.pin(&blk2)?;
+        assert_eq!(reader.get_int(&blk1, 80)?, 0);
+
+        // the updates logged since are found by the next ones
+        let tx = db.new_tx()?;
+        tx.pin(&blk1)?;
+        tx.pin(&blk2)?;
+        tx.set_int(&blk1, 80, 1, true)?;
+        tx.set_int(&blk2, 80, 2, true)?;
+        tx.commit()?;
+        assert_eq!(reader.get_int(&blk2, 80)?, 0);
+        let result = reader.set_int(&blk1, 80, 3, true);
+        assert!(matches!(result, Err(DbError::SerializationFailure(b)) if b == blk1));
+        reader.rollback()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot_query() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.execute_update("create table t (a int)")?;
+        for i in 0..3 {
+            db.execute_update(&format!("insert into t (a) values ({})", i))?;
+        }
+
+        let mut conn = Connection::new(&db);
+        conn.begin_with_isolation(IsolationLevel::Snapshot)?;
+        assert_eq!(conn.execute_query("select a from t")?.len(), 3);
+        for i in 3..40 {
+            db.execute_update(&format!("insert into t (a) values ({})", i))?;
+        }
+        db.execute_update("delete from t where a = 0")?;
+        assert_eq!(conn.execute_query("select a from t")?.len(), 3);
+        conn.commit()?;
+        assert_eq!(conn.execute_query("select a from t")?.len(), 39);
+        Ok(())
+    }
+}
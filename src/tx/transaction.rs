@@ -0,0 +1,1069 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock, RwLockReadGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{
+    concurrency::{ConcurrencyManager, IsolationLevel, LockTable},
+    recovery::{Durability, RecoveryManager},
+    snapshot::Snapshot,
+    BufferList, TxnIdAllocator,
+};
+use crate::{
+    buffer::{BufferManager, BufferPage, BufferStats},
+    error::{DbError, DbResult},
+    file::{is_temporary, BlockId, FileManager, Page},
+    log::{LogManager, Lsn},
+};
+
+// Provide transaction management for clients,
+// ensuring that transactions are serializable, unless
+// created at a weaker isolation level, and
+// that all modifications are written to the log.
+// Buffers pinned through the transaction are remembered
+// in its buffer list, so that they can all be unpinned
+// when it completes.
+// A read-only transaction has no recovery manager, as
+// it writes nothing to the log.
+// Another thread can abort the transaction, as when it has
+// run for too long: each operation holds a read lock on ops,
+// and the abort waits for the write lock, so that it rolls
+// back between operations; the operations after it fail.
+// Files are truncated only once the transaction commits,
+// since a truncation cannot be undone; it is logged when
+// asked for, so that recovery can redo it. Blocks appended
+// to the file before then reuse the blocks to be cut off,
+// and every modification of those is logged, so that a
+// rollback brings their old contents back.
+pub struct Transaction {
+    txnum: i32,
+    started: Instant,
+    ops: RwLock<()>,
+    aborted: AtomicBool,
+    completed: AtomicBool,
+    fm: Arc<FileManager>,
+    bm: Arc<BufferManager>,
+    rm: Option<RecoveryManager>,
+    cm: ConcurrencyManager,
+    snapshot: Option<Snapshot>,
+    buffers: Mutex<BufferList>,
+    // The files to truncate on commit, with their new length
+    // and the LSN of the record of each truncation; the last
+    // truncation of a file gives its length.
+    truncations: Mutex<Vec<(String, u64, Lsn)>>,
+}
+
+impl Transaction {
+    // The number of the block that no file reaches,
+    // used for the end-of-file lock.
+    const END_OF_FILE: u64 = u64::MAX;
+
+    // The number of the block that stands for the whole file,
+    // used for the lock of a truncation.
+    const WHOLE_FILE: u64 = u64::MAX - 1;
+
+    // Create a new transaction, numbered by the allocator
+    // of the database, and its associated
+    // recovery and concurrency managers.
+    pub fn new(
+        fm: Arc<FileManager>,
+        lm: Arc<Mutex<LogManager>>,
+        bm: Arc<BufferManager>,
+        lock_table: Arc<LockTable>,
+        txn_ids: &TxnIdAllocator,
+    ) -> DbResult<Self> {
+        Self::with_isolation(fm, lm, bm, lock_table, txn_ids, IsolationLevel::default())
+    }
+
+    // Create a new transaction running at the specified
+    // isolation level, rather than being serializable.
+    pub fn with_isolation(
+        fm: Arc<FileManager>,
+        lm: Arc<Mutex<LogManager>>,
+        bm: Arc<BufferManager>,
+        lock_table: Arc<LockTable>,
+        txn_ids: &TxnIdAllocator,
+        isolation: IsolationLevel,
+    ) -> DbResult<Self> {
+        let txnum = txn_ids.allocate();
+        let snapshot =
+            (isolation == IsolationLevel::Snapshot).then(|| Snapshot::new(Arc::clone(&lm), txnum));
+        let rm = RecoveryManager::new(txnum, lm, Arc::clone(&bm))?;
+        Ok(Self::create(
+            fm,
+            bm,
+            lock_table,
+            txnum,
+            isolation,
+            Some(rm),
+            snapshot,
+        ))
+    }
+
+    // Create a transaction for reading only, such as for
+    // a report. It writes no log records and takes no XLocks,
+    // modifying only the temporary tables it creates, and
+    // reads at the read committed level, so that it keeps
+    // no SLocks.
+    pub fn read_only(
+        fm: Arc<FileManager>,
+        bm: Arc<BufferManager>,
+        lock_table: Arc<LockTable>,
+        txn_ids: &TxnIdAllocator,
+    ) -> Self {
+        let txnum = txn_ids.allocate();
+        let isolation = IsolationLevel::ReadCommitted;
+        Self::create(fm, bm, lock_table, txnum, isolation, None, None)
+    }
+
+    fn create(
+        fm: Arc<FileManager>,
+        bm: Arc<BufferManager>,
+        lock_table: Arc<LockTable>,
+        txnum: i32,
+        isolation: IsolationLevel,
+        rm: Option<RecoveryManager>,
+        snapshot: Option<Snapshot>,
+    ) -> Self {
+        let buffers = BufferList::new(Arc::clone(&bm));
+
+        Transaction {
+            txnum,
+            started: Instant::now(),
+            ops: RwLock::new(()),
+            aborted: AtomicBool::new(false),
+            completed: AtomicBool::new(false),
+            fm,
+            bm,
+            rm,
+            cm: ConcurrencyManager::with_isolation(lock_table, txnum, isolation),
+            snapshot,
+            buffers: Mutex::new(buffers),
+            truncations: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Set what the commit of the transaction waits for,
+    // rather than for its buffers to be written.
+    // A read-only transaction writes nothing anyway.
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        if let Some(rm) = &mut self.rm {
+            rm.set_durability(durability);
+        }
+        self
+    }
+
+    pub fn txnum(&self) -> i32 {
+        self.txnum
+    }
+
+    pub fn isolation(&self) -> IsolationLevel {
+        self.cm.isolation()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.rm.is_none()
+    }
+
+    // How long ago the transaction started.
+    pub fn age(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    // The number of blocks the transaction holds locks on.
+    pub fn lock_count(&self) -> usize {
+        self.cm.lock_count()
+    }
+
+    // Return true once the transaction has committed
+    // or rolled back.
+    pub fn is_completed(&self) -> bool {
+        self.completed.load(Ordering::SeqCst)
+    }
+
+    // Commit the current transaction.
+    // Flush all modified buffers (and their log records),
+    // write and flush a commit record to the log, unless
+    // the transaction's durability says otherwise,
+    // unpin any pinned buffers, truncate the files it
+    // asked to, and release all locks.
+    // The transaction has committed even if a truncation fails.
+    pub fn commit(&self) -> DbResult<()> {
+        let _op = self.begin_op()?;
+        if let Some(rm) = &self.rm {
+            rm.commit()?;
+        }
+        let truncations = std::mem::take(&mut *self.truncations.lock().unwrap());
+        self.unpin_all();
+        let truncated = self.truncate_files(truncations);
+        self.cm.release();
+        self.completed.store(true, Ordering::SeqCst);
+        truncated
+    }
+
+    // Cut the files down as asked, once the commit record is on
+    // disk, each to the length of its last truncation.
+    // The buffers of the blocks cut off are discarded,
+    // so that none of them is written back later, extending
+    // the file again. The locks on the files are held until
+    // then, so no other transaction reads them meanwhile.
+    fn truncate_files(&self, truncations: Vec<(String, u64, Lsn)>) -> DbResult<()> {
+        if truncations.is_empty() {
+            return Ok(());
+        }
+        if let Some(rm) = &self.rm {
+            rm.flush()?;
+        }
+        let mut cut = HashSet::new();
+        for (filename, len, _) in truncations.into_iter().rev() {
+            if cut.insert(filename.clone()) {
+                self.bm.discard(&filename, len);
+                self.fm.truncate(&filename, len)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Roll back the current transaction.
+    // Undo any modified values, flush those buffers,
+    // write and flush a rollback record to the log,
+    // release all locks, and unpin any pinned buffers.
+    // A transaction that was aborted is already rolled back.
+    pub fn rollback(&self) -> DbResult<()> {
+        let _op = self.ops.read().unwrap();
+        self.rollback_locked()
+    }
+
+    // Roll back the transaction from another thread, once
+    // its current operation, if any, is done. An operation
+    // waiting for a lock is cancelled rather than waited for.
+    // Its later operations fail with DbError::TransactionAborted.
+    // Returns false if the transaction had already completed.
+    pub fn abort(&self) -> DbResult<bool> {
+        self.aborted.store(true, Ordering::SeqCst);
+        self.cm.cancel();
+        let _ops = self.ops.write().unwrap();
+        if self.is_completed() {
+            self.cm.release();
+            return Ok(false);
+        }
+        self.rollback_locked()?;
+        Ok(true)
+    }
+
+    fn rollback_locked(&self) -> DbResult<()> {
+        if self.is_completed() {
+            return Ok(());
+        }
+        self.truncations.lock().unwrap().clear();
+        if let Some(rm) = &self.rm {
+            rm.rollback()?;
+        }
+        self.cm.release();
+        self.unpin_all();
+        self.completed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    // Start an operation of the transaction,
+    // unless the transaction has been aborted.
+    fn begin_op(&self) -> DbResult<RwLockReadGuard<'_, ()>> {
+        let op = self.ops.read().unwrap();
+        if self.aborted.load(Ordering::SeqCst) {
+            return Err(DbError::TransactionAborted(self.txnum));
+        }
+        Ok(op)
+    }
+
+    // Set how long the transaction waits for a lock held by
+    // another before failing with DbError::LockTimeout,
+    // instead of the timeout of the lock table.
+    pub fn set_lock_timeout(&self, timeout: Duration) {
+        self.cm.set_timeout(timeout);
+    }
+
+    // Mark the current point of the transaction, so that
+    // the modifications made after it can be undone alone.
+    pub fn savepoint(&self) -> Lsn {
+        self.rm.as_ref().map_or(Lsn(0), RecoveryManager::savepoint)
+    }
+
+    // Undo the modifications made since the savepoint, and
+    // cancel the truncations asked for since.
+    // The transaction stays active, keeping its locks
+    // and pinned buffers.
+    pub fn rollback_to(&self, savepoint: Lsn) -> DbResult<()> {
+        let _op = self.begin_op()?;
+        let Some(rm) = &self.rm else {
+            return Ok(());
+        };
+        rm.rollback_to(savepoint)?;
+        let mut truncations = self.truncations.lock().unwrap();
+        for (filename, len, asked) in truncations.iter() {
+            if *asked > savepoint {
+                rm.cancel_truncate(*asked, filename, *len)?;
+            }
+        }
+        truncations.retain(|(_, _, asked)| *asked <= savepoint);
+        Ok(())
+    }
+
+    // Pin the specified block.
+    // The transaction manages the buffer for the client.
+    pub fn pin(&self, blk: &BlockId) -> DbResult<()> {
+        let _op = self.begin_op()?;
+        self.buffers.lock().unwrap().pin(blk)
+    }
+
+    // Pin all of the specified blocks, or none of them.
+    // Operations that need several blocks at once
+    // use this to avoid waiting while holding some of them.
+    pub fn pin_all(&self, blks: &[BlockId]) -> DbResult<()> {
+        let _op = self.begin_op()?;
+        self.buffers.lock().unwrap().pin_all(blks)
+    }
+
+    // Unpin the specified block.
+    // The transaction looks up the buffer pinned to this block,
+    // and unpins it.
+    pub fn unpin(&self, blk: &BlockId) {
+        let left = self.buffers.lock().unwrap().unpin(blk);
+        if let Some(snapshot) = &self.snapshot {
+            if left == 0 {
+                snapshot.forget(blk);
+            }
+        }
+    }
+
+    // Return the integer value stored at the
+    // specified offset of the specified block.
+    // The method first obtains an SLock on the block,
+    // then it calls the buffer to retrieve the value.
+    // The SLock is kept or released as the isolation
+    // level of the transaction requires.
+    pub fn get_int(&self, blk: &BlockId, offset: usize) -> DbResult<i32> {
+        self.read(blk, |page| page.get_int(offset))
+    }
+
+    // Return the string value stored at the
+    // specified offset of the specified block.
+    pub fn get_string(&self, blk: &BlockId, offset: usize) -> DbResult<String> {
+        self.read(blk, |page| page.get_string(offset))
+    }
+
+    // Return the byte array stored at the
+    // specified offset of the specified block.
+    pub fn get_bytes(&self, blk: &BlockId, offset: usize) -> DbResult<Vec<u8>> {
+        self.read(blk, |page| page.get_bytes(offset))
+    }
+
+    // Store an integer at the specified offset of the specified block.
+    // The method first obtains an XLock on the block.
+    // It then reads the current value at that offset,
+    // puts it into an update log record, and
+    // writes that record to the log.
+    // Finally, it calls the buffer to store the value,
+    // passing in the LSN of the log record and the transaction's id.
+    pub fn set_int(&self, blk: &BlockId, offset: usize, val: i32, ok_to_log: bool) -> DbResult<()> {
+        let _op = self.begin_op()?;
+        self.xlock(blk)?;
+        let buff = self.buffer(blk)?;
+        let mut buff = buff.lock().unwrap();
+        let lsn = match &self.rm {
+            Some(rm) if ok_to_log || self.is_cut_off(blk) => {
+                Some(rm.set_int(&mut buff, offset, val)?)
+            }
+            _ => None,
+        };
+        buff.contents().set_int(offset, val);
+        buff.set_modified(self.txnum, lsn);
+        Ok(())
+    }
+
+    // Replace the integer at the specified offset of the
+    // specified block by the value that f returns for it,
+    // and return the value replaced.
+    // The change is a nested top action: it takes no lock,
+    // so no other transaction waits for this one, and it is
+    // not undone if the transaction rolls back, though it is
+    // redone after a crash. The caller must keep other
+    // transactions from changing the value at the same time.
+    pub fn update_int_nested(
+        &self,
+        blk: &BlockId,
+        offset: usize,
+        f: impl FnOnce(i32) -> DbResult<i32>,
+    ) -> DbResult<i32> {
+        let _op = self.begin_op()?;
+        let Some(rm) = &self.rm else {
+            return Err(DbError::ReadOnly(blk.filename().to_string()));
+        };
+        let buff = self.buffer(blk)?;
+        let mut buff = buff.lock().unwrap();
+        let old = buff.contents().get_int(offset);
+        let new = f(old)?;
+        if new != old {
+            let lsn = rm.set_int_nested(&mut buff, offset, new)?;
+            buff.contents().set_int(offset, new);
+            buff.set_modified(self.txnum, Some(lsn));
+        }
+        Ok(old)
+    }
+
+    // Store a string at the specified offset of the specified block.
+    pub fn set_string(
+        &self,
+        blk: &BlockId,
+        offset: usize,
+        val: &str,
+        ok_to_log: bool,
+    ) -> DbResult<()> {
+        let _op = self.begin_op()?;
+        self.xlock(blk)?;
+        let buff = self.buffer(blk)?;
+        let mut buff = buff.lock().unwrap();
+        let lsn = match &self.rm {
+            Some(rm) if ok_to_log || self.is_cut_off(blk) => {
+                Some(rm.set_string(&mut buff, offset, val)?)
+            }
+            _ => None,
+        };
+        buff.contents().set_string(offset, val);
+        buff.set_modified(self.txnum, lsn);
+        Ok(())
+    }
+
+    // Store a byte array at the specified offset of the specified block.
+    pub fn set_bytes(
+        &self,
+        blk: &BlockId,
+        offset: usize,
+        val: &[u8],
+        ok_to_log: bool,
+    ) -> DbResult<()> {
+        let _op = self.begin_op()?;
+        self.xlock(blk)?;
+        let buff = self.buffer(blk)?;
+        let mut buff = buff.lock().unwrap();
+        let lsn = match &self.rm {
+            Some(rm) if ok_to_log || self.is_cut_off(blk) => {
+                Some(rm.set_bytes(&mut buff, offset, val)?)
+            }
+            _ => None,
+        };
+        buff.contents().set_bytes(offset, val);
+        buff.set_modified(self.txnum, lsn);
+        Ok(())
+    }
+
+    // Return the number of blocks in the specified file.
+    // A serializable transaction first obtains an SLock on the
+    // "end of the file", so that no other transaction can append
+    // to the file (creating a phantom) before it completes.
+    // A file the transaction is to truncate has the length
+    // it will be cut down to.
+    pub fn size(&self, filename: &str) -> DbResult<u64> {
+        let _op = self.begin_op()?;
+        self.cm.slock_end_of_file(Self::end_of_file(filename))?;
+        let len = self.fm.length(filename)?;
+        Ok(self
+            .truncated_length(filename)
+            .map_or(len, |to| to.min(len)))
+    }
+
+    // Append a new block to the end of the specified file
+    // and return a reference to it.
+    // The method first obtains an XLock on the "end of the file",
+    // before performing the append.
+    // A file the transaction is to truncate is extended by
+    // the first block to be cut off, if any is left, which
+    // is then kept by truncating the file one block later.
+    pub fn append(&self, filename: &str) -> DbResult<BlockId> {
+        let _op = self.begin_op()?;
+        if self.is_read_only() {
+            Self::check_temporary(filename)?;
+        } else {
+            self.cm.xlock(Self::end_of_file(filename))?;
+        }
+        let Some(len) = self.truncated_length(filename) else {
+            return Ok(self.fm.append(filename)?);
+        };
+        let blk = if len < self.fm.length(filename)? {
+            BlockId::new(filename, len)
+        } else {
+            self.fm.append(filename)?
+        };
+        self.ask_truncate(filename, len + 1)?;
+        Ok(blk)
+    }
+
+    // Cut the specified file down to its first len blocks when
+    // the transaction commits; a rollback leaves it as it is.
+    // The method obtains an XLock on the "end of the file" and
+    // on the whole file, which every transaction modifying or
+    // reading one of its blocks holds an SLock on, rather than
+    // on each block to be cut off. Their contents need not be
+    // logged, since the truncation is never undone.
+    pub fn truncate(&self, filename: &str, len: u64) -> DbResult<()> {
+        let _op = self.begin_op()?;
+        if self.is_read_only() {
+            Self::check_temporary(filename)?;
+        } else {
+            self.cm.xlock(Self::end_of_file(filename))?;
+            self.cm.xlock(Self::whole_file(filename))?;
+        }
+        let len = self
+            .truncated_length(filename)
+            .map_or(len, |to| to.min(len));
+        self.ask_truncate(filename, len)
+    }
+
+    // Log the truncation of the file to len blocks, and
+    // remember it for the commit.
+    fn ask_truncate(&self, filename: &str, len: u64) -> DbResult<()> {
+        let asked = match &self.rm {
+            Some(rm) => rm.truncate(filename, len)?,
+            None => Lsn(0),
+        };
+        self.truncations
+            .lock()
+            .unwrap()
+            .push((filename.to_string(), len, asked));
+        Ok(())
+    }
+
+    // The length the transaction is to cut the file down to,
+    // if it asked to truncate it.
+    fn truncated_length(&self, filename: &str) -> Option<u64> {
+        self.truncations
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(name, _, _)| name == filename)
+            .map(|&(_, len, _)| len)
+    }
+
+    // Return true if the block was to be cut off by a
+    // truncation of the transaction, so that it may be
+    // reused, holding contents that a rollback restores.
+    fn is_cut_off(&self, blk: &BlockId) -> bool {
+        self.truncations
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(name, len, _)| name == blk.filename() && blk.number() >= *len)
+    }
+
+    // The dummy block locked to stand for the end of the file.
+    fn end_of_file(filename: &str) -> BlockId {
+        BlockId::new(filename, Self::END_OF_FILE)
+    }
+
+    // The dummy block locked to stand for the whole file.
+    fn whole_file(filename: &str) -> BlockId {
+        BlockId::new(filename, Self::WHOLE_FILE)
+    }
+
+    // Return the size of the blocks of the database, which
+    // needs no lock as it never changes.
+    pub fn block_size(&self) -> usize {
+        self.fm.block_size()
+    }
+
+    // Return the number of unpinned buffers.
+    pub fn available_buffs(&self) -> usize {
+        self.bm.available()
+    }
+
+    // Return the number of unpinned buffers that the operators
+    // of a query may claim, within the work limit of the pool.
+    pub fn work_buffs(&self) -> usize {
+        self.bm.work_buffers()
+    }
+
+    // Return the statistics of the buffer pool, which
+    // is shared by every transaction on the database.
+    pub fn buffer_stats(&self) -> BufferStats {
+        self.bm.stats()
+    }
+
+    // Return the locks held by every transaction on the
+    // database, as listed by the lock table.
+    pub fn held_locks(&self) -> Vec<(BlockId, i32, &'static str)> {
+        self.cm.held_locks()
+    }
+
+    // Read from the page of the pinned block under an SLock,
+    // or from its version in the snapshot of the transaction.
+    fn read<T>(&self, blk: &BlockId, f: impl FnOnce(&mut Page) -> T) -> DbResult<T> {
+        let _op = self.begin_op()?;
+        if let Some(snapshot) = &self.snapshot {
+            let current = || {
+                let buff = self.buffer(blk)?;
+                let mut buff = buff.lock().unwrap();
+                Ok(Page::from_bytes(buff.contents().contents().to_vec()))
+            };
+            return snapshot.read(blk, current, f);
+        }
+        self.cm.slock(Self::whole_file(blk.filename()))?;
+        self.cm.slock(blk.clone())?;
+        let val = f(self.buffer(blk)?.lock().unwrap().contents());
+        self.cm.end_read(blk);
+        Ok(val)
+    }
+
+    // Obtain an XLock on the block, before modifying it.
+    // A read-only transaction can only modify its temporary
+    // tables, which no other transaction reads, without a lock.
+    fn xlock(&self, blk: &BlockId) -> DbResult<()> {
+        if self.is_read_only() {
+            return Self::check_temporary(blk.filename());
+        }
+        self.cm.slock_file(Self::whole_file(blk.filename()))?;
+        self.cm.xlock(blk.clone())?;
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.write(blk)?;
+        }
+        Ok(())
+    }
+
+    fn check_temporary(filename: &str) -> DbResult<()> {
+        if is_temporary(filename) {
+            Ok(())
+        } else {
+            Err(DbError::ReadOnly(filename.to_string()))
+        }
+    }
+
+    fn buffer(&self, blk: &BlockId) -> DbResult<Arc<Mutex<BufferPage>>> {
+        let buffers = self.buffers.lock().unwrap();
+        buffers
+            .buffer(blk)
+            .map(Arc::clone)
+            .ok_or_else(|| DbError::BufferAbort(format!("block {} is not pinned", blk)))
+    }
+
+    fn unpin_all(&self) {
+        self.buffers.lock().unwrap().unpin_all();
+    }
+
+    // Abandon the transaction as a crash would, undoing nothing,
+    // but releasing its locks and unpinning its buffers, so
+    // that dropping it later does not roll it back.
+    #[cfg(test)]
+    pub(crate) fn crash(&self) {
+        self.cm.release();
+        self.unpin_all();
+        self.completed.store(true, Ordering::SeqCst);
+    }
+}
+
+// A transaction dropped before it completes is rolled back,
+// since the registry forgets it, and its locks would otherwise
+// be held for good. If the rollback fails, its locks are still
+// released and its buffers unpinned; recovery undoes the rest.
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.is_completed() && self.rollback_locked().is_err() {
+            self.cm.release();
+            self.unpin_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SimpleDB;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_set_and_get_across_transactions() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+
+        let tx1 = db.new_tx()?;
+        tx1.pin(&blk)?;
+        tx1.set_int(&blk, 80, 1, false)?;
+        tx1.set_string(&blk, 40, "one", false)?;
+        tx1.commit()?;
+
+        let tx2 = db.new_tx()?;
+        tx2.pin(&blk)?;
+        assert_eq!(tx2.get_int(&blk, 80)?, 1);
+        assert_eq!(tx2.get_string(&blk, 40)?, "one");
+        tx2.set_int(&blk, 80, 2, true)?;
+        tx2.set_string(&blk, 40, "two", true)?;
+        tx2.commit()?;
+
+        let tx3 = db.new_tx()?;
+        tx3.pin(&blk)?;
+        assert_eq!(tx3.get_int(&blk, 80)?, 2);
+        assert_eq!(tx3.get_string(&blk, 40)?, "two");
+        tx3.commit()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transactions_share_locks() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+
+        let tx1 = db.new_tx()?;
+        tx1.pin(&blk)?;
+        tx1.set_int(&blk, 80, 1, true)?;
+        let reader = {
+            let tx2 = db.new_tx()?;
+            let blk = blk.clone();
+            std::thread::spawn(move || -> DbResult<i32> {
+                tx2.pin(&blk)?;
+                // waits for the xlock of tx1
+                let val = tx2.get_int(&blk, 80)?;
+                tx2.commit()?;
+                Ok(val)
+            })
+        };
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        tx1.set_int(&blk, 80, 2, true)?;
+        tx1.commit()?;
+        assert_eq!(reader.join().unwrap()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_timeout() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+
+        let tx1 = db.new_tx()?;
+        tx1.pin(&blk)?;
+        tx1.set_int(&blk, 80, 1, true)?;
+        let tx2 = db.new_tx()?;
+        tx2.set_lock_timeout(Duration::from_millis(20));
+        tx2.pin(&blk)?;
+        let result = tx2.get_int(&blk, 80);
+        assert!(matches!(result, Err(DbError::LockTimeout(b)) if b == blk));
+        tx2.rollback()?;
+        tx1.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_appends() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Arc::new(SimpleDB::new(temp_dir.path(), 400, 8)?);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let db = Arc::clone(&db);
+                std::thread::spawn(move || -> DbResult<Vec<u64>> {
+                    let mut numbers = Vec::new();
+                    while numbers.len() < 5 {
+                        let tx = db.new_tx()?;
+                        let blk = match tx.append("testfile") {
+                            Ok(blk) => blk,
+                            // another transaction is upgrading its lock
+                            // on the end of the file; try again
+                            Err(DbError::LockAbort) => {
+                                tx.rollback()?;
+                                continue;
+                            }
+                            Err(e) => return Err(e),
+                        };
+                        // no other transaction appends until the commit
+                        std::thread::yield_now();
+                        assert_eq!(tx.size("testfile")?, blk.number() + 1);
+                        tx.commit()?;
+                        numbers.push(blk.number());
+                    }
+                    Ok(numbers)
+                })
+            })
+            .collect();
+        let mut numbers = Vec::new();
+        for handle in handles {
+            numbers.extend(handle.join().unwrap()?);
+        }
+        numbers.sort();
+        assert_eq!(numbers, (0..20).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_end_of_file_lock() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        db.file_manager().append("testfile")?;
+
+        let tx1 = db.new_tx()?;
+        assert_eq!(tx1.size("testfile")?, 1);
+        let tx2 = db.new_tx()?;
+        tx2.set_lock_timeout(Duration::from_millis(20));
+        let result = tx2.append("testfile");
+        assert!(matches!(result, Err(DbError::LockTimeout(b)) if b.filename() == "testfile"));
+        tx2.rollback()?;
+        assert_eq!(tx1.size("testfile")?, 1);
+        tx1.commit()?;
+
+        let tx3 = db.new_tx()?;
+        let blk = tx3.append("testfile")?;
+        assert_eq!(blk.number(), 1);
+        let tx4 = db.new_tx()?;
+        tx4.set_lock_timeout(Duration::from_millis(20));
+        assert!(matches!(tx4.size("testfile"), Err(DbError::LockTimeout(_))));
+        tx4.rollback()?;
+        tx3.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_isolation_levels() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+        let new_tx_at = |isolation| {
+            let tx = db.new_tx_with_isolation(isolation).unwrap();
+            tx.set_lock_timeout(Duration::from_millis(20));
+            tx.pin(&blk).unwrap();
+            tx
+        };
+
+        // an uncommitted value can be read without waiting
+        let writer = new_tx_at(IsolationLevel::Serializable);
+        writer.set_int(&blk, 80, 1, true)?;
+        let reader = new_tx_at(IsolationLevel::ReadUncommitted);
+        assert_eq!(reader.get_int(&blk, 80)?, 1);
+        let reader = new_tx_at(IsolationLevel::ReadCommitted);
+        assert!(matches!(
+            reader.get_int(&blk, 80),
+            Err(DbError::LockTimeout(_))
+        ));
+        reader.rollback()?;
+        writer.rollback()?;
+
+        // the block can be modified after a read committed read
+        let reader = new_tx_at(IsolationLevel::ReadCommitted);
+        assert_eq!(reader.get_int(&blk, 80)?, 0);
+        let writer = new_tx_at(IsolationLevel::Serializable);
+        writer.set_int(&blk, 80, 2, true)?;
+        writer.commit()?;
+        assert_eq!(reader.get_int(&blk, 80)?, 2);
+        reader.commit()?;
+
+        // a repeatable read keeps the block but not the end of the file
+        let reader = new_tx_at(IsolationLevel::RepeatableRead);
+        assert_eq!(reader.get_int(&blk, 80)?, 2);
+        assert_eq!(reader.size("testfile")?, 1);
+        let writer = new_tx_at(IsolationLevel::Serializable);
+        assert!(matches!(
+            writer.set_int(&blk, 80, 3, true),
+            Err(DbError::LockTimeout(_))
+        ));
+        writer.append("testfile")?;
+        writer.commit()?;
+        assert_eq!(reader.size("testfile")?, 2);
+        reader.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_only() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+        let latest_lsn = || db.log_manager().lock().unwrap().latest_lsn();
+        let lsn = latest_lsn();
+
+        let reader = db.new_read_only_tx();
+        assert!(reader.is_read_only());
+        reader.pin(&blk)?;
+        assert_eq!(reader.get_int(&blk, 80)?, 0);
+        let result = reader.set_int(&blk, 80, 1, true);
+        assert!(matches!(result, Err(DbError::ReadOnly(f)) if f == "testfile"));
+        assert!(matches!(
+            reader.append("testfile"),
+            Err(DbError::ReadOnly(_))
+        ));
+        // its temporary tables can be written
+        let temp = reader.append("temp1.tbl")?;
+        reader.pin(&temp)?;
+        reader.set_int(&temp, 0, 7, true)?;
+        assert_eq!(reader.get_int(&temp, 0)?, 7);
+
+        // the read kept no slock
+        let writer = db.new_tx()?;
+        writer.set_lock_timeout(Duration::from_millis(20));
+        writer.pin(&blk)?;
+        writer.set_int(&blk, 80, 2, true)?;
+        writer.commit()?;
+        assert_eq!(reader.get_int(&blk, 80)?, 2);
+        reader.commit()?;
+        assert_eq!(latest_lsn(), lsn.next().next().next());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+        let other = db.file_manager().append("testfile")?;
+
+        let tx1 = db.new_tx()?;
+        tx1.pin(&blk)?;
+        tx1.set_int(&blk, 80, 1, true)?;
+        tx1.set_string(&blk, 40, "one", true)?;
+        tx1.commit()?;
+
+        let tx2 = db.new_tx()?;
+        let tx3 = db.new_tx()?;
+        tx2.pin(&blk)?;
+        tx3.pin(&other)?;
+        tx2.set_int(&blk, 80, 2, true)?;
+        tx3.set_int(&other, 0, 3, true)?;
+        tx2.set_string(&blk, 40, "two", true)?;
+        tx2.set_int(&blk, 80, 4, true)?;
+        tx2.set_bytes(&blk, 100, &[5, 6], true)?;
+        tx2.rollback()?;
+        tx3.commit()?;
+        assert_eq!(db.buffer_manager().available(), 8);
+
+        let tx4 = db.new_tx()?;
+        tx4.pin(&blk)?;
+        tx4.pin(&other)?;
+        assert_eq!(tx4.get_int(&blk, 80)?, 1);
+        assert_eq!(tx4.get_string(&blk, 40)?, "one");
+        assert_eq!(tx4.get_bytes(&blk, 100)?, Vec::<u8>::new());
+        assert_eq!(tx4.get_int(&other, 0)?, 3);
+        tx4.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_rolls_back() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let blk = db.file_manager().append("testfile")?;
+
+        let tx = db.new_tx()?;
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 80, 1, true)?;
+        assert!(!db.lock_table().is_empty());
+        drop(tx);
+        assert!(db.active_transactions().is_empty());
+        assert!(db.lock_table().is_empty());
+        assert_eq!(db.buffer_manager().available(), 8);
+
+        let tx = db.new_tx()?;
+        tx.set_lock_timeout(Duration::from_millis(100));
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 80, 2, true)?;
+        tx.rollback()?;
+        let tx = db.new_tx()?;
+        tx.pin(&blk)?;
+        assert_eq!(tx.get_int(&blk, 80)?, 0);
+        tx.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_unpins_buffers() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+
+        let tx = db.new_tx()?;
+        tx.pin(&tx.append("testfile")?)?;
+        tx.pin(&tx.append("testfile")?)?;
+        assert_eq!(db.buffer_manager().available(), 6);
+
+        tx.commit()?;
+        assert_eq!(db.buffer_manager().available(), 8);
+
+        let tx = db.new_tx()?;
+        let blocks = [tx.append("testfile")?, tx.append("testfile")?];
+        tx.pin_all(&blocks)?;
+        tx.set_int(&blocks[1], 0, 7, false)?;
+        assert_eq!(db.buffer_manager().available(), 6);
+        tx.unpin(&blocks[0]);
+        assert_eq!(db.buffer_manager().available(), 7);
+        tx.commit()?;
+        assert_eq!(db.buffer_manager().available(), 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_buffer_manager() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let tx1 = db.new_tx()?;
+        let blocks: Vec<BlockId> = (0..9)
+            .map(|_| tx1.append("testfile"))
+            .collect::<DbResult<_>>()?;
+        for blk in &blocks[..8] {
+            tx1.pin(blk)?;
+        }
+
+        // A transaction on another thread waits for a buffer,
+        // without keeping tx1 from committing
+        std::thread::scope(|scope| {
+            let waiter = scope.spawn(|| {
+                let tx2 = db.new_tx()?;
+                tx2.pin(&blocks[8])?;
+                tx2.commit()
+            });
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            tx1.commit()?;
+            waiter.join().unwrap()
+        })?;
+        assert_eq!(db.buffer_manager().available(), 8);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_on_commit() -> DbResult<()> {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SimpleDB::new(temp_dir.path(), 400, 8)?;
+        let fm = db.file_manager();
+        for _ in 0..4 {
+            fm.append("testfile")?;
+        }
+
+        // a rollback keeps the file as it was
+        let tx = db.new_tx()?;
+        tx.truncate("testfile", 2)?;
+        tx.rollback()?;
+        assert_eq!(fm.length("testfile")?, 4);
+
+        // as does a rollback to a savepoint before the truncation
+        let tx = db.new_tx()?;
+        let blk = BlockId::new("testfile", 0);
+        tx.pin(&blk)?;
+        tx.set_int(&blk, 0, 1, true)?;
+        let savepoint = tx.savepoint();
+        tx.set_int(&blk, 0, 2, true)?;
+        tx.truncate("testfile", 1)?;
+        tx.rollback_to(savepoint)?;
+        tx.truncate("testfile", 3)?;
+        assert_eq!(fm.length("testfile")?, 4);
+
+        // the file is locked until the commit
+        let other = db.new_tx()?;
+        other.set_lock_timeout(Duration::from_millis(20));
+        assert!(other.size("testfile").is_err());
+        tx.commit()?;
+        assert_eq!(fm.length("testfile")?, 3);
+        assert_eq!(other.size("testfile")?, 3);
+        other.commit()?;
+        Ok(())
+    }
+}
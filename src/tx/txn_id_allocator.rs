@@ -0,0 +1,84 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+// Hands out the transaction numbers of a database, each one
+// greater than the numbers handed out before it, however many
+// threads create transactions at the same time.
+// The numbers stay increasing across restarts: recovery
+// advances the allocator past every number found in the log,
+// and a checkpoint, which starts the log afresh, records the
+// next number to hand out.
+// The count is kept in 64 bits, so that running out of
+// transaction numbers is detected rather than wrapping around.
+#[derive(Debug)]
+pub struct TxnIdAllocator {
+    next: AtomicI64,
+}
+
+impl Default for TxnIdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TxnIdAllocator {
+    pub fn new() -> Self {
+        TxnIdAllocator {
+            next: AtomicI64::new(1),
+        }
+    }
+
+    // Return a transaction number that no other
+    // transaction has been given.
+    pub fn allocate(&self) -> i32 {
+        let txnum = self.next.fetch_add(1, Ordering::SeqCst);
+        i32::try_from(txnum).expect("transaction numbers are exhausted")
+    }
+
+    // The number that the next transaction will be given.
+    pub fn peek(&self) -> i32 {
+        let txnum = self.next.load(Ordering::SeqCst);
+        i32::try_from(txnum).expect("transaction numbers are exhausted")
+    }
+
+    // Make sure that no number below next is handed out.
+    pub fn advance_to(&self, next: i32) {
+        self.next.fetch_max(next as i64, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashSet, sync::Arc, thread};
+
+    #[test]
+    fn test_allocate() {
+        let txn_ids = Arc::new(TxnIdAllocator::new());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let txn_ids = Arc::clone(&txn_ids);
+                thread::spawn(move || {
+                    let txnums: Vec<_> = (0..100).map(|_| txn_ids.allocate()).collect();
+                    assert!(txnums.windows(2).all(|pair| pair[0] < pair[1]));
+                    txnums
+                })
+            })
+            .collect();
+        let txnums: HashSet<_> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(txnums, (1..=400).collect());
+        assert_eq!(txn_ids.peek(), 401);
+    }
+
+    #[test]
+    fn test_advance_to() {
+        let txn_ids = TxnIdAllocator::new();
+        txn_ids.advance_to(10);
+        assert_eq!(txn_ids.allocate(), 10);
+        // an allocator is never moved back
+        txn_ids.advance_to(5);
+        assert_eq!(txn_ids.allocate(), 11);
+    }
+}
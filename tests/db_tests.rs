@@ -1,4 +1,4 @@
-use simpledb::SimpleDB;
+use simpledb::{driver::Outcome, query::Constant, SimpleDB};
 use tempfile::TempDir;
 
 #[test]
@@ -11,3 +11,56 @@ fn test_simpledb_creation() {
     assert!(temp_path.exists());
     assert!(temp_path.is_dir());
 }
+
+#[test]
+fn test_new_tx() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+
+    let tx = db.new_tx().unwrap();
+    let blk = tx.append("testfile").unwrap();
+    tx.pin(&blk).unwrap();
+    tx.set_int(&blk, 80, 42, true).unwrap();
+    tx.commit().unwrap();
+
+    let tx = db.new_tx().unwrap();
+    tx.pin(&blk).unwrap();
+    assert_eq!(tx.get_int(&blk, 80).unwrap(), 42);
+    tx.rollback().unwrap();
+}
+
+#[test]
+fn test_run() {
+    let temp_dir = TempDir::new().unwrap();
+    let db = SimpleDB::new(temp_dir.path(), 400, 8).unwrap();
+
+    let outcome = db.run("create table t (a int, b varchar(10))").unwrap();
+    assert_eq!(outcome.count(), 0);
+    for i in 0..3 {
+        let sql = format!("insert into t (a, b) values ({}, 'b{}')", i, i);
+        assert!(matches!(db.run(&sql).unwrap(), Outcome::Count(1)));
+    }
+    let outcome = db.run("select b from t where a = 2").unwrap();
+    let rs = outcome.rows().unwrap();
+    assert_eq!(rs.get(0, "b"), Some(&Constant::from("b2".to_string())));
+
+    // a failed statement leaves no locks behind
+    assert!(db.run("select c from t").is_err());
+    assert_eq!(
+        db.run("update t set b = 'x' where a = 1").unwrap().count(),
+        1
+    );
+    assert_eq!(db.run("create table u (c int)").unwrap().count(), 0);
+
+    // several statements in a transaction of one's own
+    let planner = db.planner().unwrap();
+    let tx = db.new_tx().unwrap();
+    planner
+        .execute_update("delete from t where a = 0", &tx)
+        .unwrap();
+    planner
+        .execute_update("insert into t (a, b) values (9, 'b9')", &tx)
+        .unwrap();
+    tx.rollback().unwrap();
+    assert_eq!(db.run("select a from t").unwrap().count(), 3);
+}